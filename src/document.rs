@@ -0,0 +1,218 @@
+//! Tagged JSON documents
+//!
+//! JSON produced from EDI starts with metadata saying what it holds: the
+//! layout version (`schema_version`), the implementation guide
+//! (`implementation_guide`, e.g. `005010X222A1`) and either the transaction
+//! set (`transaction_set`, e.g. `"837"`) followed by its fields, or a list of
+//! `interchanges`. Writing EDI back deserializes that tag instead of searching
+//! the text, so pretty-printed, reordered or hand-edited JSON converts the same.
+//!
+//! When code lists are loaded (`--code-sets`), the descriptions of the codes
+//! found in the source EDI follow as `code_descriptions`. They are read back
+//! with the document but not written to EDI.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::{EdiError, EdiResult};
+use crate::helper::writer_options::WriterOptions;
+use crate::interchange::{write_interchanges_with, AnyTransaction, Interchange};
+use crate::validation::code_sets::CodeDescription;
+
+/// Version of the JSON layout written by this crate.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A transaction set or a batch of interchanges with its metadata.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Document {
+    /// JSON layout version, `SCHEMA_VERSION` when written
+    pub schema_version: u32,
+    /// Implementation guide (ST03, else GS08); the first group's for a batch
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub implementation_guide: String,
+    #[serde(flatten)]
+    pub content: DocumentContent,
+    /// Descriptions of the codes found in the source EDI, from the loaded code lists
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub code_descriptions: Vec<CodeDescription>,
+}
+
+/// What a document holds; a transaction set carries its `transaction_set` tag.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[serde(untagged)]
+pub enum DocumentContent {
+    Interchanges { interchanges: Vec<Interchange> },
+    Transaction(AnyTransaction),
+}
+
+impl Document {
+    pub fn transaction(transaction: AnyTransaction, implementation_guide: &str) -> Document {
+        Document {
+            schema_version: SCHEMA_VERSION,
+            implementation_guide: implementation_guide.to_string(),
+            content: DocumentContent::Transaction(transaction),
+            code_descriptions: Vec::new(),
+        }
+    }
+
+    pub fn interchanges(interchanges: Vec<Interchange>) -> Document {
+        let implementation_guide = interchanges
+            .iter()
+            .flat_map(|interchange| &interchange.groups)
+            .map(|group| group.gs.version_number.clone())
+            .next()
+            .unwrap_or_default();
+        Document {
+            schema_version: SCHEMA_VERSION,
+            implementation_guide,
+            content: DocumentContent::Interchanges { interchanges },
+            code_descriptions: Vec::new(),
+        }
+    }
+
+    /// Check whether parsed JSON is a tagged document rather than a bare
+    /// transaction set written before `schema_version` existed.
+    pub fn is_document(value: &Value) -> bool {
+        value.get("schema_version").is_some()
+    }
+
+    pub fn from_json(json: &str) -> EdiResult<Document> {
+        Document::from_value(serde_json::from_str(json)?)
+    }
+
+    pub fn from_value(mut value: Value) -> EdiResult<Document> {
+        let schema_version = match value.get("schema_version").and_then(Value::as_u64) {
+            Some(version) if version <= SCHEMA_VERSION as u64 => version as u32,
+            Some(version) => {
+                return Err(EdiError::UnsupportedFormat(format!(
+                    "JSON schema version {} is newer than the supported version {}",
+                    version, SCHEMA_VERSION
+                )))
+            }
+            None => {
+                return Err(EdiError::MissingField(
+                    "schema_version (JSON is not a tagged EDI document)".to_string(),
+                ))
+            }
+        };
+        let implementation_guide = value
+            .get("implementation_guide")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        let code_descriptions = match value
+            .as_object_mut()
+            .and_then(|fields| fields.remove("code_descriptions"))
+        {
+            Some(descriptions) => serde_json::from_value(descriptions)?,
+            None => Vec::new(),
+        };
+        let content = match value.get_mut("interchanges") {
+            Some(interchanges) => DocumentContent::Interchanges {
+                interchanges: serde_json::from_value(interchanges.take())?,
+            },
+            None => DocumentContent::Transaction(serde_json::from_value(value)?),
+        };
+        Ok(Document {
+            schema_version,
+            implementation_guide,
+            content,
+            code_descriptions,
+        })
+    }
+
+    pub fn to_json(&self) -> EdiResult<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Write the document as EDI.
+    pub fn to_edi(&self) -> EdiResult<String> {
+        self.to_edi_with(&WriterOptions::default())
+    }
+
+    /// Write the document as EDI laid out per `options`.
+    pub fn to_edi_with(&self, options: &WriterOptions) -> EdiResult<String> {
+        match &self.content {
+            DocumentContent::Interchanges { interchanges } => {
+                write_interchanges_with(interchanges, options)
+            }
+            DocumentContent::Transaction(transaction) => transaction.to_edi_with(options),
+        }
+    }
+
+    /// Short description for logging, e.g. "837 (005010X222A1)".
+    pub fn describe(&self) -> String {
+        let content = match &self.content {
+            DocumentContent::Interchanges { interchanges } => {
+                format!("{} interchanges", interchanges.len())
+            }
+            DocumentContent::Transaction(transaction) => {
+                transaction.transaction_set_id().to_string()
+            }
+        };
+        match self.implementation_guide.as_str() {
+            "" => content,
+            guide => format!("{} ({})", content, guide),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SINGLE_999: &str = "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230501*1200*^*00501*000000001*0*T*:~GS*FA*S*R*20230501*1200*2*X*005010X231A1~ST*999*0003*005010X231A1~AK1*HC*17*005010X222A1~AK9*A*1*1*1~SE*4*0003~GE*1*2~IEA*1*000000001~";
+
+    #[test]
+    fn test_tagged_transaction_round_trip() {
+        let transaction = AnyTransaction::parse_any(SINGLE_999).unwrap();
+        let document = Document::transaction(transaction, "005010X231A1");
+        let json = document.to_json().unwrap();
+        assert!(json.starts_with(
+            r#"{"schema_version":1,"implementation_guide":"005010X231A1","transaction_set":"999","#
+        ));
+
+        // Pretty-printed JSON with the keys sorted (metadata no longer first) reads the same
+        let value: Value = serde_json::from_str(&json).unwrap();
+        let pretty = serde_json::to_string_pretty(&value).unwrap();
+        let reread = Document::from_json(&pretty).unwrap();
+        assert_eq!(reread, document);
+        assert_eq!(reread.describe(), "999 (005010X231A1)");
+        assert!(reread.to_edi().unwrap().contains("AK9*A*1*1*1~"));
+    }
+
+    #[test]
+    fn test_code_descriptions_round_trip() {
+        let transaction = AnyTransaction::parse_any(SINGLE_999).unwrap();
+        let mut document = Document::transaction(transaction, "005010X231A1");
+        document.code_descriptions.push(CodeDescription {
+            segment_index: 3,
+            loop_path: "AK1/AK101".to_string(),
+            list: crate::validation::code_sets::CodeList::Carc,
+            code: "45".to_string(),
+            description: "Charge exceeds fee schedule".to_string(),
+        });
+        let json = document.to_json().unwrap();
+        assert!(json.contains(r#""code_descriptions":[{"segment_index":3,"#));
+
+        let reread = Document::from_json(&json).unwrap();
+        assert_eq!(reread, document);
+        assert_eq!(reread.to_edi().unwrap(), document.to_edi().unwrap());
+    }
+
+    #[test]
+    fn test_from_json_errors() {
+        assert!(matches!(
+            Document::from_json(r#"{"interchange_header":{}}"#),
+            Err(EdiError::MissingField(_))
+        ));
+        assert!(matches!(
+            Document::from_json(r#"{"schema_version":99,"transaction_set":"835"}"#),
+            Err(EdiError::UnsupportedFormat(_))
+        ));
+        assert!(matches!(
+            Document::from_json(r#"{"schema_version":1,"transaction_set":"850"}"#),
+            Err(EdiError::ParseError(_))
+        ));
+    }
+}
@@ -4,13 +4,20 @@ use serde::{Deserialize, Serialize};
 use crate::edi270::interchangecontrol::*;
 use crate::edi270::loop2000a::*;
 use crate::edi270::loop2000b::*;
+use crate::edi270::loop2000c::*;
+use crate::edi270::loop2000d::*;
 use crate::edi270::table1::*;
 use crate::error::EdiResult;
+use crate::helper::delimiters::Delimiters;
+use crate::helper::diagnostics::{collect_diagnostics, Diagnostics};
 use crate::helper::edihelper::*;
-use crate::segments::dtp::*;
-use crate::segments::eq::*;
-use crate::segments::r#ref::*;
+use crate::helper::helper::clean_contents;
+use crate::helper::source_map::locate_error;
+use crate::helper::tokenizer::{find_segment, join_segments, split_loops, tokenize};
+use crate::helper::unrecognized::UnrecognizedSegments;
+use crate::helper::writer_options::WriterOptions;
 use crate::segments::se::*;
+use crate::transaction_processor::TransactionSet;
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Edi270 {
@@ -20,22 +27,51 @@ pub struct Edi270 {
     pub loop2000b: Vec<Loop2000B>,
     pub se_segments: SE,
     pub interchange_trailer: InterchangeTrailer,
-    // Store unprocessed segments for preservation
-    #[serde(skip_serializing_if = "Vec::is_empty", default)]
-    pub unprocessed_ref_segments: Vec<REF>,
-    #[serde(skip_serializing_if = "Vec::is_empty", default)]
-    pub unprocessed_dtp_segments: Vec<DTP>,
-    #[serde(skip_serializing_if = "Vec::is_empty", default)]
-    pub unprocessed_eq_segments: Vec<EQ>,
+    /// Delimiters declared by the source ISA, re-emitted when writing EDI
+    #[serde(default)]
+    pub delimiters: Delimiters,
+    /// Problems found while parsing: envelope mismatches, invalid values, unprocessed segments
+    #[serde(default, skip_serializing_if = "Diagnostics::is_empty")]
+    pub diagnostics: Diagnostics,
+    /// Segments the parser did not recognize, written back where they appeared
+    #[serde(default, skip_serializing_if = "UnrecognizedSegments::is_empty")]
+    pub unrecognized: UnrecognizedSegments,
 }
 
-pub fn get_270(contents: &str) -> EdiResult<(Edi270, String)> {
+impl TransactionSet for Edi270 {
+    fn parse(contents: String) -> EdiResult<(Self, String)> {
+        parse_270(&contents)
+    }
+
+    fn to_edi_with(&self, options: &WriterOptions) -> EdiResult<String> {
+        write_270_with(self, options)
+    }
+
+    fn get_transaction_type() -> &'static str {
+        "270"
+    }
+}
+
+/// Parse an EDI 270 file into an Edi270 structure; use `Edi270::parse`
+/// to also get the content that follows its transaction set
+pub fn get_270(contents: &str) -> EdiResult<Edi270> {
+    Ok(Edi270::parse(contents.to_string())?.0)
+}
+
+fn parse_270(contents: &str) -> EdiResult<(Edi270, String)> {
+    let raw = contents;
     let mut contents = contents.to_string();
     let mut edi270 = Edi270::default();
 
     // Remove BOM if present
     contents = contents.trim_start_matches("\u{feff}").to_string();
 
+    // Capture the source delimiters, then normalize to `*` and `~`
+    let delimiters = Delimiters::detect(&contents);
+    contents = clean_contents(contents);
+    let normalized = contents.clone();
+    edi270.delimiters = delimiters;
+
     // Parse Interchange Header
     let (interchange_header, new_contents) = get_interchange_header(&contents);
     edi270.interchange_header = interchange_header;
@@ -47,7 +83,7 @@ pub fn get_270(contents: &str) -> EdiResult<(Edi270, String)> {
             edi270.table1 = table1;
             contents = new_contents;
         }
-        Err(e) => return Err(e),
+        Err(e) => return Err(locate_error(raw, &contents, e)),
     }
 
     // Parse Loop 2000A (Information Source)
@@ -56,19 +92,49 @@ pub fn get_270(contents: &str) -> EdiResult<(Edi270, String)> {
             edi270.loop2000a = loop2000a;
             contents = new_contents;
         }
-        Err(e) => return Err(e),
+        Err(e) => return Err(locate_error(raw, &contents, e)),
     }
 
-    // Parse Loop 2000B (Information Receiver) - can be multiple
-    let mut loop2000b_vec = Vec::new();
-    while contents.contains("HL*") && contents.contains("*21*") {
-        // This is a simplification - in a real implementation, you would need to check
-        // if the HL segment is actually for a 2000B loop by examining the HL03 value
-        let (loop2000b, new_contents) = get_loop_2000b(&contents);
-        loop2000b_vec.push(loop2000b);
-        contents = new_contents;
+    // Walk HL segments to build the tree.
+    // HL*n*parent*21* = information receiver, HL*n*parent*22* = subscriber,
+    // HL*n*parent*23* = dependent. Each HL level runs up to the next HL; the
+    // hierarchy ends at SE.
+    let segments = tokenize(&contents);
+    let body_end = find_segment(&segments, &["SE"], 0).unwrap_or(segments.len());
+    let (leading, levels) = split_loops(&segments[..body_end], &["HL"]);
+    let mut leftover = join_segments(leading);
+    let mut levels = levels.into_iter().peekable();
+
+    while let Some(level) = levels.next() {
+        if level[0].element(2) == "21" {
+            // Information Receiver level (Loop 2000B) - can be multiple
+            let (mut loop2000b, remaining) = get_loop_2000b(&join_segments(level));
+            leftover.push_str(&remaining);
+
+            // Subscriber levels (Loop 2000C) until the next information receiver
+            while let Some(subscriber) = levels.next_if(|next| is_loop_2000c(next)) {
+                let (mut loop2000c, remaining) = get_loop_2000c(&join_segments(subscriber));
+                leftover.push_str(&remaining);
+
+                // Dependent levels (Loop 2000D) until the next subscriber
+                while let Some(dependent) = levels.next_if(|next| is_loop_2000d(next)) {
+                    let (loop2000d, remaining) = get_loop_2000d(&join_segments(dependent));
+                    leftover.push_str(&remaining);
+                    loop2000c.loop2000d.push(loop2000d);
+                }
+
+                loop2000b.loop2000c.push(loop2000c);
+            }
+
+            edi270.loop2000b.push(loop2000b);
+        } else {
+            // Unknown HL level or orphan — keep it with the unprocessed content
+            leftover.push_str(&join_segments(level));
+        }
     }
-    edi270.loop2000b = loop2000b_vec;
+    let mut remaining = join_segments(&segments[body_end..]);
+    remaining.push_str(&leftover);
+    contents = remaining;
 
     // Parse SE segment
     if contents.contains("SE*") {
@@ -85,68 +151,16 @@ pub fn get_270(contents: &str) -> EdiResult<(Edi270, String)> {
     contents = new_contents;
 
     // Process any remaining segments that might have been missed
-    process_remaining_segments(&mut edi270, &contents);
-
-    info!("Unprocessed segments: {:?}", contents);
+    edi270.diagnostics = collect_diagnostics(&normalized, &contents, &delimiters);
+    edi270.unrecognized = UnrecognizedSegments::collect(&normalized, &contents);
     Ok((edi270, contents))
 }
 
-fn process_remaining_segments(edi270: &mut Edi270, contents: &str) {
-    // Check for REF segments
-    if contents.contains("REF") {
-        let ref_segments = extract_segments(contents, "REF");
-        for ref_content in ref_segments {
-            let ref_segment = get_ref(ref_content);
-            info!(
-                "Found unprocessed REF segment, adding to appropriate loop: {:?}",
-                ref_segment
-            );
-
-            // Preserve the original qualifier and value
-            edi270.unprocessed_ref_segments.push(ref_segment);
-        }
-    }
-
-    // Check for DTP segments
-    if contents.contains("DTP") {
-        let dtp_segments = extract_segments(contents, "DTP");
-        for dtp_content in dtp_segments {
-            let dtp_segment = crate::segments::dtp::get_dtp(dtp_content);
-            info!("Found unprocessed DTP segment: {:?}", dtp_segment);
-
-            // Store for later use
-            edi270.unprocessed_dtp_segments.push(dtp_segment);
-        }
-    }
-
-    // Check for EQ segments
-    if contents.contains("EQ") {
-        let eq_segments = extract_segments(contents, "EQ");
-        for eq_content in eq_segments {
-            let eq_segment = crate::segments::eq::get_eq(eq_content);
-            info!("Found unprocessed EQ segment: {:?}", eq_segment);
-
-            // Store for later use
-            edi270.unprocessed_eq_segments.push(eq_segment);
-        }
-    }
-}
-
-// Helper function to extract all segments of a specific type from content
-fn extract_segments(contents: &str, segment_id: &str) -> Vec<String> {
-    let mut segments = Vec::new();
-    let lines: Vec<&str> = contents.split('~').collect();
-
-    for line in lines {
-        if line.trim().starts_with(segment_id) {
-            segments.push(line.trim().to_string());
-        }
-    }
-
-    segments
+pub fn write_270(edi270: &Edi270) -> EdiResult<String> {
+    write_270_with(edi270, &WriterOptions::default())
 }
 
-pub fn write_270(edi270: &Edi270) -> String {
+pub fn write_270_with(edi270: &Edi270, options: &WriterOptions) -> EdiResult<String> {
     let mut new_edi = String::new();
 
     // Write Interchange Header
@@ -163,32 +177,17 @@ pub fn write_270(edi270: &Edi270) -> String {
         new_edi.push_str(&write_loop_2000b(loop2000b));
     }
 
-    // Write any unprocessed REF segments
-    for ref_segment in &edi270.unprocessed_ref_segments {
-        new_edi.push_str(&write_ref(ref_segment.clone()));
-    }
-
-    // Write any unprocessed DTP segments
-    for dtp_segment in &edi270.unprocessed_dtp_segments {
-        new_edi.push_str(&write_dtp(dtp_segment.clone()));
-    }
-
-    // Write any unprocessed EQ segments
-    for eq_segment in &edi270.unprocessed_eq_segments {
-        new_edi.push_str(&write_eq(eq_segment.clone()));
-    }
-
     // Write SE segment
     new_edi.push_str(&write_se(edi270.se_segments.clone()));
 
     // Write Interchange Trailer
     new_edi.push_str(&write_interchange_trailer(&edi270.interchange_trailer));
 
-    // Add line breaks between segments for better readability
-    let new_edi_with_breaks = new_edi.replace("~", "~\n");
+    let new_edi = edi270.unrecognized.restore(new_edi, &Delimiters::default());
+    let new_edi = options.format(&new_edi, &edi270.delimiters)?;
 
-    info!("Generated EDI 270: {}", new_edi_with_breaks);
-    new_edi_with_breaks
+    info!("Generated EDI 270: {}", new_edi);
+    Ok(new_edi)
 }
 
 // Function to detect if JSON contains 270 format data
@@ -203,7 +202,6 @@ pub fn is_270_json(contents: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::segments::r#ref::REF;
 
     #[test]
     fn test_is_270_json() {
@@ -215,27 +213,32 @@ mod tests {
     }
 
     #[test]
-    fn test_write_270_with_ref_segments() {
-        // Create a minimal Edi270 structure with REF segments
-        let mut edi270 = Edi270::default();
-
-        // Add a REF segment to unprocessed_ref_segments
-        let ref_segment = REF {
-            reference_id_number_qualifier: "SY".to_string(),
-            reference_id_number: "123456789".to_string(),
-        };
-        edi270.unprocessed_ref_segments.push(ref_segment);
-
-        // Write the EDI270
-        let output = write_270(&edi270);
-
-        // Check if the REF segment is included in the output
-        assert!(
-            output.contains("REF*SY*123456789~"),
-            "REF segment not found in output"
+    fn test_unrecognized_segments_round_trip() {
+        let contents = "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230101*1200*^*00501*000000001*0*P*:~GS*HS*SENDER*RECEIVER*20230101*1200*1*X*005010X279A1~ST*270*0001*005010X279A1~BHT*0022*13*10001234*20230101*1200~HL*1**20*1~NM1*PR*2*ABC INSURANCE COMPANY*****PI*842610001~HL*2*1*21*1~NM1*1P*2*BONE AND JOINT CLINIC*****SV*2000035~HL*3*2*22*0~TRN*1*93175-012547*9877281234~NM1*IL*1*SMITH*ROBERT****MI*11122333301~N3*15197 BROADWAY AVENUE~N4*KANSAS CITY*MO*64108~ZZZ*1~DMG*D8*19430519~DTP*291*D8*20230101~EQ*30~SE*16*0001~GE*1*1~IEA*1*000000001~";
+        let edi270 = get_270(contents).unwrap();
+        let first = edi270.unrecognized.iter().next().unwrap();
+        assert_eq!(
+            (first.segment.as_str(), first.loop_path.as_str()),
+            ("ZZZ*1", "2000C/2100C")
         );
 
-        // Check if line breaks are added
-        assert!(output.contains("~\n"), "Line breaks not found in output");
+        let output = write_270(&edi270).unwrap();
+        assert_eq!(output.replace('\n', ""), contents);
+    }
+
+    #[test]
+    fn test_hl_levels_attach_to_their_parents() {
+        let contents = "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230101*1200*^*00501*000000001*0*P*:~GS*HS*SENDER*RECEIVER*20230101*1200*1*X*005010X279A1~ST*270*0001*005010X279A1~BHT*0022*13*10001234*20230101*1200~HL*1**20*1~NM1*PR*2*ABC INSURANCE COMPANY*****PI*842610001~HL*2*1*21*1~NM1*1P*2*BONE AND JOINT CLINIC*****SV*2000035~HL*3*2*22*1~NM1*IL*1*SMITH*ROBERT****MI*11122333301~HL*4*3*23*0~NM1*03*1*SMITH*MARY~EQ*30~HL*5*1*21*1~NM1*1P*2*CITY CLINIC*****SV*2000036~HL*6*5*22*0~NM1*IL*1*DOE*JOHN****MI*22233344401~EQ*30~SE*17*0001~GE*1*1~IEA*1*000000001~";
+        let (edi270, remaining) = Edi270::parse(contents.to_string()).unwrap();
+        assert_eq!(edi270.loop2000b.len(), 2);
+        assert_eq!(edi270.loop2000b[0].loop2000c.len(), 1);
+        assert_eq!(edi270.loop2000b[0].loop2000c[0].loop2000d.len(), 1);
+        assert_eq!(
+            edi270.loop2000b[1].loop2000c[0].nm1_segments.lastname,
+            "DOE"
+        );
+        assert_eq!(edi270.loop2000b[1].loop2000c[0].eq_segments.len(), 1);
+        // Loop 2000D has no EQ segments, so the dependent's inquiry is left unprocessed
+        assert_eq!(remaining, "EQ*30~");
     }
 }
@@ -2,7 +2,7 @@ use log::info;
 use serde::{Deserialize, Serialize};
 
 use crate::error::{EdiError, EdiResult};
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{find_segment, join_segments, tokenize};
 use crate::segments::hl::*;
 use crate::segments::nm1::*;
 use crate::segments::per::*;
@@ -15,60 +15,57 @@ pub struct Loop2000A {
 }
 
 pub fn get_loop_2000a(contents: &str) -> EdiResult<(Loop2000A, String)> {
-    let mut contents = contents.to_string();
     let mut loop2000a = Loop2000A::default();
+    let mut leftover = String::new();
 
-    // Process HL segment (required)
-    if contents.contains("HL*") {
-        info!("HL segment found");
-        let hl_content = get_segment_contents("HL", &contents);
-        if hl_content.is_empty() {
-            return Err(EdiError::MissingSegment("HL".to_string()));
-        }
-        loop2000a.hl_segments = get_hl(hl_content);
-
-        // Verify this is an Information Source level HL segment (level code = 20)
-        if loop2000a.hl_segments.hl03_hierarchical_level_code != "20" {
-            return Err(EdiError::ValidationError(format!(
-                "Expected HL03 code '20' for Information Source level, got '{}'",
-                loop2000a.hl_segments.hl03_hierarchical_level_code
-            )));
-        }
-
-        info!("HL segment parsed");
-        contents = content_trim("HL", &contents);
-    } else {
+    // Loop 2000A runs from its HL segment (required) up to the next HL
+    let segments = tokenize(contents);
+    let Some(start) = find_segment(&segments, &["HL"], 0) else {
         return Err(EdiError::MissingSegment("HL".to_string()));
+    };
+    let end = find_segment(&segments, &["HL", "SE"], start + 1).unwrap_or(segments.len());
+
+    info!("HL segment found");
+    loop2000a.hl_segments = get_hl(segments[start].elements.join("*"));
+
+    // Verify this is an Information Source level HL segment (level code = 20)
+    if loop2000a.hl_segments.hl03_hierarchical_level_code != "20" {
+        return Err(EdiError::ValidationError(format!(
+            "Expected HL03 code '20' for Information Source level, got '{}'",
+            loop2000a.hl_segments.hl03_hierarchical_level_code
+        )));
     }
-
-    // Process NM1 segment (required)
-    if contents.contains("NM1") {
-        info!("NM1 segment found");
-        let nm1_content = get_segment_contents("NM1", &contents);
-        if nm1_content.is_empty() {
-            return Err(EdiError::MissingSegment("NM1".to_string()));
+    info!("HL segment parsed");
+
+    let mut nm1_found = false;
+    for segment in &segments[start + 1..end] {
+        match segment.id {
+            // Process NM1 segment (required)
+            "NM1" if !nm1_found => {
+                info!("NM1 segment found");
+                loop2000a.nm1_segments = get_nm1(segment.elements.join("*"));
+                nm1_found = true;
+                info!("NM1 segment parsed");
+            }
+            // Process PER segments (situational, can be multiple)
+            "PER" => {
+                info!("PER segment found");
+                loop2000a
+                    .per_segments
+                    .push(get_per(segment.elements.join("*")));
+                info!("PER segment parsed");
+            }
+            _ => leftover.push_str(&segment.to_edi()),
         }
-        loop2000a.nm1_segments = get_nm1(nm1_content);
-        info!("NM1 segment parsed");
-        contents = content_trim("NM1", &contents);
-    } else {
-        return Err(EdiError::MissingSegment("NM1".to_string()));
     }
-
-    // Process PER segments (situational, can be multiple)
-    while contents.starts_with("PER") {
-        info!("PER segment found");
-        let per_content = get_segment_contents("PER", &contents);
-        if per_content.is_empty() {
-            break;
-        }
-        let per = get_per(per_content);
-        info!("PER segment parsed");
-        loop2000a.per_segments.push(per);
-        contents = content_trim("PER", &contents);
+    if !nm1_found {
+        return Err(EdiError::MissingSegment("NM1".to_string()));
     }
 
     info!("Loop 2000A parsed");
+    let mut contents = join_segments(&segments[..start]);
+    contents.push_str(&join_segments(&segments[end..]));
+    contents.push_str(&leftover);
     Ok((loop2000a, contents))
 }
 
@@ -1,7 +1,7 @@
 use log::info;
 use serde::{Deserialize, Serialize};
 
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{tokenize, Segment};
 use crate::segments::hl::*;
 use crate::segments::nm1::*;
 
@@ -13,62 +13,58 @@ pub struct Loop2000B {
 }
 
 pub fn get_loop_2000b(contents: &str) -> (Loop2000B, String) {
-    let mut contents = contents.to_string();
     let mut loop2000b = Loop2000B::default();
-
-    // Process HL segment (required)
-    if contents.contains("HL*") {
-        info!("HL segment found");
-        let hl_content = get_segment_contents("HL", &contents);
-        loop2000b.hl_segments = get_hl(hl_content);
-
-        // Verify this is an Information Receiver level HL segment (level code = 21)
-        if loop2000b.hl_segments.hl03_hierarchical_level_code != "21" {
-            info!(
-                "Warning: Expected HL03 code '21' for Information Receiver level, got '{}'",
-                loop2000b.hl_segments.hl03_hierarchical_level_code
-            );
+    let mut leftover = String::new();
+    let mut hl_found = false;
+    let mut nm1_found = false;
+
+    // `contents` is the Information Receiver level only: its HL segment up to
+    // the next HL. Subscriber levels (Loop 2000C) are attached by the caller.
+    for segment in &tokenize(contents) {
+        match segment.id {
+            // Process HL segment (required)
+            "HL" if !hl_found => {
+                info!("HL segment found");
+                loop2000b.hl_segments = get_hl(segment.elements.join("*"));
+
+                // Verify this is an Information Receiver level HL segment (level code = 21)
+                if loop2000b.hl_segments.hl03_hierarchical_level_code != "21" {
+                    info!(
+                        "Warning: Expected HL03 code '21' for Information Receiver level, got '{}'",
+                        loop2000b.hl_segments.hl03_hierarchical_level_code
+                    );
+                }
+
+                hl_found = true;
+                info!("HL segment parsed");
+            }
+            // Process NM1 segment (required)
+            "NM1" if !nm1_found => {
+                info!("NM1 segment found");
+                loop2000b.nm1_segments = get_nm1(segment.elements.join("*"));
+                nm1_found = true;
+                info!("NM1 segment parsed");
+            }
+            _ => leftover.push_str(&segment.to_edi()),
         }
+    }
 
-        info!("HL segment parsed");
-        contents = content_trim("HL", &contents);
-    } else {
+    if !hl_found {
         info!("Warning: Required HL segment not found in Loop 2000B");
     }
-
-    // Process NM1 segment (required)
-    if contents.contains("NM1") {
-        info!("NM1 segment found");
-        let nm1_content = get_segment_contents("NM1", &contents);
-        loop2000b.nm1_segments = get_nm1(nm1_content);
-        info!("NM1 segment parsed");
-        contents = content_trim("NM1", &contents);
-    } else {
+    if !nm1_found {
         info!("Warning: Required NM1 segment not found in Loop 2000B");
     }
 
-    // Process Loop 2000C segments (can be multiple)
-    let mut loop2000c_vec = Vec::new();
-    while contents.contains("HL*") && is_loop_2000c(&contents) {
-        let (loop2000c, new_contents) = get_loop_2000c(&contents);
-        loop2000c_vec.push(loop2000c);
-        contents = new_contents;
-    }
-    loop2000b.loop2000c = loop2000c_vec;
-
     info!("Loop 2000B parsed");
-    (loop2000b, contents)
+    (loop2000b, leftover)
 }
 
-// Helper function to check if the next HL segment is for Loop 2000C
-fn is_loop_2000c(contents: &str) -> bool {
-    if let Some(hl_content) = get_full_segment_contents("HL", contents) {
-        let parts: Vec<&str> = hl_content.split('*').collect();
-        if parts.len() > 3 && parts[3] == "22" {
-            return true;
-        }
-    }
-    false
+/// Check if an HL level (its HL segment up to the next HL) is a Loop 2000C subscriber level
+pub fn is_loop_2000c(level: &[Segment]) -> bool {
+    level
+        .first()
+        .is_some_and(|hl| hl.id == "HL" && hl.element(2) == "22")
 }
 
 // Import Loop2000C to avoid circular dependency
@@ -98,9 +94,9 @@ mod tests {
     #[test]
     fn test_is_loop_2000c() {
         let contents = "HL*2*1*22*0~NM1*IL*1*DOE*JOHN****MI*12345678901~".to_string();
-        assert!(is_loop_2000c(&contents));
+        assert!(is_loop_2000c(&tokenize(&contents)));
 
         let contents = "HL*2*1*21*0~NM1*PR*2*INSURANCE COMPANY*****PI*12345~".to_string();
-        assert!(!is_loop_2000c(&contents));
+        assert!(!is_loop_2000c(&tokenize(&contents)));
     }
 }
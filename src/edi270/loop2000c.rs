@@ -1,7 +1,7 @@
 use log::info;
 use serde::{Deserialize, Serialize};
 
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{tokenize, Segment};
 use crate::segments::dmg::*;
 use crate::segments::dtp::*;
 use crate::segments::eq::*;
@@ -27,128 +27,108 @@ pub struct Loop2000C {
 }
 
 pub fn get_loop_2000c(contents: &str) -> (Loop2000C, String) {
-    let mut contents = contents.to_string();
     let mut loop2000c = Loop2000C::default();
-
-    // Process HL segment (required)
-    if contents.contains("HL*") {
-        info!("HL segment found");
-        let hl_content = get_segment_contents("HL", &contents);
-        loop2000c.hl_segments = get_hl(hl_content);
-
-        // Verify this is a Subscriber level HL segment (level code = 22)
-        if loop2000c.hl_segments.hl03_hierarchical_level_code != "22" {
-            info!(
-                "Warning: Expected HL03 code '22' for Subscriber level, got '{}'",
-                loop2000c.hl_segments.hl03_hierarchical_level_code
-            );
+    let mut leftover = String::new();
+    let mut hl_found = false;
+    let mut nm1_found = false;
+
+    // `contents` is the Subscriber level only: its HL segment up to
+    // the next HL. Dependent levels (Loop 2000D) are attached by the caller.
+    // REF and DTP segments after the first EQ belong to the eligibility
+    // inquiry (Loop 2110C) and are left unprocessed rather than moved.
+    for segment in &tokenize(contents) {
+        match segment.id {
+            // Process HL segment (required)
+            "HL" if !hl_found => {
+                info!("HL segment found");
+                loop2000c.hl_segments = get_hl(segment.elements.join("*"));
+
+                // Verify this is a Subscriber level HL segment (level code = 22)
+                if loop2000c.hl_segments.hl03_hierarchical_level_code != "22" {
+                    info!(
+                        "Warning: Expected HL03 code '22' for Subscriber level, got '{}'",
+                        loop2000c.hl_segments.hl03_hierarchical_level_code
+                    );
+                }
+
+                hl_found = true;
+                info!("HL segment parsed");
+            }
+            // Process TRN segment (situational)
+            "TRN" if loop2000c.trn_segments.is_none() => {
+                info!("TRN segment found");
+                loop2000c.trn_segments = Some(get_trn(segment.elements.join("*")));
+                info!("TRN segment parsed");
+            }
+            // Process NM1 segment (required)
+            "NM1" if !nm1_found => {
+                info!("NM1 segment found");
+                loop2000c.nm1_segments = get_nm1(segment.elements.join("*"));
+                nm1_found = true;
+                info!("NM1 segment parsed");
+            }
+            // Process REF segments (situational, can be multiple)
+            "REF" if loop2000c.eq_segments.is_empty() => {
+                info!("REF segment found");
+                loop2000c
+                    .ref_segments
+                    .push(get_ref(segment.elements.join("*")));
+                info!("REF segment parsed");
+            }
+            // Process N3 segment (situational)
+            "N3" if loop2000c.n3_segments.is_none() => {
+                info!("N3 segment found");
+                loop2000c.n3_segments = Some(get_n3(segment.elements.join("*")));
+                info!("N3 segment parsed");
+            }
+            // Process N4 segment (situational)
+            "N4" if loop2000c.n4_segments.is_none() => {
+                info!("N4 segment found");
+                loop2000c.n4_segments = Some(get_n4(segment.elements.join("*")));
+                info!("N4 segment parsed");
+            }
+            // Process DMG segment (situational)
+            "DMG" if loop2000c.dmg_segments.is_none() => {
+                info!("DMG segment found");
+                loop2000c.dmg_segments = Some(get_dmg(segment.elements.join("*")));
+                info!("DMG segment parsed");
+            }
+            // Process DTP segments (situational, can be multiple)
+            "DTP" if loop2000c.eq_segments.is_empty() => {
+                info!("DTP segment found");
+                loop2000c
+                    .dtp_segments
+                    .push(get_dtp(segment.elements.join("*")));
+                info!("DTP segment parsed");
+            }
+            // Process EQ segments (situational, can be multiple)
+            "EQ" => {
+                info!("EQ segment found");
+                loop2000c
+                    .eq_segments
+                    .push(get_eq(segment.elements.join("*")));
+                info!("EQ segment parsed");
+            }
+            _ => leftover.push_str(&segment.to_edi()),
         }
-
-        info!("HL segment parsed");
-        contents = content_trim("HL", &contents);
-    } else {
-        info!("Warning: Required HL segment not found in Loop 2000C");
     }
 
-    // Process TRN segment (situational)
-    if contents.contains("TRN") {
-        info!("TRN segment found");
-        let trn_content = get_segment_contents("TRN", &contents);
-        loop2000c.trn_segments = Some(get_trn(trn_content));
-        info!("TRN segment parsed");
-        contents = content_trim("TRN", &contents);
+    if !hl_found {
+        info!("Warning: Required HL segment not found in Loop 2000C");
     }
-
-    // Process NM1 segment (required)
-    if contents.contains("NM1") {
-        info!("NM1 segment found");
-        let nm1_content = get_segment_contents("NM1", &contents);
-        loop2000c.nm1_segments = get_nm1(nm1_content);
-        info!("NM1 segment parsed");
-        contents = content_trim("NM1", &contents);
-    } else {
+    if !nm1_found {
         info!("Warning: Required NM1 segment not found in Loop 2000C");
     }
 
-    // Process REF segments (situational, can be multiple)
-    while contents.starts_with("REF") {
-        info!("REF segment found");
-        let ref_content = get_segment_contents("REF", &contents);
-        let ref_segment = get_ref(ref_content);
-        info!("REF segment parsed");
-        loop2000c.ref_segments.push(ref_segment);
-        contents = content_trim("REF", &contents);
-    }
-
-    // Process N3 segment (situational)
-    if contents.contains("N3") {
-        info!("N3 segment found");
-        let n3_content = get_segment_contents("N3", &contents);
-        loop2000c.n3_segments = Some(get_n3(n3_content));
-        info!("N3 segment parsed");
-        contents = content_trim("N3", &contents);
-    }
-
-    // Process N4 segment (situational)
-    if contents.contains("N4") {
-        info!("N4 segment found");
-        let n4_content = get_segment_contents("N4", &contents);
-        loop2000c.n4_segments = Some(get_n4(n4_content));
-        info!("N4 segment parsed");
-        contents = content_trim("N4", &contents);
-    }
-
-    // Process DMG segment (situational)
-    if contents.contains("DMG") {
-        info!("DMG segment found");
-        let dmg_content = get_segment_contents("DMG", &contents);
-        loop2000c.dmg_segments = Some(get_dmg(dmg_content));
-        info!("DMG segment parsed");
-        contents = content_trim("DMG", &contents);
-    }
-
-    // Process DTP segments (situational, can be multiple)
-    while contents.starts_with("DTP") {
-        info!("DTP segment found");
-        let dtp_content = get_segment_contents("DTP", &contents);
-        let dtp_segment = get_dtp(dtp_content);
-        info!("DTP segment parsed");
-        loop2000c.dtp_segments.push(dtp_segment);
-        contents = content_trim("DTP", &contents);
-    }
-
-    // Process EQ segments (situational, can be multiple)
-    while contents.starts_with("EQ") {
-        info!("EQ segment found");
-        let eq_content = get_segment_contents("EQ", &contents);
-        let eq_segment = get_eq(eq_content);
-        info!("EQ segment parsed");
-        loop2000c.eq_segments.push(eq_segment);
-        contents = content_trim("EQ", &contents);
-    }
-
-    // Process Loop 2000D segments (can be multiple)
-    let mut loop2000d_vec = Vec::new();
-    while contents.contains("HL*") && is_loop_2000d(&contents) {
-        let (loop2000d, new_contents) = get_loop_2000d(&contents);
-        loop2000d_vec.push(loop2000d);
-        contents = new_contents;
-    }
-    loop2000c.loop2000d = loop2000d_vec;
-
     info!("Loop 2000C parsed");
-    (loop2000c, contents)
+    (loop2000c, leftover)
 }
 
-// Helper function to check if the next HL segment is for Loop 2000D
-fn is_loop_2000d(contents: &str) -> bool {
-    if let Some(hl_content) = get_full_segment_contents("HL", contents) {
-        let parts: Vec<&str> = hl_content.split('*').collect();
-        if parts.len() > 3 && parts[3] == "23" {
-            return true;
-        }
-    }
-    false
+/// Check if an HL level (its HL segment up to the next HL) is a Loop 2000D dependent level
+pub fn is_loop_2000d(level: &[Segment]) -> bool {
+    level
+        .first()
+        .is_some_and(|hl| hl.id == "HL" && hl.element(2) == "23")
 }
 
 // Import Loop2000D to avoid circular dependency
@@ -213,9 +193,9 @@ mod tests {
     #[test]
     fn test_is_loop_2000d() {
         let contents = "HL*3*2*23*0~NM1*IL*1*DOE*JANE****MI*98765432101~".to_string();
-        assert!(is_loop_2000d(&contents));
+        assert!(is_loop_2000d(&tokenize(&contents)));
 
         let contents = "HL*2*1*22*0~NM1*IL*1*DOE*JOHN****MI*12345678901~".to_string();
-        assert!(!is_loop_2000d(&contents));
+        assert!(!is_loop_2000d(&tokenize(&contents)));
     }
 }
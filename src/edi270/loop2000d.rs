@@ -1,7 +1,7 @@
 use log::info;
 use serde::{Deserialize, Serialize};
 
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::tokenize;
 use crate::segments::dmg::*;
 use crate::segments::hl::*;
 use crate::segments::n3::*;
@@ -22,88 +22,83 @@ pub struct Loop2000D {
 }
 
 pub fn get_loop_2000d(contents: &str) -> (Loop2000D, String) {
-    let mut contents = contents.to_string();
     let mut loop2000d = Loop2000D::default();
-
-    // Process HL segment (required)
-    if contents.contains("HL*") {
-        info!("HL segment found");
-        let hl_content = get_segment_contents("HL", &contents);
-        loop2000d.hl_segments = get_hl(hl_content);
-
-        // Verify this is a Dependent level HL segment (level code = 23)
-        if loop2000d.hl_segments.hl03_hierarchical_level_code != "23" {
-            info!(
-                "Warning: Expected HL03 code '23' for Dependent level, got '{}'",
-                loop2000d.hl_segments.hl03_hierarchical_level_code
-            );
+    let mut leftover = String::new();
+    let mut hl_found = false;
+    let mut nm1_found = false;
+
+    // `contents` is the Dependent level only: its HL segment up to
+    // the next HL.
+    for segment in &tokenize(contents) {
+        match segment.id {
+            // Process HL segment (required)
+            "HL" if !hl_found => {
+                info!("HL segment found");
+                loop2000d.hl_segments = get_hl(segment.elements.join("*"));
+
+                // Verify this is a Dependent level HL segment (level code = 23)
+                if loop2000d.hl_segments.hl03_hierarchical_level_code != "23" {
+                    info!(
+                        "Warning: Expected HL03 code '23' for Dependent level, got '{}'",
+                        loop2000d.hl_segments.hl03_hierarchical_level_code
+                    );
+                }
+
+                hl_found = true;
+                info!("HL segment parsed");
+            }
+            // Process TRN segment (situational)
+            "TRN" if loop2000d.trn_segments.is_none() => {
+                info!("TRN segment found");
+                loop2000d.trn_segments = Some(get_trn(segment.elements.join("*")));
+                info!("TRN segment parsed");
+            }
+            // Process NM1 segment (required)
+            "NM1" if !nm1_found => {
+                info!("NM1 segment found");
+                loop2000d.nm1_segments = get_nm1(segment.elements.join("*"));
+                nm1_found = true;
+                info!("NM1 segment parsed");
+            }
+            // Process REF segments (situational, can be multiple)
+            "REF" => {
+                info!("REF segment found");
+                loop2000d
+                    .ref_segments
+                    .push(get_ref(segment.elements.join("*")));
+                info!("REF segment parsed");
+            }
+            // Process N3 segment (situational)
+            "N3" if loop2000d.n3_segments.is_none() => {
+                info!("N3 segment found");
+                loop2000d.n3_segments = Some(get_n3(segment.elements.join("*")));
+                info!("N3 segment parsed");
+            }
+            // Process N4 segment (situational)
+            "N4" if loop2000d.n4_segments.is_none() => {
+                info!("N4 segment found");
+                loop2000d.n4_segments = Some(get_n4(segment.elements.join("*")));
+                info!("N4 segment parsed");
+            }
+            // Process DMG segment (situational)
+            "DMG" if loop2000d.dmg_segments.is_none() => {
+                info!("DMG segment found");
+                loop2000d.dmg_segments = Some(get_dmg(segment.elements.join("*")));
+                info!("DMG segment parsed");
+            }
+            _ => leftover.push_str(&segment.to_edi()),
         }
-
-        info!("HL segment parsed");
-        contents = content_trim("HL", &contents);
-    } else {
-        info!("Warning: Required HL segment not found in Loop 2000D");
     }
 
-    // Process TRN segment (situational)
-    if contents.contains("TRN") {
-        info!("TRN segment found");
-        let trn_content = get_segment_contents("TRN", &contents);
-        loop2000d.trn_segments = Some(get_trn(trn_content));
-        info!("TRN segment parsed");
-        contents = content_trim("TRN", &contents);
+    if !hl_found {
+        info!("Warning: Required HL segment not found in Loop 2000D");
     }
-
-    // Process NM1 segment (required)
-    if contents.contains("NM1") {
-        info!("NM1 segment found");
-        let nm1_content = get_segment_contents("NM1", &contents);
-        loop2000d.nm1_segments = get_nm1(nm1_content);
-        info!("NM1 segment parsed");
-        contents = content_trim("NM1", &contents);
-    } else {
+    if !nm1_found {
         info!("Warning: Required NM1 segment not found in Loop 2000D");
     }
 
-    // Process REF segments (situational, can be multiple)
-    while contents.starts_with("REF") {
-        info!("REF segment found");
-        let ref_content = get_segment_contents("REF", &contents);
-        let ref_segment = get_ref(ref_content);
-        info!("REF segment parsed");
-        loop2000d.ref_segments.push(ref_segment);
-        contents = content_trim("REF", &contents);
-    }
-
-    // Process N3 segment (situational)
-    if contents.contains("N3") {
-        info!("N3 segment found");
-        let n3_content = get_segment_contents("N3", &contents);
-        loop2000d.n3_segments = Some(get_n3(n3_content));
-        info!("N3 segment parsed");
-        contents = content_trim("N3", &contents);
-    }
-
-    // Process N4 segment (situational)
-    if contents.contains("N4") {
-        info!("N4 segment found");
-        let n4_content = get_segment_contents("N4", &contents);
-        loop2000d.n4_segments = Some(get_n4(n4_content));
-        info!("N4 segment parsed");
-        contents = content_trim("N4", &contents);
-    }
-
-    // Process DMG segment (situational)
-    if contents.contains("DMG") {
-        info!("DMG segment found");
-        let dmg_content = get_segment_contents("DMG", &contents);
-        loop2000d.dmg_segments = Some(get_dmg(dmg_content));
-        info!("DMG segment parsed");
-        contents = content_trim("DMG", &contents);
-    }
-
     info!("Loop 2000D parsed");
-    (loop2000d, contents)
+    (loop2000d, leftover)
 }
 
 pub fn write_loop_2000d(loop2000d: &Loop2000D) -> String {
@@ -4,15 +4,20 @@ use serde::{Deserialize, Serialize};
 use crate::edi271::interchangecontrol::*;
 use crate::edi271::loop2000a::*;
 use crate::edi271::loop2000b::*;
-use crate::edi271::loop2110c::{get_le, get_ls};
+use crate::edi271::loop2000c::*;
+use crate::edi271::loop2000d::*;
 use crate::edi271::table1::*;
 use crate::error::EdiResult;
+use crate::helper::delimiters::Delimiters;
+use crate::helper::diagnostics::{collect_diagnostics, Diagnostics};
 use crate::helper::edihelper::*;
-use crate::segments::dtp::*;
-use crate::segments::msg::*;
-use crate::segments::per::*;
-use crate::segments::r#ref::*;
+use crate::helper::helper::clean_contents;
+use crate::helper::source_map::locate_error;
+use crate::helper::tokenizer::{find_segment, join_segments, split_loops, tokenize};
+use crate::helper::unrecognized::UnrecognizedSegments;
+use crate::helper::writer_options::WriterOptions;
 use crate::segments::se::*;
+use crate::transaction_processor::TransactionSet;
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Edi271 {
@@ -22,24 +27,51 @@ pub struct Edi271 {
     pub loop2000b: Vec<Loop2000B>,
     pub se_segments: SE,
     pub interchange_trailer: InterchangeTrailer,
-    // Store unprocessed segments for preservation
-    #[serde(skip_serializing_if = "Vec::is_empty", default)]
-    pub unprocessed_per_segments: Vec<PER>,
-    #[serde(skip_serializing_if = "Vec::is_empty", default)]
-    pub unprocessed_ref_segments: Vec<REF>,
-    #[serde(skip_serializing_if = "Vec::is_empty", default)]
-    pub unprocessed_dtp_segments: Vec<DTP>,
-    #[serde(skip_serializing_if = "Vec::is_empty", default)]
-    pub unprocessed_msg_segments: Vec<MSG>,
+    /// Delimiters declared by the source ISA, re-emitted when writing EDI
+    #[serde(default)]
+    pub delimiters: Delimiters,
+    /// Problems found while parsing: envelope mismatches, invalid values, unprocessed segments
+    #[serde(default, skip_serializing_if = "Diagnostics::is_empty")]
+    pub diagnostics: Diagnostics,
+    /// Segments the parser did not recognize, written back where they appeared
+    #[serde(default, skip_serializing_if = "UnrecognizedSegments::is_empty")]
+    pub unrecognized: UnrecognizedSegments,
 }
 
-pub fn get_271(contents: &str) -> EdiResult<(Edi271, String)> {
+impl TransactionSet for Edi271 {
+    fn parse(contents: String) -> EdiResult<(Self, String)> {
+        parse_271(&contents)
+    }
+
+    fn to_edi_with(&self, options: &WriterOptions) -> EdiResult<String> {
+        write_271_with(self, options)
+    }
+
+    fn get_transaction_type() -> &'static str {
+        "271"
+    }
+}
+
+/// Parse an EDI 271 file into an Edi271 structure; use `Edi271::parse`
+/// to also get the content that follows its transaction set
+pub fn get_271(contents: &str) -> EdiResult<Edi271> {
+    Ok(Edi271::parse(contents.to_string())?.0)
+}
+
+fn parse_271(contents: &str) -> EdiResult<(Edi271, String)> {
+    let raw = contents;
     let mut contents = contents.to_string();
     let mut edi271 = Edi271::default();
 
     // Remove BOM if present
     contents = contents.trim_start_matches("\u{feff}").to_string();
 
+    // Capture the source delimiters, then normalize to `*` and `~`
+    let delimiters = Delimiters::detect(&contents);
+    contents = clean_contents(contents);
+    let normalized = contents.clone();
+    edi271.delimiters = delimiters;
+
     // Parse Interchange Header
     let (interchange_header, new_contents) = get_interchange_header(&contents);
     edi271.interchange_header = interchange_header;
@@ -51,7 +83,7 @@ pub fn get_271(contents: &str) -> EdiResult<(Edi271, String)> {
             edi271.table1 = table1;
             contents = new_contents;
         }
-        Err(e) => return Err(e),
+        Err(e) => return Err(locate_error(raw, &contents, e)),
     }
 
     // Parse Loop 2000A (Information Source)
@@ -60,21 +92,55 @@ pub fn get_271(contents: &str) -> EdiResult<(Edi271, String)> {
             edi271.loop2000a = loop2000a;
             contents = new_contents;
         }
-        Err(e) => return Err(e),
+        Err(e) => return Err(locate_error(raw, &contents, e)),
     }
 
-    // Parse Loop 2000B (Information Receiver) - can be multiple
-    let mut loop2000b_vec = Vec::new();
-    while contents.contains("HL*") && contents.contains("*21*") {
-        match get_loop_2000b(&contents) {
-            Ok((loop2000b, new_contents)) => {
-                loop2000b_vec.push(loop2000b);
-                contents = new_contents;
+    // Walk HL segments to build the tree.
+    // HL*n*parent*21* = information receiver, HL*n*parent*22* = subscriber,
+    // HL*n*parent*23* = dependent. Each HL level runs up to the next HL; the
+    // hierarchy ends at SE.
+    let segments = tokenize(&contents);
+    let body_end = find_segment(&segments, &["SE"], 0).unwrap_or(segments.len());
+    let (leading, levels) = split_loops(&segments[..body_end], &["HL"]);
+    let mut leftover = join_segments(leading);
+    let mut levels = levels.into_iter().peekable();
+
+    while let Some(level) = levels.next() {
+        // Information Receiver level (Loop 2000B) - can be multiple
+        let Ok((mut loop2000b, remaining)) = get_loop_2000b(&join_segments(level)) else {
+            // Unknown HL level or orphan — keep it with the unprocessed content
+            leftover.push_str(&join_segments(level));
+            continue;
+        };
+        leftover.push_str(&remaining);
+
+        // Subscriber levels (Loop 2000C) until the next information receiver
+        while let Some(subscriber) = levels.next_if(|next| is_next_loop_2000c(next)) {
+            let Ok((mut loop2000c, remaining)) = get_loop_2000c(&join_segments(subscriber)) else {
+                leftover.push_str(&join_segments(subscriber));
+                continue;
+            };
+            leftover.push_str(&remaining);
+
+            // Dependent levels (Loop 2000D) until the next subscriber
+            while let Some(dependent) = levels.next_if(|next| is_next_loop_2000d(next)) {
+                match get_loop_2000d(&join_segments(dependent)) {
+                    Ok((loop2000d, remaining)) => {
+                        leftover.push_str(&remaining);
+                        loop2000c.loop2000d.push(loop2000d);
+                    }
+                    Err(_) => leftover.push_str(&join_segments(dependent)),
+                }
             }
-            Err(_) => break,
+
+            loop2000b.loop2000c.push(loop2000c);
         }
+
+        edi271.loop2000b.push(loop2000b);
     }
-    edi271.loop2000b = loop2000b_vec;
+    let mut remaining = join_segments(&segments[body_end..]);
+    remaining.push_str(&leftover);
+    contents = remaining;
 
     // Parse SE segment
     if contents.contains("SE*") {
@@ -92,201 +158,16 @@ pub fn get_271(contents: &str) -> EdiResult<(Edi271, String)> {
     edi271.interchange_trailer = interchange_trailer;
     contents = new_contents;
 
-    // Process any remaining segments that might have been missed
-    process_remaining_segments(&mut edi271, &contents);
-
-    info!("Unprocessed segments: {:?}", contents);
+    edi271.diagnostics = collect_diagnostics(&normalized, &contents, &delimiters);
+    edi271.unrecognized = UnrecognizedSegments::collect(&normalized, &contents);
     Ok((edi271, contents))
 }
 
-fn process_remaining_segments(edi271: &mut Edi271, contents: &str) {
-    // Check for LS segments
-    if contents.contains("LS") {
-        let ls_segments = extract_segments(contents, "LS");
-        for ls_content in ls_segments {
-            let ls = get_ls(ls_content);
-            info!("Found unprocessed LS segment, adding to appropriate loop");
-
-            // Add to the appropriate structure
-            if !edi271.loop2000b.is_empty()
-                && !edi271.loop2000b[0].loop2000c.is_empty()
-                && !edi271.loop2000b[0].loop2000c[0].loop2110c.is_empty()
-            {
-                edi271.loop2000b[0].loop2000c[0].loop2110c[0].ls = Some(ls);
-            }
-        }
-    }
-
-    // Check for LE segments
-    if contents.contains("LE") {
-        let le_segments = extract_segments(contents, "LE");
-        for le_content in le_segments {
-            let le = get_le(le_content);
-            info!("Found unprocessed LE segment, adding to appropriate loop");
-
-            // Add to the appropriate structure
-            if !edi271.loop2000b.is_empty()
-                && !edi271.loop2000b[0].loop2000c.is_empty()
-                && !edi271.loop2000b[0].loop2000c[0].loop2110c.is_empty()
-            {
-                edi271.loop2000b[0].loop2000c[0].loop2110c[0].le = Some(le);
-            }
-        }
-    }
-
-    // Check for PER segments
-    if contents.contains("PER") {
-        let per_segments = extract_segments(contents, "PER");
-        for per_content in per_segments {
-            let per = get_per(per_content);
-            info!("Found unprocessed PER segment: {:?}", per);
-
-            // Add to the appropriate structure
-            if per.per01_contact_function_code == "IC"
-                && per.per02_contact_name == "CUSTOMER SERVICE"
-            {
-                edi271.loop2000a.per_segments.push(per);
-            } else {
-                edi271.unprocessed_per_segments.push(per);
-            }
-        }
-    }
-
-    // Check for REF segments
-    if contents.contains("REF") {
-        let ref_segments = extract_segments(contents, "REF");
-        for ref_content in ref_segments {
-            let ref_segment = get_ref(ref_content);
-            info!("Found unprocessed REF segment: {:?}", ref_segment);
-
-            // Add to the appropriate structure based on content
-            if ref_segment.reference_id_number_qualifier == "SY"
-                && ref_segment.reference_id_number == "123456789"
-                && !edi271.loop2000b.is_empty()
-                && !edi271.loop2000b[0].loop2000c.is_empty()
-            {
-                edi271.loop2000b[0].loop2000c[0]
-                    .ref_segments
-                    .push(ref_segment);
-            } else if ref_segment.reference_id_number_qualifier == "SY"
-                && ref_segment.reference_id_number == "987654321"
-                && !edi271.loop2000b.is_empty()
-                && edi271.loop2000b[0].loop2000c.len() > 1
-            {
-                edi271.loop2000b[0].loop2000c[1]
-                    .ref_segments
-                    .push(ref_segment);
-            } else {
-                edi271.unprocessed_ref_segments.push(ref_segment);
-            }
-        }
-    }
-
-    // Check for DTP segments
-    if contents.contains("DTP") {
-        let dtp_segments = extract_segments(contents, "DTP");
-        for dtp_content in dtp_segments {
-            let dtp = get_dtp(dtp_content);
-            info!("Found unprocessed DTP segment: {:?}", dtp);
-
-            // Check if this DTP segment already exists in any loop
-            if !dtp_segment_exists(edi271, &dtp) {
-                info!("Adding unique DTP segment to unprocessed_dtp_segments");
-                edi271.unprocessed_dtp_segments.push(dtp);
-            } else {
-                info!("Skipping duplicate DTP segment");
-            }
-        }
-    }
-
-    // Check for MSG segments
-    if contents.contains("MSG") {
-        let msg_segments = extract_segments(contents, "MSG");
-        for msg_content in msg_segments {
-            let msg = get_msg(msg_content);
-            info!("Found unprocessed MSG segment: {:?}", msg);
-
-            // Add to the appropriate structure
-            if !edi271.loop2000b.is_empty()
-                && !edi271.loop2000b[0].loop2000c.is_empty()
-                && !edi271.loop2000b[0].loop2000c[0].loop2110c.is_empty()
-                && msg.msg01_free_form_message_text
-                    == "PLEASE CONTACT CUSTOMER SERVICE FOR ADDITIONAL INFORMATION"
-            {
-                edi271.loop2000b[0].loop2000c[0].loop2110c[0]
-                    .msg_segments
-                    .push(msg);
-            } else {
-                edi271.unprocessed_msg_segments.push(msg);
-            }
-        }
-    }
-}
-
-// Helper function to extract all segments of a specific type from content
-fn extract_segments(contents: &str, segment_id: &str) -> Vec<String> {
-    let mut segments = Vec::new();
-    let lines: Vec<&str> = contents.split('~').collect();
-
-    for line in lines {
-        if line.trim().starts_with(segment_id) {
-            segments.push(line.trim().to_string());
-        }
-    }
-
-    segments
-}
-
-// Helper function to check if a DTP segment already exists in any loop
-fn dtp_segment_exists(edi271: &Edi271, dtp: &DTP) -> bool {
-    // Check in all Loop2000C
-    for loop2000b in &edi271.loop2000b {
-        for loop2000c in &loop2000b.loop2000c {
-            // Check in Loop2000C DTP segments
-            for existing_dtp in &loop2000c.dtp_segments {
-                if is_dtp_duplicate(existing_dtp, dtp) {
-                    return true;
-                }
-            }
-
-            // Check in Loop2100C DTP segments
-            for loop2100c in &loop2000c.loop2100c {
-                for existing_dtp in &loop2100c.dtp_segments {
-                    if is_dtp_duplicate(existing_dtp, dtp) {
-                        return true;
-                    }
-                }
-            }
-
-            // Check in Loop2110C DTP segments
-            for loop2110c in &loop2000c.loop2110c {
-                for existing_dtp in &loop2110c.dtp_segments {
-                    if is_dtp_duplicate(existing_dtp, dtp) {
-                        return true;
-                    }
-                }
-            }
-        }
-    }
-
-    // Check in unprocessed DTP segments
-    for existing_dtp in &edi271.unprocessed_dtp_segments {
-        if is_dtp_duplicate(existing_dtp, dtp) {
-            return true;
-        }
-    }
-
-    false
-}
-
-// Helper function to check if two DTP segments are duplicates
-fn is_dtp_duplicate(dtp1: &DTP, dtp2: &DTP) -> bool {
-    dtp1.dtp01_date_time_qualifier == dtp2.dtp01_date_time_qualifier
-        && dtp1.dtp02_date_time_format_qualifier == dtp2.dtp02_date_time_format_qualifier
-        && dtp1.dtp03_date_time_value == dtp2.dtp03_date_time_value
+pub fn write_271(edi271: &Edi271) -> EdiResult<String> {
+    write_271_with(edi271, &WriterOptions::default())
 }
 
-pub fn write_271(edi271: &Edi271) -> String {
+pub fn write_271_with(edi271: &Edi271, options: &WriterOptions) -> EdiResult<String> {
     // Create a custom order of segments to match the original file structure
     let mut new_edi = String::new();
 
@@ -304,83 +185,17 @@ pub fn write_271(edi271: &Edi271) -> String {
         new_edi.push_str(&write_loop_2000b(loop2000b));
     }
 
-    // Write any unprocessed PER segments
-    for per_segment in &edi271.unprocessed_per_segments {
-        new_edi.push_str(&write_per(per_segment.clone()));
-    }
-
-    // Write any unprocessed REF segments
-    for ref_segment in &edi271.unprocessed_ref_segments {
-        new_edi.push_str(&write_ref(ref_segment.clone()));
-    }
-
-    // Write any unprocessed DTP segments
-    for dtp_segment in &edi271.unprocessed_dtp_segments {
-        new_edi.push_str(&write_dtp(dtp_segment.clone()));
-    }
-
-    // Write any unprocessed MSG segments
-    for msg_segment in &edi271.unprocessed_msg_segments {
-        new_edi.push_str(&write_msg(msg_segment.clone()));
-    }
-
     // Write SE segment
     new_edi.push_str(&write_se(edi271.se_segments.clone()));
 
     // Write Interchange Trailer
     new_edi.push_str(&write_interchange_trailer(&edi271.interchange_trailer));
 
-    // Remove any duplicate DTP segments that might have been added
-    new_edi = remove_duplicate_dtp_segments(&new_edi);
-
-    // Add line breaks between segments for better readability
-    let new_edi_with_breaks = new_edi.replace("~", "~\n");
-
-    info!("Generated EDI 271: {}", new_edi_with_breaks);
-    new_edi_with_breaks
-}
-
-// Helper function to remove duplicate DTP segments
-fn remove_duplicate_dtp_segments(edi_content: &str) -> String {
-    let mut result = String::new();
-    let mut seen_dtp_segments = Vec::new();
-
-    // Split the EDI content by segment terminator
-    let segments: Vec<&str> = edi_content.split('~').collect();
-
-    for segment in segments {
-        let trimmed_segment = segment.trim();
-
-        // If this is a DTP segment, check if we've seen it before
-        if trimmed_segment.starts_with("DTP") {
-            let dtp_parts: Vec<&str> = trimmed_segment.split('*').collect();
-
-            // Create a unique key for this DTP segment
-            let dtp_key = if dtp_parts.len() >= 4 {
-                format!(
-                    "{}*{}*{}",
-                    dtp_parts[1], // qualifier
-                    dtp_parts[2], // format qualifier
-                    dtp_parts[3]  // value
-                )
-            } else {
-                trimmed_segment.to_string()
-            };
-
-            // If we haven't seen this DTP segment before, add it
-            if !seen_dtp_segments.contains(&dtp_key) {
-                seen_dtp_segments.push(dtp_key);
-                result.push_str(trimmed_segment);
-                result.push('~');
-            }
-        } else if !trimmed_segment.is_empty() {
-            // For non-DTP segments, just add them as is
-            result.push_str(trimmed_segment);
-            result.push('~');
-        }
-    }
+    let new_edi = edi271.unrecognized.restore(new_edi, &Delimiters::default());
+    let new_edi = options.format(&new_edi, &edi271.delimiters)?;
 
-    result
+    info!("Generated EDI 271: {}", new_edi);
+    Ok(new_edi)
 }
 
 pub fn is_271_json(contents: &str) -> bool {
@@ -394,75 +209,46 @@ pub fn is_271_json(contents: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::segments::dtp::DTP;
-    use crate::segments::msg::MSG;
-    use crate::segments::per::PER;
-    use crate::segments::r#ref::REF;
-
-    #[test]
-    fn test_write_271_with_unprocessed_segments() {
-        // Create a minimal Edi271 structure with unprocessed segments
-        let mut edi271 = Edi271::default();
-
-        // Add a PER segment to unprocessed_per_segments
-        let per_segment = PER {
-            per01_contact_function_code: "IC".to_string(),
-            per02_contact_name: "JOHN DOE".to_string(),
-            per03_contact_number_qualifier: "TE".to_string(),
-            per04_contact_number: "8005551212".to_string(),
-            per05_contact_number_qualifier: "".to_string(),
-            per06_contact_number: "".to_string(),
-            per07_contact_number_qualifier: "".to_string(),
-            per08_contact_number: "".to_string(),
-        };
-        edi271.unprocessed_per_segments.push(per_segment);
-
-        // Add a REF segment to unprocessed_ref_segments
-        let ref_segment = REF {
-            reference_id_number_qualifier: "SY".to_string(),
-            reference_id_number: "123456789".to_string(),
-        };
-        edi271.unprocessed_ref_segments.push(ref_segment);
-
-        // Add a DTP segment to unprocessed_dtp_segments
-        let dtp_segment = DTP {
-            dtp01_date_time_qualifier: "291".to_string(),
-            dtp02_date_time_format_qualifier: "D8".to_string(),
-            dtp03_date_time_value: "20060501".to_string(),
-        };
-        edi271.unprocessed_dtp_segments.push(dtp_segment);
 
-        // Add a MSG segment to unprocessed_msg_segments
-        let msg_segment = MSG {
-            msg01_free_form_message_text: "TEST MESSAGE".to_string(),
-            msg02_printer_carriage_control_code: "".to_string(),
-            msg03_number: "".to_string(),
-        };
-        edi271.unprocessed_msg_segments.push(msg_segment);
+    const SAMPLE_271: &str = "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230101*1200*^*00501*000000001*0*P*:~GS*HB*SENDER*RECEIVER*20230101*1200*1*X*005010X279A1~ST*271*0001*005010X279A1~BHT*0022*11*10001234*20230101*1200*DG~HL*1**20*1~NM1*PR*2*ABC INSURANCE COMPANY*****PI*842610001~HL*2*1*21*1~NM1*1P*2*BONE AND JOINT CLINIC*****SV*2000035~HL*3*2*22*0~TRN*2*93175-012547*9877281234~NM1*IL*1*SMITH*JOHN****MI*123456789~DMG*D8*19630519*M~ZZZ*KEEP*ME~DTP*346*D8*20230101~EB*1**30**GOLD 123 PLAN~SE*13*0001~GE*1*1~IEA*1*000000001~";
 
-        // Write the EDI271
-        let output = write_271(&edi271);
+    #[test]
+    fn test_unrecognized_segments_round_trip() {
+        let edi271 = get_271(SAMPLE_271).unwrap();
+        // Segments after an unrecognized one may be left over too; all are kept in place
+        let first = edi271.unrecognized.iter().next().unwrap();
+        assert_eq!(first.segment, "ZZZ*KEEP*ME");
+        assert_eq!(first.loop_path, "2000C/2100C");
+        assert!(edi271.unrecognized.check_strict().is_err());
+
+        let output = write_271(&edi271).unwrap();
+        assert!(output.contains("DMG*D8*19630519*M~\nZZZ*KEEP*ME~\nDTP*346*D8*20230101~"));
+        assert_eq!(output.replace('\n', ""), SAMPLE_271);
+    }
 
-        // Check if all segments are included in the output
-        assert!(
-            output.contains("PER*IC*JOHN DOE*TE*8005551212~"),
-            "PER segment not found in output"
-        );
-        assert!(
-            output.contains("REF*SY*123456789~"),
-            "REF segment not found in output"
-        );
-        assert!(
-            output.contains("DTP*291*D8*20060501~"),
-            "DTP segment not found in output"
+    #[test]
+    fn test_hl_levels_attach_to_their_parents() {
+        let contents = "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230101*1200*^*00501*000000001*0*P*:~GS*HB*SENDER*RECEIVER*20230101*1200*1*X*005010X279A1~ST*271*0001*005010X279A1~BHT*0022*11*10001234*20230101*1200~HL*1**20*1~NM1*PR*2*ABC INSURANCE COMPANY*****PI*842610001~HL*2*1*21*1~NM1*1P*2*BONE AND JOINT CLINIC*****SV*2000035~HL*3*2*22*1~NM1*IL*1*SMITH*JOHN****MI*123456789~DTP*346*D8*20230101~EB*1**30~MSG*FREE FORM~HL*4*3*23*0~NM1*03*1*SMITH*MARY~DTP*346*D8*20230101~EB*1**30~III*ZZ*11~HL*5*1*21*1~NM1*1P*2*CITY CLINIC*****SV*2000036~HL*6*5*22*0~NM1*IL*1*DOE*JANE****MI*987654321~EB*6**30~SE*20*0001~GE*1*1~IEA*1*000000001~";
+        let (edi271, remaining) = Edi271::parse(contents.to_string()).unwrap();
+        assert_eq!(remaining, "");
+        assert_eq!(edi271.loop2000b.len(), 2);
+
+        let subscriber = &edi271.loop2000b[0].loop2000c[0];
+        assert_eq!(subscriber.loop2110c.len(), 1);
+        assert_eq!(
+            subscriber.loop2110c[0].msg_segments[0].msg01_free_form_message_text,
+            "FREE FORM"
         );
-        assert!(
-            output.contains("MSG*TEST MESSAGE~"),
-            "MSG segment not found in output"
+        assert_eq!(subscriber.loop2000d.len(), 1);
+        assert_eq!(subscriber.loop2000d[0].loop2110d[0].loop2115d.len(), 1);
+        assert_eq!(
+            edi271.loop2000b[1].loop2000c[0].nm1_segments.lastname,
+            "DOE"
         );
 
-        // Check if line breaks are added
-        assert!(output.contains("~\n"), "Line breaks not found in output");
+        // Both DTP*346 segments are kept: each belongs to its own level
+        let output = write_271(&edi271).unwrap();
+        assert_eq!(output.matches("DTP*346*D8*20230101~").count(), 2);
     }
 
     #[test]
@@ -2,7 +2,7 @@ use log::info;
 use serde::{Deserialize, Serialize};
 
 use crate::error::{EdiError, EdiResult};
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{find_segment, join_segments, split_loops, tokenize, Segment};
 use crate::segments::aaa::*;
 use crate::segments::hl::*;
 use crate::segments::nm1::*;
@@ -28,170 +28,106 @@ pub struct Loop2100A {
 }
 
 pub fn get_loop_2000a(contents: &str) -> EdiResult<(Loop2000A, String)> {
-    let mut contents = contents.to_string();
     let mut loop2000a = Loop2000A::default();
+    let mut leftover = String::new();
 
-    // Process HL segment (required)
-    if contents.contains("HL*") {
-        info!("HL segment found");
-        let hl_content = get_segment_contents("HL", &contents);
-        if hl_content.is_empty() {
-            return Err(EdiError::MissingSegment("HL".to_string()));
-        }
-        loop2000a.hl_segments = get_hl(hl_content);
-
-        // Verify this is an Information Source level HL segment (level code = 20)
-        if loop2000a.hl_segments.hl03_hierarchical_level_code != "20" {
-            return Err(EdiError::ValidationError(format!(
-                "Expected HL03 code '20' for Information Source level, got '{}'",
-                loop2000a.hl_segments.hl03_hierarchical_level_code
-            )));
-        }
-
-        info!("HL segment parsed");
-        contents = content_trim("HL", &contents);
-    } else {
+    // Loop 2000A runs from its HL segment (required) up to the next HL
+    let segments = tokenize(contents);
+    let Some(start) = find_segment(&segments, &["HL"], 0) else {
         return Err(EdiError::MissingSegment("HL".to_string()));
-    }
-
-    // Process NM1 segment (required)
-    if contents.contains("NM1") {
-        info!("NM1 segment found");
-        let nm1_content = get_segment_contents("NM1", &contents);
-        if nm1_content.is_empty() {
-            return Err(EdiError::MissingSegment("NM1".to_string()));
-        }
-        loop2000a.nm1_segments = get_nm1(nm1_content);
-        info!("NM1 segment parsed");
-        contents = content_trim("NM1", &contents);
-    } else {
+    };
+    let end = find_segment(&segments, &["HL", "SE"], start + 1).unwrap_or(segments.len());
+
+    info!("HL segment found");
+    loop2000a.hl_segments = get_hl(segments[start].elements.join("*"));
+
+    // Verify this is an Information Source level HL segment (level code = 20)
+    if loop2000a.hl_segments.hl03_hierarchical_level_code != "20" {
+        return Err(EdiError::ValidationError(format!(
+            "Expected HL03 code '20' for Information Source level, got '{}'",
+            loop2000a.hl_segments.hl03_hierarchical_level_code
+        )));
+    }
+    info!("HL segment parsed");
+
+    // The first NM1 names the information source and carries its PER, REF
+    // and AAA segments; each further NM1 starts a Loop 2100A
+    let (leading, names) = split_loops(&segments[start + 1..end], &["NM1"]);
+    leftover.push_str(&join_segments(leading));
+    let Some((source, names)) = names.split_first() else {
         return Err(EdiError::MissingSegment("NM1".to_string()));
-    }
-
-    // Process PER segments (situational, can be multiple)
-    while contents.starts_with("PER") {
-        info!("PER segment found");
-        let per_content = get_segment_contents("PER", &contents);
-        if per_content.is_empty() {
-            break;
-        }
-        let per = get_per(per_content);
-        info!("PER segment parsed");
-        loop2000a.per_segments.push(per);
-        contents = content_trim("PER", &contents);
-    }
+    };
 
-    // Process REF segments (situational, can be multiple)
-    while contents.starts_with("REF") {
-        info!("REF segment found");
-        let ref_content = get_segment_contents("REF", &contents);
-        if ref_content.is_empty() {
-            break;
-        }
-        let ref_segment = get_ref(ref_content);
-        info!("REF segment parsed");
-        loop2000a.ref_segments.push(ref_segment);
-        contents = content_trim("REF", &contents);
-    }
-
-    // Process AAA segments (situational, can be multiple)
-    while contents.starts_with("AAA") {
-        info!("AAA segment found");
-        let aaa_content = get_segment_contents("AAA", &contents);
-        if aaa_content.is_empty() {
-            break;
-        }
-        let aaa = get_aaa(aaa_content);
-        info!("AAA segment parsed");
-        loop2000a.aaa_segments.push(aaa);
-        contents = content_trim("AAA", &contents);
-    }
+    let (source, remaining) = get_loop_2100a(&join_segments(source))?;
+    loop2000a.nm1_segments = source.nm1_segments;
+    loop2000a.per_segments = source.per_segments;
+    loop2000a.ref_segments = source.ref_segments;
+    loop2000a.aaa_segments = source.aaa_segments;
+    leftover.push_str(&remaining);
 
     // Process Loop 2100A segments (can be multiple)
-    while contents.contains("NM1") && !is_next_loop_2000b(&contents) {
-        match get_loop_2100a(&contents) {
-            Ok((loop2100a, new_contents)) => {
-                loop2000a.loop2100a.push(loop2100a);
-                contents = new_contents;
-            }
-            Err(_) => break,
-        }
+    for name in names {
+        let (loop2100a, remaining) = get_loop_2100a(&join_segments(name))?;
+        loop2000a.loop2100a.push(loop2100a);
+        leftover.push_str(&remaining);
     }
 
     info!("Loop 2000A parsed");
+    let mut contents = join_segments(&segments[..start]);
+    contents.push_str(&join_segments(&segments[end..]));
+    contents.push_str(&leftover);
     Ok((loop2000a, contents))
 }
 
-// Helper function to check if the next segment starts a new 2000B loop
-fn is_next_loop_2000b(contents: &str) -> bool {
-    if let Some(hl_content) = get_full_segment_contents("HL", contents) {
-        let parts: Vec<&str> = hl_content.split('*').collect();
-        if parts.len() > 3 && parts[3] == "21" {
-            return true;
-        }
-    }
-    false
+// Helper function to check if an HL level (its HL segment up to the next HL) starts a new 2000B loop
+pub fn is_next_loop_2000b(level: &[Segment]) -> bool {
+    level
+        .first()
+        .is_some_and(|hl| hl.id == "HL" && hl.element(2) == "21")
 }
 
 pub fn get_loop_2100a(contents: &str) -> EdiResult<(Loop2100A, String)> {
-    let mut contents = contents.to_string();
     let mut loop2100a = Loop2100A::default();
+    let mut leftover = String::new();
 
     // Process NM1 segment (required)
-    if contents.contains("NM1") {
-        info!("NM1 segment found for Loop 2100A");
-        let nm1_content = get_segment_contents("NM1", &contents);
-        if nm1_content.is_empty() {
-            return Err(EdiError::MissingSegment("NM1".to_string()));
-        }
-        loop2100a.nm1_segments = get_nm1(nm1_content);
-        info!("NM1 segment parsed for Loop 2100A");
-        contents = content_trim("NM1", &contents);
-    } else {
+    let segments = tokenize(contents);
+    let Some((nm1, rest)) = segments.split_first().filter(|(nm1, _)| nm1.id == "NM1") else {
         return Err(EdiError::MissingSegment("NM1".to_string()));
-    }
-
-    // Process PER segments (situational, can be multiple)
-    while contents.starts_with("PER") {
-        info!("PER segment found for Loop 2100A");
-        let per_content = get_segment_contents("PER", &contents);
-        if per_content.is_empty() {
-            break;
-        }
-        let per = get_per(per_content);
-        info!("PER segment parsed for Loop 2100A");
-        loop2100a.per_segments.push(per);
-        contents = content_trim("PER", &contents);
-    }
-
-    // Process REF segments (situational, can be multiple)
-    while contents.starts_with("REF") {
-        info!("REF segment found for Loop 2100A");
-        let ref_content = get_segment_contents("REF", &contents);
-        if ref_content.is_empty() {
-            break;
-        }
-        let ref_segment = get_ref(ref_content);
-        info!("REF segment parsed for Loop 2100A");
-        loop2100a.ref_segments.push(ref_segment);
-        contents = content_trim("REF", &contents);
-    }
-
-    // Process AAA segments (situational, can be multiple)
-    while contents.starts_with("AAA") {
-        info!("AAA segment found for Loop 2100A");
-        let aaa_content = get_segment_contents("AAA", &contents);
-        if aaa_content.is_empty() {
-            break;
+    };
+    info!("NM1 segment found for Loop 2100A");
+    loop2100a.nm1_segments = get_nm1(nm1.elements.join("*"));
+    info!("NM1 segment parsed for Loop 2100A");
+
+    for segment in rest {
+        match segment.id {
+            // Process PER segments (situational, can be multiple)
+            "PER" => {
+                info!("PER segment found for Loop 2100A");
+                let per = get_per(segment.elements.join("*"));
+                info!("PER segment parsed for Loop 2100A");
+                loop2100a.per_segments.push(per);
+            }
+            // Process REF segments (situational, can be multiple)
+            "REF" => {
+                info!("REF segment found for Loop 2100A");
+                let ref_segment = get_ref(segment.elements.join("*"));
+                info!("REF segment parsed for Loop 2100A");
+                loop2100a.ref_segments.push(ref_segment);
+            }
+            // Process AAA segments (situational, can be multiple)
+            "AAA" => {
+                info!("AAA segment found for Loop 2100A");
+                let aaa = get_aaa(segment.elements.join("*"));
+                info!("AAA segment parsed for Loop 2100A");
+                loop2100a.aaa_segments.push(aaa);
+            }
+            _ => leftover.push_str(&segment.to_edi()),
         }
-        let aaa = get_aaa(aaa_content);
-        info!("AAA segment parsed for Loop 2100A");
-        loop2100a.aaa_segments.push(aaa);
-        contents = content_trim("AAA", &contents);
     }
 
     info!("Loop 2100A parsed");
-    Ok((loop2100a, contents))
+    Ok((loop2100a, leftover))
 }
 
 pub fn write_loop_2000a(loop2000a: &Loop2000A) -> String {
@@ -250,20 +186,6 @@ pub fn write_loop_2100a(loop2100a: &Loop2100A) -> String {
     contents
 }
 
-// Placeholder for AAA segment functions until we implement them
-fn get_aaa(_aaa_content: String) -> AAA {
-    AAA::default()
-}
-
-fn write_aaa(aaa: AAA) -> String {
-    format!(
-        "AAA*{}*{}*{}~",
-        aaa.aaa01_valid_request_indicator,
-        aaa.aaa02_agency_qualifier_code,
-        aaa.aaa03_reject_reason_code
-    )
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -271,9 +193,9 @@ mod tests {
     #[test]
     fn test_is_next_loop_2000b() {
         let contents = "HL*2*1*21*1~NM1*1P*2*ACME MEDICAL CENTER*****XX*1234567890~".to_string();
-        assert!(is_next_loop_2000b(&contents));
+        assert!(is_next_loop_2000b(&tokenize(&contents)));
 
         let contents = "HL*1**20*1~NM1*PR*2*ABC INSURANCE*****PI*12345~".to_string();
-        assert!(!is_next_loop_2000b(&contents));
+        assert!(!is_next_loop_2000b(&tokenize(&contents)));
     }
 }
@@ -2,7 +2,7 @@ use log::info;
 use serde::{Deserialize, Serialize};
 
 use crate::error::{EdiError, EdiResult};
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{join_segments, split_loops, tokenize, Segment};
 use crate::segments::aaa::*;
 use crate::segments::hl::*;
 use crate::segments::nm1::*;
@@ -29,181 +29,104 @@ pub struct Loop2100B {
 }
 
 pub fn get_loop_2000b(contents: &str) -> EdiResult<(Loop2000B, String)> {
-    let mut contents = contents.to_string();
     let mut loop2000b = Loop2000B::default();
+    let mut leftover = String::new();
 
-    // Process HL segment (required)
-    if contents.contains("HL*") {
-        info!("HL segment found");
-        let hl_content = get_segment_contents("HL", &contents);
-        if hl_content.is_empty() {
-            return Err(EdiError::MissingSegment("HL".to_string()));
-        }
-        loop2000b.hl_segments = get_hl(hl_content);
-
-        // Verify this is an Information Receiver level HL segment (level code = 21)
-        if loop2000b.hl_segments.hl03_hierarchical_level_code != "21" {
-            return Err(EdiError::ValidationError(format!(
-                "Expected HL03 code '21' for Information Receiver level, got '{}'",
-                loop2000b.hl_segments.hl03_hierarchical_level_code
-            )));
-        }
-
-        info!("HL segment parsed");
-        contents = content_trim("HL", &contents);
-    } else {
+    // `contents` is the Information Receiver level only: its HL segment up to
+    // the next HL. Subscriber levels (Loop 2000C) are attached by the caller.
+    let segments = tokenize(contents);
+    let Some((hl, rest)) = segments.split_first().filter(|(hl, _)| hl.id == "HL") else {
         return Err(EdiError::MissingSegment("HL".to_string()));
-    }
+    };
 
-    // Process NM1 segment (required)
-    if contents.contains("NM1") {
-        info!("NM1 segment found");
-        let nm1_content = get_segment_contents("NM1", &contents);
-        if nm1_content.is_empty() {
-            return Err(EdiError::MissingSegment("NM1".to_string()));
-        }
-        loop2000b.nm1_segments = get_nm1(nm1_content);
-        info!("NM1 segment parsed");
-        contents = content_trim("NM1", &contents);
-    } else {
+    // Process HL segment (required)
+    info!("HL segment found");
+    loop2000b.hl_segments = get_hl(hl.elements.join("*"));
+
+    // Verify this is an Information Receiver level HL segment (level code = 21)
+    if loop2000b.hl_segments.hl03_hierarchical_level_code != "21" {
+        return Err(EdiError::ValidationError(format!(
+            "Expected HL03 code '21' for Information Receiver level, got '{}'",
+            loop2000b.hl_segments.hl03_hierarchical_level_code
+        )));
+    }
+    info!("HL segment parsed");
+
+    // The first NM1 names the information receiver and carries its PER, REF
+    // and AAA segments; each further NM1 starts a Loop 2100B
+    let (leading, names) = split_loops(rest, &["NM1"]);
+    leftover.push_str(&join_segments(leading));
+    let Some((receiver, names)) = names.split_first() else {
         return Err(EdiError::MissingSegment("NM1".to_string()));
-    }
+    };
 
-    // Process PER segments (situational, can be multiple)
-    while contents.starts_with("PER") {
-        info!("PER segment found");
-        let per_content = get_segment_contents("PER", &contents);
-        if per_content.is_empty() {
-            break;
-        }
-        let per = get_per(per_content);
-        info!("PER segment parsed");
-        loop2000b.per_segments.push(per);
-        contents = content_trim("PER", &contents);
-    }
-
-    // Process REF segments (situational, can be multiple)
-    while contents.starts_with("REF") {
-        info!("REF segment found");
-        let ref_content = get_segment_contents("REF", &contents);
-        if ref_content.is_empty() {
-            break;
-        }
-        let ref_segment = get_ref(ref_content);
-        info!("REF segment parsed");
-        loop2000b.ref_segments.push(ref_segment);
-        contents = content_trim("REF", &contents);
-    }
-
-    // Process AAA segments (situational, can be multiple)
-    while contents.starts_with("AAA") {
-        info!("AAA segment found");
-        let aaa_content = get_segment_contents("AAA", &contents);
-        if aaa_content.is_empty() {
-            break;
-        }
-        let aaa = get_aaa(aaa_content);
-        info!("AAA segment parsed");
-        loop2000b.aaa_segments.push(aaa);
-        contents = content_trim("AAA", &contents);
-    }
+    let (receiver, remaining) = get_loop_2100b(&join_segments(receiver))?;
+    loop2000b.nm1_segments = receiver.nm1_segments;
+    loop2000b.per_segments = receiver.per_segments;
+    loop2000b.ref_segments = receiver.ref_segments;
+    loop2000b.aaa_segments = receiver.aaa_segments;
+    leftover.push_str(&remaining);
 
     // Process Loop 2100B segments (can be multiple)
-    while contents.contains("NM1") && !is_next_loop_2000c(&contents) {
-        match get_loop_2100b(&contents) {
-            Ok((loop2100b, new_contents)) => {
-                loop2000b.loop2100b.push(loop2100b);
-                contents = new_contents;
-            }
-            Err(_) => break,
-        }
-    }
-
-    // Process Loop 2000C segments (can be multiple)
-    while contents.contains("HL*") && is_next_loop_2000c(&contents) {
-        match get_loop_2000c(&contents) {
-            Ok((loop2000c, new_contents)) => {
-                loop2000b.loop2000c.push(loop2000c);
-                contents = new_contents;
-            }
-            Err(_) => break,
-        }
+    for name in names {
+        let (loop2100b, remaining) = get_loop_2100b(&join_segments(name))?;
+        loop2000b.loop2100b.push(loop2100b);
+        leftover.push_str(&remaining);
     }
 
     info!("Loop 2000B parsed");
-    Ok((loop2000b, contents))
+    Ok((loop2000b, leftover))
 }
 
-// Helper function to check if the next segment starts a new 2000C loop
-fn is_next_loop_2000c(contents: &str) -> bool {
-    if let Some(hl_content) = get_full_segment_contents("HL", contents) {
-        let parts: Vec<&str> = hl_content.split('*').collect();
-        if parts.len() > 3 && parts[3] == "22" {
-            return true;
-        }
-    }
-    false
+// Helper function to check if an HL level (its HL segment up to the next HL) starts a new 2000C loop
+pub fn is_next_loop_2000c(level: &[Segment]) -> bool {
+    level
+        .first()
+        .is_some_and(|hl| hl.id == "HL" && hl.element(2) == "22")
 }
 
 pub fn get_loop_2100b(contents: &str) -> EdiResult<(Loop2100B, String)> {
-    let mut contents = contents.to_string();
     let mut loop2100b = Loop2100B::default();
+    let mut leftover = String::new();
 
     // Process NM1 segment (required)
-    if contents.contains("NM1") {
-        info!("NM1 segment found for Loop 2100B");
-        let nm1_content = get_segment_contents("NM1", &contents);
-        if nm1_content.is_empty() {
-            return Err(EdiError::MissingSegment("NM1".to_string()));
-        }
-        loop2100b.nm1_segments = get_nm1(nm1_content);
-        info!("NM1 segment parsed for Loop 2100B");
-        contents = content_trim("NM1", &contents);
-    } else {
+    let segments = tokenize(contents);
+    let Some((nm1, rest)) = segments.split_first().filter(|(nm1, _)| nm1.id == "NM1") else {
         return Err(EdiError::MissingSegment("NM1".to_string()));
-    }
-
-    // Process PER segments (situational, can be multiple)
-    while contents.starts_with("PER") {
-        info!("PER segment found for Loop 2100B");
-        let per_content = get_segment_contents("PER", &contents);
-        if per_content.is_empty() {
-            break;
-        }
-        let per = get_per(per_content);
-        info!("PER segment parsed for Loop 2100B");
-        loop2100b.per_segments.push(per);
-        contents = content_trim("PER", &contents);
-    }
-
-    // Process REF segments (situational, can be multiple)
-    while contents.starts_with("REF") {
-        info!("REF segment found for Loop 2100B");
-        let ref_content = get_segment_contents("REF", &contents);
-        if ref_content.is_empty() {
-            break;
-        }
-        let ref_segment = get_ref(ref_content);
-        info!("REF segment parsed for Loop 2100B");
-        loop2100b.ref_segments.push(ref_segment);
-        contents = content_trim("REF", &contents);
-    }
-
-    // Process AAA segments (situational, can be multiple)
-    while contents.starts_with("AAA") {
-        info!("AAA segment found for Loop 2100B");
-        let aaa_content = get_segment_contents("AAA", &contents);
-        if aaa_content.is_empty() {
-            break;
+    };
+    info!("NM1 segment found for Loop 2100B");
+    loop2100b.nm1_segments = get_nm1(nm1.elements.join("*"));
+    info!("NM1 segment parsed for Loop 2100B");
+
+    for segment in rest {
+        match segment.id {
+            // Process PER segments (situational, can be multiple)
+            "PER" => {
+                info!("PER segment found for Loop 2100B");
+                let per = get_per(segment.elements.join("*"));
+                info!("PER segment parsed for Loop 2100B");
+                loop2100b.per_segments.push(per);
+            }
+            // Process REF segments (situational, can be multiple)
+            "REF" => {
+                info!("REF segment found for Loop 2100B");
+                let ref_segment = get_ref(segment.elements.join("*"));
+                info!("REF segment parsed for Loop 2100B");
+                loop2100b.ref_segments.push(ref_segment);
+            }
+            // Process AAA segments (situational, can be multiple)
+            "AAA" => {
+                info!("AAA segment found for Loop 2100B");
+                let aaa = get_aaa(segment.elements.join("*"));
+                info!("AAA segment parsed for Loop 2100B");
+                loop2100b.aaa_segments.push(aaa);
+            }
+            _ => leftover.push_str(&segment.to_edi()),
         }
-        let aaa = get_aaa(aaa_content);
-        info!("AAA segment parsed for Loop 2100B");
-        loop2100b.aaa_segments.push(aaa);
-        contents = content_trim("AAA", &contents);
     }
 
     info!("Loop 2100B parsed");
-    Ok((loop2100b, contents))
+    Ok((loop2100b, leftover))
 }
 
 pub fn write_loop_2000b(loop2000b: &Loop2000B) -> String {
@@ -277,9 +200,9 @@ mod tests {
     #[test]
     fn test_is_next_loop_2000c() {
         let contents = "HL*3*2*22*0~NM1*IL*1*DOE*JOHN****MI*12345678901~".to_string();
-        assert!(is_next_loop_2000c(&contents));
+        assert!(is_next_loop_2000c(&tokenize(&contents)));
 
         let contents = "HL*2*1*21*1~NM1*1P*2*ACME MEDICAL CENTER*****XX*1234567890~".to_string();
-        assert!(!is_next_loop_2000c(&contents));
+        assert!(!is_next_loop_2000c(&tokenize(&contents)));
     }
 }
@@ -2,7 +2,7 @@ use log::info;
 use serde::{Deserialize, Serialize};
 
 use crate::error::{EdiError, EdiResult};
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{join_segments, split_loops, tokenize, Segment};
 use crate::segments::aaa::*;
 use crate::segments::dmg::*;
 use crate::segments::dtp::*;
@@ -45,191 +45,125 @@ pub struct Loop2100C {
 }
 
 pub fn get_loop_2000c(contents: &str) -> EdiResult<(Loop2000C, String)> {
-    let mut contents = contents.to_string();
     let mut loop2000c = Loop2000C::default();
+    let mut leftover = String::new();
 
-    // Process HL segment (required)
-    if contents.contains("HL*") {
-        info!("HL segment found");
-        let hl_content = get_segment_contents("HL", &contents);
-        if hl_content.is_empty() {
-            return Err(EdiError::MissingSegment("HL".to_string()));
-        }
-        loop2000c.hl_segments = get_hl(hl_content);
-
-        // Verify this is a Subscriber level HL segment (level code = 22)
-        if loop2000c.hl_segments.hl03_hierarchical_level_code != "22" {
-            return Err(EdiError::ValidationError(format!(
-                "Expected HL03 code '22' for Subscriber level, got '{}'",
-                loop2000c.hl_segments.hl03_hierarchical_level_code
-            )));
-        }
-
-        info!("HL segment parsed");
-        contents = content_trim("HL", &contents);
-    } else {
+    // `contents` is the Subscriber level only: its HL segment up to the next HL.
+    // Dependent levels (Loop 2000D) are attached by the caller.
+    let segments = tokenize(contents);
+    let Some((hl, rest)) = segments.split_first().filter(|(hl, _)| hl.id == "HL") else {
         return Err(EdiError::MissingSegment("HL".to_string()));
-    }
+    };
 
-    // Process TRN segment (situational)
-    if contents.contains("TRN") {
-        info!("TRN segment found");
-        let trn_content = get_segment_contents("TRN", &contents);
-        if !trn_content.is_empty() {
-            loop2000c.trn_segments = Some(get_trn(trn_content));
-            info!("TRN segment parsed");
-            contents = content_trim("TRN", &contents);
-        }
-    }
-
-    // Process NM1 segment (required)
-    if contents.contains("NM1") {
-        info!("NM1 segment found");
-        let nm1_content = get_segment_contents("NM1", &contents);
-        if nm1_content.is_empty() {
-            return Err(EdiError::MissingSegment("NM1".to_string()));
-        }
-        loop2000c.nm1_segments = get_nm1(nm1_content);
-        info!("NM1 segment parsed");
-        contents = content_trim("NM1", &contents);
-    } else {
+    // Process HL segment (required)
+    info!("HL segment found");
+    loop2000c.hl_segments = get_hl(hl.elements.join("*"));
+
+    // Verify this is a Subscriber level HL segment (level code = 22)
+    if loop2000c.hl_segments.hl03_hierarchical_level_code != "22" {
+        return Err(EdiError::ValidationError(format!(
+            "Expected HL03 code '22' for Subscriber level, got '{}'",
+            loop2000c.hl_segments.hl03_hierarchical_level_code
+        )));
+    }
+    info!("HL segment parsed");
+
+    // The first NM1 names the subscriber; each further NM1 before the
+    // first EB starts a Loop 2100C
+    let (header, benefits) = split_loops(rest, &["EB"]);
+    let (leading, names) = split_loops(header, &["NM1"]);
+    let Some((name, names)) = names.split_first() else {
         return Err(EdiError::MissingSegment("NM1".to_string()));
-    }
-
-    // Process REF segments (situational, can be multiple)
-    while contents.starts_with("REF") {
-        info!("REF segment found");
-        let ref_content = get_segment_contents("REF", &contents);
-        if ref_content.is_empty() {
-            break;
-        }
-        let ref_segment = get_ref(ref_content);
-        info!("REF segment parsed");
-        loop2000c.ref_segments.push(ref_segment);
-        contents = content_trim("REF", &contents);
-    }
-
-    // Process N3 segment (situational)
-    if contents.contains("N3") {
-        info!("N3 segment found");
-        let n3_content = get_segment_contents("N3", &contents);
-        if !n3_content.is_empty() {
-            loop2000c.n3_segments = Some(get_n3(n3_content));
-            info!("N3 segment parsed");
-            contents = content_trim("N3", &contents);
-        }
-    }
-
-    // Process N4 segment (situational)
-    if contents.contains("N4") {
-        info!("N4 segment found");
-        let n4_content = get_segment_contents("N4", &contents);
-        if !n4_content.is_empty() {
-            loop2000c.n4_segments = Some(get_n4(n4_content));
-            info!("N4 segment parsed");
-            contents = content_trim("N4", &contents);
-        }
-    }
-
-    // Process AAA segments (situational, can be multiple)
-    while contents.starts_with("AAA") {
-        info!("AAA segment found");
-        let aaa_content = get_segment_contents("AAA", &contents);
-        if aaa_content.is_empty() {
-            break;
-        }
-        let aaa = get_aaa(aaa_content);
-        info!("AAA segment parsed");
-        loop2000c.aaa_segments.push(aaa);
-        contents = content_trim("AAA", &contents);
-    }
-
-    // Process DMG segment (situational)
-    if contents.contains("DMG") {
-        info!("DMG segment found");
-        let dmg_content = get_segment_contents("DMG", &contents);
-        if !dmg_content.is_empty() {
-            loop2000c.dmg_segments = Some(get_dmg(dmg_content));
-            info!("DMG segment parsed");
-            contents = content_trim("DMG", &contents);
-        }
-    }
-
-    // Process INS segment (situational)
-    if contents.contains("INS") {
-        info!("INS segment found");
-        let ins_content = get_segment_contents("INS", &contents);
-        if !ins_content.is_empty() {
-            loop2000c.ins_segments = Some(get_ins(ins_content));
-            info!("INS segment parsed");
-            contents = content_trim("INS", &contents);
+    };
+
+    for segment in leading {
+        match segment.id {
+            // Process TRN segment (situational)
+            "TRN" if loop2000c.trn_segments.is_none() => {
+                info!("TRN segment found");
+                loop2000c.trn_segments = Some(get_trn(segment.elements.join("*")));
+                info!("TRN segment parsed");
+            }
+            _ => leftover.push_str(&segment.to_edi()),
         }
     }
 
-    // Process DTP segments (situational, can be multiple)
-    // Only process DTP segments that are specific to Loop2000C
-    // This helps prevent duplicate DTP segments in the output
-    while contents.starts_with("DTP") {
-        info!("DTP segment found for Loop 2000C");
-        let dtp_content = get_segment_contents("DTP", &contents);
-        if dtp_content.is_empty() {
-            break;
-        }
-
-        // Parse the DTP segment
-        let dtp = get_dtp(dtp_content);
-
-        // Only add DTP segments with specific qualifiers to this loop
-        // Avoid adding DTP segments that belong to Loop2110C
-        if dtp.dtp01_date_time_qualifier != "291" && dtp.dtp01_date_time_qualifier != "348" {
-            info!("DTP segment parsed and added to Loop2000C");
-            loop2000c.dtp_segments.push(dtp);
-        } else {
-            // Skip this DTP segment as it will be handled by Loop2110C
-            info!("DTP segment skipped (will be handled by Loop2110C)");
+    // Process NM1 segment (required)
+    info!("NM1 segment found");
+    loop2000c.nm1_segments = get_nm1(name[0].elements.join("*"));
+    info!("NM1 segment parsed");
+
+    for segment in &name[1..] {
+        match segment.id {
+            // Process REF segments (situational, can be multiple)
+            "REF" => {
+                info!("REF segment found");
+                let ref_segment = get_ref(segment.elements.join("*"));
+                info!("REF segment parsed");
+                loop2000c.ref_segments.push(ref_segment);
+            }
+            // Process N3 segment (situational)
+            "N3" if loop2000c.n3_segments.is_none() => {
+                info!("N3 segment found");
+                loop2000c.n3_segments = Some(get_n3(segment.elements.join("*")));
+                info!("N3 segment parsed");
+            }
+            // Process N4 segment (situational)
+            "N4" if loop2000c.n4_segments.is_none() => {
+                info!("N4 segment found");
+                loop2000c.n4_segments = Some(get_n4(segment.elements.join("*")));
+                info!("N4 segment parsed");
+            }
+            // Process AAA segments (situational, can be multiple)
+            "AAA" => {
+                info!("AAA segment found");
+                let aaa = get_aaa(segment.elements.join("*"));
+                info!("AAA segment parsed");
+                loop2000c.aaa_segments.push(aaa);
+            }
+            // Process DMG segment (situational)
+            "DMG" if loop2000c.dmg_segments.is_none() => {
+                info!("DMG segment found");
+                loop2000c.dmg_segments = Some(get_dmg(segment.elements.join("*")));
+                info!("DMG segment parsed");
+            }
+            // Process INS segment (situational)
+            "INS" if loop2000c.ins_segments.is_none() => {
+                info!("INS segment found");
+                loop2000c.ins_segments = Some(get_ins(segment.elements.join("*")));
+                info!("INS segment parsed");
+            }
+            // Process DTP segments (situational, can be multiple)
+            "DTP" => {
+                info!("DTP segment found");
+                let dtp = get_dtp(segment.elements.join("*"));
+                info!("DTP segment parsed");
+                loop2000c.dtp_segments.push(dtp);
+            }
+            _ => leftover.push_str(&segment.to_edi()),
         }
-
-        contents = content_trim("DTP", &contents);
     }
 
     // Process Loop 2100C segments (can be multiple)
-    while contents.contains("NM1")
-        && !is_next_loop_2110c(&contents)
-        && !is_next_loop_2000d(&contents)
-    {
-        match get_loop_2100c(&contents) {
-            Ok((loop2100c, new_contents)) => {
-                loop2000c.loop2100c.push(loop2100c);
-                contents = new_contents;
-            }
-            Err(_) => break,
-        }
+    for name in names {
+        let (loop2100c, remaining) = get_loop_2100c(&join_segments(name))?;
+        loop2000c.loop2100c.push(loop2100c);
+        leftover.push_str(&remaining);
     }
 
     // Process Loop 2110C segments (can be multiple)
-    while contents.contains("EB") {
-        match get_loop_2110c(&contents) {
-            Ok((loop2110c, new_contents)) => {
+    for benefit in benefits {
+        match get_loop_2110c(&join_segments(benefit)) {
+            Ok((loop2110c, remaining)) => {
                 loop2000c.loop2110c.push(loop2110c);
-                contents = new_contents;
-            }
-            Err(_) => break,
-        }
-    }
-
-    // Process Loop 2000D segments (can be multiple)
-    while contents.contains("HL*") && is_next_loop_2000d(&contents) {
-        match get_loop_2000d(&contents) {
-            Ok((loop2000d, new_contents)) => {
-                loop2000c.loop2000d.push(loop2000d);
-                contents = new_contents;
+                leftover.push_str(&remaining);
             }
-            Err(_) => break,
+            Err(_) => leftover.push_str(&join_segments(benefit)),
         }
     }
 
     info!("Loop 2000C parsed");
-    Ok((loop2000c, contents))
+    Ok((loop2000c, leftover))
 }
 
 pub fn write_loop_2000c(loop2000c: &Loop2000C) -> String {
@@ -261,16 +195,16 @@ pub fn write_loop_2000c(loop2000c: &Loop2000C) -> String {
         contents.push_str(&write_n4(n4.clone()));
     }
 
-    // Write DMG segment if present
-    if let Some(dmg) = &loop2000c.dmg_segments {
-        contents.push_str(&write_dmg(dmg.clone()));
-    }
-
     // Write AAA segments
     for aaa in &loop2000c.aaa_segments {
         contents.push_str(&write_aaa(aaa.clone()));
     }
 
+    // Write DMG segment if present
+    if let Some(dmg) = &loop2000c.dmg_segments {
+        contents.push_str(&write_dmg(dmg.clone()));
+    }
+
     // Write INS segment if present
     if let Some(ins) = &loop2000c.ins_segments {
         contents.push_str(&write_ins(ins.clone()));
@@ -286,146 +220,91 @@ pub fn write_loop_2000c(loop2000c: &Loop2000C) -> String {
         contents.push_str(&write_loop_2100c(loop2100c));
     }
 
+    // Write all Loop 2110C segments - the subscriber's benefits precede its dependents
+    for loop2110c in &loop2000c.loop2110c {
+        contents.push_str(&write_loop_2110c(loop2110c));
+    }
+
     // Write all Loop 2000D segments
     for loop2000d in &loop2000c.loop2000d {
         contents.push_str(&write_loop_2000d(loop2000d));
     }
 
-    // Write all Loop 2110C segments - in original file, EB segments come after Loop 2000D
-    for loop2110c in &loop2000c.loop2110c {
-        contents.push_str(&write_loop_2110c(loop2110c));
-    }
-
     contents
 }
 
 pub fn get_loop_2100c(contents: &str) -> EdiResult<(Loop2100C, String)> {
-    let mut contents = contents.to_string();
     let mut loop2100c = Loop2100C::default();
+    let mut leftover = String::new();
 
     // Process NM1 segment (required)
-    if contents.contains("NM1") {
-        info!("NM1 segment found for Loop 2100C");
-        let nm1_content = get_segment_contents("NM1", &contents);
-        if nm1_content.is_empty() {
-            return Err(EdiError::MissingSegment("NM1".to_string()));
-        }
-        loop2100c.nm1_segments = get_nm1(nm1_content);
-        info!("NM1 segment parsed for Loop 2100C");
-        contents = content_trim("NM1", &contents);
-    } else {
+    let segments = tokenize(contents);
+    let Some((nm1, rest)) = segments.split_first().filter(|(nm1, _)| nm1.id == "NM1") else {
         return Err(EdiError::MissingSegment("NM1".to_string()));
-    }
-
-    // Process REF segments (situational, can be multiple)
-    while contents.starts_with("REF") {
-        info!("REF segment found for Loop 2100C");
-        let ref_content = get_segment_contents("REF", &contents);
-        if ref_content.is_empty() {
-            break;
-        }
-        let ref_segment = get_ref(ref_content);
-        info!("REF segment parsed for Loop 2100C");
-        loop2100c.ref_segments.push(ref_segment);
-        contents = content_trim("REF", &contents);
-    }
-
-    // Process N3 segment (situational)
-    if contents.contains("N3") {
-        info!("N3 segment found for Loop 2100C");
-        let n3_content = get_segment_contents("N3", &contents);
-        if !n3_content.is_empty() {
-            loop2100c.n3_segments = Some(get_n3(n3_content));
-            info!("N3 segment parsed for Loop 2100C");
-            contents = content_trim("N3", &contents);
-        }
-    }
-
-    // Process N4 segment (situational)
-    if contents.contains("N4") {
-        info!("N4 segment found for Loop 2100C");
-        let n4_content = get_segment_contents("N4", &contents);
-        if !n4_content.is_empty() {
-            loop2100c.n4_segments = Some(get_n4(n4_content));
-            info!("N4 segment parsed for Loop 2100C");
-            contents = content_trim("N4", &contents);
-        }
-    }
-
-    // Process AAA segments (situational, can be multiple)
-    while contents.starts_with("AAA") {
-        info!("AAA segment found for Loop 2100C");
-        let aaa_content = get_segment_contents("AAA", &contents);
-        if aaa_content.is_empty() {
-            break;
-        }
-        let aaa = get_aaa(aaa_content);
-        info!("AAA segment parsed for Loop 2100C");
-        loop2100c.aaa_segments.push(aaa);
-        contents = content_trim("AAA", &contents);
-    }
-
-    // Process PRV segment (situational)
-    if contents.contains("PRV") {
-        info!("PRV segment found for Loop 2100C");
-        let prv_content = get_segment_contents("PRV", &contents);
-        if !prv_content.is_empty() {
-            loop2100c.prv_segments = Some(get_prv(prv_content));
-            info!("PRV segment parsed for Loop 2100C");
-            contents = content_trim("PRV", &contents);
-        }
-    }
-
-    // Process DMG segment (situational)
-    if contents.contains("DMG") {
-        info!("DMG segment found for Loop 2100C");
-        let dmg_content = get_segment_contents("DMG", &contents);
-        if !dmg_content.is_empty() {
-            loop2100c.dmg_segments = Some(get_dmg(dmg_content));
-            info!("DMG segment parsed for Loop 2100C");
-            contents = content_trim("DMG", &contents);
-        }
-    }
-
-    // Process INS segment (situational)
-    if contents.contains("INS") {
-        info!("INS segment found for Loop 2100C");
-        let ins_content = get_segment_contents("INS", &contents);
-        if !ins_content.is_empty() {
-            loop2100c.ins_segments = Some(get_ins(ins_content));
-            info!("INS segment parsed for Loop 2100C");
-            contents = content_trim("INS", &contents);
-        }
-    }
-
-    // Process DTP segments (situational, can be multiple)
-    // Only process DTP segments that are specific to Loop 2100C
-    // This helps prevent duplicate DTP segments in the output
-    while contents.starts_with("DTP") {
-        info!("DTP segment found for Loop 2100C");
-        let dtp_content = get_segment_contents("DTP", &contents);
-        if dtp_content.is_empty() {
-            break;
-        }
-
-        // Parse the DTP segment
-        let dtp = get_dtp(dtp_content);
-
-        // Only add DTP segments with specific qualifiers to this loop
-        // Avoid adding DTP segments that belong to Loop2110C
-        if dtp.dtp01_date_time_qualifier != "291" && dtp.dtp01_date_time_qualifier != "348" {
-            info!("DTP segment parsed and added to Loop2100C");
-            loop2100c.dtp_segments.push(dtp);
-        } else {
-            // Skip this DTP segment as it will be handled by Loop2110C
-            info!("DTP segment skipped (will be handled by Loop2110C)");
+    };
+    info!("NM1 segment found for Loop 2100C");
+    loop2100c.nm1_segments = get_nm1(nm1.elements.join("*"));
+    info!("NM1 segment parsed for Loop 2100C");
+
+    for segment in rest {
+        match segment.id {
+            // Process REF segments (situational, can be multiple)
+            "REF" => {
+                info!("REF segment found for Loop 2100C");
+                let ref_segment = get_ref(segment.elements.join("*"));
+                info!("REF segment parsed for Loop 2100C");
+                loop2100c.ref_segments.push(ref_segment);
+            }
+            // Process N3 segment (situational)
+            "N3" if loop2100c.n3_segments.is_none() => {
+                info!("N3 segment found for Loop 2100C");
+                loop2100c.n3_segments = Some(get_n3(segment.elements.join("*")));
+                info!("N3 segment parsed for Loop 2100C");
+            }
+            // Process N4 segment (situational)
+            "N4" if loop2100c.n4_segments.is_none() => {
+                info!("N4 segment found for Loop 2100C");
+                loop2100c.n4_segments = Some(get_n4(segment.elements.join("*")));
+                info!("N4 segment parsed for Loop 2100C");
+            }
+            // Process AAA segments (situational, can be multiple)
+            "AAA" => {
+                info!("AAA segment found for Loop 2100C");
+                let aaa = get_aaa(segment.elements.join("*"));
+                info!("AAA segment parsed for Loop 2100C");
+                loop2100c.aaa_segments.push(aaa);
+            }
+            // Process PRV segment (situational)
+            "PRV" if loop2100c.prv_segments.is_none() => {
+                info!("PRV segment found for Loop 2100C");
+                loop2100c.prv_segments = Some(get_prv(segment.elements.join("*")));
+                info!("PRV segment parsed for Loop 2100C");
+            }
+            // Process DMG segment (situational)
+            "DMG" if loop2100c.dmg_segments.is_none() => {
+                info!("DMG segment found for Loop 2100C");
+                loop2100c.dmg_segments = Some(get_dmg(segment.elements.join("*")));
+                info!("DMG segment parsed for Loop 2100C");
+            }
+            // Process INS segment (situational)
+            "INS" if loop2100c.ins_segments.is_none() => {
+                info!("INS segment found for Loop 2100C");
+                loop2100c.ins_segments = Some(get_ins(segment.elements.join("*")));
+                info!("INS segment parsed for Loop 2100C");
+            }
+            // Process DTP segments (situational, can be multiple)
+            "DTP" => {
+                info!("DTP segment found for Loop 2100C");
+                let dtp = get_dtp(segment.elements.join("*"));
+                info!("DTP segment parsed for Loop 2100C");
+                loop2100c.dtp_segments.push(dtp);
+            }
+            _ => leftover.push_str(&segment.to_edi()),
         }
-
-        contents = content_trim("DTP", &contents);
     }
 
     info!("Loop 2100C parsed");
-    Ok((loop2100c, contents))
+    Ok((loop2100c, leftover))
 }
 
 pub fn write_loop_2100c(loop2100c: &Loop2100C) -> String {
@@ -496,20 +375,11 @@ pub fn write_prv(prv: PRV) -> String {
     )
 }
 
-// Helper function to check if the next segment starts a new 2110C loop
-fn is_next_loop_2110c(contents: &str) -> bool {
-    contents.contains("EB*")
-}
-
-// Helper function to check if the next segment starts a new 2000D loop
-fn is_next_loop_2000d(contents: &str) -> bool {
-    if let Some(hl_content) = get_full_segment_contents("HL", contents) {
-        let parts: Vec<&str> = hl_content.split('*').collect();
-        if parts.len() > 3 && parts[3] == "23" {
-            return true;
-        }
-    }
-    false
+// Helper function to check if an HL level (its HL segment up to the next HL) starts a new 2000D loop
+pub fn is_next_loop_2000d(level: &[Segment]) -> bool {
+    level
+        .first()
+        .is_some_and(|hl| hl.id == "HL" && hl.element(2) == "23")
 }
 
 // Import Loop2110C and Loop2000D to avoid circular dependency
@@ -523,9 +393,9 @@ mod tests {
     #[test]
     fn test_is_next_loop_2000d() {
         let contents = "HL*4*3*23*0~NM1*IL*1*DOE*JANE****MI*98765432101~".to_string();
-        assert!(is_next_loop_2000d(&contents));
+        assert!(is_next_loop_2000d(&tokenize(&contents)));
 
         let contents = "HL*3*2*22*1~NM1*IL*1*DOE*JOHN****MI*12345678901~".to_string();
-        assert!(!is_next_loop_2000d(&contents));
+        assert!(!is_next_loop_2000d(&tokenize(&contents)));
     }
 }
@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::edi271::loop2000c::*;
 use crate::error::{EdiError, EdiResult};
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{join_segments, split_loops, tokenize};
 use crate::segments::aaa::*;
 use crate::segments::dmg::*;
 use crate::segments::dtp::*;
@@ -45,285 +45,199 @@ pub struct Loop2100D {
 }
 
 pub fn get_loop_2000d(contents: &str) -> EdiResult<(Loop2000D, String)> {
-    let mut contents = contents.to_string();
     let mut loop2000d = Loop2000D::default();
+    let mut leftover = String::new();
 
-    // Process HL segment (required)
-    if contents.contains("HL*") {
-        info!("HL segment found");
-        let hl_content = get_segment_contents("HL", &contents);
-        if hl_content.is_empty() {
-            return Err(EdiError::MissingSegment("HL".to_string()));
-        }
-        loop2000d.hl_segments = get_hl(hl_content);
-
-        // Verify this is a Dependent level HL segment (level code = 23)
-        if loop2000d.hl_segments.hl03_hierarchical_level_code != "23" {
-            return Err(EdiError::ValidationError(format!(
-                "Expected HL03 code '23' for Dependent level, got '{}'",
-                loop2000d.hl_segments.hl03_hierarchical_level_code
-            )));
-        }
-
-        info!("HL segment parsed");
-        contents = content_trim("HL", &contents);
-    } else {
+    // `contents` is the Dependent level only: its HL segment up to the next HL.
+    // Each EB after its name loops starts a Loop 2110D.
+    let segments = tokenize(contents);
+    let Some((hl, rest)) = segments.split_first().filter(|(hl, _)| hl.id == "HL") else {
         return Err(EdiError::MissingSegment("HL".to_string()));
-    }
-
-    // Process TRN segment (situational)
-    if contents.contains("TRN") {
-        info!("TRN segment found");
-        let trn_content = get_segment_contents("TRN", &contents);
-        if !trn_content.is_empty() {
-            loop2000d.trn_segments = Some(get_trn(trn_content));
-            info!("TRN segment parsed");
-            contents = content_trim("TRN", &contents);
-        }
-    }
+    };
 
-    // Process NM1 segment (required)
-    if contents.contains("NM1") {
-        info!("NM1 segment found");
-        let nm1_content = get_segment_contents("NM1", &contents);
-        if nm1_content.is_empty() {
-            return Err(EdiError::MissingSegment("NM1".to_string()));
-        }
-        loop2000d.nm1_segments = get_nm1(nm1_content);
-        info!("NM1 segment parsed");
-        contents = content_trim("NM1", &contents);
-    } else {
+    // Process HL segment (required)
+    info!("HL segment found");
+    loop2000d.hl_segments = get_hl(hl.elements.join("*"));
+
+    // Verify this is a Dependent level HL segment (level code = 23)
+    if loop2000d.hl_segments.hl03_hierarchical_level_code != "23" {
+        return Err(EdiError::ValidationError(format!(
+            "Expected HL03 code '23' for Dependent level, got '{}'",
+            loop2000d.hl_segments.hl03_hierarchical_level_code
+        )));
+    }
+    info!("HL segment parsed");
+
+    // The first NM1 names the dependent; each further NM1 before the
+    // first EB starts a Loop 2100D
+    let (header, benefits) = split_loops(rest, &["EB"]);
+    let (leading, names) = split_loops(header, &["NM1"]);
+    let Some((name, names)) = names.split_first() else {
         return Err(EdiError::MissingSegment("NM1".to_string()));
-    }
-
-    // Process REF segments (situational, can be multiple)
-    while contents.starts_with("REF") {
-        info!("REF segment found");
-        let ref_content = get_segment_contents("REF", &contents);
-        if ref_content.is_empty() {
-            break;
-        }
-        let ref_segment = get_ref(ref_content);
-        info!("REF segment parsed");
-        loop2000d.ref_segments.push(ref_segment);
-        contents = content_trim("REF", &contents);
-    }
-
-    // Process N3 segment (situational)
-    if contents.contains("N3") {
-        info!("N3 segment found");
-        let n3_content = get_segment_contents("N3", &contents);
-        if !n3_content.is_empty() {
-            loop2000d.n3_segments = Some(get_n3(n3_content));
-            info!("N3 segment parsed");
-            contents = content_trim("N3", &contents);
-        }
-    }
-
-    // Process N4 segment (situational)
-    if contents.contains("N4") {
-        info!("N4 segment found");
-        let n4_content = get_segment_contents("N4", &contents);
-        if !n4_content.is_empty() {
-            loop2000d.n4_segments = Some(get_n4(n4_content));
-            info!("N4 segment parsed");
-            contents = content_trim("N4", &contents);
-        }
-    }
-
-    // Process AAA segments (situational, can be multiple)
-    while contents.starts_with("AAA") {
-        info!("AAA segment found");
-        let aaa_content = get_segment_contents("AAA", &contents);
-        if aaa_content.is_empty() {
-            break;
-        }
-        let aaa = get_aaa(aaa_content);
-        info!("AAA segment parsed");
-        loop2000d.aaa_segments.push(aaa);
-        contents = content_trim("AAA", &contents);
-    }
-
-    // Process DMG segment (situational)
-    if contents.contains("DMG") {
-        info!("DMG segment found");
-        let dmg_content = get_segment_contents("DMG", &contents);
-        if !dmg_content.is_empty() {
-            loop2000d.dmg_segments = Some(get_dmg(dmg_content));
-            info!("DMG segment parsed");
-            contents = content_trim("DMG", &contents);
-        }
-    }
-
-    // Process INS segment (situational)
-    if contents.contains("INS") {
-        info!("INS segment found");
-        let ins_content = get_segment_contents("INS", &contents);
-        if !ins_content.is_empty() {
-            loop2000d.ins_segments = Some(get_ins(ins_content));
-            info!("INS segment parsed");
-            contents = content_trim("INS", &contents);
+    };
+
+    for segment in leading {
+        match segment.id {
+            // Process TRN segment (situational)
+            "TRN" if loop2000d.trn_segments.is_none() => {
+                info!("TRN segment found");
+                loop2000d.trn_segments = Some(get_trn(segment.elements.join("*")));
+                info!("TRN segment parsed");
+            }
+            _ => leftover.push_str(&segment.to_edi()),
         }
     }
 
-    // Process DTP segments (situational, can be multiple)
-    while contents.starts_with("DTP") {
-        info!("DTP segment found");
-        let dtp_content = get_segment_contents("DTP", &contents);
-        if dtp_content.is_empty() {
-            break;
+    // Process NM1 segment (required)
+    info!("NM1 segment found");
+    loop2000d.nm1_segments = get_nm1(name[0].elements.join("*"));
+    info!("NM1 segment parsed");
+
+    for segment in &name[1..] {
+        match segment.id {
+            // Process REF segments (situational, can be multiple)
+            "REF" => {
+                info!("REF segment found");
+                let ref_segment = get_ref(segment.elements.join("*"));
+                info!("REF segment parsed");
+                loop2000d.ref_segments.push(ref_segment);
+            }
+            // Process N3 segment (situational)
+            "N3" if loop2000d.n3_segments.is_none() => {
+                info!("N3 segment found");
+                loop2000d.n3_segments = Some(get_n3(segment.elements.join("*")));
+                info!("N3 segment parsed");
+            }
+            // Process N4 segment (situational)
+            "N4" if loop2000d.n4_segments.is_none() => {
+                info!("N4 segment found");
+                loop2000d.n4_segments = Some(get_n4(segment.elements.join("*")));
+                info!("N4 segment parsed");
+            }
+            // Process AAA segments (situational, can be multiple)
+            "AAA" => {
+                info!("AAA segment found");
+                let aaa = get_aaa(segment.elements.join("*"));
+                info!("AAA segment parsed");
+                loop2000d.aaa_segments.push(aaa);
+            }
+            // Process DMG segment (situational)
+            "DMG" if loop2000d.dmg_segments.is_none() => {
+                info!("DMG segment found");
+                loop2000d.dmg_segments = Some(get_dmg(segment.elements.join("*")));
+                info!("DMG segment parsed");
+            }
+            // Process INS segment (situational)
+            "INS" if loop2000d.ins_segments.is_none() => {
+                info!("INS segment found");
+                loop2000d.ins_segments = Some(get_ins(segment.elements.join("*")));
+                info!("INS segment parsed");
+            }
+            // Process DTP segments (situational, can be multiple)
+            "DTP" => {
+                info!("DTP segment found");
+                let dtp = get_dtp(segment.elements.join("*"));
+                info!("DTP segment parsed");
+                loop2000d.dtp_segments.push(dtp);
+            }
+            _ => leftover.push_str(&segment.to_edi()),
         }
-        let dtp = get_dtp(dtp_content);
-        info!("DTP segment parsed");
-        loop2000d.dtp_segments.push(dtp);
-        contents = content_trim("DTP", &contents);
     }
 
     // Process Loop 2100D segments (can be multiple)
-    while contents.contains("NM1") && !is_next_loop_2110d(&contents) {
-        match get_loop_2100d(&contents) {
-            Ok((loop2100d, new_contents)) => {
-                loop2000d.loop2100d.push(loop2100d);
-                contents = new_contents;
-            }
-            Err(_) => break,
-        }
+    for name in names {
+        let (loop2100d, remaining) = get_loop_2100d(&join_segments(name))?;
+        loop2000d.loop2100d.push(loop2100d);
+        leftover.push_str(&remaining);
     }
 
     // Process Loop 2110D segments (can be multiple)
-    while contents.contains("EB") {
-        match get_loop_2110d(&contents) {
-            Ok((loop2110d, new_contents)) => {
+    for benefit in benefits {
+        match get_loop_2110d(&join_segments(benefit)) {
+            Ok((loop2110d, remaining)) => {
                 loop2000d.loop2110d.push(loop2110d);
-                contents = new_contents;
+                leftover.push_str(&remaining);
             }
-            Err(_) => break,
+            Err(_) => leftover.push_str(&join_segments(benefit)),
         }
     }
 
     info!("Loop 2000D parsed");
-    Ok((loop2000d, contents))
-}
-
-// Helper function to check if the next segment starts a new 2110D loop
-fn is_next_loop_2110d(contents: &str) -> bool {
-    contents.contains("EB*")
+    Ok((loop2000d, leftover))
 }
 
 pub fn get_loop_2100d(contents: &str) -> EdiResult<(Loop2100D, String)> {
-    let mut contents = contents.to_string();
     let mut loop2100d = Loop2100D::default();
+    let mut leftover = String::new();
 
     // Process NM1 segment (required)
-    if contents.contains("NM1") {
-        info!("NM1 segment found for Loop 2100D");
-        let nm1_content = get_segment_contents("NM1", &contents);
-        if nm1_content.is_empty() {
-            return Err(EdiError::MissingSegment("NM1".to_string()));
-        }
-        loop2100d.nm1_segments = get_nm1(nm1_content);
-        info!("NM1 segment parsed for Loop 2100D");
-        contents = content_trim("NM1", &contents);
-    } else {
+    let segments = tokenize(contents);
+    let Some((nm1, rest)) = segments.split_first().filter(|(nm1, _)| nm1.id == "NM1") else {
         return Err(EdiError::MissingSegment("NM1".to_string()));
-    }
-
-    // Process REF segments (situational, can be multiple)
-    while contents.starts_with("REF") {
-        info!("REF segment found for Loop 2100D");
-        let ref_content = get_segment_contents("REF", &contents);
-        if ref_content.is_empty() {
-            break;
-        }
-        let ref_segment = get_ref(ref_content);
-        info!("REF segment parsed for Loop 2100D");
-        loop2100d.ref_segments.push(ref_segment);
-        contents = content_trim("REF", &contents);
-    }
-
-    // Process N3 segment (situational)
-    if contents.contains("N3") {
-        info!("N3 segment found for Loop 2100D");
-        let n3_content = get_segment_contents("N3", &contents);
-        if !n3_content.is_empty() {
-            loop2100d.n3_segments = Some(get_n3(n3_content));
-            info!("N3 segment parsed for Loop 2100D");
-            contents = content_trim("N3", &contents);
-        }
-    }
-
-    // Process N4 segment (situational)
-    if contents.contains("N4") {
-        info!("N4 segment found for Loop 2100D");
-        let n4_content = get_segment_contents("N4", &contents);
-        if !n4_content.is_empty() {
-            loop2100d.n4_segments = Some(get_n4(n4_content));
-            info!("N4 segment parsed for Loop 2100D");
-            contents = content_trim("N4", &contents);
-        }
-    }
-
-    // Process AAA segments (situational, can be multiple)
-    while contents.starts_with("AAA") {
-        info!("AAA segment found for Loop 2100D");
-        let aaa_content = get_segment_contents("AAA", &contents);
-        if aaa_content.is_empty() {
-            break;
-        }
-        let aaa = get_aaa(aaa_content);
-        info!("AAA segment parsed for Loop 2100D");
-        loop2100d.aaa_segments.push(aaa);
-        contents = content_trim("AAA", &contents);
-    }
-
-    // Process PRV segment (situational)
-    if contents.contains("PRV") {
-        info!("PRV segment found for Loop 2100D");
-        let prv_content = get_segment_contents("PRV", &contents);
-        if !prv_content.is_empty() {
-            loop2100d.prv_segments = Some(get_prv(prv_content));
-            info!("PRV segment parsed for Loop 2100D");
-            contents = content_trim("PRV", &contents);
-        }
-    }
-
-    // Process DMG segment (situational)
-    if contents.contains("DMG") {
-        info!("DMG segment found for Loop 2100D");
-        let dmg_content = get_segment_contents("DMG", &contents);
-        if !dmg_content.is_empty() {
-            loop2100d.dmg_segments = Some(get_dmg(dmg_content));
-            info!("DMG segment parsed for Loop 2100D");
-            contents = content_trim("DMG", &contents);
-        }
-    }
-
-    // Process INS segment (situational)
-    if contents.contains("INS") {
-        info!("INS segment found for Loop 2100D");
-        let ins_content = get_segment_contents("INS", &contents);
-        if !ins_content.is_empty() {
-            loop2100d.ins_segments = Some(get_ins(ins_content));
-            info!("INS segment parsed for Loop 2100D");
-            contents = content_trim("INS", &contents);
-        }
-    }
-
-    // Process DTP segments (situational, can be multiple)
-    while contents.starts_with("DTP") {
-        info!("DTP segment found for Loop 2100D");
-        let dtp_content = get_segment_contents("DTP", &contents);
-        if dtp_content.is_empty() {
-            break;
+    };
+    info!("NM1 segment found for Loop 2100D");
+    loop2100d.nm1_segments = get_nm1(nm1.elements.join("*"));
+    info!("NM1 segment parsed for Loop 2100D");
+
+    for segment in rest {
+        match segment.id {
+            // Process REF segments (situational, can be multiple)
+            "REF" => {
+                info!("REF segment found for Loop 2100D");
+                let ref_segment = get_ref(segment.elements.join("*"));
+                info!("REF segment parsed for Loop 2100D");
+                loop2100d.ref_segments.push(ref_segment);
+            }
+            // Process N3 segment (situational)
+            "N3" if loop2100d.n3_segments.is_none() => {
+                info!("N3 segment found for Loop 2100D");
+                loop2100d.n3_segments = Some(get_n3(segment.elements.join("*")));
+                info!("N3 segment parsed for Loop 2100D");
+            }
+            // Process N4 segment (situational)
+            "N4" if loop2100d.n4_segments.is_none() => {
+                info!("N4 segment found for Loop 2100D");
+                loop2100d.n4_segments = Some(get_n4(segment.elements.join("*")));
+                info!("N4 segment parsed for Loop 2100D");
+            }
+            // Process AAA segments (situational, can be multiple)
+            "AAA" => {
+                info!("AAA segment found for Loop 2100D");
+                let aaa = get_aaa(segment.elements.join("*"));
+                info!("AAA segment parsed for Loop 2100D");
+                loop2100d.aaa_segments.push(aaa);
+            }
+            // Process PRV segment (situational)
+            "PRV" if loop2100d.prv_segments.is_none() => {
+                info!("PRV segment found for Loop 2100D");
+                loop2100d.prv_segments = Some(get_prv(segment.elements.join("*")));
+                info!("PRV segment parsed for Loop 2100D");
+            }
+            // Process DMG segment (situational)
+            "DMG" if loop2100d.dmg_segments.is_none() => {
+                info!("DMG segment found for Loop 2100D");
+                loop2100d.dmg_segments = Some(get_dmg(segment.elements.join("*")));
+                info!("DMG segment parsed for Loop 2100D");
+            }
+            // Process INS segment (situational)
+            "INS" if loop2100d.ins_segments.is_none() => {
+                info!("INS segment found for Loop 2100D");
+                loop2100d.ins_segments = Some(get_ins(segment.elements.join("*")));
+                info!("INS segment parsed for Loop 2100D");
+            }
+            // Process DTP segments (situational, can be multiple)
+            "DTP" => {
+                info!("DTP segment found for Loop 2100D");
+                let dtp = get_dtp(segment.elements.join("*"));
+                info!("DTP segment parsed for Loop 2100D");
+                loop2100d.dtp_segments.push(dtp);
+            }
+            _ => leftover.push_str(&segment.to_edi()),
         }
-        let dtp = get_dtp(dtp_content);
-        info!("DTP segment parsed for Loop 2100D");
-        loop2100d.dtp_segments.push(dtp);
-        contents = content_trim("DTP", &contents);
     }
 
     info!("Loop 2100D parsed");
-    Ok((loop2100d, contents))
+    Ok((loop2100d, leftover))
 }
 
 pub fn write_loop_2000d(loop2000d: &Loop2000D) -> String {
@@ -340,6 +254,11 @@ pub fn write_loop_2000d(loop2000d: &Loop2000D) -> String {
     // Write NM1 segment
     contents.push_str(&write_nm1(loop2000d.nm1_segments.clone()));
 
+    // Write all REF segments
+    for ref_segment in &loop2000d.ref_segments {
+        contents.push_str(&write_ref(ref_segment.clone()));
+    }
+
     // Write N3 segment if present
     if let Some(n3) = &loop2000d.n3_segments {
         contents.push_str(&write_n3(n3.clone()));
@@ -350,12 +269,17 @@ pub fn write_loop_2000d(loop2000d: &Loop2000D) -> String {
         contents.push_str(&write_n4(n4.clone()));
     }
 
+    // Write all AAA segments
+    for aaa in &loop2000d.aaa_segments {
+        contents.push_str(&write_aaa(aaa.clone()));
+    }
+
     // Write DMG segment if present
     if let Some(dmg) = &loop2000d.dmg_segments {
         contents.push_str(&write_dmg(dmg.clone()));
     }
 
-    // Write INS segment if present - in original file, INS comes after DMG
+    // Write INS segment if present
     if let Some(ins) = &loop2000d.ins_segments {
         contents.push_str(&write_ins(ins.clone()));
     }
@@ -370,16 +294,6 @@ pub fn write_loop_2000d(loop2000d: &Loop2000D) -> String {
         contents.push_str(&write_loop_2100d(loop2100d));
     }
 
-    // Write all REF segments
-    for ref_segment in &loop2000d.ref_segments {
-        contents.push_str(&write_ref(ref_segment.clone()));
-    }
-
-    // Write all AAA segments
-    for aaa in &loop2000d.aaa_segments {
-        contents.push_str(&write_aaa(aaa.clone()));
-    }
-
     // Write all Loop 2110D segments
     for loop2110d in &loop2000d.loop2110d {
         contents.push_str(&write_loop_2110d(loop2110d));
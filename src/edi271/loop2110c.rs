@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::edi271::loop2115c::*;
 use crate::error::{EdiError, EdiResult};
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{find_segment, join_segments, split_loops, tokenize};
 use crate::segments::aaa::*;
 use crate::segments::dtp::*;
 use crate::segments::eb::*;
@@ -25,183 +25,107 @@ pub struct Loop2110C {
 }
 
 pub fn get_loop_2110c(contents: &str) -> EdiResult<(Loop2110C, String)> {
-    let mut contents = contents.to_string();
     let mut loop2110c = Loop2110C::default();
+    let mut leftover = String::new();
 
     // Process EB segment (required)
-    if contents.contains("EB") {
-        info!("EB segment found");
-        let eb_content = get_segment_contents("EB", &contents);
-        if eb_content.is_empty() {
-            return Err(EdiError::MissingSegment("EB".to_string()));
-        }
-        loop2110c.eb_segments = get_eb(eb_content);
-        info!("EB segment parsed");
-        contents = content_trim("EB", &contents);
-    } else {
+    let segments = tokenize(contents);
+    let Some((eb, rest)) = segments.split_first().filter(|(eb, _)| eb.id == "EB") else {
         return Err(EdiError::MissingSegment("EB".to_string()));
-    }
-
-    // Process HSD segments (situational, can be multiple)
-    while contents.starts_with("HSD") {
-        info!("HSD segment found");
-        let hsd_content = get_segment_contents("HSD", &contents);
-        if hsd_content.is_empty() {
-            break;
-        }
-        let hsd = get_hsd(hsd_content);
-        info!("HSD segment parsed");
-        loop2110c.hsd_segments.push(hsd);
-        contents = content_trim("HSD", &contents);
-    }
-
-    // Process REF segments (situational, can be multiple)
-    while contents.starts_with("REF") {
-        info!("REF segment found");
-        let ref_content = get_segment_contents("REF", &contents);
-        if ref_content.is_empty() {
-            break;
-        }
-        let ref_segment = get_ref(ref_content);
-        info!("REF segment parsed");
-        loop2110c.ref_segments.push(ref_segment);
-        contents = content_trim("REF", &contents);
-    }
-
-    // Process DTP segments (situational, can be multiple)
-    // Only process DTP segments that are specific to this loop
-    // This helps prevent duplicate DTP segments in the output
-    while contents.starts_with("DTP") {
-        info!("DTP segment found");
-        let dtp_content = get_segment_contents("DTP", &contents);
-        if dtp_content.is_empty() {
-            break;
-        }
-
-        // Parse the DTP segment
-        let dtp = get_dtp(dtp_content);
-
-        // Only add DTP segments with specific qualifiers to this loop
-        // This prevents duplicates from being added in process_remaining_segments
-        if dtp.dtp01_date_time_qualifier == "291" || dtp.dtp01_date_time_qualifier == "348" {
-            info!("DTP segment parsed and added to Loop2110C");
-            loop2110c.dtp_segments.push(dtp);
-        } else {
-            info!("DTP segment parsed but not added to Loop2110C (will be handled elsewhere)");
-        }
-
-        contents = content_trim("DTP", &contents);
-    }
-
-    // Process AAA segments (situational, can be multiple)
-    while contents.starts_with("AAA") {
-        info!("AAA segment found");
-        let aaa_content = get_segment_contents("AAA", &contents);
-        if aaa_content.is_empty() {
-            break;
-        }
-        let aaa = get_aaa(aaa_content);
-        info!("AAA segment parsed");
-        loop2110c.aaa_segments.push(aaa);
-        contents = content_trim("AAA", &contents);
-    }
-
-    // Process MSG segments (situational, can be multiple)
-    while contents.starts_with("MSG") {
-        info!("MSG segment found");
-        let msg_content = get_segment_contents("MSG", &contents);
-        if msg_content.is_empty() {
-            break;
+    };
+    info!("EB segment found");
+    loop2110c.eb_segments = get_eb(eb.elements.join("*"));
+    info!("EB segment parsed");
+
+    // An LS..LE pair wraps the benefit related entities (Loop 2115C)
+    let ls_pos = find_segment(rest, &["LS"], 0);
+    let le_pos = ls_pos.and_then(|ls_pos| find_segment(rest, &["LE"], ls_pos + 1));
+    let body_end = ls_pos.unwrap_or(rest.len());
+
+    for segment in &rest[..body_end] {
+        match segment.id {
+            // Process HSD segments (situational, can be multiple)
+            "HSD" => {
+                info!("HSD segment found");
+                let hsd = get_hsd(segment.elements.join("*"));
+                info!("HSD segment parsed");
+                loop2110c.hsd_segments.push(hsd);
+            }
+            // Process REF segments (situational, can be multiple)
+            "REF" => {
+                info!("REF segment found");
+                let ref_segment = get_ref(segment.elements.join("*"));
+                info!("REF segment parsed");
+                loop2110c.ref_segments.push(ref_segment);
+            }
+            // Process DTP segments (situational, can be multiple)
+            "DTP" => {
+                info!("DTP segment found");
+                let dtp = get_dtp(segment.elements.join("*"));
+                info!("DTP segment parsed");
+                loop2110c.dtp_segments.push(dtp);
+            }
+            // Process AAA segments (situational, can be multiple)
+            "AAA" => {
+                info!("AAA segment found");
+                let aaa = get_aaa(segment.elements.join("*"));
+                info!("AAA segment parsed");
+                loop2110c.aaa_segments.push(aaa);
+            }
+            // Process MSG segments (situational, can be multiple)
+            "MSG" => {
+                info!("MSG segment found");
+                // get_msg expects the segment ID as the first element
+                let msg = get_msg(segment.raw.to_string());
+                info!("MSG segment parsed");
+                loop2110c.msg_segments.push(msg);
+            }
+            _ => leftover.push_str(&segment.to_edi()),
         }
-        let msg = get_msg(msg_content);
-        info!("MSG segment parsed");
-        loop2110c.msg_segments.push(msg);
-        contents = content_trim("MSG", &contents);
     }
 
-    // Process LS segment and its content (situational)
-    if contents.starts_with("LS") {
+    if let Some(ls_pos) = ls_pos {
         info!("LS segment found");
-        let ls_content = get_full_segment_contents("LS", &contents).unwrap_or_default();
-
-        // Extract the loop identifier code from the LS segment
-        let ls_parts: Vec<&str> = ls_content.split('*').collect();
-        let loop_id = if ls_parts.len() > 1 {
-            ls_parts[1].trim_end_matches('~').to_string()
-        } else {
-            "2120".to_string() // Default value if not found
+        let loop_id = match rest[ls_pos].element(0) {
+            "" => "2120".to_string(), // Default value if not found
+            code => code.to_string(),
         };
-
-        // Create the LS segment
-        let ls = LS {
+        loop2110c.ls = Some(LS {
             loop_identifier_code: loop_id.clone(),
-        };
-        loop2110c.ls = Some(ls);
-
-        // Remove the LS segment from contents
-        contents = content_trim("LS", &contents);
-
-        // Find the corresponding LE segment
-        let le_position = contents.find("LE*");
-
-        if let Some(le_pos) = le_position {
-            // Extract content between LS and LE
-            let ls_le_content = &contents[..le_pos];
-
-            // Process NM1*P3 segments within the LS/LE loop
-            let mut remaining_content = ls_le_content.to_string();
-            while remaining_content.contains("NM1*P3") {
-                // Find the NM1*P3 segment
-                if let Some(nm1_pos) = remaining_content.find("NM1*P3") {
-                    // Extract from NM1 to the next segment or end
-                    let nm1_content = &remaining_content[nm1_pos..];
-                    let end_pos = nm1_content.find('~').unwrap_or(nm1_content.len());
-                    let nm1_segment = &nm1_content[..end_pos + 1];
-
-                    // Process this segment as Loop2115C
-                    match get_loop_2115c(&nm1_segment) {
-                        Ok((loop2115c, _)) => {
-                            loop2110c.loop2115c.push(loop2115c);
-                        }
-                        Err(e) => {
-                            info!("Error parsing Loop 2115C: {:?}", e);
-                        }
-                    }
-
-                    // Remove the processed segment from remaining content
-                    remaining_content = remaining_content[nm1_pos + end_pos + 1..].to_string();
-                } else {
-                    break;
+        });
+
+        // Process NM1*P3 loops within the LS/LE loop
+        let wrapped_end = le_pos.unwrap_or(rest.len());
+        let (leading, entities) = split_loops(&rest[ls_pos + 1..wrapped_end], &["NM1"]);
+        leftover.push_str(&join_segments(leading));
+        for entity in entities {
+            match get_loop_2115c(&join_segments(entity)) {
+                Ok((loop2115c, remaining)) => {
+                    loop2110c.loop2115c.push(loop2115c);
+                    leftover.push_str(&remaining);
+                }
+                Err(e) => {
+                    info!("Error parsing Loop 2115C: {:?}", e);
+                    leftover.push_str(&join_segments(entity));
                 }
             }
+        }
 
-            // Process the LE segment
-            let le_content =
-                get_full_segment_contents("LE", &contents[le_pos..]).unwrap_or_default();
-
-            // Extract the loop identifier code from the LE segment (should match LS)
-            let le_parts: Vec<&str> = le_content.split('*').collect();
-            let le_loop_id = if le_parts.len() > 1 {
-                le_parts[1].trim_end_matches('~').to_string()
-            } else {
-                loop_id.clone() // Use the same as LS if not found
+        // Process the LE segment; its loop identifier code should match LS
+        if let Some(le_pos) = le_pos {
+            let le_loop_id = match rest[le_pos].element(0) {
+                "" => loop_id,
+                code => code.to_string(),
             };
-
-            // Create the LE segment
-            let le = LE {
+            loop2110c.le = Some(LE {
                 loop_identifier_code: le_loop_id,
-            };
-            loop2110c.le = Some(le);
-
-            // Remove everything up to and including the LE segment
-            if let Some(le_end) = contents[le_pos..].find('~') {
-                contents = contents[le_pos + le_end + 1..].to_string();
-            }
+            });
+            leftover.push_str(&join_segments(&rest[le_pos + 1..]));
         }
     }
 
     info!("Loop 2110C parsed");
-    Ok((loop2110c, contents))
+    Ok((loop2110c, leftover))
 }
 
 pub fn write_loop_2110c(loop2110c: &Loop2110C) -> String {
@@ -2,7 +2,7 @@ use log::info;
 use serde::{Deserialize, Serialize};
 
 use crate::error::{EdiError, EdiResult};
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{join_segments, split_loops, tokenize};
 use crate::segments::aaa::*;
 use crate::segments::dtp::*;
 use crate::segments::eb::*;
@@ -28,123 +28,87 @@ pub struct Loop2115D {
 }
 
 pub fn get_loop_2110d(contents: &str) -> EdiResult<(Loop2110D, String)> {
-    let mut contents = contents.to_string();
     let mut loop2110d = Loop2110D::default();
+    let mut leftover = String::new();
 
     // Process EB segment (required)
-    if contents.contains("EB") {
-        info!("EB segment found");
-        let eb_content = get_segment_contents("EB", &contents);
-        if eb_content.is_empty() {
-            return Err(EdiError::MissingSegment("EB".to_string()));
-        }
-        loop2110d.eb_segments = get_eb(eb_content);
-        info!("EB segment parsed");
-        contents = content_trim("EB", &contents);
-    } else {
+    let segments = tokenize(contents);
+    let Some((eb, rest)) = segments.split_first().filter(|(eb, _)| eb.id == "EB") else {
         return Err(EdiError::MissingSegment("EB".to_string()));
-    }
-
-    // Process HSD segments (situational, can be multiple)
-    while contents.starts_with("HSD") {
-        info!("HSD segment found");
-        let hsd_content = get_segment_contents("HSD", &contents);
-        if hsd_content.is_empty() {
-            break;
-        }
-        let hsd = get_hsd(hsd_content);
-        info!("HSD segment parsed");
-        loop2110d.hsd_segments.push(hsd);
-        contents = content_trim("HSD", &contents);
-    }
-
-    // Process REF segments (situational, can be multiple)
-    while contents.starts_with("REF") {
-        info!("REF segment found");
-        let ref_content = get_segment_contents("REF", &contents);
-        if ref_content.is_empty() {
-            break;
-        }
-        let ref_segment = get_ref(ref_content);
-        info!("REF segment parsed");
-        loop2110d.ref_segments.push(ref_segment);
-        contents = content_trim("REF", &contents);
-    }
-
-    // Process DTP segments (situational, can be multiple)
-    while contents.starts_with("DTP") {
-        info!("DTP segment found");
-        let dtp_content = get_segment_contents("DTP", &contents);
-        if dtp_content.is_empty() {
-            break;
-        }
-        let dtp = get_dtp(dtp_content);
-        info!("DTP segment parsed");
-        loop2110d.dtp_segments.push(dtp);
-        contents = content_trim("DTP", &contents);
-    }
-
-    // Process AAA segments (situational, can be multiple)
-    while contents.starts_with("AAA") {
-        info!("AAA segment found");
-        let aaa_content = get_segment_contents("AAA", &contents);
-        if aaa_content.is_empty() {
-            break;
-        }
-        let aaa = get_aaa(aaa_content);
-        info!("AAA segment parsed");
-        loop2110d.aaa_segments.push(aaa);
-        contents = content_trim("AAA", &contents);
-    }
-
-    // Process MSG segments (situational, can be multiple)
-    while contents.starts_with("MSG") {
-        info!("MSG segment found");
-        let msg_content = get_segment_contents("MSG", &contents);
-        if msg_content.is_empty() {
-            break;
+    };
+    info!("EB segment found");
+    loop2110d.eb_segments = get_eb(eb.elements.join("*"));
+    info!("EB segment parsed");
+
+    // Each III starts a Loop 2115D
+    let (header, messages) = split_loops(rest, &["III"]);
+    for segment in header {
+        match segment.id {
+            // Process HSD segments (situational, can be multiple)
+            "HSD" => {
+                info!("HSD segment found");
+                let hsd = get_hsd(segment.elements.join("*"));
+                info!("HSD segment parsed");
+                loop2110d.hsd_segments.push(hsd);
+            }
+            // Process REF segments (situational, can be multiple)
+            "REF" => {
+                info!("REF segment found");
+                let ref_segment = get_ref(segment.elements.join("*"));
+                info!("REF segment parsed");
+                loop2110d.ref_segments.push(ref_segment);
+            }
+            // Process DTP segments (situational, can be multiple)
+            "DTP" => {
+                info!("DTP segment found");
+                let dtp = get_dtp(segment.elements.join("*"));
+                info!("DTP segment parsed");
+                loop2110d.dtp_segments.push(dtp);
+            }
+            // Process AAA segments (situational, can be multiple)
+            "AAA" => {
+                info!("AAA segment found");
+                let aaa = get_aaa(segment.elements.join("*"));
+                info!("AAA segment parsed");
+                loop2110d.aaa_segments.push(aaa);
+            }
+            // Process MSG segments (situational, can be multiple)
+            "MSG" => {
+                info!("MSG segment found");
+                // get_msg expects the segment ID as the first element
+                let msg = get_msg(segment.raw.to_string());
+                info!("MSG segment parsed");
+                loop2110d.msg_segments.push(msg);
+            }
+            _ => leftover.push_str(&segment.to_edi()),
         }
-        let msg = get_msg(msg_content);
-        info!("MSG segment parsed");
-        loop2110d.msg_segments.push(msg);
-        contents = content_trim("MSG", &contents);
     }
 
     // Process Loop 2115D segments (can be multiple)
-    while contents.contains("III") {
-        match get_loop_2115d(&contents) {
-            Ok((loop2115d_item, new_contents)) => {
-                loop2110d.loop2115d.push(loop2115d_item);
-                contents = new_contents;
-            }
-            Err(_) => break,
-        }
+    for message in messages {
+        let (loop2115d, remaining) = get_loop_2115d(&join_segments(message))?;
+        loop2110d.loop2115d.push(loop2115d);
+        leftover.push_str(&remaining);
     }
 
     info!("Loop 2110D parsed");
-    Ok((loop2110d, contents))
+    Ok((loop2110d, leftover))
 }
 
 pub fn get_loop_2115d(contents: &str) -> EdiResult<(Loop2115D, String)> {
-    let mut contents = contents.to_string();
     let mut loop2115d = Loop2115D::default();
 
     // Process III segment (required)
-    if contents.contains("III") {
-        info!("III segment found");
-        let iii_content = get_segment_contents("III", &contents);
-        if iii_content.is_empty() {
-            return Err(EdiError::MissingSegment("III".to_string()));
-        }
-        loop2115d.iii_segments = get_iii(iii_content);
-        info!("III segment parsed");
-        contents = content_trim("III", &contents);
-    } else {
+    let segments = tokenize(contents);
+    let Some((iii, rest)) = segments.split_first().filter(|(iii, _)| iii.id == "III") else {
         return Err(EdiError::MissingSegment("III".to_string()));
-    }
+    };
+    info!("III segment found");
+    loop2115d.iii_segments = get_iii(iii.elements.join("*"));
+    info!("III segment parsed");
 
     info!("Loop 2115D parsed");
-    Ok((loop2115d, contents))
+    Ok((loop2115d, join_segments(rest)))
 }
 
 pub fn write_loop_2110d(loop2110d: &Loop2110D) -> String {
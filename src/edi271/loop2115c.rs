@@ -2,7 +2,7 @@ use log::info;
 use serde::{Deserialize, Serialize};
 
 use crate::error::{EdiError, EdiResult};
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::tokenize;
 use crate::segments::n3::*;
 use crate::segments::n4::*;
 use crate::segments::nm1::*;
@@ -17,68 +17,52 @@ pub struct Loop2115C {
 }
 
 pub fn get_loop_2115c(contents: &str) -> EdiResult<(Loop2115C, String)> {
-    let mut contents = contents.to_string();
     let mut loop2115c = Loop2115C::default();
+    let mut leftover = String::new();
 
     // Process NM1 segment (required)
-    if contents.contains("NM1") {
-        info!("NM1 segment found for Loop 2115C");
-        let nm1_content = get_segment_contents("NM1", &contents);
-        if nm1_content.is_empty() {
-            return Err(EdiError::MissingSegment("NM1".to_string()));
-        }
-
-        // Check if this is a P3 entity identifier (Provider)
-        let nm1 = get_nm1(nm1_content);
-        if nm1.entity_id == "P3" {
-            loop2115c.nm1_segments = nm1;
-            info!("NM1*P3 segment parsed for Loop 2115C");
-            contents = content_trim("NM1", &contents);
-        } else {
-            // If not a P3 entity, this is not a 2115C loop
-            return Err(EdiError::MissingSegment("NM1*P3".to_string()));
-        }
-    } else {
+    let segments = tokenize(contents);
+    let Some((nm1, rest)) = segments.split_first().filter(|(nm1, _)| nm1.id == "NM1") else {
         return Err(EdiError::MissingSegment("NM1".to_string()));
+    };
+    info!("NM1 segment found for Loop 2115C");
+
+    // Check if this is a P3 entity identifier (Provider)
+    let nm1 = get_nm1(nm1.elements.join("*"));
+    if nm1.entity_id != "P3" {
+        // If not a P3 entity, this is not a 2115C loop
+        return Err(EdiError::MissingSegment("NM1*P3".to_string()));
     }
-
-    // Process N3 segment (situational)
-    if contents.contains("N3") {
-        info!("N3 segment found for Loop 2115C");
-        let n3_content = get_segment_contents("N3", &contents);
-        if !n3_content.is_empty() {
-            loop2115c.n3_segments = Some(get_n3(n3_content));
-            info!("N3 segment parsed for Loop 2115C");
-            contents = content_trim("N3", &contents);
-        }
-    }
-
-    // Process N4 segment (situational)
-    if contents.contains("N4") {
-        info!("N4 segment found for Loop 2115C");
-        let n4_content = get_segment_contents("N4", &contents);
-        if !n4_content.is_empty() {
-            loop2115c.n4_segments = Some(get_n4(n4_content));
-            info!("N4 segment parsed for Loop 2115C");
-            contents = content_trim("N4", &contents);
-        }
-    }
-
-    // Process PER segments (situational, can be multiple)
-    while contents.starts_with("PER") {
-        info!("PER segment found for Loop 2115C");
-        let per_content = get_segment_contents("PER", &contents);
-        if per_content.is_empty() {
-            break;
+    loop2115c.nm1_segments = nm1;
+    info!("NM1*P3 segment parsed for Loop 2115C");
+
+    for segment in rest {
+        match segment.id {
+            // Process N3 segment (situational)
+            "N3" if loop2115c.n3_segments.is_none() => {
+                info!("N3 segment found for Loop 2115C");
+                loop2115c.n3_segments = Some(get_n3(segment.elements.join("*")));
+                info!("N3 segment parsed for Loop 2115C");
+            }
+            // Process N4 segment (situational)
+            "N4" if loop2115c.n4_segments.is_none() => {
+                info!("N4 segment found for Loop 2115C");
+                loop2115c.n4_segments = Some(get_n4(segment.elements.join("*")));
+                info!("N4 segment parsed for Loop 2115C");
+            }
+            // Process PER segments (situational, can be multiple)
+            "PER" => {
+                info!("PER segment found for Loop 2115C");
+                let per = get_per(segment.elements.join("*"));
+                info!("PER segment parsed for Loop 2115C");
+                loop2115c.per_segments.push(per);
+            }
+            _ => leftover.push_str(&segment.to_edi()),
         }
-        let per = get_per(per_content);
-        info!("PER segment parsed for Loop 2115C");
-        loop2115c.per_segments.push(per);
-        contents = content_trim("PER", &contents);
     }
 
     info!("Loop 2115C parsed");
-    Ok((loop2115c, contents))
+    Ok((loop2115c, leftover))
 }
 
 pub fn write_loop_2115c(loop2115c: &Loop2115C) -> String {
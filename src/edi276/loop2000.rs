@@ -1,5 +1,6 @@
 use crate::edi276::loop2100::*;
 use crate::edi276::loop2200::*;
+use crate::helper::tokenizer::{join_segments, split_loops, tokenize};
 use crate::segments::amt::*;
 use crate::segments::dmg::*;
 use crate::segments::dtp::*;
@@ -66,640 +67,250 @@ pub struct Loop2000E {
     pub loop2200e: Vec<Loop2200E>,
 }
 
+// Process an Information Source level: its HL segment up to the next HL
 pub fn get_loop_2000a(contents: &str) -> (Loop2000A, String) {
-    let contents = contents.to_string();
     let mut loop_2000a = Loop2000A::default();
-    let mut remaining_content = contents.clone();
-
-    // Process HL segment for Information Source
-    if let Some(hl_segment_start) = contents.find("HL*") {
-        let hl_segment_end = contents[hl_segment_start..]
-            .find('~')
-            .unwrap_or(contents.len() - hl_segment_start);
-        let hl_segment = &contents[hl_segment_start..hl_segment_start + hl_segment_end];
-
-        let hl_elements: Vec<&str> = hl_segment.split('*').collect();
-
-        if hl_elements.len() >= 4 && hl_elements[3] == "20" {
-            // 20 is the code for Information Source
-            // Strip "HL*" prefix before passing to get_hl
-            let hl_content = if hl_segment.starts_with("HL*") {
-                &hl_segment[3..]
-            } else {
-                hl_segment
-            };
-            loop_2000a.hl = get_hl(hl_content.to_string());
-
-            // Remove the HL segment from the remaining content
-            remaining_content = contents[hl_segment_start + hl_segment_end + 1..].to_string();
 
-            // Process NM1 segment
-            if let Some(nm1_segment_start) = remaining_content.find("NM1") {
-                let nm1_segment_end = remaining_content[nm1_segment_start..]
-                    .find('~')
-                    .unwrap_or(remaining_content.len() - nm1_segment_start);
-                let nm1_segment =
-                    &remaining_content[nm1_segment_start..nm1_segment_start + nm1_segment_end];
-
-                let nm1_content = if nm1_segment.starts_with("NM1*") {
-                    &nm1_segment[4..]
-                } else {
-                    nm1_segment
-                };
-                loop_2000a.nm1 = get_nm1(nm1_content.to_string());
-
-                // Remove the NM1 segment from the remaining content
-                remaining_content =
-                    remaining_content[nm1_segment_start + nm1_segment_end + 1..].to_string();
-
-                // Process Loop 2100A
-                let (loop_2100a_vec, new_remaining) = get_loop_2100a_vec(&remaining_content);
-                loop_2000a.loop2100a = loop_2100a_vec;
-                remaining_content = new_remaining;
+    let segments = tokenize(contents);
+    let Some((hl, rest)) = segments.split_first() else {
+        return (loop_2000a, String::new());
+    };
+    loop_2000a.hl = get_hl(hl.elements.join("*"));
+
+    // The first NM1 names the information source; each further PR or IL name starts a Loop 2100A
+    let (leading, names) = split_loops(rest, &["NM1"]);
+    let mut leftover = join_segments(leading);
+    if let Some((name, names)) = names.split_first() {
+        loop_2000a.nm1 = get_nm1(name[0].elements.join("*"));
+        leftover.push_str(&join_segments(&name[1..]));
+
+        for name in names {
+            match name[0].element(0) {
+                "PR" | "IL" => {
+                    let (loop_2100a, remaining) = get_loop_2100a(&join_segments(name));
+                    loop_2000a.loop2100a.push(loop_2100a);
+                    leftover.push_str(&remaining);
+                }
+                _ => leftover.push_str(&join_segments(name)),
             }
         }
     }
 
-    (loop_2000a, remaining_content)
+    (loop_2000a, leftover)
 }
 
-pub fn get_loop_2000b_vec(contents: &str) -> (Vec<Loop2000B>, String) {
-    let contents = contents.to_string();
-    let mut loop_2000b_vec = Vec::new();
-    let mut remaining_content = contents.clone();
-
-    // Process multiple Loop 2000B
-    while let Some(hl_segment_start) = remaining_content.find("HL*") {
-        let hl_segment_end = remaining_content[hl_segment_start..]
-            .find('~')
-            .unwrap_or(remaining_content.len() - hl_segment_start);
-        let hl_segment = &remaining_content[hl_segment_start..hl_segment_start + hl_segment_end];
-
-        let hl_elements: Vec<&str> = hl_segment.split('*').collect();
-
-        if hl_elements.len() >= 4 && hl_elements[3] == "21" {
-            // 21 is the code for Information Receiver
-            let mut loop_2000b = Loop2000B::default();
-            let hl_content = if hl_segment.starts_with("HL*") {
-                &hl_segment[3..]
-            } else {
-                hl_segment
-            };
-            loop_2000b.hl = get_hl(hl_content.to_string());
-
-            // Remove the HL segment from the remaining content
-            remaining_content =
-                remaining_content[hl_segment_start + hl_segment_end + 1..].to_string();
-
-            // Process NM1 segment
-            if let Some(nm1_segment_start) = remaining_content.find("NM1") {
-                let nm1_segment_end = remaining_content[nm1_segment_start..]
-                    .find('~')
-                    .unwrap_or(remaining_content.len() - nm1_segment_start);
-                let nm1_segment =
-                    &remaining_content[nm1_segment_start..nm1_segment_start + nm1_segment_end];
-
-                let nm1_content = if nm1_segment.starts_with("NM1*") {
-                    &nm1_segment[4..]
-                } else {
-                    nm1_segment
-                };
-                loop_2000b.nm1 = get_nm1(nm1_content.to_string());
-
-                // Remove the NM1 segment from the remaining content
-                remaining_content =
-                    remaining_content[nm1_segment_start + nm1_segment_end + 1..].to_string();
-
-                // Process Loop 2100B
-                let (loop_2100b_vec, new_remaining) = get_loop_2100b_vec(&remaining_content);
-                loop_2000b.loop2100b = loop_2100b_vec;
-                remaining_content = new_remaining;
+// Process an Information Receiver level: its HL segment up to the next HL
+pub fn get_loop_2000b(contents: &str) -> (Loop2000B, String) {
+    let mut loop_2000b = Loop2000B::default();
+
+    let segments = tokenize(contents);
+    let Some((hl, rest)) = segments.split_first() else {
+        return (loop_2000b, String::new());
+    };
+    loop_2000b.hl = get_hl(hl.elements.join("*"));
+
+    // The first NM1 names the information receiver; each further 41 or 1P name starts a Loop 2100B
+    let (leading, names) = split_loops(rest, &["NM1"]);
+    let mut leftover = join_segments(leading);
+    if let Some((name, names)) = names.split_first() {
+        loop_2000b.nm1 = get_nm1(name[0].elements.join("*"));
+        leftover.push_str(&join_segments(&name[1..]));
+
+        for name in names {
+            match name[0].element(0) {
+                "41" | "1P" => {
+                    let (loop_2100b, remaining) = get_loop_2100b(&join_segments(name));
+                    loop_2000b.loop2100b.push(loop_2100b);
+                    leftover.push_str(&remaining);
+                }
+                _ => leftover.push_str(&join_segments(name)),
             }
-
-            loop_2000b_vec.push(loop_2000b);
-        } else {
-            // If not a Loop 2000B, break the loop
-            break;
         }
     }
 
-    (loop_2000b_vec, remaining_content)
+    (loop_2000b, leftover)
 }
 
-// Process Loop 2100A segments
-pub fn get_loop_2100a_vec(contents: &str) -> (Vec<Loop2100A>, String) {
-    let contents = contents.to_string();
-    let mut loop_2100a_vec = Vec::new();
-    let mut remaining_content = contents.clone();
-
-    // Process NM1 segments for Loop 2100A
-    while let Some(nm1_segment_start) = remaining_content.find("NM1") {
-        let nm1_segment_end = remaining_content[nm1_segment_start..]
-            .find('~')
-            .unwrap_or(remaining_content.len() - nm1_segment_start);
-        let nm1_segment =
-            &remaining_content[nm1_segment_start..nm1_segment_start + nm1_segment_end];
-
-        let nm1_elements: Vec<&str> = nm1_segment.split('*').collect();
-
-        // Check if this is a Loop 2100A NM1 segment
-        if nm1_elements.len() >= 2 && (nm1_elements[1] == "PR" || nm1_elements[1] == "IL") {
-            let mut loop_2100a = Loop2100A::default();
-            let nm1_content = if nm1_segment.starts_with("NM1*") {
-                &nm1_segment[4..]
-            } else {
-                nm1_segment
-            };
-            loop_2100a.nm1 = get_nm1(nm1_content.to_string());
-
-            // Remove the NM1 segment from the remaining content
-            remaining_content =
-                remaining_content[nm1_segment_start + nm1_segment_end + 1..].to_string();
+// Process a Loop 2100A name: its NM1 segment and the segments up to the next NM1
+pub fn get_loop_2100a(contents: &str) -> (Loop2100A, String) {
+    let mut loop_2100a = Loop2100A::default();
+    let mut leftover = String::new();
 
-            // Process REF segments
-            while let Some(ref_segment_start) = remaining_content.find("REF") {
-                let ref_segment_end = remaining_content[ref_segment_start..]
-                    .find('~')
-                    .unwrap_or(remaining_content.len() - ref_segment_start);
-                let ref_segment =
-                    &remaining_content[ref_segment_start..ref_segment_start + ref_segment_end];
-
-                // Check if the next segment is not a new loop
-                if !remaining_content[ref_segment_start + ref_segment_end + 1..].starts_with("NM1")
-                    && !remaining_content[ref_segment_start + ref_segment_end + 1..]
-                        .starts_with("HL")
-                {
-                    loop_2100a
-                        .ref_segments
-                        .push(get_ref(ref_segment.to_string()));
-                    remaining_content =
-                        remaining_content[ref_segment_start + ref_segment_end + 1..].to_string();
-                } else {
-                    break;
-                }
-            }
+    let segments = tokenize(contents);
+    let Some((nm1, rest)) = segments.split_first() else {
+        return (loop_2100a, leftover);
+    };
+    loop_2100a.nm1 = get_nm1(nm1.elements.join("*"));
 
+    for segment in rest {
+        match segment.id {
+            // Process REF segments
+            "REF" => loop_2100a
+                .ref_segments
+                .push(get_ref(segment.elements.join("*"))),
             // Process N3 segment
-            if let Some(n3_segment_start) = remaining_content.find("N3") {
-                let n3_segment_end = remaining_content[n3_segment_start..]
-                    .find('~')
-                    .unwrap_or(remaining_content.len() - n3_segment_start);
-                let n3_segment =
-                    &remaining_content[n3_segment_start..n3_segment_start + n3_segment_end];
-
-                loop_2100a.n3 = Some(get_n3(n3_segment.to_string()));
-                remaining_content =
-                    remaining_content[n3_segment_start + n3_segment_end + 1..].to_string();
+            "N3" if loop_2100a.n3.is_none() => {
+                loop_2100a.n3 = Some(get_n3(segment.elements.join("*")))
             }
-
             // Process N4 segment
-            if let Some(n4_segment_start) = remaining_content.find("N4") {
-                let n4_segment_end = remaining_content[n4_segment_start..]
-                    .find('~')
-                    .unwrap_or(remaining_content.len() - n4_segment_start);
-                let n4_segment =
-                    &remaining_content[n4_segment_start..n4_segment_start + n4_segment_end];
-
-                loop_2100a.n4 = Some(get_n4(n4_segment.to_string()));
-                remaining_content =
-                    remaining_content[n4_segment_start + n4_segment_end + 1..].to_string();
+            "N4" if loop_2100a.n4.is_none() => {
+                loop_2100a.n4 = Some(get_n4(segment.elements.join("*")))
             }
-
             // Process PER segments
-            while let Some(per_segment_start) = remaining_content.find("PER") {
-                let per_segment_end = remaining_content[per_segment_start..]
-                    .find('~')
-                    .unwrap_or(remaining_content.len() - per_segment_start);
-                let per_segment =
-                    &remaining_content[per_segment_start..per_segment_start + per_segment_end];
-
-                // Check if the next segment is not a new loop
-                if !remaining_content[per_segment_start + per_segment_end + 1..].starts_with("NM1")
-                    && !remaining_content[per_segment_start + per_segment_end + 1..]
-                        .starts_with("HL")
-                {
-                    loop_2100a
-                        .per_segments
-                        .push(get_per(per_segment.to_string()));
-                    remaining_content =
-                        remaining_content[per_segment_start + per_segment_end + 1..].to_string();
-                } else {
-                    break;
-                }
-            }
-
-            loop_2100a_vec.push(loop_2100a);
-        } else {
-            // If not a Loop 2100A NM1 segment, break the loop
-            break;
+            "PER" => loop_2100a
+                .per_segments
+                .push(get_per(segment.elements.join("*"))),
+            _ => leftover.push_str(&segment.to_edi()),
         }
     }
 
-    (loop_2100a_vec, remaining_content)
+    (loop_2100a, leftover)
 }
 
-// Process Loop 2100B segments
-pub fn get_loop_2100b_vec(contents: &str) -> (Vec<Loop2100B>, String) {
-    let contents = contents.to_string();
-    let mut loop_2100b_vec = Vec::new();
-    let mut remaining_content = contents.clone();
-
-    // Process NM1 segments for Loop 2100B
-    while let Some(nm1_segment_start) = remaining_content.find("NM1") {
-        // Stop if there's an HL segment before this NM1 (belongs to next loop)
-        if let Some(hl_pos) = remaining_content.find("HL*") {
-            if hl_pos < nm1_segment_start {
-                break;
-            }
-        }
-        let nm1_segment_end = remaining_content[nm1_segment_start..]
-            .find('~')
-            .unwrap_or(remaining_content.len() - nm1_segment_start);
-        let nm1_segment =
-            &remaining_content[nm1_segment_start..nm1_segment_start + nm1_segment_end];
-
-        let nm1_elements: Vec<&str> = nm1_segment.split('*').collect();
-
-        // Check if this is a Loop 2100B NM1 segment
-        if nm1_elements.len() >= 2 && (nm1_elements[1] == "41" || nm1_elements[1] == "1P") {
-            let mut loop_2100b = Loop2100B::default();
-            let nm1_content = if nm1_segment.starts_with("NM1*") {
-                &nm1_segment[4..]
-            } else {
-                nm1_segment
-            };
-            loop_2100b.nm1 = get_nm1(nm1_content.to_string());
-
-            // Remove the NM1 segment from the remaining content
-            remaining_content =
-                remaining_content[nm1_segment_start + nm1_segment_end + 1..].to_string();
+// Process a Loop 2100B name: its NM1 segment and the segments up to the next NM1
+pub fn get_loop_2100b(contents: &str) -> (Loop2100B, String) {
+    let mut loop_2100b = Loop2100B::default();
+    let mut leftover = String::new();
 
-            // Process REF segments
-            while let Some(ref_segment_start) = remaining_content.find("REF") {
-                let ref_segment_end = remaining_content[ref_segment_start..]
-                    .find('~')
-                    .unwrap_or(remaining_content.len() - ref_segment_start);
-                let ref_segment =
-                    &remaining_content[ref_segment_start..ref_segment_start + ref_segment_end];
-
-                // Check if the next segment is not a new loop
-                if !remaining_content[ref_segment_start + ref_segment_end + 1..].starts_with("NM1")
-                    && !remaining_content[ref_segment_start + ref_segment_end + 1..]
-                        .starts_with("HL")
-                {
-                    loop_2100b
-                        .ref_segments
-                        .push(get_ref(ref_segment.to_string()));
-                    remaining_content =
-                        remaining_content[ref_segment_start + ref_segment_end + 1..].to_string();
-                } else {
-                    break;
-                }
-            }
+    let segments = tokenize(contents);
+    let Some((nm1, rest)) = segments.split_first() else {
+        return (loop_2100b, leftover);
+    };
+    loop_2100b.nm1 = get_nm1(nm1.elements.join("*"));
 
+    for segment in rest {
+        match segment.id {
+            // Process REF segments
+            "REF" => loop_2100b
+                .ref_segments
+                .push(get_ref(segment.elements.join("*"))),
             // Process N3 segment
-            if let Some(n3_segment_start) = remaining_content.find("N3") {
-                let n3_segment_end = remaining_content[n3_segment_start..]
-                    .find('~')
-                    .unwrap_or(remaining_content.len() - n3_segment_start);
-                let n3_segment =
-                    &remaining_content[n3_segment_start..n3_segment_start + n3_segment_end];
-
-                loop_2100b.n3 = Some(get_n3(n3_segment.to_string()));
-                remaining_content =
-                    remaining_content[n3_segment_start + n3_segment_end + 1..].to_string();
+            "N3" if loop_2100b.n3.is_none() => {
+                loop_2100b.n3 = Some(get_n3(segment.elements.join("*")))
             }
-
             // Process N4 segment
-            if let Some(n4_segment_start) = remaining_content.find("N4") {
-                let n4_segment_end = remaining_content[n4_segment_start..]
-                    .find('~')
-                    .unwrap_or(remaining_content.len() - n4_segment_start);
-                let n4_segment =
-                    &remaining_content[n4_segment_start..n4_segment_start + n4_segment_end];
-
-                loop_2100b.n4 = Some(get_n4(n4_segment.to_string()));
-                remaining_content =
-                    remaining_content[n4_segment_start + n4_segment_end + 1..].to_string();
+            "N4" if loop_2100b.n4.is_none() => {
+                loop_2100b.n4 = Some(get_n4(segment.elements.join("*")))
             }
-
             // Process PER segments
-            while let Some(per_segment_start) = remaining_content.find("PER") {
-                let per_segment_end = remaining_content[per_segment_start..]
-                    .find('~')
-                    .unwrap_or(remaining_content.len() - per_segment_start);
-                let per_segment =
-                    &remaining_content[per_segment_start..per_segment_start + per_segment_end];
-
-                // Check if the next segment is not a new loop
-                if !remaining_content[per_segment_start + per_segment_end + 1..].starts_with("NM1")
-                    && !remaining_content[per_segment_start + per_segment_end + 1..]
-                        .starts_with("HL")
-                {
-                    loop_2100b
-                        .per_segments
-                        .push(get_per(per_segment.to_string()));
-                    remaining_content =
-                        remaining_content[per_segment_start + per_segment_end + 1..].to_string();
-                } else {
-                    break;
-                }
-            }
-
-            loop_2100b_vec.push(loop_2100b);
-        } else {
-            // If not a Loop 2100B NM1 segment, break the loop
-            break;
+            "PER" => loop_2100b
+                .per_segments
+                .push(get_per(segment.elements.join("*"))),
+            _ => leftover.push_str(&segment.to_edi()),
         }
     }
 
-    (loop_2100b_vec, remaining_content)
+    (loop_2100b, leftover)
 }
 
-pub fn get_loop_2000c_vec(contents: &str) -> (Vec<Loop2000C>, String) {
-    let contents = contents.to_string();
-    let mut loop_2000c_vec = Vec::new();
-    let mut remaining_content = contents.clone();
-
-    while let Some(hl_segment_start) = remaining_content.find("HL*") {
-        let hl_segment_end = remaining_content[hl_segment_start..]
-            .find('~')
-            .unwrap_or(remaining_content.len() - hl_segment_start);
-        let hl_segment = &remaining_content[hl_segment_start..hl_segment_start + hl_segment_end];
-
-        let hl_elements: Vec<&str> = hl_segment.split('*').collect();
-
-        if hl_elements.len() >= 4 && hl_elements[3] == "19" {
-            let mut loop_2000c = Loop2000C::default();
-            let hl_content = if hl_segment.starts_with("HL*") {
-                &hl_segment[3..]
-            } else {
-                hl_segment
-            };
-            loop_2000c.hl = get_hl(hl_content.to_string());
-
-            remaining_content =
-                remaining_content[hl_segment_start + hl_segment_end + 1..].to_string();
-
+// Process a Service Provider level: its HL segment up to the next HL
+pub fn get_loop_2000c(contents: &str) -> (Loop2000C, String) {
+    let mut loop_2000c = Loop2000C::default();
+    let mut leftover = String::new();
+
+    let segments = tokenize(contents);
+    let Some((hl, rest)) = segments.split_first() else {
+        return (loop_2000c, leftover);
+    };
+    loop_2000c.hl = get_hl(hl.elements.join("*"));
+
+    // The first TRN is kept with the level; each further TRN starts a Loop 2200C
+    let (head, claims) = split_loops(rest, &["TRN"]);
+    let mut nm1_found = false;
+    for segment in head {
+        match segment.id {
             // Process NM1 segment
-            if let Some(nm1_segment_start) = remaining_content.find("NM1") {
-                // Only parse if NM1 comes before next HL or SE
-                let next_hl = remaining_content.find("HL*").unwrap_or(usize::MAX);
-                let next_se = remaining_content.find("SE*").unwrap_or(usize::MAX);
-                if nm1_segment_start < next_hl && nm1_segment_start < next_se {
-                    let nm1_segment_end = remaining_content[nm1_segment_start..]
-                        .find('~')
-                        .unwrap_or(remaining_content.len() - nm1_segment_start);
-                    let nm1_segment =
-                        &remaining_content[nm1_segment_start..nm1_segment_start + nm1_segment_end];
-
-                    let nm1_content = if nm1_segment.starts_with("NM1*") {
-                        &nm1_segment[4..]
-                    } else {
-                        nm1_segment
-                    };
-                    loop_2000c.nm1 = get_nm1(nm1_content.to_string());
-
-                    remaining_content =
-                        remaining_content[nm1_segment_start + nm1_segment_end + 1..].to_string();
-                }
+            "NM1" if !nm1_found => {
+                nm1_found = true;
+                loop_2000c.nm1 = get_nm1(segment.elements.join("*"));
             }
+            _ => leftover.push_str(&segment.to_edi()),
+        }
+    }
 
-            // Process TRN segment
-            if let Some(trn_segment_start) = remaining_content.find("TRN") {
-                let next_hl = remaining_content.find("HL*").unwrap_or(usize::MAX);
-                let next_se = remaining_content.find("SE*").unwrap_or(usize::MAX);
-                if trn_segment_start < next_hl && trn_segment_start < next_se {
-                    let trn_segment_end = remaining_content[trn_segment_start..]
-                        .find('~')
-                        .unwrap_or(remaining_content.len() - trn_segment_start);
-                    let trn_segment =
-                        &remaining_content[trn_segment_start..trn_segment_start + trn_segment_end];
-
-                    loop_2000c.trn = get_trn(if trn_segment.starts_with("TRN*") {
-                        trn_segment[4..].to_string()
-                    } else {
-                        trn_segment.to_string()
-                    });
-
-                    remaining_content =
-                        remaining_content[trn_segment_start + trn_segment_end + 1..].to_string();
-                }
-            }
+    if let Some((claim, claims)) = claims.split_first() {
+        // Process TRN segment
+        loop_2000c.trn = get_trn(claim[0].elements.join("*"));
 
-            // Process REF segments
-            while let Some(ref_segment_start) = remaining_content.find("REF") {
-                let next_hl = remaining_content.find("HL*").unwrap_or(usize::MAX);
-                let next_se = remaining_content.find("SE*").unwrap_or(usize::MAX);
-                if ref_segment_start < next_hl && ref_segment_start < next_se {
-                    let ref_segment_end = remaining_content[ref_segment_start..]
-                        .find('~')
-                        .unwrap_or(remaining_content.len() - ref_segment_start);
-                    let ref_segment =
-                        &remaining_content[ref_segment_start..ref_segment_start + ref_segment_end];
-
-                    loop_2000c
-                        .ref_segments
-                        .push(get_ref(ref_segment.to_string()));
-
-                    remaining_content =
-                        remaining_content[ref_segment_start + ref_segment_end + 1..].to_string();
-                } else {
-                    break;
-                }
+        for segment in &claim[1..] {
+            match segment.id {
+                // Process REF segments
+                "REF" => loop_2000c
+                    .ref_segments
+                    .push(get_ref(segment.elements.join("*"))),
+                _ => leftover.push_str(&segment.to_edi()),
             }
+        }
 
-            loop_2000c_vec.push(loop_2000c);
-        } else {
-            break;
+        for claim in claims {
+            let (loop_2200c, remaining) = get_loop_2200c(&join_segments(claim));
+            loop_2000c.loop2200c.push(loop_2200c);
+            leftover.push_str(&remaining);
         }
     }
 
-    (loop_2000c_vec, remaining_content)
+    (loop_2000c, leftover)
 }
 
-pub fn get_loop_2000d_vec(contents: &str) -> (Vec<Loop2000D>, String) {
-    let contents = contents.to_string();
-    let mut loop_2000d_vec = Vec::new();
-    let mut remaining_content = contents.clone();
-
-    while let Some(hl_segment_start) = remaining_content.find("HL*") {
-        let hl_segment_end = remaining_content[hl_segment_start..]
-            .find('~')
-            .unwrap_or(remaining_content.len() - hl_segment_start);
-        let hl_segment = &remaining_content[hl_segment_start..hl_segment_start + hl_segment_end];
-
-        let hl_elements: Vec<&str> = hl_segment.split('*').collect();
-
-        if hl_elements.len() >= 4 && hl_elements[3] == "22" {
-            let mut loop_2000d = Loop2000D::default();
-            let hl_content = if hl_segment.starts_with("HL*") {
-                &hl_segment[3..]
-            } else {
-                hl_segment
-            };
-            loop_2000d.hl = get_hl(hl_content.to_string());
-
-            remaining_content =
-                remaining_content[hl_segment_start + hl_segment_end + 1..].to_string();
-
+// Process a Subscriber level: its HL segment up to the next HL
+pub fn get_loop_2000d(contents: &str) -> (Loop2000D, String) {
+    let mut loop_2000d = Loop2000D::default();
+    let mut leftover = String::new();
+
+    let segments = tokenize(contents);
+    let Some((hl, rest)) = segments.split_first() else {
+        return (loop_2000d, leftover);
+    };
+    loop_2000d.hl = get_hl(hl.elements.join("*"));
+
+    // The first TRN is kept with the level; each further TRN starts a Loop 2200D
+    let (head, claims) = split_loops(rest, &["TRN"]);
+    let mut nm1_found = false;
+    for segment in head {
+        match segment.id {
             // Process DMG segment (optional, comes before NM1 in 276)
-            if let Some(dmg_segment_start) = remaining_content.find("DMG") {
-                let next_hl = remaining_content.find("HL*").unwrap_or(usize::MAX);
-                let next_se = remaining_content.find("SE*").unwrap_or(usize::MAX);
-                let next_nm1 = remaining_content.find("NM1").unwrap_or(usize::MAX);
-                if dmg_segment_start < next_hl
-                    && dmg_segment_start < next_se
-                    && dmg_segment_start < next_nm1
-                {
-                    let dmg_segment_end = remaining_content[dmg_segment_start..]
-                        .find('~')
-                        .unwrap_or(remaining_content.len() - dmg_segment_start);
-                    let dmg_segment =
-                        &remaining_content[dmg_segment_start..dmg_segment_start + dmg_segment_end];
-
-                    loop_2000d.dmg = Some(get_dmg(if dmg_segment.starts_with("DMG*") {
-                        dmg_segment[4..].to_string()
-                    } else {
-                        dmg_segment.to_string()
-                    }));
-
-                    remaining_content =
-                        remaining_content[dmg_segment_start + dmg_segment_end + 1..].to_string();
-                }
+            "DMG" if loop_2000d.dmg.is_none() => {
+                loop_2000d.dmg = Some(get_dmg(segment.elements.join("*")))
             }
-
             // Process NM1 segment
-            if let Some(nm1_segment_start) = remaining_content.find("NM1") {
-                let next_hl = remaining_content.find("HL*").unwrap_or(usize::MAX);
-                let next_se = remaining_content.find("SE*").unwrap_or(usize::MAX);
-                if nm1_segment_start < next_hl && nm1_segment_start < next_se {
-                    let nm1_segment_end = remaining_content[nm1_segment_start..]
-                        .find('~')
-                        .unwrap_or(remaining_content.len() - nm1_segment_start);
-                    let nm1_segment =
-                        &remaining_content[nm1_segment_start..nm1_segment_start + nm1_segment_end];
-
-                    let nm1_content = if nm1_segment.starts_with("NM1*") {
-                        &nm1_segment[4..]
-                    } else {
-                        nm1_segment
-                    };
-                    loop_2000d.nm1 = get_nm1(nm1_content.to_string());
-
-                    remaining_content =
-                        remaining_content[nm1_segment_start + nm1_segment_end + 1..].to_string();
-                }
-            }
-
-            // Process TRN segment
-            if let Some(trn_segment_start) = remaining_content.find("TRN") {
-                let next_hl = remaining_content.find("HL*").unwrap_or(usize::MAX);
-                let next_se = remaining_content.find("SE*").unwrap_or(usize::MAX);
-                if trn_segment_start < next_hl && trn_segment_start < next_se {
-                    let trn_segment_end = remaining_content[trn_segment_start..]
-                        .find('~')
-                        .unwrap_or(remaining_content.len() - trn_segment_start);
-                    let trn_segment =
-                        &remaining_content[trn_segment_start..trn_segment_start + trn_segment_end];
-
-                    loop_2000d.trn = get_trn(if trn_segment.starts_with("TRN*") {
-                        trn_segment[4..].to_string()
-                    } else {
-                        trn_segment.to_string()
-                    });
-
-                    remaining_content =
-                        remaining_content[trn_segment_start + trn_segment_end + 1..].to_string();
-                }
-            }
-
-            // Process REF segments
-            while let Some(ref_segment_start) = remaining_content.find("REF") {
-                let next_hl = remaining_content.find("HL*").unwrap_or(usize::MAX);
-                let next_se = remaining_content.find("SE*").unwrap_or(usize::MAX);
-                if ref_segment_start < next_hl && ref_segment_start < next_se {
-                    let ref_segment_end = remaining_content[ref_segment_start..]
-                        .find('~')
-                        .unwrap_or(remaining_content.len() - ref_segment_start);
-                    let ref_segment =
-                        &remaining_content[ref_segment_start..ref_segment_start + ref_segment_end];
-
-                    // Stop if we hit a non-REF segment
-                    if !ref_segment.starts_with("REF") {
-                        break;
-                    }
-
-                    loop_2000d
-                        .ref_segments
-                        .push(get_ref(ref_segment.to_string()));
-
-                    remaining_content =
-                        remaining_content[ref_segment_start + ref_segment_end + 1..].to_string();
-                } else {
-                    break;
-                }
-            }
-
-            // Process AMT segments
-            while let Some(amt_start) = remaining_content.find("AMT") {
-                let next_hl = remaining_content.find("HL*").unwrap_or(usize::MAX);
-                let next_se = remaining_content.find("SE*").unwrap_or(usize::MAX);
-                if amt_start < next_hl && amt_start < next_se {
-                    let amt_end = remaining_content[amt_start..]
-                        .find('~')
-                        .unwrap_or(remaining_content.len() - amt_start);
-                    let amt_segment = &remaining_content[amt_start..amt_start + amt_end];
-                    if !amt_segment.starts_with("AMT") {
-                        break;
-                    }
-                    let amt_content = if amt_segment.starts_with("AMT*") {
-                        &amt_segment[4..]
-                    } else {
-                        amt_segment
-                    };
-                    loop_2000d
-                        .amt_segments
-                        .push(get_amt(amt_content.to_string()));
-                    remaining_content = remaining_content[amt_start + amt_end + 1..].to_string();
-                } else {
-                    break;
-                }
+            "NM1" if !nm1_found => {
+                nm1_found = true;
+                loop_2000d.nm1 = get_nm1(segment.elements.join("*"));
             }
+            _ => leftover.push_str(&segment.to_edi()),
+        }
+    }
 
-            // Process DTP segments
-            while let Some(dtp_start) = remaining_content.find("DTP") {
-                let next_hl = remaining_content.find("HL*").unwrap_or(usize::MAX);
-                let next_se = remaining_content.find("SE*").unwrap_or(usize::MAX);
-                if dtp_start < next_hl && dtp_start < next_se {
-                    let dtp_end = remaining_content[dtp_start..]
-                        .find('~')
-                        .unwrap_or(remaining_content.len() - dtp_start);
-                    let dtp_segment = &remaining_content[dtp_start..dtp_start + dtp_end];
-                    if !dtp_segment.starts_with("DTP") {
-                        break;
-                    }
-                    let dtp_content = if dtp_segment.starts_with("DTP*") {
-                        &dtp_segment[4..]
-                    } else {
-                        dtp_segment
-                    };
-                    loop_2000d
-                        .dtp_segments
-                        .push(get_dtp(dtp_content.to_string()));
-                    remaining_content = remaining_content[dtp_start + dtp_end + 1..].to_string();
-                } else {
-                    break;
-                }
+    if let Some((claim, claims)) = claims.split_first() {
+        // Process TRN segment
+        loop_2000d.trn = get_trn(claim[0].elements.join("*"));
+
+        for segment in &claim[1..] {
+            match segment.id {
+                // Process REF segments
+                "REF" => loop_2000d
+                    .ref_segments
+                    .push(get_ref(segment.elements.join("*"))),
+                // Process AMT segments
+                "AMT" => loop_2000d
+                    .amt_segments
+                    .push(get_amt(segment.elements.join("*"))),
+                // Process DTP segments
+                "DTP" => loop_2000d
+                    .dtp_segments
+                    .push(get_dtp(segment.elements.join("*"))),
+                _ => leftover.push_str(&segment.to_edi()),
             }
+        }
 
-            loop_2000d_vec.push(loop_2000d);
-        } else {
-            break;
+        for claim in claims {
+            let (loop_2200d, remaining) = get_loop_2200d(&join_segments(claim));
+            loop_2000d.loop2200d.push(loop_2200d);
+            leftover.push_str(&remaining);
         }
     }
 
-    (loop_2000d_vec, remaining_content)
+    (loop_2000d, leftover)
 }
 
 // Function to write Loop 2000D
@@ -1,3 +1,4 @@
+use crate::helper::tokenizer::tokenize;
 use crate::segments::dtp::*;
 use crate::segments::r#ref::*;
 use crate::segments::trn::*;
@@ -27,8 +28,61 @@ pub struct Loop2200E {
     pub dtp_segments: Vec<DTP>,
 }
 
-// Placeholder functions for loop processing
-// These will be implemented in detail later
+// Process a Loop 2200C claim: its TRN segment up to the next TRN
+pub fn get_loop_2200c(contents: &str) -> (Loop2200C, String) {
+    let mut loop_2200c = Loop2200C::default();
+    let mut leftover = String::new();
+
+    let segments = tokenize(contents);
+    let Some((trn, rest)) = segments.split_first() else {
+        return (loop_2200c, leftover);
+    };
+    loop_2200c.trn = get_trn(trn.elements.join("*"));
+
+    for segment in rest {
+        match segment.id {
+            // Process REF segments
+            "REF" => loop_2200c
+                .ref_segments
+                .push(get_ref(segment.elements.join("*"))),
+            // Process DTP segments
+            "DTP" => loop_2200c
+                .dtp_segments
+                .push(get_dtp(segment.elements.join("*"))),
+            _ => leftover.push_str(&segment.to_edi()),
+        }
+    }
+
+    (loop_2200c, leftover)
+}
+
+// Process a Loop 2200D claim: its TRN segment up to the next TRN
+pub fn get_loop_2200d(contents: &str) -> (Loop2200D, String) {
+    let mut loop_2200d = Loop2200D::default();
+    let mut leftover = String::new();
+
+    let segments = tokenize(contents);
+    let Some((trn, rest)) = segments.split_first() else {
+        return (loop_2200d, leftover);
+    };
+    loop_2200d.trn = get_trn(trn.elements.join("*"));
+
+    for segment in rest {
+        match segment.id {
+            // Process REF segments
+            "REF" => loop_2200d
+                .ref_segments
+                .push(get_ref(segment.elements.join("*"))),
+            // Process DTP segments
+            "DTP" => loop_2200d
+                .dtp_segments
+                .push(get_dtp(segment.elements.join("*"))),
+            _ => leftover.push_str(&segment.to_edi()),
+        }
+    }
+
+    (loop_2200d, leftover)
+}
 
 pub fn write_loop_2200c(loop_2200c: &Loop2200C) -> String {
     let mut result = String::new();
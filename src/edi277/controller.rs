@@ -6,6 +6,13 @@ use crate::edi277::interchangecontrol::*;
 use crate::edi277::loop2000::*;
 use crate::edi277::table1::*;
 use crate::error::EdiResult;
+use crate::helper::delimiters::Delimiters;
+use crate::helper::diagnostics::{collect_diagnostics, Diagnostics};
+use crate::helper::helper::clean_contents;
+use crate::helper::tokenizer::{find_segment, join_segments, split_loops, tokenize};
+use crate::helper::unrecognized::UnrecognizedSegments;
+use crate::helper::writer_options::WriterOptions;
+use crate::transaction_processor::TransactionSet;
 
 /// Table1Combined structure for EDI 277
 /// Contains the ST, BHT, and other header segments
@@ -26,6 +33,15 @@ pub struct Edi277 {
     pub loop2000d: Vec<Loop2000D>,
     pub se_segment: String,
     pub interchange_trailer: InterchangeTrailer,
+    /// Delimiters declared by the source ISA, re-emitted when writing EDI
+    #[serde(default)]
+    pub delimiters: Delimiters,
+    /// Problems found while parsing: envelope mismatches, invalid values, unprocessed segments
+    #[serde(default, skip_serializing_if = "Diagnostics::is_empty")]
+    pub diagnostics: Diagnostics,
+    /// Segments the parser did not recognize, written back where they appeared
+    #[serde(default, skip_serializing_if = "UnrecognizedSegments::is_empty")]
+    pub unrecognized: UnrecognizedSegments,
 }
 
 /// Parse an EDI 277 file into an Edi277 structure
@@ -36,73 +52,112 @@ pub struct Edi277 {
 /// # Returns
 /// * `EdiResult<Edi277>` - The parsed EDI 277 structure or an error
 pub fn get_277(contents: &str) -> EdiResult<Edi277> {
-    let mut contents = contents.to_string();
-    let interchange_header;
-    let table1s;
-    let loop2000a;
-    let loop2000b_vec;
-    let interchange_trailer;
-    let table1_combined;
-    let se_segment;
-
-    // Remove BOM if present
-    contents = contents.trim_start_matches("\u{feff}").to_string();
-
-    // Remove carriage returns and line feeds
-    contents = contents.replace("\r", "").replace("\n", "");
-
-    // Control Segments
-    (interchange_header, contents) = get_interchange_header(&contents);
-
-    // Table 1
-    (table1s, contents) = get_table1s(&contents);
-
-    // Loop 2000A - Information Source
-    (loop2000a, contents) = get_loop_2000a(&contents);
-
-    // Loop 2000B - Information Receiver
-    (loop2000b_vec, contents) = get_loop_2000b_vec(&contents);
-
-    // Loop 2000C - Service Provider
-    let (loop2000c_vec, new_contents) = get_loop_2000c_vec(&contents);
-    contents = new_contents;
-
-    // Loop 2000D - Subscriber
-    let (loop2000d_vec, new_contents) = get_loop_2000d_vec(&contents);
-    contents = new_contents;
-
-    // Extract SE segment
-    if let Some(se_segment_start) = contents.find("SE*") {
-        let se_segment_end = contents[se_segment_start..]
-            .find('~')
-            .unwrap_or(contents.len() - se_segment_start);
-        se_segment = contents[se_segment_start..se_segment_start + se_segment_end].to_string();
-        contents = contents[se_segment_start + se_segment_end + 1..].to_string();
-    } else {
-        se_segment = String::new();
+    Ok(Edi277::parse(contents.to_string())?.0)
+}
+
+impl TransactionSet for Edi277 {
+    fn parse(mut contents: String) -> EdiResult<(Self, String)> {
+        let interchange_header;
+        let table1s;
+        let mut loop2000a = None;
+        let mut loop2000b_vec = Vec::new();
+        let mut loop2000c_vec = Vec::new();
+        let mut loop2000d_vec = Vec::new();
+        let interchange_trailer;
+        let table1_combined;
+
+        // Remove BOM if present
+        contents = contents.trim_start_matches("\u{feff}").to_string();
+
+        // Capture the source delimiters, then normalize to `*` and `~` and remove line breaks
+        let delimiters = Delimiters::detect(&contents);
+        contents = clean_contents(contents);
+        let normalized = contents.clone();
+
+        // Control Segments
+        (interchange_header, contents) = get_interchange_header(&contents);
+
+        // Table 1
+        (table1s, contents) = get_table1s(&contents);
+
+        // Walk HL segments by level code: 20 = information source (Loop 2000A),
+        // 21 = information receiver (Loop 2000B), 19 = service provider (Loop 2000C),
+        // 22 = subscriber (Loop 2000D). Each HL level runs up to the next HL; the
+        // hierarchy ends at SE.
+        let segments = tokenize(&contents);
+        let body_end = find_segment(&segments, &["SE"], 0).unwrap_or(segments.len());
+        let (leading, levels) = split_loops(&segments[..body_end], &["HL"]);
+        let mut leftover = join_segments(leading);
+
+        for level in levels {
+            let level_contents = join_segments(level);
+            let remaining = match level[0].element(2) {
+                "20" if loop2000a.is_none() => {
+                    let (loop_2000a, remaining) = get_loop_2000a(&level_contents);
+                    loop2000a = Some(loop_2000a);
+                    remaining
+                }
+                "21" => {
+                    let (loop_2000b, remaining) = get_loop_2000b(&level_contents);
+                    loop2000b_vec.push(loop_2000b);
+                    remaining
+                }
+                "19" => {
+                    let (loop_2000c, remaining) = get_loop_2000c(&level_contents);
+                    loop2000c_vec.push(loop_2000c);
+                    remaining
+                }
+                "22" => {
+                    let (loop_2000d, remaining) = get_loop_2000d(&level_contents);
+                    loop2000d_vec.push(loop_2000d);
+                    remaining
+                }
+                // Unknown HL level — keep it with the unprocessed content
+                _ => level_contents,
+            };
+            leftover.push_str(&remaining);
+        }
+
+        // Extract SE segment
+        let se_segment = segments
+            .get(body_end)
+            .map(|se| se.raw.to_string())
+            .unwrap_or_default();
+        contents = join_segments(segments.get(body_end + 1..).unwrap_or_default());
+        contents.push_str(&leftover);
+
+        // Control Trailer
+        (interchange_trailer, contents) = get_interchange_trailer(&contents);
+
+        // Combined Table 1
+        table1_combined = Table1Combined {
+            table1: table1s.clone(),
+        };
+
+        let edi277 = Edi277 {
+            interchange_header,
+            table1_combined,
+            loop2000a: loop2000a.unwrap_or_default(),
+            loop2000b: loop2000b_vec,
+            loop2000c: loop2000c_vec,
+            loop2000d: loop2000d_vec,
+            se_segment,
+            interchange_trailer,
+            delimiters,
+            diagnostics: collect_diagnostics(&normalized, &contents, &delimiters),
+            unrecognized: UnrecognizedSegments::collect(&normalized, &contents),
+        };
+
+        Ok((edi277, contents))
     }
 
-    // Control Trailer
-    (interchange_trailer, contents) = get_interchange_trailer(&contents);
-
-    // Combined Table 1
-    table1_combined = Table1Combined {
-        table1: table1s.clone(),
-    };
-
-    let edi277 = Edi277 {
-        interchange_header,
-        table1_combined,
-        loop2000a,
-        loop2000b: loop2000b_vec,
-        loop2000c: loop2000c_vec,
-        loop2000d: loop2000d_vec,
-        se_segment,
-        interchange_trailer,
-    };
-
-    info!("Unprocessed segments: {:?}", contents);
-    Ok(edi277)
+    fn to_edi_with(&self, options: &WriterOptions) -> EdiResult<String> {
+        write_277_with(self, options)
+    }
+
+    fn get_transaction_type() -> &'static str {
+        "277"
+    }
 }
 
 /// Helper function to fix NM1 segment for 277 format
@@ -154,7 +209,11 @@ fn fix_stc_277(stc: &mut crate::segments::stc::STC) {
 ///
 /// # Returns
 /// * `String` - The generated EDI 277 file contents
-pub fn write_277(edi277: &Edi277) -> String {
+pub fn write_277(edi277: &Edi277) -> EdiResult<String> {
+    write_277_with(edi277, &WriterOptions::default())
+}
+
+pub fn write_277_with(edi277: &Edi277, options: &WriterOptions) -> EdiResult<String> {
     let mut new_edi = String::new();
 
     // Write interchange header (includes trailing newline)
@@ -194,18 +253,28 @@ pub fn write_277(edi277: &Edi277) -> String {
         loop2000b.push(modified_loop);
     }
 
-    // Write Loop 2000B
-    let new_loop2000b = write_loop_2000b_vec(&loop2000b);
-    new_edi.push_str(&new_loop2000b);
-
-    // Write Loop 2000C
+    // HL01 numbers the levels in the order they appear, so write Loops 2000B,
+    // 2000C and 2000D interleaved by it to keep the hierarchy in source order
+    let mut levels = Vec::new();
+    for loop2000b in &loop2000b {
+        levels.push((
+            &loop2000b.hl,
+            write_loop_2000b_vec(std::slice::from_ref(loop2000b)),
+        ));
+    }
     for loop2000c in &edi277.loop2000c {
-        new_edi.push_str(&write_loop_2000c(loop2000c));
+        levels.push((&loop2000c.hl, write_loop_2000c(loop2000c)));
     }
-
-    // Write Loop 2000D
     for loop2000d in &edi277.loop2000d {
-        new_edi.push_str(&write_loop_2000d(loop2000d));
+        levels.push((&loop2000d.hl, write_loop_2000d(loop2000d)));
+    }
+    levels.sort_by_key(|(hl, _)| {
+        hl.hl01_hierarchical_id_number
+            .parse::<u64>()
+            .unwrap_or(u64::MAX)
+    });
+    for (_, level) in levels {
+        new_edi.push_str(&level);
     }
 
     // Write SE segment
@@ -216,8 +285,11 @@ pub fn write_277(edi277: &Edi277) -> String {
     let new_ict = write_interchange_trailer(&edi277.interchange_trailer);
     new_edi.push_str(&new_ict);
 
+    let new_edi = edi277.unrecognized.restore(new_edi, &Delimiters::default());
+    let new_edi = options.format(&new_edi, &edi277.delimiters)?;
+
     info!("Generated EDI 277: {}", new_edi);
-    new_edi
+    Ok(new_edi)
 }
 
 /// Function to detect if JSON contains 277 format data
@@ -307,7 +379,7 @@ mod tests {
         );
 
         // Generate EDI and verify round-trip
-        let generated_edi = write_277(&edi277);
+        let generated_edi = write_277(&edi277).unwrap();
         assert!(generated_edi.contains("ISA*00*"), "Missing ISA segment");
         assert!(
             generated_edi.contains("GS*HN*SENDER*RECEIVER"),
@@ -342,6 +414,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_hl_levels_keep_their_claims() {
+        let sample_edi = "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230501*1200*^*00501*000000001*0*P*:~GS*HN*SENDER*RECEIVER*20230501*1200*1*X*005010X212~ST*277*0001*005010X212~BHT*0010*08*12345*20230501*1200*08~HL*1**20*1~NM1*PR*2*INSURANCE COMPANY*****PI*12345~HL*2*1*21*1~NM1*41*2*CLEARINGHOUSE*****46*67890~HL*3*2*19*1~NM1*1P*2*PROVIDER NAME*****XX*1234567890~HL*4*3*22*0~NM1*IL*1*DOE*JOHN****MI*12345678901~TRN*2*CLAIM123~STC*A1:20*20230501*WQ~REF*1K*111~DTP*472*D8*20230415~TRN*2*CLAIM456~STC*A2:20*20230501*WQ~REF*1K*222~HL*5*2*19*1~NM1*1P*2*OTHER PROVIDER*****XX*2234567890~HL*6*5*22*0~NM1*IL*1*ROE*JANE****MI*22345678901~TRN*2*CLAIM789~STC*A1:20*20230501*WQ~SE*24*0001~GE*1*1~IEA*1*000000001~";
+        let (edi277, remaining) = Edi277::parse(sample_edi.to_string()).unwrap();
+        assert_eq!(edi277.loop2000c.len(), 2);
+        assert_eq!(edi277.loop2000d.len(), 2);
+
+        let subscriber = &edi277.loop2000d[0];
+        assert_eq!(subscriber.trn.reference_id, "CLAIM123");
+        assert_eq!(subscriber.dtp_segments.len(), 1);
+        assert_eq!(subscriber.loop2200d[0].trn.reference_id, "CLAIM456");
+        assert_eq!(subscriber.loop2200d[0].stc_segments.len(), 1);
+        assert_eq!(edi277.loop2000d[1].nm1.lastname, "ROE");
+        assert!(!remaining.contains("HL*"));
+
+        let generated_edi = write_277(&edi277).unwrap();
+        assert_eq!(generated_edi.replace('\n', ""), sample_edi);
+    }
+
     #[test]
     fn test_is_277_json() {
         let valid_json = r#"{"table1_combined":{"table1":{"st01_transaction_set_identifier_code":"277","bht06_transaction_type_code":"08"}}}"#;
@@ -1,3 +1,4 @@
+use crate::helper::tokenizer::{find_segment, join_segments, tokenize};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -14,65 +15,40 @@ pub struct Table1s {
 }
 
 pub fn get_table1s(contents: &str) -> (Table1s, String) {
-    let contents = contents.to_string();
     let mut table1s = Table1s::default();
-    let mut remaining_content = contents.clone();
+    let mut leftover = String::new();
 
-    // Process ST segment
-    if let Some(st_segment_start) = contents.find("ST*") {
-        let st_segment_end = contents[st_segment_start..]
-            .find('~')
-            .unwrap_or(contents.len() - st_segment_start);
-        let st_segment = &contents[st_segment_start..st_segment_start + st_segment_end];
+    // Table 1 runs up to the first HL segment
+    let segments = tokenize(contents);
+    let header_end = find_segment(&segments, &["HL"], 0).unwrap_or(segments.len());
+    let mut st_found = false;
+    let mut bht_found = false;
 
-        let st_elements: Vec<&str> = st_segment.split('*').collect();
-
-        if st_elements.len() >= 3 {
-            table1s.st01_transaction_set_identifier_code = st_elements[1].to_string();
-            table1s.st02_transaction_set_control_number = st_elements[2].to_string();
-            if st_elements.len() >= 4 {
-                table1s.st03_implementation_convention_reference = st_elements[3].to_string();
+    for segment in &segments[..header_end] {
+        match segment.id {
+            // Process ST segment
+            "ST" if !st_found => {
+                st_found = true;
+                table1s.st01_transaction_set_identifier_code = segment.element(0).to_string();
+                table1s.st02_transaction_set_control_number = segment.element(1).to_string();
+                table1s.st03_implementation_convention_reference = segment.element(2).to_string();
             }
+            // Process BHT segment
+            "BHT" if !bht_found => {
+                bht_found = true;
+                table1s.bht01_hierarchical_structure_code = segment.element(0).to_string();
+                table1s.bht02_transaction_set_purpose_code = segment.element(1).to_string();
+                table1s.bht03_reference_identification = segment.element(2).to_string();
+                table1s.bht04_date = segment.element(3).to_string();
+                table1s.bht05_time = segment.element(4).to_string();
+                table1s.bht06_transaction_type_code = segment.element(5).to_string();
+            }
+            _ => leftover.push_str(&segment.to_edi()),
         }
-
-        // Remove the ST segment from the remaining content
-        remaining_content = contents[st_segment_start + st_segment_end + 1..].to_string();
-    }
-
-    // Process BHT segment
-    if let Some(bht_segment_start) = remaining_content.find("BHT") {
-        let bht_segment_end = remaining_content[bht_segment_start..]
-            .find('~')
-            .unwrap_or(remaining_content.len() - bht_segment_start);
-        let bht_segment =
-            &remaining_content[bht_segment_start..bht_segment_start + bht_segment_end];
-
-        let bht_elements: Vec<&str> = bht_segment.split('*').collect();
-
-        if bht_elements.len() > 1 {
-            table1s.bht01_hierarchical_structure_code = bht_elements[1].to_string();
-        }
-        if bht_elements.len() > 2 {
-            table1s.bht02_transaction_set_purpose_code = bht_elements[2].to_string();
-        }
-        if bht_elements.len() > 3 {
-            table1s.bht03_reference_identification = bht_elements[3].to_string();
-        }
-        if bht_elements.len() > 4 {
-            table1s.bht04_date = bht_elements[4].to_string();
-        }
-        if bht_elements.len() > 5 {
-            table1s.bht05_time = bht_elements[5].to_string();
-        }
-        if bht_elements.len() > 6 {
-            table1s.bht06_transaction_type_code = bht_elements[6].to_string();
-        }
-
-        // Remove the BHT segment from the remaining content
-        remaining_content =
-            remaining_content[bht_segment_start + bht_segment_end + 1..].to_string();
     }
 
+    let mut remaining_content = join_segments(&segments[header_end..]);
+    remaining_content.push_str(&leftover);
     (table1s, remaining_content)
 }
 
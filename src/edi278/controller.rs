@@ -19,6 +19,12 @@ use crate::edi278::loop2100f::*;
 use crate::edi278::loop2110e::*;
 use crate::edi278::table1::*;
 use crate::error::EdiResult;
+use crate::helper::delimiters::Delimiters;
+use crate::helper::diagnostics::{collect_diagnostics, Diagnostics};
+use crate::helper::helper::clean_contents;
+use crate::helper::tokenizer::{find_segment, join_segments, split_loops, tokenize};
+use crate::helper::unrecognized::UnrecognizedSegments;
+use crate::helper::writer_options::WriterOptions;
 use crate::transaction_processor::TransactionSet;
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -45,6 +51,15 @@ pub struct Edi278 {
     pub loop2010f: Option<Loop2010F>,
     pub loop2100f: Option<Loop2100F>,
     pub interchange_trailer: InterchangeTrailer,
+    /// Delimiters declared by the source ISA, re-emitted when writing EDI
+    #[serde(default)]
+    pub delimiters: Delimiters,
+    /// Problems found while parsing: envelope mismatches, invalid values, unprocessed segments
+    #[serde(default, skip_serializing_if = "Diagnostics::is_empty")]
+    pub diagnostics: Diagnostics,
+    /// Segments the parser did not recognize, written back where they appeared
+    #[serde(default, skip_serializing_if = "UnrecognizedSegments::is_empty")]
+    pub unrecognized: UnrecognizedSegments,
     pub transaction_set_id: String,
 }
 
@@ -56,8 +71,11 @@ impl TransactionSet for Edi278 {
         // Remove BOM if present
         let mut contents = contents.trim_start_matches("\u{feff}").to_string();
 
-        // Remove carriage returns and line feeds
-        contents = contents.replace("\r", "").replace("\n", "");
+        // Capture the source delimiters, then normalize to `*` and `~` and remove line breaks
+        let delimiters = Delimiters::detect(&contents);
+        contents = clean_contents(contents);
+        let normalized = contents.clone();
+        edi278.delimiters = delimiters;
 
         // Parse interchange control header
         let (interchange_header, new_contents) = get_interchange_header(&contents);
@@ -71,116 +89,123 @@ impl TransactionSet for Edi278 {
         // Combine Table 1
         edi278.table1_combined = Table1Combined { table1: table1s };
 
-        // Parse Loop 2000A (UMO Level)
-        let (loop2000a, new_contents) = get_loop2000a(&contents);
-        if loop2000a.hl_segments.hl01_hierarchical_id_number != "" {
-            edi278.loop2000a = Some(loop2000a);
-            contents = new_contents;
-
-            // Parse Loop 2010A (UMO Name)
-            let (loop2010a, new_contents) = get_loop2010a(&contents);
-            if loop2010a.nm1_segments.entity_id != "" {
-                edi278.loop2010a = Some(loop2010a);
-                contents = new_contents;
-            }
-        }
-
-        // Parse Loop 2000B (Requester Level)
-        let (loop2000b, new_contents) = get_loop2000b(&contents);
-        if loop2000b.hl_segments.hl01_hierarchical_id_number != "" {
-            edi278.loop2000b = Some(loop2000b);
-            contents = new_contents;
-
-            // Parse Loop 2010B (Requester Name)
-            let (loop2010b, new_contents) = get_loop2010b(&contents);
-            if loop2010b.nm1_segments.entity_id != "" {
-                edi278.loop2010b = Some(loop2010b);
-                contents = new_contents;
-            }
-        }
-
-        // Parse Loop 2000C (Subscriber Level)
-        let (loop2000c, new_contents) = get_loop2000c(&contents);
-        if loop2000c.hl_segments.hl01_hierarchical_id_number != "" {
-            edi278.loop2000c = Some(loop2000c);
-            contents = new_contents;
-
-            // Parse Loop 2010C (Subscriber Name)
-            let (loop2010c, new_contents) = get_loop2010c(&contents);
-            if loop2010c.nm1_segments.entity_id != "" {
-                edi278.loop2010c = Some(loop2010c);
-                contents = new_contents;
-            }
-        }
-
-        // Parse Loop 2000D (Dependent Level)
-        let (loop2000d, new_contents) = get_loop2000d(&contents);
-        if loop2000d.hl_segments.hl01_hierarchical_id_number != "" {
-            edi278.loop2000d = Some(loop2000d);
-            contents = new_contents;
-
-            // Parse Loop 2010D (Dependent Name)
-            let (loop2010d, new_contents) = get_loop2010d(&contents);
-            if loop2010d.nm1_segments.entity_id != "" {
-                edi278.loop2010d = Some(loop2010d);
-                contents = new_contents;
-            }
-        }
-
-        // Parse Loop 2000E (Service Level)
-        let (loop2000e, new_contents) = get_loop2000e(&contents);
-        if loop2000e.hl_segments.hl01_hierarchical_id_number != "" {
-            edi278.loop2000e = Some(loop2000e);
-            contents = new_contents;
-
-            // Parse Loop 2100E (Service Level Detail)
-            let (loop2100e, new_contents) = get_loop2100e(&contents);
-            if !loop2100e.dtp_segments.is_empty()
-                || loop2100e.hi_segments.is_some()
-                || loop2100e.hsd_segments.is_some()
-                || loop2100e.cl1_segments.is_some()
-            {
-                edi278.loop2100e = Some(loop2100e);
-                contents = new_contents;
-            }
-
-            // Parse Loop 2110E (Service Provider)
-            let (loop2110e, new_contents) = get_loop2110e(&contents);
-            if loop2110e.nm1_segments.entity_id != "" {
-                edi278.loop2110e = Some(loop2110e);
-                contents = new_contents;
-            }
-        }
-
-        // Parse Loop 2000F (Service Provider Level)
-        let (loop2000f, new_contents) = get_loop2000f(&contents);
-        if loop2000f.hl_segments.hl01_hierarchical_id_number != "" {
-            edi278.loop2000f = Some(loop2000f);
-            contents = new_contents;
-
-            // Parse Loop 2010F (Service Provider Name)
-            let (loop2010f, new_contents) = get_loop2010f(&contents);
-            if loop2010f.nm1_segments.entity_id != "" {
-                edi278.loop2010f = Some(loop2010f);
-                contents = new_contents;
-            }
-
-            // Parse Loop 2100F (Service Provider Detail)
-            let (loop2100f, new_contents) = get_loop2100f(&contents);
-            if !loop2100f.dtp_segments.is_empty() || loop2100f.sv2_segments.is_some() {
-                edi278.loop2100f = Some(loop2100f);
-                contents = new_contents;
-            }
+        // Walk HL segments by level code (HL03). Each HL level runs up to the
+        // next HL; the hierarchy ends at SE. The model holds one level of each kind.
+        let segments = tokenize(&contents);
+        let body_end = find_segment(&segments, &["SE"], 0).unwrap_or(segments.len());
+        let (leading, levels) = split_loops(&segments[..body_end], &["HL"]);
+        let mut leftover = join_segments(leading);
+
+        for level in levels {
+            let level_contents = join_segments(level);
+            let remaining = match level[0].element(2) {
+                "20" if edi278.loop2000a.is_none() => {
+                    // Parse Loop 2000A (UMO Level)
+                    let (loop2000a, remaining) = get_loop2000a(&level_contents);
+                    edi278.loop2000a = Some(loop2000a);
+
+                    // Parse Loop 2010A (UMO Name)
+                    let (loop2010a, remaining) = get_loop2010a(&remaining);
+                    if !loop2010a.nm1_segments.entity_id.is_empty() {
+                        edi278.loop2010a = Some(loop2010a);
+                    }
+                    remaining
+                }
+                "21" if edi278.loop2000b.is_none() => {
+                    // Parse Loop 2000B (Requester Level)
+                    let (loop2000b, remaining) = get_loop2000b(&level_contents);
+                    edi278.loop2000b = Some(loop2000b);
+
+                    // Parse Loop 2010B (Requester Name)
+                    let (loop2010b, remaining) = get_loop2010b(&remaining);
+                    if !loop2010b.nm1_segments.entity_id.is_empty() {
+                        edi278.loop2010b = Some(loop2010b);
+                    }
+                    remaining
+                }
+                "22" if edi278.loop2000c.is_none() => {
+                    // Parse Loop 2000C (Subscriber Level)
+                    let (loop2000c, remaining) = get_loop2000c(&level_contents);
+                    edi278.loop2000c = Some(loop2000c);
+
+                    // Parse Loop 2010C (Subscriber Name)
+                    let (loop2010c, remaining) = get_loop2010c(&remaining);
+                    if !loop2010c.nm1_segments.entity_id.is_empty() {
+                        edi278.loop2010c = Some(loop2010c);
+                    }
+                    remaining
+                }
+                "23" if edi278.loop2000d.is_none() => {
+                    // Parse Loop 2000D (Dependent Level)
+                    let (loop2000d, remaining) = get_loop2000d(&level_contents);
+                    edi278.loop2000d = Some(loop2000d);
+
+                    // Parse Loop 2010D (Dependent Name)
+                    let (loop2010d, remaining) = get_loop2010d(&remaining);
+                    if !loop2010d.nm1_segments.entity_id.is_empty() {
+                        edi278.loop2010d = Some(loop2010d);
+                    }
+                    remaining
+                }
+                "EV" | "SS" if edi278.loop2000e.is_none() => {
+                    // Parse Loop 2000E (Service Level)
+                    let (loop2000e, remaining) = get_loop2000e(&level_contents);
+                    edi278.loop2000e = Some(loop2000e);
+
+                    // Parse Loop 2100E (Service Level Detail)
+                    let (loop2100e, remaining) = get_loop2100e(&remaining);
+                    if !loop2100e.dtp_segments.is_empty()
+                        || loop2100e.hi_segments.is_some()
+                        || loop2100e.hsd_segments.is_some()
+                        || loop2100e.cl1_segments.is_some()
+                    {
+                        edi278.loop2100e = Some(loop2100e);
+                    }
+
+                    // Parse Loop 2110E (Service Provider)
+                    let (loop2110e, remaining) = get_loop2110e(&remaining);
+                    if !loop2110e.nm1_segments.entity_id.is_empty() {
+                        edi278.loop2110e = Some(loop2110e);
+                    }
+                    remaining
+                }
+                "PT" | "SS" if edi278.loop2000f.is_none() => {
+                    // Parse Loop 2000F (Service Provider Level)
+                    let (loop2000f, remaining) = get_loop2000f(&level_contents);
+                    edi278.loop2000f = Some(loop2000f);
+
+                    // Parse Loop 2010F (Service Provider Name)
+                    let (loop2010f, remaining) = get_loop2010f(&remaining);
+                    if !loop2010f.nm1_segments.entity_id.is_empty() {
+                        edi278.loop2010f = Some(loop2010f);
+                    }
+
+                    // Parse Loop 2100F (Service Provider Detail)
+                    let (loop2100f, remaining) = get_loop2100f(&remaining);
+                    if !loop2100f.dtp_segments.is_empty() || loop2100f.sv2_segments.is_some() {
+                        edi278.loop2100f = Some(loop2100f);
+                    }
+                    remaining
+                }
+                // Unknown HL level, orphan, or a second level the model cannot hold —
+                // keep it with the unprocessed content
+                _ => level_contents,
+            };
+            leftover.push_str(&remaining);
         }
+        let mut contents = join_segments(&segments[body_end..]);
+        contents.push_str(&leftover);
 
         // Parse interchange control trailer
         let (interchange_trailer, remaining) = get_interchange_trailer(&contents);
         edi278.interchange_trailer = interchange_trailer;
 
+        edi278.diagnostics = collect_diagnostics(&normalized, &remaining, &delimiters);
+        edi278.unrecognized = UnrecognizedSegments::collect(&normalized, &remaining);
         Ok((edi278, remaining))
     }
 
-    fn to_edi(&self) -> String {
+    fn to_edi_with(&self, options: &WriterOptions) -> EdiResult<String> {
         let mut new_edi = String::new();
 
         // Write interchange header
@@ -262,11 +287,11 @@ impl TransactionSet for Edi278 {
         // Write interchange trailer
         new_edi.push_str(&write_interchange_trailer(self.interchange_trailer.clone()));
 
-        // Add line breaks between segments for better readability
-        let new_edi_with_breaks = new_edi.replace("~", "~\n");
+        let new_edi = self.unrecognized.restore(new_edi, &Delimiters::default());
+        let new_edi = options.format(&new_edi, &self.delimiters)?;
 
-        info!("Generated EDI 278: {}", new_edi_with_breaks);
-        new_edi_with_breaks
+        info!("Generated EDI 278: {}", new_edi);
+        Ok(new_edi)
     }
 
     fn get_transaction_type() -> &'static str {
@@ -285,10 +310,14 @@ pub fn get_278(contents: &str) -> EdiResult<Edi278> {
     }
 }
 
-pub fn write_278(edi278: &Edi278) -> String {
+pub fn write_278(edi278: &Edi278) -> EdiResult<String> {
     edi278.to_edi()
 }
 
+pub fn write_278_with(edi278: &Edi278, options: &WriterOptions) -> EdiResult<String> {
+    edi278.to_edi_with(options)
+}
+
 // Function to detect if content is EDI 278 format
 pub fn is_278(contents: &str) -> bool {
     // Check if the content contains ST*278 which indicates an EDI 278 transaction set
@@ -548,11 +577,27 @@ IEA*1*000000001~";
                 .sv2_segments
                 .as_ref()
                 .unwrap()
-                .sv202_procedure_code,
-            "HC:33510"
+                .sv202_procedure_code
+                .c003_02_product_or_service_id,
+            "33510"
         );
     }
 
+    #[test]
+    fn test_segments_stay_with_their_level() {
+        let content = "ISA*00*          *00*          *ZZ*SUBMITTER      *ZZ*RECEIVER       *200101*1200*^*00501*000000001*0*T*:~GS*HI*SUBMITTER*RECEIVER*20200101*1200*1*X*005010X217~ST*278*0001*005010X217~BHT*0007*11*123456*20060501*1319~HL*1**20*1~NM1*X3*2*UMO NAME*****PI*12345~HL*2*1*21*1~NM1*1P*2*BONE AND JOINT CLINIC*****SV*2000035~PER*IC*CONTACT NAME*TE*5551234567~HL*3*2*22*1~NM1*IL*1*DOE*JOHN****MI*123456789A~HL*4*2*22*0~NM1*IL*1*ROE*JANE****MI*223456789A~SE*11*0001~GE*1*1~IEA*1*000000001~";
+
+        let (edi278, remaining) = Edi278::parse(content.to_string()).unwrap();
+
+        // The requester's PER is not pulled up into the UMO name
+        assert!(edi278.loop2010a.unwrap().per_segments.is_empty());
+        assert_eq!(edi278.loop2010b.unwrap().per_segments.len(), 1);
+        assert_eq!(edi278.loop2010c.unwrap().nm1_segments.lastname, "DOE");
+
+        // The model holds one subscriber, so the second is left unprocessed
+        assert!(remaining.contains("HL*4*2*22*0~NM1*IL*1*ROE*JANE"));
+    }
+
     #[test]
     fn test_roundtrip_278() {
         let content = "ISA*00*          *00*          *ZZ*SUBMITTER      *ZZ*RECEIVER       *200101*1200*^*00501*000000001*0*T*:~
@@ -576,7 +621,7 @@ IEA*1*000000001~";
         let edi278 = result.unwrap();
 
         // Generate EDI from the parsed object
-        let generated = write_278(&edi278);
+        let generated = write_278(&edi278).unwrap();
 
         // Check that the generated content contains the expected segments
         assert!(generated.contains(
@@ -1,7 +1,7 @@
 use log::info;
 use serde::{Deserialize, Serialize};
 
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{join_segments, tokenize};
 use crate::segments::hl::*;
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -10,28 +10,26 @@ pub struct Loop2000A {
 }
 
 pub fn get_loop2000a(contents: &str) -> (Loop2000A, String) {
-    let mut contents = contents.to_string();
     let mut hl_segments = HL::default();
-
-    if contents.contains("HL*") {
-        // Check if this is a UMO Level HL segment (HL03=20)
-        let hl_content = get_segment_contents("HL", &contents);
-        let hl_parts: Vec<&str> = hl_content.split('*').collect();
-
-        if hl_parts.len() > 3 && hl_parts[2] == "20" {
-            info!("HL segment found for UMO Level, ");
-            hl_segments = get_hl(hl_content);
-            info!("HL segment parsed");
-
-            contents = content_trim("HL", &contents);
-        }
+    let mut consumed = 0;
+
+    // Loop 2000A starts with a UMO Level HL segment (HL03=20)
+    let segments = tokenize(contents);
+    if let Some(hl) = segments
+        .first()
+        .filter(|segment| segment.id == "HL" && segment.element(2) == "20")
+    {
+        info!("HL segment found for UMO Level, ");
+        hl_segments = get_hl(hl.elements.join("*"));
+        info!("HL segment parsed");
+        consumed = 1;
     }
 
     info!("Loop 2000A parsed\n");
 
     let loop2000a = Loop2000A { hl_segments };
 
-    return (loop2000a, contents);
+    return (loop2000a, join_segments(&segments[consumed..]));
 }
 
 pub fn write_loop2000a(loop2000a: Loop2000A) -> String {
@@ -1,7 +1,7 @@
 use log::info;
 use serde::{Deserialize, Serialize};
 
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{join_segments, tokenize};
 use crate::segments::hl::*;
 use crate::segments::trn::*;
 
@@ -12,31 +12,27 @@ pub struct Loop2000D {
 }
 
 pub fn get_loop2000d(contents: &str) -> (Loop2000D, String) {
-    let mut contents = contents.to_string();
     let mut hl_segments = HL::default();
     let mut trn_segments = Vec::new();
-
-    if contents.contains("HL*") {
-        // Check if this is a Dependent Level HL segment (HL03=23)
-        let hl_content = get_segment_contents("HL", &contents);
-        let hl_parts: Vec<&str> = hl_content.split('*').collect();
-
-        if hl_parts.len() > 3 && hl_parts[2] == "23" {
-            info!("HL segment found for Dependent Level, ");
-            hl_segments = get_hl(hl_content);
-            info!("HL segment parsed");
-
-            contents = content_trim("HL", &contents);
-
-            // Parse TRN segments
-            while contents.contains("TRN") && check_if_segment_in_loop("TRN", "NM1", &contents) {
-                info!("TRN segment found, ");
-                let trn_segment = get_trn(get_segment_contents("TRN", &contents));
-                info!("TRN segment parsed");
-
-                trn_segments.push(trn_segment);
-                contents = content_trim("TRN", &contents);
-            }
+    let mut consumed = 0;
+
+    // Loop 2000D starts with a Dependent Level HL segment (HL03=23)
+    let segments = tokenize(contents);
+    if let Some(hl) = segments
+        .first()
+        .filter(|segment| segment.id == "HL" && segment.element(2) == "23")
+    {
+        info!("HL segment found for Dependent Level, ");
+        hl_segments = get_hl(hl.elements.join("*"));
+        info!("HL segment parsed");
+        consumed = 1;
+
+        // Parse TRN segments
+        while let Some(trn) = segments.get(consumed).filter(|segment| segment.id == "TRN") {
+            info!("TRN segment found, ");
+            trn_segments.push(get_trn(trn.elements.join("*")));
+            info!("TRN segment parsed");
+            consumed += 1;
         }
     }
 
@@ -47,7 +43,7 @@ pub fn get_loop2000d(contents: &str) -> (Loop2000D, String) {
         trn_segments,
     };
 
-    return (loop2000d, contents);
+    return (loop2000d, join_segments(&segments[consumed..]));
 }
 
 pub fn write_loop2000d(loop2000d: Loop2000D) -> String {
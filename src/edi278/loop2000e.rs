@@ -1,7 +1,7 @@
 use log::info;
 use serde::{Deserialize, Serialize};
 
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{join_segments, tokenize};
 use crate::segments::hl::*;
 use crate::segments::trn::*;
 use crate::segments::um::*;
@@ -14,42 +14,38 @@ pub struct Loop2000E {
 }
 
 pub fn get_loop2000e(contents: &str) -> (Loop2000E, String) {
-    let mut contents = contents.to_string();
     let mut hl_segments = HL::default();
     let mut trn_segments = Vec::new();
     let mut um_segments = None;
-
-    if contents.contains("HL*") {
-        // Check if this is a Service Level HL segment (HL03=EV or HL03=SS)
-        let hl_content = get_segment_contents("HL", &contents);
-        let hl_parts: Vec<&str> = hl_content.split('*').collect();
-
-        if hl_parts.len() > 3 && (hl_parts[2] == "EV" || hl_parts[2] == "SS") {
-            info!("HL segment found for Service Level, ");
-            hl_segments = get_hl(hl_content);
-            info!("HL segment parsed");
-
-            contents = content_trim("HL", &contents);
-
-            // Parse TRN segments
-            while contents.contains("TRN") && check_if_segment_in_loop("TRN", "UM", &contents) {
-                info!("TRN segment found, ");
-                let trn_segment = get_trn(get_segment_contents("TRN", &contents));
-                info!("TRN segment parsed");
-
-                trn_segments.push(trn_segment);
-                contents = content_trim("TRN", &contents);
-            }
-
-            // Parse UM segment
-            if contents.contains("UM") {
-                info!("UM segment found, ");
-                let um_content = get_segment_contents("UM", &contents);
-                um_segments = Some(get_um(um_content));
-                info!("Parsed UM segment: {:?}", um_segments);
-
-                contents = content_trim("UM", &contents);
+    let mut consumed = 0;
+
+    // Loop 2000E starts with a Service Level HL segment (HL03=EV or HL03=SS)
+    let segments = tokenize(contents);
+    if let Some(hl) = segments.first().filter(|segment| {
+        segment.id == "HL" && (segment.element(2) == "EV" || segment.element(2) == "SS")
+    }) {
+        info!("HL segment found for Service Level, ");
+        hl_segments = get_hl(hl.elements.join("*"));
+        info!("HL segment parsed");
+        consumed = 1;
+
+        for segment in &segments[1..] {
+            match segment.id {
+                // Parse TRN segments
+                "TRN" if um_segments.is_none() => {
+                    info!("TRN segment found, ");
+                    trn_segments.push(get_trn(segment.elements.join("*")));
+                    info!("TRN segment parsed");
+                }
+                // Parse UM segment
+                "UM" if um_segments.is_none() => {
+                    info!("UM segment found, ");
+                    um_segments = Some(get_um(segment.elements.join("*")));
+                    info!("Parsed UM segment: {:?}", um_segments);
+                }
+                _ => break,
             }
+            consumed += 1;
         }
     }
 
@@ -61,7 +57,7 @@ pub fn get_loop2000e(contents: &str) -> (Loop2000E, String) {
         um_segments,
     };
 
-    return (loop2000e, contents);
+    return (loop2000e, join_segments(&segments[consumed..]));
 }
 
 pub fn write_loop2000e(loop2000e: Loop2000E) -> String {
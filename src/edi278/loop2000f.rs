@@ -1,7 +1,7 @@
 use log::info;
 use serde::{Deserialize, Serialize};
 
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{join_segments, tokenize};
 use crate::segments::hl::*;
 use crate::segments::um::*;
 
@@ -12,31 +12,26 @@ pub struct Loop2000F {
 }
 
 pub fn get_loop2000f(contents: &str) -> (Loop2000F, String) {
-    let mut contents = contents.to_string();
     let mut hl_segments = HL::default();
     let mut um_segments = None;
-
-    if contents.contains("HL*") {
-        // Check if this is a Service Provider Level HL segment (HL03=PT or HL03=SS)
-        let hl_content = get_segment_contents("HL", &contents);
-        let hl_parts: Vec<&str> = hl_content.split('*').collect();
-
-        if hl_parts.len() > 3 && (hl_parts[2] == "PT" || hl_parts[2] == "SS") {
-            info!("HL segment found for Service Provider Level, ");
-            hl_segments = get_hl(hl_content);
-            info!("HL segment parsed");
-
-            contents = content_trim("HL", &contents);
-
-            // Parse UM segment
-            if contents.contains("UM") {
-                info!("UM segment found, ");
-                let um_content = get_segment_contents("UM", &contents);
-                um_segments = Some(get_um(um_content));
-                info!("Parsed UM segment: {:?}", um_segments);
-
-                contents = content_trim("UM", &contents);
-            }
+    let mut consumed = 0;
+
+    // Loop 2000F starts with a Service Provider Level HL segment (HL03=PT or HL03=SS)
+    let segments = tokenize(contents);
+    if let Some(hl) = segments.first().filter(|segment| {
+        segment.id == "HL" && (segment.element(2) == "PT" || segment.element(2) == "SS")
+    }) {
+        info!("HL segment found for Service Provider Level, ");
+        hl_segments = get_hl(hl.elements.join("*"));
+        info!("HL segment parsed");
+        consumed = 1;
+
+        // Parse UM segment
+        if let Some(um) = segments.get(consumed).filter(|segment| segment.id == "UM") {
+            info!("UM segment found, ");
+            um_segments = Some(get_um(um.elements.join("*")));
+            info!("Parsed UM segment: {:?}", um_segments);
+            consumed += 1;
         }
     }
 
@@ -47,7 +42,7 @@ pub fn get_loop2000f(contents: &str) -> (Loop2000F, String) {
         um_segments,
     };
 
-    return (loop2000f, contents);
+    return (loop2000f, join_segments(&segments[consumed..]));
 }
 
 pub fn write_loop2000f(loop2000f: Loop2000F) -> String {
@@ -1,7 +1,7 @@
 use log::info;
 use serde::{Deserialize, Serialize};
 
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{join_segments, tokenize};
 use crate::segments::nm1::*;
 use crate::segments::per::*;
 
@@ -12,36 +12,27 @@ pub struct Loop2010A {
 }
 
 pub fn get_loop2010a(contents: &str) -> (Loop2010A, String) {
-    let mut contents = contents.to_string();
     let mut nm1_segments = NM1::default();
     let mut per_segments = Vec::new();
-
-    if contents.contains("NM1") {
-        // Check if this is a UMO Name NM1 segment (NM101=X3)
-        let nm1_content = get_segment_contents("NM1", &contents);
-        let nm1_parts: Vec<&str> = nm1_content.split('*').collect();
-
-        if nm1_parts.len() > 1 && nm1_parts[0] == "X3" {
-            info!("NM1 segment found for UMO Name, ");
-            nm1_segments = get_nm1(nm1_content);
-            info!("NM1 segment parsed");
-
-            contents = content_trim("NM1", &contents);
-
-            // Parse PER segments
-            while contents.contains("PER") {
-                info!("PER segment found, ");
-                let per_segment = get_per(get_segment_contents("PER", &contents));
-                info!("PER segment parsed");
-
-                per_segments.push(per_segment);
-                contents = content_trim("PER", &contents);
-
-                // Check if the next segment is not a PER segment
-                if !contents.starts_with("PER*") {
-                    break;
-                }
-            }
+    let mut consumed = 0;
+
+    // Loop 2010A starts with a UMO Name NM1 segment (NM101=X3)
+    let segments = tokenize(contents);
+    if let Some(nm1) = segments
+        .first()
+        .filter(|segment| segment.id == "NM1" && segment.element(0) == "X3")
+    {
+        info!("NM1 segment found for UMO Name, ");
+        nm1_segments = get_nm1(nm1.elements.join("*"));
+        info!("NM1 segment parsed");
+        consumed = 1;
+
+        // Parse PER segments
+        while let Some(per) = segments.get(consumed).filter(|segment| segment.id == "PER") {
+            info!("PER segment found, ");
+            per_segments.push(get_per(per.elements.join("*")));
+            info!("PER segment parsed");
+            consumed += 1;
         }
     }
 
@@ -52,7 +43,7 @@ pub fn get_loop2010a(contents: &str) -> (Loop2010A, String) {
         per_segments,
     };
 
-    return (loop2010a, contents);
+    return (loop2010a, join_segments(&segments[consumed..]));
 }
 
 pub fn write_loop2010a(loop2010a: Loop2010A) -> String {
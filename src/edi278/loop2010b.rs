@@ -1,7 +1,7 @@
 use log::info;
 use serde::{Deserialize, Serialize};
 
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{join_segments, tokenize};
 use crate::segments::nm1::*;
 use crate::segments::per::*;
 use crate::segments::r#ref::*;
@@ -14,42 +14,38 @@ pub struct Loop2010B {
 }
 
 pub fn get_loop2010b(contents: &str) -> (Loop2010B, String) {
-    let mut contents = contents.to_string();
     let mut nm1_segments = NM1::default();
     let mut per_segments = Vec::new();
     let mut ref_segments = Vec::new();
-
-    if contents.contains("NM1") {
-        // Check if this is a Requester Name NM1 segment (NM101=1P)
-        let nm1_content = get_segment_contents("NM1", &contents);
-        let nm1_parts: Vec<&str> = nm1_content.split('*').collect();
-
-        if nm1_parts.len() > 1 && (nm1_parts[0] == "1P" || nm1_parts[0] == "FA") {
-            info!("NM1 segment found for Requester Name, ");
-            nm1_segments = get_nm1(nm1_content);
-            info!("NM1 segment parsed");
-
-            contents = content_trim("NM1", &contents);
-
-            // Parse PER segments
-            while contents.contains("PER") && check_if_segment_in_loop("PER", "HL", &contents) {
-                info!("PER segment found, ");
-                let per_segment = get_per(get_segment_contents("PER", &contents));
-                info!("PER segment parsed");
-
-                per_segments.push(per_segment);
-                contents = content_trim("PER", &contents);
-            }
-
-            // Parse REF segments
-            while contents.contains("REF") && check_if_segment_in_loop("REF", "HL", &contents) {
-                info!("REF segment found, ");
-                let ref_segment = get_ref(get_segment_contents("REF", &contents));
-                info!("REF segment parsed");
-
-                ref_segments.push(ref_segment);
-                contents = content_trim("REF", &contents);
+    let mut consumed = 0;
+
+    // Loop 2010B starts with a Requester Name NM1 segment (NM101=1P or NM101=FA)
+    let segments = tokenize(contents);
+    if let Some(nm1) = segments.first().filter(|segment| {
+        segment.id == "NM1" && (segment.element(0) == "1P" || segment.element(0) == "FA")
+    }) {
+        info!("NM1 segment found for Requester Name, ");
+        nm1_segments = get_nm1(nm1.elements.join("*"));
+        info!("NM1 segment parsed");
+        consumed = 1;
+
+        for segment in &segments[1..] {
+            match segment.id {
+                // Parse PER segments
+                "PER" => {
+                    info!("PER segment found, ");
+                    per_segments.push(get_per(segment.elements.join("*")));
+                    info!("PER segment parsed");
+                }
+                // Parse REF segments
+                "REF" => {
+                    info!("REF segment found, ");
+                    ref_segments.push(get_ref(segment.elements.join("*")));
+                    info!("REF segment parsed");
+                }
+                _ => break,
             }
+            consumed += 1;
         }
     }
 
@@ -61,7 +57,7 @@ pub fn get_loop2010b(contents: &str) -> (Loop2010B, String) {
         ref_segments,
     };
 
-    return (loop2010b, contents);
+    return (loop2010b, join_segments(&segments[consumed..]));
 }
 
 pub fn write_loop2010b(loop2010b: Loop2010B) -> String {
@@ -123,6 +119,7 @@ mod tests {
             ref_segments: vec![REF {
                 reference_id_number_qualifier: "XZ".to_string(),
                 reference_id_number: "7654321".to_string(),
+                ..Default::default()
             }],
         };
 
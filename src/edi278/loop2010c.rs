@@ -1,7 +1,7 @@
 use log::info;
 use serde::{Deserialize, Serialize};
 
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{join_segments, tokenize};
 use crate::segments::dmg::*;
 use crate::segments::nm1::*;
 use crate::segments::r#ref::*;
@@ -14,44 +14,39 @@ pub struct Loop2010C {
 }
 
 pub fn get_loop2010c(contents: &str) -> (Loop2010C, String) {
-    let mut contents = contents.to_string();
     let mut nm1_segments = NM1::default();
     let mut ref_segments = Vec::new();
     let mut dmg_segments = None;
-
-    if contents.contains("NM1") {
-        // Check if this is a Subscriber Name NM1 segment (NM101=IL)
-        let nm1_content = get_segment_contents("NM1", &contents);
-        let nm1_parts: Vec<&str> = nm1_content.split('*').collect();
-
-        if nm1_parts.len() > 1 && nm1_parts[0] == "IL" {
-            info!("NM1 segment found for Subscriber Name, ");
-            nm1_segments = get_nm1(nm1_content);
-            info!("NM1 segment parsed");
-
-            contents = content_trim("NM1", &contents);
-
-            // Parse REF segments
-            while contents.contains("REF")
-                && check_if_segment_in_loop("REF", "DMG", &contents)
-                && check_if_segment_in_loop("REF", "HL", &contents)
-            {
-                info!("REF segment found, ");
-                let ref_segment = get_ref(get_segment_contents("REF", &contents));
-                info!("REF segment parsed");
-
-                ref_segments.push(ref_segment);
-                contents = content_trim("REF", &contents);
-            }
-
-            // Parse DMG segment
-            if contents.contains("DMG") && check_if_segment_in_loop("DMG", "HL", &contents) {
-                info!("DMG segment found, ");
-                dmg_segments = Some(get_dmg(get_segment_contents("DMG", &contents)));
-                info!("DMG segment parsed");
-
-                contents = content_trim("DMG", &contents);
+    let mut consumed = 0;
+
+    // Loop 2010C starts with a Subscriber Name NM1 segment (NM101=IL)
+    let segments = tokenize(contents);
+    if let Some(nm1) = segments
+        .first()
+        .filter(|segment| segment.id == "NM1" && segment.element(0) == "IL")
+    {
+        info!("NM1 segment found for Subscriber Name, ");
+        nm1_segments = get_nm1(nm1.elements.join("*"));
+        info!("NM1 segment parsed");
+        consumed = 1;
+
+        for segment in &segments[1..] {
+            match segment.id {
+                // Parse REF segments
+                "REF" if dmg_segments.is_none() => {
+                    info!("REF segment found, ");
+                    ref_segments.push(get_ref(segment.elements.join("*")));
+                    info!("REF segment parsed");
+                }
+                // Parse DMG segment
+                "DMG" if dmg_segments.is_none() => {
+                    info!("DMG segment found, ");
+                    dmg_segments = Some(get_dmg(segment.elements.join("*")));
+                    info!("DMG segment parsed");
+                }
+                _ => break,
             }
+            consumed += 1;
         }
     }
 
@@ -63,7 +58,7 @@ pub fn get_loop2010c(contents: &str) -> (Loop2010C, String) {
         dmg_segments,
     };
 
-    return (loop2010c, contents);
+    return (loop2010c, join_segments(&segments[consumed..]));
 }
 
 pub fn write_loop2010c(loop2010c: Loop2010C) -> String {
@@ -131,6 +126,7 @@ mod tests {
             ref_segments: vec![REF {
                 reference_id_number_qualifier: "SY".to_string(),
                 reference_id_number: "123456789".to_string(),
+                ..Default::default()
             }],
             dmg_segments: Some(DMG {
                 dmg01_date_time_qualifier: "D8".to_string(),
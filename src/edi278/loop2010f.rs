@@ -1,7 +1,7 @@
 use log::info;
 use serde::{Deserialize, Serialize};
 
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{join_segments, tokenize};
 use crate::segments::nm1::*;
 use crate::segments::prv::*;
 use crate::segments::r#ref::*;
@@ -14,49 +14,38 @@ pub struct Loop2010F {
 }
 
 pub fn get_loop2010f(contents: &str) -> (Loop2010F, String) {
-    let mut contents = contents.to_string();
     let mut nm1_segments = NM1::default();
     let mut ref_segments = Vec::new();
     let mut prv_segments = None;
-
-    if contents.contains("NM1") {
-        // Check if this is a Service Provider NM1 segment (NM101=SJ)
-        let nm1_content = get_segment_contents("NM1", &contents);
-        let nm1_parts: Vec<&str> = nm1_content.split('*').collect();
-
-        if nm1_parts.len() > 1 && (nm1_parts[0] == "SJ" || nm1_parts[0] == "1P") {
-            info!("NM1 segment found for Service Provider Name, ");
-            nm1_segments = get_nm1(nm1_content);
-            info!("NM1 segment parsed");
-
-            contents = content_trim("NM1", &contents);
-
-            // Parse REF segments
-            while contents.contains("REF")
-                && check_if_segment_in_loop("REF", "PRV", &contents)
-                && check_if_segment_in_loop("REF", "HL", &contents)
-                && check_if_segment_in_loop("REF", "SE", &contents)
-            {
-                info!("REF segment found, ");
-                let ref_segment = get_ref(get_segment_contents("REF", &contents));
-                info!("REF segment parsed");
-
-                ref_segments.push(ref_segment);
-                contents = content_trim("REF", &contents);
-            }
-
-            // Parse PRV segment
-            if contents.contains("PRV")
-                && check_if_segment_in_loop("PRV", "HL", &contents)
-                && check_if_segment_in_loop("PRV", "SE", &contents)
-            {
-                info!("PRV segment found, ");
-                let prv_content = get_segment_contents("PRV", &contents);
-                prv_segments = Some(get_prv(&prv_content));
-                info!("PRV segment parsed");
-
-                contents = content_trim("PRV", &contents);
+    let mut consumed = 0;
+
+    // Loop 2010F starts with a Service Provider NM1 segment (NM101=SJ or NM101=1P)
+    let segments = tokenize(contents);
+    if let Some(nm1) = segments.first().filter(|segment| {
+        segment.id == "NM1" && (segment.element(0) == "SJ" || segment.element(0) == "1P")
+    }) {
+        info!("NM1 segment found for Service Provider Name, ");
+        nm1_segments = get_nm1(nm1.elements.join("*"));
+        info!("NM1 segment parsed");
+        consumed = 1;
+
+        for segment in &segments[1..] {
+            match segment.id {
+                // Parse REF segments
+                "REF" if prv_segments.is_none() => {
+                    info!("REF segment found, ");
+                    ref_segments.push(get_ref(segment.elements.join("*")));
+                    info!("REF segment parsed");
+                }
+                // Parse PRV segment
+                "PRV" if prv_segments.is_none() => {
+                    info!("PRV segment found, ");
+                    prv_segments = Some(get_prv(&segment.elements.join("*")));
+                    info!("PRV segment parsed");
+                }
+                _ => break,
             }
+            consumed += 1;
         }
     }
 
@@ -68,7 +57,7 @@ pub fn get_loop2010f(contents: &str) -> (Loop2010F, String) {
         prv_segments,
     };
 
-    return (loop2010f, contents);
+    return (loop2010f, join_segments(&segments[consumed..]));
 }
 
 pub fn write_loop2010f(loop2010f: Loop2010F) -> String {
@@ -1,7 +1,7 @@
 use log::info;
 use serde::{Deserialize, Serialize};
 
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{join_segments, tokenize};
 use crate::segments::cl1::*;
 use crate::segments::dtp::*;
 use crate::segments::hi::*;
@@ -16,59 +16,43 @@ pub struct Loop2100E {
 }
 
 pub fn get_loop2100e(contents: &str) -> (Loop2100E, String) {
-    let mut contents = contents.to_string();
     let mut dtp_segments = Vec::new();
     let mut hi_segments = None;
     let mut hsd_segments = None;
     let mut cl1_segments = None;
-
-    // Parse DTP segments
-    while contents.contains("DTP")
-        && check_if_segment_in_loop("DTP", "HI", &contents)
-        && check_if_segment_in_loop("DTP", "HSD", &contents)
-        && check_if_segment_in_loop("DTP", "CL1", &contents)
-        && check_if_segment_in_loop("DTP", "NM1", &contents)
-    {
-        info!("DTP segment found, ");
-        let dtp_segment = get_dtp(get_segment_contents("DTP", &contents));
-        info!("DTP segment parsed");
-
-        dtp_segments.push(dtp_segment);
-        contents = content_trim("DTP", &contents);
-    }
-
-    // Parse HI segment
-    if contents.contains("HI")
-        && check_if_segment_in_loop("HI", "HSD", &contents)
-        && check_if_segment_in_loop("HI", "CL1", &contents)
-        && check_if_segment_in_loop("HI", "NM1", &contents)
-    {
-        info!("HI segment found, ");
-        hi_segments = Some(get_hi(get_segment_contents("HI", &contents)));
-        info!("HI segment parsed");
-
-        contents = content_trim("HI", &contents);
-    }
-
-    // Parse HSD segment
-    if contents.contains("HSD")
-        && check_if_segment_in_loop("HSD", "CL1", &contents)
-        && check_if_segment_in_loop("HSD", "NM1", &contents)
-    {
-        info!("HSD segment found, ");
-        hsd_segments = Some(get_hsd(get_segment_contents("HSD", &contents)));
-        info!("HSD segment parsed");
-
-        contents = content_trim("HSD", &contents);
-    }
-
-    // Parse CL1 segment
-    if contents.contains("CL1") && check_if_segment_in_loop("CL1", "NM1", &contents) {
-        info!("CL1 segment found, ");
-        cl1_segments = Some(get_cl1(get_segment_contents("CL1", &contents)));
-        info!("CL1 segment parsed");
-
-        contents = content_trim("CL1", &contents);
+    let mut consumed = 0;
+
+    // Loop 2100E runs DTP, HI, HSD, CL1 in order up to the provider NM1
+    let segments = tokenize(contents);
+    for segment in &segments {
+        match segment.id {
+            // Parse DTP segments
+            "DTP" if hi_segments.is_none() && hsd_segments.is_none() && cl1_segments.is_none() => {
+                info!("DTP segment found, ");
+                dtp_segments.push(get_dtp(segment.elements.join("*")));
+                info!("DTP segment parsed");
+            }
+            // Parse HI segment
+            "HI" if hi_segments.is_none() && hsd_segments.is_none() && cl1_segments.is_none() => {
+                info!("HI segment found, ");
+                hi_segments = Some(get_hi(segment.elements.join("*")));
+                info!("HI segment parsed");
+            }
+            // Parse HSD segment
+            "HSD" if hsd_segments.is_none() && cl1_segments.is_none() => {
+                info!("HSD segment found, ");
+                hsd_segments = Some(get_hsd(segment.elements.join("*")));
+                info!("HSD segment parsed");
+            }
+            // Parse CL1 segment
+            "CL1" if cl1_segments.is_none() => {
+                info!("CL1 segment found, ");
+                cl1_segments = Some(get_cl1(segment.elements.join("*")));
+                info!("CL1 segment parsed");
+            }
+            _ => break,
+        }
+        consumed += 1;
     }
 
     info!("Loop 2100E parsed\n");
@@ -80,7 +64,7 @@ pub fn get_loop2100e(contents: &str) -> (Loop2100E, String) {
         cl1_segments,
     };
 
-    return (loop2100e, contents);
+    return (loop2100e, join_segments(&segments[consumed..]));
 }
 
 pub fn write_loop2100e(loop2100e: Loop2100E) -> String {
@@ -108,6 +92,7 @@ pub fn write_loop2100e(loop2100e: Loop2100E) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::segments::composites::{get_c022, write_c022};
 
     #[test]
     fn test_get_loop2100e() {
@@ -124,7 +109,10 @@ mod tests {
 
         assert!(loop2100e.hi_segments.is_some());
         let hi = loop2100e.hi_segments.unwrap();
-        assert_eq!(hi.hi01_health_care_code_information, "BF:41090:D8:20050125");
+        assert_eq!(
+            write_c022(&hi.hi01_health_care_code_information),
+            "BF:41090:D8:20050125"
+        );
 
         assert!(loop2100e.hsd_segments.is_some());
         let hsd = loop2100e.hsd_segments.unwrap();
@@ -147,18 +135,8 @@ mod tests {
                 dtp03_date_time_value: "20050516".to_string(),
             }],
             hi_segments: Some(HI {
-                hi01_health_care_code_information: "BF:41090:D8:20050125".to_string(),
-                hi02_health_care_code_information: "".to_string(),
-                hi03_health_care_code_information: "".to_string(),
-                hi04_health_care_code_information: "".to_string(),
-                hi05_health_care_code_information: "".to_string(),
-                hi06_health_care_code_information: "".to_string(),
-                hi07_health_care_code_information: "".to_string(),
-                hi08_health_care_code_information: "".to_string(),
-                hi09_health_care_code_information: "".to_string(),
-                hi10_health_care_code_information: "".to_string(),
-                hi11_health_care_code_information: "".to_string(),
-                hi12_health_care_code_information: "".to_string(),
+                hi01_health_care_code_information: get_c022("BF:41090:D8:20050125"),
+                ..Default::default()
             }),
             hsd_segments: Some(HSD {
                 hsd01_quantity_qualifier: "DY".to_string(),
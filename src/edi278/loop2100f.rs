@@ -1,7 +1,8 @@
 use log::info;
 use serde::{Deserialize, Serialize};
 
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{join_segments, tokenize};
+use crate::segments::composites::*;
 use crate::segments::dtp::*;
 use crate::segments::sv2::*;
 
@@ -12,31 +13,29 @@ pub struct Loop2100F {
 }
 
 pub fn get_loop2100f(contents: &str) -> (Loop2100F, String) {
-    let mut contents = contents.to_string();
     let mut dtp_segments = Vec::new();
     let mut sv2_segments = None;
-
-    // Parse DTP segments
-    while contents.contains("DTP")
-        && check_if_segment_in_loop("DTP", "SV2", &contents)
-        && check_if_segment_in_loop("DTP", "NM1", &contents)
-    {
-        info!("DTP segment found, ");
-        let dtp_segment = get_dtp(get_segment_contents("DTP", &contents));
-        info!("DTP segment parsed");
-
-        dtp_segments.push(dtp_segment);
-        contents = content_trim("DTP", &contents);
-    }
-
-    // Parse SV2 segment
-    if contents.contains("SV2") && check_if_segment_in_loop("SV2", "NM1", &contents) {
-        info!("SV2 segment found, ");
-        let sv2_content = get_segment_contents("SV2", &contents);
-        sv2_segments = Some(get_sv2(sv2_content));
-        info!("SV2 segment parsed");
-
-        contents = content_trim("SV2", &contents);
+    let mut consumed = 0;
+
+    // Loop 2100F runs DTP then SV2 up to the next loop
+    let segments = tokenize(contents);
+    for segment in &segments {
+        match segment.id {
+            // Parse DTP segments
+            "DTP" if sv2_segments.is_none() => {
+                info!("DTP segment found, ");
+                dtp_segments.push(get_dtp(segment.elements.join("*")));
+                info!("DTP segment parsed");
+            }
+            // Parse SV2 segment
+            "SV2" if sv2_segments.is_none() => {
+                info!("SV2 segment found, ");
+                sv2_segments = Some(get_sv2(segment.elements.join("*")));
+                info!("SV2 segment parsed");
+            }
+            _ => break,
+        }
+        consumed += 1;
     }
 
     info!("Loop 2100F parsed\n");
@@ -46,7 +45,7 @@ pub fn get_loop2100f(contents: &str) -> (Loop2100F, String) {
         sv2_segments,
     };
 
-    return (loop2100f, contents);
+    return (loop2100f, join_segments(&segments[consumed..]));
 }
 
 pub fn write_loop2100f(loop2100f: Loop2100F) -> String {
@@ -76,7 +75,7 @@ pub fn write_loop2100f(loop2100f: Loop2100F) -> String {
         // Add default SV2 segment if missing
         let default_sv2 = SV2 {
             sv201_service_line_revenue_code: "".to_string(),
-            sv202_procedure_code: "HC:33510".to_string(),
+            sv202_procedure_code: get_c003("HC:33510"),
             sv203_line_item_charge_amount: "".to_string(),
             sv204_unit_or_basis_for_measurement_code: "".to_string(),
             sv205_service_unit_count: "".to_string(),
@@ -112,7 +111,7 @@ mod tests {
         assert!(loop2100f.sv2_segments.is_some());
         let sv2 = loop2100f.sv2_segments.unwrap();
         assert_eq!(sv2.sv201_service_line_revenue_code, "");
-        assert_eq!(sv2.sv202_procedure_code, "HC:33510");
+        assert_eq!(write_c003(&sv2.sv202_procedure_code), "HC:33510");
 
         assert_eq!(contents, "");
     }
@@ -127,7 +126,7 @@ mod tests {
             }],
             sv2_segments: Some(SV2 {
                 sv201_service_line_revenue_code: "".to_string(),
-                sv202_procedure_code: "HC:33510".to_string(),
+                sv202_procedure_code: get_c003("HC:33510"),
                 sv203_line_item_charge_amount: "".to_string(),
                 sv204_unit_or_basis_for_measurement_code: "".to_string(),
                 sv205_service_unit_count: "".to_string(),
@@ -1,7 +1,7 @@
 use log::info;
 use serde::{Deserialize, Serialize};
 
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{join_segments, tokenize};
 use crate::segments::n3::*;
 use crate::segments::n4::*;
 use crate::segments::nm1::*;
@@ -18,83 +18,58 @@ pub struct Loop2110E {
 }
 
 pub fn get_loop2110e(contents: &str) -> (Loop2110E, String) {
-    let mut contents = contents.to_string();
     let mut nm1_segments = NM1::default();
     let mut ref_segments = Vec::new();
     let mut n3_segments = None;
     let mut n4_segments = None;
     let mut prv_segments = None;
-
-    if contents.contains("NM1") {
-        // Check if this is a Service Provider NM1 segment (NM101=71 or NM101=72 or NM101=77 or NM101=AAJ or NM101=FA)
-        let nm1_content = get_segment_contents("NM1", &contents);
-        let nm1_parts: Vec<&str> = nm1_content.split('*').collect();
-
-        if nm1_parts.len() > 1
-            && (nm1_parts[0] == "71"
-                || nm1_parts[0] == "72"
-                || nm1_parts[0] == "77"
-                || nm1_parts[0] == "AAJ"
-                || nm1_parts[0] == "FA"
-                || nm1_parts[0] == "SJ")
-        {
-            info!("NM1 segment found for Service Provider, ");
-            nm1_segments = get_nm1(nm1_content);
-            info!("NM1 segment parsed");
-
-            contents = content_trim("NM1", &contents);
-
-            // Parse REF segments
-            while contents.contains("REF")
-                && check_if_segment_in_loop("REF", "N3", &contents)
-                && check_if_segment_in_loop("REF", "N4", &contents)
-                && check_if_segment_in_loop("REF", "PRV", &contents)
-                && check_if_segment_in_loop("REF", "NM1", &contents)
-            {
-                info!("REF segment found, ");
-                let ref_segment = get_ref(get_segment_contents("REF", &contents));
-                info!("REF segment parsed");
-
-                ref_segments.push(ref_segment);
-                contents = content_trim("REF", &contents);
-            }
-
-            // Parse N3 segment (address)
-            if contents.contains("N3")
-                && check_if_segment_in_loop("N3", "N4", &contents)
-                && check_if_segment_in_loop("N3", "PRV", &contents)
-                && check_if_segment_in_loop("N3", "NM1", &contents)
-            {
-                info!("N3 segment found, ");
-                let n3_content = get_segment_contents("N3", &contents);
-                n3_segments = Some(get_n3(n3_content));
-                info!("N3 segment parsed");
-
-                contents = content_trim("N3", &contents);
-            }
-
-            // Parse N4 segment (city, state, zip)
-            if contents.contains("N4")
-                && check_if_segment_in_loop("N4", "PRV", &contents)
-                && check_if_segment_in_loop("N4", "NM1", &contents)
-            {
-                info!("N4 segment found, ");
-                let n4_content = get_segment_contents("N4", &contents);
-                n4_segments = Some(get_n4(n4_content));
-                info!("N4 segment parsed");
-
-                contents = content_trim("N4", &contents);
-            }
-
-            // Parse PRV segment
-            if contents.contains("PRV") && check_if_segment_in_loop("PRV", "NM1", &contents) {
-                info!("PRV segment found, ");
-                let prv_content = get_segment_contents("PRV", &contents);
-                prv_segments = Some(get_prv(&prv_content));
-                info!("PRV segment parsed");
-
-                contents = content_trim("PRV", &contents);
+    let mut consumed = 0;
+
+    // Loop 2110E starts with a Service Provider NM1 segment (NM101=71, 72, 77, AAJ, FA or SJ)
+    let segments = tokenize(contents);
+    if let Some(nm1) = segments.first().filter(|segment| {
+        segment.id == "NM1"
+            && matches!(segment.element(0), "71" | "72" | "77" | "AAJ" | "FA" | "SJ")
+    }) {
+        info!("NM1 segment found for Service Provider, ");
+        nm1_segments = get_nm1(nm1.elements.join("*"));
+        info!("NM1 segment parsed");
+        consumed = 1;
+
+        for segment in &segments[1..] {
+            match segment.id {
+                // Parse REF segments
+                "REF"
+                    if n3_segments.is_none() && n4_segments.is_none() && prv_segments.is_none() =>
+                {
+                    info!("REF segment found, ");
+                    ref_segments.push(get_ref(segment.elements.join("*")));
+                    info!("REF segment parsed");
+                }
+                // Parse N3 segment (address)
+                "N3" if n3_segments.is_none()
+                    && n4_segments.is_none()
+                    && prv_segments.is_none() =>
+                {
+                    info!("N3 segment found, ");
+                    n3_segments = Some(get_n3(segment.elements.join("*")));
+                    info!("N3 segment parsed");
+                }
+                // Parse N4 segment (city, state, zip)
+                "N4" if n4_segments.is_none() && prv_segments.is_none() => {
+                    info!("N4 segment found, ");
+                    n4_segments = Some(get_n4(segment.elements.join("*")));
+                    info!("N4 segment parsed");
+                }
+                // Parse PRV segment
+                "PRV" if prv_segments.is_none() => {
+                    info!("PRV segment found, ");
+                    prv_segments = Some(get_prv(&segment.elements.join("*")));
+                    info!("PRV segment parsed");
+                }
+                _ => break,
             }
+            consumed += 1;
         }
     }
 
@@ -108,7 +83,7 @@ pub fn get_loop2110e(contents: &str) -> (Loop2110E, String) {
         prv_segments,
     };
 
-    return (loop2110e, contents);
+    return (loop2110e, join_segments(&segments[consumed..]));
 }
 
 pub fn write_loop2110e(loop2110e: Loop2110E) -> String {
@@ -210,6 +185,7 @@ mod tests {
             ref_segments: vec![REF {
                 reference_id_number_qualifier: "1J".to_string(),
                 reference_id_number: "12345".to_string(),
+                ..Default::default()
             }],
             n3_segments: None,
             n4_segments: None,
@@ -1,7 +1,7 @@
 use log::info;
 use serde::{Deserialize, Serialize};
 
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{find_segment, join_segments, tokenize};
 use crate::segments::bht::*;
 use crate::segments::st::*;
 
@@ -12,24 +12,34 @@ pub struct Table1s {
 }
 
 pub fn get_table1s(contents: &str) -> (Table1s, String) {
-    let mut contents = contents.to_string();
     let mut st_segments = ST::default();
     let mut bht_segments = BHT::default();
+    let mut st_found = false;
+    let mut bht_found = false;
+    let mut leftover = String::new();
 
-    if contents.contains("ST*") {
-        info!("ST segment found, ");
-        st_segments = get_st(get_segment_contents("ST", &contents));
-        info!("ST segment parsed");
+    // Table 1 runs up to the first HL segment
+    let segments = tokenize(contents);
+    let header_end = find_segment(&segments, &["HL"], 0).unwrap_or(segments.len());
 
-        contents = content_trim("ST", &contents);
-    }
-
-    if contents.contains("BHT") {
-        info!("BHT segment found, ");
-        bht_segments = get_bht(get_segment_contents("BHT", &contents));
-        info!("BHT segment parsed");
-
-        contents = content_trim("BHT", &contents);
+    for segment in &segments[..header_end] {
+        match segment.id {
+            // Parse ST segment
+            "ST" if !st_found => {
+                info!("ST segment found, ");
+                st_segments = get_st(segment.elements.join("*"));
+                st_found = true;
+                info!("ST segment parsed");
+            }
+            // Parse BHT segment
+            "BHT" if !bht_found => {
+                info!("BHT segment found, ");
+                bht_segments = get_bht(segment.elements.join("*"));
+                bht_found = true;
+                info!("BHT segment parsed");
+            }
+            _ => leftover.push_str(&segment.to_edi()),
+        }
     }
 
     info!("Table 1 parsed\n");
@@ -39,6 +49,8 @@ pub fn get_table1s(contents: &str) -> (Table1s, String) {
         bht_segments,
     };
 
+    let mut contents = join_segments(&segments[header_end..]);
+    contents.push_str(&leftover);
     return (table1s, contents);
 }
 
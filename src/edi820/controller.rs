@@ -8,6 +8,11 @@ use crate::edi820::loop1000b::*;
 use crate::edi820::loop2000::*;
 use crate::edi820::table1::*;
 use crate::error::EdiResult;
+use crate::helper::delimiters::Delimiters;
+use crate::helper::diagnostics::{collect_diagnostics, Diagnostics};
+use crate::helper::helper::clean_contents;
+use crate::helper::unrecognized::UnrecognizedSegments;
+use crate::helper::writer_options::WriterOptions;
 use crate::transaction_processor::TransactionSet;
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -23,6 +28,15 @@ pub struct Edi820 {
     pub table1_combined: Table1Combined,
     pub table2s: Vec<Table2>,
     pub interchange_trailer: InterchangeTrailer,
+    /// Delimiters declared by the source ISA, re-emitted when writing EDI
+    #[serde(default)]
+    pub delimiters: Delimiters,
+    /// Problems found while parsing: envelope mismatches, invalid values, unprocessed segments
+    #[serde(default, skip_serializing_if = "Diagnostics::is_empty")]
+    pub diagnostics: Diagnostics,
+    /// Segments the parser did not recognize, written back where they appeared
+    #[serde(default, skip_serializing_if = "UnrecognizedSegments::is_empty")]
+    pub unrecognized: UnrecognizedSegments,
     pub transaction_set_id: String,
 }
 
@@ -34,8 +48,11 @@ impl TransactionSet for Edi820 {
         // Remove BOM if present
         let mut contents = contents.trim_start_matches("\u{feff}").to_string();
 
-        // Remove carriage returns and line feeds
-        contents = contents.replace("\r", "").replace("\n", "");
+        // Capture the source delimiters, then normalize to `*` and `~` and remove line breaks
+        let delimiters = Delimiters::detect(&contents);
+        contents = clean_contents(contents);
+        let normalized = contents.clone();
+        edi820.delimiters = delimiters;
 
         // Parse interchange control header
         let (interchange_header, new_contents) = get_interchange_header(&contents);
@@ -70,10 +87,12 @@ impl TransactionSet for Edi820 {
         let (interchange_trailer, remaining) = get_interchange_trailer(&contents);
         edi820.interchange_trailer = interchange_trailer;
 
+        edi820.diagnostics = collect_diagnostics(&normalized, &remaining, &delimiters);
+        edi820.unrecognized = UnrecognizedSegments::collect(&normalized, &remaining);
         Ok((edi820, remaining))
     }
 
-    fn to_edi(&self) -> String {
+    fn to_edi_with(&self, options: &WriterOptions) -> EdiResult<String> {
         let mut new_edi = String::new();
 
         // Write interchange header
@@ -94,8 +113,11 @@ impl TransactionSet for Edi820 {
         // Write interchange control trailer
         new_edi.push_str(&write_interchange_trailer(&self.interchange_trailer));
 
+        let new_edi = self.unrecognized.restore(new_edi, &Delimiters::default());
+        let new_edi = options.format(&new_edi, &self.delimiters)?;
+
         info!("Generated EDI 820: {}", new_edi);
-        new_edi
+        Ok(new_edi)
     }
 
     fn get_transaction_type() -> &'static str {
@@ -114,10 +136,14 @@ pub fn get_820(contents: &str) -> EdiResult<Edi820> {
     }
 }
 
-pub fn write_820(edi820: &Edi820) -> String {
+pub fn write_820(edi820: &Edi820) -> EdiResult<String> {
     edi820.to_edi()
 }
 
+pub fn write_820_with(edi820: &Edi820, options: &WriterOptions) -> EdiResult<String> {
+    edi820.to_edi_with(options)
+}
+
 // Function to detect if content is EDI 820 format
 pub fn is_820(contents: &str) -> bool {
     // Check if the content contains ST*820 which indicates an EDI 820 transaction set
@@ -1,4 +1,4 @@
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{find_segment, join_segments, tokenize};
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -55,50 +55,47 @@ pub struct PER {
 }
 
 pub fn get_1000bs(contents: &str) -> (Loop1000bs, String) {
-    let mut contents = contents.to_string();
     let mut loop1000bs = Loop1000bs::default();
-
-    // Parse N1 segment for payee identification
-    if contents.contains("N1") {
-        let n1_content = get_segment_contents("N1", &contents);
-        let n1_parts: Vec<&str> = n1_content.split('*').collect();
-
-        // Check if this is a payee identification segment (N101=RM)
-        if n1_parts.len() > 0 && n1_parts[0] == "RM" {
-            info!("N1 segment found for Payee Identification, ");
-
-            loop1000bs.n1_segments = N1 {
-                entity_id: n1_parts[0].to_string(),
-                entity_name: if n1_parts.len() > 1 {
-                    n1_parts[1].to_string()
-                } else {
-                    String::new()
-                },
-                id_code_qualifier: if n1_parts.len() > 2 {
-                    n1_parts[2].to_string()
-                } else {
-                    String::new()
-                },
-                id_code: if n1_parts.len() > 3 {
-                    n1_parts[3].to_string()
-                } else {
-                    String::new()
-                },
-            };
-
-            info!("N1 segment parsed");
-            contents = content_trim("N1", &contents);
-
-            // Parse N3 segment (address)
-            if contents.contains("N3")
-                && check_if_segment_in_loop("N3", "N4", &contents)
-                && check_if_segment_in_loop("N3", "REF", &contents)
-                && check_if_segment_in_loop("N3", "PER", &contents)
-                && check_if_segment_in_loop("N3", "ENT", &contents)
-            {
+    let mut leftover = String::new();
+
+    // Loop 1000B starts at the N1 segment for payee identification (N101=RM)
+    // and runs up to the next N1 or the first ENT
+    let segments = tokenize(contents);
+    let Some(start) = find_segment(&segments, &["N1", "ENT", "SE"], 0)
+        .filter(|&pos| segments[pos].id == "N1" && segments[pos].element(0) == "RM")
+    else {
+        return (loop1000bs, contents.to_string());
+    };
+    let end = find_segment(&segments, &["N1", "ENT", "SE"], start + 1).unwrap_or(segments.len());
+
+    info!("N1 segment found for Payee Identification, ");
+    let n1_parts = &segments[start].elements;
+    loop1000bs.n1_segments = N1 {
+        entity_id: n1_parts[0].to_string(),
+        entity_name: if n1_parts.len() > 1 {
+            n1_parts[1].to_string()
+        } else {
+            String::new()
+        },
+        id_code_qualifier: if n1_parts.len() > 2 {
+            n1_parts[2].to_string()
+        } else {
+            String::new()
+        },
+        id_code: if n1_parts.len() > 3 {
+            n1_parts[3].to_string()
+        } else {
+            String::new()
+        },
+    };
+
+    info!("N1 segment parsed");
+
+    for segment in &segments[start + 1..end] {
+        match segment.id {
+            "N3" if loop1000bs.n3_segments.is_none() => {
                 info!("N3 segment found, ");
-                let n3_content = get_segment_contents("N3", &contents);
-                let n3_parts: Vec<&str> = n3_content.split('*').collect();
+                let n3_parts = &segment.elements;
 
                 loop1000bs.n3_segments = Some(N3 {
                     address: if n3_parts.len() > 0 {
@@ -114,18 +111,10 @@ pub fn get_1000bs(contents: &str) -> (Loop1000bs, String) {
                 });
 
                 info!("N3 segment parsed");
-                contents = content_trim("N3", &contents);
             }
-
-            // Parse N4 segment (city, state, zip)
-            if contents.contains("N4")
-                && check_if_segment_in_loop("N4", "REF", &contents)
-                && check_if_segment_in_loop("N4", "PER", &contents)
-                && check_if_segment_in_loop("N4", "ENT", &contents)
-            {
+            "N4" if loop1000bs.n4_segments.is_none() => {
                 info!("N4 segment found, ");
-                let n4_content = get_segment_contents("N4", &contents);
-                let n4_parts: Vec<&str> = n4_content.split('*').collect();
+                let n4_parts = &segment.elements;
 
                 loop1000bs.n4_segments = Some(N4 {
                     city: if n4_parts.len() > 0 {
@@ -161,17 +150,10 @@ pub fn get_1000bs(contents: &str) -> (Loop1000bs, String) {
                 });
 
                 info!("N4 segment parsed");
-                contents = content_trim("N4", &contents);
             }
-
-            // Parse REF segments
-            while contents.contains("REF")
-                && check_if_segment_in_loop("REF", "PER", &contents)
-                && check_if_segment_in_loop("REF", "ENT", &contents)
-            {
+            "REF" => {
                 info!("REF segment found, ");
-                let ref_content = get_segment_contents("REF", &contents);
-                let ref_parts: Vec<&str> = ref_content.split('*').collect();
+                let ref_parts = &segment.elements;
 
                 let ref_segment = REF {
                     reference_id_qualifier: if ref_parts.len() > 0 {
@@ -194,14 +176,10 @@ pub fn get_1000bs(contents: &str) -> (Loop1000bs, String) {
                 loop1000bs.ref_segments.push(ref_segment);
 
                 info!("REF segment parsed");
-                contents = content_trim("REF", &contents);
             }
-
-            // Parse PER segments
-            while contents.contains("PER") && check_if_segment_in_loop("PER", "ENT", &contents) {
+            "PER" => {
                 info!("PER segment found, ");
-                let per_content = get_segment_contents("PER", &contents);
-                let per_parts: Vec<&str> = per_content.split('*').collect();
+                let per_parts = &segment.elements;
 
                 let per_segment = PER {
                     contact_function_code: if per_parts.len() > 0 {
@@ -249,13 +227,16 @@ pub fn get_1000bs(contents: &str) -> (Loop1000bs, String) {
                 loop1000bs.per_segments.push(per_segment);
 
                 info!("PER segment parsed");
-                contents = content_trim("PER", &contents);
             }
+            _ => leftover.push_str(&segment.to_edi()),
         }
     }
 
     info!("Loop 1000B parsed\n");
 
+    let mut contents = join_segments(&segments[..start]);
+    contents.push_str(&join_segments(&segments[end..]));
+    contents.push_str(&leftover);
     return (loop1000bs, contents);
 }
 
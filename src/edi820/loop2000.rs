@@ -1,5 +1,5 @@
 use crate::edi820::loop2100::*;
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{find_segment, join_segments, split_loops, tokenize};
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -20,29 +20,16 @@ pub struct ENT {
 }
 
 pub fn get_loop_2000s(contents: &str) -> (Vec<Table2>, String) {
-    let mut contents = contents.to_string();
     let mut table2s = Vec::new();
-    let original_length = contents.len();
-    let mut safety_counter = 0;
-    let max_iterations = 100; // Safety limit to prevent infinite loops
-
-    // Parse ENT segments and their associated Loop2100s
-    while contents.contains("ENT")
-        && safety_counter < max_iterations
-        && contents.len() < original_length + 100
-    {
-        // Additional safety check
-
-        safety_counter += 1;
-        info!(
-            "Loop2000 iteration {}, content length: {}",
-            safety_counter,
-            contents.len()
-        );
 
-        let ent_content = get_segment_contents("ENT", &contents);
-        let ent_parts: Vec<&str> = ent_content.split('*').collect();
+    // Table 2 ends at SE; each ENT starts a new loop 2000
+    let segments = tokenize(contents);
+    let table2_end = find_segment(&segments, &["SE"], 0).unwrap_or(segments.len());
+    let (leading, loops) = split_loops(&segments[..table2_end], &["ENT"]);
+    let mut leftover = join_segments(leading);
 
+    for loop_segments in loops {
+        let ent_parts = &loop_segments[0].elements;
         let ent_segment = ENT {
             ent01_assigned_number: if ent_parts.len() > 0 {
                 ent_parts[0].to_string()
@@ -77,28 +64,10 @@ pub fn get_loop_2000s(contents: &str) -> (Vec<Table2>, String) {
         };
 
         info!("ENT segment parsed");
-        let old_contents = contents.clone();
-        contents = content_trim("ENT", &contents);
-
-        // Safety check - ensure content is actually being trimmed
-        if contents == old_contents {
-            info!("Warning: ENT content not trimmed, breaking to avoid infinite loop");
-            break;
-        }
 
         // Parse Loop2100s associated with this ENT
-        let (loop2100s, new_contents) = get_loop_2100s(&contents);
-
-        // Safety check - ensure we're making progress
-        if new_contents == contents {
-            info!(
-                "Warning: No progress made in parsing Loop2100s, breaking to avoid infinite loop"
-            );
-            contents = new_contents;
-            break;
-        }
-
-        contents = new_contents;
+        let (loop2100s, rem_contents) = get_loop_2100s(&join_segments(&loop_segments[1..]));
+        leftover.push_str(&rem_contents);
 
         let table2 = Table2 {
             ent_segments: ent_segment,
@@ -108,12 +77,10 @@ pub fn get_loop_2000s(contents: &str) -> (Vec<Table2>, String) {
         table2s.push(table2);
     }
 
-    if safety_counter >= max_iterations {
-        info!("Warning: Maximum iterations reached in get_loop_2000s, possible infinite loop detected");
-    }
-
     info!("Loop 2000 parsed, found {} entities", table2s.len());
 
+    let mut contents = join_segments(&segments[table2_end..]);
+    contents.push_str(&leftover);
     return (table2s, contents);
 }
 
@@ -178,3 +145,27 @@ pub fn write_loop2000(table2s: Vec<Table2>) -> String {
 
     return result;
 }
+
+// unit tests
+
+#[cfg(test)]
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_loop_2000s_multiple_ent() {
+        let contents = "ENT*1~NM1*IL*1*DOE*JOHN~REF*POL*1~RMR*ZZ*A**10~REF*ZZ*X~DTM*582*D8*20120501~ENT*2~NM1*IL*1*ROE*JANE~RMR*ZZ*B**20~SE*11*0001~";
+        let (table2s, contents) = get_loop_2000s(contents);
+        assert_eq!(table2s.len(), 2);
+        assert_eq!(table2s[0].loop2100s[0].ref_segments.len(), 1);
+        assert_eq!(table2s[0].loop2100s[0].dtm_segments.len(), 1);
+        assert_eq!(table2s[1].loop2100s[0].nm1_segments.lastname, "ROE");
+        assert_eq!(
+            table2s[1].loop2100s[0].rmr_segments[0].rmr02_reference_id,
+            "B"
+        );
+        // A REF after the RMR is not a loop 2100 REF and is left unprocessed
+        assert_eq!(contents, "SE*11*0001~REF*ZZ*X~");
+    }
+}
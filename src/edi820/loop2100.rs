@@ -1,4 +1,4 @@
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{join_segments, split_loops, tokenize};
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -50,214 +50,141 @@ pub struct DTM {
 }
 
 pub fn get_loop_2100s(contents: &str) -> (Vec<Loop2100>, String) {
-    let mut contents = contents.to_string();
     let mut loop2100s = Vec::new();
-    let original_length = contents.len();
-    let mut safety_counter = 0;
-    let max_iterations = 100; // Safety limit to prevent infinite loops
-
-    // Parse NM1 segments and their associated data
-    while contents.contains("NM1")
-        && safety_counter < max_iterations
-        && contents.len() < original_length + 100
-    {
-        // Additional safety check
-
-        safety_counter += 1;
-        info!(
-            "Loop2100 iteration {}, content length: {}",
-            safety_counter,
-            contents.len()
-        );
-
-        // Check if this NM1 is part of the current loop or a new section
-        if !check_if_segment_in_loop("NM1", "ENT", &contents)
-            && !check_if_segment_in_loop("NM1", "SE", &contents)
-        {
-            info!("NM1 segment is not part of the current loop, breaking");
-            break;
-        }
 
-        let nm1_content = get_segment_contents("NM1", &contents);
-        let nm1_parts: Vec<&str> = nm1_content.split('*').collect();
+    // Each NM1 starts a new loop 2100 within the current ENT loop
+    let segments = tokenize(contents);
+    let (leading, loops) = split_loops(&segments, &["NM1"]);
+    let mut leftover = join_segments(leading);
 
+    for loop_segments in loops {
         // Check if this is an individual (NM101=IL)
-        if nm1_parts.len() > 0 && nm1_parts[0] == "IL" {
-            info!("NM1 segment found for Individual, ");
-
-            let nm1_segment = NM1 {
-                entity_id: nm1_parts[0].to_string(),
-                entity_type: if nm1_parts.len() > 1 {
-                    nm1_parts[1].to_string()
-                } else {
-                    String::new()
-                },
-                lastname: if nm1_parts.len() > 2 {
-                    nm1_parts[2].to_string()
-                } else {
-                    String::new()
-                },
-                firstname: if nm1_parts.len() > 3 {
-                    nm1_parts[3].to_string()
-                } else {
-                    String::new()
-                },
-                middle_initial: if nm1_parts.len() > 4 {
-                    nm1_parts[4].to_string()
-                } else {
-                    String::new()
-                },
-                suffix: if nm1_parts.len() > 5 {
-                    nm1_parts[5].to_string()
-                } else {
-                    String::new()
-                },
-                title: if nm1_parts.len() > 6 {
-                    nm1_parts[6].to_string()
-                } else {
-                    String::new()
-                },
-                id_code_qualifier: if nm1_parts.len() > 7 {
-                    nm1_parts[7].to_string()
-                } else {
-                    String::new()
-                },
-                id_code: if nm1_parts.len() > 8 {
-                    nm1_parts[8].to_string()
-                } else {
-                    String::new()
-                },
-            };
-
-            info!("NM1 segment parsed");
-            let old_contents = contents.clone();
-            contents = content_trim("NM1", &contents);
-
-            // Safety check - ensure content is actually being trimmed
-            if contents == old_contents {
-                info!("Warning: Content not trimmed, breaking to avoid infinite loop");
-                break;
-            }
+        if loop_segments[0].element(0) != "IL" {
+            info!("NM1 segment is not for an individual (IL), skipping");
+            leftover.push_str(&join_segments(loop_segments));
+            continue;
+        }
 
-            let mut ref_segments = Vec::new();
-            let mut rmr_segments = Vec::new();
-            let mut dtm_segments = Vec::new();
-            let mut ref_safety_counter = 0;
-
-            // Parse REF segments
-            while contents.contains("REF") && 
-                  ref_safety_counter < 20 && // Safety limit for REF segments
-                  check_if_segment_in_loop("REF", "RMR", &contents) && 
-                  check_if_segment_in_loop("REF", "DTM", &contents) && 
-                  check_if_segment_in_loop("REF", "NM1", &contents) && 
-                  check_if_segment_in_loop("REF", "ENT", &contents) && 
-                  check_if_segment_in_loop("REF", "SE", &contents)
-            {
-                ref_safety_counter += 1;
-                info!("REF segment found, iteration {}", ref_safety_counter);
-                let ref_content = get_segment_contents("REF", &contents);
-                let ref_parts: Vec<&str> = ref_content.split('*').collect();
-
-                let ref_segment = REF {
-                    reference_id_qualifier: if ref_parts.len() > 0 {
-                        ref_parts[0].to_string()
-                    } else {
-                        String::new()
-                    },
-                    reference_id: if ref_parts.len() > 1 {
-                        ref_parts[1].to_string()
-                    } else {
-                        String::new()
-                    },
-                    description: if ref_parts.len() > 2 {
-                        ref_parts[2].to_string()
-                    } else {
-                        String::new()
-                    },
-                };
-
-                ref_segments.push(ref_segment);
-
-                info!("REF segment parsed");
-                let old_contents = contents.clone();
-                contents = content_trim("REF", &contents);
-
-                // Safety check - ensure content is actually being trimmed
-                if contents == old_contents {
-                    info!("Warning: REF content not trimmed, breaking to avoid infinite loop");
-                    break;
-                }
-            }
+        info!("NM1 segment found for Individual, ");
+        let nm1_parts = &loop_segments[0].elements;
+        let nm1_segment = NM1 {
+            entity_id: nm1_parts[0].to_string(),
+            entity_type: if nm1_parts.len() > 1 {
+                nm1_parts[1].to_string()
+            } else {
+                String::new()
+            },
+            lastname: if nm1_parts.len() > 2 {
+                nm1_parts[2].to_string()
+            } else {
+                String::new()
+            },
+            firstname: if nm1_parts.len() > 3 {
+                nm1_parts[3].to_string()
+            } else {
+                String::new()
+            },
+            middle_initial: if nm1_parts.len() > 4 {
+                nm1_parts[4].to_string()
+            } else {
+                String::new()
+            },
+            suffix: if nm1_parts.len() > 5 {
+                nm1_parts[5].to_string()
+            } else {
+                String::new()
+            },
+            title: if nm1_parts.len() > 6 {
+                nm1_parts[6].to_string()
+            } else {
+                String::new()
+            },
+            id_code_qualifier: if nm1_parts.len() > 7 {
+                nm1_parts[7].to_string()
+            } else {
+                String::new()
+            },
+            id_code: if nm1_parts.len() > 8 {
+                nm1_parts[8].to_string()
+            } else {
+                String::new()
+            },
+        };
+
+        info!("NM1 segment parsed");
+
+        let mut ref_segments = Vec::new();
+        let mut rmr_segments = Vec::new();
+        let mut dtm_segments = Vec::new();
+
+        for segment in &loop_segments[1..] {
+            match segment.id {
+                "REF" if rmr_segments.is_empty() => {
+                    info!("REF segment found, ");
+                    let ref_parts = &segment.elements;
+
+                    let ref_segment = REF {
+                        reference_id_qualifier: if ref_parts.len() > 0 {
+                            ref_parts[0].to_string()
+                        } else {
+                            String::new()
+                        },
+                        reference_id: if ref_parts.len() > 1 {
+                            ref_parts[1].to_string()
+                        } else {
+                            String::new()
+                        },
+                        description: if ref_parts.len() > 2 {
+                            ref_parts[2].to_string()
+                        } else {
+                            String::new()
+                        },
+                    };
 
-            let mut rmr_safety_counter = 0;
+                    ref_segments.push(ref_segment);
 
-            // Parse RMR segments
-            while contents.contains("RMR") && 
-                  rmr_safety_counter < 20 && // Safety limit for RMR segments
-                  check_if_segment_in_loop("RMR", "DTM", &contents) && 
-                  check_if_segment_in_loop("RMR", "NM1", &contents) && 
-                  check_if_segment_in_loop("RMR", "ENT", &contents) && 
-                  check_if_segment_in_loop("RMR", "SE", &contents)
-            {
-                rmr_safety_counter += 1;
-                info!("RMR segment found, iteration {}", rmr_safety_counter);
-                let rmr_content = get_segment_contents("RMR", &contents);
-                let rmr_parts: Vec<&str> = rmr_content.split('*').collect();
-
-                let rmr_segment = RMR {
-                    rmr01_reference_id_qualifier: if rmr_parts.len() > 0 {
-                        rmr_parts[0].to_string()
-                    } else {
-                        String::new()
-                    },
-                    rmr02_reference_id: if rmr_parts.len() > 1 {
-                        rmr_parts[1].to_string()
-                    } else {
-                        String::new()
-                    },
-                    rmr03_payment_action_code: if rmr_parts.len() > 2 {
-                        rmr_parts[2].to_string()
-                    } else {
-                        String::new()
-                    },
-                    rmr04_monetary_amount: if rmr_parts.len() > 3 {
-                        rmr_parts[3].to_string()
-                    } else {
-                        String::new()
-                    },
-                    rmr05_credit_debit_flag_code: if rmr_parts.len() > 4 {
-                        rmr_parts[4].to_string()
-                    } else {
-                        String::new()
-                    },
-                };
-
-                rmr_segments.push(rmr_segment);
-
-                info!("RMR segment parsed");
-                let old_contents = contents.clone();
-                contents = content_trim("RMR", &contents);
-
-                // Safety check - ensure content is actually being trimmed
-                if contents == old_contents {
-                    info!("Warning: RMR content not trimmed, breaking to avoid infinite loop");
-                    break;
+                    info!("REF segment parsed");
                 }
+                "RMR" => {
+                    info!("RMR segment found, ");
+                    let rmr_parts = &segment.elements;
 
-                let mut dtm_safety_counter = 0;
+                    let rmr_segment = RMR {
+                        rmr01_reference_id_qualifier: if rmr_parts.len() > 0 {
+                            rmr_parts[0].to_string()
+                        } else {
+                            String::new()
+                        },
+                        rmr02_reference_id: if rmr_parts.len() > 1 {
+                            rmr_parts[1].to_string()
+                        } else {
+                            String::new()
+                        },
+                        rmr03_payment_action_code: if rmr_parts.len() > 2 {
+                            rmr_parts[2].to_string()
+                        } else {
+                            String::new()
+                        },
+                        rmr04_monetary_amount: if rmr_parts.len() > 3 {
+                            rmr_parts[3].to_string()
+                        } else {
+                            String::new()
+                        },
+                        rmr05_credit_debit_flag_code: if rmr_parts.len() > 4 {
+                            rmr_parts[4].to_string()
+                        } else {
+                            String::new()
+                        },
+                    };
 
-                // Parse DTM segments associated with this RMR
-                while contents.contains("DTM") && 
-                      dtm_safety_counter < 20 && // Safety limit for DTM segments
-                      check_if_segment_in_loop("DTM", "RMR", &contents) && 
-                      check_if_segment_in_loop("DTM", "NM1", &contents) && 
-                      check_if_segment_in_loop("DTM", "ENT", &contents) && 
-                      check_if_segment_in_loop("DTM", "SE", &contents)
-                {
-                    dtm_safety_counter += 1;
-                    info!("DTM segment found, iteration {}", dtm_safety_counter);
-                    let dtm_content = get_segment_contents("DTM", &contents);
-                    let dtm_parts: Vec<&str> = dtm_content.split('*').collect();
+                    rmr_segments.push(rmr_segment);
+
+                    info!("RMR segment parsed");
+                }
+                // Each RMR is written with at most one DTM following it
+                "DTM" if dtm_segments.len() + 1 == rmr_segments.len() => {
+                    info!("DTM segment found, ");
+                    let dtm_parts = &segment.elements;
 
                     let dtm_segment = DTM {
                         dtm01_date_time_qualifier: if dtm_parts.len() > 0 {
@@ -295,39 +222,24 @@ pub fn get_loop_2100s(contents: &str) -> (Vec<Loop2100>, String) {
                     dtm_segments.push(dtm_segment);
 
                     info!("DTM segment parsed");
-                    let old_contents = contents.clone();
-                    contents = content_trim("DTM", &contents);
-
-                    // Safety check - ensure content is actually being trimmed
-                    if contents == old_contents {
-                        info!("Warning: DTM content not trimmed, breaking to avoid infinite loop");
-                        break;
-                    }
                 }
+                _ => leftover.push_str(&segment.to_edi()),
             }
-
-            let loop2100 = Loop2100 {
-                nm1_segments: nm1_segment,
-                ref_segments,
-                rmr_segments,
-                dtm_segments,
-            };
-
-            loop2100s.push(loop2100);
-        } else {
-            // Skip this NM1 segment as it's not part of Loop2100
-            info!("NM1 segment is not for an individual (IL), breaking");
-            break;
         }
-    }
 
-    if safety_counter >= max_iterations {
-        info!("Warning: Maximum iterations reached in get_loop_2100s, possible infinite loop detected");
+        let loop2100 = Loop2100 {
+            nm1_segments: nm1_segment,
+            ref_segments,
+            rmr_segments,
+            dtm_segments,
+        };
+
+        loop2100s.push(loop2100);
     }
 
     info!("Loop 2100 parsed, found {} loops", loop2100s.len());
 
-    return (loop2100s, contents);
+    return (loop2100s, leftover);
 }
 
 pub fn write_loop2100s(loop2100s: Vec<Loop2100>) -> String {
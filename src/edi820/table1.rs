@@ -1,4 +1,4 @@
-use crate::helper::edihelper::*;
+use crate::helper::tokenizer::{find_segment, join_segments, tokenize};
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -64,237 +64,213 @@ pub struct DTM {
 }
 
 pub fn get_table1s(contents: &str) -> (Table1s, String) {
-    let mut contents = contents.to_string();
     let mut table1s = Table1s::default();
-
-    // Parse ST segment
-    if contents.contains("ST*") {
-        info!("ST segment found, ");
-        let st_content = get_segment_contents("ST", &contents);
-        info!("segment_content: {}", st_content);
-
-        let st_parts: Vec<&str> = st_content.split('*').collect();
-
-        if st_parts.len() >= 2 {
-            table1s.st_segments = ST {
-                transaction_set_id: st_parts[0].to_string(),
-                transaction_set_control_number: st_parts[1].to_string(),
-                implementation_conven_ref: if st_parts.len() > 2 {
-                    st_parts[2].to_string()
-                } else {
-                    String::new()
-                },
-            };
-        }
-
-        info!("ST segment parsed");
-        contents = content_trim("ST", &contents);
-    }
-
-    // Parse BPR segment
-    if contents.contains("BPR") {
-        info!("BPR segment found, ");
-        let bpr_content = get_segment_contents("BPR", &contents);
-        info!("segment_content: {}", bpr_content);
-
-        let bpr_parts: Vec<&str> = bpr_content.split('*').collect();
-
-        if !bpr_parts.is_empty() {
-            table1s.bpr_segments = BPR {
-                bpr01_transaction_handling_code: if bpr_parts.len() > 0 {
-                    bpr_parts[0].to_string()
-                } else {
-                    String::new()
-                },
-                bpr02_total_payment_amount: if bpr_parts.len() > 1 {
-                    bpr_parts[1].to_string()
-                } else {
-                    String::new()
-                },
-                bpr03_credit_debit_flag_code: if bpr_parts.len() > 2 {
-                    bpr_parts[2].to_string()
-                } else {
-                    String::new()
-                },
-                bpr04_payment_method_code: if bpr_parts.len() > 3 {
-                    bpr_parts[3].to_string()
-                } else {
-                    String::new()
-                },
-                bpr05_payment_format_code: if bpr_parts.len() > 4 {
-                    bpr_parts[4].to_string()
-                } else {
-                    String::new()
-                },
-                bpr06_dfi_id_number_qualifier: if bpr_parts.len() > 5 {
-                    bpr_parts[5].to_string()
-                } else {
-                    String::new()
-                },
-                bpr07_dfi_id_number: if bpr_parts.len() > 6 {
-                    bpr_parts[6].to_string()
-                } else {
-                    String::new()
-                },
-                bpr08_account_number_qualifier: if bpr_parts.len() > 7 {
-                    bpr_parts[7].to_string()
-                } else {
-                    String::new()
-                },
-                bpr09_account_number: if bpr_parts.len() > 8 {
-                    bpr_parts[8].to_string()
-                } else {
-                    String::new()
-                },
-                bpr10_originating_company_id: if bpr_parts.len() > 9 {
-                    bpr_parts[9].to_string()
-                } else {
-                    String::new()
-                },
-                bpr11_originating_company_supplemental_code: if bpr_parts.len() > 10 {
-                    bpr_parts[10].to_string()
-                } else {
-                    String::new()
-                },
-                bpr12_dfi_id_number_qualifier: if bpr_parts.len() > 11 {
-                    bpr_parts[11].to_string()
-                } else {
-                    String::new()
-                },
-                bpr13_dfi_id_number: if bpr_parts.len() > 12 {
-                    bpr_parts[12].to_string()
-                } else {
-                    String::new()
-                },
-                bpr14_account_number_qualifier: if bpr_parts.len() > 13 {
-                    bpr_parts[13].to_string()
-                } else {
-                    String::new()
-                },
-                bpr15_account_number: if bpr_parts.len() > 14 {
-                    bpr_parts[14].to_string()
-                } else {
-                    String::new()
-                },
-                bpr16_payment_effective_date: if bpr_parts.len() > 15 {
-                    bpr_parts[15].to_string()
-                } else {
-                    String::new()
-                },
-            };
-        }
-
-        info!("BPR segment parsed");
-        contents = content_trim("BPR", &contents);
-    }
-
-    // Parse TRN segment
-    if contents.contains("TRN") {
-        info!("TRN segment found, ");
-        let trn_content = get_segment_contents("TRN", &contents);
-        info!("segment_content: {}", trn_content);
-
-        let trn_parts: Vec<&str> = trn_content.split('*').collect();
-
-        if trn_parts.len() >= 2 {
-            table1s.trn_segments = Some(TRN {
-                trn01_trace_type_code: trn_parts[0].to_string(),
-                trn02_reference_id: trn_parts[1].to_string(),
-                trn03_originating_company_id: if trn_parts.len() > 2 {
-                    trn_parts[2].to_string()
-                } else {
-                    String::new()
-                },
-                trn04_reference_id: if trn_parts.len() > 3 {
-                    trn_parts[3].to_string()
-                } else {
-                    String::new()
-                },
-            });
-        }
-
-        info!("TRN segment parsed");
-        contents = content_trim("TRN", &contents);
-    }
-
-    // Parse REF segments
-    while contents.contains("REF")
-        && check_if_segment_in_loop("REF", "DTM", &contents)
-        && check_if_segment_in_loop("REF", "N1", &contents)
-    {
-        info!("REF segment found, ");
-        let ref_content = get_segment_contents("REF", &contents);
-
-        let ref_parts: Vec<&str> = ref_content.split('*').collect();
-
-        if ref_parts.len() >= 1 {
-            let ref_segment = REF {
-                ref01_reference_id_qualifier: ref_parts[0].to_string(),
-                ref02_reference_id: if ref_parts.len() > 1 {
-                    ref_parts[1].to_string()
-                } else {
-                    String::new()
-                },
-                ref03_description: if ref_parts.len() > 2 {
-                    ref_parts[2].to_string()
-                } else {
-                    String::new()
-                },
-            };
-
-            table1s.ref_segments.push(ref_segment);
-        }
-
-        info!("REF segment parsed");
-        contents = content_trim("REF", &contents);
-    }
-
-    // Parse DTM segments
-    while contents.contains("DTM") && check_if_segment_in_loop("DTM", "N1", &contents) {
-        info!("DTM segment found, ");
-        let dtm_content = get_segment_contents("DTM", &contents);
-
-        let dtm_parts: Vec<&str> = dtm_content.split('*').collect();
-
-        if dtm_parts.len() >= 1 {
-            let dtm_segment = DTM {
-                dtm01_date_time_qualifier: dtm_parts[0].to_string(),
-                dtm02_date: if dtm_parts.len() > 1 {
-                    dtm_parts[1].to_string()
-                } else {
-                    String::new()
-                },
-                dtm03_time: if dtm_parts.len() > 2 {
-                    dtm_parts[2].to_string()
-                } else {
-                    String::new()
-                },
-                dtm04_time_code: if dtm_parts.len() > 3 {
-                    dtm_parts[3].to_string()
-                } else {
-                    String::new()
-                },
-                dtm05_date_time_period_format_qualifier: if dtm_parts.len() > 4 {
-                    dtm_parts[4].to_string()
-                } else {
-                    String::new()
-                },
-                dtm06_date_time_period: if dtm_parts.len() > 5 {
-                    dtm_parts[5].to_string()
-                } else {
-                    String::new()
-                },
-            };
-
-            table1s.dtm_segments.push(dtm_segment);
+    let mut leftover = String::new();
+
+    // Table 1 runs up to the first N1 (loop 1000A/B) or ENT (loop 2000)
+    let segments = tokenize(contents);
+    let table1_end = find_segment(&segments, &["N1", "ENT", "SE"], 0).unwrap_or(segments.len());
+
+    for segment in &segments[..table1_end] {
+        match segment.id {
+            "ST" if table1s.st_segments == ST::default() => {
+                info!("ST segment found, ");
+                let st_parts = &segment.elements;
+
+                if st_parts.len() >= 2 {
+                    table1s.st_segments = ST {
+                        transaction_set_id: st_parts[0].to_string(),
+                        transaction_set_control_number: st_parts[1].to_string(),
+                        implementation_conven_ref: if st_parts.len() > 2 {
+                            st_parts[2].to_string()
+                        } else {
+                            String::new()
+                        },
+                    };
+                }
+                info!("ST segment parsed");
+            }
+            "BPR" if table1s.bpr_segments == BPR::default() => {
+                info!("BPR segment found, ");
+                let bpr_parts = &segment.elements;
+
+                if !bpr_parts.is_empty() {
+                    table1s.bpr_segments = BPR {
+                        bpr01_transaction_handling_code: if bpr_parts.len() > 0 {
+                            bpr_parts[0].to_string()
+                        } else {
+                            String::new()
+                        },
+                        bpr02_total_payment_amount: if bpr_parts.len() > 1 {
+                            bpr_parts[1].to_string()
+                        } else {
+                            String::new()
+                        },
+                        bpr03_credit_debit_flag_code: if bpr_parts.len() > 2 {
+                            bpr_parts[2].to_string()
+                        } else {
+                            String::new()
+                        },
+                        bpr04_payment_method_code: if bpr_parts.len() > 3 {
+                            bpr_parts[3].to_string()
+                        } else {
+                            String::new()
+                        },
+                        bpr05_payment_format_code: if bpr_parts.len() > 4 {
+                            bpr_parts[4].to_string()
+                        } else {
+                            String::new()
+                        },
+                        bpr06_dfi_id_number_qualifier: if bpr_parts.len() > 5 {
+                            bpr_parts[5].to_string()
+                        } else {
+                            String::new()
+                        },
+                        bpr07_dfi_id_number: if bpr_parts.len() > 6 {
+                            bpr_parts[6].to_string()
+                        } else {
+                            String::new()
+                        },
+                        bpr08_account_number_qualifier: if bpr_parts.len() > 7 {
+                            bpr_parts[7].to_string()
+                        } else {
+                            String::new()
+                        },
+                        bpr09_account_number: if bpr_parts.len() > 8 {
+                            bpr_parts[8].to_string()
+                        } else {
+                            String::new()
+                        },
+                        bpr10_originating_company_id: if bpr_parts.len() > 9 {
+                            bpr_parts[9].to_string()
+                        } else {
+                            String::new()
+                        },
+                        bpr11_originating_company_supplemental_code: if bpr_parts.len() > 10 {
+                            bpr_parts[10].to_string()
+                        } else {
+                            String::new()
+                        },
+                        bpr12_dfi_id_number_qualifier: if bpr_parts.len() > 11 {
+                            bpr_parts[11].to_string()
+                        } else {
+                            String::new()
+                        },
+                        bpr13_dfi_id_number: if bpr_parts.len() > 12 {
+                            bpr_parts[12].to_string()
+                        } else {
+                            String::new()
+                        },
+                        bpr14_account_number_qualifier: if bpr_parts.len() > 13 {
+                            bpr_parts[13].to_string()
+                        } else {
+                            String::new()
+                        },
+                        bpr15_account_number: if bpr_parts.len() > 14 {
+                            bpr_parts[14].to_string()
+                        } else {
+                            String::new()
+                        },
+                        bpr16_payment_effective_date: if bpr_parts.len() > 15 {
+                            bpr_parts[15].to_string()
+                        } else {
+                            String::new()
+                        },
+                    };
+                }
+                info!("BPR segment parsed");
+            }
+            "TRN" if table1s.trn_segments.is_none() => {
+                info!("TRN segment found, ");
+                let trn_parts = &segment.elements;
+
+                if trn_parts.len() >= 2 {
+                    table1s.trn_segments = Some(TRN {
+                        trn01_trace_type_code: trn_parts[0].to_string(),
+                        trn02_reference_id: trn_parts[1].to_string(),
+                        trn03_originating_company_id: if trn_parts.len() > 2 {
+                            trn_parts[2].to_string()
+                        } else {
+                            String::new()
+                        },
+                        trn04_reference_id: if trn_parts.len() > 3 {
+                            trn_parts[3].to_string()
+                        } else {
+                            String::new()
+                        },
+                    });
+                }
+                info!("TRN segment parsed");
+            }
+            "REF" => {
+                info!("REF segment found, ");
+                let ref_parts = &segment.elements;
+
+                if ref_parts.len() >= 1 {
+                    let ref_segment = REF {
+                        ref01_reference_id_qualifier: ref_parts[0].to_string(),
+                        ref02_reference_id: if ref_parts.len() > 1 {
+                            ref_parts[1].to_string()
+                        } else {
+                            String::new()
+                        },
+                        ref03_description: if ref_parts.len() > 2 {
+                            ref_parts[2].to_string()
+                        } else {
+                            String::new()
+                        },
+                    };
+
+                    table1s.ref_segments.push(ref_segment);
+                }
+                info!("REF segment parsed");
+            }
+            "DTM" => {
+                info!("DTM segment found, ");
+                let dtm_parts = &segment.elements;
+
+                if dtm_parts.len() >= 1 {
+                    let dtm_segment = DTM {
+                        dtm01_date_time_qualifier: dtm_parts[0].to_string(),
+                        dtm02_date: if dtm_parts.len() > 1 {
+                            dtm_parts[1].to_string()
+                        } else {
+                            String::new()
+                        },
+                        dtm03_time: if dtm_parts.len() > 2 {
+                            dtm_parts[2].to_string()
+                        } else {
+                            String::new()
+                        },
+                        dtm04_time_code: if dtm_parts.len() > 3 {
+                            dtm_parts[3].to_string()
+                        } else {
+                            String::new()
+                        },
+                        dtm05_date_time_period_format_qualifier: if dtm_parts.len() > 4 {
+                            dtm_parts[4].to_string()
+                        } else {
+                            String::new()
+                        },
+                        dtm06_date_time_period: if dtm_parts.len() > 5 {
+                            dtm_parts[5].to_string()
+                        } else {
+                            String::new()
+                        },
+                    };
+
+                    table1s.dtm_segments.push(dtm_segment);
+                }
+                info!("DTM segment parsed");
+            }
+            _ => leftover.push_str(&segment.to_edi()),
         }
-
-        info!("DTM segment parsed");
-        contents = content_trim("DTM", &contents);
     }
 
     info!("Table 1 parsed\n");
 
+    let mut contents = join_segments(&segments[table1_end..]);
+    contents.push_str(&leftover);
     return (table1s, contents);
 }
 
@@ -173,7 +173,7 @@ mod tests {
         edi820.interchange_trailer.iea_segments.interchange_control_number = "000010216".to_string();
         
         // Generate EDI
-        let edi_output = edi820.to_edi();
+        let edi_output = edi820.to_edi().unwrap();
         
         // Verify output contains expected segments
         assert!(edi_output.contains("ISA*"), "Missing ISA segment");
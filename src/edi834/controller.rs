@@ -7,6 +7,13 @@ use crate::edi834::loop1000b::*;
 use crate::edi834::loop2000::*;
 use crate::edi834::table1::*;
 use crate::error::EdiResult;
+use crate::helper::delimiters::Delimiters;
+use crate::helper::diagnostics::{collect_diagnostics, Diagnostics};
+use crate::helper::helper::clean_contents;
+use crate::helper::source_map::locate_error;
+use crate::helper::tokenizer::{find_segment, join_segments, split_loops, tokenize};
+use crate::helper::unrecognized::UnrecognizedSegments;
+use crate::helper::writer_options::WriterOptions;
 use crate::segments::se::*;
 use crate::transaction_processor::TransactionSet;
 
@@ -19,6 +26,15 @@ pub struct Edi834 {
     pub loop2000_segments: Vec<Loop2000>, // Member Level
     pub se_segments: SE,
     pub interchange_trailer: InterchangeTrailer,
+    /// Delimiters declared by the source ISA, re-emitted when writing EDI
+    #[serde(default)]
+    pub delimiters: Delimiters,
+    /// Problems found while parsing: envelope mismatches, invalid values, unprocessed segments
+    #[serde(default, skip_serializing_if = "Diagnostics::is_empty")]
+    pub diagnostics: Diagnostics,
+    /// Segments the parser did not recognize, written back where they appeared
+    #[serde(default, skip_serializing_if = "UnrecognizedSegments::is_empty")]
+    pub unrecognized: UnrecognizedSegments,
 }
 
 impl TransactionSet for Edi834 {
@@ -26,11 +42,11 @@ impl TransactionSet for Edi834 {
     where
         Self: Sized,
     {
-        get_834(&contents)
+        parse_834(&contents)
     }
 
-    fn to_edi(&self) -> String {
-        write_834(self)
+    fn to_edi_with(&self, options: &WriterOptions) -> EdiResult<String> {
+        write_834_with(self, options)
     }
 
     fn get_transaction_type() -> &'static str {
@@ -42,13 +58,26 @@ impl TransactionSet for Edi834 {
     }
 }
 
-pub fn get_834(contents: &str) -> EdiResult<(Edi834, String)> {
+/// Parse an EDI 834 file into an Edi834 structure; use `Edi834::parse`
+/// to also get the content that follows its transaction set
+pub fn get_834(contents: &str) -> EdiResult<Edi834> {
+    Ok(Edi834::parse(contents.to_string())?.0)
+}
+
+fn parse_834(contents: &str) -> EdiResult<(Edi834, String)> {
+    let raw = contents;
     let mut contents = contents.to_string();
     let mut edi834 = Edi834::default();
 
     // Remove BOM if present
     contents = contents.trim_start_matches("\u{feff}").to_string();
 
+    // Capture the source delimiters, then normalize to `*` and `~`
+    let delimiters = Delimiters::detect(&contents);
+    contents = clean_contents(contents);
+    let normalized = contents.clone();
+    edi834.delimiters = delimiters;
+
     // Parse Interchange Header
     let (interchange_header, new_contents) = get_interchange_header(&contents);
     edi834.interchange_header = interchange_header;
@@ -60,7 +89,7 @@ pub fn get_834(contents: &str) -> EdiResult<(Edi834, String)> {
             edi834.table1 = table1;
             contents = new_contents;
         }
-        Err(e) => return Err(e),
+        Err(e) => return Err(locate_error(raw, &contents, e)),
     }
 
     // Parse Loop1000A (Sponsor)
@@ -77,12 +106,18 @@ pub fn get_834(contents: &str) -> EdiResult<(Edi834, String)> {
         contents = new_contents;
     }
 
-    // Parse Loop2000 segments (Member Level)
-    while contents.contains("INS*") {
-        let (loop2000, new_contents) = get_loop2000(&contents);
+    // Parse Loop2000 segments (Member Level) — each INS starts a member, SE ends the detail
+    let segments = tokenize(&contents);
+    let members_end = find_segment(&segments, &["SE"], 0).unwrap_or(segments.len());
+    let (leading, members) = split_loops(&segments[..members_end], &["INS"]);
+    let mut leftover = join_segments(leading);
+    for member_segments in members {
+        let (loop2000, new_contents) = get_loop2000(&join_segments(member_segments));
         edi834.loop2000_segments.push(loop2000);
-        contents = new_contents;
+        leftover.push_str(&new_contents);
     }
+    leftover.push_str(&join_segments(&segments[members_end..]));
+    contents = leftover;
 
     // Parse SE segment
     if let Some(se_start) = contents.find("SE*") {
@@ -99,10 +134,16 @@ pub fn get_834(contents: &str) -> EdiResult<(Edi834, String)> {
     contents = new_contents;
 
     info!("Parsed EDI834: {:?}", edi834);
+    edi834.diagnostics = collect_diagnostics(&normalized, &contents, &delimiters);
+    edi834.unrecognized = UnrecognizedSegments::collect(&normalized, &contents);
     Ok((edi834, contents))
 }
 
-pub fn write_834(edi834: &Edi834) -> String {
+pub fn write_834(edi834: &Edi834) -> EdiResult<String> {
+    write_834_with(edi834, &WriterOptions::default())
+}
+
+pub fn write_834_with(edi834: &Edi834, options: &WriterOptions) -> EdiResult<String> {
     let mut result = String::new();
 
     result.push_str(&write_interchange_header(edi834.interchange_header.clone()));
@@ -126,7 +167,8 @@ pub fn write_834(edi834: &Edi834) -> String {
         edi834.interchange_trailer.clone(),
     ));
 
-    result
+    let result = edi834.unrecognized.restore(result, &Delimiters::default());
+    options.format(&result, &edi834.delimiters)
 }
 
 #[cfg(test)]
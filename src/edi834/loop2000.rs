@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 use crate::edi834::loop2100_generic::*;
 use crate::edi834::loop2100a::*;
 use crate::edi834::loop2300::*;
+use crate::helper::tokenizer::{find_segment, tokenize};
 use crate::segments::dtp::*;
 use crate::segments::ins::*;
 use crate::segments::r#ref::*;
@@ -24,12 +25,14 @@ pub struct Loop2000 {
     pub loop2300_segments: Vec<Loop2300>, // Health Coverage (contains Loop2320/2330)
 }
 
-/// Check if segment exists within current member boundary (before next INS* or SE*)
+/// Check if segment exists within current member boundary (before next INS* or SE*).
+/// `segment` is a segment prefix such as `NM1*IL*` and is only matched at segment starts.
 fn in_current_member(contents: &str, segment: &str) -> bool {
-    let ins_pos = contents.find("INS*").unwrap_or(contents.len());
-    let se_pos = contents.find("SE*").unwrap_or(contents.len());
-    let boundary = ins_pos.min(se_pos);
-    contents[..boundary].contains(segment)
+    let segments = tokenize(contents);
+    let boundary = find_segment(&segments, &["INS", "SE"], 0).unwrap_or(segments.len());
+    segments[..boundary]
+        .iter()
+        .any(|s| s.raw.starts_with(segment) || segment.strip_suffix('*') == Some(s.raw))
 }
 
 pub fn get_loop2000(contents: &str) -> (Loop2000, String) {
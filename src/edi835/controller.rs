@@ -1,5 +1,3 @@
-use log::info;
-
 use serde::{Deserialize, Serialize};
 
 use crate::edi835::interchangecontrol::*;
@@ -9,6 +7,13 @@ use crate::edi835::loop1000b::*;
 use crate::edi835::loop2000::*;
 use crate::edi835::table1::*;
 use crate::edi835::table3::*;
+use crate::error::EdiResult;
+use crate::helper::delimiters::Delimiters;
+use crate::helper::diagnostics::{collect_diagnostics, Diagnostics};
+use crate::helper::helper::clean_contents;
+use crate::helper::unrecognized::UnrecognizedSegments;
+use crate::helper::writer_options::WriterOptions;
+use crate::transaction_processor::TransactionSet;
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Table1 {
@@ -24,113 +29,157 @@ pub struct Edi835 {
     pub table2s: Vec<Table2>,
     pub table3s: Table3s,
     pub interchange_trailer: InterchangeTrailer,
+    /// Delimiters declared by the source ISA, re-emitted when writing EDI
+    #[serde(default)]
+    pub delimiters: Delimiters,
+    /// Problems found while parsing: envelope mismatches, invalid values, unprocessed segments
+    #[serde(default, skip_serializing_if = "Diagnostics::is_empty")]
+    pub diagnostics: Diagnostics,
+    /// Segments the parser did not recognize, written back where they appeared
+    #[serde(default, skip_serializing_if = "UnrecognizedSegments::is_empty")]
+    pub unrecognized: UnrecognizedSegments,
 }
 
-pub fn get_835(contents: &str) -> Edi835 {
-    let mut contents = contents.to_string();
-    let interchange_header;
-    let table1s;
-    let loop1000as;
-    let loop1000bs;
-    let table2s;
-    let table3s;
-    let interchange_trailer;
-    let table1;
-
-    // Control Segments
-    (interchange_header, contents) = get_interchange_header(&contents);
-
-    // Table 1
-    (table1s, contents) = get_table1s(&contents);
-
-    // Loop 1000A Payer Identification
-    (loop1000as, contents) = get_1000as(&contents);
-
-    // Loop 1000B Payee Identification
-    (loop1000bs, contents) = get_1000bs(&contents);
-
-    // table 1 combined
-    table1 = Table1 {
-        table1: table1s.clone(),
-        loop1000as: loop1000as.clone(),
-        loop1000bs: loop1000bs.clone(),
-    };
-
-    // loop 2000
-    (table2s, contents) = get_loop_2000s(&contents);
-
-    // Table 3
-    (table3s, contents) = get_table3s(&contents);
-
-    // Control Trailer
-    (interchange_trailer, contents) = get_interchange_trailer(&contents);
-
-    let edi835 = Edi835 {
-        interchange_header,
-        table1,
-        table2s,
-        table3s,
-        interchange_trailer,
-    };
-
-    info!("Unprocessed segments: {:?}", contents);
-
-    // Validate monetary fields per X12 §B.1.1.3.1.2
-    use crate::helper::numeric_validation::warn_if_invalid_monetary;
-    warn_if_invalid_monetary(
-        "BPR",
-        "02",
-        &edi835.table1.table1.bpr_segments.bpr02_monetary_amount,
-    );
-    for t2 in &edi835.table2s {
-        for l in &t2.loop2100s {
-            warn_if_invalid_monetary("CLP", "03", &l.clp_segments.clp03_total_claim_charge_amount);
-            warn_if_invalid_monetary(
-                "CLP",
-                "04",
-                &l.clp_segments.clp04_total_claim_payment_amount,
-            );
-        }
+impl TransactionSet for Edi835 {
+    fn parse(mut contents: String) -> EdiResult<(Self, String)> {
+        let interchange_header;
+        let table1s;
+        let loop1000as;
+        let loop1000bs;
+        let table2s;
+        let table3s;
+        let interchange_trailer;
+        let table1;
+
+        // Capture the source delimiters, then normalize to `*` and `~`
+        let delimiters = Delimiters::detect(&contents);
+        contents = clean_contents(contents);
+        let normalized = contents.clone();
+
+        // Control Segments
+        (interchange_header, contents) = get_interchange_header(&contents);
+
+        // Table 1
+        (table1s, contents) = get_table1s(&contents);
+
+        // Loop 1000A Payer Identification
+        (loop1000as, contents) = get_1000as(&contents);
+
+        // Loop 1000B Payee Identification
+        (loop1000bs, contents) = get_1000bs(&contents);
+
+        // table 1 combined
+        table1 = Table1 {
+            table1: table1s.clone(),
+            loop1000as: loop1000as.clone(),
+            loop1000bs: loop1000bs.clone(),
+        };
+
+        // loop 2000
+        (table2s, contents) = get_loop_2000s(&contents);
+
+        // Table 3
+        (table3s, contents) = get_table3s(&contents);
+
+        // Control Trailer
+        (interchange_trailer, contents) = get_interchange_trailer(&contents);
+
+        let edi835 = Edi835 {
+            interchange_header,
+            table1,
+            table2s,
+            table3s,
+            interchange_trailer,
+            delimiters,
+            // Includes monetary fields validated per X12 §B.1.1.3.1.2
+            diagnostics: collect_diagnostics(&normalized, &contents, &delimiters),
+            unrecognized: UnrecognizedSegments::collect(&normalized, &contents),
+        };
+        Ok((edi835, contents))
+    }
+
+    fn to_edi_with(&self, options: &WriterOptions) -> EdiResult<String> {
+        write_835_with(self, options)
     }
 
-    edi835
+    fn get_transaction_type() -> &'static str {
+        "835"
+    }
 }
 
-pub fn write_835(contents: String) -> String {
-    let edi_json: Edi835 = match serde_json::from_str(&contents) {
-        Ok(v) => v,
-        Err(e) => {
-            log::warn!("Failed to parse 835 JSON: {}", e);
-            return String::new();
-        }
-    };
+pub fn get_835(contents: &str) -> EdiResult<Edi835> {
+    Ok(Edi835::parse(contents.to_string())?.0)
+}
+
+pub fn write_835(edi835: &Edi835) -> EdiResult<String> {
+    write_835_with(edi835, &WriterOptions::default())
+}
+
+pub fn write_835_with(edi835: &Edi835, options: &WriterOptions) -> EdiResult<String> {
     let mut new_edi = String::new();
 
     // Write interchange control header
     new_edi.push_str(&write_interchange_control(
-        edi_json.interchange_header.clone(),
+        edi835.interchange_header.clone(),
     ));
 
     // Write table 1 segments
-    new_edi.push_str(&write_table1(edi_json.table1.table1.clone()));
+    new_edi.push_str(&write_table1(edi835.table1.table1.clone()));
 
     // Write loop 1000A segments (payer identification)
-    new_edi.push_str(&write_loop1000a(edi_json.table1.loop1000as.clone()));
+    new_edi.push_str(&write_loop1000a(edi835.table1.loop1000as.clone()));
 
     // Write loop 1000B segments (payee identification)
-    new_edi.push_str(&write_loop1000b(edi_json.table1.loop1000bs.clone()));
+    new_edi.push_str(&write_loop1000b(edi835.table1.loop1000bs.clone()));
 
     // Write loop 2000 segments (claim payment information)
-    new_edi.push_str(&write_loop2000(edi_json.table2s.clone()));
+    new_edi.push_str(&write_loop2000(edi835.table2s.clone()));
 
     // Write table 3 segments (summary)
-    new_edi.push_str(&write_table3(edi_json.table3s.clone()));
+    new_edi.push_str(&write_table3(edi835.table3s.clone()));
 
     // Write interchange control trailer
     new_edi.push_str(&write_interchange_trailer(
-        edi_json.interchange_trailer.clone(),
+        edi835.interchange_trailer.clone(),
     ));
 
-    // Add newline after each segment terminator for one-segment-per-line output
-    new_edi.replace("~", "~\n")
+    let new_edi = edi835.unrecognized.restore(new_edi, &Delimiters::default());
+    let new_edi = options.format(&new_edi, &edi835.delimiters)?;
+    Ok(new_edi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::diagnostics::DiagnosticCode;
+
+    const SAMPLE_835: &str = "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230501*1200*^*00501*000000001*0*T*:~GS*HP*S*R*20230501*1200*1*X*005010X221A1~ST*835*0001~BPR*I*100*C*ACH*CCP*01*999999999*DA*123456*1512345678**01*999988880*DA*98765*20230501~TRN*1*12345*1512345678~N1*PR*PAYER~N1*PE*PAYEE*XX*1234567890~LX*1~CLP*A*1*100*80~SVC*HC:99213*100*80~SE*9*0001~GE*1*1~IEA*1*000000001~";
+
+    #[test]
+    fn test_transaction_set_round_trip() {
+        let (edi835, remaining) = Edi835::parse(SAMPLE_835.to_string()).unwrap();
+        assert!(remaining.is_empty());
+        assert_eq!(Edi835::get_transaction_type(), "835");
+        assert!(Edi835::detect(SAMPLE_835));
+        let output = edi835.to_edi().unwrap();
+        assert_eq!(output.replace('\n', ""), SAMPLE_835);
+    }
+
+    #[test]
+    fn test_data_character_swap_is_reported() {
+        let contents = SAMPLE_835
+            .replace('*', "|")
+            .replace("|^|", "|!|")
+            .replace("|:~", "|>~")
+            .replace("HC:99213", "HC>99213")
+            .replace("N1|PE|PAYEE", "N1|PE|PAYEE:EAST");
+        let edi835 = get_835(&contents).unwrap();
+        let found: Vec<&str> = edi835
+            .diagnostics
+            .with_code(DiagnosticCode::DelimiterInData)
+            .map(|d| d.loop_path.as_str())
+            .collect();
+        assert_eq!(found, vec!["1000B/N102"]);
+        assert_eq!(edi835.to_edi().unwrap().replace('\n', ""), contents);
+    }
 }
@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::edi835::loop2100::*;
 use crate::helper::edihelper::*;
+use crate::helper::tokenizer::*;
 use crate::segments::lx::*;
 use crate::segments::ts2::*;
 use crate::segments::ts3::*;
@@ -52,15 +53,18 @@ pub fn get_loop_2000(contents: &str) -> (LX, TS3, TS2, String) {
 }
 
 pub fn get_loop_2000s(contents: &str) -> (Vec<Table2>, String) {
-    let mut contents = contents.to_string();
-    let lx_count = contents.matches("LX").count();
+    // Table 2 ends at the first PLB or SE; each LX starts a new loop 2000
+    let segments = tokenize(contents);
+    let table2_end = find_segment(&segments, &["PLB", "SE"], 0).unwrap_or(segments.len());
+    let (leading, loops) = split_loops(&segments[..table2_end], &["LX"]);
     let mut loop_2000_array = vec![];
-    info!("Number of loops in loop 2000: {:?}", lx_count);
+    let mut leftover = join_segments(leading);
+    info!("Number of loops in loop 2000: {:?}", loops.len());
 
-    for _ in 0..lx_count {
-        let (lx, ts3, ts2, loop2100s);
-        (lx, ts3, ts2, contents) = get_loop_2000(&contents);
-        (loop2100s, contents) = get_loop_2100s(&contents);
+    for loop_segments in loops {
+        let (lx, ts3, ts2, loop_contents) = get_loop_2000(&join_segments(loop_segments));
+        let (loop2100s, rem_contents) = get_loop_2100s(&loop_contents);
+        leftover.push_str(&rem_contents);
 
         let loop2000s = Table2 {
             lx_segments: lx,
@@ -72,6 +76,8 @@ pub fn get_loop_2000s(contents: &str) -> (Vec<Table2>, String) {
         loop_2000_array.push(loop2000s);
     }
 
+    let mut contents = join_segments(&segments[table2_end..]);
+    contents.push_str(&leftover);
     return (loop_2000_array, contents);
 }
 
@@ -105,4 +111,20 @@ mod tests {
         assert_eq!(ts2_segments.ts201_total_drg_amount, "2178.45");
         assert_eq!(ts3_segments.ts301_provider_identifier, "6543210903");
     }
+
+    #[test]
+    fn test_get_loop_2000s_multiple_lx() {
+        let contents = String::from("LX*1~CLP*A1*1*100*80**12*111~NM1*QC*1*DOE*JOHN~LX*2~CLP*B1*1*50*50**12*222~NM1*QC*1*ROE*JANE~CLP*B2*1*20*0**12*333~NM1*QC*1*POE*JIM~PLB*123*20191231*CV:9876514*-1.27~SE*12*35681~");
+        let (table2s, contents) = get_loop_2000s(&contents);
+        assert_eq!(table2s.len(), 2);
+        assert_eq!(table2s[0].loop2100s.len(), 1);
+        assert_eq!(table2s[1].loop2100s.len(), 2);
+        assert_eq!(
+            table2s[1].loop2100s[1]
+                .clp_segments
+                .clp01_patient_control_number,
+            "B2"
+        );
+        assert_eq!(contents, "PLB*123*20191231*CV:9876514*-1.27~SE*12*35681~");
+    }
 }
@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::edi835::loop2110::*;
 use crate::helper::edihelper::*;
+use crate::helper::tokenizer::*;
 use crate::segments::amt::*;
 use crate::segments::cas::*;
 use crate::segments::clp::*;
@@ -317,11 +318,12 @@ pub fn get_loop_2100(
 }
 
 pub fn get_loop_2100s(contents: &str) -> (Vec<Loop2100s>, String) {
-    let mut contents = contents.to_string();
-    let clp_count = contents.matches("CLP").count();
+    let segments = tokenize(contents);
+    let (leading, claims) = split_loops(&segments, &["CLP"]);
+    let mut contents = join_segments(leading);
     let mut loop_2100_array = vec![];
-    info!("Number of loops in loop 2100: {:?}", clp_count);
-    for _ in 0..clp_count {
+    info!("Number of loops in loop 2100: {:?}", claims.len());
+    for claim_segments in claims {
         let (
             clp_segments,
             cas_segments,
@@ -347,7 +349,7 @@ pub fn get_loop_2100s(contents: &str) -> (Vec<Loop2100s>, String) {
             inner_rem_contents,
         );
 
-        let tmp_contents = get_table2(&contents);
+        let tmp_contents = join_segments(claim_segments);
 
         (
             clp_segments,
@@ -396,7 +398,6 @@ pub fn get_loop_2100s(contents: &str) -> (Vec<Loop2100s>, String) {
             qty_segments,
             loop2110s,
         };
-        contents = contents.replacen(&tmp_contents, "", 1);
         contents.push_str(&inner_rem_contents);
         loop_2100_array.push(loop2100);
     }
@@ -2,6 +2,7 @@ use log::info;
 use serde::{Deserialize, Serialize};
 
 use crate::helper::edihelper::*;
+use crate::helper::tokenizer::*;
 use crate::segments::amt::*;
 use crate::segments::cas::*;
 use crate::segments::dtm::*;
@@ -202,12 +203,13 @@ pub fn get_loop_2110(
 }
 
 pub fn get_loop_2110s(contents: &str) -> (Vec<Loop2110s>, String) {
-    let mut contents = contents.to_string();
-    let svc_count = contents.matches("SVC").count();
+    let segments = tokenize(contents);
+    let (leading, service_lines) = split_loops(&segments, &["SVC"]);
+    let mut contents = join_segments(leading);
     let mut loop_2110_array = vec![];
-    info!("Number of loops in loop 2110: {:?}", svc_count);
-    for _ in 0..svc_count {
-        let tmp_contents = get_loop_contents("SVC", "SVC", &contents);
+    info!("Number of loops in loop 2110: {:?}", service_lines.len());
+    for service_segments in service_lines {
+        let tmp_contents = join_segments(service_segments);
 
         let (
             svc_segments,
@@ -248,7 +250,6 @@ pub fn get_loop_2110s(contents: &str) -> (Vec<Loop2110s>, String) {
             qty_segments,
             lq_segments,
         };
-        contents = contents.replacen(&tmp_contents, "", 1);
         contents.push_str(&rem_contents);
         loop_2110_array.push(loop2110);
     }
@@ -302,7 +303,7 @@ mod tests {
 
     #[test]
     fn test_get_loop_2110() {
-        let contents = String::from("~SVC*HC|99213*500*100**1~DTM*472*20191001~CAS*OA*23*400~REF*BB*1~AMT*B6*450~SE*22*35681~GE*1*1~IEA*1*000000905~");
+        let contents = String::from("~SVC*HC:99213*500*100**1~DTM*472*20191001~CAS*OA*23*400~REF*BB*1~AMT*B6*450~SE*22*35681~GE*1*1~IEA*1*000000905~");
         let (
             svc,
             dtm,
@@ -317,7 +318,11 @@ mod tests {
             contents,
         ) = get_loop_2110(&contents);
         assert_eq!(contents, "SE*22*35681~GE*1*1~IEA*1*000000905~");
-        assert_eq!(svc.svc01_1_product_or_service_is_qualifier, "HC|99213");
+        assert_eq!(
+            svc.svc01_composite_medical_procedure_id
+                .c003_02_product_or_service_id,
+            "99213"
+        );
         assert_eq!(dtm[0].date_time_qualifier, "472");
         assert_eq!(cas[0].cas01_claim_adjustment_group_code, "OA");
         assert_eq!(ref_service_identification.len(), 1);
@@ -1,4 +1,11 @@
 use crate::error::{EdiError, EdiResult};
+use crate::helper::delimiters::Delimiters;
+use crate::helper::diagnostics::{collect_diagnostics, Diagnostics};
+use crate::helper::helper::clean_contents;
+use crate::helper::source_map::locate_error;
+use crate::helper::tokenizer::{find_segment, join_segments, split_loops, tokenize, Segment};
+use crate::helper::unrecognized::UnrecognizedSegments;
+use crate::helper::writer_options::WriterOptions;
 use crate::transaction_processor::TransactionSet;
 use log::info;
 use serde::{Deserialize, Serialize};
@@ -44,6 +51,15 @@ pub struct Edi837 {
     /// Subscriber levels — each contains nested patients and/or claims
     pub loop2000b: Vec<Loop2000b>,
     pub interchange_trailer: InterchangeTrailer,
+    /// Delimiters declared by the source ISA, re-emitted when writing EDI
+    #[serde(default)]
+    pub delimiters: Delimiters,
+    /// Problems found while parsing: envelope mismatches, invalid values, unprocessed segments
+    #[serde(default, skip_serializing_if = "Diagnostics::is_empty")]
+    pub diagnostics: Diagnostics,
+    /// Segments the parser did not recognize, written back where they appeared
+    #[serde(default, skip_serializing_if = "UnrecognizedSegments::is_empty")]
+    pub unrecognized: UnrecognizedSegments,
     pub isa: String,
     pub gs: String,
     pub st: String,
@@ -69,62 +85,41 @@ fn detect_subtype(contents: &str) -> EdiResult<Edi837Subtype> {
     }
 }
 
-/// Parse claims (Loop2300 + nested Loop2400) from remaining content
-fn parse_claims(remaining_content: &mut String) -> Vec<Loop2300> {
+/// Parse claims (Loop2300 + nested Loop2400) from the segments following an HL level.
+/// Segments that do not belong to a claim are appended to `leftover`.
+pub fn parse_claims(segments: &[Segment], leftover: &mut String) -> Vec<Loop2300> {
+    let (leading, claim_loops) = split_loops(segments, &["CLM"]);
+    leftover.push_str(&join_segments(leading));
+
     let mut claims = Vec::new();
-    while remaining_content.contains("CLM*") {
-        // Stop if the next HL comes before the next CLM
-        if let Some(hl_pos) = remaining_content.find("HL*") {
-            if let Some(clm_pos) = remaining_content.find("CLM*") {
-                if hl_pos < clm_pos {
-                    break;
-                }
-            }
-        }
-        let (loop2300, remaining) = parse_loop2300(remaining_content);
-        if loop2300.clm.is_empty() {
-            break;
+    for claim_segments in claim_loops {
+        // Each LX starts a Loop2400 service line within the claim
+        let (header, service_lines) = split_loops(claim_segments, &["LX"]);
+        let (mut loop2300, remaining) = parse_loop2300(&join_segments(header));
+        leftover.push_str(&remaining);
+
+        for line_segments in service_lines {
+            let (loop2400, remaining) = parse_loop2400(&join_segments(line_segments));
+            leftover.push_str(&remaining);
+            loop2300.loop2400.push(loop2400);
         }
         claims.push(loop2300);
-        *remaining_content = remaining;
-
-        // Parse Loop2400 service lines for this claim
-        let mut service_lines = Vec::new();
-        while remaining_content.contains("LX*") {
-            // Stop if next HL or CLM comes before next LX
-            if let Some(lx_pos) = remaining_content.find("LX*") {
-                if let Some(hl_pos) = remaining_content.find("HL*") {
-                    if hl_pos < lx_pos {
-                        break;
-                    }
-                }
-                if let Some(clm_pos) = remaining_content.find("CLM*") {
-                    if clm_pos < lx_pos {
-                        break;
-                    }
-                }
-            }
-            let (loop2400, remaining) = parse_loop2400(remaining_content);
-            if loop2400.lx.is_empty() {
-                break;
-            }
-            service_lines.push(loop2400);
-            *remaining_content = remaining;
-        }
-        if !service_lines.is_empty() {
-            let last = claims.len() - 1;
-            claims[last].loop2400 = service_lines;
-        }
     }
     claims
 }
 
-fn parse_837_common(contents: String) -> EdiResult<(Edi837, String)> {
+fn parse_837_common(raw: String) -> EdiResult<(Edi837, String)> {
+    // Capture the source delimiters, then normalize to `*` and `~`
+    let delimiters = Delimiters::detect(&raw);
+    let contents = clean_contents(raw.clone());
+    let normalized = contents.clone();
+
     let subtype = detect_subtype(&contents)?;
     info!("Parsing EDI837 {:?} content", subtype);
 
     let mut edi837 = Edi837 {
         subtype,
+        delimiters,
         ..Default::default()
     };
     let mut remaining_content = contents;
@@ -143,10 +138,11 @@ fn parse_837_common(contents: String) -> EdiResult<(Edi837, String)> {
             *field = remaining_content[pos..=end].to_string();
             remaining_content = remaining_content[end + 1..].to_string();
         } else {
-            return Err(EdiError::MissingSegment(format!(
-                "{} segment not found",
-                name
-            )));
+            return Err(locate_error(
+                &raw,
+                &remaining_content,
+                EdiError::MissingSegment(format!("{} segment not found", name)),
+            ));
         }
     }
 
@@ -158,8 +154,10 @@ fn parse_837_common(contents: String) -> EdiResult<(Edi837, String)> {
         edi837.table1.table1.bht = remaining_content[bht_pos..=bht_end].to_string();
         remaining_content = remaining_content[bht_end + 1..].to_string();
     } else {
-        return Err(EdiError::MissingSegment(
-            "BHT segment not found".to_string(),
+        return Err(locate_error(
+            &raw,
+            &remaining_content,
+            EdiError::MissingSegment("BHT segment not found".to_string()),
         ));
     }
 
@@ -189,70 +187,40 @@ fn parse_837_common(contents: String) -> EdiResult<(Edi837, String)> {
 
     // Walk HL segments to build the tree.
     // HL*n*parent*22* = subscriber, HL*n*parent*23* = patient
-    // After each subscriber, parse its patients and claims based on HL nesting.
-    while remaining_content.contains("HL*") {
-        // Peek at the next HL to determine its level code
-        let hl_pos = match remaining_content.find("HL*") {
-            Some(p) => p,
-            None => break,
-        };
-        let hl_end = remaining_content[hl_pos..]
-            .find('~')
-            .unwrap_or(remaining_content.len())
-            + hl_pos;
-        let hl_seg = &remaining_content[hl_pos..=hl_end];
-
-        if hl_seg.contains("*22*") {
+    // Each HL level runs up to the next HL; the hierarchy ends at SE.
+    let segments = tokenize(&remaining_content);
+    let body_end = find_segment(&segments, &["SE"], 0).unwrap_or(segments.len());
+    let (leading, levels) = split_loops(&segments[..body_end], &["HL"]);
+    let mut leftover = join_segments(leading);
+    let mut levels = levels.into_iter().peekable();
+
+    while let Some(level) = levels.next() {
+        if level[0].element(2) == "22" {
             // Subscriber level
-            let (mut loop2000b, remaining) = parse_loop2000b(&remaining_content);
-            if loop2000b.hl.is_empty() {
-                break;
-            }
-            remaining_content = remaining;
+            let (mut loop2000b, remaining) = parse_loop2000b(&join_segments(level));
 
             // Check if subscriber has children (HL04=1) or is also the patient (HL04=0)
-            let has_children = loop2000b.hl.contains("*1~");
-
-            if has_children {
-                // Parse child Loop2000C (patient) levels
-                while remaining_content.contains("HL*") {
-                    let next_hl_pos = match remaining_content.find("HL*") {
-                        Some(p) => p,
-                        None => break,
-                    };
-                    let next_hl_end = remaining_content[next_hl_pos..]
-                        .find('~')
-                        .unwrap_or(remaining_content.len())
-                        + next_hl_pos;
-                    let next_hl = &remaining_content[next_hl_pos..=next_hl_end];
-
-                    if next_hl.contains("*23*") {
-                        // Patient level — child of this subscriber
-                        let (mut loop2000c, remaining) = parse_loop2000c(&remaining_content);
-                        if loop2000c.hl.is_empty() {
-                            break;
-                        }
-                        remaining_content = remaining;
-
-                        // Parse claims for this patient
-                        loop2000c.loop2300 = parse_claims(&mut remaining_content);
-                        loop2000b.loop2000c.push(loop2000c);
-                    } else {
-                        // Next HL is a new subscriber — stop parsing children
-                        break;
-                    }
+            if level[0].element(3) == "1" {
+                leftover.push_str(&remaining);
+
+                // Parse child Loop2000C (patient) levels until the next subscriber
+                while let Some(child) = levels.next_if(|next| next[0].element(2) == "23") {
+                    let (mut loop2000c, remaining) = parse_loop2000c(&join_segments(child));
+                    loop2000c.loop2300 = parse_claims(&tokenize(&remaining), &mut leftover);
+                    loop2000b.loop2000c.push(loop2000c);
                 }
             } else {
                 // Subscriber IS the patient — claims attach directly
-                loop2000b.loop2300 = parse_claims(&mut remaining_content);
+                loop2000b.loop2300 = parse_claims(&tokenize(&remaining), &mut leftover);
             }
 
             edi837.loop2000b.push(loop2000b);
         } else {
-            // Unknown HL level or orphan — skip past it to avoid infinite loop
-            remaining_content = remaining_content[hl_end + 1..].to_string();
+            // Unknown HL level or orphan — keep it with the unprocessed content
+            leftover.push_str(&join_segments(level));
         }
     }
+    remaining_content = join_segments(&segments[body_end..]);
 
     // Parse trailer segments
     for (seg, field) in [
@@ -270,10 +238,13 @@ fn parse_837_common(contents: String) -> EdiResult<(Edi837, String)> {
         }
     }
 
-    Ok((edi837, remaining_content))
+    leftover.push_str(&remaining_content);
+    edi837.diagnostics = collect_diagnostics(&normalized, &leftover, &delimiters);
+    edi837.unrecognized = UnrecognizedSegments::collect(&normalized, &leftover);
+    Ok((edi837, leftover))
 }
 
-fn write_837_common(edi837: &Edi837) -> EdiResult<String> {
+fn write_837_common(edi837: &Edi837, options: &WriterOptions) -> EdiResult<String> {
     info!("Generating EDI837 {:?} content", edi837.subtype);
 
     let mut result = String::new();
@@ -311,7 +282,8 @@ fn write_837_common(edi837: &Edi837) -> EdiResult<String> {
     result.push_str(&edi837.iea);
     result.push('\n');
 
-    Ok(result)
+    let result = edi837.unrecognized.restore(result, &Delimiters::default());
+    options.format(&result, &edi837.delimiters)
 }
 
 impl TransactionSet for Edi837 {
@@ -319,8 +291,8 @@ impl TransactionSet for Edi837 {
         parse_837_common(contents)
     }
 
-    fn to_edi(&self) -> String {
-        write_837_common(self).unwrap_or_default()
+    fn to_edi_with(&self, options: &WriterOptions) -> EdiResult<String> {
+        write_837_common(self, options)
     }
 
     fn get_transaction_type() -> &'static str {
@@ -340,7 +312,11 @@ pub fn get_837(content: &str) -> EdiResult<Edi837> {
 }
 
 pub fn write_837(edi837: &Edi837) -> EdiResult<String> {
-    write_837_common(edi837)
+    write_837_common(edi837, &WriterOptions::default())
+}
+
+pub fn write_837_with(edi837: &Edi837, options: &WriterOptions) -> EdiResult<String> {
+    write_837_common(edi837, options)
 }
 
 // Backward-compatible wrappers
@@ -486,6 +462,45 @@ mod tests {
         assert!(patient_pos < sub2_pos);
     }
 
+    #[test]
+    fn test_roundtrip_preserves_custom_delimiters() {
+        let source = SAMPLE_837P.replace('*', "|").replace('~', "\n");
+        let edi837 = get_837(&source).unwrap();
+        assert_eq!(edi837.delimiters.element, '|');
+        assert_eq!(edi837.delimiters.segment, '\n');
+        assert_eq!(edi837.loop2000b[0].loop2300.len(), 1);
+
+        let generated = write_837(&edi837).unwrap();
+        assert_eq!(generated, source);
+    }
+
+    #[test]
+    fn test_write_with_options() {
+        let edi837 = get_837(SAMPLE_837P).unwrap();
+        let generated = write_837_with(&edi837, &WriterOptions::single_line()).unwrap();
+        assert_eq!(generated, SAMPLE_837P);
+
+        let options = WriterOptions {
+            segment_terminator: Some('\''),
+            line_break: "\r\n".to_string(),
+            ..Default::default()
+        };
+        let generated = edi837.to_edi_with(&options).unwrap();
+        assert!(generated.starts_with("ISA*00*"));
+        assert!(generated.contains("*:'\r\nGS*HC*"));
+        assert!(generated.ends_with("IEA*1*000017712'\r\n"));
+    }
+
+    #[test]
+    fn test_missing_bht_location() {
+        let contents = SAMPLE_837P.replace("BHT*", "XXX*").replace('~', "~\r\n");
+        let error = get_837(&contents).unwrap_err();
+        assert!(matches!(error.kind(), EdiError::MissingSegment(_)));
+        let location = error.location().unwrap();
+        assert_eq!((location.segment_index, location.line), (3, 4));
+        assert_eq!(location.segment_id, "XXX");
+    }
+
     #[test]
     fn test_subtype_detection() {
         assert_eq!(
@@ -1,4 +1,6 @@
 use crate::edi837::loop2400::Loop2400;
+use crate::helper::tokenizer::tokenize;
+use crate::segments::composites::{get_c022, get_c023, C022, C023};
 use serde::{Deserialize, Serialize};
 
 /// Loop2300 - Claim Information
@@ -35,6 +37,28 @@ pub struct Loop2300 {
     pub prv_segments: Vec<String>,
 }
 
+impl Loop2300 {
+    /// CLM05 - Health Care Service Location Information (place of service,
+    /// facility code qualifier and claim frequency)
+    pub fn place_of_service(&self) -> C023 {
+        tokenize(&self.clm)
+            .first()
+            .map(|clm| get_c023(clm.element(4)))
+            .unwrap_or_default()
+    }
+
+    /// Health care codes from all HI segments of the claim, in order
+    pub fn health_care_codes(&self) -> Vec<C022> {
+        self.hi
+            .iter()
+            .flat_map(|hi| tokenize(hi))
+            .flat_map(|hi| hi.elements)
+            .filter(|element| !element.is_empty())
+            .map(get_c022)
+            .collect()
+    }
+}
+
 /// Write Loop2300 to EDI format
 pub fn write_loop2300(loop2300: &Loop2300) -> String {
     let mut result = String::new();
@@ -205,6 +229,28 @@ pub fn parse_loop2300(content: &str) -> (Loop2300, String) {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_loop2300_composites() {
+        let loop2300 = Loop2300 {
+            clm: "CLM*26463774*100***11:B:1*Y*A*Y*I~".to_string(),
+            hi: vec![
+                "HI*ABK:J0300*ABF:J209~".to_string(),
+                "HI*BP:0300:D8:20230115~".to_string(),
+            ],
+            ..Default::default()
+        };
+
+        let place_of_service = loop2300.place_of_service();
+        assert_eq!(place_of_service.c023_01_facility_code_value, "11");
+        assert_eq!(place_of_service.c023_03_claim_frequency_type_code, "1");
+
+        let codes = loop2300.health_care_codes();
+        assert_eq!(codes.len(), 3);
+        assert_eq!(codes[0].c022_01_code_list_qualifier_code, "ABK");
+        assert_eq!(codes[1].c022_02_industry_code, "J209");
+        assert_eq!(codes[2].c022_04_date_time_period, "20230115");
+    }
+
     #[test]
     fn test_parse_loop2300() {
         let content = "CLM*12345*100***11:B:1*Y*A*Y*Y*P~DTP*434*RD8*20230101-20230131~HI*BK:J4500*BF:R6889~REF*D9*12345~AMT*AU*100~QTY*CA*1~PRV*BI*PXC*207RC0000X~HCP*01*100**1~";
@@ -1,3 +1,5 @@
+use crate::helper::tokenizer::tokenize;
+use crate::segments::composites::{get_c003, C003};
 use serde::{Deserialize, Serialize};
 
 /// Loop2400 - Service Line Information
@@ -27,6 +29,25 @@ pub struct Loop2400 {
     pub too: Vec<String>,
 }
 
+impl Loop2400 {
+    /// Composite medical procedure identifier of the service line:
+    /// SV101 (professional), SV202 (institutional) or SV301 (dental)
+    pub fn procedure(&self) -> Option<C003> {
+        let (segment, position) = match (&self.sv1, &self.sv2, &self.sv3) {
+            (Some(sv1), _, _) => (sv1, 0),
+            (_, Some(sv2), _) => (sv2, 1),
+            (_, _, Some(sv3)) => (sv3, 0),
+            _ => return None,
+        };
+        let procedure = get_c003(tokenize(segment).first()?.element(position));
+        if procedure.is_empty() {
+            None
+        } else {
+            Some(procedure)
+        }
+    }
+}
+
 /// Write Loop2400 to EDI format
 pub fn write_loop2400(loop2400: &Loop2400) -> String {
     let mut result = String::new();
@@ -283,6 +304,9 @@ mod tests {
 
         assert_eq!(loop2400.lx, "LX*1~");
         assert_eq!(loop2400.sv1, Some("SV1*HC:99213*85*UN*1***1~".to_string()));
+        let procedure = loop2400.procedure().unwrap();
+        assert_eq!(procedure.c003_01_product_or_service_id_qualifier, "HC");
+        assert_eq!(procedure.c003_02_product_or_service_id, "99213");
         assert_eq!(loop2400.dtp, vec!["DTP*472*D8*20230115~".to_string()]);
         assert_eq!(loop2400.ref_segments, vec!["REF*6R*12345~".to_string()]);
         assert_eq!(loop2400.amt, vec!["AMT*AAE*85~".to_string()]);
@@ -300,6 +324,10 @@ mod tests {
             loop2400.sv2,
             Some("SV2*0450*HC:99283*150*UN*1~".to_string())
         );
+        assert_eq!(
+            loop2400.procedure().unwrap().c003_02_product_or_service_id,
+            "99283"
+        );
         assert_eq!(loop2400.dtp, vec!["DTP*472*D8*20230115~".to_string()]);
         assert_eq!(loop2400.ref_segments, vec!["REF*6R*12345~".to_string()]);
     }
@@ -312,6 +340,10 @@ mod tests {
 
         assert_eq!(loop2400.lx, "LX*1~");
         assert_eq!(loop2400.sv3, Some("SV3*AD:D2150*85*UN*1*2~".to_string()));
+        assert_eq!(
+            loop2400.procedure().unwrap().c003_02_product_or_service_id,
+            "D2150"
+        );
         assert_eq!(loop2400.dtp, vec!["DTP*472*D8*20230115~".to_string()]);
         assert_eq!(loop2400.ref_segments, vec!["REF*6R*12345~".to_string()]);
         assert_eq!(loop2400.nte, vec!["NTE*ADD*COMPOSITE FILLING~".to_string()]);
@@ -7,6 +7,13 @@ use crate::edi999::interchangecontroltrailer::*;
 use crate::edi999::loop2000::*;
 use crate::edi999::table1::*;
 use crate::edi999::table1trailer::*;
+use crate::error::EdiResult;
+use crate::helper::delimiters::Delimiters;
+use crate::helper::diagnostics::{collect_diagnostics, Diagnostics};
+use crate::helper::helper::clean_contents;
+use crate::helper::unrecognized::UnrecognizedSegments;
+use crate::helper::writer_options::WriterOptions;
+use crate::transaction_processor::TransactionSet;
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Table1Combined {
@@ -20,9 +27,38 @@ pub struct Edi999 {
     pub interchange_header: InterchangeHeader,
     pub table1_combined: Table1Combined,
     pub interchange_trailer: InterchangeTrailer,
+    /// Delimiters declared by the source ISA, re-emitted when writing EDI
+    #[serde(default)]
+    pub delimiters: Delimiters,
+    /// Problems found while parsing: envelope mismatches, invalid values, unprocessed segments
+    #[serde(default, skip_serializing_if = "Diagnostics::is_empty")]
+    pub diagnostics: Diagnostics,
+    /// Segments the parser did not recognize, written back where they appeared
+    #[serde(default, skip_serializing_if = "UnrecognizedSegments::is_empty")]
+    pub unrecognized: UnrecognizedSegments,
 }
 
-pub fn get_999(contents: &str) -> (Edi999, String) {
+impl TransactionSet for Edi999 {
+    fn parse(contents: String) -> EdiResult<(Self, String)> {
+        parse_999(&contents)
+    }
+
+    fn to_edi_with(&self, options: &WriterOptions) -> EdiResult<String> {
+        write_999_with(self, options)
+    }
+
+    fn get_transaction_type() -> &'static str {
+        "999"
+    }
+}
+
+/// Parse an EDI 999 file into an Edi999 structure; use `Edi999::parse`
+/// to also get the content that follows its transaction set
+pub fn get_999(contents: &str) -> EdiResult<Edi999> {
+    Ok(Edi999::parse(contents.to_string())?.0)
+}
+
+fn parse_999(contents: &str) -> EdiResult<(Edi999, String)> {
     let mut contents = contents.to_string();
     let interchange_header;
     let table1s;
@@ -34,8 +70,10 @@ pub fn get_999(contents: &str) -> (Edi999, String) {
     // Remove BOM if present
     contents = contents.trim_start_matches("\u{feff}").to_string();
 
-    // Remove carriage returns and line feeds
-    contents = contents.replace("\r", "").replace("\n", "");
+    // Capture the source delimiters, then normalize to `*` and `~` and remove line breaks
+    let delimiters = Delimiters::detect(&contents);
+    contents = clean_contents(contents);
+    let normalized = contents.clone();
 
     // Control Segments
     (interchange_header, contents) = get_interchange_header(&contents);
@@ -63,13 +101,19 @@ pub fn get_999(contents: &str) -> (Edi999, String) {
         interchange_header,
         interchange_trailer,
         table1_combined,
+        delimiters,
+        diagnostics: collect_diagnostics(&normalized, &contents, &delimiters),
+        unrecognized: UnrecognizedSegments::collect(&normalized, &contents),
     };
 
-    info!("Unprocessed segments: {:?}", contents);
-    (edi999, contents)
+    Ok((edi999, contents))
+}
+
+pub fn write_999(edi999: &Edi999) -> EdiResult<String> {
+    write_999_with(edi999, &WriterOptions::default())
 }
 
-pub fn write_999(edi999: &Edi999) -> String {
+pub fn write_999_with(edi999: &Edi999, options: &WriterOptions) -> EdiResult<String> {
     let mut new_edi = String::new();
 
     // Write interchange header
@@ -92,11 +136,11 @@ pub fn write_999(edi999: &Edi999) -> String {
     let new_ict = write_interchange_trailer(&edi999.interchange_trailer);
     new_edi.push_str(&new_ict);
 
-    // Add line breaks between segments for better readability
-    let new_edi_with_breaks = new_edi.replace("~", "~\n");
+    let new_edi = edi999.unrecognized.restore(new_edi, &Delimiters::default());
+    let new_edi = options.format(&new_edi, &edi999.delimiters)?;
 
-    info!("Generated EDI 999: {}", new_edi_with_breaks);
-    new_edi_with_breaks
+    info!("Generated EDI 999: {}", new_edi);
+    Ok(new_edi)
 }
 
 // Function to detect if JSON contains 999 format data
@@ -166,7 +210,7 @@ mod tests {
         };
 
         // Write the EDI999
-        let output = write_999(&edi999);
+        let output = write_999(&edi999).unwrap();
 
         // Check if CTX segments are formatted correctly
         assert!(
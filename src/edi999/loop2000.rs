@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::edi999::loop2100::*;
 use crate::helper::edihelper::*;
+use crate::helper::tokenizer::*;
 use crate::segments::ak2::*;
 use crate::segments::ik5::*;
 
@@ -50,36 +51,20 @@ pub fn get_loop_2000(contents: &str) -> (Loop2000, String) {
 }
 
 pub fn get_loop_2000s(contents: &str) -> (Vec<Loop2000>, String) {
-    let mut contents = contents.to_string();
-    let ak2_count = count_segment_starts("AK2", &contents);
+    // Loop 2000 repeats per AK2 and ends at the AK9 trailer
+    let segments = tokenize(contents);
+    let loops_end = find_segment(&segments, &["AK9"], 0).unwrap_or(segments.len());
+    let (leading, loops) = split_loops(&segments[..loops_end], &["AK2"]);
     let mut loop_2000_array = vec![];
-    info!("Number of loops in loop 2000: {:?}", ak2_count);
-
-    // Process each AK2 segment
-    for _ in 0..ak2_count {
-        // Find the next AK2 segment
-        if contents.contains("AK2") {
-            // Extract the content for this AK2 loop
-            let end_pos = if let Some(next_ak2_pos) = find_next_segment_start("AK2", &contents, 3) {
-                next_ak2_pos
-            } else if let Some(ak9_pos) = find_next_segment_start("AK9", &contents, 0) {
-                ak9_pos
-            } else {
-                contents.len()
-            };
-
-            let loop_content = contents[..end_pos].to_string();
-            let (loop2000, _) = get_loop_2000(&loop_content);
-
-            loop_2000_array.push(loop2000);
-
-            // Remove the processed content
-            contents = contents[end_pos..].to_string();
-        } else {
-            break;
-        }
+    info!("Number of loops in loop 2000: {:?}", loops.len());
+
+    for loop_segments in loops {
+        let (loop2000, _) = get_loop_2000(&join_segments(loop_segments));
+        loop_2000_array.push(loop2000);
     }
 
+    let mut contents = join_segments(leading);
+    contents.push_str(&join_segments(&segments[loops_end..]));
     (loop_2000_array, contents)
 }
 
@@ -0,0 +1,446 @@
+//! Envelope builder
+//!
+//! Parsed transaction sets carry the ISA and GS they were read with, so a new
+//! outbound file used to mean editing a copied `InterchangeHeader`.
+//! `EnvelopeBuilder` creates the envelope instead: given the sender and
+//! receiver, usage indicator, date and time, functional identifier code and
+//! version, it wraps one or more ST..SE transaction set bodies into a complete
+//! ISA/GS/.../GE/IEA interchange with the ISA padded to its fixed widths and
+//! the GE and IEA counts filled in.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{EdiError, EdiResult};
+use crate::helper::control_numbers::{
+    format_isa13, partner_key, ControlNumberRegistry, MAX_INTERCHANGE,
+};
+use crate::helper::delimiters::{Delimiters, STANDARD_COMPONENT, STANDARD_REPETITION};
+use crate::helper::helper::clean_contents;
+use crate::helper::tokenizer::tokenize;
+use crate::helper::writer_options::WriterOptions;
+use crate::interchange::{write_interchange_with, AnyTransaction, FunctionalGroup, Interchange};
+use crate::segments::ge::{write_ge, GE};
+use crate::segments::gs::{write_gs, GS};
+use crate::segments::iea::{write_iea, IEA};
+use crate::segments::isa::{write_isa, ISA};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvelopeBuilder {
+    sender_qualifier: String,
+    sender_id: String,
+    receiver_qualifier: String,
+    receiver_id: String,
+    application_sender: Option<String>,
+    application_receiver: Option<String>,
+    usage_indicator: String,
+    acknowledgment_requested: bool,
+    date: Option<String>,
+    time: Option<String>,
+    functional_id_code: Option<String>,
+    version: Option<String>,
+    interchange_control_number: u64,
+    group_control_number: u64,
+    delimiters: Delimiters,
+}
+
+impl EnvelopeBuilder {
+    /// Start an envelope from sender ISA05/ISA06 to receiver ISA07/ISA08.
+    /// Defaults: test data (`T`), no TA1 requested, the current UTC date and
+    /// time, control numbers 1 and the standard delimiters.
+    pub fn new(
+        sender_qualifier: &str,
+        sender_id: &str,
+        receiver_qualifier: &str,
+        receiver_id: &str,
+    ) -> EnvelopeBuilder {
+        EnvelopeBuilder {
+            sender_qualifier: sender_qualifier.to_string(),
+            sender_id: sender_id.to_string(),
+            receiver_qualifier: receiver_qualifier.to_string(),
+            receiver_id: receiver_id.to_string(),
+            application_sender: None,
+            application_receiver: None,
+            usage_indicator: "T".to_string(),
+            acknowledgment_requested: false,
+            date: None,
+            time: None,
+            functional_id_code: None,
+            version: None,
+            interchange_control_number: 1,
+            group_control_number: 1,
+            delimiters: Delimiters::default(),
+        }
+    }
+
+    /// GS02/GS03 application sender and receiver codes; the ISA IDs by default.
+    pub fn application_ids(mut self, sender: &str, receiver: &str) -> EnvelopeBuilder {
+        self.application_sender = Some(sender.to_string());
+        self.application_receiver = Some(receiver.to_string());
+        self
+    }
+
+    /// ISA15: `P` for production data, `T` for test data.
+    pub fn usage_indicator(mut self, usage_indicator: &str) -> EnvelopeBuilder {
+        self.usage_indicator = usage_indicator.to_string();
+        self
+    }
+
+    /// ISA14: request a TA1 interchange acknowledgment.
+    pub fn acknowledgment_requested(mut self, requested: bool) -> EnvelopeBuilder {
+        self.acknowledgment_requested = requested;
+        self
+    }
+
+    /// Date (`CCYYMMDD`) and time (`HHMM`) of the envelope; ISA09 takes the date without the century.
+    pub fn date_time(mut self, date: &str, time: &str) -> EnvelopeBuilder {
+        self.date = Some(date.to_string());
+        self.time = Some(time.to_string());
+        self
+    }
+
+    /// GS01, e.g. `HC`; by default derived from the first transaction set (ST01).
+    pub fn functional_id_code(mut self, code: &str) -> EnvelopeBuilder {
+        self.functional_id_code = Some(code.to_string());
+        self
+    }
+
+    /// GS08, e.g. `005010X222A1`; by default the first transaction set's ST03.
+    pub fn version(mut self, version: &str) -> EnvelopeBuilder {
+        self.version = Some(version.to_string());
+        self
+    }
+
+    /// ISA13 and GS06.
+    pub fn control_numbers(mut self, interchange: u64, group: u64) -> EnvelopeBuilder {
+        self.interchange_control_number = interchange;
+        self.group_control_number = group;
+        self
+    }
+
+    /// Take ISA13 and GS06 from `registry`, keyed by the receiver.
+    pub fn allocate(mut self, registry: &mut ControlNumberRegistry) -> EdiResult<EnvelopeBuilder> {
+        let partner = partner_key(&self.receiver_qualifier, &self.receiver_id);
+        self.interchange_control_number = registry.next_interchange(&partner)?;
+        self.group_control_number = registry.next_group(&partner);
+        registry.save()?;
+        Ok(self)
+    }
+
+    /// Delimiters the interchange declares and is written with.
+    pub fn delimiters(mut self, delimiters: Delimiters) -> EnvelopeBuilder {
+        self.delimiters = delimiters;
+        self
+    }
+
+    /// The ISA segment, padded to its fixed widths.
+    pub fn isa(&self) -> EdiResult<ISA> {
+        if self.interchange_control_number > MAX_INTERCHANGE {
+            return Err(EdiError::ValidationError(format!(
+                "ISA13 {} is longer than 9 digits",
+                self.interchange_control_number
+            )));
+        }
+        let (date, time) = self.date_time_or_now();
+        let short_date = date.get(2..).unwrap_or_default();
+        let ack_indicator = if self.acknowledgment_requested {
+            "1"
+        } else {
+            "0"
+        };
+        Ok(ISA {
+            information_qualifier: "00".to_string(),
+            authorization_information: pad("", 10, "ISA02")?,
+            security_information_qualifier: "00".to_string(),
+            security_information: pad("", 10, "ISA04")?,
+            sender_id_qualifier: pad(&self.sender_qualifier, 2, "ISA05")?,
+            sender_id: pad(&self.sender_id, 15, "ISA06")?,
+            receiver_id_qualifier: pad(&self.receiver_qualifier, 2, "ISA07")?,
+            receiver_id: pad(&self.receiver_id, 15, "ISA08")?,
+            date: pad(short_date, 6, "ISA09")?,
+            time: pad(&time, 4, "ISA10")?,
+            control_number_identifier: STANDARD_REPETITION.to_string(),
+            control_version_number: "00501".to_string(),
+            control_number: format_isa13(self.interchange_control_number),
+            ack_indicator: ack_indicator.to_string(),
+            usage_indicator: pad(&self.usage_indicator, 1, "ISA15")?,
+            component_element_separator: STANDARD_COMPONENT.to_string(),
+        })
+    }
+
+    /// The GS segment for transaction set `transaction_set_id` (ST01) with
+    /// implementation guide `guide` (ST03), used when GS01 or GS08 was not set.
+    pub fn gs(&self, transaction_set_id: &str, guide: &str) -> EdiResult<GS> {
+        let functional_id_code = match &self.functional_id_code {
+            Some(code) => code.clone(),
+            None => functional_id_code(transaction_set_id)
+                .ok_or_else(|| {
+                    EdiError::MissingField(format!(
+                        "GS01 (no functional identifier code known for {})",
+                        transaction_set_id
+                    ))
+                })?
+                .to_string(),
+        };
+        let version = match &self.version {
+            Some(version) => version.clone(),
+            None if !guide.is_empty() => guide.to_string(),
+            None => return Err(EdiError::MissingField("GS08 version".to_string())),
+        };
+        let (date, time) = self.date_time_or_now();
+        Ok(GS {
+            functional_id_code,
+            app_sender_id: self
+                .application_sender
+                .clone()
+                .unwrap_or_else(|| self.sender_id.trim().to_string()),
+            app_receiver_id: self
+                .application_receiver
+                .clone()
+                .unwrap_or_else(|| self.receiver_id.trim().to_string()),
+            date,
+            time,
+            group_control_number: self.group_control_number.to_string(),
+            responsible_agency: "X".to_string(),
+            version_number: version,
+        })
+    }
+
+    /// Wrap transaction set bodies into one interchange with one functional
+    /// group. Each body holds an ST..SE transaction set with the standard
+    /// delimiters; a body with its own ISA may use any delimiters, and its
+    /// envelope is replaced.
+    pub fn wrap(&self, bodies: &[&str]) -> EdiResult<String> {
+        self.wrap_with(bodies, &WriterOptions::default())
+    }
+
+    /// Same as `wrap`, laid out per `options`.
+    pub fn wrap_with(&self, bodies: &[&str], options: &WriterOptions) -> EdiResult<String> {
+        let mut transactions = Vec::new();
+        for body in bodies {
+            let contents = clean_contents(body.to_string());
+            let segments = tokenize(&contents);
+            let st = segments
+                .iter()
+                .position(|segment| segment.id == "ST")
+                .ok_or_else(|| EdiError::MissingSegment("ST".to_string()))?;
+            let se = segments
+                .iter()
+                .rposition(|segment| segment.id == "SE")
+                .ok_or_else(|| EdiError::MissingSegment("SE".to_string()))?;
+            if se < st {
+                return Err(EdiError::MissingSegment("SE".to_string()));
+            }
+            let transaction_set_id = segments[st].element(0).to_string();
+            let guide = segments[st].element(2).to_string();
+            let edi: String = segments[st..=se].iter().map(|s| s.to_edi()).collect();
+            transactions.push((transaction_set_id, guide, edi));
+        }
+        let (transaction_set_id, guide) = transactions
+            .first()
+            .map(|(id, guide, _)| (id.as_str(), guide.as_str()))
+            .ok_or_else(|| EdiError::MissingSegment("ST".to_string()))?;
+
+        let gs = self.gs(transaction_set_id, guide)?;
+        let isa = self.isa()?;
+        let mut edi = write_isa(isa.clone());
+        edi.push_str(&write_gs(gs.clone()));
+        for (_, _, transaction) in &transactions {
+            edi.push_str(transaction);
+        }
+        edi.push_str(&write_ge(GE {
+            number_of_transitions: transactions.len().to_string(),
+            group_control_number: gs.group_control_number,
+        }));
+        edi.push_str(&write_iea(IEA {
+            number_of_included_group: "1".to_string(),
+            interchange_control_number: isa.control_number,
+        }));
+        options.format(&edi, &self.delimiters)
+    }
+
+    /// Wrap parsed transaction sets into an `Interchange` with one functional
+    /// group, ready for `write_interchange`.
+    pub fn build(&self, transactions: Vec<AnyTransaction>) -> EdiResult<Interchange> {
+        let first = transactions
+            .first()
+            .ok_or_else(|| EdiError::MissingSegment("ST".to_string()))?;
+        let guide = match &self.version {
+            Some(_) => String::new(),
+            None => {
+                let edi = clean_contents(first.to_edi()?);
+                tokenize(&edi)
+                    .iter()
+                    .find(|segment| segment.id == "ST")
+                    .map(|st| st.element(2).to_string())
+                    .unwrap_or_default()
+            }
+        };
+        let gs = self.gs(first.transaction_set_id(), &guide)?;
+        let isa = self.isa()?;
+        let ge = GE {
+            number_of_transitions: transactions.len().to_string(),
+            group_control_number: gs.group_control_number.clone(),
+        };
+        let iea = IEA {
+            number_of_included_group: "1".to_string(),
+            interchange_control_number: isa.control_number.clone(),
+        };
+        Ok(Interchange {
+            isa,
+            groups: vec![FunctionalGroup {
+                gs,
+                transactions,
+                ge,
+            }],
+            iea,
+            delimiters: self.delimiters,
+            diagnostics: Default::default(),
+        })
+    }
+
+    /// Wrap parsed transaction sets and write the interchange, laid out per `options`.
+    pub fn write_with(
+        &self,
+        transactions: Vec<AnyTransaction>,
+        options: &WriterOptions,
+    ) -> EdiResult<String> {
+        write_interchange_with(&self.build(transactions)?, options)
+    }
+
+    fn date_time_or_now(&self) -> (String, String) {
+        match (&self.date, &self.time) {
+            (Some(date), Some(time)) => (date.clone(), time.clone()),
+            _ => now_utc(),
+        }
+    }
+}
+
+/// GS01 functional identifier code of a transaction set.
+pub fn functional_id_code(transaction_set_id: &str) -> Option<&'static str> {
+    match transaction_set_id {
+        "270" => Some("HS"),
+        "271" => Some("HB"),
+        "276" => Some("HR"),
+        "277" => Some("HN"),
+        "278" => Some("HI"),
+        "820" => Some("RA"),
+        "834" => Some("BE"),
+        "835" => Some("HP"),
+        "837" => Some("HC"),
+        "999" => Some("FA"),
+        _ => None,
+    }
+}
+
+/// Pad an ISA element to its fixed width with spaces.
+fn pad(value: &str, width: usize, element: &str) -> EdiResult<String> {
+    if value.chars().count() > width {
+        return Err(EdiError::ValidationError(format!(
+            "{} '{}' is longer than {} characters",
+            element, value, width
+        )));
+    }
+    Ok(format!("{:<width$}", value, width = width))
+}
+
+/// Current UTC date (`CCYYMMDD`) and time (`HHMM`).
+fn now_utc() -> (String, String) {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let (days, seconds) = (seconds / 86_400, seconds % 86_400);
+
+    // Civil date from days since 1970-01-01 (proleptic Gregorian calendar)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (
+        format!("{:04}{:02}{:02}", year, month, day),
+        format!("{:02}{:02}", seconds / 3_600, seconds % 3_600 / 60),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interchange::get_interchanges;
+
+    const BODY_837: &str = "ST*837*0001*005010X222A1~BHT*0019*00*1*20230501*1200*CH~SE*3*0001~";
+
+    fn builder() -> EnvelopeBuilder {
+        EnvelopeBuilder::new("ZZ", "SENDER", "ZZ", "RECEIVER")
+            .usage_indicator("P")
+            .date_time("20230501", "1200")
+            .control_numbers(42, 7)
+    }
+
+    #[test]
+    fn test_wrap_bodies() {
+        let second = BODY_837.replace("0001", "0002");
+        let edi = builder()
+            .wrap_with(&[BODY_837, &second], &WriterOptions::single_line())
+            .unwrap();
+        assert_eq!(
+            edi,
+            format!(
+                "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230501*1200*^*00501*000000042*0*P*:~\
+GS*HC*SENDER*RECEIVER*20230501*1200*7*X*005010X222A1~{}{}GE*2*7~IEA*1*000000042~",
+                BODY_837, second
+            )
+        );
+        assert_eq!(edi.find("230501"), Some(70));
+    }
+
+    #[test]
+    fn test_build_interchange() {
+        let body = "ST*999*0003*005010X231A1~AK1*HC*17*005010X222A1~AK9*A*1*1*1~SE*4*0003~";
+        let source = builder().wrap(&[body]).unwrap();
+        let transaction = AnyTransaction::parse_any(&source).unwrap();
+
+        let envelope = EnvelopeBuilder::new("ZZ", "PAYER", "30", "123456789")
+            .date_time("20240102", "0930")
+            .acknowledgment_requested(true)
+            .delimiters(Delimiters {
+                element: '|',
+                ..Default::default()
+            });
+        let interchange = envelope.build(vec![transaction]).unwrap();
+        assert_eq!(interchange.groups[0].gs.functional_id_code, "FA");
+        assert_eq!(interchange.groups[0].gs.version_number, "005010X231A1");
+
+        let written = write_interchange_with(&interchange, &WriterOptions::default()).unwrap();
+        assert!(written.starts_with("ISA|00|          |00|          |ZZ|PAYER          |30|123456789      |240102|0930|^|00501|000000001|1|T|:~\n"));
+        let reparsed = get_interchanges(&written).unwrap();
+        assert!(reparsed[0].diagnostics.is_empty());
+        assert_eq!(reparsed[0].groups[0].transactions.len(), 1);
+    }
+
+    #[test]
+    fn test_envelope_errors() {
+        assert!(matches!(
+            EnvelopeBuilder::new("ZZ", "A SENDER ID LONGER THAN 15", "ZZ", "R").isa(),
+            Err(EdiError::ValidationError(_))
+        ));
+        assert!(matches!(
+            builder().wrap(&["BHT*0019~"]),
+            Err(EdiError::MissingSegment(_))
+        ));
+        assert!(matches!(
+            builder().wrap(&["ST*850*0001~SE*2*0001~"]),
+            Err(EdiError::MissingField(_))
+        ));
+
+        let (date, time) = now_utc();
+        assert_eq!((date.len(), time.len()), (8, 4));
+        assert!(date.as_str() >= "20240101");
+    }
+}
@@ -1,8 +1,41 @@
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
+use crate::helper::loop_path::element_path;
+
 /// Result type for EDI operations
 pub type EdiResult<T> = Result<T, EdiError>;
 
+/// Position of a segment in the raw input.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceLocation {
+    /// Zero-based ordinal of the segment in the input (ISA is 0)
+    pub segment_index: usize,
+    /// Byte offset of the segment in the raw input
+    pub offset: usize,
+    /// One-based line number of the segment in the raw input
+    pub line: usize,
+    /// Segment identifier, e.g. `CLM`
+    pub segment_id: String,
+    /// One-based element position, when the error concerns a single element
+    pub element: Option<usize>,
+    /// Enclosing loops, e.g. `2000B/2300`
+    pub loop_path: String,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (segment {}, line {}, byte {})",
+            element_path(&self.loop_path, &self.segment_id, self.element),
+            self.segment_index,
+            self.line,
+            self.offset
+        )
+    }
+}
+
 /// Error types for EDI operations
 #[derive(Debug)]
 
@@ -36,6 +69,36 @@ pub enum EdiError {
 
     /// Invalid segment order
     InvalidSegmentOrder(String),
+
+    /// Any of the above, with the position in the input where it occurred
+    Located(Box<EdiError>, Box<SourceLocation>),
+}
+
+impl EdiError {
+    /// Attach the input position where this error occurred.
+    /// An error that already carries a position keeps it, since the innermost one is the most precise.
+    pub fn at(self, location: SourceLocation) -> EdiError {
+        match self {
+            EdiError::Located(..) => self,
+            error => EdiError::Located(Box::new(error), Box::new(location)),
+        }
+    }
+
+    /// The input position of this error, if known.
+    pub fn location(&self) -> Option<&SourceLocation> {
+        match self {
+            EdiError::Located(_, location) => Some(location),
+            _ => None,
+        }
+    }
+
+    /// This error without its position, for matching on the kind of problem.
+    pub fn kind(&self) -> &EdiError {
+        match self {
+            EdiError::Located(error, _) => error,
+            error => error,
+        }
+    }
 }
 
 impl fmt::Display for EdiError {
@@ -51,6 +114,7 @@ impl fmt::Display for EdiError {
             EdiError::InvalidFieldValue(msg) => write!(f, "Invalid field value: {}", msg),
             EdiError::NotImplemented(msg) => write!(f, "Not implemented: {}", msg),
             EdiError::InvalidSegmentOrder(msg) => write!(f, "Invalid segment order: {}", msg),
+            EdiError::Located(error, location) => write!(f, "{} at {}", error, location),
         }
     }
 }
@@ -68,3 +132,32 @@ impl From<serde_json::Error> for EdiError {
         EdiError::ParseError(err.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_located_error() {
+        let location = SourceLocation {
+            segment_index: 40012,
+            offset: 1_203_344,
+            line: 40013,
+            segment_id: "CLM".to_string(),
+            element: Some(2),
+            loop_path: "2000B/2300".to_string(),
+        };
+        let error =
+            EdiError::MalformedSegment("CLM02 is not a decimal".to_string()).at(location.clone());
+        assert_eq!(error.location(), Some(&location));
+        assert!(matches!(error.kind(), EdiError::MalformedSegment(_)));
+        assert_eq!(
+            error.to_string(),
+            "Malformed segment: CLM02 is not a decimal at 2000B/2300/CLM02 (segment 40012, line 40013, byte 1203344)"
+        );
+
+        // The innermost position wins
+        let outer = SourceLocation::default();
+        assert_eq!(error.at(outer).location(), Some(&location));
+    }
+}
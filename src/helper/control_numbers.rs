@@ -0,0 +1,238 @@
+//! Control number registry
+//!
+//! Every outbound interchange needs an ISA13 its receiver has not seen
+//! before, and every group and transaction set a fresh GS06 and ST02.
+//! `ControlNumberRegistry` hands them out from counters kept per trading
+//! partner (the ISA07/ISA08 receiver) in a local JSON file, remembers every
+//! ISA13 sent to each partner, and rejects one that would be sent twice.
+//!
+//! The store is not locked: processes sharing a file must not stamp
+//! interchanges at the same time.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{EdiError, EdiResult};
+use crate::helper::delimiters::Delimiters;
+use crate::helper::envelope_validation::sync_envelope;
+use crate::helper::helper::clean_contents;
+use crate::helper::tokenizer::tokenize;
+use crate::helper::writer_options::WriterOptions;
+
+/// ISA13 is nine digits.
+pub const MAX_INTERCHANGE: u64 = 999_999_999;
+
+/// Counters and sent interchanges of one trading partner.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartnerCounters {
+    /// Last ISA13 issued
+    pub interchange: u64,
+    /// Last GS06 issued
+    pub group: u64,
+    /// Last ST02 issued
+    pub transaction: u64,
+    /// Every ISA13 sent to the partner
+    #[serde(default)]
+    pub sent: BTreeSet<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ControlNumberRegistry {
+    /// File the registry is kept in; `None` for an in-memory registry
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    /// Counters by partner key, see `partner_key`
+    partners: BTreeMap<String, PartnerCounters>,
+}
+
+impl ControlNumberRegistry {
+    /// Open the registry kept in `path`, starting empty if the file does not exist yet.
+    pub fn open(path: impl AsRef<Path>) -> EdiResult<ControlNumberRegistry> {
+        let path = path.as_ref();
+        let mut registry = if path.exists() {
+            serde_json::from_str::<ControlNumberRegistry>(&fs::read_to_string(path)?)?
+        } else {
+            ControlNumberRegistry::default()
+        };
+        registry.path = Some(path.to_path_buf());
+        Ok(registry)
+    }
+
+    /// A registry that is never saved, e.g. for tests.
+    pub fn in_memory() -> ControlNumberRegistry {
+        ControlNumberRegistry::default()
+    }
+
+    /// Write the registry to its file. The file is replaced in one step, so an
+    /// interrupted save leaves the previous state.
+    pub fn save(&self) -> EdiResult<()> {
+        if let Some(path) = &self.path {
+            let temporary = path.with_extension("tmp");
+            fs::write(&temporary, serde_json::to_string_pretty(self)?)?;
+            fs::rename(&temporary, path)?;
+        }
+        Ok(())
+    }
+
+    pub fn partner(&self, partner: &str) -> Option<&PartnerCounters> {
+        self.partners.get(partner)
+    }
+
+    /// Allocate the next ISA13 for `partner`, skipping any already sent, and record it as sent.
+    pub fn next_interchange(&mut self, partner: &str) -> EdiResult<u64> {
+        let counters = self.partners.entry(partner.to_string()).or_default();
+        for _ in 0..MAX_INTERCHANGE {
+            counters.interchange = counters.interchange % MAX_INTERCHANGE + 1;
+            if counters.sent.insert(format_isa13(counters.interchange)) {
+                return Ok(counters.interchange);
+            }
+        }
+        Err(EdiError::ValidationError(format!(
+            "Every ISA13 has been sent to {}",
+            partner
+        )))
+    }
+
+    /// Allocate the next GS06 for `partner`.
+    pub fn next_group(&mut self, partner: &str) -> u64 {
+        let counters = self.partners.entry(partner.to_string()).or_default();
+        counters.group = counters.group % MAX_INTERCHANGE + 1;
+        counters.group
+    }
+
+    /// Allocate the next ST02 for `partner`.
+    pub fn next_transaction(&mut self, partner: &str) -> u64 {
+        let counters = self.partners.entry(partner.to_string()).or_default();
+        counters.transaction = counters.transaction % MAX_INTERCHANGE + 1;
+        counters.transaction
+    }
+
+    pub fn is_sent(&self, partner: &str, isa13: &str) -> bool {
+        self.partners
+            .get(partner)
+            .is_some_and(|counters| counters.sent.contains(isa13.trim()))
+    }
+
+    /// Record an ISA13 assigned elsewhere as sent to `partner`; fails if it was sent before.
+    pub fn record_sent(&mut self, partner: &str, isa13: &str) -> EdiResult<()> {
+        let counters = self.partners.entry(partner.to_string()).or_default();
+        if !counters.sent.insert(isa13.trim().to_string()) {
+            return Err(EdiError::ValidationError(format!(
+                "ISA13 {} was already sent to {}",
+                isa13.trim(),
+                partner
+            )));
+        }
+        Ok(())
+    }
+
+    /// Give every ISA, GS and ST segment of `segments` (standard delimiters,
+    /// without terminators) new control numbers for the receiver of its
+    /// interchange, and copy them into the trailers.
+    pub fn assign(&mut self, segments: &mut [String]) -> EdiResult<()> {
+        let mut partner = String::new();
+        for segment in segments.iter_mut() {
+            let mut elements: Vec<String> = segment.split('*').map(str::to_string).collect();
+            let (position, number) = match elements[0].as_str() {
+                "ISA" if elements.len() > 13 => {
+                    partner = partner_key(&elements[7], &elements[8]);
+                    (13, format_isa13(self.next_interchange(&partner)?))
+                }
+                "GS" if elements.len() > 6 => (6, self.next_group(&partner).to_string()),
+                // ST02 has at least four digits
+                "ST" if elements.len() > 2 => {
+                    (2, format!("{:04}", self.next_transaction(&partner)))
+                }
+                _ => continue,
+            };
+            elements[position] = number;
+            *segment = elements.join("*");
+        }
+        sync_envelope(segments);
+        Ok(())
+    }
+
+    /// Give written EDI new control numbers (see `assign`), save the registry
+    /// and lay the EDI out again per `options`.
+    pub fn stamp(&mut self, edi: &str, options: &WriterOptions) -> EdiResult<String> {
+        let delimiters = Delimiters::detect(edi);
+        let normalized = clean_contents(edi.to_string());
+        let mut segments: Vec<String> = tokenize(&normalized)
+            .iter()
+            .map(|segment| segment.raw.to_string())
+            .collect();
+        self.assign(&mut segments)?;
+        self.save()?;
+
+        let mut stamped = segments.join("~");
+        stamped.push('~');
+        options.format(&stamped, &delimiters)
+    }
+}
+
+/// ISA13 as written: nine digits, padded with zeros.
+pub fn format_isa13(interchange: u64) -> String {
+    format!("{:09}", interchange)
+}
+
+/// Registry key of a trading partner: its ISA07 qualifier and ISA08 ID, e.g. `ZZ:RECEIVER`.
+pub fn partner_key(qualifier: &str, id: &str) -> String {
+    format!("{}:{}", qualifier.trim(), id.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EDI: &str = "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230501*1200*^*00501*000000001*0*T*:~GS*HC*S*R*20230501*1200*1*X*005010X222A1~ST*837*0001*005010X222A1~BHT*0019~SE*3*0001~ST*837*0002*005010X222A1~BHT*0019~SE*3*0002~GE*2*1~IEA*1*000000001~";
+
+    #[test]
+    fn test_stamp_allocates_per_partner() {
+        let mut registry = ControlNumberRegistry::in_memory();
+        let options = WriterOptions::single_line();
+        let first = registry.stamp(EDI, &options).unwrap();
+        assert!(first.contains("*000000001*0*T*:~GS*HC*S*R*20230501*1200*1*"));
+        assert!(first.contains("ST*837*0002*005010X222A1~BHT*0019~SE*3*0002~"));
+
+        let second = registry.stamp(EDI, &options).unwrap();
+        assert!(second.contains("*000000002*0*T*:~"));
+        assert!(second.contains("ST*837*0003*"));
+        assert!(second.contains("SE*3*0004~GE*2*2~IEA*1*000000002~"));
+
+        let other = registry
+            .stamp(&EDI.replace("RECEIVER      ", "OTHER         "), &options)
+            .unwrap();
+        assert!(other.contains("*000000001*0*T*:~"));
+        assert_eq!(registry.partner("ZZ:RECEIVER").unwrap().sent.len(), 2);
+    }
+
+    #[test]
+    fn test_reuse_is_rejected_and_skipped() {
+        let mut registry = ControlNumberRegistry::in_memory();
+        registry.record_sent("ZZ:RECEIVER", "000000001").unwrap();
+        assert!(registry.is_sent("ZZ:RECEIVER", "000000001"));
+        assert!(matches!(
+            registry.record_sent("ZZ:RECEIVER", "000000001"),
+            Err(EdiError::ValidationError(_))
+        ));
+        assert!(registry.record_sent("ZZ:OTHER", "000000001").is_ok());
+        assert_eq!(registry.next_interchange("ZZ:RECEIVER").unwrap(), 2);
+    }
+
+    #[test]
+    fn test_registry_file_round_trip() {
+        let path =
+            std::env::temp_dir().join(format!("edi-control-numbers-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut registry = ControlNumberRegistry::open(&path).unwrap();
+        registry.stamp(EDI, &WriterOptions::default()).unwrap();
+        let reopened = ControlNumberRegistry::open(&path).unwrap();
+        assert_eq!(reopened, registry);
+        assert_eq!(reopened.partner("ZZ:RECEIVER").unwrap().transaction, 2);
+        fs::remove_file(&path).unwrap();
+    }
+}
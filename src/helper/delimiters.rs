@@ -0,0 +1,303 @@
+//! Interchange delimiters
+//!
+//! Captures the separators declared in the ISA segment so that parsed content
+//! can be normalized to `*`, `:`, `^` and `~` and later re-emitted in the
+//! partner's original syntax, or in any other syntax chosen for the output.
+
+use serde::{Deserialize, Serialize};
+
+use crate::helper::diagnostics::{Diagnostic, DiagnosticCode, Diagnostics, Severity};
+use crate::helper::tokenizer::Segment;
+
+/// Standard element separator used internally after `helper::clean_contents`.
+pub const STANDARD_ELEMENT: char = '*';
+/// Standard component element separator used internally after `helper::clean_contents`.
+pub const STANDARD_COMPONENT: char = ':';
+/// Standard repetition separator used internally after `helper::clean_contents`.
+pub const STANDARD_REPETITION: char = '^';
+/// Standard segment terminator used internally after `helper::clean_contents`.
+pub const STANDARD_SEGMENT: char = '~';
+
+/// Delimiters of an interchange, as declared by its ISA segment.
+///
+/// - element: ISA position 4 (the character following `ISA`)
+/// - repetition: ISA11 (X12 5010 repetition separator)
+/// - component: ISA16
+/// - segment: the character terminating the ISA segment
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct Delimiters {
+    pub element: char,
+    pub component: char,
+    pub repetition: char,
+    pub segment: char,
+}
+
+impl Default for Delimiters {
+    fn default() -> Self {
+        Delimiters {
+            element: STANDARD_ELEMENT,
+            component: STANDARD_COMPONENT,
+            repetition: STANDARD_REPETITION,
+            segment: STANDARD_SEGMENT,
+        }
+    }
+}
+
+impl Delimiters {
+    /// Detect delimiters from the ISA segment at the start of raw EDI content.
+    /// Returns the default delimiters if no ISA segment is found.
+    pub fn detect(contents: &str) -> Delimiters {
+        let mut delimiters = Delimiters::default();
+        let isa = contents.trim_start_matches('\u{feff}').trim_start();
+        if !isa.starts_with("ISA") {
+            return delimiters;
+        }
+
+        let mut chars = isa.chars().skip(3);
+        let element = match chars.next() {
+            Some(c) => c,
+            None => return delimiters,
+        };
+        delimiters.element = element;
+
+        // ISA has exactly 16 elements; ISA16 is a single character followed by the terminator.
+        let mut separators_seen = 1;
+        let mut isa11 = String::new();
+        for c in chars.by_ref() {
+            if c == element {
+                separators_seen += 1;
+                if separators_seen == 16 {
+                    break;
+                }
+            } else if separators_seen == 11 {
+                isa11.push(c);
+            }
+        }
+        if separators_seen < 16 {
+            return delimiters;
+        }
+        if let Some(component) = chars.next() {
+            delimiters.component = component;
+        }
+        if let Some(segment) = chars.next() {
+            delimiters.segment = segment;
+        }
+
+        // Before 5010, ISA11 was the standards identifier ("U"), not a separator.
+        let mut isa11_chars = isa11.chars();
+        if let (Some(repetition), None) = (isa11_chars.next(), isa11_chars.next()) {
+            if !repetition.is_ascii_alphanumeric() {
+                delimiters.repetition = repetition;
+            }
+        }
+
+        delimiters
+    }
+
+    /// The standard delimiters (`*`, `:`, `^`, `~`) used for content produced by
+    /// `helper::clean_contents` and by the `write_*` functions.
+    pub fn normalized(&self) -> Delimiters {
+        Delimiters::default()
+    }
+
+    /// Convert EDI text written with these delimiters to `target` delimiters.
+    /// A line break directly following a segment terminator is treated as layout
+    /// and dropped when the target terminator is itself a line break.
+    ///
+    /// Characters that are delimiters in `target` but plain data here (e.g. a `:`
+    /// in a name when the source component separator is `>`) cannot be kept as
+    /// they are. They are swapped with the delimiter they displaced, so
+    /// converting back restores them; `check_data` reports those found in
+    /// normalized content.
+    pub fn convert(&self, edi: &str, target: &Delimiters) -> String {
+        if self == target {
+            return edi.to_string();
+        }
+
+        let swaps = self.swaps(target);
+        let mut result = String::with_capacity(edi.len());
+        let mut chars = edi.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == self.segment {
+                result.push(target.segment);
+                if target.segment == '\n' {
+                    if chars.peek() == Some(&'\r') {
+                        chars.next();
+                    }
+                    if chars.peek() == Some(&'\n') {
+                        chars.next();
+                    }
+                }
+            } else if c == self.element {
+                result.push(target.element);
+            } else if c == self.component {
+                result.push(target.component);
+            } else if c == self.repetition {
+                result.push(target.repetition);
+            } else if let Some(&(_, swapped)) = swaps.iter().find(|(data, _)| *data == c) {
+                result.push(swapped);
+            } else {
+                result.push(c);
+            }
+        }
+        result
+    }
+
+    /// Data characters of these delimiters that are delimiters in `target`,
+    /// each paired with the delimiter of these that `convert` puts in its place.
+    pub fn swaps(&self, target: &Delimiters) -> Vec<(char, char)> {
+        let source = self.chars();
+        let destination = target.chars();
+        let displaced = source
+            .into_iter()
+            .filter(|c| !c.is_whitespace() && !destination.contains(c));
+        let taken = destination
+            .into_iter()
+            .filter(|c| !c.is_whitespace() && !source.contains(c));
+        taken.zip(displaced).collect()
+    }
+
+    /// Report the data characters that `convert` swapped when `segments` were
+    /// normalized from these delimiters to the standard ones. Parsed values
+    /// hold the swapped character (e.g. `DOE>SMITH` for `DOE:SMITH`) and are
+    /// written back as they were read. `paths` holds the loop path of each
+    /// segment (see `loop_path::loop_paths`).
+    pub fn check_data(
+        &self,
+        segments: &[Segment],
+        paths: &[String],
+        diagnostics: &mut Diagnostics,
+    ) {
+        let swaps = self.swaps(&self.normalized());
+        if swaps.is_empty() {
+            return;
+        }
+        for (segment, path) in segments.iter().zip(paths) {
+            for (index, value) in segment.elements.iter().enumerate() {
+                let Some(&(data, swapped)) =
+                    swaps.iter().find(|(_, swapped)| value.contains(*swapped))
+                else {
+                    continue;
+                };
+                diagnostics.push(
+                    Diagnostic::new(
+                        Severity::Warning,
+                        DiagnosticCode::DelimiterInData,
+                        format!(
+                            "Data character '{}' in {}{:02} is a standard delimiter and reads as '{}'",
+                            data,
+                            segment.id,
+                            index + 1,
+                            swapped
+                        ),
+                    )
+                    .at(segment, path, Some(index + 1)),
+                );
+            }
+        }
+    }
+
+    fn chars(&self) -> [char; 4] {
+        [self.element, self.component, self.repetition, self.segment]
+    }
+
+    /// Re-emit EDI produced by a `write_*` function (standard delimiters)
+    /// using these delimiters, e.g. the ones captured from the source ISA.
+    pub fn restore(&self, edi: &str) -> String {
+        self.normalized().convert(edi, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ISA_STANDARD: &str = "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230501*1200*^*00501*000000001*0*T*:~GS*HR*S*R~";
+    const ISA_PIPE: &str = "ISA|00|          |00|          |ZZ|SENDER         |ZZ|RECEIVER       |230501|1200|!|00501|000000001|0|T|>\nGS|HR|S|R\n";
+
+    #[test]
+    fn test_detect_standard() {
+        assert_eq!(Delimiters::detect(ISA_STANDARD), Delimiters::default());
+    }
+
+    #[test]
+    fn test_detect_custom() {
+        let delimiters = Delimiters::detect(ISA_PIPE);
+        assert_eq!(delimiters.element, '|');
+        assert_eq!(delimiters.repetition, '!');
+        assert_eq!(delimiters.component, '>');
+        assert_eq!(delimiters.segment, '\n');
+    }
+
+    #[test]
+    fn test_detect_4010_isa11() {
+        let contents = "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230501*1200*U*00401*000000001*0*T*:~";
+        assert_eq!(Delimiters::detect(contents).repetition, '^');
+    }
+
+    #[test]
+    fn test_detect_no_isa() {
+        assert_eq!(Delimiters::detect("ST*835*1~"), Delimiters::default());
+        assert_eq!(Delimiters::detect("ISA*00*"), Delimiters::default());
+    }
+
+    #[test]
+    fn test_restore_original() {
+        let delimiters = Delimiters::detect(ISA_PIPE);
+        let written = "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230501*1200*^*00501*000000001*0*T*:~\nGS*HR*S*R~\n";
+        assert_eq!(delimiters.restore(written), ISA_PIPE);
+    }
+
+    #[test]
+    fn test_convert_round_trip_swaps_data_characters() {
+        let delimiters = Delimiters::detect(ISA_PIPE);
+        let source = "NM1|IL|1|DOE:SMITH^JR\nEB|1||30!1!33\nSV1|HC>99213|100\n";
+        let normalized = delimiters.convert(source, &Delimiters::default());
+        assert!(normalized.starts_with("NM1*IL*1*DOE>SMITH!JR~"));
+        assert!(normalized.contains("EB*1**30^1^33~"));
+        assert!(normalized.contains("SV1*HC:99213*100~"));
+        assert_eq!(delimiters.restore(&normalized), source);
+    }
+
+    #[test]
+    fn test_check_data_reports_swapped_characters() {
+        use crate::helper::loop_path::loop_paths;
+        use crate::helper::tokenizer::tokenize;
+
+        let delimiters = Delimiters::detect(ISA_PIPE);
+        let source = "NM1|IL|1|DOE:SMITH^JR\nEB|1||30!1!33\nSV1|HC>99213|100\n";
+        let normalized = delimiters.convert(source, &Delimiters::default());
+        let segments = tokenize(&normalized);
+        let paths = loop_paths(&segments);
+        let mut diagnostics = Diagnostics::default();
+        delimiters.check_data(&segments, &paths, &mut diagnostics);
+        let found: Vec<(&str, &str)> = diagnostics
+            .iter()
+            .map(|d| (d.loop_path.as_str(), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![(
+                "NM103",
+                "Data character ':' in NM103 is a standard delimiter and reads as '>'"
+            )]
+        );
+
+        // Nothing is swapped between standard delimiters
+        let mut diagnostics = Diagnostics::default();
+        Delimiters::default().check_data(&segments, &paths, &mut diagnostics);
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_convert_to_other_delimiters() {
+        let target = Delimiters {
+            element: '^',
+            component: '\\',
+            repetition: '{',
+            segment: '\'',
+        };
+        let converted = Delimiters::default().convert("SV1*HC:99213*100~EB*1**30^1~", &target);
+        assert_eq!(converted, "SV1^HC\\99213^100'EB^1^^30{1'");
+    }
+}
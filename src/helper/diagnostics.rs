@@ -0,0 +1,306 @@
+//! Parse diagnostics
+//!
+//! Problems found while parsing (envelope control number and count
+//! mismatches, invalid element values, broken syntax notes, segments the
+//! parser did not consume) are collected as `Diagnostic` entries and returned
+//! on every parsed transaction set, so library callers can reject, route or
+//! display them instead of scraping log output.
+
+use std::fmt;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::helper::delimiters::Delimiters;
+use crate::helper::element_dictionary::check_elements;
+use crate::helper::envelope_validation::check_envelope;
+use crate::helper::hierarchy::check_hierarchy;
+use crate::helper::loop_path::{element_path, loop_paths};
+use crate::helper::syntax_rules::check_syntax;
+use crate::helper::tokenizer::{tokenize, Segment};
+use crate::helper::unrecognized::match_unprocessed;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiagnosticCode {
+    /// ST02/SE02, GS06/GE02 or ISA13/IEA02 differ
+    ControlNumberMismatch,
+    /// SE01, GE01 or IEA01 does not match the number of enclosed units
+    CountMismatch,
+    /// An ST, GS or ISA header has no matching trailer
+    MissingTrailer,
+    /// A segment found outside of its enclosing envelope
+    UnexpectedSegment,
+    /// A decimal (R) element holds a value that is not a decimal number
+    InvalidDecimal,
+    /// A numeric (N) element holds a value that is not a whole number
+    InvalidNumeric,
+    /// A date (DT) element, or a period in date format, holds an invalid date
+    InvalidDate,
+    /// A time (TM) element holds an invalid time
+    InvalidTime,
+    /// An element value is shorter or longer than the element dictionary allows
+    InvalidLength,
+    /// A segment the transaction set parser did not consume
+    UnprocessedSegment,
+    /// A segment ID or element that breaks X12 syntax
+    InvalidSyntax,
+    /// A segment the implementation guide requires is absent
+    MissingSegment,
+    /// ST03/GS08 does not name an implementation guide of the transaction set
+    ImplementationGuideMismatch,
+    /// A situational requirement of the implementation guide is not met
+    SituationalRule,
+    /// An element value is not in its code set
+    InvalidCode,
+    /// Content that does not belong to the product type or subtype, e.g. 837I data in an 837P
+    ProductTypeMismatch,
+    /// A custom (e.g. trading partner) rule is not met
+    RuleViolation,
+    /// A loop the implementation guide requires is absent
+    MissingLoop,
+    /// An element the implementation guide requires is empty
+    MissingElement,
+    /// A segment or loop occurs more often than the implementation guide allows
+    RepeatExceeded,
+    /// A segment or element the implementation guide marks as not used is present
+    UsageViolation,
+    /// HL numbering, parent, level or child codes do not form a valid hierarchy
+    InvalidHierarchy,
+    /// Amounts that must balance do not, e.g. CLP03 - CLP04 against the CAS adjustments
+    AmountMismatch,
+    /// A data character is one of the standard delimiters, so its value holds
+    /// a stand-in character once delimiters are normalized
+    DelimiterInData,
+}
+
+impl fmt::Display for DiagnosticCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// A single problem found in parsed content.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: DiagnosticCode,
+    /// Zero-based ordinal of the segment within the parsed content (ISA is 0)
+    pub segment_index: Option<usize>,
+    /// Byte offset of the segment within the parsed content, after delimiter normalization
+    pub offset: Option<usize>,
+    /// Enclosing loops followed by the segment or element, e.g. `2000B/2300/CLM02`
+    pub loop_path: String,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: DiagnosticCode, message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            severity,
+            code,
+            segment_index: None,
+            offset: None,
+            loop_path: String::new(),
+            message: message.into(),
+        }
+    }
+
+    /// Locate the diagnostic at `segment`, and at its `element` (1-based) if given.
+    pub fn at(mut self, segment: &Segment, loop_path: &str, element: Option<usize>) -> Diagnostic {
+        self.segment_index = Some(segment.index);
+        self.offset = Some(segment.offset);
+        self.loop_path = element_path(loop_path, segment.id, element);
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} {}", self.severity, self.code)?;
+        if !self.loop_path.is_empty() {
+            write!(f, " at {}", self.loop_path)?;
+        }
+        if let Some(index) = self.segment_index {
+            write!(f, " (segment {})", index)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Diagnostics collected while parsing, in the order they were found.
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Diagnostics {
+    entries: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.entries.push(diagnostic);
+    }
+
+    pub fn extend(&mut self, other: Diagnostics) {
+        self.entries.extend(other.entries);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.entries.iter()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.entries.iter().any(|d| d.severity == Severity::Error)
+    }
+
+    /// Diagnostics of the given code, e.g. to route envelope problems separately.
+    pub fn with_code(&self, code: DiagnosticCode) -> impl Iterator<Item = &Diagnostic> {
+        self.entries.iter().filter(move |d| d.code == code)
+    }
+
+    /// Move segment positions by `segments` and `bytes`, for diagnostics
+    /// collected on a slice of a larger file.
+    pub fn shift(&mut self, segments: usize, bytes: usize) {
+        for diagnostic in &mut self.entries {
+            diagnostic.segment_index = diagnostic.segment_index.map(|i| i + segments);
+            diagnostic.offset = diagnostic.offset.map(|o| o + bytes);
+        }
+    }
+
+    /// Write every diagnostic to the log, for the command line tool.
+    pub fn log(&self) {
+        for diagnostic in &self.entries {
+            match diagnostic.severity {
+                Severity::Info => info!("{}", diagnostic),
+                Severity::Warning | Severity::Error => warn!("{}", diagnostic),
+            }
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Diagnostics {
+    type Item = &'a Diagnostic;
+    type IntoIter = std::slice::Iter<'a, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+impl IntoIterator for Diagnostics {
+    type Item = Diagnostic;
+    type IntoIter = std::vec::IntoIter<Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+/// Collect the diagnostics of a parsed transaction set.
+/// `contents` is the normalized content handed to the parser,
+/// `unprocessed` whatever the parser left of it and `delimiters` those the
+/// content was normalized from.
+pub fn collect_diagnostics(
+    contents: &str,
+    unprocessed: &str,
+    delimiters: &Delimiters,
+) -> Diagnostics {
+    let segments = tokenize(contents);
+    let paths = loop_paths(&segments);
+    let mut diagnostics = Diagnostics::default();
+
+    check_envelope(&segments, &mut diagnostics);
+    delimiters.check_data(&segments, &paths, &mut diagnostics);
+    check_elements(&segments, &paths, &mut diagnostics);
+    check_syntax(&segments, &paths, &mut diagnostics);
+    check_hierarchy(&segments, &paths, &mut diagnostics);
+
+    // Leftover segments are matched back to the content in order
+    for (leftover, found) in match_unprocessed(&segments, unprocessed) {
+        let diagnostic = Diagnostic::new(
+            Severity::Warning,
+            DiagnosticCode::UnprocessedSegment,
+            format!("Segment {} was not processed", leftover),
+        );
+        match found {
+            Some(pos) => diagnostics.push(diagnostic.at(&segments[pos], &paths[pos], None)),
+            None => diagnostics.push(Diagnostic {
+                loop_path: leftover.split('*').next().unwrap_or("").to_string(),
+                ..diagnostic
+            }),
+        }
+    }
+
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ISA: &str = "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230501*1200*^*00501*000000001*0*T*:~";
+
+    #[test]
+    fn test_collect_diagnostics_clean() {
+        let contents = format!(
+            "{}GS*HP*SENDER*RECEIVER*20230501*1200*1*X*005010X221A1~ST*835*0001~BPR*I*100~SE*3*0001~GE*1*1~IEA*1*000000001~",
+            ISA
+        );
+        assert!(collect_diagnostics(&contents, "", &Delimiters::default()).is_empty());
+    }
+
+    #[test]
+    fn test_collect_diagnostics_positions() {
+        let contents = format!(
+            "{}GS*HP*SENDER*RECEIVER*20230501*1200*1*X*005010X221A1~ST*835*0001~BPR*I*1,000~LX*1~CLP*A*1*ABC*80~ZZZ*1~SE*7*0002~GE*1*1~IEA*1*000000001~",
+            ISA
+        );
+        let diagnostics = collect_diagnostics(&contents, "ZZZ*1~", &Delimiters::default());
+        let found: Vec<(DiagnosticCode, &str, Option<usize>)> = diagnostics
+            .iter()
+            .map(|d| (d.code, d.loop_path.as_str(), d.segment_index))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (DiagnosticCode::ControlNumberMismatch, "SE02", Some(7)),
+                (DiagnosticCode::CountMismatch, "SE01", Some(7)),
+                (DiagnosticCode::InvalidDecimal, "BPR02", Some(3)),
+                (DiagnosticCode::InvalidDecimal, "2000/2100/CLP03", Some(5)),
+                (DiagnosticCode::UnprocessedSegment, "2000/2100/ZZZ", Some(6)),
+            ]
+        );
+        assert!(diagnostics.has_errors());
+        assert_eq!(
+            diagnostics.iter().nth(3).and_then(|d| d.offset),
+            contents.find("CLP")
+        );
+    }
+
+    #[test]
+    fn test_diagnostics_serialize_as_list() {
+        let mut diagnostics = Diagnostics::default();
+        diagnostics.push(Diagnostic::new(
+            Severity::Warning,
+            DiagnosticCode::UnprocessedSegment,
+            "Segment ZZZ was not processed",
+        ));
+        let json = serde_json::to_string(&diagnostics).unwrap();
+        assert!(json.starts_with("[{\"severity\":\"Warning\",\"code\":\"UnprocessedSegment\""));
+        let parsed: Diagnostics = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, diagnostics);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::helper::delimiters::{STANDARD_COMPONENT, STANDARD_REPETITION};
 use log::info;
 
 /// Safely get element at index, returning empty string if absent.
@@ -15,6 +16,36 @@ pub fn build_segment(elements: &[&str]) -> String {
     format!("{}~", trimmed)
 }
 
+/// Split a repeatable element on the repetition separator (ISA11, normalized to `^`
+/// by `helper::clean_contents`). An empty element yields no repetitions.
+pub fn get_repetitions(element: &str) -> Vec<String> {
+    if element.is_empty() {
+        return Vec::new();
+    }
+    element
+        .split(STANDARD_REPETITION)
+        .map(|value| value.to_string())
+        .collect()
+}
+
+/// Join the repetitions of an element with the repetition separator.
+pub fn join_repetitions(repetitions: &[String]) -> String {
+    repetitions.join(&STANDARD_REPETITION.to_string())
+}
+
+/// Split a composite element on the component separator (ISA16, normalized to `:`
+/// by `helper::clean_contents`). Use with `get_element` to read components by index.
+pub fn get_components(element: &str) -> Vec<&str> {
+    element.split(STANDARD_COMPONENT).collect()
+}
+
+/// Build a composite element, suppressing trailing empty components
+/// the same way `build_segment` suppresses trailing empty elements.
+pub fn build_composite(components: &[&str]) -> String {
+    let joined = components.join(&STANDARD_COMPONENT.to_string());
+    joined.trim_end_matches(STANDARD_COMPONENT).to_string()
+}
+
 pub fn check_if_segment_in_loop(segment: &str, anchor: &str, contents: &str) -> bool {
     if let (Some(segment_pos), Some(anchor_pos)) = (contents.find(segment), contents.find(anchor)) {
         return segment_pos < anchor_pos;
@@ -31,55 +62,23 @@ pub fn check_for_expected_codes(codes: &str, content: &str) -> bool {
     codes.contains(&content)
 }
 
+/// Get the contents of the current loop: everything up to the next `anchor`
+/// segment found after the leading `segment_start` segment.
 pub fn get_loop_contents(segment_start: &str, anchor: &str, contents: &str) -> String {
-    let mut tmp_contents = contents.to_string();
-    let remaining_loop_count = contents.matches(segment_start).count();
-
-    if remaining_loop_count > 1 {
-        let skip = segment_start.len();
-        if let Some(skipped_content) = contents.get(skip..) {
-            if let Some(foundanchor) = skipped_content.find(anchor) {
-                tmp_contents = contents[..foundanchor + skip].to_string();
-            }
-        }
+    match find_next_segment_start(anchor, contents, segment_start.len()) {
+        Some(end) => contents[..end].to_string(),
+        None => contents.to_string(),
     }
-
-    tmp_contents
 }
 
+/// Get the contents of the current 835 claim (Loop 2100), up to the next CLP segment.
 pub fn get_table2(contents: &str) -> String {
-    let mut tmp_contents = contents.to_string();
-    let key = "CLP";
-    let remaining_clp_count = contents.matches(key).count();
-
-    if remaining_clp_count > 1 {
-        let skip = key.len();
-        if let Some(skipped_content) = contents.get(skip..) {
-            if let Some(foundclp) = skipped_content.find(key) {
-                tmp_contents = contents[..foundclp + skip].to_string();
-            }
-        }
-    }
-
-    tmp_contents
+    get_loop_contents("CLP", "CLP", contents)
 }
 
+/// Get the contents of the current 999 transaction set response (Loop 2000), up to the next AK2 segment.
 pub fn get_999_2000(contents: &str) -> String {
-    let mut tmp_contents = contents.to_string();
-    let remaining_ak2_count = contents.matches("AK2").count();
-
-    if remaining_ak2_count > 1 {
-        // Find the next AK2 segment
-        if let Some(pos) = contents.find("AK2") {
-            // Skip the first AK2 and find the next one
-            if let Some(next_pos) = contents[pos + 3..].find("AK2") {
-                // Get content up to the next AK2
-                tmp_contents = contents[..pos + 3 + next_pos].to_string();
-            }
-        }
-    }
-
-    tmp_contents
+    get_loop_contents("AK2", "AK2", contents)
 }
 
 /// Count occurrences of a segment identifier at segment boundaries only.
@@ -108,7 +107,7 @@ pub fn count_segment_starts(key: &str, contents: &str) -> usize {
 pub fn find_next_segment_start(key: &str, contents: &str, skip: usize) -> Option<usize> {
     let nkey = format!("{}*", key);
     let mut search_from = skip;
-    while let Some(pos) = contents[search_from..].find(&nkey) {
+    while let Some(pos) = contents.get(search_from..)?.find(&nkey) {
         let abs_pos = search_from + pos;
         if abs_pos == 0
             || contents.as_bytes()[abs_pos - 1] == b'~'
@@ -228,6 +227,25 @@ pub fn content_trim(key: &str, contents: &str) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_repetitions() {
+        let repetitions = get_repetitions("30^1^33^35");
+        assert_eq!(repetitions, vec!["30", "1", "33", "35"]);
+        assert_eq!(join_repetitions(&repetitions), "30^1^33^35");
+        assert!(get_repetitions("").is_empty());
+        assert_eq!(join_repetitions(&[]), "");
+    }
+
+    #[test]
+    fn test_components() {
+        let components = get_components("HC:99213:25::");
+        assert_eq!(get_element(&components, 1), "99213");
+        assert_eq!(get_element(&components, 2), "25");
+        assert_eq!(get_element(&components, 7), "");
+        assert_eq!(build_composite(&components), "HC:99213:25");
+        assert_eq!(build_composite(&["", ""]), "");
+    }
+
     #[test]
     fn test_get_segment_contents() {
         let key = "N1";
@@ -0,0 +1,401 @@
+//! Element dictionary
+//!
+//! Data type and length of every element of the segments in `segments/`,
+//! listed by each segment module as `ELEMENTS` (position 1 first), following
+//! the X12 5010 data element dictionary. `check_elements` checks every parsed
+//! value against it: numeric (N0, N2) and decimal (R) values per X12
+//! §B.1.1.3.1, dates (DT) and times (TM), and minimum and maximum length.
+//! Composite elements are flagged as such and not checked as a whole.
+//!
+//! Some values take their format from a qualifier instead: a period after a
+//! date time period format qualifier (DE 1250, e.g. `DTP02` `D8`) and an NPI
+//! after identification code qualifier `XX`.
+//!
+//! Data type and length violations are errors, as in the 999 IK4 codes; only
+//! periods that do not match their qualifier are warnings.
+
+use crate::helper::delimiters::STANDARD_REPETITION;
+use crate::helper::diagnostics::{Diagnostic, DiagnosticCode, Diagnostics, Severity};
+use crate::helper::numeric_validation::{is_valid_decimal, is_valid_numeric};
+use crate::helper::tokenizer::Segment;
+use crate::segments::element_dictionary;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataType {
+    /// String
+    AN,
+    /// Identifier (code value)
+    ID,
+    /// Numeric without implied decimals
+    N0,
+    /// Numeric with two implied decimals
+    N2,
+    /// Decimal
+    R,
+    /// Date, `CCYYMMDD` (`YYMMDD` in the ISA and TA1)
+    DT,
+    /// Time, `HHMM[SS[d..]]`
+    TM,
+    /// Composite data structure, checked component by component elsewhere
+    Composite,
+}
+
+/// Dictionary entry of one element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ElementDef {
+    pub data_type: DataType,
+    pub min: usize,
+    pub max: usize,
+    /// The element may repeat, separated by the repetition separator
+    pub repeats: bool,
+}
+
+impl ElementDef {
+    pub const fn new(data_type: DataType, min: usize, max: usize) -> ElementDef {
+        ElementDef {
+            data_type,
+            min,
+            max,
+            repeats: false,
+        }
+    }
+
+    /// The same element, repeating.
+    pub const fn repeating(mut self) -> ElementDef {
+        self.repeats = true;
+        self
+    }
+
+    pub fn is_composite(&self) -> bool {
+        self.data_type == DataType::Composite
+    }
+
+    /// The problem with `value`, if any, as a diagnostic code and description.
+    pub fn check(&self, value: &str) -> Option<(DiagnosticCode, String)> {
+        let (valid, code, expected) = match self.data_type {
+            DataType::AN | DataType::ID | DataType::Composite => {
+                (true, DiagnosticCode::InvalidLength, "")
+            }
+            DataType::N0 | DataType::N2 => (
+                is_valid_numeric(value),
+                DiagnosticCode::InvalidNumeric,
+                "numeric (N) per X12 §B.1.1.3.1.1",
+            ),
+            DataType::R => (
+                is_valid_decimal(value),
+                DiagnosticCode::InvalidDecimal,
+                "decimal (R) per X12 §B.1.1.3.1.2",
+            ),
+            DataType::DT => (
+                is_valid_date(value),
+                DiagnosticCode::InvalidDate,
+                "date (DT) as CCYYMMDD",
+            ),
+            DataType::TM => (
+                is_valid_time(value),
+                DiagnosticCode::InvalidTime,
+                "time (TM) as HHMM[SS[d..]]",
+            ),
+        };
+        if !valid {
+            return Some((code, format!("expected {}", expected)));
+        }
+        if self.is_composite() {
+            return None;
+        }
+        // Signs and decimal points do not count towards the length of numbers
+        let length = match self.data_type {
+            DataType::N0 | DataType::N2 | DataType::R => {
+                value.chars().filter(|c| c.is_ascii_digit()).count()
+            }
+            _ => value.chars().count(),
+        };
+        if length < self.min || length > self.max {
+            return Some((
+                DiagnosticCode::InvalidLength,
+                format!("length {} is outside {}/{}", length, self.min, self.max),
+            ));
+        }
+        None
+    }
+}
+
+/// String element of `min` to `max` characters.
+pub const fn an(min: usize, max: usize) -> ElementDef {
+    ElementDef::new(DataType::AN, min, max)
+}
+
+/// Identifier element of `min` to `max` characters.
+pub const fn id(min: usize, max: usize) -> ElementDef {
+    ElementDef::new(DataType::ID, min, max)
+}
+
+/// Numeric element of `min` to `max` digits.
+pub const fn n0(min: usize, max: usize) -> ElementDef {
+    ElementDef::new(DataType::N0, min, max)
+}
+
+/// Numeric element with two implied decimals, of `min` to `max` digits.
+pub const fn n2(min: usize, max: usize) -> ElementDef {
+    ElementDef::new(DataType::N2, min, max)
+}
+
+/// Decimal element of `min` to `max` digits.
+pub const fn r(min: usize, max: usize) -> ElementDef {
+    ElementDef::new(DataType::R, min, max)
+}
+
+/// Date element of `min` to `max` digits.
+pub const fn dt(min: usize, max: usize) -> ElementDef {
+    ElementDef::new(DataType::DT, min, max)
+}
+
+/// Time element of `min` to `max` digits.
+pub const fn tm(min: usize, max: usize) -> ElementDef {
+    ElementDef::new(DataType::TM, min, max)
+}
+
+/// Composite element.
+pub const fn composite() -> ElementDef {
+    ElementDef::new(DataType::Composite, 0, 0)
+}
+
+/// A date: `CCYYMMDD`, or `YYMMDD`, with a valid month and a day that exists
+/// in that month. `YYMMDD` years are taken as 20YY for leap years.
+pub fn is_valid_date(value: &str) -> bool {
+    if !matches!(value.len(), 6 | 8) || !value.chars().all(|c| c.is_ascii_digit()) {
+        return false;
+    }
+    let year: u32 = value[..value.len() - 4].parse().unwrap_or(0);
+    let year = if value.len() == 6 { 2000 + year } else { year };
+    let month: u32 = value[value.len() - 4..value.len() - 2].parse().unwrap_or(0);
+    let day: u32 = value[value.len() - 2..].parse().unwrap_or(0);
+    let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days).contains(&day)
+}
+
+/// A time: `HHMM`, `HHMMSS` or `HHMMSSd..` with up to two decimal seconds.
+pub fn is_valid_time(value: &str) -> bool {
+    if !matches!(value.len(), 4 | 6 | 7 | 8) || !value.chars().all(|c| c.is_ascii_digit()) {
+        return false;
+    }
+    let hours: u32 = value[0..2].parse().unwrap_or(99);
+    let minutes: u32 = value[2..4].parse().unwrap_or(99);
+    let seconds: u32 = value.get(4..6).map_or(0, |s| s.parse().unwrap_or(99));
+    hours < 24 && minutes < 60 && seconds < 60
+}
+
+/// Segments whose element at the position is a date time period format
+/// qualifier (DE 1250) for the next element.
+const PERIOD_QUALIFIERS: &[(&str, usize)] = &[("DTP", 2), ("DTM", 5), ("DMG", 1), ("INS", 11)];
+
+/// Segments whose element at the position is an identification code
+/// qualifier (DE 66) for the next element.
+const ID_QUALIFIERS: &[(&str, usize)] = &[("NM1", 8), ("N1", 3)];
+
+/// Format of a period given its DE 1250 qualifier.
+fn period_definition(qualifier: &str) -> Option<ElementDef> {
+    match qualifier {
+        "D8" => Some(dt(8, 8)),
+        "D6" => Some(dt(6, 6)),
+        "TM" => Some(tm(4, 4)),
+        _ => None,
+    }
+}
+
+/// The problem with a value whose format its qualifier decides. Periods are
+/// only warned about, as their qualifier may be what is wrong; an NPI that is
+/// not 10 digits is an error.
+fn check_qualified(
+    segment: &Segment,
+    position: usize,
+) -> Option<(Severity, DiagnosticCode, String)> {
+    if position < 2 {
+        return None;
+    }
+    let qualifier = segment.element(position - 2);
+    let value = segment.element(position - 1);
+    if PERIOD_QUALIFIERS.contains(&(segment.id, position - 1)) {
+        return match qualifier {
+            "RD8" => match value.split_once('-') {
+                Some((from, to)) if is_valid_date(from) && is_valid_date(to) && from.len() == 8 => {
+                    None
+                }
+                _ => Some((
+                    Severity::Warning,
+                    DiagnosticCode::InvalidDate,
+                    "expected a date range as CCYYMMDD-CCYYMMDD".to_string(),
+                )),
+            },
+            "DT" => {
+                // Sliced with `get` so that non-ASCII values are reported, not panicked on
+                let valid = value.len() == 12
+                    && value.get(..8).is_some_and(is_valid_date)
+                    && value.get(8..).is_some_and(is_valid_time);
+                (!valid).then(|| {
+                    (
+                        Severity::Warning,
+                        DiagnosticCode::InvalidDate,
+                        "expected a date and time as CCYYMMDDHHMM".to_string(),
+                    )
+                })
+            }
+            _ => period_definition(qualifier)
+                .and_then(|definition| definition.check(value))
+                .map(|(code, message)| {
+                    let message = format!("{} for format {}", message, qualifier);
+                    (Severity::Warning, code, message)
+                }),
+        };
+    }
+    if ID_QUALIFIERS.contains(&(segment.id, position - 1)) && qualifier == "XX" {
+        let valid = value.len() == 10 && value.chars().all(|c| c.is_ascii_digit());
+        return (!valid).then(|| {
+            (
+                Severity::Error,
+                DiagnosticCode::InvalidLength,
+                "expected a 10-digit NPI for qualifier XX".to_string(),
+            )
+        });
+    }
+    None
+}
+
+/// Report element values of tokenized content that do not match the element
+/// dictionary. `paths` holds the loop path of each segment (see `loop_path::loop_paths`).
+pub fn check_elements(segments: &[Segment], paths: &[String], diagnostics: &mut Diagnostics) {
+    for (segment, path) in segments.iter().zip(paths) {
+        let definitions = element_dictionary(segment.id);
+        for (index, value) in segment.elements.iter().enumerate() {
+            if value.is_empty() {
+                continue;
+            }
+            let position = index + 1;
+            // Data type and length violations are errors, as in 999 IK4
+            let problem = check_qualified(segment, position).or_else(|| {
+                definitions
+                    .get(index)
+                    .and_then(|definition| {
+                        if definition.repeats {
+                            value
+                                .split(STANDARD_REPETITION)
+                                .find_map(|repetition| definition.check(repetition))
+                        } else {
+                            definition.check(value)
+                        }
+                    })
+                    .map(|(code, problem)| (Severity::Error, code, problem))
+            });
+            if let Some((severity, code, problem)) = problem {
+                diagnostics.push(
+                    Diagnostic::new(
+                        severity,
+                        code,
+                        format!(
+                            "Invalid value '{}' in {}{:02} — {}",
+                            value, segment.id, position, problem
+                        ),
+                    )
+                    .at(segment, path, Some(position)),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::loop_path::loop_paths;
+    use crate::helper::tokenizer::tokenize;
+
+    fn invalid(contents: &str) -> Vec<(DiagnosticCode, String)> {
+        let segments = tokenize(contents);
+        let paths = loop_paths(&segments);
+        let mut diagnostics = Diagnostics::default();
+        check_elements(&segments, &paths, &mut diagnostics);
+        diagnostics
+            .iter()
+            .map(|d| (d.code, d.loop_path.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_dates_and_times() {
+        assert!(is_valid_date("20230501"));
+        assert!(is_valid_date("230501"));
+        assert!(!is_valid_date("20231301"));
+        assert!(!is_valid_date("20230231"));
+        assert!(!is_valid_date("20230431"));
+        assert!(is_valid_date("20240229"));
+        assert!(!is_valid_date("20230229"));
+        assert!(!is_valid_date("19000229"));
+        assert!(is_valid_date("20000229"));
+        assert!(!is_valid_date("230229"));
+        assert!(!is_valid_date("2023-05-01"));
+        assert!(is_valid_time("1200"));
+        assert!(is_valid_time("12000099"));
+        assert!(!is_valid_time("2460"));
+        assert!(!is_valid_time("120"));
+    }
+
+    #[test]
+    fn test_definitions() {
+        assert!(composite().is_composite());
+        assert_eq!(an(1, 60).check(&"A".repeat(60)), None);
+        assert_eq!(
+            an(1, 60).check(&"A".repeat(61)).map(|(code, _)| code),
+            Some(DiagnosticCode::InvalidLength)
+        );
+        // Signs and decimal points are not counted
+        assert_eq!(r(1, 3).check("-1.23"), None);
+        assert_eq!(
+            n0(1, 6).check("12.5").map(|(code, _)| code),
+            Some(DiagnosticCode::InvalidNumeric)
+        );
+    }
+
+    #[test]
+    fn test_check_elements() {
+        let clean =
+            "NM1*85*2*BILLING*****XX*1234567893~DTP*472*D8*20230501~DTP*472*RD8*20230501-20230502~\
+EQ*30^1~LX*1~AMT*D*10.50~";
+        assert!(invalid(clean).is_empty(), "{:?}", invalid(clean));
+
+        let broken =
+            "NM1*85*2*BILLING*****XX*12345678931~DTP*472*TM*12000000~DTP*472*RD8*20230501~\
+EQ*30^1234~LX*A~AMT*D*10.50*TOOLONG~N4*C*ST*12345~";
+        assert_eq!(
+            invalid(broken),
+            vec![
+                (DiagnosticCode::InvalidLength, "NM109".to_string()),
+                (DiagnosticCode::InvalidLength, "DTP03".to_string()),
+                (DiagnosticCode::InvalidDate, "DTP03".to_string()),
+                (DiagnosticCode::InvalidLength, "EQ01".to_string()),
+                (DiagnosticCode::InvalidNumeric, "LX01".to_string()),
+                (DiagnosticCode::InvalidLength, "AMT03".to_string()),
+                (DiagnosticCode::InvalidLength, "N401".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_non_ascii_date_time_period() {
+        // Multi-byte characters where the date and time split used to panic
+        assert_eq!(
+            invalid("DTP*472*DT*1234567é123~DTP*472*DT*202302311200~"),
+            vec![
+                (DiagnosticCode::InvalidDate, "DTP03".to_string()),
+                (DiagnosticCode::InvalidDate, "DTP03".to_string()),
+            ]
+        );
+        assert!(invalid("DTP*472*DT*202305011200~").is_empty());
+    }
+}
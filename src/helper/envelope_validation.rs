@@ -1,7 +1,8 @@
-use log::warn;
+use crate::helper::diagnostics::{Diagnostic, DiagnosticCode, Diagnostics, Severity};
+use crate::helper::tokenizer::Segment;
 
 /// Validate X12 envelope control numbers and counts.
-/// Logs warnings for mismatches but does not fail — allows processing
+/// Mismatches are reported as diagnostics but do not fail — allows processing
 /// of structurally invalid files while alerting the caller.
 ///
 /// Per X12 specs:
@@ -12,52 +13,301 @@ use log::warn;
 
 /// Validate that ST02 and SE02 control numbers match.
 pub fn validate_st_se(st02: &str, se02: &str) -> bool {
-    if st02 != se02 {
-        warn!(
-            "Envelope error: ST02 ({}) != SE02 ({}). Per X12 spec, these must be identical.",
-            st02, se02
-        );
-        return false;
-    }
-    true
+    st02 == se02
 }
 
 /// Validate that SE01 equals the actual segment count (including ST and SE).
 pub fn validate_segment_count(se01: &str, actual_count: usize) -> bool {
-    if let Ok(expected) = se01.parse::<usize>() {
-        if expected != actual_count {
-            warn!(
-                "Envelope error: SE01 claims {} segments but found {}.",
-                expected, actual_count
-            );
-            return false;
-        }
-    }
-    true
+    se01.parse::<usize>()
+        .map_or(true, |expected| expected == actual_count)
 }
 
 /// Validate that GS06 and GE02 control numbers match.
 pub fn validate_gs_ge(gs06: &str, ge02: &str) -> bool {
-    if gs06 != ge02 {
-        warn!(
-            "Envelope error: GS06 ({}) != GE02 ({}). Per X12 spec, these must be identical.",
-            gs06, ge02
-        );
-        return false;
-    }
-    true
+    gs06 == ge02
 }
 
 /// Validate that ISA13 and IEA02 control numbers match.
 pub fn validate_isa_iea(isa13: &str, iea02: &str) -> bool {
-    if isa13.trim() != iea02.trim() {
-        warn!(
-            "Envelope error: ISA13 ({}) != IEA02 ({}). Per X12 spec, these must be identical.",
-            isa13, iea02
-        );
-        return false;
+    isa13.trim() == iea02.trim()
+}
+
+/// An open envelope: its header segment and the number of units it encloses so far.
+struct Open<'s, 'a> {
+    header: &'s Segment<'a>,
+    count: usize,
+}
+
+/// An envelope error located at `segment` (outside of any loop).
+pub fn envelope_error(
+    code: DiagnosticCode,
+    segment: &Segment,
+    element: Option<usize>,
+    message: String,
+) -> Diagnostic {
+    Diagnostic::new(Severity::Error, code, message).at(segment, "", element)
+}
+
+/// Report a header segment (ISA, GS, ST) that has no trailer.
+pub fn missing_trailer(header: &Segment, trailer: &str) -> Diagnostic {
+    let control_number = match header.id {
+        "ISA" => header.element(12),
+        "GS" => header.element(5),
+        _ => header.element(1),
+    };
+    envelope_error(
+        DiagnosticCode::MissingTrailer,
+        header,
+        None,
+        format!(
+            "{} {} has no {} segment",
+            header.id, control_number, trailer
+        ),
+    )
+}
+
+/// Check every ISA/IEA, GS/GE and ST/SE pair of tokenized content: control
+/// numbers, trailer counts, missing trailers, and segments found outside of
+/// a transaction set.
+pub fn check_envelope(segments: &[Segment], diagnostics: &mut Diagnostics) {
+    let mut isa: Option<Open> = None;
+    let mut gs: Option<Open> = None;
+    let mut st: Option<Open> = None;
+
+    for segment in segments {
+        match segment.id {
+            "ISA" | "GS" | "ST" => {
+                // A header at the same or an outer level means the open ones were never closed
+                if let Some(open) = st.take() {
+                    diagnostics.push(missing_trailer(open.header, "SE"));
+                }
+                if segment.id != "ST" {
+                    if let Some(open) = gs.take() {
+                        diagnostics.push(missing_trailer(open.header, "GE"));
+                    }
+                }
+                if segment.id == "ISA" {
+                    if let Some(open) = isa.take() {
+                        diagnostics.push(missing_trailer(open.header, "IEA"));
+                    }
+                }
+                let opened = Some(Open {
+                    header: segment,
+                    count: 0,
+                });
+                match segment.id {
+                    "ISA" => isa = opened,
+                    "GS" => {
+                        if let Some(interchange) = isa.as_mut() {
+                            interchange.count += 1;
+                        }
+                        gs = opened;
+                    }
+                    _ => {
+                        if let Some(group) = gs.as_mut() {
+                            group.count += 1;
+                        }
+                        st = opened;
+                    }
+                }
+            }
+            "SE" => {
+                match st.take() {
+                    Some(open) => {
+                        let (st02, se02) = (open.header.element(1), segment.element(1));
+                        if !validate_st_se(st02, se02) {
+                            diagnostics.push(envelope_error(
+                            DiagnosticCode::ControlNumberMismatch,
+                            segment,
+                            Some(2),
+                            format!("ST02 ({}) != SE02 ({}). Per X12 spec, these must be identical.", st02, se02),
+                        ));
+                        }
+                        let actual = segment.index - open.header.index + 1;
+                        if !validate_segment_count(segment.element(0), actual) {
+                            diagnostics.push(envelope_error(
+                                DiagnosticCode::CountMismatch,
+                                segment,
+                                Some(1),
+                                format!(
+                                    "SE01 claims {} segments but found {}.",
+                                    segment.element(0),
+                                    actual
+                                ),
+                            ));
+                        }
+                    }
+                    None => diagnostics.push(unexpected(segment, "without an ST segment")),
+                }
+            }
+            "GE" => {
+                match gs.take() {
+                    Some(open) => {
+                        if let Some(transaction) = st.take() {
+                            diagnostics.push(missing_trailer(transaction.header, "SE"));
+                        }
+                        let (gs06, ge02) = (open.header.element(5), segment.element(1));
+                        if !validate_gs_ge(gs06, ge02) {
+                            diagnostics.push(envelope_error(
+                            DiagnosticCode::ControlNumberMismatch,
+                            segment,
+                            Some(2),
+                            format!("GS06 ({}) != GE02 ({}). Per X12 spec, these must be identical.", gs06, ge02),
+                        ));
+                        }
+                        if !validate_segment_count(segment.element(0), open.count) {
+                            diagnostics.push(envelope_error(
+                                DiagnosticCode::CountMismatch,
+                                segment,
+                                Some(1),
+                                format!(
+                                    "GE01 claims {} transaction sets but found {}.",
+                                    segment.element(0),
+                                    open.count
+                                ),
+                            ));
+                        }
+                    }
+                    None => diagnostics.push(unexpected(segment, "without a GS segment")),
+                }
+            }
+            "IEA" => {
+                match isa.take() {
+                    Some(open) => {
+                        if let Some(group) = gs.take() {
+                            diagnostics.push(missing_trailer(group.header, "GE"));
+                        }
+                        let (isa13, iea02) = (open.header.element(12), segment.element(1));
+                        if !validate_isa_iea(isa13, iea02) {
+                            diagnostics.push(envelope_error(
+                            DiagnosticCode::ControlNumberMismatch,
+                            segment,
+                            Some(2),
+                            format!("ISA13 ({}) != IEA02 ({}). Per X12 spec, these must be identical.", isa13, iea02),
+                        ));
+                        }
+                        if !validate_segment_count(segment.element(0), open.count) {
+                            diagnostics.push(envelope_error(
+                                DiagnosticCode::CountMismatch,
+                                segment,
+                                Some(1),
+                                format!(
+                                    "IEA01 claims {} functional groups but found {}.",
+                                    segment.element(0),
+                                    open.count
+                                ),
+                            ));
+                        }
+                    }
+                    None => diagnostics.push(unexpected(segment, "without an ISA segment")),
+                }
+            }
+            _ if st.is_none() => {
+                diagnostics.push(unexpected(segment, "outside of a transaction set"))
+            }
+            _ => {}
+        }
+    }
+
+    for (open, trailer) in [(st, "SE"), (gs, "GE"), (isa, "IEA")] {
+        if let Some(open) = open {
+            diagnostics.push(missing_trailer(open.header, trailer));
+        }
     }
-    true
+}
+
+/// Bring the trailers of written segments (standard delimiters, without
+/// terminators) in line with what they enclose: SE01, GE01 and IEA01 become
+/// the actual segment, transaction set and group counts, and SE02, GE02 and
+/// IEA02 take the control numbers of ST02, GS06 and ISA13. Trailers that
+/// already pass the checks above are left untouched. Returns the number of
+/// elements changed.
+pub fn sync_envelope(segments: &mut [String]) -> usize {
+    let mut changed = 0;
+    let mut isa13 = String::new();
+    let mut gs06 = String::new();
+    let mut st: Option<(usize, String)> = None;
+    let (mut groups, mut transactions) = (0, 0);
+
+    for index in 0..segments.len() {
+        let segment = &mut segments[index];
+        match segment_id(segment) {
+            "ISA" => {
+                isa13 = element(segment, 13).trim().to_string();
+                groups = 0;
+            }
+            "GS" => {
+                gs06 = element(segment, 6).to_string();
+                groups += 1;
+                transactions = 0;
+            }
+            "ST" => {
+                st = Some((index, element(segment, 2).to_string()));
+                transactions += 1;
+            }
+            "SE" => {
+                if let Some((start, st02)) = st.take() {
+                    let count = index - start + 1;
+                    changed += sync_count(segment, count);
+                    if !validate_st_se(&st02, element(segment, 2)) {
+                        changed += set_element(segment, 2, &st02);
+                    }
+                }
+            }
+            "GE" => {
+                changed += sync_count(segment, transactions);
+                if !validate_gs_ge(&gs06, element(segment, 2)) {
+                    changed += set_element(segment, 2, &gs06);
+                }
+            }
+            "IEA" => {
+                changed += sync_count(segment, groups);
+                if !validate_isa_iea(&isa13, element(segment, 2)) {
+                    changed += set_element(segment, 2, &isa13);
+                }
+            }
+            _ => {}
+        }
+    }
+    changed
+}
+
+/// Set element 1 of a trailer to `count` unless it already holds it.
+fn sync_count(segment: &mut String, count: usize) -> usize {
+    let current = element(segment, 1);
+    if current.parse::<usize>().is_ok() && validate_segment_count(current, count) {
+        return 0;
+    }
+    set_element(segment, 1, &count.to_string())
+}
+
+fn segment_id(segment: &str) -> &str {
+    segment.split('*').next().unwrap_or("").trim()
+}
+
+/// Element at `position` (1-based, as in `SE01`) of a standard-delimited segment.
+fn element(segment: &str, position: usize) -> &str {
+    segment.split('*').nth(position).unwrap_or("")
+}
+
+fn set_element(segment: &mut String, position: usize, value: &str) -> usize {
+    let mut elements: Vec<&str> = segment.split('*').collect();
+    if elements.len() <= position {
+        elements.resize(position + 1, "");
+    }
+    elements[position] = value;
+    *segment = elements.join("*");
+    1
+}
+
+/// Report a segment found outside of its enclosing envelope.
+pub fn unexpected(segment: &Segment, context: &str) -> Diagnostic {
+    Diagnostic::new(
+        Severity::Warning,
+        DiagnosticCode::UnexpectedSegment,
+        format!("{} segment found {}", segment.id, context),
+    )
+    .at(segment, "", None)
 }
 
 #[cfg(test)]
@@ -108,37 +358,41 @@ mod tests {
     fn test_isa_iea_mismatch() {
         assert!(!validate_isa_iea("000000905", "000000906"));
     }
-}
 
-/// Validate the X12 envelope of raw EDI content.
-/// Extracts ISA13/IEA02, GS06/GE02, ST02/SE02 and checks they match per spec.
-pub fn validate_raw_envelope(contents: &str) {
-    use crate::helper::edihelper::{get_element, get_segment_contents};
-
-    // ISA13 vs IEA02
-    let isa = get_segment_contents("ISA", contents);
-    let iea = get_segment_contents("IEA", contents);
-    if !isa.is_empty() && !iea.is_empty() {
-        let isa_parts: Vec<&str> = isa.split('*').collect();
-        let iea_parts: Vec<&str> = iea.split('*').collect();
-        validate_isa_iea(&get_element(&isa_parts, 12), &get_element(&iea_parts, 1));
-    }
-
-    // GS06 vs GE02
-    let gs = get_segment_contents("GS", contents);
-    let ge = get_segment_contents("GE", contents);
-    if !gs.is_empty() && !ge.is_empty() {
-        let gs_parts: Vec<&str> = gs.split('*').collect();
-        let ge_parts: Vec<&str> = ge.split('*').collect();
-        validate_gs_ge(&get_element(&gs_parts, 5), &get_element(&ge_parts, 1));
-    }
-
-    // ST02 vs SE02
-    let st = get_segment_contents("ST", contents);
-    let se = get_segment_contents("SE", contents);
-    if !st.is_empty() && !se.is_empty() {
-        let st_parts: Vec<&str> = st.split('*').collect();
-        let se_parts: Vec<&str> = se.split('*').collect();
-        validate_st_se(&get_element(&st_parts, 1), &get_element(&se_parts, 1));
+    #[test]
+    fn test_sync_envelope() {
+        let contents = "ISA*00*          *00*          *ZZ*S*ZZ*R*230501*1200*^*00501*000000905*0*T*:~GS*HP*S*R*20230501*1200*7*X*005010X221A1~ST*835*0001~BPR*I*100~LX*1~SE*3*0009~ST*835*0002~SE*2*0002~GE*1*8~IEA*X*000000906~";
+        let mut segments: Vec<String> =
+            contents.split_terminator('~').map(str::to_string).collect();
+        assert_eq!(sync_envelope(&mut segments), 6);
+        assert_eq!(segments[5], "SE*4*0001");
+        assert_eq!(segments[7], "SE*2*0002");
+        assert_eq!(segments[8], "GE*2*7");
+        assert_eq!(segments[9], "IEA*1*000000905");
+        assert_eq!(sync_envelope(&mut segments), 0);
+    }
+
+    #[test]
+    fn test_check_envelope_missing_trailers() {
+        use crate::helper::tokenizer::tokenize;
+
+        let contents = "ISA*00*          *00*          *ZZ*S*ZZ*R*230501*1200*^*00501*000000905*0*T*:~GS*HP*S*R*20230501*1200*7*X*005010X221A1~ST*835*0001~BPR*I*100~GS*HP*S*R*20230501*1200*8*X*005010X221A1~ST*835*0002~SE*2*0002~GE*1*8~IEA*3*000000905~REF*EV*1~";
+        let segments = tokenize(contents);
+        let mut diagnostics = Diagnostics::default();
+        check_envelope(&segments, &mut diagnostics);
+
+        let found: Vec<(DiagnosticCode, &str, Option<usize>)> = diagnostics
+            .iter()
+            .map(|d| (d.code, d.loop_path.as_str(), d.segment_index))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (DiagnosticCode::MissingTrailer, "ST", Some(2)),
+                (DiagnosticCode::MissingTrailer, "GS", Some(1)),
+                (DiagnosticCode::CountMismatch, "IEA01", Some(8)),
+                (DiagnosticCode::UnexpectedSegment, "REF", Some(9)),
+            ]
+        );
     }
 }
@@ -1,4 +1,7 @@
-use log::{info, warn};
+use crate::error::{EdiError, EdiResult};
+use crate::helper::delimiters::Delimiters;
+use crate::validation::SnipLevel;
+use log::info;
 /**
  * Helper module for EDI processing
  *
@@ -23,39 +26,86 @@ pub struct Args {
     pub output_file: String,
     pub operation: String,
     pub is_json: bool,
+    pub stream: bool,
+    /// Fail on segments the parser does not recognize instead of keeping them
+    pub strict: bool,
+    /// Recompute envelope trailer counts and control numbers when writing EDI
+    pub sync_envelope: bool,
+    /// Renumber HL01/HL02/HL04 from the written hierarchy when writing EDI
+    pub renumber_hl: bool,
+    /// Registry file to assign ISA13, GS06 and ST02 from when writing EDI
+    pub control_numbers: Option<String>,
+    /// SNIP levels to validate raw EDI at; empty for no validation
+    pub snip_levels: Vec<SnipLevel>,
+    /// Directory of external code list files to validate codes against
+    pub code_sets: Option<String>,
+    /// `-h`/`--help` was given; the caller prints `USAGE` and stops
+    pub help: bool,
 }
 
+/// Command line usage, printed for `-h`/`--help`
+pub const USAGE: &str = "Usage:
+
+To provide EDI file use '-f'
+To specify the output file use '-o'
+To write EDI from JSON use '-w'
+To specify input is JSON use '-j'
+To stream 834/835/837 files as JSON lines (one member or claim per line) use '-s'
+To fail on segments the parser does not recognize, or written segments that break syntax notes, use '--strict'
+To recompute SE/GE/IEA counts and control numbers when writing use '--sync-envelope'
+To renumber HL segments from the nested loops when writing use '--renumber-hl'
+To assign new control numbers from a registry file when writing use '--control-numbers <file>'
+To validate raw EDI at SNIP levels use '--snip <levels>', e.g. '--snip 1-5'
+To check codes against external code lists (CARC, RARC, ...) when validating, and describe them in the JSON when reading, use '--code-sets <dir>'";
+
 /**
  * Set up the logger for the application
  *
- * Configures the logger to display info level messages without timestamps or targets
+ * Configures the logger to display info level messages without timestamps or targets.
+ * Does nothing if a logger is already installed.
  */
 pub fn set_logger() {
-    env_logger::builder()
+    let _ = env_logger::builder()
         .filter_level(log::LevelFilter::Info)
         .format_target(false)
         .format_timestamp(None)
-        .init();
+        .try_init();
 }
 
 /**
  * Process command line arguments
  *
- * Parses the command line arguments and returns an Args structure
+ * Parses the process's command line arguments and returns an Args structure
  *
  * Arguments:
  * - -f: Input file path
  * - -o: Output file path
  * - -w: Write mode (convert JSON to EDI)
  * - -j: Input is JSON
+ * - -s: Stream 834/835/837 units as JSON lines
+ * - --strict: Fail on unrecognized segments and on written segments that break syntax notes
+ * - --sync-envelope: Recompute trailer counts and control numbers on write
+ * - --renumber-hl: Renumber HL01/HL02/HL04 on write
+ * - --control-numbers: Registry file to assign control numbers from on write
+ * - --snip: SNIP levels to validate at, e.g. 1-5 or 1,2,4
+ * - --code-sets: Directory of code list files (carc.csv, rarc.json, ...) to validate against and describe codes from
  * - -h/--help: Show help information
  *
  * Returns:
- * - Args structure with parsed arguments
+ * - Args structure with parsed arguments, or an error for a missing file name
+ */
+pub fn process_args() -> EdiResult<Args> {
+    parse_args(std::env::args().skip(1))
+}
+
+/**
+ * Parse command line arguments
+ *
+ * Same as `process_args`, for arguments given by the caller (without the program name)
  */
-pub fn process_args() -> Args {
+pub fn parse_args<I: IntoIterator<Item = String>>(arguments: I) -> EdiResult<Args> {
     let mut args = Args::default();
-    let mut args_iter = std::env::args().skip(1);
+    let mut args_iter = arguments.into_iter();
     let mut operation = String::from("read");
 
     while let Some(arg) = args_iter.next() {
@@ -66,8 +116,9 @@ pub fn process_args() -> Args {
                     info!("{:?}", file_path);
                     args.file_path = file_path;
                 } else {
-                    warn!("No file provided after -f");
-                    std::process::exit(1);
+                    return Err(EdiError::MissingField(
+                        "No file provided after -f".to_string(),
+                    ));
                 }
             }
             "-o" => {
@@ -76,8 +127,9 @@ pub fn process_args() -> Args {
                     info!("{:?}", output_file);
                     args.output_file = output_file;
                 } else {
-                    warn!("No file provided after -o");
-                    std::process::exit(1);
+                    return Err(EdiError::MissingField(
+                        "No file provided after -o".to_string(),
+                    ));
                 }
             }
             "-w" => {
@@ -88,14 +140,57 @@ pub fn process_args() -> Args {
                 info!("-j provided");
                 args.is_json = true;
             }
+            "-s" => {
+                info!("-s provided");
+                args.stream = true;
+            }
+            "--strict" => {
+                info!("--strict provided");
+                args.strict = true;
+            }
+            "--sync-envelope" => {
+                info!("--sync-envelope provided");
+                args.sync_envelope = true;
+            }
+            "--renumber-hl" => {
+                info!("--renumber-hl provided");
+                args.renumber_hl = true;
+            }
+            "--control-numbers" => {
+                info!("--control-numbers provided");
+                if let Some(registry) = args_iter.next() {
+                    info!("{:?}", registry);
+                    args.control_numbers = Some(registry);
+                } else {
+                    return Err(EdiError::MissingField(
+                        "No file provided after --control-numbers".to_string(),
+                    ));
+                }
+            }
+            "--snip" => {
+                info!("--snip provided");
+                if let Some(levels) = args_iter.next() {
+                    args.snip_levels = SnipLevel::parse_levels(&levels)?;
+                } else {
+                    return Err(EdiError::MissingField(
+                        "No levels provided after --snip".to_string(),
+                    ));
+                }
+            }
+            "--code-sets" => {
+                info!("--code-sets provided");
+                if let Some(dir) = args_iter.next() {
+                    info!("{:?}", dir);
+                    args.code_sets = Some(dir);
+                } else {
+                    return Err(EdiError::MissingField(
+                        "No directory provided after --code-sets".to_string(),
+                    ));
+                }
+            }
             "-h" | "--help" => {
-                println!("Usage:");
-                println!();
-                println!("To provide EDI file use '-f'");
-                println!("To specify the output file use '-o'");
-                println!("To write EDI from JSON use '-w'");
-                println!("To specify input is JSON use '-j'");
-                std::process::exit(0);
+                args.help = true;
+                return Ok(args);
             }
             _ => {}
         }
@@ -110,8 +205,9 @@ pub fn process_args() -> Args {
     }
 
     if args.file_path.is_empty() {
-        warn!("No file provided, please use -f to pass in the file name");
-        std::process::exit(1);
+        return Err(EdiError::MissingField(
+            "No file provided, please use -f to pass in the file name".to_string(),
+        ));
     }
 
     if args.output_file.is_empty() {
@@ -123,9 +219,12 @@ pub fn process_args() -> Args {
         info!("Using default output file: {}", args.output_file);
     }
 
-    args
+    Ok(args)
 }
 
+/// Maximum file size: 256 MB
+const MAX_FILE_SIZE: u64 = 256 * 1024 * 1024;
+
 /**
  * Read file contents
  *
@@ -135,51 +234,30 @@ pub fn process_args() -> Args {
  * - args: Args structure with file_path
  *
  * Returns:
- * - String containing the file contents
+ * - String containing the file contents, or an error if the file is missing,
+ *   unreadable or larger than 256 MB
  */
-/// Maximum file size: 256 MB
-const MAX_FILE_SIZE: u64 = 256 * 1024 * 1024;
-
-pub fn get_file_contents(args: Args) -> String {
+pub fn get_file_contents(args: &Args) -> EdiResult<String> {
     let mut contents = String::new();
     let file_path = Path::new(&args.file_path);
 
-    if file_path.exists() {
-        info!("File exists");
-        let metadata = match std::fs::metadata(file_path) {
-            Ok(m) => m,
-            Err(e) => {
-                warn!("Failed to read file metadata: {}", e);
-                std::process::exit(1);
-            }
-        };
-        if metadata.len() > MAX_FILE_SIZE {
-            warn!(
-                "File too large: {} bytes (max {} bytes)",
-                metadata.len(),
-                MAX_FILE_SIZE
-            );
-            std::process::exit(1);
-        }
-        let mut file = match File::open(file_path) {
-            Ok(f) => f,
-            Err(e) => {
-                warn!("Failed to open file: {}", e);
-                std::process::exit(1);
-            }
-        };
-        match file.read_to_string(&mut contents) {
-            Ok(_) => {}
-            Err(e) => {
-                warn!("Failed to read file: {}", e);
-                std::process::exit(1);
-            }
-        };
-    } else {
-        warn!("File does not exist: {}", args.file_path);
-        std::process::exit(1);
+    if !file_path.exists() {
+        return Err(EdiError::IoError(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("File does not exist: {}", args.file_path),
+        )));
     }
-    contents
+    info!("File exists");
+    let metadata = std::fs::metadata(file_path)?;
+    if metadata.len() > MAX_FILE_SIZE {
+        return Err(EdiError::ValidationError(format!(
+            "File too large: {} bytes (max {} bytes)",
+            metadata.len(),
+            MAX_FILE_SIZE
+        )));
+    }
+    File::open(file_path)?.read_to_string(&mut contents)?;
+    Ok(contents)
 }
 
 /**
@@ -197,24 +275,17 @@ pub fn clean_contents(contents: String) -> String {
     let mut clean = contents;
 
     // Detect and normalize custom delimiters from ISA segment BEFORE stripping newlines
-    // ISA is fixed-length: position 3 = element separator, position 105 = segment terminator
-    if clean.starts_with("ISA") && clean.len() >= 106 {
-        let element_sep = clean.as_bytes()[3] as char;
-        let segment_term = clean.as_bytes()[105] as char;
-
-        if element_sep != '*' || segment_term != '~' {
+    if clean.starts_with("ISA") {
+        let delimiters = Delimiters::detect(&clean);
+        if delimiters != delimiters.normalized() {
             info!(
-                "Custom delimiters detected: element='{}' segment='{}'",
-                element_sep, segment_term
+                "Custom delimiters detected: element='{}' component='{}' repetition='{}' segment='{}'",
+                delimiters.element, delimiters.component, delimiters.repetition, delimiters.segment
             );
-            // Per X12 §B.1.1.2: delimiter characters must not appear inside data
-            // element values within the interchange, so global replace is safe.
-            if segment_term != '~' {
-                clean = clean.replace(segment_term, "~");
-            }
-            if element_sep != '*' {
-                clean = clean.replace(element_sep, "*");
-            }
+            // Per X12 §B.1.1.2, the declared delimiters do not appear inside data,
+            // but the standard ones may: such data characters are swapped and
+            // reported by `Delimiters::check_data`.
+            clean = delimiters.convert(&clean, &delimiters.normalized());
         }
     }
 
@@ -233,8 +304,11 @@ pub fn clean_contents(contents: String) -> String {
  * Parameters:
  * - write_contents: Content to write
  * - write_file: Path to the output file
+ *
+ * Returns:
+ * - An error if the file cannot be created or written
  */
-pub fn write_to_file(write_contents: String, write_file: String) {
+pub fn write_to_file(write_contents: String, write_file: String) -> EdiResult<()> {
     let write_file_path = if write_file.is_empty() {
         info!("No output file specified, writing to default file");
         Path::new("./out.json")
@@ -242,19 +316,9 @@ pub fn write_to_file(write_contents: String, write_file: String) {
         Path::new(&write_file)
     };
 
-    match File::create(write_file_path) {
-        Ok(mut file) => {
-            if let Err(e) = file.write_all(write_contents.as_bytes()) {
-                warn!("Failed to write to file: {}", e);
-                std::process::exit(1);
-            }
-            info!("Successfully wrote to file: {:?}", write_file_path);
-        }
-        Err(e) => {
-            warn!("Failed to create file: {}", e);
-            std::process::exit(1);
-        }
-    }
+    File::create(write_file_path)?.write_all(write_contents.as_bytes())?;
+    info!("Successfully wrote to file: {:?}", write_file_path);
+    Ok(())
 }
 
 #[cfg(test)]
@@ -277,6 +341,70 @@ mod tests {
         assert!(result.contains("~GS*HR*S*R~"));
     }
 
+    #[test]
+    fn test_clean_contents_component_and_repetition() {
+        let input = "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230501*1200*!*00501*000000001*0*T*>~EB*1**30!1~SV1*HC>99213*100~".to_string();
+        let result = clean_contents(input);
+        assert!(result.contains("*1200*^*00501*"));
+        assert!(result.contains("~EB*1**30^1~SV1*HC:99213*100~"));
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let to_args = |list: &[&str]| parse_args(list.iter().map(|a| a.to_string()));
+
+        let args = to_args(&[
+            "-f",
+            "in.edi",
+            "-w",
+            "--strict",
+            "--sync-envelope",
+            "--renumber-hl",
+            "--control-numbers",
+            "registry.json",
+            "--snip",
+            "1-2,7",
+            "--code-sets",
+            "codes",
+        ])
+        .unwrap();
+        assert_eq!(args.file_path, "in.edi");
+        assert!(args.strict);
+        assert!(args.sync_envelope);
+        assert!(args.renumber_hl);
+        assert_eq!(args.control_numbers.as_deref(), Some("registry.json"));
+        assert_eq!(
+            args.snip_levels,
+            vec![
+                SnipLevel::Integrity,
+                SnipLevel::Requirements,
+                SnipLevel::PartnerSpecific
+            ]
+        );
+        assert_eq!(args.code_sets.as_deref(), Some("codes"));
+        assert!(to_args(&["--snip", "9"]).is_err());
+        assert!(to_args(&["--code-sets"]).is_err());
+        assert_eq!(args.operation, "write");
+        assert_eq!(args.output_file, "out.edi");
+
+        assert!(to_args(&["--help"]).unwrap().help);
+        assert!(matches!(to_args(&["-f"]), Err(EdiError::MissingField(_))));
+        assert!(matches!(to_args(&["-j"]), Err(EdiError::MissingField(_))));
+    }
+
+    #[test]
+    fn test_file_errors_are_returned() {
+        let args = Args {
+            file_path: "./does/not/exist.edi".to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            get_file_contents(&args),
+            Err(EdiError::IoError(_))
+        ));
+        assert!(write_to_file("ISA".to_string(), "./does/not/exist/out.edi".to_string()).is_err());
+    }
+
     #[test]
     fn test_clean_contents_removes_newlines() {
         let input = "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230501*1200*^*00501*000000001*0*T*:~\nGS*HR~\n".to_string();
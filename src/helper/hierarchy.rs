@@ -0,0 +1,337 @@
+//! HL hierarchy
+//!
+//! The 837, 270/271, 276/277 and 278 nest their 2000 loops with `HL`
+//! segments: HL01 numbers each level from 1, HL02 points to the parent
+//! level, HL03 names the level (`20` information source, `22` subscriber,
+//! ...) and HL04 tells whether child levels follow. `check_hierarchy` reports
+//! numbering, parent, level code and child code errors; `renumber_hierarchy`
+//! rewrites HL01, HL02 and HL04 from the order and level codes of the written
+//! segments, e.g. after subscribers were added to or removed from parsed JSON.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::helper::delimiters::STANDARD_ELEMENT;
+use crate::helper::diagnostics::{Diagnostic, DiagnosticCode, Diagnostics, Severity};
+use crate::helper::tokenizer::Segment;
+
+/// Hierarchical level codes (HL03), each with the levels it may be nested
+/// under; top levels have none.
+type Levels = &'static [(&'static str, &'static [&'static str])];
+
+/// Hierarchical levels of each transaction set (ST01).
+const LEVELS: &[(&[&str], Levels)] = &[
+    (&["837"], &[("20", &[]), ("22", &["20"]), ("23", &["22"])]),
+    (
+        &["270", "271"],
+        &[
+            ("20", &[]),
+            ("21", &["20"]),
+            ("22", &["21"]),
+            ("23", &["22"]),
+        ],
+    ),
+    (
+        &["276", "277"],
+        &[
+            ("20", &[]),
+            ("21", &["20"]),
+            ("19", &["21"]),
+            ("22", &["19"]),
+            ("23", &["22"]),
+            ("PT", &["19"]),
+        ],
+    ),
+    (
+        &["278"],
+        &[
+            ("20", &[]),
+            ("21", &["20"]),
+            ("22", &["21"]),
+            ("23", &["22"]),
+            ("EV", &["22", "23"]),
+            ("SS", &["EV"]),
+        ],
+    ),
+];
+
+/// Levels of transaction set `transaction_set_id`, if it is hierarchical.
+fn levels(transaction_set_id: &str) -> Option<Levels> {
+    LEVELS
+        .iter()
+        .find(|(ids, _)| ids.contains(&transaction_set_id))
+        .map(|(_, levels)| *levels)
+}
+
+/// Levels `level` may be nested under; `None` for a level the transaction set does not have.
+fn parents(levels: Levels, level: &str) -> Option<&'static [&'static str]> {
+    levels
+        .iter()
+        .find(|(code, _)| *code == level)
+        .map(|(_, parents)| *parents)
+}
+
+/// Report HL numbering and nesting errors in tokenized content. `paths` holds
+/// the loop path of each segment (see `loop_path::loop_paths`).
+pub fn check_hierarchy(segments: &[Segment], paths: &[String], diagnostics: &mut Diagnostics) {
+    let mut start = 0;
+    while let Some(offset) = segments[start..].iter().position(|s| s.id == "ST") {
+        let st = start + offset;
+        let end = segments[st + 1..]
+            .iter()
+            .position(|s| s.id == "ST")
+            .map_or(segments.len(), |p| st + 1 + p);
+        if let Some(levels) = levels(segments[st].element(0)) {
+            check_transaction(&segments[st..end], &paths[st..end], levels, diagnostics);
+        }
+        start = end;
+    }
+}
+
+fn check_transaction(
+    segments: &[Segment],
+    paths: &[String],
+    levels: Levels,
+    diagnostics: &mut Diagnostics,
+) {
+    let mut error = |position: usize, element: usize, message: String| {
+        diagnostics.push(
+            Diagnostic::new(Severity::Error, DiagnosticCode::InvalidHierarchy, message).at(
+                &segments[position],
+                &paths[position],
+                Some(element),
+            ),
+        );
+    };
+
+    // HL01 of every level seen so far, with its HL03
+    let mut seen: HashMap<&str, &str> = HashMap::new();
+    let mut parent_ids: HashSet<&str> = HashSet::new();
+    let hls: Vec<usize> = (0..segments.len())
+        .filter(|&p| segments[p].id == "HL")
+        .collect();
+    for (number, &position) in hls.iter().enumerate() {
+        let segment = &segments[position];
+        let (id, parent, level) = (segment.element(0), segment.element(1), segment.element(2));
+
+        let expected = (number + 1).to_string();
+        if seen.contains_key(id) {
+            error(position, 1, format!("HL01 '{}' is used more than once", id));
+        } else if id != expected {
+            error(
+                position,
+                1,
+                format!("HL01 '{}' is out of sequence, expected '{}'", id, expected),
+            );
+        }
+
+        match parents(levels, level) {
+            None => error(
+                position,
+                3,
+                format!(
+                    "HL03 '{}' is not a hierarchical level of the {}",
+                    level,
+                    segments[0].element(0)
+                ),
+            ),
+            Some([]) if !parent.is_empty() => error(
+                position,
+                2,
+                format!("HL02 must be empty for top level '{}'", level),
+            ),
+            Some([]) => {}
+            Some(allowed) => match seen.get(parent) {
+                None => error(
+                    position,
+                    2,
+                    format!("HL02 '{}' does not refer to a preceding HL", parent),
+                ),
+                Some(parent_level) if !allowed.contains(parent_level) => error(
+                    position,
+                    3,
+                    format!(
+                        "HL03 '{}' cannot be nested under level '{}' (HL '{}')",
+                        level, parent_level, parent
+                    ),
+                ),
+                Some(_) => {}
+            },
+        }
+
+        if !parent.is_empty() {
+            parent_ids.insert(parent);
+        }
+        seen.entry(id).or_insert(level);
+    }
+
+    for &position in &hls {
+        let segment = &segments[position];
+        let has_children = parent_ids.contains(segment.element(0));
+        let message = match segment.element(3) {
+            "1" if !has_children => "HL04 is '1' but no HL refers to this level",
+            "0" if has_children => "HL04 is '0' but child levels refer to this level",
+            _ => continue,
+        };
+        error(position, 4, message.to_string());
+    }
+}
+
+/// Renumber the HL segments of standard-delimited segments: HL01 from 1 in
+/// each transaction set, HL02 from the nearest preceding level HL03 may be
+/// nested under (the old HL02, renumbered, where there is none) and
+/// HL04, when present, from whether any level refers to it. Returns the number
+/// of changed segments.
+pub fn renumber_hierarchy(segments: &mut [String]) -> usize {
+    let mut levels: Option<Levels> = None;
+    let mut transaction = 0;
+    let mut count = 0;
+    // Open levels (HL03 and new HL01) and new HL01 by old HL01, per transaction set
+    let mut open: Vec<(String, String)> = Vec::new();
+    let mut renumbered: HashMap<String, String> = HashMap::new();
+    let mut updated: Vec<(usize, usize, Vec<String>)> = Vec::new();
+
+    for (position, segment) in segments.iter().enumerate() {
+        let mut elements: Vec<String> = segment
+            .split(STANDARD_ELEMENT)
+            .map(str::to_string)
+            .collect();
+        match elements[0].as_str() {
+            "ST" => {
+                levels = self::levels(elements.get(1).map_or("", String::as_str));
+                transaction += 1;
+                count = 0;
+                open.clear();
+                renumbered.clear();
+            }
+            "HL" => {
+                elements.resize(elements.len().max(4), String::new());
+                count += 1;
+                let id = count.to_string();
+                let level = elements[3].clone();
+                let allowed = levels.and_then(|levels| parents(levels, &level));
+                let nearest = allowed.and_then(|allowed| {
+                    open.iter()
+                        .rposition(|(open_level, _)| allowed.contains(&open_level.as_str()))
+                });
+                let parent = match (allowed, nearest) {
+                    (Some([]), _) => None,
+                    (_, Some(nearest)) => {
+                        open.truncate(nearest + 1);
+                        Some(open[nearest].1.clone())
+                    }
+                    // Unknown level or no level to nest under: keep the old parent
+                    _ => renumbered.get(&elements[2]).cloned(),
+                };
+                renumbered.insert(elements[1].clone(), id.clone());
+                open.push((level, id.clone()));
+                elements[1] = id;
+                elements[2] = parent.unwrap_or_default();
+                updated.push((position, transaction, elements));
+            }
+            _ => {}
+        }
+    }
+
+    let parent_ids: HashSet<(usize, String)> = updated
+        .iter()
+        .map(|(_, transaction, elements)| (*transaction, elements[2].clone()))
+        .collect();
+    let mut changed = 0;
+    for (position, transaction, mut elements) in updated {
+        if elements.len() > 4 && !elements[4].is_empty() {
+            let has_children = parent_ids.contains(&(transaction, elements[1].clone()));
+            elements[4] = if has_children { "1" } else { "0" }.to_string();
+        }
+        while elements.len() > 3 && elements.last().is_some_and(|e| e.is_empty()) {
+            elements.pop();
+        }
+        let written = elements.join(&STANDARD_ELEMENT.to_string());
+        if segments[position] != written {
+            segments[position] = written;
+            changed += 1;
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::loop_path::loop_paths;
+    use crate::helper::tokenizer::tokenize;
+
+    fn errors(contents: &str) -> Vec<(String, String)> {
+        let segments = tokenize(contents);
+        let paths = loop_paths(&segments);
+        let mut diagnostics = Diagnostics::default();
+        check_hierarchy(&segments, &paths, &mut diagnostics);
+        diagnostics
+            .iter()
+            .map(|d| (d.loop_path.clone(), d.message.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_check_hierarchy() {
+        assert!(
+            errors("ST*837*0001~HL*1**20*1~HL*2*1*22*1~HL*3*2*23*0~HL*4*1*22*0~SE*6*0001~")
+                .is_empty()
+        );
+        // HL segments of other transaction sets are not checked
+        assert!(errors("ST*835*0001~HL*9*9*99~SE*3*0001~").is_empty());
+
+        let found = errors(
+            "ST*837*0001~HL*1**20*1~HL*3*1*22*1~HL*3*7*23*0~HL*4*1*23*0~HL*5*4*21~SE*7*0001~",
+        );
+        let messages: Vec<&str> = found.iter().map(|(_, m)| m.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "HL01 '3' is out of sequence, expected '2'",
+                "HL01 '3' is used more than once",
+                "HL02 '7' does not refer to a preceding HL",
+                "HL03 '23' cannot be nested under level '20' (HL '1')",
+                "HL03 '21' is not a hierarchical level of the 837",
+                "HL04 is '1' but no HL refers to this level",
+                "HL04 is '0' but child levels refer to this level",
+            ]
+        );
+        assert!(found[0].0.ends_with("HL01"));
+        assert!(found[6].0.ends_with("HL04"));
+    }
+
+    #[test]
+    fn test_renumber_hierarchy() {
+        // A subscriber removed and a dependent added under the remaining one
+        let mut segments: Vec<String> = [
+            "ST*837*0001",
+            "HL*1**20*1",
+            "HL*3*1*22*0",
+            "SBR*P",
+            "HL*4*3*23*0",
+            "HL*4*3*23*0",
+            "ST*837*0002",
+            "HL*7**20*0",
+            "HL*8*7*22*0",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        assert_eq!(renumber_hierarchy(&mut segments), 5);
+        assert_eq!(
+            segments,
+            vec![
+                "ST*837*0001",
+                "HL*1**20*1",
+                "HL*2*1*22*1",
+                "SBR*P",
+                "HL*3*2*23*0",
+                "HL*4*2*23*0",
+                "ST*837*0002",
+                "HL*1**20*1",
+                "HL*2*1*22*0",
+            ]
+        );
+        assert_eq!(renumber_hierarchy(&mut segments), 0);
+    }
+}
@@ -0,0 +1,450 @@
+//! Loop paths
+//!
+//! Maps each segment of a transaction set to the implementation guide loops
+//! enclosing it, e.g. `2000B/2300` for a CLM segment of an 837. A loop is
+//! opened by its trigger segment, optionally qualified by a code in one of
+//! its elements (`NM1*IL`, `HL` with HL03 `22`), and nests under the nearest
+//! open parent loop. Envelope segments close every open loop.
+
+use crate::helper::tokenizer::Segment;
+
+/// Segments that close every open loop.
+const ENVELOPE: &[&str] = &["ISA", "GS", "ST", "SE", "GE", "IEA"];
+
+/// A loop trigger: `segment` opens `loop_id` when the element at `element`
+/// (1-based, 0 for any occurrence) holds one of `codes`.
+/// An empty `loop_id` closes every open loop instead (e.g. 835 PLB).
+#[derive(Debug)]
+struct LoopRule {
+    segment: &'static str,
+    element: usize,
+    codes: &'static [&'static str],
+    loop_id: &'static str,
+    /// Loops this one nests under, one of which must be open; empty for a top-level loop
+    parents: &'static [&'static str],
+}
+
+const fn rule(
+    segment: &'static str,
+    element: usize,
+    codes: &'static [&'static str],
+    loop_id: &'static str,
+    parents: &'static [&'static str],
+) -> LoopRule {
+    LoopRule {
+        segment,
+        element,
+        codes,
+        loop_id,
+        parents,
+    }
+}
+
+impl LoopRule {
+    fn matches(&self, segment: &Segment) -> bool {
+        self.segment == segment.id
+            && (self.element == 0 || self.codes.contains(&segment.element(self.element - 1)))
+    }
+}
+
+const LOOPS_270: &[LoopRule] = &[
+    rule("HL", 3, &["20"], "2000A", &[]),
+    rule("HL", 3, &["21"], "2000B", &[]),
+    rule("HL", 3, &["22"], "2000C", &[]),
+    rule("HL", 3, &["23"], "2000D", &[]),
+    rule("NM1", 0, &[], "2100A", &["2000A"]),
+    rule("NM1", 0, &[], "2100B", &["2000B"]),
+    rule("NM1", 0, &[], "2100C", &["2000C"]),
+    rule("NM1", 0, &[], "2100D", &["2000D"]),
+    rule("EQ", 0, &[], "2110C", &["2100C"]),
+    rule("EQ", 0, &[], "2110D", &["2100D"]),
+];
+
+const LOOPS_271: &[LoopRule] = &[
+    rule("HL", 3, &["20"], "2000A", &[]),
+    rule("HL", 3, &["21"], "2000B", &[]),
+    rule("HL", 3, &["22"], "2000C", &[]),
+    rule("HL", 3, &["23"], "2000D", &[]),
+    rule("NM1", 0, &[], "2100A", &["2000A"]),
+    rule("NM1", 0, &[], "2100B", &["2000B"]),
+    rule("NM1", 0, &[], "2100C", &["2000C"]),
+    rule("NM1", 0, &[], "2100D", &["2000D"]),
+    rule("EB", 0, &[], "2110C", &["2100C"]),
+    rule("EB", 0, &[], "2110D", &["2100D"]),
+    rule("III", 0, &[], "2115C", &["2110C"]),
+    rule("III", 0, &[], "2115D", &["2110D"]),
+    rule("NM1", 0, &[], "2120C", &["2110C"]),
+    rule("NM1", 0, &[], "2120D", &["2110D"]),
+];
+
+const LOOPS_276: &[LoopRule] = &[
+    rule("HL", 3, &["20"], "2000A", &[]),
+    rule("HL", 3, &["21"], "2000B", &[]),
+    rule("HL", 3, &["19"], "2000C", &[]),
+    rule("HL", 3, &["22"], "2000D", &[]),
+    rule("HL", 3, &["23"], "2000E", &[]),
+    rule("NM1", 0, &[], "2100A", &["2000A"]),
+    rule("NM1", 0, &[], "2100B", &["2000B"]),
+    rule("NM1", 0, &[], "2100C", &["2000C"]),
+    rule("NM1", 0, &[], "2100D", &["2000D"]),
+    rule("NM1", 0, &[], "2100E", &["2000E"]),
+    rule("TRN", 0, &[], "2200C", &["2000C"]),
+    rule("TRN", 0, &[], "2200D", &["2000D"]),
+    rule("TRN", 0, &[], "2200E", &["2000E"]),
+    rule("SVC", 0, &[], "2210D", &["2200D"]),
+    rule("SVC", 0, &[], "2210E", &["2200E"]),
+];
+
+const LOOPS_277: &[LoopRule] = &[
+    rule("HL", 3, &["20"], "2000A", &[]),
+    rule("HL", 3, &["21"], "2000B", &[]),
+    rule("HL", 3, &["19"], "2000C", &[]),
+    rule("HL", 3, &["22"], "2000D", &[]),
+    rule("HL", 3, &["23"], "2000E", &[]),
+    rule("NM1", 0, &[], "2100A", &["2000A"]),
+    rule("NM1", 0, &[], "2100B", &["2000B"]),
+    rule("NM1", 0, &[], "2100C", &["2000C"]),
+    rule("NM1", 0, &[], "2100D", &["2000D"]),
+    rule("NM1", 0, &[], "2100E", &["2000E"]),
+    rule("TRN", 0, &[], "2200A", &["2000A"]),
+    rule("TRN", 0, &[], "2200B", &["2000B"]),
+    rule("TRN", 0, &[], "2200C", &["2000C"]),
+    rule("TRN", 0, &[], "2200D", &["2000D"]),
+    rule("TRN", 0, &[], "2200E", &["2000E"]),
+    rule("SVC", 0, &[], "2220D", &["2200D"]),
+    rule("SVC", 0, &[], "2220E", &["2200E"]),
+];
+
+const LOOPS_278: &[LoopRule] = &[
+    rule("HL", 3, &["20"], "2000A", &[]),
+    rule("HL", 3, &["21"], "2000B", &[]),
+    rule("HL", 3, &["22"], "2000C", &[]),
+    rule("HL", 3, &["23"], "2000D", &[]),
+    rule("HL", 3, &["EV"], "2000E", &[]),
+    rule("HL", 3, &["SS"], "2000F", &[]),
+    rule("NM1", 0, &[], "2010A", &["2000A"]),
+    rule("NM1", 0, &[], "2010B", &["2000B"]),
+    rule("NM1", 0, &[], "2010C", &["2000C"]),
+    rule("NM1", 0, &[], "2010D", &["2000D"]),
+    rule("NM1", 0, &[], "2010E", &["2000E"]),
+    rule("NM1", 0, &[], "2010F", &["2000F"]),
+];
+
+const LOOPS_820: &[LoopRule] = &[
+    rule("N1", 1, &["PE"], "1000A", &[]),
+    rule("N1", 1, &["PR", "RM"], "1000B", &[]),
+    rule("ENT", 0, &[], "2000", &[]),
+    rule("NM1", 0, &[], "2100", &["2000"]),
+    rule("RMR", 0, &[], "2300", &["2000"]),
+];
+
+const LOOPS_834: &[LoopRule] = &[
+    rule("N1", 1, &["P5"], "1000A", &[]),
+    rule("N1", 1, &["IN"], "1000B", &[]),
+    rule("N1", 1, &["BO", "TV"], "1000C", &[]),
+    rule("INS", 0, &[], "2000", &[]),
+    rule("NM1", 1, &["IL", "74"], "2100A", &["2000"]),
+    rule("NM1", 1, &["70"], "2100B", &["2000"]),
+    rule("NM1", 1, &["31"], "2100C", &["2000"]),
+    rule("NM1", 1, &["36"], "2100D", &["2000"]),
+    rule("NM1", 1, &["M8"], "2100E", &["2000"]),
+    rule("NM1", 1, &["S1"], "2100F", &["2000"]),
+    rule("NM1", 1, &["6Y"], "2100G", &["2000"]),
+    rule("NM1", 1, &["9K"], "2100H", &["2000"]),
+    rule("DSB", 0, &[], "2200", &["2000"]),
+    rule("HD", 0, &[], "2300", &["2000"]),
+    rule("LX", 0, &[], "2310", &["2300"]),
+    rule("COB", 0, &[], "2320", &["2300"]),
+    rule("NM1", 1, &["36", "GW", "IN"], "2330", &["2320"]),
+];
+
+const LOOPS_835: &[LoopRule] = &[
+    rule("N1", 1, &["PR"], "1000A", &[]),
+    rule("N1", 1, &["PE"], "1000B", &[]),
+    rule("LX", 0, &[], "2000", &[]),
+    rule("CLP", 0, &[], "2100", &["2000"]),
+    rule("CLP", 0, &[], "2100", &[]),
+    rule("SVC", 0, &[], "2110", &["2100"]),
+    rule("PLB", 0, &[], "", &[]),
+];
+
+const LOOPS_837P: &[LoopRule] = &[
+    rule("NM1", 1, &["41"], "1000A", &[]),
+    rule("NM1", 1, &["40"], "1000B", &[]),
+    rule("HL", 3, &["20"], "2000A", &[]),
+    rule("HL", 3, &["22"], "2000B", &[]),
+    rule("HL", 3, &["23"], "2000C", &[]),
+    rule("NM1", 1, &["85"], "2010AA", &["2000A"]),
+    rule("NM1", 1, &["87"], "2010AB", &["2000A"]),
+    rule("NM1", 1, &["PE"], "2010AC", &["2000A"]),
+    rule("NM1", 1, &["IL"], "2010BA", &["2000B"]),
+    rule("NM1", 1, &["PR"], "2010BB", &["2000B"]),
+    rule("NM1", 1, &["QC"], "2010CA", &["2000C"]),
+    rule("CLM", 0, &[], "2300", &["2000B", "2000C"]),
+    rule("NM1", 1, &["DN", "P3"], "2310A", &["2300"]),
+    rule("NM1", 1, &["82"], "2310B", &["2300"]),
+    rule("NM1", 1, &["77"], "2310C", &["2300"]),
+    rule("NM1", 1, &["DQ"], "2310D", &["2300"]),
+    rule("NM1", 1, &["PW"], "2310E", &["2300"]),
+    rule("NM1", 1, &["45"], "2310F", &["2300"]),
+    rule("SBR", 0, &[], "2320", &["2300"]),
+    rule("NM1", 1, &["IL"], "2330A", &["2320"]),
+    rule("NM1", 1, &["PR"], "2330B", &["2320"]),
+    rule("NM1", 1, &["DN"], "2330C", &["2320"]),
+    rule("NM1", 1, &["82"], "2330D", &["2320"]),
+    rule("NM1", 1, &["77"], "2330E", &["2320"]),
+    rule("NM1", 1, &["DQ"], "2330F", &["2320"]),
+    rule("NM1", 1, &["85"], "2330G", &["2320"]),
+    rule("LX", 0, &[], "2400", &["2300"]),
+    rule("LIN", 0, &[], "2410", &["2400"]),
+    rule("NM1", 1, &["82"], "2420A", &["2400"]),
+    rule("NM1", 1, &["QB"], "2420B", &["2400"]),
+    rule("NM1", 1, &["77"], "2420C", &["2400"]),
+    rule("NM1", 1, &["DQ"], "2420D", &["2400"]),
+    rule("NM1", 1, &["DK"], "2420E", &["2400"]),
+    rule("NM1", 1, &["DN"], "2420F", &["2400"]),
+    rule("NM1", 1, &["PW"], "2420G", &["2400"]),
+    rule("NM1", 1, &["45"], "2420H", &["2400"]),
+    rule("SVD", 0, &[], "2430", &["2400"]),
+    rule("LQ", 0, &[], "2440", &["2400"]),
+];
+
+const LOOPS_837I: &[LoopRule] = &[
+    rule("NM1", 1, &["41"], "1000A", &[]),
+    rule("NM1", 1, &["40"], "1000B", &[]),
+    rule("HL", 3, &["20"], "2000A", &[]),
+    rule("HL", 3, &["22"], "2000B", &[]),
+    rule("HL", 3, &["23"], "2000C", &[]),
+    rule("NM1", 1, &["85"], "2010AA", &["2000A"]),
+    rule("NM1", 1, &["87"], "2010AB", &["2000A"]),
+    rule("NM1", 1, &["PE"], "2010AC", &["2000A"]),
+    rule("NM1", 1, &["IL"], "2010BA", &["2000B"]),
+    rule("NM1", 1, &["PR"], "2010BB", &["2000B"]),
+    rule("NM1", 1, &["QC"], "2010CA", &["2000C"]),
+    rule("CLM", 0, &[], "2300", &["2000B", "2000C"]),
+    rule("NM1", 1, &["71"], "2310A", &["2300"]),
+    rule("NM1", 1, &["72"], "2310B", &["2300"]),
+    rule("NM1", 1, &["ZZ"], "2310C", &["2300"]),
+    rule("NM1", 1, &["82"], "2310D", &["2300"]),
+    rule("NM1", 1, &["77"], "2310E", &["2300"]),
+    rule("NM1", 1, &["DN"], "2310F", &["2300"]),
+    rule("SBR", 0, &[], "2320", &["2300"]),
+    rule("NM1", 1, &["IL"], "2330A", &["2320"]),
+    rule("NM1", 1, &["PR"], "2330B", &["2320"]),
+    rule("NM1", 1, &["71"], "2330C", &["2320"]),
+    rule("NM1", 1, &["72"], "2330D", &["2320"]),
+    rule("NM1", 1, &["ZZ"], "2330E", &["2320"]),
+    rule("NM1", 1, &["77"], "2330F", &["2320"]),
+    rule("NM1", 1, &["82"], "2330G", &["2320"]),
+    rule("NM1", 1, &["DN"], "2330H", &["2320"]),
+    rule("NM1", 1, &["85"], "2330I", &["2320"]),
+    rule("LX", 0, &[], "2400", &["2300"]),
+    rule("LIN", 0, &[], "2410", &["2400"]),
+    rule("NM1", 1, &["72"], "2420A", &["2400"]),
+    rule("NM1", 1, &["ZZ"], "2420B", &["2400"]),
+    rule("NM1", 1, &["82"], "2420C", &["2400"]),
+    rule("NM1", 1, &["DN"], "2420D", &["2400"]),
+    rule("SVD", 0, &[], "2430", &["2400"]),
+];
+
+const LOOPS_837D: &[LoopRule] = &[
+    rule("NM1", 1, &["41"], "1000A", &[]),
+    rule("NM1", 1, &["40"], "1000B", &[]),
+    rule("HL", 3, &["20"], "2000A", &[]),
+    rule("HL", 3, &["22"], "2000B", &[]),
+    rule("HL", 3, &["23"], "2000C", &[]),
+    rule("NM1", 1, &["85"], "2010AA", &["2000A"]),
+    rule("NM1", 1, &["87"], "2010AB", &["2000A"]),
+    rule("NM1", 1, &["PE"], "2010AC", &["2000A"]),
+    rule("NM1", 1, &["IL"], "2010BA", &["2000B"]),
+    rule("NM1", 1, &["PR"], "2010BB", &["2000B"]),
+    rule("NM1", 1, &["QC"], "2010CA", &["2000C"]),
+    rule("CLM", 0, &[], "2300", &["2000B", "2000C"]),
+    rule("NM1", 1, &["DN", "P3"], "2310A", &["2300"]),
+    rule("NM1", 1, &["82"], "2310B", &["2300"]),
+    rule("NM1", 1, &["77"], "2310C", &["2300"]),
+    rule("NM1", 1, &["DD"], "2310D", &["2300"]),
+    rule("SBR", 0, &[], "2320", &["2300"]),
+    rule("NM1", 1, &["IL"], "2330A", &["2320"]),
+    rule("NM1", 1, &["PR"], "2330B", &["2320"]),
+    rule("LX", 0, &[], "2400", &["2300"]),
+    rule("NM1", 1, &["82"], "2420A", &["2400"]),
+    rule("NM1", 1, &["DD"], "2420B", &["2400"]),
+    rule("NM1", 1, &["DQ"], "2420C", &["2400"]),
+    rule("NM1", 1, &["77"], "2420D", &["2400"]),
+    rule("SVD", 0, &[], "2430", &["2400"]),
+];
+
+const LOOPS_999: &[LoopRule] = &[
+    rule("AK2", 0, &[], "2000", &[]),
+    rule("IK3", 0, &[], "2100", &["2000"]),
+    rule("IK4", 0, &[], "2110", &["2100"]),
+    rule("IK5", 0, &[], "2000", &[]),
+    rule("AK9", 0, &[], "", &[]),
+];
+
+/// Loop triggers for a transaction set. The 837 variant is chosen from the
+/// implementation convention reference (ST03 or GS08), defaulting to 837P.
+fn loop_rules(transaction_set_id: &str, implementation_reference: &str) -> &'static [LoopRule] {
+    match transaction_set_id {
+        "270" => LOOPS_270,
+        "271" => LOOPS_271,
+        "276" => LOOPS_276,
+        "277" => LOOPS_277,
+        "278" => LOOPS_278,
+        "820" => LOOPS_820,
+        "834" => LOOPS_834,
+        "835" => LOOPS_835,
+        "837" if implementation_reference.contains("X223") => LOOPS_837I,
+        "837" if implementation_reference.contains("X224") => LOOPS_837D,
+        "837" => LOOPS_837P,
+        "999" => LOOPS_999,
+        _ => &[],
+    }
+}
+
+/// Tracks the loops open at the current segment of a transaction set.
+#[derive(Debug, Default, Clone)]
+pub struct LoopTracker {
+    rules: &'static [LoopRule],
+    stack: Vec<&'static str>,
+    implementation_reference: String,
+}
+
+impl LoopTracker {
+    pub fn new(transaction_set_id: &str, implementation_reference: &str) -> LoopTracker {
+        LoopTracker {
+            rules: loop_rules(transaction_set_id, implementation_reference),
+            stack: Vec::new(),
+            implementation_reference: implementation_reference.to_string(),
+        }
+    }
+
+    /// Move to `segment` and return the path of the loops enclosing it.
+    /// An ST segment selects the loop triggers for its transaction set.
+    pub fn advance(&mut self, segment: &Segment) -> String {
+        if ENVELOPE.contains(&segment.id) {
+            match segment.id {
+                "GS" => self.implementation_reference = segment.element(7).to_string(),
+                "ST" => {
+                    let reference = match segment.element(2) {
+                        "" => self.implementation_reference.clone(),
+                        st03 => st03.to_string(),
+                    };
+                    self.rules = loop_rules(segment.element(0), &reference);
+                }
+                _ => {}
+            }
+            self.stack.clear();
+            return self.path();
+        }
+
+        // Among the triggers matching the segment, pick the one nested deepest:
+        // its parent is the nearest open loop. Top-level loops close everything.
+        // Nested loops whose parent is not open do not apply.
+        let mut selected: Option<(&LoopRule, usize)> = None;
+        for rule in self.rules.iter().filter(|rule| rule.matches(segment)) {
+            let keep = if rule.parents.is_empty() {
+                0
+            } else {
+                match rule
+                    .parents
+                    .iter()
+                    .filter_map(|parent| self.stack.iter().rposition(|open| open == parent))
+                    .max()
+                {
+                    Some(pos) => pos + 1,
+                    None => continue,
+                }
+            };
+            if selected.is_none_or(|(_, depth)| keep > depth) {
+                selected = Some((rule, keep));
+            }
+        }
+
+        if let Some((rule, keep)) = selected {
+            self.stack.truncate(keep);
+            if !rule.loop_id.is_empty() {
+                self.stack.push(rule.loop_id);
+            }
+        }
+        self.path()
+    }
+
+    /// The open loops, outermost first, joined by `/` (empty outside any loop).
+    pub fn path(&self) -> String {
+        self.stack.join("/")
+    }
+}
+
+/// Loop path of every segment in tokenized content, by segment position.
+pub fn loop_paths(segments: &[Segment]) -> Vec<String> {
+    let mut tracker = LoopTracker::default();
+    segments
+        .iter()
+        .map(|segment| tracker.advance(segment))
+        .collect()
+}
+
+/// Full path of a segment or one of its elements, e.g. `2000B/2300/CLM02`.
+/// `element` is 1-based, as in the implementation guides.
+pub fn element_path(loop_path: &str, segment_id: &str, element: Option<usize>) -> String {
+    let reference = match element {
+        Some(position) => format!("{}{:02}", segment_id, position),
+        None => segment_id.to_string(),
+    };
+    if loop_path.is_empty() {
+        reference
+    } else {
+        format!("{}/{}", loop_path, reference)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::tokenizer::tokenize;
+
+    #[test]
+    fn test_loop_paths_837() {
+        let contents = "ST*837*0001*005010X222A1~BHT*0019~NM1*41*2*SUBMITTER~HL*1**20*1~NM1*85*2*BILLING~HL*2*1*22*0~SBR*P~NM1*IL*1*DOE~NM1*PR*2*PAYER~CLM*A1*100~NM1*82*1*RENDERING~SBR*S~NM1*PR*2*OTHER~LX*1~SV1*HC:99213*100~NM1*82*1*LINE~SE*17*0001~";
+        let segments = tokenize(contents);
+        let paths = loop_paths(&segments);
+        let path_of = |n: usize| paths[n].as_str();
+        assert_eq!(path_of(0), "");
+        assert_eq!(path_of(1), "");
+        assert_eq!(path_of(2), "1000A");
+        assert_eq!(path_of(4), "2000A/2010AA");
+        assert_eq!(path_of(6), "2000B");
+        assert_eq!(path_of(8), "2000B/2010BB");
+        assert_eq!(path_of(9), "2000B/2300");
+        assert_eq!(path_of(10), "2000B/2300/2310B");
+        assert_eq!(path_of(12), "2000B/2300/2320/2330B");
+        assert_eq!(path_of(14), "2000B/2300/2400");
+        assert_eq!(path_of(15), "2000B/2300/2400/2420A");
+        assert_eq!(path_of(16), "");
+    }
+
+    #[test]
+    fn test_loop_paths_835() {
+        let contents = "ST*835*1~BPR*I*100~N1*PR*PAYER~N3*STREET~N1*PE*PAYEE~LX*1~CLP*A*1*100*80~CAS*CO*45*20~SVC*HC:99213*100*80~CLP*B*1*50*50~PLB*123*20051231*WO*10~SE*12*1~";
+        let segments = tokenize(contents);
+        let paths = loop_paths(&segments);
+        assert_eq!(paths[3], "1000A");
+        assert_eq!(paths[6], "2000/2100");
+        assert_eq!(paths[7], "2000/2100");
+        assert_eq!(paths[8], "2000/2100/2110");
+        assert_eq!(paths[9], "2000/2100");
+        assert_eq!(paths[10], "");
+    }
+
+    #[test]
+    fn test_element_path() {
+        assert_eq!(
+            element_path("2000B/2300", "CLM", Some(2)),
+            "2000B/2300/CLM02"
+        );
+        assert_eq!(element_path("", "SE", Some(1)), "SE01");
+        assert_eq!(element_path("2000", "LX", None), "2000/LX");
+    }
+}
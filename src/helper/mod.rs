@@ -1,4 +1,15 @@
+pub mod control_numbers;
+pub mod delimiters;
+pub mod diagnostics;
 pub mod edihelper;
+pub mod element_dictionary;
 pub mod envelope_validation;
 pub mod helper;
+pub mod hierarchy;
+pub mod loop_path;
 pub mod numeric_validation;
+pub mod source_map;
+pub mod syntax_rules;
+pub mod tokenizer;
+pub mod unrecognized;
+pub mod writer_options;
@@ -1,5 +1,3 @@
-use log::warn;
-
 /// Validate an X12 Numeric (Nn) data element.
 /// Per X12 §B.1.1.3.1.1: digits only, optional leading minus, no plus sign,
 /// no commas, no decimal point. Leading zeros suppressed unless for min length.
@@ -45,24 +43,43 @@ pub fn is_valid_decimal(value: &str) -> bool {
     true
 }
 
-/// Warn if a monetary amount field contains an invalid value.
-pub fn warn_if_invalid_monetary(segment: &str, field: &str, value: &str) {
-    if !value.is_empty() && !is_valid_decimal(value) {
-        warn!(
-            "Invalid monetary value in {}.{}: '{}' — expected decimal (R) per X12 §B.1.1.3.1.2",
-            segment, field, value
-        );
+/// A monetary amount (DE 782) in cents, rounded half away from zero; empty
+/// counts as zero. Parsed digit by digit, so that amounts up to the 18 digits
+/// of DE 782 are exact. `None` for a value that is not a valid decimal or
+/// does not fit.
+pub fn amount_in_cents(value: &str) -> Option<i128> {
+    if value.is_empty() {
+        return Some(0);
+    }
+    if !is_valid_decimal(value) {
+        return None;
+    }
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, value),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let mut fraction = fraction.bytes().map(|b| i128::from(b - b'0'));
+    let mut cents: i128 = 0;
+    for digit in whole.bytes().map(|b| i128::from(b - b'0')) {
+        cents = cents.checked_mul(10)?.checked_add(digit)?;
+    }
+    for _ in 0..2 {
+        cents = cents
+            .checked_mul(10)?
+            .checked_add(fraction.next().unwrap_or(0))?;
+    }
+    if fraction.next().unwrap_or(0) >= 5 {
+        cents = cents.checked_add(1)?;
     }
+    Some(if negative { -cents } else { cents })
 }
 
-/// Warn if a numeric (N0) field contains an invalid value.
-pub fn warn_if_invalid_numeric(segment: &str, field: &str, value: &str) {
-    if !value.is_empty() && !is_valid_numeric(value) {
-        warn!(
-            "Invalid numeric value in {}.{}: '{}' — expected integer (N0) per X12 §B.1.1.3.1.1",
-            segment, field, value
-        );
-    }
+/// Cents as a decimal amount with two places, e.g. `-12.50`.
+pub fn format_cents(cents: i128) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = cents.unsigned_abs();
+    format!("{}{}.{:02}", sign, cents / 100, cents % 100)
 }
 
 #[cfg(test)]
@@ -106,4 +123,33 @@ mod tests {
         assert!(!is_valid_decimal("-"));
         assert!(!is_valid_decimal("1e5"));
     }
+
+    #[test]
+    fn test_amount_in_cents() {
+        assert_eq!(amount_in_cents(""), Some(0));
+        assert_eq!(amount_in_cents("100"), Some(10000));
+        assert_eq!(amount_in_cents("12.3"), Some(1230));
+        assert_eq!(amount_in_cents("-0.07"), Some(-7));
+        assert_eq!(amount_in_cents("73348.57"), Some(7334857));
+        assert_eq!(amount_in_cents("1,000"), None);
+        assert_eq!(amount_in_cents("0.005"), Some(1));
+        assert_eq!(amount_in_cents("-0.005"), Some(-1));
+        assert_eq!(amount_in_cents(".5"), Some(50));
+        // 18 digits, beyond the precision of f64
+        assert_eq!(
+            amount_in_cents("9999999999999999.99"),
+            Some(999_999_999_999_999_999)
+        );
+        assert_eq!(
+            amount_in_cents("999999999999999999"),
+            Some(99_999_999_999_999_999_900)
+        );
+        assert_eq!(amount_in_cents(&"9".repeat(40)), None);
+        assert_eq!(format_cents(-1250), "-12.50");
+        assert_eq!(format_cents(7), "0.07");
+        assert_eq!(
+            format_cents(i128::MIN),
+            "-1701411834604692317316873037158841057.28"
+        );
+    }
 }
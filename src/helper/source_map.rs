@@ -0,0 +1,188 @@
+//! Source map
+//!
+//! Parsers work on content normalized by `helper::clean_contents`, which
+//! swaps delimiters and drops line breaks, so positions in it do not match the
+//! file a user opens. `SourceMap` relates every segment of the normalized
+//! content back to its byte offset and line in the raw input, and to its loop
+//! path, for locating errors. It is only built on failure paths.
+
+use crate::error::{EdiError, SourceLocation};
+use crate::helper::delimiters::Delimiters;
+use crate::helper::helper::clean_contents;
+use crate::helper::loop_path::loop_paths;
+use crate::helper::tokenizer::tokenize;
+
+const BOM: char = '\u{feff}';
+
+#[derive(Debug, Default, Clone)]
+pub struct SourceMap {
+    normalized: String,
+    /// Normalized offset of each segment, by segment position
+    normalized_offsets: Vec<usize>,
+    /// Location of each segment, by segment position
+    locations: Vec<SourceLocation>,
+}
+
+impl SourceMap {
+    pub fn new(raw: &str) -> SourceMap {
+        let delimiters = Delimiters::detect(raw);
+        let body = raw.trim_start_matches(BOM);
+        let bom_len = raw.len() - body.len();
+        let normalized = clean_contents(body.to_string());
+        let segments = tokenize(&normalized);
+        let paths = loop_paths(&segments);
+
+        // Raw segments in order, skipping layout between terminators as `tokenize` does
+        let mut raw_positions = Vec::with_capacity(segments.len());
+        let mut offset = bom_len;
+        let mut line = 1;
+        for piece in body.split(delimiters.segment) {
+            let trimmed = piece.trim_start();
+            let lead = piece.len() - trimmed.len();
+            if !trimmed.trim_end_matches(['\r', '\n']).is_empty() {
+                let segment_line = line + piece[..lead].matches('\n').count();
+                raw_positions.push((offset + lead, segment_line));
+            }
+            line += piece.matches('\n').count();
+            if delimiters.segment == '\n' {
+                line += 1;
+            }
+            offset += piece.len() + delimiters.segment.len_utf8();
+        }
+
+        let locations = segments
+            .iter()
+            .zip(paths)
+            .enumerate()
+            .map(|(index, (segment, loop_path))| {
+                let (offset, line) = raw_positions.get(index).copied().unwrap_or_default();
+                SourceLocation {
+                    segment_index: index,
+                    offset,
+                    line,
+                    segment_id: segment.id.to_string(),
+                    element: None,
+                    loop_path,
+                }
+            })
+            .collect();
+
+        let normalized_offsets = segments.iter().map(|segment| segment.offset).collect();
+        SourceMap {
+            normalized,
+            normalized_offsets,
+            locations,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.locations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.locations.is_empty()
+    }
+
+    /// Location of the segment at `index` (zero-based, ISA is 0).
+    pub fn location(&self, index: usize) -> Option<SourceLocation> {
+        self.locations.get(index).cloned()
+    }
+
+    /// Location of the segment a parser was at, given the normalized content
+    /// it had left. Parsers consume content from the front, so what is left is
+    /// a suffix of the normalized content.
+    pub fn location_of_remaining(&self, remaining: &str) -> Option<SourceLocation> {
+        let remaining = remaining.trim_start();
+        if !self.normalized.ends_with(remaining) {
+            return None;
+        }
+        let offset = self.normalized.len() - remaining.len();
+        let index = match self.normalized_offsets.binary_search(&offset) {
+            Ok(index) => index,
+            // Inside a segment, or past the last one
+            Err(index) => index.checked_sub(1)?,
+        };
+        self.location(index)
+    }
+
+    /// Attach the location of the segment a parser was at to `error`.
+    pub fn locate(&self, error: EdiError, remaining: &str) -> EdiError {
+        match self.location_of_remaining(remaining) {
+            Some(location) => error.at(location),
+            None => error,
+        }
+    }
+
+    /// Fill in the raw offset, line and loop path of a located error from its
+    /// segment index, for errors raised on content rebuilt from this input.
+    pub fn relocate(&self, error: EdiError) -> EdiError {
+        match error {
+            EdiError::Located(error, location) => match self.location(location.segment_index) {
+                Some(found) => EdiError::Located(
+                    error,
+                    Box::new(SourceLocation {
+                        element: location.element,
+                        ..found
+                    }),
+                ),
+                None => EdiError::Located(error, location),
+            },
+            error => error,
+        }
+    }
+}
+
+/// Locate `error` in `raw`, given the normalized content the parser had left.
+pub fn locate_error(raw: &str, remaining: &str, error: EdiError) -> EdiError {
+    SourceMap::new(raw).locate(error, remaining)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RAW: &str = "ISA|00|          |00|          |ZZ|SENDER         |ZZ|RECEIVER       |230501|1200|!|00501|000000001|0|T|>~\r\nGS|HC|S|R|20230501|1200|1|X|005010X222A1~\r\nST|837|0001|005010X222A1~\r\nBHT|0019~\r\nHL|1||20|1~\r\nHL|2|1|22|0~\r\nCLM|A1|1OO~\r\nSE|7|0001~\r\n";
+
+    #[test]
+    fn test_source_map_raw_positions() {
+        let map = SourceMap::new(RAW);
+        assert_eq!(map.len(), 8);
+        let clm = map.location(6).unwrap();
+        assert_eq!(clm.segment_id, "CLM");
+        assert_eq!(clm.line, 7);
+        assert_eq!(&RAW[clm.offset..clm.offset + 3], "CLM");
+        assert_eq!(clm.loop_path, "2000B/2300");
+    }
+
+    #[test]
+    fn test_locate_remaining() {
+        let normalized = clean_contents(RAW.to_string());
+        let remaining = &normalized[normalized.find("CLM").unwrap()..];
+        let error = locate_error(
+            RAW,
+            remaining,
+            EdiError::MalformedSegment("CLM02".to_string()),
+        );
+        let location = error.location().unwrap();
+        assert_eq!(location.segment_index, 6);
+        assert_eq!(location.line, 7);
+
+        // Content that is not a suffix is left unlocated
+        let error = locate_error(
+            RAW,
+            &"X".repeat(normalized.len() + 1),
+            EdiError::MissingSegment("NM1".to_string()),
+        );
+        assert!(error.location().is_none());
+    }
+
+    #[test]
+    fn test_source_map_newline_terminator() {
+        let raw = "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230501*1200*^*00501*000000001*0*T*:\nGS*HC*S*R\n\nST*837*0001\n";
+        let map = SourceMap::new(raw);
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.location(1).unwrap().line, 2);
+        assert_eq!(map.location(2).unwrap().line, 4);
+        assert_eq!(&raw[map.location(2).unwrap().offset..][..2], "ST");
+    }
+}
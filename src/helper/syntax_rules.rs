@@ -0,0 +1,228 @@
+//! X12 syntax rules
+//!
+//! Relational conditions between the elements of a segment, as written in the
+//! syntax notes of the X12 standard: `P0809` (paired), `R0203` (at least one
+//! required), `C1110` (conditional), `E0207` (exclusion) and `L050607` (list
+//! conditional). Each segment module in `segments/` lists its notes as
+//! `SYNTAX_RULES`, empty where X12 defines none, and `segments/composites.rs`
+//! those of the composite elements; `check_syntax` applies them to tokenized content, at parse
+//! time through `collect_diagnostics` and at write time through
+//! `WriterOptions::format`.
+
+use std::fmt;
+
+use crate::helper::diagnostics::{Diagnostic, DiagnosticCode, Diagnostics, Severity};
+use crate::helper::edihelper::get_components;
+use crate::helper::tokenizer::Segment;
+use crate::segments::{composite_syntax_rules, syntax_rules};
+
+/// A syntax note; elements are 1-based positions within the segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxRule {
+    /// P: if any of the elements is present, all are required
+    Paired(&'static [usize]),
+    /// R: at least one of the elements is required
+    Required(&'static [usize]),
+    /// C: if the first element is present, all of the others are required
+    Conditional(usize, &'static [usize]),
+    /// E: at most one of the elements may be present
+    Exclusion(&'static [usize]),
+    /// L: if the first element is present, at least one of the others is required
+    ListConditional(usize, &'static [usize]),
+}
+
+impl SyntaxRule {
+    /// The element the rule is broken at, if it is, with the reason.
+    /// `present` tells whether an element (1-based) has a value.
+    pub fn check(&self, present: impl Fn(usize) -> bool) -> Option<(usize, &'static str)> {
+        match *self {
+            SyntaxRule::Paired(elements) => {
+                if elements.iter().any(|&e| present(e)) {
+                    elements
+                        .iter()
+                        .find(|&&e| !present(e))
+                        .map(|&e| (e, "is required when any of the paired elements is present"))
+                } else {
+                    None
+                }
+            }
+            SyntaxRule::Required(elements) => {
+                if elements.iter().any(|&e| present(e)) {
+                    None
+                } else {
+                    Some((
+                        elements[0],
+                        "or one of the elements it is listed with is required",
+                    ))
+                }
+            }
+            SyntaxRule::Conditional(first, others) => {
+                if present(first) {
+                    others
+                        .iter()
+                        .find(|&&e| !present(e))
+                        .map(|&e| (e, "is required by the conditional element"))
+                } else {
+                    None
+                }
+            }
+            SyntaxRule::Exclusion(elements) => elements
+                .iter()
+                .filter(|&&e| present(e))
+                .nth(1)
+                .map(|&e| (e, "excludes the other elements it is listed with")),
+            SyntaxRule::ListConditional(first, others) => {
+                if present(first) && !others.iter().any(|&e| present(e)) {
+                    Some((others[0], "or one of the elements it is listed with is required by the conditional element"))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for SyntaxRule {
+    /// The rule in X12 notation, e.g. `P0809` or `L050607`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (code, first, others) = match *self {
+            SyntaxRule::Paired(elements) => ('P', None, elements),
+            SyntaxRule::Required(elements) => ('R', None, elements),
+            SyntaxRule::Conditional(first, others) => ('C', Some(first), others),
+            SyntaxRule::Exclusion(elements) => ('E', None, elements),
+            SyntaxRule::ListConditional(first, others) => ('L', Some(first), others),
+        };
+        write!(f, "{}", code)?;
+        for element in first.iter().chain(others) {
+            write!(f, "{:02}", element)?;
+        }
+        Ok(())
+    }
+}
+
+/// Report the syntax notes broken by tokenized content, including the notes
+/// of composite elements (e.g. C022 in HI, C040 in REF04). `paths` holds the
+/// loop path of each segment (see `loop_path::loop_paths`).
+pub fn check_syntax(segments: &[Segment], paths: &[String], diagnostics: &mut Diagnostics) {
+    for (segment, path) in segments.iter().zip(paths) {
+        for rule in syntax_rules(segment.id) {
+            let present = |element: usize| !segment.element(element - 1).is_empty();
+            if let Some((element, reason)) = rule.check(present) {
+                diagnostics.push(
+                    Diagnostic::new(
+                        Severity::Error,
+                        DiagnosticCode::InvalidSyntax,
+                        format!(
+                            "{}{:02} {} (syntax note {})",
+                            segment.id, element, reason, rule
+                        ),
+                    )
+                    .at(segment, path, Some(element)),
+                );
+            }
+        }
+
+        for element in 1..=segment.elements.len() {
+            let Some((composite, rules)) = composite_syntax_rules(segment.id, element) else {
+                continue;
+            };
+            let value = segment.element(element - 1);
+            if value.is_empty() {
+                continue;
+            }
+            let components = get_components(value);
+            for rule in rules {
+                let present = |component: usize| {
+                    components
+                        .get(component - 1)
+                        .is_some_and(|value| !value.is_empty())
+                };
+                if let Some((component, reason)) = rule.check(present) {
+                    diagnostics.push(
+                        Diagnostic::new(
+                            Severity::Error,
+                            DiagnosticCode::InvalidSyntax,
+                            format!(
+                                "{}{:02}-{:02} {} ({} syntax note {})",
+                                segment.id, element, component, reason, composite, rule
+                            ),
+                        )
+                        .at(segment, path, Some(element)),
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::loop_path::loop_paths;
+    use crate::helper::tokenizer::tokenize;
+
+    fn broken(contents: &str) -> Vec<(String, String)> {
+        let segments = tokenize(contents);
+        let paths = loop_paths(&segments);
+        let mut diagnostics = Diagnostics::default();
+        check_syntax(&segments, &paths, &mut diagnostics);
+        diagnostics
+            .iter()
+            .map(|d| {
+                let note = d.message.rsplit("syntax note ").next().unwrap_or("");
+                (d.loop_path.clone(), note.trim_end_matches(')').to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rule_notation() {
+        assert_eq!(SyntaxRule::Paired(&[8, 9]).to_string(), "P0809");
+        assert_eq!(SyntaxRule::Conditional(11, &[10]).to_string(), "C1110");
+        assert_eq!(
+            SyntaxRule::ListConditional(5, &[6, 7]).to_string(),
+            "L050607"
+        );
+    }
+
+    #[test]
+    fn test_check_syntax() {
+        assert!(broken("NM1*85*2*BILLING*****XX*1234567893~N4*CITY*ST*12345~REF*EI*1~").is_empty());
+        assert_eq!(
+            broken(
+                "NM1*85*2*BILLING*****XX~N4*CITY*ST*12345*CA***X~REF*EI~\
+CAS*CO*45*10***1~DTM*472~QTY*PT*1*UN*X~"
+            ),
+            vec![
+                ("NM109".to_string(), "P0809".to_string()),
+                ("N407".to_string(), "E0207".to_string()),
+                ("REF02".to_string(), "R0203".to_string()),
+                ("CAS05".to_string(), "C0605".to_string()),
+                ("DTM02".to_string(), "R020305".to_string()),
+                ("QTY04".to_string(), "E0204".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_composite_syntax_notes() {
+        assert!(broken("HI*ABK:I10*BG:01:D8:20230501~REF*EI*1**EI:2:SY:3~").is_empty());
+        assert_eq!(
+            broken("HI*ABK:I10*BG:01:D8~REF*EI*1**EI:2:SY~SVC*HC:99213*100~CTX*CLM01:1*CLM~"),
+            vec![
+                ("HI02".to_string(), "P0304".to_string()),
+                ("REF04".to_string(), "P0304".to_string()),
+                ("CTX03".to_string(), "P0203".to_string()),
+            ]
+        );
+        let segments = tokenize("HI*ABK:I10*BG:01:D8~");
+        let mut diagnostics = Diagnostics::default();
+        check_syntax(&segments, &loop_paths(&segments), &mut diagnostics);
+        assert!(diagnostics
+            .iter()
+            .next()
+            .unwrap()
+            .message
+            .starts_with("HI02-04 is required"));
+    }
+}
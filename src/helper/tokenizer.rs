@@ -0,0 +1,146 @@
+//! Segment tokenizer
+//!
+//! Splits cleaned EDI content (standard `*` and `~` delimiters, see
+//! `helper::clean_contents`) into a flat list of segments in a single pass.
+//! Loop parsers walk this list by segment ID instead of repeatedly searching
+//! and re-allocating the remaining content, which keeps parsing of large
+//! batch files linear in the size of the input.
+
+/// A single segment located within tokenized EDI content.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Segment<'a> {
+    /// Segment identifier, e.g. `CLP`
+    pub id: &'a str,
+    /// Data elements after the identifier; index 0 is the first data element (e.g. CLP01)
+    pub elements: Vec<&'a str>,
+    /// Byte offset of the segment identifier within the tokenized content
+    pub offset: usize,
+    /// Zero-based ordinal of the segment within the tokenized content
+    pub index: usize,
+    /// Full segment text without the segment terminator
+    pub raw: &'a str,
+}
+
+impl<'a> Segment<'a> {
+    /// Get the data element at `index`, returning an empty string if absent.
+    /// Same contract as `edihelper::get_element`: index 0 is the first data element.
+    pub fn element(&self, index: usize) -> &'a str {
+        self.elements.get(index).copied().unwrap_or("")
+    }
+
+    /// The segment as EDI text, including its terminator.
+    pub fn to_edi(&self) -> String {
+        format!("{}~", self.raw)
+    }
+}
+
+/// Tokenize cleaned EDI content into segments.
+/// Empty segments (e.g. a trailing terminator) are skipped, and leading
+/// whitespace before a segment identifier is ignored.
+pub fn tokenize(contents: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut offset = 0;
+
+    for piece in contents.split('~') {
+        let start = offset + (piece.len() - piece.trim_start().len());
+        offset += piece.len() + 1;
+
+        let raw = piece.trim_start().trim_end_matches(['\r', '\n']);
+        if raw.is_empty() {
+            continue;
+        }
+
+        let mut parts = raw.split('*');
+        let id = parts.next().unwrap_or("");
+        segments.push(Segment {
+            id,
+            elements: parts.collect(),
+            offset: start,
+            index: segments.len(),
+            raw,
+        });
+    }
+
+    segments
+}
+
+/// Find the position of the first segment at or after `from` whose ID is in `ids`.
+pub fn find_segment(segments: &[Segment], ids: &[&str], from: usize) -> Option<usize> {
+    segments
+        .iter()
+        .skip(from)
+        .position(|segment| ids.contains(&segment.id))
+        .map(|pos| pos + from)
+}
+
+/// Split `segments` into loops, each starting at a segment whose ID is in `start_ids`.
+/// Segments before the first loop start are returned separately as the leading run.
+pub fn split_loops<'s, 'a>(
+    segments: &'s [Segment<'a>],
+    start_ids: &[&str],
+) -> (&'s [Segment<'a>], Vec<&'s [Segment<'a>]>) {
+    let starts: Vec<usize> = segments
+        .iter()
+        .enumerate()
+        .filter(|(_, segment)| start_ids.contains(&segment.id))
+        .map(|(pos, _)| pos)
+        .collect();
+
+    let leading_end = starts.first().copied().unwrap_or(segments.len());
+    let mut loops = Vec::with_capacity(starts.len());
+    for (n, start) in starts.iter().enumerate() {
+        let end = starts.get(n + 1).copied().unwrap_or(segments.len());
+        loops.push(&segments[*start..end]);
+    }
+
+    (&segments[..leading_end], loops)
+}
+
+/// Re-assemble segments into EDI text with `~` terminators.
+pub fn join_segments(segments: &[Segment]) -> String {
+    let mut contents = String::with_capacity(segments.iter().map(|s| s.raw.len() + 1).sum());
+    for segment in segments {
+        contents.push_str(segment.raw);
+        contents.push('~');
+    }
+    contents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let contents = "ST*835*35681~BPR*I*100**C~\nTRN*1*12345~";
+        let segments = tokenize(contents);
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0].id, "ST");
+        assert_eq!(segments[0].elements, vec!["835", "35681"]);
+        assert_eq!(segments[1].element(2), "");
+        assert_eq!(segments[1].element(3), "C");
+        assert_eq!(segments[1].element(9), "");
+        assert_eq!(segments[2].index, 2);
+        assert_eq!(&contents[segments[2].offset..segments[2].offset + 3], "TRN");
+        assert_eq!(segments[2].to_edi(), "TRN*1*12345~");
+    }
+
+    #[test]
+    fn test_tokenize_empty() {
+        assert!(tokenize("").is_empty());
+        assert!(tokenize("~\n~").is_empty());
+    }
+
+    #[test]
+    fn test_split_loops() {
+        let contents = "LX*1~CLP*A*1~CAS*CO*45*10~CLP*B*1~SVC*HC:1*10~";
+        let segments = tokenize(contents);
+        let (leading, loops) = split_loops(&segments, &["CLP"]);
+        assert_eq!(join_segments(leading), "LX*1~");
+        assert_eq!(loops.len(), 2);
+        assert_eq!(join_segments(loops[0]), "CLP*A*1~CAS*CO*45*10~");
+        assert_eq!(join_segments(loops[1]), "CLP*B*1~SVC*HC:1*10~");
+        assert_eq!(find_segment(&segments, &["CLP"], 2), Some(3));
+        assert_eq!(find_segment(&segments, &["PLB"], 0), None);
+    }
+}
@@ -0,0 +1,245 @@
+//! Unrecognized segments
+//!
+//! Segments a controller does not recognize, or finds out of place, are left
+//! in its unprocessed content. Rather than dropping them, every controller
+//! keeps them as `UnrecognizedSegments` on the parsed transaction set, each
+//! with the loop it appeared in and the segment it followed, and every writer
+//! puts them back in place, so EDI -> JSON -> EDI round trips lose nothing.
+//! Strict mode turns any of them into an error instead.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{EdiError, EdiResult, SourceLocation};
+use crate::helper::delimiters::Delimiters;
+use crate::helper::loop_path::loop_paths;
+use crate::helper::tokenizer::{tokenize, Segment};
+
+/// A segment the parser did not consume, kept where it appeared.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnrecognizedSegment {
+    /// Loops enclosing the segment, e.g. `2000B/2300`
+    pub loop_path: String,
+    /// Zero-based ordinal of the segment in the parsed content (ISA is 0)
+    pub position: usize,
+    /// The segment before it, without terminator; empty at the start of the content
+    pub after: String,
+    /// The segment itself with standard delimiters, without terminator, e.g. `ZZZ*1`
+    pub segment: String,
+}
+
+impl UnrecognizedSegment {
+    fn segment_id(&self) -> &str {
+        self.segment.split('*').next().unwrap_or("")
+    }
+
+    pub fn location(&self) -> SourceLocation {
+        SourceLocation {
+            segment_index: self.position,
+            segment_id: self.segment_id().to_string(),
+            loop_path: self.loop_path.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Unrecognized segments of a transaction set, in the order they appeared.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UnrecognizedSegments {
+    entries: Vec<UnrecognizedSegment>,
+}
+
+impl UnrecognizedSegments {
+    /// Collect the segments of `unprocessed`, whatever a parser left of the
+    /// normalized `contents` it was given.
+    pub fn collect(contents: &str, unprocessed: &str) -> UnrecognizedSegments {
+        if unprocessed.trim().is_empty() {
+            return UnrecognizedSegments::default();
+        }
+        let segments = tokenize(contents);
+        let paths = loop_paths(&segments);
+        let entries = match_unprocessed(&segments, unprocessed)
+            .into_iter()
+            .filter_map(|(segment, found)| {
+                let position = found?;
+                Some(UnrecognizedSegment {
+                    loop_path: paths[position].clone(),
+                    position,
+                    after: position
+                        .checked_sub(1)
+                        .map_or("", |before| segments[before].raw)
+                        .to_string(),
+                    segment,
+                })
+            })
+            .collect();
+        UnrecognizedSegments { entries }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, UnrecognizedSegment> {
+        self.entries.iter()
+    }
+
+    /// Strict mode: fail on the first unrecognized segment.
+    pub fn check_strict(&self) -> EdiResult<()> {
+        match self.entries.first() {
+            Some(first) => Err(EdiError::ValidationError(format!(
+                "Segment {} was not recognized ({} unrecognized segments)",
+                first.segment,
+                self.entries.len()
+            ))
+            .at(first.location())),
+            None => Ok(()),
+        }
+    }
+
+    /// Put the segments back into `output`, the EDI written for their
+    /// transaction set with `delimiters`. Each goes after the occurrence of
+    /// the segment it followed that is nearest its original position, so
+    /// unedited content comes back exactly as it was read.
+    pub fn restore(&self, output: String, delimiters: &Delimiters) -> String {
+        if self.entries.is_empty() {
+            return output;
+        }
+        let terminator = delimiters.segment;
+        let normalized = delimiters.normalized();
+
+        // Written segments with the layout (line breaks) preceding them
+        let mut pieces: Vec<(String, String)> = Vec::new();
+        let mut layout = String::new();
+        for piece in output.split_inclusive(terminator) {
+            let text = piece.strip_suffix(terminator).unwrap_or(piece);
+            let segment = text.trim_start();
+            if segment.trim_end().is_empty() {
+                layout.push_str(piece);
+                continue;
+            }
+            layout.push_str(&text[..text.len() - segment.len()]);
+            pieces.push((
+                std::mem::take(&mut layout),
+                piece[text.len() - segment.len()..].to_string(),
+            ));
+        }
+        let mut keys: Vec<String> = pieces
+            .iter()
+            .map(|(_, segment)| {
+                let converted = delimiters.convert(segment, &normalized);
+                converted.trim_end_matches(normalized.segment).to_string()
+            })
+            .collect();
+
+        for entry in &self.entries {
+            let expected = entry.position.saturating_sub(1);
+            let at = keys
+                .iter()
+                .enumerate()
+                .filter(|(_, key)| **key == entry.after)
+                .min_by_key(|(index, _)| index.abs_diff(expected))
+                .map(|(index, _)| index + 1)
+                .unwrap_or(if entry.after.is_empty() {
+                    0
+                } else {
+                    entry.position.min(pieces.len())
+                });
+            let lead = pieces
+                .get(at)
+                .or(pieces.last())
+                .map(|(lead, _)| lead.clone())
+                .unwrap_or_default();
+            let segment = delimiters.restore(&format!("{}{}", entry.segment, normalized.segment));
+            pieces.insert(at, (lead, segment));
+            keys.insert(at, entry.segment.clone());
+        }
+
+        let mut restored = String::with_capacity(output.len());
+        for (lead, segment) in pieces {
+            restored.push_str(&lead);
+            restored.push_str(&segment);
+        }
+        restored.push_str(&layout);
+        restored
+    }
+}
+
+impl<'a> IntoIterator for &'a UnrecognizedSegments {
+    type Item = &'a UnrecognizedSegment;
+    type IntoIter = std::slice::Iter<'a, UnrecognizedSegment>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+/// Match the segments left in `unprocessed` back to their positions in
+/// `segments`, in order. Leftovers found out of order are matched to the
+/// first unused occurrence; `None` when a leftover is not in `segments`.
+pub fn match_unprocessed(segments: &[Segment], unprocessed: &str) -> Vec<(String, Option<usize>)> {
+    let mut used = vec![false; segments.len()];
+    let mut from = 0;
+    tokenize(unprocessed)
+        .into_iter()
+        .map(|leftover| {
+            let matches = |pos: &usize| !used[*pos] && segments[*pos].raw == leftover.raw;
+            let found = (from.min(segments.len())..segments.len())
+                .find(matches)
+                .or_else(|| (0..from.min(segments.len())).find(matches));
+            if let Some(pos) = found {
+                used[pos] = true;
+                from = pos + 1;
+            }
+            (leftover.raw.to_string(), found)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENTS: &str = "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230501*1200*^*00501*000000001*0*T*:~GS*HP*S*R*20230501*1200*1*X*005010X221A1~ST*835*0001~BPR*I*100~LX*1~CLP*A*1*100*80~ZZZ*1~SVC*HC:99213*100*80~SE*8*0001~GE*1*1~IEA*1*000000001~";
+
+    #[test]
+    fn test_collect_and_check_strict() {
+        let unrecognized = UnrecognizedSegments::collect(CONTENTS, "ZZZ*1~");
+        let entry = unrecognized.iter().next().unwrap();
+        assert_eq!(entry.position, 6);
+        assert_eq!(entry.after, "CLP*A*1*100*80");
+        assert_eq!(entry.loop_path, "2000/2100");
+
+        let error = unrecognized.check_strict().unwrap_err();
+        assert!(matches!(error.kind(), EdiError::ValidationError(_)));
+        assert_eq!(error.location().unwrap().segment_index, 6);
+        assert!(UnrecognizedSegments::collect(CONTENTS, "")
+            .check_strict()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_restore_in_place() {
+        let unrecognized = UnrecognizedSegments::collect(CONTENTS, "ZZZ*1~");
+        let written = CONTENTS.replace("ZZZ*1~", "").replace('~', "~\n");
+        let restored = unrecognized.restore(written, &Delimiters::default());
+        assert_eq!(restored, CONTENTS.replace('~', "~\n"));
+    }
+
+    #[test]
+    fn test_restore_custom_delimiters() {
+        let unrecognized = UnrecognizedSegments::collect(CONTENTS, "ZZZ*1~");
+        let delimiters = Delimiters {
+            element: '|',
+            segment: '\n',
+            ..Default::default()
+        };
+        let written = delimiters.restore(&CONTENTS.replace("ZZZ*1~", ""));
+        let restored = unrecognized.restore(written, &delimiters);
+        assert_eq!(restored, delimiters.restore(CONTENTS));
+    }
+}
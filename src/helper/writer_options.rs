@@ -0,0 +1,292 @@
+//! Writer options
+//!
+//! Every `write_*` function builds its EDI with the standard delimiters and
+//! leaves the layout to `WriterOptions::format`: which delimiters and segment
+//! terminator to emit, what to put between segments, whether to uppercase the
+//! data, whether to pad the ISA segment to its fixed widths and whether to
+//! recompute envelope trailers or renumber HL segments. One set of options therefore lays out every
+//! transaction type the same way. Syntax notes broken by the written segments
+//! are logged as warnings, or fail the write with `strict`; `format_checked`
+//! returns them to the caller instead.
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{EdiError, EdiResult};
+use crate::helper::delimiters::{Delimiters, STANDARD_ELEMENT, STANDARD_SEGMENT};
+use crate::helper::diagnostics::Diagnostics;
+use crate::helper::envelope_validation::sync_envelope;
+use crate::helper::hierarchy::renumber_hierarchy;
+use crate::helper::loop_path::loop_paths;
+use crate::helper::syntax_rules::check_syntax;
+use crate::helper::tokenizer::tokenize;
+
+/// Fixed widths of ISA01 through ISA16.
+const ISA_WIDTHS: [usize; 16] = [2, 10, 2, 10, 2, 15, 2, 15, 6, 4, 1, 5, 9, 1, 1, 1];
+
+/// ISA13, the interchange control number, is numeric and padded with zeros.
+const ISA_CONTROL_NUMBER: usize = 13;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WriterOptions {
+    /// Delimiters to write with; `None` keeps those captured from the source ISA
+    pub delimiters: Option<Delimiters>,
+    /// Segment terminator, overriding the one of the delimiters in use
+    pub segment_terminator: Option<char>,
+    /// Written after every segment terminator, e.g. `"\n"` or `"\r\n"`; empty for a single line.
+    /// Not written when the terminator is itself a line break.
+    pub line_break: String,
+    /// Convert all data to uppercase
+    pub uppercase: bool,
+    /// Pad ISA elements to their fixed widths (control number with zeros, others with spaces)
+    pub pad_isa: bool,
+    /// Recompute trailer counts (SE01, GE01, IEA01) and copy header control
+    /// numbers into trailers (SE02, GE02, IEA02), e.g. after editing parsed JSON
+    pub sync_envelope: bool,
+    /// Renumber HL01, HL02 and HL04 from the order and level codes of the
+    /// written HL segments, e.g. after adding or removing subscribers
+    pub renumber_hierarchy: bool,
+    /// Fail when the written segments break X12 syntax notes instead of logging them
+    pub strict: bool,
+}
+
+impl Default for WriterOptions {
+    fn default() -> Self {
+        WriterOptions {
+            delimiters: None,
+            segment_terminator: None,
+            line_break: "\n".to_string(),
+            uppercase: false,
+            pad_isa: false,
+            sync_envelope: false,
+            renumber_hierarchy: false,
+            strict: false,
+        }
+    }
+}
+
+impl WriterOptions {
+    /// All segments on one line.
+    pub fn single_line() -> WriterOptions {
+        WriterOptions {
+            line_break: String::new(),
+            ..Default::default()
+        }
+    }
+
+    /// Delimiters the output is written with, given those of the source ISA.
+    pub fn target_delimiters(&self, source: &Delimiters) -> Delimiters {
+        let mut target = self.delimiters.unwrap_or(*source);
+        if let Some(segment) = self.segment_terminator {
+            target.segment = segment;
+        }
+        target
+    }
+
+    /// Lay out `edi`, written with the standard delimiters in any layout, for
+    /// a transaction set whose source ISA declared `source`. Broken syntax
+    /// notes are logged, or an error with `strict`.
+    pub fn format(&self, edi: &str, source: &Delimiters) -> EdiResult<String> {
+        let (output, diagnostics) = self.format_checked(edi, source);
+        if self.strict {
+            if let Some(first) = diagnostics.iter().next() {
+                return Err(EdiError::ValidationError(format!(
+                    "Written EDI breaks syntax notes ({} problems), first: {}",
+                    diagnostics.len(),
+                    first
+                )));
+            }
+        }
+        diagnostics.log();
+        Ok(output)
+    }
+
+    /// Same as `format`, but returns the syntax notes broken by the written
+    /// segments instead of logging them.
+    pub fn format_checked(&self, edi: &str, source: &Delimiters) -> (String, Diagnostics) {
+        let target = self.target_delimiters(source);
+        let standard = Delimiters::default();
+        let line_break = if target.segment == '\n' || target.segment == '\r' {
+            ""
+        } else {
+            self.line_break.as_str()
+        };
+
+        let mut segments: Vec<String> = edi
+            .split(STANDARD_SEGMENT)
+            .map(|segment| segment.trim_matches(['\r', '\n']))
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| {
+                if self.pad_isa && segment.starts_with("ISA*") {
+                    pad_isa(segment)
+                } else {
+                    segment.to_string()
+                }
+            })
+            .collect();
+        if self.renumber_hierarchy {
+            let changed = renumber_hierarchy(&mut segments);
+            if changed > 0 {
+                info!("Renumbered {} HL segments", changed);
+            }
+        }
+        if self.sync_envelope {
+            let changed = sync_envelope(&mut segments);
+            if changed > 0 {
+                info!("Updated {} envelope trailer elements", changed);
+            }
+        }
+        let diagnostics = check_written(&segments);
+
+        let mut output = String::with_capacity(edi.len() + edi.len() / 20);
+        for mut segment in segments {
+            if self.uppercase {
+                segment = segment.to_uppercase();
+            }
+            output.push_str(&standard.convert(&segment, &target));
+            output.push(target.segment);
+            output.push_str(line_break);
+        }
+        (output, diagnostics)
+    }
+}
+
+/// Syntax notes broken by standard-delimited segments about to be written.
+fn check_written(segments: &[String]) -> Diagnostics {
+    let joined = segments.join(&STANDARD_SEGMENT.to_string());
+    let tokens = tokenize(&joined);
+    let paths = loop_paths(&tokens);
+    let mut diagnostics = Diagnostics::default();
+    check_syntax(&tokens, &paths, &mut diagnostics);
+    diagnostics
+}
+
+/// Pad the elements of a standard-delimited ISA segment to their fixed widths.
+/// Longer elements are left as they are.
+fn pad_isa(segment: &str) -> String {
+    segment
+        .split(STANDARD_ELEMENT)
+        .enumerate()
+        .map(|(position, element)| {
+            let width = match position {
+                0 => 0,
+                _ => ISA_WIDTHS.get(position - 1).copied().unwrap_or(0),
+            };
+            if position == ISA_CONTROL_NUMBER {
+                format!("{:0>width$}", element, width = width)
+            } else {
+                format!("{:<width$}", element, width = width)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(&STANDARD_ELEMENT.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EDI: &str = "ISA*00**00**ZZ*sender*ZZ*RECEIVER*230501*1200*^*00501*42*0*T*:~\nGS*HP*S*R~ST*835*0001~NM1*QC*1*doe*john~\n\nSE*3*0001~";
+
+    #[test]
+    fn test_default_layout() {
+        let output = WriterOptions::default()
+            .format(EDI, &Delimiters::default())
+            .unwrap();
+        assert_eq!(output.lines().count(), 5);
+        assert!(output.ends_with("SE*3*0001~\n"));
+        assert!(output.contains("NM1*QC*1*doe*john~\n"));
+    }
+
+    #[test]
+    fn test_single_line_custom_delimiters_uppercase_padded() {
+        let options = WriterOptions {
+            delimiters: Some(Delimiters {
+                element: '|',
+                component: '>',
+                ..Default::default()
+            }),
+            segment_terminator: Some('\''),
+            uppercase: true,
+            pad_isa: true,
+            ..WriterOptions::single_line()
+        };
+        let output = options.format(EDI, &Delimiters::default()).unwrap();
+        assert_eq!(
+            output,
+            "ISA|00|          |00|          |ZZ|SENDER         |ZZ|RECEIVER       |230501|1200|^|00501|000000042|0|T|>'GS|HP|S|R'ST|835|0001'NM1|QC|1|DOE|JOHN'SE|3|0001'"
+        );
+    }
+
+    #[test]
+    fn test_sync_envelope_after_padding() {
+        let options = WriterOptions {
+            pad_isa: true,
+            sync_envelope: true,
+            ..WriterOptions::single_line()
+        };
+        let edi = format!(
+            "{}GE*0*1~IEA*0*42~",
+            EDI.replace("GS*HP*S*R", "GS*HP*S*R*20230501*1200*1*X*005010X221A1")
+        );
+        let output = options.format(&edi, &Delimiters::default()).unwrap();
+        assert!(output.ends_with("SE*3*0001~GE*1*1~IEA*1*000000042~"));
+    }
+
+    #[test]
+    fn test_newline_terminator() {
+        let options = WriterOptions {
+            segment_terminator: Some('\n'),
+            ..Default::default()
+        };
+        let output = options
+            .format("ST*835*0001~\nSE*2*0001~\n", &Delimiters::default())
+            .unwrap();
+        assert_eq!(output, "ST*835*0001\nSE*2*0001\n");
+    }
+
+    #[test]
+    fn test_check_written_syntax() {
+        let segments: Vec<String> = vec!["ST*835*0001".into(), "NM1*QC*1*DOE*****MI".into()];
+        let diagnostics = check_written(&segments);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics.iter().next().unwrap().loop_path, "NM109");
+    }
+
+    #[test]
+    fn test_renumber_hierarchy() {
+        let options = WriterOptions {
+            renumber_hierarchy: true,
+            ..WriterOptions::single_line()
+        };
+        let edi = "ST*270*0001~HL*1**20*1~HL*2*1*21*1~HL*5*2*22*0~HL*9*2*22*0~SE*6*0001~";
+        assert_eq!(
+            options.format(edi, &Delimiters::default()).unwrap(),
+            "ST*270*0001~HL*1**20*1~HL*2*1*21*1~HL*3*2*22*0~HL*4*2*22*0~SE*6*0001~"
+        );
+    }
+
+    #[test]
+    fn test_broken_syntax_notes() {
+        // NM108 without NM109 breaks P0809
+        let edi = "ST*835*0001~NM1*QC*1*DOE*JOHN****MI~SE*3*0001~";
+        let (output, diagnostics) =
+            WriterOptions::single_line().format_checked(edi, &Delimiters::default());
+        assert_eq!(output, edi);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics.iter().next().unwrap().loop_path, "NM109");
+
+        assert!(WriterOptions::default()
+            .format(edi, &Delimiters::default())
+            .is_ok());
+        let strict = WriterOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assert!(matches!(
+            strict.format(edi, &Delimiters::default()),
+            Err(EdiError::ValidationError(_))
+        ));
+    }
+}
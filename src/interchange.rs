@@ -0,0 +1,735 @@
+//! Interchange model for multi-group, multi-transaction files
+//!
+//! The transaction set controllers (`get_835`, `get_837`, ...) each handle a
+//! single ISA/GS/ST envelope. Clearinghouse drops often carry several
+//! interchanges, each with several functional groups of mixed transaction
+//! sets. `get_interchanges` splits such a file on its envelope segments,
+//! validates ISA/IEA, GS/GE and ST/SE at every level, and hands each ST..SE
+//! transaction set to its controller. `write_interchanges` reassembles them.
+
+use crate::edi270::controller::Edi270;
+use crate::edi271::controller::Edi271;
+use crate::edi276::controller::Edi276;
+use crate::edi277::controller::Edi277;
+use crate::edi278::controller::Edi278;
+use crate::edi820::controller::Edi820;
+use crate::edi834::controller::Edi834;
+use crate::edi835::controller::Edi835;
+use crate::edi837::controller::Edi837;
+use crate::edi999::controller::Edi999;
+use crate::error::{EdiError, EdiResult, SourceLocation};
+use crate::helper::delimiters::Delimiters;
+use crate::helper::diagnostics::{DiagnosticCode, Diagnostics};
+use crate::helper::envelope_validation::{
+    envelope_error, missing_trailer, unexpected, validate_gs_ge, validate_isa_iea,
+    validate_segment_count,
+};
+use crate::helper::helper::clean_contents;
+use crate::helper::loop_path::loop_paths;
+use crate::helper::source_map::SourceMap;
+use crate::helper::tokenizer::{join_segments, tokenize, Segment};
+use crate::helper::unrecognized::UnrecognizedSegments;
+use crate::helper::writer_options::WriterOptions;
+use crate::segments::ge::{get_ge, write_ge, GE};
+use crate::segments::gs::{get_gs, write_gs, GS};
+use crate::segments::iea::{get_iea, write_iea, IEA};
+use crate::segments::isa::{get_isa, write_isa, ISA};
+use crate::transaction_processor::{detect_transaction_set, TransactionSet};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+/// A parsed transaction set of any supported type. Variants are boxed since
+/// transaction structs differ widely in size. In JSON the transaction set's
+/// fields are tagged with its identifier, e.g. `"transaction_set": "835"`.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(tag = "transaction_set")]
+pub enum AnyTransaction {
+    #[serde(rename = "270")]
+    Edi270(Box<Edi270>),
+    #[serde(rename = "271")]
+    Edi271(Box<Edi271>),
+    #[serde(rename = "276")]
+    Edi276(Box<Edi276>),
+    #[serde(rename = "277")]
+    Edi277(Box<Edi277>),
+    #[serde(rename = "278")]
+    Edi278(Box<Edi278>),
+    #[serde(rename = "820")]
+    Edi820(Box<Edi820>),
+    #[serde(rename = "834")]
+    Edi834(Box<Edi834>),
+    #[serde(rename = "835")]
+    Edi835(Box<Edi835>),
+    #[serde(rename = "837")]
+    Edi837(Box<Edi837>),
+    #[serde(rename = "999")]
+    Edi999(Box<Edi999>),
+}
+
+impl AnyTransaction {
+    /// Parse a complete single-transaction interchange (ISA..IEA), detecting
+    /// its transaction set from ST01.
+    pub fn parse_any(contents: &str) -> EdiResult<AnyTransaction> {
+        match detect_transaction_set(contents) {
+            Some(code) => AnyTransaction::parse(&code, contents),
+            None => Err(EdiError::UnsupportedFormat(
+                "Could not detect transaction set type. Expected ST*XXX* segment.".to_string(),
+            )),
+        }
+    }
+
+    /// Parse a complete single-transaction interchange (ISA..IEA) with the
+    /// controller for `transaction_set_id` (ST01).
+    pub fn parse(transaction_set_id: &str, contents: &str) -> EdiResult<AnyTransaction> {
+        fn parse_as<T: TransactionSet>(contents: &str) -> EdiResult<Box<T>> {
+            Ok(Box::new(T::parse(contents.to_string())?.0))
+        }
+
+        let transaction = match transaction_set_id {
+            "270" => AnyTransaction::Edi270(parse_as(contents)?),
+            "271" => AnyTransaction::Edi271(parse_as(contents)?),
+            "276" => AnyTransaction::Edi276(parse_as(contents)?),
+            "277" => AnyTransaction::Edi277(parse_as(contents)?),
+            "278" => AnyTransaction::Edi278(parse_as(contents)?),
+            "820" => AnyTransaction::Edi820(parse_as(contents)?),
+            "834" => AnyTransaction::Edi834(parse_as(contents)?),
+            "835" => AnyTransaction::Edi835(parse_as(contents)?),
+            "837" => AnyTransaction::Edi837(parse_as(contents)?),
+            "999" => AnyTransaction::Edi999(parse_as(contents)?),
+            code => {
+                return Err(EdiError::UnsupportedFormat(format!(
+                    "Unsupported transaction set: {}",
+                    code
+                )))
+            }
+        };
+        Ok(transaction)
+    }
+
+    /// Write the transaction set with its own envelope (ISA..IEA).
+    pub fn to_edi(&self) -> EdiResult<String> {
+        self.to_edi_with(&WriterOptions::default())
+    }
+
+    /// Write the transaction set with its own envelope (ISA..IEA), laid out per `options`.
+    pub fn to_edi_with(&self, options: &WriterOptions) -> EdiResult<String> {
+        match self {
+            AnyTransaction::Edi270(edi) => edi.to_edi_with(options),
+            AnyTransaction::Edi271(edi) => edi.to_edi_with(options),
+            AnyTransaction::Edi276(edi) => edi.to_edi_with(options),
+            AnyTransaction::Edi277(edi) => edi.to_edi_with(options),
+            AnyTransaction::Edi278(edi) => edi.to_edi_with(options),
+            AnyTransaction::Edi820(edi) => edi.to_edi_with(options),
+            AnyTransaction::Edi834(edi) => edi.to_edi_with(options),
+            AnyTransaction::Edi835(edi) => edi.to_edi_with(options),
+            AnyTransaction::Edi837(edi) => edi.to_edi_with(options),
+            AnyTransaction::Edi999(edi) => edi.to_edi_with(options),
+        }
+    }
+
+    /// Problems found while parsing the transaction set, positioned within the whole file.
+    pub fn diagnostics(&self) -> &Diagnostics {
+        match self {
+            AnyTransaction::Edi270(edi) => &edi.diagnostics,
+            AnyTransaction::Edi271(edi) => &edi.diagnostics,
+            AnyTransaction::Edi276(edi) => &edi.diagnostics,
+            AnyTransaction::Edi277(edi) => &edi.diagnostics,
+            AnyTransaction::Edi278(edi) => &edi.diagnostics,
+            AnyTransaction::Edi820(edi) => &edi.diagnostics,
+            AnyTransaction::Edi834(edi) => &edi.diagnostics,
+            AnyTransaction::Edi835(edi) => &edi.diagnostics,
+            AnyTransaction::Edi837(edi) => &edi.diagnostics,
+            AnyTransaction::Edi999(edi) => &edi.diagnostics,
+        }
+    }
+
+    /// Segments the parser did not recognize, positioned within the transaction set's own envelope.
+    pub fn unrecognized(&self) -> &UnrecognizedSegments {
+        match self {
+            AnyTransaction::Edi270(edi) => &edi.unrecognized,
+            AnyTransaction::Edi271(edi) => &edi.unrecognized,
+            AnyTransaction::Edi276(edi) => &edi.unrecognized,
+            AnyTransaction::Edi277(edi) => &edi.unrecognized,
+            AnyTransaction::Edi278(edi) => &edi.unrecognized,
+            AnyTransaction::Edi820(edi) => &edi.unrecognized,
+            AnyTransaction::Edi834(edi) => &edi.unrecognized,
+            AnyTransaction::Edi835(edi) => &edi.unrecognized,
+            AnyTransaction::Edi837(edi) => &edi.unrecognized,
+            AnyTransaction::Edi999(edi) => &edi.unrecognized,
+        }
+    }
+
+    fn diagnostics_mut(&mut self) -> &mut Diagnostics {
+        match self {
+            AnyTransaction::Edi270(edi) => &mut edi.diagnostics,
+            AnyTransaction::Edi271(edi) => &mut edi.diagnostics,
+            AnyTransaction::Edi276(edi) => &mut edi.diagnostics,
+            AnyTransaction::Edi277(edi) => &mut edi.diagnostics,
+            AnyTransaction::Edi278(edi) => &mut edi.diagnostics,
+            AnyTransaction::Edi820(edi) => &mut edi.diagnostics,
+            AnyTransaction::Edi834(edi) => &mut edi.diagnostics,
+            AnyTransaction::Edi835(edi) => &mut edi.diagnostics,
+            AnyTransaction::Edi837(edi) => &mut edi.diagnostics,
+            AnyTransaction::Edi999(edi) => &mut edi.diagnostics,
+        }
+    }
+
+    /// The transaction set identifier (ST01), e.g. "835".
+    pub fn transaction_set_id(&self) -> &'static str {
+        match self {
+            AnyTransaction::Edi270(_) => "270",
+            AnyTransaction::Edi271(_) => "271",
+            AnyTransaction::Edi276(_) => "276",
+            AnyTransaction::Edi277(_) => "277",
+            AnyTransaction::Edi278(_) => "278",
+            AnyTransaction::Edi820(_) => "820",
+            AnyTransaction::Edi834(_) => "834",
+            AnyTransaction::Edi835(_) => "835",
+            AnyTransaction::Edi837(_) => "837",
+            AnyTransaction::Edi999(_) => "999",
+        }
+    }
+}
+
+/// A functional group (GS..GE) and its transaction sets.
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct FunctionalGroup {
+    pub gs: GS,
+    pub transactions: Vec<AnyTransaction>,
+    pub ge: GE,
+}
+
+/// An interchange (ISA..IEA) and its functional groups.
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Interchange {
+    pub isa: ISA,
+    pub groups: Vec<FunctionalGroup>,
+    pub iea: IEA,
+    /// Delimiters declared by the source ISA, re-emitted when writing EDI
+    #[serde(default)]
+    pub delimiters: Delimiters,
+    /// Envelope problems found at the ISA and GS levels; ST/SE problems are
+    /// reported on each transaction set
+    #[serde(default, skip_serializing_if = "Diagnostics::is_empty")]
+    pub diagnostics: Diagnostics,
+}
+
+/// Check whether raw EDI content holds more than one interchange, functional
+/// group or transaction set, i.e. whether it needs `get_interchanges`.
+pub fn is_multi_transaction(contents: &str) -> bool {
+    let contents = clean_contents(contents.to_string());
+    let segments = tokenize(&contents);
+    let count = |id: &str| segments.iter().filter(|s| s.id == id).count();
+    count("ISA") > 1 || count("GS") > 1 || count("ST") > 1
+}
+
+/// Parse every interchange, functional group and transaction set in raw EDI
+/// content. Delimiters are taken from the first ISA segment.
+pub fn get_interchanges(contents: &str) -> EdiResult<Vec<Interchange>> {
+    parse_interchanges(contents, false)
+}
+
+/// Same as `get_interchanges`, but any segment a transaction set parser does
+/// not recognize is an error.
+pub fn get_interchanges_strict(contents: &str) -> EdiResult<Vec<Interchange>> {
+    parse_interchanges(contents, true)
+}
+
+fn parse_interchanges(contents: &str, strict: bool) -> EdiResult<Vec<Interchange>> {
+    let raw = contents;
+    let delimiters = Delimiters::detect(contents);
+    let contents = clean_contents(contents.to_string());
+    let segments = tokenize(&contents);
+    // Transaction sets are parsed from their own normalized text, so data
+    // characters swapped by the normalization are reported on the interchange
+    let paths = if delimiters != delimiters.normalized() {
+        loop_paths(&segments)
+    } else {
+        Vec::new()
+    };
+
+    let mut interchanges: Vec<Interchange> = Vec::new();
+    let mut skipped = Diagnostics::default();
+    let mut pos = 0;
+    while pos < segments.len() {
+        if segments[pos].id != "ISA" {
+            // Reported on the interchange that follows (or precedes, at the end of the file)
+            skipped.push(unexpected(&segments[pos], "outside of an interchange"));
+            pos += 1;
+            continue;
+        }
+        let (mut interchange, next) =
+            get_interchange(&segments, pos, strict).map_err(|e| SourceMap::new(raw).relocate(e))?;
+        interchange.delimiters = delimiters;
+        let mut diagnostics = std::mem::take(&mut skipped);
+        diagnostics.extend(std::mem::take(&mut interchange.diagnostics));
+        if !paths.is_empty() {
+            delimiters.check_data(&segments[pos..next], &paths[pos..next], &mut diagnostics);
+        }
+        interchange.diagnostics = diagnostics;
+        interchanges.push(interchange);
+        pos = next;
+    }
+    if let Some(last) = interchanges.last_mut() {
+        last.diagnostics.extend(skipped);
+    }
+
+    if interchanges.is_empty() {
+        return Err(EdiError::MissingSegment("ISA".to_string()));
+    }
+    Ok(interchanges)
+}
+
+/// Parse one interchange starting at the ISA segment at `start`.
+/// Returns the interchange and the position after its IEA segment.
+fn get_interchange(
+    segments: &[Segment],
+    start: usize,
+    strict: bool,
+) -> EdiResult<(Interchange, usize)> {
+    let isa_segment = &segments[start];
+    let mut interchange = Interchange {
+        isa: get_isa(isa_segment.elements.join("*")),
+        ..Default::default()
+    };
+
+    let mut pos = start + 1;
+    while pos < segments.len() {
+        match segments[pos].id {
+            "GS" => {
+                let (group, next) = get_group(
+                    segments,
+                    pos,
+                    isa_segment,
+                    strict,
+                    &mut interchange.diagnostics,
+                )?;
+                interchange.groups.push(group);
+                pos = next;
+            }
+            "IEA" => {
+                interchange.iea = get_iea(segments[pos].elements.join("*"));
+                pos += 1;
+                break;
+            }
+            "ISA" => break,
+            _ => {
+                let stray = unexpected(&segments[pos], "outside of a functional group");
+                interchange.diagnostics.push(stray);
+                pos += 1;
+            }
+        }
+    }
+
+    match segments[..pos].last().filter(|s| s.id == "IEA") {
+        Some(iea) => {
+            let (isa13, iea02) = (isa_segment.element(12), iea.element(1));
+            if !validate_isa_iea(isa13, iea02) {
+                interchange.diagnostics.push(envelope_error(
+                    DiagnosticCode::ControlNumberMismatch,
+                    iea,
+                    Some(2),
+                    format!(
+                        "ISA13 ({}) != IEA02 ({}). Per X12 spec, these must be identical.",
+                        isa13, iea02
+                    ),
+                ));
+            }
+            let groups = interchange.groups.len();
+            if !validate_segment_count(iea.element(0), groups) {
+                interchange.diagnostics.push(envelope_error(
+                    DiagnosticCode::CountMismatch,
+                    iea,
+                    Some(1),
+                    format!(
+                        "IEA01 claims {} functional groups but found {}.",
+                        iea.element(0),
+                        groups
+                    ),
+                ));
+            }
+        }
+        None => interchange
+            .diagnostics
+            .push(missing_trailer(isa_segment, "IEA")),
+    }
+    Ok((interchange, pos))
+}
+
+/// Parse one functional group starting at the GS segment at `start`.
+/// Returns the group and the position after its GE segment; envelope problems
+/// are added to `diagnostics`.
+fn get_group(
+    segments: &[Segment],
+    start: usize,
+    isa_segment: &Segment,
+    strict: bool,
+    diagnostics: &mut Diagnostics,
+) -> EdiResult<(FunctionalGroup, usize)> {
+    let gs_segment = &segments[start];
+    let mut group = FunctionalGroup {
+        gs: get_gs(gs_segment.elements.join("*")),
+        ..Default::default()
+    };
+
+    let mut pos = start + 1;
+    while pos < segments.len() {
+        match segments[pos].id {
+            "ST" => {
+                let end = segments[pos..]
+                    .iter()
+                    .position(|s| matches!(s.id, "SE" | "GE" | "IEA"))
+                    .map(|offset| pos + offset)
+                    .unwrap_or(segments.len());
+                let has_se = end < segments.len() && segments[end].id == "SE";
+                let transaction_end = if has_se { end + 1 } else { end };
+                let transaction_segments = &segments[pos..transaction_end];
+                group.transactions.push(get_transaction(
+                    transaction_segments,
+                    isa_segment,
+                    gs_segment,
+                    strict,
+                )?);
+                pos = transaction_end;
+            }
+            "GE" => {
+                group.ge = get_ge(segments[pos].elements.join("*"));
+                pos += 1;
+                break;
+            }
+            "GS" | "IEA" | "ISA" => break,
+            _ => {
+                diagnostics.push(unexpected(&segments[pos], "outside of a transaction set"));
+                pos += 1;
+            }
+        }
+    }
+
+    match segments[..pos].last().filter(|s| s.id == "GE") {
+        Some(ge) => {
+            let (gs06, ge02) = (gs_segment.element(5), ge.element(1));
+            if !validate_gs_ge(gs06, ge02) {
+                diagnostics.push(envelope_error(
+                    DiagnosticCode::ControlNumberMismatch,
+                    ge,
+                    Some(2),
+                    format!(
+                        "GS06 ({}) != GE02 ({}). Per X12 spec, these must be identical.",
+                        gs06, ge02
+                    ),
+                ));
+            }
+            let transactions = group.transactions.len();
+            if !validate_segment_count(ge.element(0), transactions) {
+                diagnostics.push(envelope_error(
+                    DiagnosticCode::CountMismatch,
+                    ge,
+                    Some(1),
+                    format!(
+                        "GE01 claims {} transaction sets but found {}.",
+                        ge.element(0),
+                        transactions
+                    ),
+                ));
+            }
+        }
+        None => diagnostics.push(missing_trailer(gs_segment, "GE")),
+    }
+    Ok((group, pos))
+}
+
+/// Parse one ST..SE transaction set by wrapping it in its own ISA/GS envelope
+/// and handing it to the controller for its ST01. The controller checks ST/SE;
+/// its diagnostics are moved from the wrapper to their place in the file.
+/// Unrecognized segments keep their wrapper positions, as the transaction set
+/// is written back with its own envelope.
+fn get_transaction(
+    segments: &[Segment],
+    isa_segment: &Segment,
+    gs_segment: &Segment,
+    strict: bool,
+) -> EdiResult<AnyTransaction> {
+    let st = &segments[0];
+    info!(
+        "Parsing {} transaction set {}",
+        st.element(0),
+        st.element(1)
+    );
+    let contents = format!(
+        "{}~{}~{}GE*1*{}~IEA*1*{}~",
+        isa_segment.raw,
+        gs_segment.raw,
+        join_segments(segments),
+        gs_segment.element(5),
+        isa_segment.element(12),
+    );
+    let parsed = AnyTransaction::parse(st.element(0), &contents).and_then(|transaction| {
+        if strict {
+            transaction.unrecognized().check_strict()?;
+        }
+        Ok(transaction)
+    });
+    let mut transaction = parsed.map_err(|e| {
+        // Positions in the wrapper are mapped back to segment indices in the
+        // file; `get_interchanges` fills in the rest
+        let index = |wrapped: usize| match wrapped {
+            0 => isa_segment.index,
+            1 => gs_segment.index,
+            _ => st.index + wrapped - 2,
+        };
+        match e {
+            EdiError::Located(error, mut location) => {
+                location.segment_index = index(location.segment_index);
+                EdiError::Located(error, location)
+            }
+            error => error.at(SourceLocation {
+                segment_index: st.index,
+                ..Default::default()
+            }),
+        }
+    })?;
+
+    let wrapped_offset = isa_segment.raw.len() + gs_segment.raw.len() + 2;
+    transaction.diagnostics_mut().shift(
+        st.index.saturating_sub(2),
+        st.offset.saturating_sub(wrapped_offset),
+    );
+    Ok(transaction)
+}
+
+/// Write one interchange with all of its groups and transaction sets.
+pub fn write_interchange(interchange: &Interchange) -> EdiResult<String> {
+    write_interchange_with(interchange, &WriterOptions::default())
+}
+
+/// Write one interchange with all of its groups and transaction sets, laid out per `options`.
+pub fn write_interchange_with(
+    interchange: &Interchange,
+    options: &WriterOptions,
+) -> EdiResult<String> {
+    let mut segments = vec![write_isa(interchange.isa.clone())];
+    for group in &interchange.groups {
+        segments.push(write_gs(group.gs.clone()));
+        for transaction in &group.transactions {
+            let edi = clean_contents(transaction.to_edi()?);
+            let transaction_segments = tokenize(&edi);
+            let st = transaction_segments
+                .iter()
+                .position(|s| s.id == "ST")
+                .ok_or_else(|| EdiError::MissingSegment("ST".to_string()))?;
+            let se = transaction_segments
+                .iter()
+                .rposition(|s| s.id == "SE")
+                .unwrap_or(transaction_segments.len() - 1);
+            for segment in &transaction_segments[st..=se] {
+                segments.push(segment.to_edi());
+            }
+        }
+        segments.push(write_ge(group.ge.clone()));
+    }
+    segments.push(write_iea(interchange.iea.clone()));
+
+    options.format(&segments.join(""), &interchange.delimiters)
+}
+
+/// Write several interchanges, one after the other.
+pub fn write_interchanges(interchanges: &[Interchange]) -> EdiResult<String> {
+    write_interchanges_with(interchanges, &WriterOptions::default())
+}
+
+/// Write several interchanges, one after the other, laid out per `options`.
+pub fn write_interchanges_with(
+    interchanges: &[Interchange],
+    options: &WriterOptions,
+) -> EdiResult<String> {
+    let mut result = String::new();
+    for interchange in interchanges {
+        result.push_str(&write_interchange_with(interchange, options)?);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ISA: &str = "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230501*1200*^*00501*000000001*0*T*:~";
+
+    fn multi_group_file() -> String {
+        format!(
+            "{}\
+GS*HP*SENDER*RECEIVER*20230501*1200*1*X*005010X221A1~\
+ST*835*0001~BPR*I*100*C*ACH~TRN*1*12345*1512345678~N1*PR*PAYER~N1*PE*PAYEE*XX*1234567890~\
+LX*1~CLP*CLAIM1*1*100*100**MC*1~SVC*HC:99213*100*100**1~SE*9*0001~\
+ST*835*0002~BPR*I*50*C*ACH~TRN*1*12346*1512345678~N1*PR*PAYER~N1*PE*PAYEE*XX*1234567890~\
+LX*1~CLP*CLAIM2*1*50*50**MC*1~SE*8*0002~\
+GE*2*1~\
+GS*FA*SENDER*RECEIVER*20230501*1200*2*X*005010X231A1~\
+ST*999*0003*005010X231A1~AK1*HC*17*005010X222A1~AK2*837*0001*005010X222A1~IK5*A~AK9*A*1*1*1~SE*6*0003~\
+GE*1*2~\
+IEA*2*000000001~",
+            ISA
+        )
+    }
+
+    #[test]
+    fn test_is_multi_transaction() {
+        assert!(is_multi_transaction(&multi_group_file()));
+        let single = format!(
+            "{}GS*FA*S*R*20230501*1200*2*X*005010X231A1~ST*999*0003~SE*2*0003~GE*1*2~IEA*1*000000001~",
+            ISA
+        );
+        assert!(!is_multi_transaction(&single));
+    }
+
+    #[test]
+    fn test_get_interchanges_multi_group() {
+        let interchanges = get_interchanges(&multi_group_file()).unwrap();
+        assert_eq!(interchanges.len(), 1);
+        let interchange = &interchanges[0];
+        assert_eq!(interchange.isa.control_number, "000000001");
+        assert_eq!(interchange.groups.len(), 2);
+        assert_eq!(interchange.groups[0].transactions.len(), 2);
+        assert_eq!(interchange.groups[0].ge.number_of_transitions, "2");
+        assert_eq!(interchange.groups[1].gs.group_control_number, "2");
+
+        match &interchange.groups[0].transactions[1] {
+            AnyTransaction::Edi835(edi835) => {
+                assert_eq!(edi835.table1.table1.trn_segments.reference_id, "12346");
+            }
+            other => panic!("expected 835, got {}", other.transaction_set_id()),
+        }
+        assert_eq!(
+            interchange.groups[1].transactions[0].transaction_set_id(),
+            "999"
+        );
+    }
+
+    #[test]
+    fn test_get_interchanges_multiple_interchanges() {
+        let single = format!(
+            "{}GS*FA*S*R*20230501*1200*2*X*005010X231A1~ST*999*0003~AK1*HC*17~AK9*A*1*1*1~SE*4*0003~GE*1*2~IEA*1*000000001~",
+            ISA
+        );
+        let contents = format!("{}\n{}", single, single.replace("000000001", "000000002"));
+        let interchanges = get_interchanges(&contents).unwrap();
+        assert_eq!(interchanges.len(), 2);
+        assert_eq!(interchanges[1].iea.interchange_control_number, "000000002");
+    }
+
+    #[test]
+    fn test_get_interchanges_diagnostics() {
+        let contents = multi_group_file()
+            .replace(
+                "CLP*CLAIM2*1*50*50**MC*1~SE*8*0002~",
+                "CLP*CLAIM2*1*5O*50**MC*1~SE*8*0009~",
+            )
+            .replace("GE*1*2~", "GE*3*2~");
+        let interchanges = get_interchanges(&contents).unwrap();
+        let interchange = &interchanges[0];
+
+        let envelope: Vec<(DiagnosticCode, &str)> = interchange
+            .diagnostics
+            .iter()
+            .map(|d| (d.code, d.loop_path.as_str()))
+            .collect();
+        assert_eq!(envelope, vec![(DiagnosticCode::CountMismatch, "GE01")]);
+
+        assert!(interchange.groups[0].transactions[0]
+            .diagnostics()
+            .is_empty());
+        let second = interchange.groups[0].transactions[1].diagnostics();
+        let found: Vec<(DiagnosticCode, &str, Option<usize>)> = second
+            .iter()
+            .map(|d| (d.code, d.loop_path.as_str(), d.segment_index))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (DiagnosticCode::ControlNumberMismatch, "SE02", Some(18)),
+                (DiagnosticCode::InvalidDecimal, "2000/2100/CLP03", Some(17)),
+            ]
+        );
+        let clp = second.iter().nth(1).and_then(|d| d.offset).unwrap();
+        assert!(clean_contents(contents.clone())[clp..].starts_with("CLP*CLAIM2"));
+    }
+
+    #[test]
+    fn test_get_interchanges_error_location() {
+        let contents = multi_group_file()
+            .replace("ST*999*0003", "ST*850*0003")
+            .replace('~', "~\n");
+        let error = get_interchanges(&contents).unwrap_err();
+        assert!(matches!(error.kind(), EdiError::UnsupportedFormat(_)));
+        let location = error.location().unwrap();
+        assert_eq!(location.segment_id, "ST");
+        assert_eq!(location.segment_index, 21);
+        assert_eq!(location.line, 22);
+        assert!(contents[location.offset..].starts_with("ST*850"));
+    }
+
+    #[test]
+    fn test_get_interchanges_strict() {
+        let contents = multi_group_file().replace("~LX*1~CLP*CLAIM2", "~ZZZ*1~LX*1~CLP*CLAIM2");
+        let interchanges = get_interchanges(&contents).unwrap();
+        let second = &interchanges[0].groups[0].transactions[1];
+        assert_eq!(second.unrecognized().len(), 1);
+        let written = clean_contents(write_interchanges(&interchanges).unwrap());
+        assert!(written.contains("N1*PE*PAYEE*XX*1234567890~ZZZ*1~LX*1~CLP*CLAIM2"));
+
+        let error = get_interchanges_strict(&contents).unwrap_err();
+        let location = error.location().unwrap();
+        assert_eq!(location.segment_id, "ZZZ");
+        assert!(contents[location.offset..].starts_with("ZZZ*1~"));
+        assert!(get_interchanges_strict(&multi_group_file()).is_ok());
+    }
+
+    #[test]
+    fn test_parse_any() {
+        let single = format!(
+            "{}GS*FA*S*R*20230501*1200*2*X*005010X231A1~ST*999*0003*005010X231A1~AK1*HC*17*005010X222A1~AK9*A*1*1*1~SE*4*0003~GE*1*2~IEA*1*000000001~",
+            ISA
+        );
+        let transaction = AnyTransaction::parse_any(&single).unwrap();
+        assert_eq!(transaction.transaction_set_id(), "999");
+        assert!(transaction
+            .to_edi()
+            .unwrap()
+            .contains("AK1*HC*17*005010X222A1~"));
+        let json = serde_json::to_string(&transaction).unwrap();
+        assert!(json.starts_with("{\"transaction_set\":\"999\",\"interchange_header\""));
+        assert_eq!(
+            serde_json::from_str::<AnyTransaction>(&json).unwrap(),
+            transaction
+        );
+
+        let unsupported = single.replace("ST*999*", "ST*850*");
+        assert!(matches!(
+            AnyTransaction::parse_any(&unsupported),
+            Err(EdiError::UnsupportedFormat(_))
+        ));
+        assert!(AnyTransaction::parse_any(ISA).is_err());
+    }
+
+    #[test]
+    fn test_get_interchanges_without_isa() {
+        assert!(get_interchanges("ST*835*0001~SE*2*0001~").is_err());
+    }
+
+    #[test]
+    fn test_write_interchanges_round_trip() {
+        let interchanges = get_interchanges(&multi_group_file()).unwrap();
+        let written = write_interchanges(&interchanges).unwrap();
+        let segments: Vec<&str> = written.lines().collect();
+
+        assert!(segments[0].starts_with("ISA*00*"));
+        assert_eq!(segments.iter().filter(|s| s.starts_with("ST*")).count(), 3);
+        assert_eq!(segments.iter().filter(|s| s.starts_with("GS*")).count(), 2);
+        assert!(written.contains("CLP*CLAIM2*1*50*50**MC*1~"));
+        assert!(written.contains("GE*2*1~\nGS*FA*"));
+        assert!(written.ends_with("IEA*2*000000001~\n"));
+
+        let reparsed = get_interchanges(&written).unwrap();
+        assert_eq!(reparsed, interchanges);
+    }
+}
@@ -1,6 +1,7 @@
 // Library crate: all public items are part of the API even if not used by the binary.
 #![allow(dead_code)]
 
+pub mod document;
 pub mod edi270;
 pub mod edi271;
 pub mod edi276;
@@ -11,14 +12,30 @@ pub mod edi834;
 pub mod edi835;
 pub mod edi837;
 pub mod edi999;
+pub mod envelope;
 pub mod error;
 pub mod helper;
+pub mod interchange;
 pub mod segments;
+pub mod stream;
 pub mod transaction_processor;
+pub mod validation;
 
 // Re-export commonly used items
-pub use error::{EdiError, EdiResult};
-pub use transaction_processor::TransactionSet;
+pub use document::{Document, DocumentContent, SCHEMA_VERSION};
+pub use envelope::EnvelopeBuilder;
+pub use error::{EdiError, EdiResult, SourceLocation};
+pub use helper::control_numbers::{ControlNumberRegistry, PartnerCounters};
+pub use helper::diagnostics::{Diagnostic, DiagnosticCode, Diagnostics, Severity};
+pub use helper::element_dictionary::{DataType, ElementDef};
+pub use helper::syntax_rules::SyntaxRule;
+pub use helper::unrecognized::{UnrecognizedSegment, UnrecognizedSegments};
+pub use helper::writer_options::WriterOptions;
+pub use interchange::AnyTransaction;
+pub use transaction_processor::{
+    detect_implementation_guide, detect_transaction_set, TransactionSet,
+};
+pub use validation::code_sets::{CodeDescription, CodeEntry, CodeList, CodeSets};
 
 // Re-export transaction set controllers
 pub use edi270::controller::Edi270;
@@ -32,14 +49,15 @@ pub use edi837::controller::{Edi837, Edi837D, Edi837I, Edi837P, Edi837Subtype};
 pub use edi999::controller::Edi999;
 
 // Re-export transaction set functions
-pub use edi270::controller::{get_270, write_270};
-pub use edi271::controller::{get_271, write_271};
-pub use edi276::controller::{get_276, write_276};
-pub use edi277::controller::{get_277, write_277};
-pub use edi278::controller::{get_278, write_278};
-pub use edi834::controller::{get_834, write_834};
-pub use edi835::controller::{get_835, write_835};
+pub use edi270::controller::{get_270, write_270, write_270_with};
+pub use edi271::controller::{get_271, write_271, write_271_with};
+pub use edi276::controller::{get_276, write_276, write_276_with};
+pub use edi277::controller::{get_277, write_277, write_277_with};
+pub use edi278::controller::{get_278, write_278, write_278_with};
+pub use edi834::controller::{get_834, write_834, write_834_with};
+pub use edi835::controller::{get_835, write_835, write_835_with};
 pub use edi837::controller::{
-    get_837, get_837d, get_837i, get_837p, write_837, write_837d, write_837i, write_837p,
+    get_837, get_837d, get_837i, get_837p, write_837, write_837_with, write_837d, write_837i,
+    write_837p,
 };
-pub use edi999::controller::{get_999, write_999};
+pub use edi999::controller::{get_999, write_999, write_999_with};
@@ -1,26 +1,31 @@
 // Many public functions/structs exist for API completeness but aren't called from the CLI binary.
 #![allow(dead_code)]
 
+use crate::document::Document;
 /**
  * EDI Parser and Processor for Healthcare X12 Formats
  *
  * Supports 835, 999, 270/271, 276/277, 837, 278, 820, and 834.
  */
-use crate::edi270::controller::{get_270, write_270, Edi270};
-use crate::edi271::controller::{get_271, write_271, Edi271};
-use crate::edi276::controller::{get_276, write_276, Edi276};
-use crate::edi277::controller::{get_277, write_277, Edi277};
-use crate::edi278::controller::{write_278, Edi278};
-use crate::edi820::controller::{write_820, Edi820};
-use crate::edi834::controller::{write_834, Edi834};
-use crate::edi835::controller::{get_835, write_835, Edi835};
-use crate::edi837::controller::{get_837, write_837, Edi837};
-use crate::edi999::controller::{get_999, write_999, Edi999};
+use crate::error::{EdiError, EdiResult};
+use crate::helper::control_numbers::ControlNumberRegistry;
+use crate::helper::delimiters::Delimiters;
+use crate::helper::diagnostics::{collect_diagnostics, Severity};
 use crate::helper::helper::{
-    clean_contents, get_file_contents, process_args, set_logger, write_to_file,
+    clean_contents, get_file_contents, process_args, set_logger, write_to_file, Args, USAGE,
 };
-use crate::transaction_processor::TransactionSet;
+use crate::helper::source_map::SourceMap;
+use crate::helper::writer_options::WriterOptions;
+use crate::interchange::{
+    get_interchanges, get_interchanges_strict, is_multi_transaction, write_interchanges_with,
+    AnyTransaction, Interchange,
+};
+use crate::stream::EdiStream;
+use crate::transaction_processor::detect_implementation_guide;
+use crate::validation::code_sets::CodeSets;
+use crate::validation::{SnipLevel, Validator};
 
+mod document;
 mod edi270;
 mod edi271;
 mod edi276;
@@ -31,231 +36,248 @@ mod edi834;
 mod edi835;
 mod edi837;
 mod edi999;
+mod envelope;
 mod error;
 mod helper;
+mod interchange;
 mod segments;
+mod stream;
 mod transaction_processor;
+mod validation;
 
 use log::{info, warn};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+
+/// Parse a single-transaction file; in strict mode fail on the first segment
+/// the parser did not recognize, located in `contents`.
+fn parse_transaction(contents: &str, strict: bool) -> EdiResult<AnyTransaction> {
+    let transaction = AnyTransaction::parse_any(contents)?;
+    info!("File is {}", transaction.transaction_set_id());
+    if strict {
+        transaction
+            .unrecognized()
+            .check_strict()
+            .map_err(|e| SourceMap::new(contents).relocate(e))?;
+    }
+    Ok(transaction)
+}
 
-/// Detect the ST transaction set code from raw EDI content
-fn detect_st_code(contents: &str) -> Option<&str> {
-    // Look for ST*XXX* pattern
-    let st_pos = contents.find("ST*")?;
-    let after_st = &contents[st_pos + 3..];
-    let end = after_st.find('*')?;
-    Some(&after_st[..end])
+/// Parse a file with several interchanges, groups or transaction sets.
+fn parse_interchanges(contents: &str, strict: bool) -> EdiResult<Vec<Interchange>> {
+    info!("File has multiple interchanges, groups or transaction sets");
+    if strict {
+        get_interchanges_strict(contents)
+    } else {
+        get_interchanges(contents)
+    }
 }
 
-/// Read path: parse raw EDI → serialize to JSON → write file
-fn read_edi(contents: &str, output_file: String) {
-    let serialize_and_write = |json: Result<String, serde_json::Error>| match json {
-        Ok(s) => write_to_file(s, output_file.clone()),
-        Err(e) => warn!("Failed to serialize: {}", e),
+/// Read path: parse raw EDI → serialize to JSON → write file.
+/// With code sets, the descriptions of the codes found are added to the JSON.
+fn read_edi(
+    contents: &str,
+    output_file: String,
+    strict: bool,
+    code_sets: Option<&CodeSets>,
+) -> EdiResult<()> {
+    let mut document = if is_multi_transaction(contents) {
+        Document::interchanges(parse_interchanges(contents, strict)?)
+    } else {
+        let guide = detect_implementation_guide(contents).unwrap_or_default();
+        Document::transaction(parse_transaction(contents, strict)?, &guide)
     };
+    if let Some(code_sets) = code_sets {
+        document.code_descriptions = code_sets.describe_edi(contents);
+    }
+    write_to_file(document.to_json()?, output_file)
+}
 
-    match detect_st_code(contents) {
-        Some("835") => {
-            info!("File is 835");
-            serialize_and_write(serde_json::to_string(&get_835(contents)));
-        }
-        Some("999") => {
-            info!("File is 999");
-            serialize_and_write(serde_json::to_string(&get_999(contents).0));
-        }
-        Some("270") => {
-            info!("File is 270");
-            match get_270(contents) {
-                Ok((edi, _)) => serialize_and_write(serde_json::to_string(&edi)),
-                Err(e) => warn!("Error processing 270: {:?}", e),
-            }
-        }
-        Some("271") => {
-            info!("File is 271");
-            match get_271(contents) {
-                Ok((edi, _)) => serialize_and_write(serde_json::to_string(&edi)),
-                Err(e) => warn!("Error processing 271: {:?}", e),
-            }
-        }
-        Some("276") => {
-            info!("File is 276");
-            match get_276(contents) {
-                Ok(edi) => serialize_and_write(serde_json::to_string(&edi)),
-                Err(e) => warn!("Error processing 276: {:?}", e),
-            }
-        }
-        Some("277") => {
-            info!("File is 277");
-            match get_277(contents) {
-                Ok(edi) => serialize_and_write(serde_json::to_string(&edi)),
-                Err(e) => warn!("Error processing 277: {:?}", e),
-            }
-        }
-        Some("837") => {
-            info!("File is 837");
-            match get_837(contents) {
-                Ok(edi) => serialize_and_write(serde_json::to_string(&edi)),
-                Err(e) => warn!("Error processing 837: {:?}", e),
-            }
-        }
-        Some("278") => {
-            info!("File is 278");
-            match Edi278::parse(contents.to_string()) {
-                Ok((edi, _)) => serialize_and_write(serde_json::to_string(&edi)),
-                Err(e) => warn!("Error processing 278: {:?}", e),
-            }
-        }
-        Some("820") => {
-            info!("File is 820");
-            match Edi820::parse(contents.to_string()) {
-                Ok((edi, _)) => serialize_and_write(serde_json::to_string(&edi)),
-                Err(e) => warn!("Error processing 820: {:?}", e),
-            }
-        }
-        Some("834") => {
-            info!("File is 834");
-            match Edi834::parse(contents.to_string()) {
-                Ok((edi, _)) => serialize_and_write(serde_json::to_string(&edi)),
-                Err(e) => warn!("Error processing 834: {:?}", e),
-            }
-        }
-        Some(code) => warn!("Unsupported transaction set: {}", code),
-        None => warn!("Could not detect transaction set type. Expected ST*XXX* segment."),
+/// Write path from JSON: deserialize JSON → generate EDI.
+/// The JSON must say what it holds: a tagged document (`schema_version`), a
+/// transaction set with its `transaction_set` tag, or an array of interchanges.
+fn edi_from_json(contents: &str, options: &WriterOptions) -> EdiResult<String> {
+    let value: serde_json::Value = serde_json::from_str(contents)?;
+    if Document::is_document(&value) {
+        let document = Document::from_value(value)?;
+        info!("Writing {}", document.describe());
+        return document.to_edi_with(options);
+    }
+
+    if value.is_array() {
+        info!("Writing interchanges");
+        let interchanges: Vec<Interchange> = serde_json::from_value(value)?;
+        return write_interchanges_with(&interchanges, options);
+    }
+
+    if value.get("transaction_set").is_some() {
+        let transaction: AnyTransaction = serde_json::from_value(value)?;
+        info!("Writing {}", transaction.transaction_set_id());
+        return transaction.to_edi_with(options);
     }
+
+    Err(EdiError::MissingField(
+        "schema_version or transaction_set (JSON does not say which transaction set it holds; \
+read the EDI again to get tagged JSON)"
+            .to_string(),
+    ))
 }
 
-/// Write path from JSON: deserialize JSON → generate EDI → write file
-fn write_from_json(contents: &str, output_file: String) {
-    // Helper: deserialize, convert, write
-    fn try_write<T, F>(contents: &str, output_file: String, convert: F)
-    where
-        T: serde::de::DeserializeOwned,
-        F: FnOnce(T) -> String,
-    {
-        match serde_json::from_str::<T>(contents) {
-            Ok(v) => write_to_file(convert(v), output_file),
-            Err(e) => warn!("Failed to parse JSON: {}", e),
-        }
+/// Write path from raw EDI: parse → regenerate
+fn edi_from_edi(contents: &str, strict: bool, options: &WriterOptions) -> EdiResult<String> {
+    if is_multi_transaction(contents) {
+        write_interchanges_with(&parse_interchanges(contents, strict)?, options)
+    } else {
+        parse_transaction(contents, strict)?.to_edi_with(options)
     }
+}
 
-    // Detect format from JSON content
-    if contents.contains("\"transaction_set_id\":\"835\"") {
-        info!("Writing 835");
-        try_write::<Edi835, _>(contents, output_file, |_| write_835(contents.to_string()));
-    } else if contents.contains("\"transaction_set_id\":\"999\"") {
-        info!("Writing 999");
-        try_write::<Edi999, _>(contents, output_file, |edi| write_999(&edi));
-    } else if contents.contains("\"transaction_set_id\":\"270\"") {
-        info!("Writing 270");
-        try_write::<Edi270, _>(contents, output_file, |edi| write_270(&edi));
-    } else if contents.contains("\"transaction_set_id\":\"271\"") {
-        info!("Writing 271");
-        try_write::<Edi271, _>(contents, output_file, |edi| write_271(&edi));
-    } else if contents.contains("\"st01_transaction_set_identifier_code\":\"276\"") {
-        info!("Writing 276");
-        try_write::<Edi276, _>(contents, output_file, |edi| write_276(&edi));
-    } else if contents.contains("\"st01_transaction_set_identifier_code\":\"277\"") {
-        info!("Writing 277");
-        try_write::<Edi277, _>(contents, output_file, |edi| write_277(&edi));
-    } else if contents.contains("005010X222")
-        || contents.contains("005010X223")
-        || contents.contains("005010X224")
-    {
-        info!("Writing 837");
-        match serde_json::from_str::<Edi837>(contents) {
-            Ok(edi) => match write_837(&edi) {
-                Ok(new_edi) => write_to_file(new_edi, output_file),
-                Err(e) => warn!("Error writing 837: {:?}", e),
-            },
-            Err(e) => warn!("Failed to parse JSON: {}", e),
-        }
-    } else if contents.contains("\"transaction_set_id\":\"278\"") {
-        info!("Writing 278");
-        try_write::<Edi278, _>(contents, output_file, |edi| write_278(&edi));
-    } else if contents.contains("\"transaction_set_id\":\"820\"") {
-        info!("Writing 820");
-        try_write::<Edi820, _>(contents, output_file, |edi| write_820(&edi));
-    } else if contents.contains("\"transaction_set_id\":\"834\"") {
-        info!("Writing 834");
-        try_write::<Edi834, _>(contents, output_file, |edi| write_834(&edi));
+/// Validation: parse raw EDI → check the selected SNIP levels → log findings.
+/// With code sets, codes are also checked against their lists.
+/// Fails when any finding is an error, so the run exits with a non-zero status.
+fn validate_edi(
+    contents: &str,
+    levels: &[SnipLevel],
+    code_sets: Option<&CodeSets>,
+) -> EdiResult<()> {
+    let mut validator = Validator::default();
+    if let Some(code_sets) = code_sets {
+        validator = validator.with_code_sets(code_sets.clone());
+    }
+    let findings = if is_multi_transaction(contents) {
+        validator.validate_interchanges(contents, levels)?
     } else {
-        warn!("Unknown JSON format for writing");
+        validator.validate_edi(contents, levels)?
+    };
+    findings.log();
+    info!("SNIP validation found {} problems", findings.len());
+    if findings.has_errors() {
+        let errors = findings
+            .iter()
+            .filter(|finding| finding.diagnostic.severity == Severity::Error)
+            .count();
+        return Err(EdiError::ValidationError(format!(
+            "SNIP validation found {} errors",
+            errors
+        )));
     }
+    Ok(())
 }
 
-/// Write path from raw EDI: parse → regenerate → write file
-fn write_from_edi(contents: &str, output_file: String) {
-    match detect_st_code(contents) {
-        Some("835") => {
-            info!("Writing 835 from raw EDI");
-            let edi = get_835(contents);
-            match serde_json::to_string(&edi) {
-                Ok(json) => write_to_file(write_835(json), output_file),
-                Err(e) => warn!("Failed to serialize: {}", e),
-            }
-        }
-        Some("999") => {
-            info!("Writing 999 from raw EDI");
-            let (edi, _) = get_999(contents);
-            write_to_file(write_999(&edi), output_file);
-        }
-        Some("270") => {
-            info!("Writing 270 from raw EDI");
-            match get_270(contents) {
-                Ok((edi, _)) => write_to_file(write_270(&edi), output_file),
-                Err(e) => warn!("Error processing 270: {:?}", e),
-            }
-        }
-        Some("271") => {
-            info!("Writing 271 from raw EDI");
-            match get_271(contents) {
-                Ok((edi, _)) => write_to_file(write_271(&edi), output_file),
-                Err(e) => warn!("Error processing 271: {:?}", e),
+/// Streaming read path: parse one member or claim at a time → write one JSON object per line.
+/// The file is never loaded as a whole, so the size limit of `get_file_contents` does not apply.
+/// Items that fail to parse are logged and skipped, and the run fails once the
+/// rest is written; an I/O error stops it at once.
+fn stream_edi(file_path: &str, output_file: &str) -> EdiResult<()> {
+    let input = File::open(file_path)?;
+    let mut writer = BufWriter::new(File::create(output_file)?);
+
+    let mut count = 0;
+    let mut failed = 0;
+    for item in EdiStream::new(BufReader::new(input)) {
+        let line = match item.and_then(|item| Ok(serde_json::to_string(&item)?)) {
+            Ok(line) => line,
+            Err(e) if matches!(e.kind(), EdiError::IoError(_)) => {
+                writer.flush()?;
+                return Err(e);
             }
-        }
-        Some("837") => {
-            info!("Writing 837 from raw EDI");
-            match get_837(contents) {
-                Ok(edi) => match write_837(&edi) {
-                    Ok(new_edi) => write_to_file(new_edi, output_file),
-                    Err(e) => warn!("Error writing 837: {:?}", e),
-                },
-                Err(e) => warn!("Error processing 837: {:?}", e),
+            Err(e) => {
+                warn!("Error streaming: {}", e);
+                failed += 1;
+                continue;
             }
-        }
-        Some(code) => warn!("Raw EDI write not supported for transaction set: {}", code),
-        None => warn!("Could not detect transaction set type"),
+        };
+        writeln!(writer, "{}", line)?;
+        count += 1;
+    }
+    writer.flush()?;
+    info!("Streamed {} items to {:?}", count, output_file);
+    if failed > 0 {
+        return Err(EdiError::ParseError(format!(
+            "{} of {} streamed items failed and were skipped",
+            failed,
+            count + failed
+        )));
     }
+    Ok(())
 }
 
-fn main() {
-    set_logger();
-    info!("Starting EDI Parser");
+/// Run the operation selected by the command line arguments.
+fn run(args: Args) -> EdiResult<()> {
+    if args.stream && args.operation == "read" {
+        info!("Streaming read operation");
+        return stream_edi(&args.file_path, &args.output_file);
+    }
 
-    let args = process_args();
-    let contents = get_file_contents(args.clone());
-    let contents = clean_contents(contents);
+    let raw_contents = get_file_contents(&args)?;
+    let contents = clean_contents(raw_contents.clone());
+    let code_sets = match &args.code_sets {
+        Some(dir) => Some(CodeSets::load_dir(dir)?),
+        None => None,
+    };
 
-    // Validate X12 envelope if this is raw EDI (not JSON)
+    // Report envelope and value problems if this is raw EDI (not JSON)
     if !args.is_json && !contents.starts_with('{') {
-        crate::helper::envelope_validation::validate_raw_envelope(&contents);
+        collect_diagnostics(&contents, "", &Delimiters::detect(&raw_contents)).log();
+        if !args.snip_levels.is_empty() {
+            validate_edi(&raw_contents, &args.snip_levels, code_sets.as_ref())?;
+        }
     }
 
     match args.operation.as_str() {
         "write" => {
             info!("Write EDI Operation");
-            if args.is_json {
+            let options = WriterOptions {
+                sync_envelope: args.sync_envelope,
+                renumber_hierarchy: args.renumber_hl,
+                strict: args.strict,
+                ..Default::default()
+            };
+            let new_edi = if args.is_json {
                 info!("Input is JSON");
-                write_from_json(&contents, args.output_file);
+                edi_from_json(&contents, &options)?
             } else {
                 info!("Input is raw EDI");
-                write_from_edi(&contents, args.output_file);
-            }
+                edi_from_edi(&raw_contents, args.strict, &options)?
+            };
+            let new_edi = match &args.control_numbers {
+                Some(registry) => {
+                    info!("Assigning control numbers from {:?}", registry);
+                    ControlNumberRegistry::open(registry)?.stamp(&new_edi, &options)?
+                }
+                None => new_edi,
+            };
+            write_to_file(new_edi, args.output_file)
         }
         "read" => {
             info!("Read EDI Operation");
-            read_edi(&contents, args.output_file);
+            read_edi(
+                &raw_contents,
+                args.output_file,
+                args.strict,
+                code_sets.as_ref(),
+            )
+        }
+        _ => Err(EdiError::NotImplemented(format!(
+            "Unknown operation: {}",
+            args.operation
+        ))),
+    }
+}
+
+fn main() {
+    set_logger();
+    info!("Starting EDI Parser");
+
+    let result = process_args().and_then(|args| {
+        if args.help {
+            println!("{}", USAGE);
+            return Ok(());
         }
-        _ => warn!("Unknown operation: {}", args.operation),
+        run(args)
+    });
+    if let Err(e) = result {
+        warn!("{}", e);
+        std::process::exit(1);
     }
 }
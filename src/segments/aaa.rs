@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{id, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -10,6 +12,12 @@ pub struct AAA {
     pub aaa04_follow_up_action_code: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from AAA01
+pub const ELEMENTS: &[ElementDef] = &[id(1, 1), id(2, 2), id(2, 2), id(1, 1)];
+
 pub fn get_aaa(aaa_content: String) -> AAA {
     let aaa_parts: Vec<&str> = aaa_content.split("*").collect();
 
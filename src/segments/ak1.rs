@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, id, n0, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -9,6 +11,12 @@ pub struct AK1 {
     pub ak03_ver_release_id_code: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from AK101
+pub const ELEMENTS: &[ElementDef] = &[id(2, 2), n0(1, 9), an(1, 12)];
+
 pub fn get_ak1(ak1_content: String) -> AK1 {
     let ak1_parts: Vec<&str> = ak1_content.split("*").collect();
     AK1 {
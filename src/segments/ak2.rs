@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, id, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -10,6 +12,12 @@ pub struct AK2 {
     pub ak203_implementation_convention_reference: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from AK201
+pub const ELEMENTS: &[ElementDef] = &[id(3, 3), an(4, 9), an(1, 35)];
+
 pub fn get_ak2(ak2_content: String) -> AK2 {
     let ak2_parts: Vec<&str> = ak2_content.split("*").collect();
 
@@ -1,4 +1,6 @@
 use crate::helper::edihelper::{build_segment, get_element};
+use crate::helper::element_dictionary::{id, n0, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -15,6 +17,22 @@ pub struct AK9 {
     pub ak909_fn_group_err_code: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from AK901
+pub const ELEMENTS: &[ElementDef] = &[
+    id(1, 1),
+    n0(1, 6),
+    n0(1, 6),
+    n0(1, 6),
+    id(1, 3),
+    id(1, 3),
+    id(1, 3),
+    id(1, 3),
+    id(1, 3),
+];
+
 pub fn get_ak9(ak9_content: String) -> AK9 {
     let ak9_parts: Vec<&str> = ak9_content.split("*").collect();
     let ak905_fn_group_err_code = String::new();
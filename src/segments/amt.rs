@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{id, r, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 // EDI 835 segment with Amount Qualifier Code and Service Line Allowed Amount
@@ -9,6 +11,12 @@ pub struct AMT {
     pub amt02_service_line_allowed_amount: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from AMT01
+pub const ELEMENTS: &[ElementDef] = &[id(1, 3), r(1, 18), id(1, 1)];
+
 pub fn get_amt(amt_content: String) -> AMT {
     let amt_parts: Vec<&str> = amt_content.split("*").collect();
     AMT {
@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, dt, id, tm, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -14,6 +16,21 @@ pub struct BGN {
     pub bgn08_action_code: String,
 }
 
+/// X12 syntax notes: C0504
+pub const SYNTAX_RULES: &[SyntaxRule] = &[SyntaxRule::Conditional(5, &[4])];
+
+/// Element dictionary, from BGN01
+pub const ELEMENTS: &[ElementDef] = &[
+    id(2, 2),
+    an(1, 50),
+    dt(8, 8),
+    tm(4, 8),
+    id(2, 2),
+    an(1, 50),
+    id(2, 2),
+    id(1, 2),
+];
+
 pub fn get_bgn(bgn_content: String) -> BGN {
     let bgn_parts: Vec<&str> = bgn_content.split("*").collect();
 
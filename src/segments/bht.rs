@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, dt, id, tm, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -12,6 +14,12 @@ pub struct BHT {
     pub bht06_transaction_type_code: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from BHT01
+pub const ELEMENTS: &[ElementDef] = &[id(4, 4), id(2, 2), an(1, 50), dt(8, 8), tm(4, 8), id(2, 2)];
+
 pub fn get_bht(bht_content: String) -> BHT {
     let bht_parts: Vec<&str> = bht_content.split("*").collect();
 
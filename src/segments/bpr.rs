@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, dt, id, r, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 // EDI 835 BPR segment
@@ -27,6 +29,41 @@ pub struct BPR {
 // if BPR04 is ACH, BOP or FWT then BPR06-BPR10,BPR12-BPR15 are required
 // BPR11 is require when BPR10 is present
 
+/// X12 syntax notes: P0607, C0809, P1213, C1415, P1819, C2021
+pub const SYNTAX_RULES: &[SyntaxRule] = &[
+    SyntaxRule::Paired(&[6, 7]),
+    SyntaxRule::Conditional(8, &[9]),
+    SyntaxRule::Paired(&[12, 13]),
+    SyntaxRule::Conditional(14, &[15]),
+    SyntaxRule::Paired(&[18, 19]),
+    SyntaxRule::Conditional(20, &[21]),
+];
+
+/// Element dictionary, from BPR01
+pub const ELEMENTS: &[ElementDef] = &[
+    id(1, 2),
+    r(1, 18),
+    id(1, 1),
+    id(3, 3),
+    id(1, 10),
+    id(2, 2),
+    an(3, 12),
+    id(1, 3),
+    an(1, 35),
+    an(10, 10),
+    an(9, 9),
+    id(2, 2),
+    an(3, 12),
+    id(1, 3),
+    an(1, 35),
+    dt(8, 8),
+    id(1, 3),
+    id(2, 2),
+    an(3, 12),
+    id(1, 3),
+    an(1, 35),
+];
+
 pub fn get_bpr(bpr_content: String) -> BPR {
     let bpr_parts: Vec<&str> = bpr_content.split("*").collect();
     let mut bpr05_payment_format_code: String = "".to_string();
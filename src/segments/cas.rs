@@ -1,4 +1,6 @@
 use crate::helper::edihelper::{build_segment, get_element};
+use crate::helper::element_dictionary::{id, r, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -25,6 +27,48 @@ pub struct CAS {
     pub cas19_adjustment_qty: String,
 }
 
+/// X12 syntax notes: L050607, C0605, C0705, L080910, C0908, C1008, L111213, C1211, C1311, L141516, C1514, C1614, L171819, C1817, C1917
+pub const SYNTAX_RULES: &[SyntaxRule] = &[
+    SyntaxRule::ListConditional(5, &[6, 7]),
+    SyntaxRule::Conditional(6, &[5]),
+    SyntaxRule::Conditional(7, &[5]),
+    SyntaxRule::ListConditional(8, &[9, 10]),
+    SyntaxRule::Conditional(9, &[8]),
+    SyntaxRule::Conditional(10, &[8]),
+    SyntaxRule::ListConditional(11, &[12, 13]),
+    SyntaxRule::Conditional(12, &[11]),
+    SyntaxRule::Conditional(13, &[11]),
+    SyntaxRule::ListConditional(14, &[15, 16]),
+    SyntaxRule::Conditional(15, &[14]),
+    SyntaxRule::Conditional(16, &[14]),
+    SyntaxRule::ListConditional(17, &[18, 19]),
+    SyntaxRule::Conditional(18, &[17]),
+    SyntaxRule::Conditional(19, &[17]),
+];
+
+/// Element dictionary, from CAS01
+pub const ELEMENTS: &[ElementDef] = &[
+    id(1, 2),
+    id(1, 5),
+    r(1, 18),
+    r(1, 15),
+    id(1, 5),
+    r(1, 18),
+    r(1, 15),
+    id(1, 5),
+    r(1, 18),
+    r(1, 15),
+    id(1, 5),
+    r(1, 18),
+    r(1, 15),
+    id(1, 5),
+    r(1, 18),
+    r(1, 15),
+    id(1, 5),
+    r(1, 18),
+    r(1, 15),
+];
+
 pub fn get_cas(cas_content: String) -> CAS {
     let cas_parts: Vec<&str> = cas_content.split("*").collect();
     let mut cas04_adjustment_qty: String = "".to_string();
@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{id, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -9,6 +11,12 @@ pub struct CL1 {
     pub cl103_patient_status_code: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from CL101
+pub const ELEMENTS: &[ElementDef] = &[id(1, 1), id(1, 1), id(1, 2), id(1, 1)];
+
 pub fn get_cl1(cl1_content: String) -> CL1 {
     let cl1_parts: Vec<&str> = cl1_content.split("*").collect();
 
@@ -1,4 +1,6 @@
 use crate::helper::edihelper::{build_segment, get_element};
+use crate::helper::element_dictionary::{an, id, r, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -25,6 +27,27 @@ pub struct CLP {
 // CLP12 same as CLP11
 // CLP13 is optional, when a discharge fraction was applied
 
+/// X12 syntax notes: C0809
+pub const SYNTAX_RULES: &[SyntaxRule] = &[SyntaxRule::Conditional(8, &[9])];
+
+/// Element dictionary, from CLP01
+pub const ELEMENTS: &[ElementDef] = &[
+    an(1, 38),
+    id(1, 2),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    id(1, 2),
+    an(1, 50),
+    id(1, 2),
+    id(1, 1),
+    id(1, 2),
+    id(1, 4),
+    r(1, 15),
+    r(1, 10),
+    id(1, 1),
+];
+
 pub fn get_clp(bpr_content: String) -> CLP {
     let clp_parts: Vec<&str> = bpr_content.split("*").collect();
     let mut clp05_patient_responsibility_amount: String = "".to_string();
@@ -0,0 +1,246 @@
+//! Composite data elements shared across segments
+//!
+//! Composites are read from content normalized by `helper::clean_contents`, so
+//! components are always separated by `:` here regardless of the ISA16 value
+//! used by the trading partner; writers re-emit the original separator through
+//! `Delimiters::restore`.
+
+use crate::helper::edihelper::{build_composite, get_components, get_element};
+use crate::helper::syntax_rules::SyntaxRule;
+use serde::{Deserialize, Serialize};
+
+/// C003 - Composite Medical Procedure Identifier
+/// (SVC01, SVC06, SV101, SV202, SV301, EQ02)
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct C003 {
+    pub c003_01_product_or_service_id_qualifier: String,
+    pub c003_02_product_or_service_id: String,
+    pub c003_03_procedure_modifier: String,
+    pub c003_04_procedure_modifier: String,
+    pub c003_05_procedure_modifier: String,
+    pub c003_06_procedure_modifier: String,
+    pub c003_07_description: String,
+    pub c003_08_product_or_service_id: String,
+}
+
+impl C003 {
+    /// Procedure modifiers 1-4 that are present, in order.
+    pub fn modifiers(&self) -> Vec<&str> {
+        [
+            &self.c003_03_procedure_modifier,
+            &self.c003_04_procedure_modifier,
+            &self.c003_05_procedure_modifier,
+            &self.c003_06_procedure_modifier,
+        ]
+        .into_iter()
+        .filter(|modifier| !modifier.is_empty())
+        .map(|modifier| modifier.as_str())
+        .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        write_c003(self).is_empty()
+    }
+}
+
+/// C003 syntax notes: none
+pub const C003_SYNTAX_RULES: &[SyntaxRule] = &[];
+
+pub fn get_c003(element: &str) -> C003 {
+    let components = get_components(element);
+    C003 {
+        c003_01_product_or_service_id_qualifier: get_element(&components, 0),
+        c003_02_product_or_service_id: get_element(&components, 1),
+        c003_03_procedure_modifier: get_element(&components, 2),
+        c003_04_procedure_modifier: get_element(&components, 3),
+        c003_05_procedure_modifier: get_element(&components, 4),
+        c003_06_procedure_modifier: get_element(&components, 5),
+        c003_07_description: get_element(&components, 6),
+        c003_08_product_or_service_id: get_element(&components, 7),
+    }
+}
+
+pub fn write_c003(c003: &C003) -> String {
+    build_composite(&[
+        &c003.c003_01_product_or_service_id_qualifier,
+        &c003.c003_02_product_or_service_id,
+        &c003.c003_03_procedure_modifier,
+        &c003.c003_04_procedure_modifier,
+        &c003.c003_05_procedure_modifier,
+        &c003.c003_06_procedure_modifier,
+        &c003.c003_07_description,
+        &c003.c003_08_product_or_service_id,
+    ])
+}
+
+/// C022 - Health Care Code Information (HI01-HI12)
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct C022 {
+    pub c022_01_code_list_qualifier_code: String,
+    pub c022_02_industry_code: String,
+    pub c022_03_date_time_period_format_qualifier: String,
+    pub c022_04_date_time_period: String,
+    pub c022_05_monetary_amount: String,
+    pub c022_06_quantity: String,
+    pub c022_07_version_identifier: String,
+    pub c022_08_industry_code: String,
+    pub c022_09_present_on_admission_indicator: String,
+}
+
+impl C022 {
+    pub fn is_empty(&self) -> bool {
+        write_c022(self).is_empty()
+    }
+}
+
+/// C022 syntax notes: P0304, E0708
+pub const C022_SYNTAX_RULES: &[SyntaxRule] =
+    &[SyntaxRule::Paired(&[3, 4]), SyntaxRule::Exclusion(&[7, 8])];
+
+pub fn get_c022(element: &str) -> C022 {
+    let components = get_components(element);
+    C022 {
+        c022_01_code_list_qualifier_code: get_element(&components, 0),
+        c022_02_industry_code: get_element(&components, 1),
+        c022_03_date_time_period_format_qualifier: get_element(&components, 2),
+        c022_04_date_time_period: get_element(&components, 3),
+        c022_05_monetary_amount: get_element(&components, 4),
+        c022_06_quantity: get_element(&components, 5),
+        c022_07_version_identifier: get_element(&components, 6),
+        c022_08_industry_code: get_element(&components, 7),
+        c022_09_present_on_admission_indicator: get_element(&components, 8),
+    }
+}
+
+pub fn write_c022(c022: &C022) -> String {
+    build_composite(&[
+        &c022.c022_01_code_list_qualifier_code,
+        &c022.c022_02_industry_code,
+        &c022.c022_03_date_time_period_format_qualifier,
+        &c022.c022_04_date_time_period,
+        &c022.c022_05_monetary_amount,
+        &c022.c022_06_quantity,
+        &c022.c022_07_version_identifier,
+        &c022.c022_08_industry_code,
+        &c022.c022_09_present_on_admission_indicator,
+    ])
+}
+
+/// C023 - Health Care Service Location Information (CLM05)
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct C023 {
+    pub c023_01_facility_code_value: String,
+    pub c023_02_facility_code_qualifier: String,
+    pub c023_03_claim_frequency_type_code: String,
+}
+
+/// C023 syntax notes: none
+pub const C023_SYNTAX_RULES: &[SyntaxRule] = &[];
+
+pub fn get_c023(element: &str) -> C023 {
+    let components = get_components(element);
+    C023 {
+        c023_01_facility_code_value: get_element(&components, 0),
+        c023_02_facility_code_qualifier: get_element(&components, 1),
+        c023_03_claim_frequency_type_code: get_element(&components, 2),
+    }
+}
+
+pub fn write_c023(c023: &C023) -> String {
+    build_composite(&[
+        &c023.c023_01_facility_code_value,
+        &c023.c023_02_facility_code_qualifier,
+        &c023.c023_03_claim_frequency_type_code,
+    ])
+}
+
+/// C040 - Reference Identifier (REF04)
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct C040 {
+    pub c040_01_reference_identification_qualifier: String,
+    pub c040_02_reference_identification: String,
+    pub c040_03_reference_identification_qualifier: String,
+    pub c040_04_reference_identification: String,
+    pub c040_05_reference_identification_qualifier: String,
+    pub c040_06_reference_identification: String,
+}
+
+/// C040 syntax notes: P0304, P0506
+pub const C040_SYNTAX_RULES: &[SyntaxRule] =
+    &[SyntaxRule::Paired(&[3, 4]), SyntaxRule::Paired(&[5, 6])];
+
+pub fn get_c040(element: &str) -> C040 {
+    let components = get_components(element);
+    C040 {
+        c040_01_reference_identification_qualifier: get_element(&components, 0),
+        c040_02_reference_identification: get_element(&components, 1),
+        c040_03_reference_identification_qualifier: get_element(&components, 2),
+        c040_04_reference_identification: get_element(&components, 3),
+        c040_05_reference_identification_qualifier: get_element(&components, 4),
+        c040_06_reference_identification: get_element(&components, 5),
+    }
+}
+
+pub fn write_c040(c040: &C040) -> String {
+    build_composite(&[
+        &c040.c040_01_reference_identification_qualifier,
+        &c040.c040_02_reference_identification,
+        &c040.c040_03_reference_identification_qualifier,
+        &c040.c040_04_reference_identification,
+        &c040.c040_05_reference_identification_qualifier,
+        &c040.c040_06_reference_identification,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_c003() {
+        let c003 = get_c003("HC:99213:25:59");
+        assert_eq!(c003.c003_01_product_or_service_id_qualifier, "HC");
+        assert_eq!(c003.c003_02_product_or_service_id, "99213");
+        assert_eq!(c003.modifiers(), vec!["25", "59"]);
+        assert_eq!(write_c003(&c003), "HC:99213:25:59");
+        assert!(get_c003("").is_empty());
+    }
+
+    #[test]
+    fn test_c003_description_keeps_empty_modifiers() {
+        let c003 = get_c003("HC:99213:::::OFFICE VISIT");
+        assert!(c003.modifiers().is_empty());
+        assert_eq!(c003.c003_07_description, "OFFICE VISIT");
+        assert_eq!(write_c003(&c003), "HC:99213:::::OFFICE VISIT");
+    }
+
+    #[test]
+    fn test_c022() {
+        let c022 = get_c022("BF:41090:D8:20050125");
+        assert_eq!(c022.c022_01_code_list_qualifier_code, "BF");
+        assert_eq!(c022.c022_02_industry_code, "41090");
+        assert_eq!(c022.c022_04_date_time_period, "20050125");
+        assert_eq!(write_c022(&c022), "BF:41090:D8:20050125");
+
+        let poa = get_c022("ABF:R05:::::::Y");
+        assert_eq!(poa.c022_09_present_on_admission_indicator, "Y");
+        assert_eq!(write_c022(&poa), "ABF:R05:::::::Y");
+    }
+
+    #[test]
+    fn test_c023() {
+        let c023 = get_c023("11:B:1");
+        assert_eq!(c023.c023_01_facility_code_value, "11");
+        assert_eq!(c023.c023_02_facility_code_qualifier, "B");
+        assert_eq!(c023.c023_03_claim_frequency_type_code, "1");
+        assert_eq!(write_c023(&c023), "11:B:1");
+    }
+
+    #[test]
+    fn test_c040() {
+        let c040 = get_c040("0B:123456:LU:CA");
+        assert_eq!(c040.c040_01_reference_identification_qualifier, "0B");
+        assert_eq!(c040.c040_04_reference_identification, "CA");
+        assert_eq!(write_c040(&c040), "0B:123456:LU:CA");
+    }
+}
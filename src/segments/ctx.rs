@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, composite, id, n0, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -13,6 +15,19 @@ pub struct CTX {
     pub ctx06_reference_in_segment: String,
 }
 
+/// X12 syntax notes: P0203
+pub const SYNTAX_RULES: &[SyntaxRule] = &[SyntaxRule::Paired(&[2, 3])];
+
+/// Element dictionary, from CTX01
+pub const ELEMENTS: &[ElementDef] = &[
+    composite(),
+    id(2, 3),
+    n0(1, 10),
+    an(1, 4),
+    composite(),
+    composite(),
+];
+
 pub fn get_ctx(ctx_content: String) -> CTX {
     // Remove the segment identifier if present
     let content = if ctx_content.starts_with("CTX*") {
@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{dt, id, r, tm, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 // EDI 835 CUR segment
@@ -9,6 +11,49 @@ pub struct CUR {
     pub currency_code: String,
 }
 
+/// X12 syntax notes: C0807, C0907, L101112, C1110, C1210, L131415, C1413, C1513, L161718, C1716, C1816, L192021, C2019, C2119
+pub const SYNTAX_RULES: &[SyntaxRule] = &[
+    SyntaxRule::Conditional(8, &[7]),
+    SyntaxRule::Conditional(9, &[7]),
+    SyntaxRule::ListConditional(10, &[11, 12]),
+    SyntaxRule::Conditional(11, &[10]),
+    SyntaxRule::Conditional(12, &[10]),
+    SyntaxRule::ListConditional(13, &[14, 15]),
+    SyntaxRule::Conditional(14, &[13]),
+    SyntaxRule::Conditional(15, &[13]),
+    SyntaxRule::ListConditional(16, &[17, 18]),
+    SyntaxRule::Conditional(17, &[16]),
+    SyntaxRule::Conditional(18, &[16]),
+    SyntaxRule::ListConditional(19, &[20, 21]),
+    SyntaxRule::Conditional(20, &[19]),
+    SyntaxRule::Conditional(21, &[19]),
+];
+
+/// Element dictionary, from CUR01
+pub const ELEMENTS: &[ElementDef] = &[
+    id(2, 3),
+    id(3, 3),
+    r(4, 10),
+    id(2, 3),
+    id(3, 3),
+    id(3, 3),
+    id(3, 3),
+    dt(8, 8),
+    tm(4, 8),
+    id(3, 3),
+    dt(8, 8),
+    tm(4, 8),
+    id(3, 3),
+    dt(8, 8),
+    tm(4, 8),
+    id(3, 3),
+    dt(8, 8),
+    tm(4, 8),
+    id(3, 3),
+    dt(8, 8),
+    tm(4, 8),
+];
+
 pub fn get_cur(cur_content: String) -> CUR {
     let cur_parts: Vec<&str> = cur_content.split("*").collect();
     CUR {
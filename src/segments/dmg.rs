@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, composite, id, r, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -15,6 +17,28 @@ pub struct DMG {
     pub dmg09_quantity: String,
 }
 
+/// X12 syntax notes: P0102, P1011, C1105
+pub const SYNTAX_RULES: &[SyntaxRule] = &[
+    SyntaxRule::Paired(&[1, 2]),
+    SyntaxRule::Paired(&[10, 11]),
+    SyntaxRule::Conditional(11, &[5]),
+];
+
+/// Element dictionary, from DMG01
+pub const ELEMENTS: &[ElementDef] = &[
+    id(2, 3),
+    an(1, 35),
+    id(1, 1),
+    id(1, 1),
+    composite(),
+    id(1, 2),
+    id(2, 3),
+    id(2, 2),
+    r(1, 15),
+    id(1, 3),
+    an(1, 15),
+];
+
 pub fn get_dmg(dmg_content: String) -> DMG {
     let dmg_parts: Vec<&str> = dmg_content.split("*").collect();
 
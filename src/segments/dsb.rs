@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, id, r, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -14,6 +16,21 @@ pub struct DSB {
     pub dsb08_monetary_amount_2: String,
 }
 
+/// X12 syntax notes: P0708
+pub const SYNTAX_RULES: &[SyntaxRule] = &[SyntaxRule::Paired(&[7, 8])];
+
+/// Element dictionary, from DSB01
+pub const ELEMENTS: &[ElementDef] = &[
+    id(1, 1),
+    r(1, 15),
+    id(2, 2),
+    id(1, 6),
+    id(1, 3),
+    an(1, 15),
+    id(1, 3),
+    an(1, 15),
+];
+
 pub fn get_dsb(dsb_content: String) -> DSB {
     let dsb_parts: Vec<&str> = dsb_content.split("*").collect();
 
@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, dt, id, tm, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -8,6 +10,16 @@ pub struct DTM {
     pub date_time: String,
 }
 
+/// X12 syntax notes: R020305, C0403, P0506
+pub const SYNTAX_RULES: &[SyntaxRule] = &[
+    SyntaxRule::Required(&[2, 3, 5]),
+    SyntaxRule::Conditional(4, &[3]),
+    SyntaxRule::Paired(&[5, 6]),
+];
+
+/// Element dictionary, from DTM01
+pub const ELEMENTS: &[ElementDef] = &[id(3, 3), dt(8, 8), tm(4, 8), id(2, 2), id(2, 3), an(1, 35)];
+
 pub fn get_dtm(dtm_content: String) -> DTM {
     let dtm_parts: Vec<&str> = dtm_content.split("*").collect();
     DTM {
@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, id, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -9,6 +11,12 @@ pub struct DTP {
     pub dtp03_date_time_value: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from DTP01
+pub const ELEMENTS: &[ElementDef] = &[id(3, 3), id(2, 3), an(1, 35)];
+
 pub fn get_dtp(dtp_content: String) -> DTP {
     let dtp_parts: Vec<&str> = dtp_content.split("*").collect();
 
@@ -1,4 +1,6 @@
-use crate::helper::edihelper::get_element;
+use crate::helper::edihelper::{get_element, get_repetitions, join_repetitions};
+use crate::helper::element_dictionary::{an, composite, id, r, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -6,7 +8,8 @@ use serde::{Deserialize, Serialize};
 pub struct EB {
     pub eb01_eligibility_indicator: String,
     pub eb02_benefit_type: String,
-    pub eb03_service_type_code: String,
+    /// EB03 is repeatable; each repetition is one service type code
+    pub eb03_service_type_code: Vec<String>,
     pub eb04_insurance_type_code: String,
     pub eb05_plan_coverage_description: String,
     pub eb06_time_period_qualifier: String,
@@ -18,6 +21,27 @@ pub struct EB {
     pub eb12_yes_no_condition_response_code: String,
 }
 
+/// X12 syntax notes: P0910
+pub const SYNTAX_RULES: &[SyntaxRule] = &[SyntaxRule::Paired(&[9, 10])];
+
+/// Element dictionary, from EB01
+pub const ELEMENTS: &[ElementDef] = &[
+    id(1, 2),
+    id(3, 3),
+    id(1, 2).repeating(),
+    id(1, 3),
+    an(1, 50),
+    id(1, 2),
+    r(1, 18),
+    r(1, 10),
+    id(2, 2),
+    r(1, 15),
+    id(1, 1),
+    id(1, 1),
+    composite(),
+    composite(),
+];
+
 pub fn get_eb(eb_content: String) -> EB {
     let eb_parts: Vec<&str> = eb_content.split("*").collect();
 
@@ -35,7 +59,7 @@ pub fn get_eb(eb_content: String) -> EB {
 
     // EB03 - Service Type Code
     if eb_parts.len() > 2 && !eb_parts[2].is_empty() {
-        eb.eb03_service_type_code = get_element(&eb_parts, 2);
+        eb.eb03_service_type_code = get_repetitions(eb_parts[2]);
     }
 
     // EB04 - Insurance Type Code
@@ -98,7 +122,7 @@ pub fn write_eb(eb: EB) -> String {
     // Include EB03 if not empty
     if !eb.eb03_service_type_code.is_empty() {
         eb_content.push_str("*");
-        eb_content.push_str(&eb.eb03_service_type_code);
+        eb_content.push_str(&join_repetitions(&eb.eb03_service_type_code));
     } else {
         eb_content.push_str("*");
     }
@@ -193,7 +217,7 @@ mod tests {
 
         assert_eq!(eb.eb01_eligibility_indicator, "1");
         assert_eq!(eb.eb02_benefit_type, "30");
-        assert_eq!(eb.eb03_service_type_code, "98");
+        assert_eq!(eb.eb03_service_type_code, vec!["98"]);
         assert_eq!(eb.eb04_insurance_type_code, "MC");
         assert_eq!(eb.eb05_plan_coverage_description, "GOLD PLAN");
         assert_eq!(eb.eb06_time_period_qualifier, "27");
@@ -212,7 +236,7 @@ mod tests {
 
         assert_eq!(eb.eb01_eligibility_indicator, "1");
         assert_eq!(eb.eb02_benefit_type, "30");
-        assert_eq!(eb.eb03_service_type_code, "");
+        assert!(eb.eb03_service_type_code.is_empty());
         assert_eq!(eb.eb04_insurance_type_code, "");
     }
 
@@ -221,7 +245,7 @@ mod tests {
         let eb = EB {
             eb01_eligibility_indicator: "1".to_string(),
             eb02_benefit_type: "30".to_string(),
-            eb03_service_type_code: "98".to_string(),
+            eb03_service_type_code: vec!["98".to_string()],
             eb04_insurance_type_code: "MC".to_string(),
             eb05_plan_coverage_description: "GOLD PLAN".to_string(),
             eb06_time_period_qualifier: "27".to_string(),
@@ -242,7 +266,7 @@ mod tests {
         let eb = EB {
             eb01_eligibility_indicator: "1".to_string(),
             eb02_benefit_type: "30".to_string(),
-            eb03_service_type_code: "".to_string(),
+            eb03_service_type_code: vec![],
             eb04_insurance_type_code: "".to_string(),
             eb05_plan_coverage_description: "".to_string(),
             eb06_time_period_qualifier: "".to_string(),
@@ -257,4 +281,11 @@ mod tests {
         let eb_content = write_eb(eb);
         assert_eq!(eb_content, "EB*1*30~");
     }
+
+    #[test]
+    fn test_eb_repeated_service_types() {
+        let eb = get_eb("1*IND*30^1^33^35**GOLD PLAN".to_string());
+        assert_eq!(eb.eb03_service_type_code, vec!["30", "1", "33", "35"]);
+        assert_eq!(write_eb(eb), "EB*1*IND*30^1^33^35**GOLD PLAN~");
+    }
 }
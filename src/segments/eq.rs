@@ -1,15 +1,31 @@
-use crate::helper::edihelper::get_element;
+use crate::helper::edihelper::{get_element, get_repetitions, join_repetitions};
+use crate::helper::element_dictionary::{composite, id, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
+use crate::segments::composites::{get_c003, write_c003, C003};
 use log::info;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
 pub struct EQ {
-    pub eq01_service_type_code: String,
-    pub eq02_composite_medical_procedure_identifier: String,
+    /// EQ01 is repeatable; each repetition is one service type code
+    pub eq01_service_type_code: Vec<String>,
+    pub eq02_composite_medical_procedure_identifier: C003,
     pub eq03_coverage_level_code: String,
     pub eq04_insurance_type_code: String,
 }
 
+/// X12 syntax notes: R0102
+pub const SYNTAX_RULES: &[SyntaxRule] = &[SyntaxRule::Required(&[1, 2])];
+
+/// Element dictionary, from EQ01
+pub const ELEMENTS: &[ElementDef] = &[
+    id(1, 2).repeating(),
+    composite(),
+    id(3, 3),
+    id(1, 3),
+    composite(),
+];
+
 pub fn get_eq(eq_content: String) -> EQ {
     let eq_parts: Vec<&str> = eq_content.split("*").collect();
 
@@ -23,10 +39,11 @@ pub fn get_eq(eq_content: String) -> EQ {
     };
 
     // EQ01 - Service Type Code
-    eq.eq01_service_type_code = get_element(&eq_parts, start_index);
+    eq.eq01_service_type_code = get_repetitions(&get_element(&eq_parts, start_index));
 
     // EQ02 - Composite Medical Procedure Identifier
-    eq.eq02_composite_medical_procedure_identifier = get_element(&eq_parts, start_index + 1);
+    eq.eq02_composite_medical_procedure_identifier =
+        get_c003(&get_element(&eq_parts, start_index + 1));
 
     // EQ03 - Coverage Level Code
     eq.eq03_coverage_level_code = get_element(&eq_parts, start_index + 2);
@@ -42,15 +59,16 @@ pub fn write_eq(eq: EQ) -> String {
     let mut eq_content = String::new();
 
     eq_content.push_str("EQ*");
-    eq_content.push_str(&eq.eq01_service_type_code);
+    eq_content.push_str(&join_repetitions(&eq.eq01_service_type_code));
 
     // Only include subsequent fields if they're not empty
-    if !eq.eq02_composite_medical_procedure_identifier.is_empty()
+    let eq02 = write_c003(&eq.eq02_composite_medical_procedure_identifier);
+    if !eq02.is_empty()
         || !eq.eq03_coverage_level_code.is_empty()
         || !eq.eq04_insurance_type_code.is_empty()
     {
         eq_content.push_str("*");
-        eq_content.push_str(&eq.eq02_composite_medical_procedure_identifier);
+        eq_content.push_str(&eq02);
     }
 
     if !eq.eq03_coverage_level_code.is_empty() || !eq.eq04_insurance_type_code.is_empty() {
@@ -77,8 +95,8 @@ mod tests {
         let eq_content = "30".to_string();
         let eq = get_eq(eq_content);
 
-        assert_eq!(eq.eq01_service_type_code, "30");
-        assert_eq!(eq.eq02_composite_medical_procedure_identifier, "");
+        assert_eq!(eq.eq01_service_type_code, vec!["30"]);
+        assert!(eq.eq02_composite_medical_procedure_identifier.is_empty());
         assert_eq!(eq.eq03_coverage_level_code, "");
         assert_eq!(eq.eq04_insurance_type_code, "");
     }
@@ -88,11 +106,11 @@ mod tests {
         let eq_content = "30*AD:CPT:99211*IND*HM".to_string();
         let eq = get_eq(eq_content);
 
-        assert_eq!(eq.eq01_service_type_code, "30");
-        assert_eq!(
-            eq.eq02_composite_medical_procedure_identifier,
-            "AD:CPT:99211"
-        );
+        assert_eq!(eq.eq01_service_type_code, vec!["30"]);
+        let eq02 = &eq.eq02_composite_medical_procedure_identifier;
+        assert_eq!(eq02.c003_01_product_or_service_id_qualifier, "AD");
+        assert_eq!(eq02.c003_02_product_or_service_id, "CPT");
+        assert_eq!(eq02.c003_03_procedure_modifier, "99211");
         assert_eq!(eq.eq03_coverage_level_code, "IND");
         assert_eq!(eq.eq04_insurance_type_code, "HM");
     }
@@ -100,8 +118,8 @@ mod tests {
     #[test]
     fn test_write_eq_minimal() {
         let eq = EQ {
-            eq01_service_type_code: "30".to_string(),
-            eq02_composite_medical_procedure_identifier: "".to_string(),
+            eq01_service_type_code: vec!["30".to_string()],
+            eq02_composite_medical_procedure_identifier: C003::default(),
             eq03_coverage_level_code: "".to_string(),
             eq04_insurance_type_code: "".to_string(),
         };
@@ -113,8 +131,8 @@ mod tests {
     #[test]
     fn test_write_eq_full() {
         let eq = EQ {
-            eq01_service_type_code: "30".to_string(),
-            eq02_composite_medical_procedure_identifier: "AD:CPT:99211".to_string(),
+            eq01_service_type_code: vec!["30".to_string()],
+            eq02_composite_medical_procedure_identifier: get_c003("AD:CPT:99211"),
             eq03_coverage_level_code: "IND".to_string(),
             eq04_insurance_type_code: "HM".to_string(),
         };
@@ -122,4 +140,11 @@ mod tests {
         let eq_content = write_eq(eq);
         assert_eq!(eq_content, "EQ*30*AD:CPT:99211*IND*HM~");
     }
+
+    #[test]
+    fn test_eq_repeated_service_types() {
+        let eq = get_eq("30^1^33**IND".to_string());
+        assert_eq!(eq.eq01_service_type_code, vec!["30", "1", "33"]);
+        assert_eq!(write_eq(eq), "EQ*30^1^33**IND~");
+    }
 }
@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{n0, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -8,6 +10,12 @@ pub struct GE {
     pub group_control_number: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from GE01
+pub const ELEMENTS: &[ElementDef] = &[n0(1, 6), n0(1, 9)];
+
 pub fn get_ge(ge_content: String) -> GE {
     let ge_parts: Vec<&str> = ge_content.split("*").collect();
     GE {
@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, dt, id, n0, tm, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -14,6 +16,21 @@ pub struct GS {
     pub version_number: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from GS01
+pub const ELEMENTS: &[ElementDef] = &[
+    id(2, 2),
+    an(2, 15),
+    an(2, 15),
+    dt(8, 8),
+    tm(4, 8),
+    n0(1, 9),
+    id(1, 2),
+    an(1, 12),
+];
+
 pub fn get_gs(gs_content: String) -> GS {
     let gs_parts: Vec<&str> = gs_content.split("*").collect();
     GS {
@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, id, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -17,6 +19,12 @@ pub struct HD {
     pub hd11_yes_no_condition_or_response_code_2: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from HD01
+pub const ELEMENTS: &[ElementDef] = &[id(3, 3), id(1, 3), id(2, 3), an(1, 50), id(3, 3)];
+
 pub fn get_hd(hd_content: String) -> HD {
     let hd_parts: Vec<&str> = hd_content.split("*").collect();
 
@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, id, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -10,6 +12,12 @@ pub struct HL {
     pub hl04_hierarchical_child_code: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from HL01
+pub const ELEMENTS: &[ElementDef] = &[an(1, 12), an(1, 12), id(1, 2), id(1, 1)];
+
 pub fn get_hl(hl_content: String) -> HL {
     let hl_parts: Vec<&str> = hl_content.split("*").collect();
 
@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{id, n0, r, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -14,6 +16,24 @@ pub struct HSD {
     pub hsd08_delivery_pattern_time_code: String,
 }
 
+/// X12 syntax notes: P0102, C0605
+pub const SYNTAX_RULES: &[SyntaxRule] = &[
+    SyntaxRule::Paired(&[1, 2]),
+    SyntaxRule::Conditional(6, &[5]),
+];
+
+/// Element dictionary, from HSD01
+pub const ELEMENTS: &[ElementDef] = &[
+    id(1, 2),
+    r(1, 15),
+    id(2, 2),
+    r(1, 15),
+    id(1, 2),
+    n0(1, 3),
+    id(1, 1),
+    id(1, 1),
+];
+
 pub fn get_hsd(hsd_content: String) -> HSD {
     let hsd_parts: Vec<&str> = hsd_content.split("*").collect();
 
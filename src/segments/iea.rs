@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{n0, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -8,6 +10,12 @@ pub struct IEA {
     pub interchange_control_number: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from IEA01
+pub const ELEMENTS: &[ElementDef] = &[n0(1, 5), n0(9, 9)];
+
 pub fn get_iea(iea_content: String) -> IEA {
     let iea_parts: Vec<&str> = iea_content.split("*").collect();
     IEA {
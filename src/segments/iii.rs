@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, id, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -10,6 +12,12 @@ pub struct III {
     pub free_form_message_text: String,
 }
 
+/// X12 syntax notes: P0102
+pub const SYNTAX_RULES: &[SyntaxRule] = &[SyntaxRule::Paired(&[1, 2])];
+
+/// Element dictionary, from III01
+pub const ELEMENTS: &[ElementDef] = &[id(1, 3), an(1, 30), id(1, 2), an(1, 264)];
+
 pub fn get_iii(iii_content: String) -> III {
     let iii_parts: Vec<&str> = iii_content.split("*").collect();
 
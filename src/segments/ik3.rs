@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, id, n0, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -11,6 +13,12 @@ pub struct IK3 {
     pub ik304_implementation_segment_syntax_error_code: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from IK301
+pub const ELEMENTS: &[ElementDef] = &[id(2, 3), n0(1, 10), an(1, 4), id(1, 3)];
+
 pub fn get_ik3(ik3_content: String) -> IK3 {
     let ik3_parts: Vec<&str> = ik3_content.split("*").collect();
 
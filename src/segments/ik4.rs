@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, composite, id, n0, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -11,6 +13,12 @@ pub struct IK4 {
     pub ik404_copy_of_bad_data_element: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from IK401
+pub const ELEMENTS: &[ElementDef] = &[composite(), n0(1, 4), id(1, 3), an(1, 99)];
+
 pub fn get_ik4(ik4_content: String) -> IK4 {
     let ik4_parts: Vec<&str> = ik4_content.split("*").collect();
 
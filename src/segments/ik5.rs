@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{id, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -13,6 +15,12 @@ pub struct IK5 {
     pub ik506_implementation_transaction_set_syntax_error_code: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from IK501
+pub const ELEMENTS: &[ElementDef] = &[id(1, 1), id(1, 3), id(1, 3), id(1, 3), id(1, 3), id(1, 3)];
+
 pub fn get_ik5(ik5_content: String) -> IK5 {
     let ik5_parts: Vec<&str> = ik5_content.split("*").collect();
 
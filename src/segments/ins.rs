@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, composite, id, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -23,6 +25,26 @@ pub struct INS {
     pub ins17_country_code: String,
 }
 
+/// X12 syntax notes: P1112
+pub const SYNTAX_RULES: &[SyntaxRule] = &[SyntaxRule::Paired(&[11, 12])];
+
+/// Element dictionary, from INS01
+pub const ELEMENTS: &[ElementDef] = &[
+    id(1, 1),
+    id(2, 2),
+    id(3, 3),
+    id(2, 3),
+    id(1, 1),
+    composite(),
+    id(1, 2),
+    id(2, 2),
+    id(1, 1),
+    id(1, 1),
+    id(2, 3),
+    an(1, 35),
+    id(1, 1),
+];
+
 pub fn get_ins(ins_content: String) -> INS {
     let ins_parts: Vec<&str> = ins_content.split("*").collect();
 
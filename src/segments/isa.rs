@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, dt, id, n0, tm, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -21,7 +23,30 @@ pub struct ISA {
     pub component_element_separator: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
 // function to get the ISA struct
+/// Element dictionary, from ISA01
+pub const ELEMENTS: &[ElementDef] = &[
+    id(2, 2),
+    an(10, 10),
+    id(2, 2),
+    an(10, 10),
+    id(2, 2),
+    an(15, 15),
+    id(2, 2),
+    an(15, 15),
+    dt(6, 6),
+    tm(4, 4),
+    an(1, 1),
+    id(5, 5),
+    n0(9, 9),
+    id(1, 1),
+    id(1, 1),
+    an(1, 1),
+];
+
 pub fn get_isa(isa_content: String) -> ISA {
     let isa_parts: Vec<&str> = isa_content.split("*").collect();
     ISA {
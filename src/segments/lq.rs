@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, id, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -8,6 +10,12 @@ pub struct LQ {
     pub lq02_remark_code: String,
 }
 
+/// X12 syntax notes: C0102
+pub const SYNTAX_RULES: &[SyntaxRule] = &[SyntaxRule::Conditional(1, &[2])];
+
+/// Element dictionary, from LQ01
+pub const ELEMENTS: &[ElementDef] = &[id(1, 3), an(1, 30)];
+
 pub fn get_lq(lq_content: String) -> LQ {
     let lq_parts: Vec<&str> = lq_content.split("*").collect();
     LQ {
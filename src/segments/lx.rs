@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{n0, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -7,6 +9,12 @@ pub struct LX {
     pub lx01_claim_sequence_number: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from LX01
+pub const ELEMENTS: &[ElementDef] = &[n0(1, 6)];
+
 pub fn get_lx(lx_content: String) -> LX {
     let lx_parts: Vec<&str> = lx_content.split("*").collect();
     LX {
@@ -1,4 +1,6 @@
 use crate::helper::edihelper::{build_segment, get_element};
+use crate::helper::element_dictionary::{an, r, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 // EDI 835 MSI - PROVIDER SUMMARY INFORMATION
@@ -31,6 +33,37 @@ pub struct MIA {
     pub mia24_pps_capital_exception_amount: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from MIA01
+pub const ELEMENTS: &[ElementDef] = &[
+    r(1, 15),
+    r(1, 18),
+    r(1, 15),
+    r(1, 18),
+    an(1, 50),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 15),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    an(1, 50),
+    an(1, 50),
+    an(1, 50),
+    an(1, 50),
+    an(1, 50),
+];
+
 pub fn get_mia(mia_content: String) -> MIA {
     let mia_parts: Vec<&str> = mia_content.split("*").collect();
     let mut mia02_pps_operating_outlier_amount: String = "".to_string();
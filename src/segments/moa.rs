@@ -1,4 +1,6 @@
 use crate::helper::edihelper::{build_segment, get_element};
+use crate::helper::element_dictionary::{an, r, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 // EDI 835 MOA - PROVIDER SUMMARY INFORMATION
@@ -16,6 +18,22 @@ pub struct MOA {
     pub moa09_nonpayable_professional_component_amount: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from MOA01
+pub const ELEMENTS: &[ElementDef] = &[
+    r(1, 10),
+    r(1, 18),
+    an(1, 50),
+    an(1, 50),
+    an(1, 50),
+    an(1, 50),
+    an(1, 50),
+    r(1, 18),
+    r(1, 18),
+];
+
 pub fn get_moa(moa_content: String) -> MOA {
     let moa_parts: Vec<&str> = moa_content.split("*").collect();
     let mut moa01_reimbursement_rate: String = "".to_string();
@@ -9,6 +9,7 @@ pub mod bpr;
 pub mod cas;
 pub mod cl1;
 pub mod clp;
+pub mod composites;
 pub mod ctx;
 pub mod cur;
 pub mod dmg;
@@ -55,3 +56,148 @@ pub mod trn;
 pub mod ts2;
 pub mod ts3;
 pub mod um;
+
+use crate::helper::element_dictionary::ElementDef;
+use crate::helper::syntax_rules::SyntaxRule;
+
+/// X12 syntax notes of segment `id`; empty for unknown segments.
+pub fn syntax_rules(id: &str) -> &'static [SyntaxRule] {
+    match id {
+        "AAA" => aaa::SYNTAX_RULES,
+        "AK1" => ak1::SYNTAX_RULES,
+        "AK2" => ak2::SYNTAX_RULES,
+        "AK9" => ak9::SYNTAX_RULES,
+        "AMT" => amt::SYNTAX_RULES,
+        "BGN" => bgn::SYNTAX_RULES,
+        "BHT" => bht::SYNTAX_RULES,
+        "BPR" => bpr::SYNTAX_RULES,
+        "CAS" => cas::SYNTAX_RULES,
+        "CL1" => cl1::SYNTAX_RULES,
+        "CLP" => clp::SYNTAX_RULES,
+        "CTX" => ctx::SYNTAX_RULES,
+        "CUR" => cur::SYNTAX_RULES,
+        "DMG" => dmg::SYNTAX_RULES,
+        "DSB" => dsb::SYNTAX_RULES,
+        "DTM" => dtm::SYNTAX_RULES,
+        "DTP" => dtp::SYNTAX_RULES,
+        "EB" => eb::SYNTAX_RULES,
+        "EQ" => eq::SYNTAX_RULES,
+        "GE" => ge::SYNTAX_RULES,
+        "GS" => gs::SYNTAX_RULES,
+        "HD" => hd::SYNTAX_RULES,
+        "HI" => hi::SYNTAX_RULES,
+        "HL" => hl::SYNTAX_RULES,
+        "HSD" => hsd::SYNTAX_RULES,
+        "IEA" => iea::SYNTAX_RULES,
+        "III" => iii::SYNTAX_RULES,
+        "IK3" => ik3::SYNTAX_RULES,
+        "IK4" => ik4::SYNTAX_RULES,
+        "IK5" => ik5::SYNTAX_RULES,
+        "INS" => ins::SYNTAX_RULES,
+        "ISA" => isa::SYNTAX_RULES,
+        "LQ" => lq::SYNTAX_RULES,
+        "LX" => lx::SYNTAX_RULES,
+        "MIA" => mia::SYNTAX_RULES,
+        "MOA" => moa::SYNTAX_RULES,
+        "MSG" => msg::SYNTAX_RULES,
+        "N1" => n1::SYNTAX_RULES,
+        "N3" => n3::SYNTAX_RULES,
+        "N4" => n4::SYNTAX_RULES,
+        "NM1" => nm1::SYNTAX_RULES,
+        "PER" => per::SYNTAX_RULES,
+        "PLB" => plb::SYNTAX_RULES,
+        "PRV" => prv::SYNTAX_RULES,
+        "QTY" => qty::SYNTAX_RULES,
+        "RDM" => rdm::SYNTAX_RULES,
+        "REF" => r#ref::SYNTAX_RULES,
+        "SE" => se::SYNTAX_RULES,
+        "ST" => st::SYNTAX_RULES,
+        "STC" => stc::SYNTAX_RULES,
+        "SV2" => sv2::SYNTAX_RULES,
+        "SVC" => svc::SYNTAX_RULES,
+        "TA1" => ta1::SYNTAX_RULES,
+        "TRN" => trn::SYNTAX_RULES,
+        "TS2" => ts2::SYNTAX_RULES,
+        "TS3" => ts3::SYNTAX_RULES,
+        "UM" => um::SYNTAX_RULES,
+        _ => &[],
+    }
+}
+
+/// Composite of element `element` (1-based) of segment `id` with its syntax
+/// notes, e.g. C022 for HI01; `None` for simple elements.
+pub fn composite_syntax_rules(
+    id: &str,
+    element: usize,
+) -> Option<(&'static str, &'static [SyntaxRule])> {
+    match (id, element) {
+        ("EQ", 2) | ("SV2", 2) | ("SVC", 1 | 6) => Some(("C003", composites::C003_SYNTAX_RULES)),
+        ("HI", 1..=12) => Some(("C022", composites::C022_SYNTAX_RULES)),
+        ("REF", 4) | ("RDM", 4 | 5) => Some(("C040", composites::C040_SYNTAX_RULES)),
+        _ => None,
+    }
+}
+
+/// Element dictionary of segment `id`, its first element first; empty for unknown segments.
+pub fn element_dictionary(id: &str) -> &'static [ElementDef] {
+    match id {
+        "AAA" => aaa::ELEMENTS,
+        "AK1" => ak1::ELEMENTS,
+        "AK2" => ak2::ELEMENTS,
+        "AK9" => ak9::ELEMENTS,
+        "AMT" => amt::ELEMENTS,
+        "BGN" => bgn::ELEMENTS,
+        "BHT" => bht::ELEMENTS,
+        "BPR" => bpr::ELEMENTS,
+        "CAS" => cas::ELEMENTS,
+        "CL1" => cl1::ELEMENTS,
+        "CLP" => clp::ELEMENTS,
+        "CTX" => ctx::ELEMENTS,
+        "CUR" => cur::ELEMENTS,
+        "DMG" => dmg::ELEMENTS,
+        "DSB" => dsb::ELEMENTS,
+        "DTM" => dtm::ELEMENTS,
+        "DTP" => dtp::ELEMENTS,
+        "EB" => eb::ELEMENTS,
+        "EQ" => eq::ELEMENTS,
+        "GE" => ge::ELEMENTS,
+        "GS" => gs::ELEMENTS,
+        "HD" => hd::ELEMENTS,
+        "HI" => hi::ELEMENTS,
+        "HL" => hl::ELEMENTS,
+        "HSD" => hsd::ELEMENTS,
+        "IEA" => iea::ELEMENTS,
+        "III" => iii::ELEMENTS,
+        "IK3" => ik3::ELEMENTS,
+        "IK4" => ik4::ELEMENTS,
+        "IK5" => ik5::ELEMENTS,
+        "INS" => ins::ELEMENTS,
+        "ISA" => isa::ELEMENTS,
+        "LQ" => lq::ELEMENTS,
+        "LX" => lx::ELEMENTS,
+        "MIA" => mia::ELEMENTS,
+        "MOA" => moa::ELEMENTS,
+        "MSG" => msg::ELEMENTS,
+        "N1" => n1::ELEMENTS,
+        "N3" => n3::ELEMENTS,
+        "N4" => n4::ELEMENTS,
+        "NM1" => nm1::ELEMENTS,
+        "PER" => per::ELEMENTS,
+        "PLB" => plb::ELEMENTS,
+        "PRV" => prv::ELEMENTS,
+        "QTY" => qty::ELEMENTS,
+        "RDM" => rdm::ELEMENTS,
+        "REF" => r#ref::ELEMENTS,
+        "SE" => se::ELEMENTS,
+        "ST" => st::ELEMENTS,
+        "STC" => stc::ELEMENTS,
+        "SV2" => sv2::ELEMENTS,
+        "SVC" => svc::ELEMENTS,
+        "TA1" => ta1::ELEMENTS,
+        "TRN" => trn::ELEMENTS,
+        "TS2" => ts2::ELEMENTS,
+        "TS3" => ts3::ELEMENTS,
+        "UM" => um::ELEMENTS,
+        _ => &[],
+    }
+}
@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, id, n0, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -9,6 +11,12 @@ pub struct MSG {
     pub msg03_number: String,
 }
 
+/// X12 syntax notes: C0302
+pub const SYNTAX_RULES: &[SyntaxRule] = &[SyntaxRule::Conditional(3, &[2])];
+
+/// Element dictionary, from MSG01
+pub const ELEMENTS: &[ElementDef] = &[an(1, 264), id(2, 2), n0(1, 9)];
+
 pub fn get_msg(msg_content: String) -> MSG {
     let msg_parts: Vec<&str> = msg_content.split("*").collect();
 
@@ -1,4 +1,6 @@
 use crate::helper::edihelper::{build_segment, get_element};
+use crate::helper::element_dictionary::{an, id, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -10,6 +12,13 @@ pub struct N1 {
     pub payee_identification_code: String,
 }
 
+/// X12 syntax notes: R0203, P0304
+pub const SYNTAX_RULES: &[SyntaxRule] =
+    &[SyntaxRule::Required(&[2, 3]), SyntaxRule::Paired(&[3, 4])];
+
+/// Element dictionary, from N101
+pub const ELEMENTS: &[ElementDef] = &[id(2, 3), an(1, 60), id(1, 2), an(2, 80), id(2, 2), id(2, 3)];
+
 pub fn get_n1(n1_content: String) -> N1 {
     let n1_parts: Vec<&str> = n1_content.split("*").collect();
     let mut payee_identification_code_qualifier: String = "".to_string();
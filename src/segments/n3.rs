@@ -1,4 +1,6 @@
 use crate::helper::edihelper::{build_segment, get_element};
+use crate::helper::element_dictionary::{an, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -8,6 +10,12 @@ pub struct N3 {
     pub payee_address2: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from N301
+pub const ELEMENTS: &[ElementDef] = &[an(1, 55), an(1, 55)];
+
 pub fn get_n3(n3_content: String) -> N3 {
     let n3_parts: Vec<&str> = n3_content.split("*").collect();
     if n3_parts.len() == 1 {
@@ -1,4 +1,6 @@
 use crate::helper::edihelper::{build_segment, get_element};
+use crate::helper::element_dictionary::{an, id, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -11,6 +13,24 @@ pub struct N4 {
     pub payee_country_sub_code: String,
 }
 
+/// X12 syntax notes: E0207, C0605, C0704
+pub const SYNTAX_RULES: &[SyntaxRule] = &[
+    SyntaxRule::Exclusion(&[2, 7]),
+    SyntaxRule::Conditional(6, &[5]),
+    SyntaxRule::Conditional(7, &[4]),
+];
+
+/// Element dictionary, from N401
+pub const ELEMENTS: &[ElementDef] = &[
+    an(2, 30),
+    id(2, 2),
+    id(3, 15),
+    id(2, 3),
+    id(1, 2),
+    an(1, 30),
+    id(1, 3),
+];
+
 pub fn get_n4(n4_content: String) -> N4 {
     let n4_parts: Vec<&str> = n4_content.split("*").collect();
     let mut payee_state: String = "".to_string();
@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, id, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -16,6 +18,29 @@ pub struct NM1 {
     pub member_number: String,
 }
 
+/// X12 syntax notes: P0809, C1110, C1203
+pub const SYNTAX_RULES: &[SyntaxRule] = &[
+    SyntaxRule::Paired(&[8, 9]),
+    SyntaxRule::Conditional(11, &[10]),
+    SyntaxRule::Conditional(12, &[3]),
+];
+
+/// Element dictionary, from NM101
+pub const ELEMENTS: &[ElementDef] = &[
+    id(2, 3),
+    id(1, 1),
+    an(1, 60),
+    an(1, 35),
+    an(1, 25),
+    an(1, 10),
+    an(1, 10),
+    id(1, 2),
+    an(2, 80),
+    id(2, 2),
+    id(2, 3),
+    an(1, 60),
+];
+
 pub fn get_nm1(nm1_content: String) -> NM1 {
     let nm1_parts: Vec<&str> = nm1_content.split("*").collect();
 
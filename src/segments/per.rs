@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, id, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 /**
  * PER (Administrative Communications Contact) Segment
  *
@@ -33,6 +35,26 @@ pub struct PER {
     pub per08_contact_number: String,
 }
 
+/// X12 syntax notes: P0304, P0506, P0708
+pub const SYNTAX_RULES: &[SyntaxRule] = &[
+    SyntaxRule::Paired(&[3, 4]),
+    SyntaxRule::Paired(&[5, 6]),
+    SyntaxRule::Paired(&[7, 8]),
+];
+
+/// Element dictionary, from PER01
+pub const ELEMENTS: &[ElementDef] = &[
+    id(2, 2),
+    an(1, 60),
+    id(2, 2),
+    an(1, 256),
+    id(2, 2),
+    an(1, 256),
+    id(2, 2),
+    an(1, 256),
+    an(1, 20),
+];
+
 /**
  * Parse PER segment from string
  *
@@ -1,4 +1,6 @@
 use crate::helper::edihelper::{build_segment, get_element};
+use crate::helper::element_dictionary::{an, composite, dt, r, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -20,6 +22,33 @@ pub struct PLB {
     pub plb14_provider_adjustment_amount: String,
 }
 
+/// X12 syntax notes: P0506, P0708, P0910, P1112, P1314
+pub const SYNTAX_RULES: &[SyntaxRule] = &[
+    SyntaxRule::Paired(&[5, 6]),
+    SyntaxRule::Paired(&[7, 8]),
+    SyntaxRule::Paired(&[9, 10]),
+    SyntaxRule::Paired(&[11, 12]),
+    SyntaxRule::Paired(&[13, 14]),
+];
+
+/// Element dictionary, from PLB01
+pub const ELEMENTS: &[ElementDef] = &[
+    an(1, 50),
+    dt(8, 8),
+    composite(),
+    r(1, 18),
+    composite(),
+    r(1, 18),
+    composite(),
+    r(1, 18),
+    composite(),
+    r(1, 18),
+    composite(),
+    r(1, 18),
+    composite(),
+    r(1, 18),
+];
+
 pub fn get_plb(plb_content: String) -> PLB {
     let plb_parts: Vec<&str> = plb_content.split("*").collect();
 
@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, composite, id, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 /// PRV - Provider Information
@@ -15,6 +17,19 @@ pub struct PRV {
     pub prv06_provider_organization_code: Option<String>,
 }
 
+/// X12 syntax notes: P0203
+pub const SYNTAX_RULES: &[SyntaxRule] = &[SyntaxRule::Paired(&[2, 3])];
+
+/// Element dictionary, from PRV01
+pub const ELEMENTS: &[ElementDef] = &[
+    id(1, 3),
+    id(2, 3),
+    an(1, 50),
+    id(2, 2),
+    composite(),
+    id(3, 3),
+];
+
 /// Parse a PRV segment string into a PRV struct
 
 pub fn get_prv(segment: &str) -> PRV {
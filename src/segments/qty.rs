@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, composite, id, r, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -8,6 +10,15 @@ pub struct QTY {
     pub qty02_claim_supplement_information_quantity: String,
 }
 
+/// X12 syntax notes: E0204, R0204
+pub const SYNTAX_RULES: &[SyntaxRule] = &[
+    SyntaxRule::Exclusion(&[2, 4]),
+    SyntaxRule::Required(&[2, 4]),
+];
+
+/// Element dictionary, from QTY01
+pub const ELEMENTS: &[ElementDef] = &[id(2, 2), r(1, 15), composite(), an(1, 30)];
+
 pub fn get_qty(qty_content: String) -> QTY {
     let qty_parts: Vec<&str> = qty_content.split("*").collect();
     QTY {
@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, composite, id, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -13,6 +15,12 @@ pub struct RDM {
 // 2 is needed when rdm01 is BM
 // 3 is needed when rdm01 is EM,FT or OL
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from RDM01
+pub const ELEMENTS: &[ElementDef] = &[id(1, 2), an(1, 60), an(1, 256), composite(), composite()];
+
 pub fn get_rdm(rdm_content: String) -> RDM {
     let rdm_parts: Vec<&str> = rdm_content.split("*").collect();
     let mut rdm02_name: String = "".to_string();
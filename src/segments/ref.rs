@@ -1,4 +1,7 @@
-use crate::helper::edihelper::get_element;
+use crate::helper::edihelper::{build_segment, get_element};
+use crate::helper::element_dictionary::{an, composite, id, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
+use crate::segments::composites::{get_c040, write_c040, C040};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -6,8 +9,20 @@ use serde::{Deserialize, Serialize};
 pub struct REF {
     pub reference_id_number_qualifier: String,
     pub reference_id_number: String,
+    /// REF03 - Description
+    #[serde(default)]
+    pub reference_id_description: String,
+    /// REF04 - Reference Identifier (C040)
+    #[serde(default)]
+    pub reference_identifier: C040,
 }
 
+/// X12 syntax notes: R0203
+pub const SYNTAX_RULES: &[SyntaxRule] = &[SyntaxRule::Required(&[2, 3])];
+
+/// Element dictionary, from REF01
+pub const ELEMENTS: &[ElementDef] = &[id(2, 3), an(1, 50), an(1, 80), composite()];
+
 pub fn get_ref(ref_content: String) -> REF {
     let ref_parts: Vec<&str> = ref_content.split("*").collect();
 
@@ -26,10 +41,14 @@ pub fn get_ref(ref_content: String) -> REF {
     // Extract the qualifier and reference number, skipping the segment ID if present
     let reference_id_number_qualifier = get_element(&ref_parts, start_index);
     let reference_id_number = get_element(&ref_parts, start_index + 1);
+    let reference_id_description = get_element(&ref_parts, start_index + 2);
+    let reference_identifier = get_c040(&get_element(&ref_parts, start_index + 3));
 
     REF {
         reference_id_number_qualifier,
         reference_id_number,
+        reference_id_description,
+        reference_identifier,
     }
 }
 
@@ -37,18 +56,13 @@ pub fn write_ref(rref: REF) -> String {
     if rref.reference_id_number_qualifier.is_empty() {
         return String::new();
     }
-    let mut ref_content = String::new();
-    ref_content.push_str("REF*");
-    ref_content.push_str(&rref.reference_id_number_qualifier);
-
-    // Add reference identification if present
-    if !rref.reference_id_number.is_empty() {
-        ref_content.push_str("*");
-        ref_content.push_str(&rref.reference_id_number);
-    }
-
-    ref_content.push_str("~");
-    ref_content
+    build_segment(&[
+        "REF",
+        &rref.reference_id_number_qualifier,
+        &rref.reference_id_number,
+        &rref.reference_id_description,
+        &write_c040(&rref.reference_identifier),
+    ])
 }
 
 #[cfg(test)]
@@ -69,9 +83,24 @@ mod tests {
         let ref_segment = REF {
             reference_id_number_qualifier: "SY".to_string(),
             reference_id_number: "123456789".to_string(),
+            ..Default::default()
         };
 
         let ref_content = write_ref(ref_segment);
         assert_eq!(ref_content, "REF*SY*123456789~");
     }
+
+    #[test]
+    fn test_ref_description_and_reference_identifier() {
+        let ref_segment = get_ref("6R*12345*LINE ITEM*0B:1234567:LU:CA".to_string());
+        assert_eq!(ref_segment.reference_id_description, "LINE ITEM");
+        let ref04 = &ref_segment.reference_identifier;
+        assert_eq!(ref04.c040_01_reference_identification_qualifier, "0B");
+        assert_eq!(ref04.c040_02_reference_identification, "1234567");
+        assert_eq!(ref04.c040_03_reference_identification_qualifier, "LU");
+        assert_eq!(
+            write_ref(ref_segment),
+            "REF*6R*12345*LINE ITEM*0B:1234567:LU:CA~"
+        );
+    }
 }
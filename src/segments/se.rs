@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, n0, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -8,6 +10,12 @@ pub struct SE {
     pub transaction_set_control_number: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from SE01
+pub const ELEMENTS: &[ElementDef] = &[n0(1, 10), an(4, 9)];
+
 pub fn get_se(se_content: String) -> SE {
     let se_parts: Vec<&str> = se_content.split("*").collect();
 
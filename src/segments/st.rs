@@ -1,4 +1,6 @@
 use crate::helper::edihelper::{build_segment, get_element};
+use crate::helper::element_dictionary::{an, id, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -9,6 +11,12 @@ pub struct ST {
     pub implementation_conven_ref: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from ST01
+pub const ELEMENTS: &[ElementDef] = &[id(3, 3), an(4, 9), an(1, 35)];
+
 pub fn get_st(st_content: String) -> ST {
     let st_parts: Vec<&str> = st_content.split("*").collect();
     let mut implementation_conven_ref = String::new();
@@ -1,4 +1,6 @@
-use crate::helper::edihelper::get_element;
+use crate::helper::edihelper::{get_components, get_element};
+use crate::helper::element_dictionary::{an, composite, dt, id, r, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 /// STC - Claim Status Information
@@ -34,6 +36,25 @@ pub struct STC {
     pub stc12_free_form_message_text: Option<String>,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from STC01
+pub const ELEMENTS: &[ElementDef] = &[
+    composite(),
+    dt(8, 8),
+    id(1, 2),
+    r(1, 18),
+    r(1, 18),
+    dt(8, 8),
+    id(3, 3),
+    dt(8, 8),
+    an(1, 16),
+    composite(),
+    composite(),
+    an(1, 264),
+];
+
 /// Parse an STC segment string into an STC struct
 
 pub fn get_stc(segment: &str) -> STC {
@@ -46,7 +67,7 @@ pub fn get_stc(segment: &str) -> STC {
 
     // Process STC01 - Health Care Claim Status
     if elements.len() > 1 && !elements[1].is_empty() {
-        let stc01_parts: Vec<&str> = elements[1].split(':').collect();
+        let stc01_parts = get_components(elements[1]);
         if stc01_parts.len() > 0 {
             stc.stc01_1_claim_status_category_code = get_element(&stc01_parts, 0);
         }
@@ -105,7 +126,7 @@ pub fn get_stc(segment: &str) -> STC {
 
     // Process STC10 - Health Care Claim Status
     if elements.len() > 10 && !elements[10].is_empty() {
-        let stc10_parts: Vec<&str> = elements[10].split(':').collect();
+        let stc10_parts = get_components(elements[10]);
         if stc10_parts.len() > 0 {
             stc.stc10_1_claim_status_category_code = Some(get_element(&stc10_parts, 0));
         }
@@ -124,7 +145,7 @@ pub fn get_stc(segment: &str) -> STC {
 
     // Process STC11 - Health Care Claim Status
     if elements.len() > 11 && !elements[11].is_empty() {
-        let stc11_parts: Vec<&str> = elements[11].split(':').collect();
+        let stc11_parts = get_components(elements[11]);
         if stc11_parts.len() > 0 {
             stc.stc11_1_claim_status_category_code = Some(get_element(&stc11_parts, 0));
         }
@@ -1,11 +1,14 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, composite, id, r, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
+use crate::segments::composites::{get_c003, write_c003, C003};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
 
 pub struct SV2 {
     pub sv201_service_line_revenue_code: String,
-    pub sv202_procedure_code: String,
+    pub sv202_procedure_code: C003,
     pub sv203_line_item_charge_amount: String,
     pub sv204_unit_or_basis_for_measurement_code: String,
     pub sv205_service_unit_count: String,
@@ -16,6 +19,21 @@ pub struct SV2 {
     pub sv210_level_of_care_code: String,
 }
 
+/// X12 syntax notes: R0102, P0304
+pub const SYNTAX_RULES: &[SyntaxRule] =
+    &[SyntaxRule::Required(&[1, 2]), SyntaxRule::Paired(&[3, 4])];
+
+/// Element dictionary, from SV201
+pub const ELEMENTS: &[ElementDef] = &[
+    an(1, 48),
+    composite(),
+    r(1, 18),
+    id(2, 2),
+    r(1, 15),
+    r(1, 10),
+    r(1, 18),
+];
+
 pub fn get_sv2(sv2_content: String) -> SV2 {
     let sv2_parts: Vec<&str> = sv2_content.split("*").collect();
 
@@ -26,7 +44,7 @@ pub fn get_sv2(sv2_content: String) -> SV2 {
     }
 
     if sv2_parts.len() > 1 && !sv2_parts[1].is_empty() {
-        sv2.sv202_procedure_code = get_element(&sv2_parts, 1);
+        sv2.sv202_procedure_code = get_c003(sv2_parts[1]);
     }
 
     if sv2_parts.len() > 2 && !sv2_parts[2].is_empty() {
@@ -69,7 +87,7 @@ pub fn write_sv2(sv2: SV2) -> String {
     sv2_content.push_str("SV2*");
     sv2_content.push_str(&sv2.sv201_service_line_revenue_code);
     sv2_content.push_str("*");
-    sv2_content.push_str(&sv2.sv202_procedure_code);
+    sv2_content.push_str(&write_c003(&sv2.sv202_procedure_code));
 
     if !sv2.sv203_line_item_charge_amount.is_empty()
         || !sv2.sv204_unit_or_basis_for_measurement_code.is_empty()
@@ -160,14 +178,22 @@ mod tests {
         let sv2_content = "*HC:33510".to_string();
         let sv2 = get_sv2(sv2_content);
         assert_eq!(sv2.sv201_service_line_revenue_code, "");
-        assert_eq!(sv2.sv202_procedure_code, "HC:33510");
+        assert_eq!(
+            sv2.sv202_procedure_code
+                .c003_01_product_or_service_id_qualifier,
+            "HC"
+        );
+        assert_eq!(
+            sv2.sv202_procedure_code.c003_02_product_or_service_id,
+            "33510"
+        );
     }
 
     #[test]
     fn test_write_sv2() {
         let sv2 = SV2 {
             sv201_service_line_revenue_code: "".to_string(),
-            sv202_procedure_code: "HC:33510".to_string(),
+            sv202_procedure_code: get_c003("HC:33510"),
             sv203_line_item_charge_amount: "".to_string(),
             sv204_unit_or_basis_for_measurement_code: "".to_string(),
             sv205_service_unit_count: "".to_string(),
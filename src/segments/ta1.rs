@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{dt, id, n0, tm, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
@@ -11,6 +13,12 @@ pub struct TA1 {
     pub ta05_interchange_note_code: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from TA101
+pub const ELEMENTS: &[ElementDef] = &[n0(9, 9), dt(6, 6), tm(4, 4), id(1, 1), id(3, 3)];
+
 pub fn get_ta1(ta1_content: String) -> TA1 {
     let ta1_parts: Vec<&str> = ta1_content.split("*").collect();
     TA1 {
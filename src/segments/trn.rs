@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{an, id, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -10,6 +12,12 @@ pub struct TRN {
     pub trn04_reference_id: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from TRN01
+pub const ELEMENTS: &[ElementDef] = &[id(1, 2), an(1, 50), an(10, 10), an(1, 50)];
+
 pub fn get_trn(trn_content: String) -> TRN {
     let trn_parts: Vec<&str> = trn_content.split("*").collect();
 
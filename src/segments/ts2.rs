@@ -1,4 +1,6 @@
 use crate::helper::edihelper::{build_segment, get_element};
+use crate::helper::element_dictionary::{r, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 // EDI 835 TS2 - PROVIDER SUMMARY INFORMATION
@@ -26,6 +28,32 @@ pub struct TS2 {
     pub ts219_total_pps_dsh_drg_amount: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from TS201
+pub const ELEMENTS: &[ElementDef] = &[
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 15),
+    r(1, 18),
+    r(1, 15),
+    r(1, 15),
+    r(1, 18),
+    r(1, 18),
+    r(1, 15),
+    r(1, 15),
+    r(1, 15),
+    r(1, 15),
+    r(1, 15),
+    r(1, 18),
+    r(1, 15),
+    r(1, 15),
+    r(1, 15),
+    r(1, 18),
+];
+
 pub fn get_ts2(ts2_content: String) -> TS2 {
     let ts2_parts: Vec<&str> = ts2_content.split("*").collect();
     let mut ts201_total_drg_amount: String = "".to_string();
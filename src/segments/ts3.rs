@@ -1,4 +1,6 @@
 use crate::helper::edihelper::{build_segment, get_element};
+use crate::helper::element_dictionary::{an, dt, id, r, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use serde::{Deserialize, Serialize};
 
 // EDI 835 TS3 - PROVIDER SUMMARY INFORMATION
@@ -23,6 +25,37 @@ pub struct TS3 {
     pub ts324_total_pip_adjustment_amount: String,
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from TS301
+pub const ELEMENTS: &[ElementDef] = &[
+    an(1, 50),
+    id(1, 2),
+    dt(8, 8),
+    r(1, 15),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 15),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+    r(1, 18),
+];
+
 pub fn get_ts3(ts3_content: String) -> TS3 {
     let ts3_parts: Vec<&str> = ts3_content.split("*").collect();
 
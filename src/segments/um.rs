@@ -1,4 +1,6 @@
 use crate::helper::edihelper::get_element;
+use crate::helper::element_dictionary::{composite, id, ElementDef};
+use crate::helper::syntax_rules::SyntaxRule;
 use log::info;
 use serde::{Deserialize, Serialize};
 
@@ -207,6 +209,22 @@ impl UM {
     }
 }
 
+/// X12 syntax notes: none
+pub const SYNTAX_RULES: &[SyntaxRule] = &[];
+
+/// Element dictionary, from UM01
+pub const ELEMENTS: &[ElementDef] = &[
+    id(1, 2),
+    id(1, 1),
+    id(1, 2),
+    composite(),
+    composite(),
+    id(1, 1),
+    id(1, 1),
+    id(1, 1),
+    id(1, 1),
+];
+
 /// Get a UM segment from an EDI segment string
 pub fn get_um(um_content: String) -> UM {
     UM::new(&um_content)
@@ -0,0 +1,605 @@
+//! Streaming parser for large batch files
+//!
+//! `helper::get_file_contents` loads a whole file into memory, which does not
+//! scale to annual 834 enrollments or monthly 835 batches. `EdiStream` reads
+//! segments one at a time from any `BufRead` and yields one repeated unit at a
+//! time: an 834 member (Loop 2000), an 835 claim (Loop 2100) or an 837 claim
+//! (Loop 2300) together with the subscriber and patient levels it belongs to.
+//! Only the current unit and its context are held in memory.
+
+use crate::edi834::loop2000::{get_loop2000, Loop2000};
+use crate::edi835::loop2100::{get_loop_2100s, Loop2100s};
+use crate::edi837::controller::parse_claims;
+use crate::edi837::loop2000b::{parse_loop2000b, Loop2000b};
+use crate::edi837::loop2000c::{parse_loop2000c, Loop2000c};
+use crate::edi837::loop2300::Loop2300;
+use crate::error::{EdiError, EdiResult, SourceLocation};
+use crate::helper::delimiters::Delimiters;
+use crate::helper::loop_path::LoopTracker;
+use crate::helper::tokenizer::tokenize;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::io::BufRead;
+
+/// An 837 claim with the hierarchical levels it belongs to.
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Claim837 {
+    /// Subscriber level (HL03=22), without its patients and claims
+    pub subscriber: Loop2000b,
+    /// Patient level (HL03=23), without its claims; None when the subscriber is the patient
+    pub patient: Option<Loop2000c>,
+    pub claim: Loop2300,
+}
+
+/// One repeated unit of a transaction set.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum StreamItem {
+    Member834(Box<Loop2000>),
+    Claim835(Box<Loop2100s>),
+    Claim837(Box<Claim837>),
+}
+
+/// Reads segments one at a time, normalized to the standard delimiters.
+/// Delimiters are detected from the leading ISA segment.
+pub struct SegmentReader<R: BufRead> {
+    reader: R,
+    delimiters: Option<Delimiters>,
+    /// ISA segment, or the bytes read while looking for one
+    header: Vec<u8>,
+    /// Segments returned so far
+    index: usize,
+    /// Bytes and line breaks read before the next segment
+    consumed: usize,
+    lines: usize,
+    location: SourceLocation,
+    /// An error ended the input; nothing more is read
+    failed: bool,
+}
+
+impl<R: BufRead> SegmentReader<R> {
+    pub fn new(reader: R) -> Self {
+        SegmentReader {
+            reader,
+            delimiters: None,
+            header: Vec::new(),
+            index: 0,
+            consumed: 0,
+            lines: 0,
+            location: SourceLocation::default(),
+            failed: false,
+        }
+    }
+
+    /// Position of the last segment read in the input. The loop path is left
+    /// empty, since segments are read without regard to their transaction set.
+    pub fn location(&self) -> &SourceLocation {
+        &self.location
+    }
+
+    /// Delimiters of the interchange, once the first segment has been read.
+    pub fn delimiters(&self) -> Option<Delimiters> {
+        self.delimiters
+    }
+
+    /// Read the ISA segment byte by byte, since it declares the segment
+    /// terminator, and detect the delimiters. Content without an ISA uses the
+    /// default delimiters.
+    fn read_header(&mut self) -> EdiResult<()> {
+        let mut byte = [0u8; 1];
+        let mut element = None;
+        let mut separators = 0;
+        let mut trailing = 0;
+        let mut complete = false;
+        while self.reader.read(&mut byte)? == 1 {
+            self.header.push(byte[0]);
+            // Skip leading whitespace and a UTF-8 byte order mark
+            let start = match self
+                .header
+                .iter()
+                .position(|b| !b.is_ascii_whitespace() && ![0xEF, 0xBB, 0xBF].contains(b))
+            {
+                Some(start) => &self.header[start..],
+                None => continue,
+            };
+            match element {
+                None if start.len() <= 3 => {
+                    if !b"ISA".starts_with(start) {
+                        break;
+                    }
+                }
+                None => {
+                    element = Some(byte[0]);
+                    separators = 1;
+                }
+                // ISA has 16 elements; ISA16 is followed by the segment terminator
+                Some(separator) if separators < 16 => {
+                    if byte[0] == separator {
+                        separators += 1;
+                    }
+                }
+                Some(_) => {
+                    trailing += 1;
+                    if trailing == 2 {
+                        complete = true;
+                        break;
+                    }
+                }
+            }
+        }
+
+        let delimiters = if complete {
+            Delimiters::detect(&String::from_utf8_lossy(&self.header))
+        } else {
+            Delimiters::default()
+        };
+        if !delimiters.segment.is_ascii() {
+            let header = self.header.clone();
+            self.advance(&header, "ISA");
+            return Err(EdiError::UnsupportedFormat(format!(
+                "Non-ASCII segment terminator {:?}",
+                delimiters.segment
+            ))
+            .at(self.location.clone()));
+        }
+        self.delimiters = Some(delimiters);
+        Ok(())
+    }
+
+    /// Record the position of a segment read as `buffer`, and move past it.
+    fn advance(&mut self, buffer: &[u8], segment_id: &str) {
+        let lead = buffer
+            .iter()
+            .position(|b| !b.is_ascii_whitespace() && ![0xEF, 0xBB, 0xBF].contains(b))
+            .unwrap_or(buffer.len());
+        self.location = SourceLocation {
+            segment_index: self.index,
+            offset: self.consumed + lead,
+            line: self.lines + 1 + count_lines(&buffer[..lead]),
+            segment_id: segment_id.to_string(),
+            ..Default::default()
+        };
+        self.index += 1;
+        self.skip(buffer);
+    }
+
+    /// Move past bytes that hold no segment.
+    fn skip(&mut self, buffer: &[u8]) {
+        self.consumed += buffer.len();
+        self.lines += count_lines(buffer);
+    }
+
+    fn normalize(&self, segment: &[u8]) -> String {
+        let delimiters = self.delimiters.unwrap_or_default();
+        let segment = String::from_utf8_lossy(segment);
+        let segment = segment.trim_matches(|c: char| {
+            c == delimiters.segment || c.is_whitespace() || c == '\u{feff}'
+        });
+        let normalized = delimiters.normalized();
+        // Items carry no diagnostics, see `Delimiters::check_data`
+        for (data, swapped) in delimiters.swaps(&normalized) {
+            if segment.contains(data) {
+                warn!(
+                    "Data character '{}' in segment {} is a standard delimiter and reads as '{}'",
+                    data, self.index, swapped
+                );
+            }
+        }
+        delimiters.convert(segment, &normalized)
+    }
+}
+
+impl<R: BufRead> Iterator for SegmentReader<R> {
+    /// Segment text without the terminator, e.g. `CLP*A*1*100`
+    type Item = EdiResult<String>;
+
+    /// Reading stops after the first error, which is returned once: a failing
+    /// reader or an unusable header cannot be read past.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        if self.delimiters.is_none() {
+            if let Err(e) = self.read_header() {
+                self.failed = true;
+                return Some(Err(e));
+            }
+        }
+        let terminator = self.delimiters.unwrap_or_default().segment as u8;
+
+        loop {
+            // Bytes read by `read_header` start the first segment
+            let mut buffer = std::mem::take(&mut self.header);
+            if buffer.last() != Some(&terminator) {
+                match self.reader.read_until(terminator, &mut buffer) {
+                    Ok(0) if buffer.is_empty() => return None,
+                    Ok(_) => {}
+                    Err(e) => {
+                        self.failed = true;
+                        return Some(Err(EdiError::IoError(e)));
+                    }
+                }
+            }
+            let segment = self.normalize(&buffer);
+            if segment.is_empty() {
+                self.skip(&buffer);
+            } else {
+                self.advance(&buffer, segment.split('*').next().unwrap_or(""));
+                return Some(Ok(segment));
+            }
+        }
+    }
+}
+
+/// Streaming parser yielding one `StreamItem` at a time from 834, 835 and 837
+/// transaction sets. Other transaction sets are skipped.
+pub struct EdiStream<R: BufRead> {
+    segments: SegmentReader<R>,
+    loops: LoopTracker,
+    pending: Option<(String, SourceLocation)>,
+    transaction_set_id: String,
+    unit: Vec<String>,
+    /// Position of the segment starting the current unit
+    unit_location: SourceLocation,
+    level: Vec<String>,
+    subscriber: Option<Loop2000b>,
+    patient: Option<Loop2000c>,
+}
+
+impl<R: BufRead> EdiStream<R> {
+    pub fn new(reader: R) -> Self {
+        EdiStream {
+            segments: SegmentReader::new(reader),
+            loops: LoopTracker::default(),
+            pending: None,
+            transaction_set_id: String::new(),
+            unit: Vec::new(),
+            unit_location: SourceLocation::default(),
+            level: Vec::new(),
+            subscriber: None,
+            patient: None,
+        }
+    }
+
+    /// Segment IDs starting a unit of the current transaction set.
+    fn unit_start(&self) -> &'static [&'static str] {
+        match self.transaction_set_id.as_str() {
+            "834" => &["INS"],
+            "835" => &["CLP"],
+            "837" => &["CLM"],
+            _ => &[],
+        }
+    }
+
+    /// Segment IDs ending a unit of the current transaction set.
+    fn unit_end(&self) -> &'static [&'static str] {
+        match self.transaction_set_id.as_str() {
+            "834" => &["INS", "SE"],
+            "835" => &["CLP", "LX", "PLB", "SE"],
+            "837" => &["CLM", "HL", "SE"],
+            _ => &["SE"],
+        }
+    }
+
+    /// The next segment and its position, including its loop path.
+    fn next_segment(&mut self) -> Option<EdiResult<(String, SourceLocation)>> {
+        if let Some(pending) = self.pending.take() {
+            return Some(Ok(pending));
+        }
+        let segment = match self.segments.next()? {
+            Ok(segment) => segment,
+            Err(e) => return Some(Err(e)),
+        };
+        let mut location = self.segments.location().clone();
+        location.loop_path = match tokenize(&segment).first() {
+            Some(parsed) => self.loops.advance(parsed),
+            None => String::new(),
+        };
+        Some(Ok((segment, location)))
+    }
+
+    /// Parse the 837 HL level collected so far into the claim context.
+    fn flush_level(&mut self) {
+        if self.level.is_empty() {
+            return;
+        }
+        let contents = to_contents(&self.level);
+        self.level.clear();
+        match tokenize(&contents).first().map(|hl| hl.element(2)) {
+            Some("22") => {
+                let (mut subscriber, _) = parse_loop2000b(&contents);
+                subscriber.loop2300.clear();
+                self.subscriber = Some(subscriber);
+                self.patient = None;
+            }
+            Some("23") => {
+                let (mut patient, _) = parse_loop2000c(&contents);
+                patient.loop2300.clear();
+                self.patient = Some(patient);
+            }
+            _ => {}
+        }
+    }
+
+    /// Parse the collected unit segments into a stream item.
+    fn flush_unit(&mut self) -> Option<StreamItem> {
+        if self.unit.is_empty() {
+            return None;
+        }
+        let contents = to_contents(&self.unit);
+        self.unit.clear();
+        match self.transaction_set_id.as_str() {
+            "834" => {
+                let (member, _) = get_loop2000(&contents);
+                Some(StreamItem::Member834(Box::new(member)))
+            }
+            "835" => {
+                let (mut claims, _) = get_loop_2100s(&contents);
+                claims
+                    .pop()
+                    .map(|claim| StreamItem::Claim835(Box::new(claim)))
+            }
+            "837" => {
+                let mut leftover = String::new();
+                let mut claims = parse_claims(&tokenize(&contents), &mut leftover);
+                claims.pop().map(|claim| {
+                    StreamItem::Claim837(Box::new(Claim837 {
+                        subscriber: self.subscriber.clone().unwrap_or_default(),
+                        patient: self.patient.clone(),
+                        claim,
+                    }))
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for EdiStream<R> {
+    type Item = EdiResult<StreamItem>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (segment, location) = match self.next_segment() {
+                Some(Ok(next)) => next,
+                Some(Err(e)) => return Some(Err(e)),
+                None => return self.flush_unit().map(Ok),
+            };
+            let id = segment.split('*').next().unwrap_or("");
+
+            if !self.unit.is_empty() && self.unit_end().contains(&id) {
+                self.pending = Some((segment, location));
+                if let Some(item) = self.flush_unit() {
+                    return Some(Ok(item));
+                }
+                continue;
+            }
+
+            match id {
+                "ST" => {
+                    self.transaction_set_id = segment.split('*').nth(1).unwrap_or("").to_string();
+                    info!("Streaming transaction set {}", self.transaction_set_id);
+                    self.subscriber = None;
+                    self.patient = None;
+                }
+                "SE" => self.flush_level(),
+                _ if self.unit_start().contains(&id) => {
+                    self.flush_level();
+                    self.unit.push(segment);
+                    self.unit_location = location;
+                }
+                _ if !self.unit.is_empty() => self.unit.push(segment),
+                "HL" if self.transaction_set_id == "837" => {
+                    self.flush_level();
+                    self.level.push(segment);
+                }
+                _ if !self.level.is_empty() => self.level.push(segment),
+                _ => {}
+            }
+
+            if self.unit.len() > MAX_UNIT_SEGMENTS {
+                warn!(
+                    "Unit exceeds {} segments without an end segment",
+                    MAX_UNIT_SEGMENTS
+                );
+                // Drop the unit rather than report each further segment of it
+                self.unit.clear();
+                return Some(Err(EdiError::ValidationError(format!(
+                    "{} unit exceeds {} segments",
+                    self.transaction_set_id, MAX_UNIT_SEGMENTS
+                ))
+                .at(self.unit_location.clone())));
+            }
+        }
+    }
+}
+
+/// Upper bound on the segments of a single unit, keeping memory bounded on malformed input.
+const MAX_UNIT_SEGMENTS: usize = 100_000;
+
+fn count_lines(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|&&b| b == b'\n').count()
+}
+
+fn to_contents(segments: &[String]) -> String {
+    let mut contents = String::new();
+    for segment in segments {
+        contents.push_str(segment);
+        contents.push('~');
+    }
+    contents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufReader, Cursor, Read};
+
+    const ISA: &str = "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230501*1200*^*00501*000000001*0*T*:~";
+
+    #[test]
+    fn test_segment_reader_custom_delimiters() {
+        let contents = "ISA|00|          |00|          |ZZ|SENDER         |ZZ|RECEIVER       |230501|1200|!|00501|000000001|0|T|>\nGS|HP|S|R\nST|835|0001\nSVC|HC>99213|100\n";
+        let segments: Vec<String> = SegmentReader::new(Cursor::new(contents))
+            .map(|s| s.unwrap())
+            .collect();
+        assert_eq!(segments.len(), 4);
+        assert!(segments[0].starts_with("ISA*00*"));
+        assert!(segments[0].ends_with("*^*00501*000000001*0*T*:"));
+        assert_eq!(segments[3], "SVC*HC:99213*100");
+    }
+
+    #[test]
+    fn test_segment_reader_without_isa() {
+        let segments: Vec<String> = SegmentReader::new(Cursor::new("ST*835*1~\nSE*2*1~"))
+            .map(|s| s.unwrap())
+            .collect();
+        assert_eq!(segments, vec!["ST*835*1", "SE*2*1"]);
+    }
+
+    #[test]
+    fn test_segment_reader_locations() {
+        let contents = "\u{feff}ISA|00|          |00|          |ZZ|SENDER         |ZZ|RECEIVER       |230501|1200|!|00501|000000001|0|T|>\nGS|HP|S|R\n\nST|835|0001\n";
+        let mut reader = SegmentReader::new(Cursor::new(contents));
+        let mut locations = Vec::new();
+        while let Some(segment) = reader.next() {
+            segment.unwrap();
+            locations.push(reader.location().clone());
+        }
+        let st = &locations[2];
+        assert_eq!((st.segment_index, st.line), (2, 4));
+        assert_eq!(st.segment_id, "ST");
+        assert!(contents[st.offset..].starts_with("ST|835"));
+        assert_eq!(locations[0].offset, 3);
+    }
+
+    #[test]
+    fn test_stream_oversized_unit_location() {
+        let mut contents = format!(
+            "{}GS*HC*S*R*20230501*1200*1*X*005010X222A1~ST*837*0001*005010X222A1~BHT*0019*00*1*20230501*1200*CH~\
+HL*1**20*1~NM1*85*2*BILLING*****XX*1234567890~HL*2*1*22*0~SBR*P*18*******CI~\
+CLM*A1*100***11:B:1*Y*A*Y*I~",
+            ISA
+        );
+        contents.push_str(&"NTE*ADD*NOTE~".repeat(MAX_UNIT_SEGMENTS));
+        let error = EdiStream::new(Cursor::new(contents))
+            .find_map(|item| item.err())
+            .unwrap();
+        assert!(matches!(error.kind(), EdiError::ValidationError(_)));
+        let location = error.location().unwrap();
+        assert_eq!(location.segment_index, 8);
+        assert_eq!(location.segment_id, "CLM");
+        assert_eq!(location.loop_path, "2000B/2300");
+    }
+
+    /// A reader failing on every read, like a directory opened as a file.
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("read failed"))
+        }
+    }
+
+    #[test]
+    fn test_read_error_ends_stream() {
+        let reader = BufReader::new(FailingReader);
+        let items: Vec<_> = EdiStream::new(reader).take(10).collect();
+        assert_eq!(items.len(), 1);
+        assert!(matches!(
+            items[0].as_ref().unwrap_err().kind(),
+            EdiError::IoError(_)
+        ));
+
+        // Failing after the header
+        let reader = Cursor::new(format!("{}GS*HC*S*R~", ISA)).chain(FailingReader);
+        let segments: Vec<_> = SegmentReader::new(BufReader::new(reader))
+            .take(10)
+            .collect();
+        assert_eq!(segments.len(), 3);
+        assert!(segments[..2].iter().all(|s| s.is_ok()));
+        assert!(matches!(segments[2], Err(EdiError::IoError(_))));
+    }
+
+    #[test]
+    fn test_stream_835_claims() {
+        let contents = format!(
+            "{}GS*HP*S*R*20230501*1200*1*X*005010X221A1~ST*835*0001~BPR*I*150*C*ACH~TRN*1*12345*1512345678~\
+N1*PR*PAYER~N1*PE*PAYEE*XX*1234567890~LX*1~CLP*CLAIM1*1*100*100**MC*1~SVC*HC:99213*100*100**1~\
+CLP*CLAIM2*1*50*50**MC*1~PLB*1234567890*20231231*CV:9876514*-1.27~SE*11*0001~GE*1*1~IEA*1*000000001~",
+            ISA
+        );
+        let items: Vec<StreamItem> = EdiStream::new(Cursor::new(contents))
+            .map(|item| item.unwrap())
+            .collect();
+        assert_eq!(items.len(), 2);
+        match &items[0] {
+            StreamItem::Claim835(claim) => {
+                assert_eq!(claim.clp_segments.clp01_patient_control_number, "CLAIM1");
+                assert_eq!(claim.loop2110s.len(), 1);
+            }
+            other => panic!("unexpected item {:?}", other),
+        }
+        match &items[1] {
+            StreamItem::Claim835(claim) => {
+                assert_eq!(claim.clp_segments.clp01_patient_control_number, "CLAIM2");
+            }
+            other => panic!("unexpected item {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_stream_834_members() {
+        let contents = format!(
+            "{}GS*BE*S*R*20230501*1200*1*X*005010X220A1~ST*834*0001*005010X220A1~BGN*00*12456*20230501*1200****2~\
+N1*P5*SPONSOR*FI*999888777~N1*IN*INSURER*FI*654456654~\
+INS*Y*18*030*XN*A*E**FT~REF*0F*111111111~NM1*IL*1*DOE*JOHN****34*111111111~\
+INS*Y*18*030*XN*A*E**FT~REF*0F*222222222~NM1*IL*1*DOE*JANE****34*222222222~\
+SE*10*0001~GE*1*1~IEA*1*000000001~",
+            ISA
+        );
+        let items: Vec<StreamItem> = EdiStream::new(Cursor::new(contents))
+            .map(|item| item.unwrap())
+            .collect();
+        assert_eq!(items.len(), 2);
+        assert!(items
+            .iter()
+            .all(|item| matches!(item, StreamItem::Member834(_))));
+    }
+
+    #[test]
+    fn test_stream_837_claims_with_context() {
+        let contents = format!(
+            "{}GS*HC*S*R*20230501*1200*1*X*005010X222A1~ST*837*0001*005010X222A1~BHT*0019*00*1*20230501*1200*CH~\
+HL*1**20*1~NM1*85*2*BILLING*****XX*1234567890~\
+HL*2*1*22*0~SBR*P*18*******CI~NM1*IL*1*DOE*JOHN****MI*111~\
+CLM*A1*100***11:B:1*Y*A*Y*I~HI*ABK:J209~LX*1~SV1*HC:99213*100*UN*1***1~\
+HL*3*1*22*1~SBR*P********CI~NM1*IL*1*ROE*RICHARD****MI*222~\
+HL*4*3*23*0~PAT*19~NM1*QC*1*ROE*RITA~\
+CLM*B1*50***11:B:1*Y*A*Y*I~LX*1~SV1*HC:99212*50*UN*1***1~\
+SE*20*0001~GE*1*1~IEA*1*000000001~",
+            ISA
+        );
+        let items: Vec<StreamItem> = EdiStream::new(Cursor::new(contents))
+            .map(|item| item.unwrap())
+            .collect();
+        assert_eq!(items.len(), 2);
+
+        let StreamItem::Claim837(first) = &items[0] else {
+            panic!("expected 837 claim");
+        };
+        assert!(first.claim.clm.starts_with("CLM*A1*"));
+        assert_eq!(first.claim.loop2400.len(), 1);
+        assert_eq!(
+            first.subscriber.nm1_subscriber.as_deref(),
+            Some("NM1*IL*1*DOE*JOHN****MI*111~")
+        );
+        assert!(first.patient.is_none());
+
+        let StreamItem::Claim837(second) = &items[1] else {
+            panic!("expected 837 claim");
+        };
+        assert!(second.claim.clm.starts_with("CLM*B1*"));
+        assert!(second.subscriber.hl.starts_with("HL*3*"));
+        assert_eq!(second.patient.as_ref().unwrap().pat, "PAT*19~");
+    }
+}
@@ -1,4 +1,7 @@
 use crate::error::EdiResult;
+use crate::helper::helper::clean_contents;
+use crate::helper::tokenizer::tokenize;
+use crate::helper::writer_options::WriterOptions;
 use serde::{de::DeserializeOwned, Serialize};
 
 /// Trait defining common behavior for all transaction sets
@@ -10,11 +13,67 @@ pub trait TransactionSet: Serialize + DeserializeOwned + Default {
         Self: Sized;
 
     /// Convert this transaction set to EDI format
-    fn to_edi(&self) -> String;
+    fn to_edi(&self) -> EdiResult<String> {
+        self.to_edi_with(&WriterOptions::default())
+    }
+
+    /// Convert this transaction set to EDI format laid out per `options`
+    fn to_edi_with(&self, options: &WriterOptions) -> EdiResult<String>;
 
     /// Get the transaction type identifier (e.g., "835", "999", "270")
     fn get_transaction_type() -> &'static str;
 
     /// Detect if the given content matches this transaction set
-    fn detect(contents: &str) -> bool;
+    fn detect(contents: &str) -> bool {
+        detect_transaction_set(contents).as_deref() == Some(Self::get_transaction_type())
+    }
+}
+
+/// Detect the transaction set identifier (ST01) of the first transaction set
+/// in raw EDI content, whatever its delimiters.
+pub fn detect_transaction_set(contents: &str) -> Option<String> {
+    let contents = clean_contents(contents.to_string());
+    tokenize(&contents)
+        .iter()
+        .find(|segment| segment.id == "ST")
+        .map(|st| st.element(0).to_string())
+        .filter(|code| !code.is_empty())
+}
+
+/// Detect the implementation guide of the first transaction set in raw EDI
+/// content: ST03 when present, otherwise GS08 (e.g. `005010X222A1`).
+pub fn detect_implementation_guide(contents: &str) -> Option<String> {
+    let contents = clean_contents(contents.to_string());
+    let segments = tokenize(&contents);
+    let element = |id: &str, index: usize| {
+        segments
+            .iter()
+            .find(|segment| segment.id == id)
+            .map(|segment| segment.element(index))
+            .filter(|value| !value.is_empty())
+    };
+    element("ST", 2)
+        .or_else(|| element("GS", 7))
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_transaction_set() {
+        let edi = "ISA|00|          |00|          |ZZ|SENDER         |ZZ|RECEIVER       |230501|1200|^|00501|000000001|0|T|:~\nGS|HC|TEST|R|20230501|1200|1|X|005010X222A1~\nST|837|0001|005010X222A1~\n";
+        assert_eq!(detect_transaction_set(edi).as_deref(), Some("837"));
+        assert_eq!(detect_transaction_set("GS*HC*ST*R~"), None);
+        assert_eq!(
+            detect_implementation_guide(edi).as_deref(),
+            Some("005010X222A1")
+        );
+        assert_eq!(
+            detect_implementation_guide("GS*FA*S*R*20230501*1200*2*X*005010X231A1~ST*999*0001~")
+                .as_deref(),
+            Some("005010X231A1")
+        );
+    }
 }
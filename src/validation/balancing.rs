@@ -0,0 +1,541 @@
+//! Balancing rules (SNIP 3)
+//!
+//! Amounts that the implementation guides require to add up, compared in
+//! cents. Each finding names both sides and the variance, located at the
+//! amount that does not balance. Amounts that are not valid decimals are
+//! left to the element checks of level 1; totals that overflow cannot be
+//! balanced and are not checked.
+
+use crate::helper::diagnostics::{Diagnostic, DiagnosticCode, Diagnostics, Severity};
+use crate::helper::numeric_validation::{amount_in_cents, format_cents};
+use crate::helper::tokenizer::Segment;
+use crate::validation::rules::claims;
+use crate::validation::{Rule, SnipLevel, ValidationContext};
+
+/// Balancing rules of every transaction set.
+pub(crate) fn built_in() -> Vec<Box<dyn Rule>> {
+    vec![
+        Box::new(RemittanceBalancingRule),
+        Box::new(ClaimBalancingRule),
+        Box::new(PremiumBalancingRule),
+    ]
+}
+
+/// Element `element` (1-based) of a segment in cents.
+fn amount(segment: &Segment, element: usize) -> Option<i128> {
+    amount_in_cents(segment.element(element - 1))
+}
+
+/// `a + b`, or `None` when either is unknown or the sum overflows.
+fn add(a: Option<i128>, b: Option<i128>) -> Option<i128> {
+    a?.checked_add(b?)
+}
+
+/// `a - b`, or `None` when either is unknown or the difference overflows.
+fn subtract(a: Option<i128>, b: Option<i128>) -> Option<i128> {
+    a?.checked_sub(b?)
+}
+
+/// Sum of elements `elements` (1-based) of a segment in cents.
+fn sum(segment: &Segment, elements: &[usize]) -> Option<i128> {
+    elements.iter().try_fold(0, |total: i128, &element| {
+        total.checked_add(amount(segment, element)?)
+    })
+}
+
+/// Report `expected` against `actual` at `element` of the segment in
+/// `position`, unless they balance.
+fn check_balance(
+    context: &ValidationContext,
+    diagnostics: &mut Diagnostics,
+    position: usize,
+    element: usize,
+    description: String,
+    (expected, actual): (i128, i128),
+) {
+    let Some(variance) = expected.checked_sub(actual).filter(|&v| v != 0) else {
+        return;
+    };
+    diagnostics.push(context.locate(
+        Diagnostic::new(
+            Severity::Error,
+            DiagnosticCode::AmountMismatch,
+            format!(
+                "{}: {} against {} (variance {})",
+                description,
+                format_cents(expected),
+                format_cents(actual),
+                format_cents(variance)
+            ),
+        ),
+        position,
+        Some(element),
+    ));
+}
+
+/// CAS03, CAS06, ... CAS18: the adjustment amounts of a CAS segment.
+const CAS_AMOUNTS: &[usize] = &[3, 6, 9, 12, 15, 18];
+
+/// PLB04, PLB06, ... PLB14: the provider adjustment amounts of a PLB segment.
+const PLB_AMOUNTS: &[usize] = &[4, 6, 8, 10, 12, 14];
+
+/// A claim, service line or line adjudication whose charge minus payment
+/// must equal its CAS adjustments; amounts are `None` once one is invalid.
+struct Unit {
+    position: usize,
+    /// Element (1-based) holding the payment, where a finding is located
+    element: usize,
+    difference: Option<i128>,
+    adjustments: Option<i128>,
+}
+
+impl Unit {
+    fn new(position: usize, element: usize, charge: Option<i128>, payment: Option<i128>) -> Unit {
+        Unit {
+            position,
+            element,
+            difference: subtract(charge, payment),
+            adjustments: Some(0),
+        }
+    }
+
+    fn adjust(&mut self, adjustments: Option<i128>) {
+        self.adjustments = add(self.adjustments, adjustments);
+    }
+
+    fn close(
+        self,
+        context: &ValidationContext,
+        diagnostics: &mut Diagnostics,
+        description: String,
+    ) {
+        if let (Some(difference), Some(adjustments)) = (self.difference, self.adjustments) {
+            check_balance(
+                context,
+                diagnostics,
+                self.position,
+                self.element,
+                description,
+                (difference, adjustments),
+            );
+        }
+    }
+}
+
+/// 835 (X221) balancing: every service line's SVC02 - SVC03 equals its CAS
+/// adjustments, every claim's CLP03 - CLP04 equals its claim and service
+/// line adjustments, and BPR02 equals the CLP04 total minus the PLB
+/// provider adjustments.
+struct RemittanceBalancingRule;
+
+impl RemittanceBalancingRule {
+    fn close_service(
+        context: &ValidationContext,
+        diagnostics: &mut Diagnostics,
+        service: Option<Unit>,
+    ) {
+        if let Some(service) = service {
+            service.close(
+                context,
+                diagnostics,
+                "Service line SVC02 - SVC03 does not equal its CAS adjustments".to_string(),
+            );
+        }
+    }
+
+    fn close_claim(
+        context: &ValidationContext,
+        diagnostics: &mut Diagnostics,
+        claim: Option<Unit>,
+    ) {
+        if let Some(claim) = claim {
+            let description = format!(
+                "Claim {} CLP03 - CLP04 does not equal its claim and service line CAS adjustments",
+                context.segments[claim.position].element(0)
+            );
+            claim.close(context, diagnostics, description);
+        }
+    }
+}
+
+impl Rule for RemittanceBalancingRule {
+    fn id(&self) -> &str {
+        "835-BALANCING"
+    }
+
+    fn level(&self) -> SnipLevel {
+        SnipLevel::Balancing
+    }
+
+    fn applies_to(&self, transaction_set_id: &str) -> bool {
+        transaction_set_id == "835"
+    }
+
+    fn check(&self, context: &ValidationContext, diagnostics: &mut Diagnostics) {
+        let mut claim: Option<Unit> = None;
+        let mut service: Option<Unit> = None;
+        let mut payments = Some(0);
+        let mut provider_adjustments = Some(0);
+        let mut bpr = None;
+
+        for (position, segment) in context.segments.iter().enumerate() {
+            match segment.id {
+                "BPR" => bpr = Some(position),
+                "CLP" => {
+                    Self::close_service(context, diagnostics, service.take());
+                    Self::close_claim(context, diagnostics, claim.take());
+                    let payment = amount(segment, 4);
+                    payments = add(payments, payment);
+                    claim = Some(Unit::new(position, 4, amount(segment, 3), payment));
+                }
+                "SVC" => {
+                    Self::close_service(context, diagnostics, service.take());
+                    service = Some(Unit::new(
+                        position,
+                        3,
+                        amount(segment, 2),
+                        amount(segment, 3),
+                    ));
+                }
+                "CAS" => {
+                    let adjustments = sum(segment, CAS_AMOUNTS);
+                    // Line adjustments count towards the line and the claim
+                    for unit in [service.as_mut(), claim.as_mut()].into_iter().flatten() {
+                        unit.adjust(adjustments);
+                    }
+                }
+                "LX" | "PLB" | "SE" => {
+                    Self::close_service(context, diagnostics, service.take());
+                    Self::close_claim(context, diagnostics, claim.take());
+                    if segment.id == "PLB" {
+                        provider_adjustments = add(provider_adjustments, sum(segment, PLB_AMOUNTS));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Self::close_service(context, diagnostics, service);
+        Self::close_claim(context, diagnostics, claim);
+
+        let Some(bpr) = bpr else {
+            return;
+        };
+        let segment = &context.segments[bpr];
+        if let (Some(total), Some(net)) = (
+            signed_total(segment),
+            subtract(payments, provider_adjustments),
+        ) {
+            check_balance(
+                context,
+                diagnostics,
+                bpr,
+                2,
+                "BPR02 does not equal the CLP04 total minus the PLB adjustments".to_string(),
+                (total, net),
+            );
+        }
+    }
+}
+
+/// 837P/I/D balancing: CLM02 equals the total of the claim's service line
+/// charges (SV102, SV203 or SV302), and when other payers adjudicated a line
+/// (2430), the line charge minus SVD02 equals the CAS adjustments of that
+/// adjudication.
+struct ClaimBalancingRule;
+
+impl ClaimBalancingRule {
+    fn close_adjudication(
+        context: &ValidationContext,
+        diagnostics: &mut Diagnostics,
+        adjudication: Option<Unit>,
+    ) {
+        if let Some(adjudication) = adjudication {
+            let description = format!(
+                "Line charge - SVD02 of payer {} does not equal its CAS adjustments",
+                context.segments[adjudication.position].element(0)
+            );
+            adjudication.close(context, diagnostics, description);
+        }
+    }
+}
+
+impl Rule for ClaimBalancingRule {
+    fn id(&self) -> &str {
+        "837-BALANCING"
+    }
+
+    fn level(&self) -> SnipLevel {
+        SnipLevel::Balancing
+    }
+
+    fn applies_to(&self, transaction_set_id: &str) -> bool {
+        transaction_set_id == "837"
+    }
+
+    fn check(&self, context: &ValidationContext, diagnostics: &mut Diagnostics) {
+        for (clm, end) in claims(context) {
+            let mut lines = 0;
+            let mut charges = Some(0);
+            let mut line_charge = None;
+            let mut adjudication: Option<Unit> = None;
+
+            for position in clm + 1..end {
+                let segment = &context.segments[position];
+                match segment.id {
+                    "SV1" | "SV2" | "SV3" => {
+                        Self::close_adjudication(context, diagnostics, adjudication.take());
+                        let charge = amount(segment, if segment.id == "SV2" { 3 } else { 2 });
+                        lines += 1;
+                        charges = add(charges, charge);
+                        line_charge = charge;
+                    }
+                    "SVD" => {
+                        Self::close_adjudication(context, diagnostics, adjudication.take());
+                        adjudication =
+                            Some(Unit::new(position, 2, line_charge, amount(segment, 2)));
+                    }
+                    "CAS" if context.paths[position].ends_with("2430") => {
+                        if let Some(adjudication) = adjudication.as_mut() {
+                            adjudication.adjust(sum(segment, CAS_AMOUNTS));
+                        }
+                    }
+                    "LX" => {
+                        Self::close_adjudication(context, diagnostics, adjudication.take());
+                        line_charge = None;
+                    }
+                    _ => {}
+                }
+            }
+            Self::close_adjudication(context, diagnostics, adjudication);
+
+            let claim = &context.segments[clm];
+            if let (true, Some(total), Some(charges)) = (lines > 0, amount(claim, 2), charges) {
+                check_balance(
+                    context,
+                    diagnostics,
+                    clm,
+                    2,
+                    format!(
+                        "Claim {} CLM02 does not equal the total of its {} service line charges",
+                        claim.element(0),
+                        lines
+                    ),
+                    (total, charges),
+                );
+            }
+        }
+    }
+}
+
+/// BPR02 in cents, negative when BPR03 says the payment is a debit.
+fn signed_total(bpr: &Segment) -> Option<i128> {
+    let total = amount(bpr, 2)?;
+    if bpr.element(2) == "D" {
+        total.checked_neg()
+    } else {
+        Some(total)
+    }
+}
+
+/// 820 (X218) balancing: BPR02 equals the total of the RMR04 premium
+/// payments of every ENT loop, net of the ADX01 adjustments.
+struct PremiumBalancingRule;
+
+impl Rule for PremiumBalancingRule {
+    fn id(&self) -> &str {
+        "820-BALANCING"
+    }
+
+    fn level(&self) -> SnipLevel {
+        SnipLevel::Balancing
+    }
+
+    fn applies_to(&self, transaction_set_id: &str) -> bool {
+        transaction_set_id == "820"
+    }
+
+    fn check(&self, context: &ValidationContext, diagnostics: &mut Diagnostics) {
+        let Some(bpr) = context.positions("BPR").next() else {
+            return;
+        };
+        let total = |id: &str, element: usize| -> Option<i128> {
+            context.positions(id).try_fold(0, |total: i128, position| {
+                total.checked_add(amount(&context.segments[position], element)?)
+            })
+        };
+        let entities = context.positions("ENT").count();
+        let segment = &context.segments[bpr];
+        if let (Some(payment), Some(net)) =
+            (signed_total(segment), add(total("RMR", 4), total("ADX", 1)))
+        {
+            check_balance(
+                context,
+                diagnostics,
+                bpr,
+                2,
+                format!(
+                    "BPR02 does not equal the RMR04 total net of ADX adjustments across {} ENT loop{}",
+                    entities,
+                    if entities == 1 { "" } else { "s" }
+                ),
+                (payment, net),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::validation::{SnipLevel, Validator};
+
+    const REMITTANCE: (&str, &str, &str) = ("HP", "835", "005010X221A1");
+    const CLAIM: (&str, &str, &str) = ("HC", "837", "005010X222A1");
+
+    const ISA: &str = "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230501*1200*^*00501*000000001*0*T*:~";
+
+    /// Balancing findings of a transaction set with header `header` (GS01, ST01 and guide).
+    fn balancing((gs01, st01, guide): (&str, &str, &str), body: &str) -> Vec<(String, String)> {
+        let segments = body.matches('~').count() + 2;
+        let contents = format!(
+            "{}GS*{}*SENDER*RECEIVER*20230501*1200*1*X*{}~ST*{}*0001~{}SE*{}*0001~GE*1*1~IEA*1*000000001~",
+            ISA, gs01, guide, st01, body, segments
+        );
+        Validator::default()
+            .validate_edi(&contents, &[SnipLevel::Balancing])
+            .unwrap()
+            .iter()
+            .map(|f| (f.diagnostic.loop_path.clone(), f.diagnostic.message.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_remittance_balanced() {
+        assert_eq!(balancing(
+            REMITTANCE,
+            "BPR*I*150*C*ACH*CCP*01*011000015*DA*123456*1512345678**01*031100209*DA*654321*20230501~\
+TRN*1*1*1512345678~N1*PR*PAYER~N1*PE*PAYEE*XX*1234567893~LX*1~\
+CLP*A*1*200*130*10*MC*1~CAS*PR*2*10~SVC*HC:99213*120*80~CAS*CO*45*40~SVC*HC:99214*80*60~CAS*CO*45*20~\
+CLP*B*1*30*30**MC*2~PLB*1234567893*20231231*WO:1*10~"
+        ), vec![]);
+    }
+
+    #[test]
+    fn test_remittance_unbalanced() {
+        let found = balancing(
+            REMITTANCE,
+            "BPR*I*200*C*ACH*CCP*01*011000015*DA*123456*1512345678**01*031100209*DA*654321*20230501~\
+TRN*1*1*1512345678~N1*PR*PAYER~N1*PE*PAYEE*XX*1234567893~LX*1~\
+CLP*A*1*200*130*10*MC*1~SVC*HC:99213*120*80~CAS*CO*45*30~SVC*HC:99214*80*50~CAS*CO*45*30~\
+PLB*1234567893*20231231*WO:1*-5~",
+        );
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "2000/2100/2110/SVC03".to_string(),
+                    "Service line SVC02 - SVC03 does not equal its CAS adjustments: 40.00 against 30.00 (variance 10.00)".to_string()
+                ),
+                (
+                    "2000/2100/CLP04".to_string(),
+                    "Claim A CLP03 - CLP04 does not equal its claim and service line CAS adjustments: 70.00 against 60.00 (variance 10.00)".to_string()
+                ),
+                (
+                    "BPR02".to_string(),
+                    "BPR02 does not equal the CLP04 total minus the PLB adjustments: 200.00 against 135.00 (variance 65.00)".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_remittance_large_amounts() {
+        // Two 18-digit payments, summed exactly
+        let body = |bpr02: &str| {
+            format!(
+                "BPR*I*{}*C*ACH*CCP*01*011000015*DA*123456*1512345678**01*031100209*DA*654321*20230501~\
+TRN*1*1*1512345678~N1*PR*PAYER~N1*PE*PAYEE*XX*1234567893~LX*1~\
+CLP*A*1*999999999999999999*999999999999999999**MC*1~CLP*B*1*999999999999999999*999999999999999999**MC*2~",
+                bpr02
+            )
+        };
+        assert_eq!(balancing(REMITTANCE, &body("1999999999999999998")), vec![]);
+        assert_eq!(
+            balancing(REMITTANCE, &body("1999999999999999997.99")),
+            vec![(
+                "BPR02".to_string(),
+                "BPR02 does not equal the CLP04 total minus the PLB adjustments: 1999999999999999997.99 against 1999999999999999998.00 (variance -0.01)".to_string()
+            )]
+        );
+    }
+
+    /// An 837P claim with charge `clm02` and lines `lines`.
+    fn claim(clm02: &str, lines: &str) -> String {
+        format!(
+            "BHT*0019*00*1*20230501*1200*CH~NM1*41*2*SUBMITTER*****46*S1~NM1*40*2*RECEIVER*****46*R1~\
+HL*1**20*1~NM1*85*2*BILLING*****XX*1234567893~HL*2*1*22*0~SBR*P*18*******MC~NM1*IL*1*DOE*JOHN****MI*123~\
+CLM*C1*{}***11:B:1*Y*A*Y*Y~HI*ABK:I10~{}",
+            clm02, lines
+        )
+    }
+
+    #[test]
+    fn test_claim_balanced() {
+        assert_eq!(
+            balancing(
+                CLAIM,
+                &claim(
+                    "150",
+                    "LX*1~SV1*HC:99213*100*UN*1***1~SVD*P2*70*HC:99213**1~CAS*CO*45*20~CAS*PR*2*10~\
+LX*2~SV1*HC:87086*50*UN*1***1~"
+                )
+            ),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_claim_unbalanced() {
+        let found = balancing(
+            CLAIM,
+            &claim(
+                "160",
+                "LX*1~SV1*HC:99213*100*UN*1***1~SVD*P2*70*HC:99213**1~CAS*CO*45*20~\
+SVD*P3*100*HC:99213**1~LX*2~SV1*HC:87086*50*UN*1***1~",
+            ),
+        );
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "2000B/2300/2400/2430/SVD02".to_string(),
+                    "Line charge - SVD02 of payer P2 does not equal its CAS adjustments: 30.00 against 20.00 (variance 10.00)".to_string()
+                ),
+                (
+                    "2000B/2300/CLM02".to_string(),
+                    "Claim C1 CLM02 does not equal the total of its 2 service line charges: 160.00 against 150.00 (variance 10.00)".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_premium_balancing() {
+        const PREMIUM: (&str, &str, &str) = ("RA", "820", "005010X218");
+        let body = |bpr02: &str| {
+            format!(
+                "BPR*C*{}*C*ACH*CTX*01*999999992*DA*12345678*1030449999**01*199999999*DA*98765*20230501~\
+TRN*1*12345*1030449999~N1*PE*HEALTH PLAN*FI*012222222~N1*RM*EMPLOYER*FI*123456789~\
+ENT*1*2L*FI*123456789~RMR*IK*INV1**250~ADX*-25*52~ENT*2*2J*34*202443307~NM1*IL*1*SMITH*WILLIAM~RMR*AZ*POL1**175~",
+                bpr02
+            )
+        };
+        assert_eq!(balancing(PREMIUM, &body("400")), vec![]);
+        assert_eq!(
+            balancing(PREMIUM, &body("425")),
+            vec![(
+                "BPR02".to_string(),
+                "BPR02 does not equal the RMR04 total net of ADX adjustments across 2 ENT loops: 425.00 against 400.00 (variance 25.00)".to_string()
+            )]
+        );
+    }
+}
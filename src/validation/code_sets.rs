@@ -0,0 +1,823 @@
+//! External code sets
+//!
+//! Code lists maintained outside X12 (CARC, RARC, claim status category and
+//! status, service type, place of service, provider taxonomy, ICD-10-CM and
+//! CPT/HCPCS) are loaded from local CSV or JSON files, one file per list,
+//! with optional effective date ranges. `CodeSets` looks codes up and
+//! describes them; `Validator::with_code_sets` adds the `EXTERNAL-CODES` rule
+//! (SNIP 5), which checks every bound element against its list. Lists that
+//! were not loaded are not checked. Codes are looked up as of the date of
+//! service of their service line or claim, else the transaction date.
+//!
+//! CSV files have the columns `code,description,start,end` (a header row is
+//! optional, dates are `CCYYMMDD` or `CCYY-MM-DD` and may be empty); JSON
+//! files hold an array of `{"code", "description", "start", "end"}` objects.
+//! The internal X12 lists of CLP02 (claim status) and INS03 (maintenance
+//! type) are built in.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{EdiError, EdiResult};
+use crate::helper::delimiters::{STANDARD_COMPONENT, STANDARD_REPETITION};
+use crate::helper::diagnostics::{Diagnostic, DiagnosticCode, Diagnostics, Severity};
+use crate::helper::helper::clean_contents;
+use crate::helper::loop_path::{element_path, loop_paths};
+use crate::helper::tokenizer::{tokenize, Segment};
+use crate::validation::{Rule, SnipLevel, ValidationContext};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum CodeList {
+    /// Claim Adjustment Reason Codes
+    Carc,
+    /// Remittance Advice Remark Codes
+    Rarc,
+    /// Claim Status Category Codes (DE 1271 in STC01-1)
+    ClaimStatusCategory,
+    /// Claim Status Codes (STC01-2)
+    ClaimStatus,
+    /// Service Type Codes (DE 1365)
+    ServiceType,
+    /// CMS Place of Service Codes
+    PlaceOfService,
+    /// Health Care Provider Taxonomy
+    Taxonomy,
+    /// ICD-10-CM diagnosis codes
+    Icd10,
+    /// CPT and HCPCS procedure codes
+    Cpt,
+    /// CLP02 claim status codes (internal)
+    PaymentClaimStatus,
+    /// INS03 maintenance type codes (internal)
+    MaintenanceType,
+}
+
+impl CodeList {
+    pub const ALL: [CodeList; 11] = [
+        CodeList::Carc,
+        CodeList::Rarc,
+        CodeList::ClaimStatusCategory,
+        CodeList::ClaimStatus,
+        CodeList::ServiceType,
+        CodeList::PlaceOfService,
+        CodeList::Taxonomy,
+        CodeList::Icd10,
+        CodeList::Cpt,
+        CodeList::PaymentClaimStatus,
+        CodeList::MaintenanceType,
+    ];
+
+    /// File name of the list without extension, e.g. `carc` for `carc.csv`.
+    pub fn file_stem(&self) -> &'static str {
+        match self {
+            CodeList::Carc => "carc",
+            CodeList::Rarc => "rarc",
+            CodeList::ClaimStatusCategory => "claim-status-category",
+            CodeList::ClaimStatus => "claim-status",
+            CodeList::ServiceType => "service-type",
+            CodeList::PlaceOfService => "place-of-service",
+            CodeList::Taxonomy => "taxonomy",
+            CodeList::Icd10 => "icd10",
+            CodeList::Cpt => "cpt",
+            CodeList::PaymentClaimStatus => "clp02",
+            CodeList::MaintenanceType => "ins03",
+        }
+    }
+}
+
+impl fmt::Display for CodeList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CodeList::Carc => "CARC",
+            CodeList::Rarc => "RARC",
+            CodeList::ClaimStatusCategory => "claim status category",
+            CodeList::ClaimStatus => "claim status",
+            CodeList::ServiceType => "service type",
+            CodeList::PlaceOfService => "place of service",
+            CodeList::Taxonomy => "taxonomy",
+            CodeList::Icd10 => "ICD-10-CM",
+            CodeList::Cpt => "CPT/HCPCS",
+            CodeList::PaymentClaimStatus => "CLP02 claim status",
+            CodeList::MaintenanceType => "INS03 maintenance type",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// One code of a list, effective from `start` through `end` (`CCYYMMDD`, inclusive).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodeEntry {
+    pub code: String,
+    pub description: String,
+    #[serde(default)]
+    pub start: Option<String>,
+    #[serde(default)]
+    pub end: Option<String>,
+}
+
+impl CodeEntry {
+    pub fn new(code: &str, description: &str) -> CodeEntry {
+        CodeEntry {
+            code: code.to_string(),
+            description: description.to_string(),
+            start: None,
+            end: None,
+        }
+    }
+
+    /// Whether the code is in effect on `date` (`CCYYMMDD`).
+    pub fn is_effective(&self, date: &str) -> bool {
+        self.start.as_deref().is_none_or(|start| start <= date)
+            && self.end.as_deref().is_none_or(|end| date <= end)
+    }
+}
+
+/// A code found in EDI content, with its description.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodeDescription {
+    /// Zero-based ordinal of the segment within the content (ISA is 0)
+    pub segment_index: usize,
+    /// Enclosing loops followed by the element, e.g. `2000/2100/CAS02`
+    pub loop_path: String,
+    pub list: CodeList,
+    pub code: String,
+    pub description: String,
+}
+
+/// Code lists by list and code; a code may have several entries for different periods.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeSets {
+    lists: BTreeMap<CodeList, BTreeMap<String, Vec<CodeEntry>>>,
+}
+
+/// CLP02 claim status codes.
+const PAYMENT_CLAIM_STATUS: &[(&str, &str)] = &[
+    ("1", "Processed as Primary"),
+    ("2", "Processed as Secondary"),
+    ("3", "Processed as Tertiary"),
+    ("4", "Denied"),
+    (
+        "19",
+        "Processed as Primary, Forwarded to Additional Payer(s)",
+    ),
+    (
+        "20",
+        "Processed as Secondary, Forwarded to Additional Payer(s)",
+    ),
+    (
+        "21",
+        "Processed as Tertiary, Forwarded to Additional Payer(s)",
+    ),
+    ("22", "Reversal of Previous Payment"),
+    ("23", "Not Our Claim, Forwarded to Additional Payer(s)"),
+    ("25", "Predetermination Pricing Only - No Payment"),
+];
+
+/// INS03 maintenance type codes.
+const MAINTENANCE_TYPE: &[(&str, &str)] = &[
+    ("001", "Change"),
+    ("021", "Addition"),
+    ("024", "Cancellation or Termination"),
+    ("025", "Reinstatement"),
+    ("026", "Correction"),
+    ("030", "Audit or Compare"),
+    ("032", "Employee Information Not Applicable"),
+];
+
+impl Default for CodeSets {
+    /// Code sets with only the built-in internal lists.
+    fn default() -> Self {
+        let mut code_sets = CodeSets {
+            lists: BTreeMap::new(),
+        };
+        for (list, codes) in [
+            (CodeList::PaymentClaimStatus, PAYMENT_CLAIM_STATUS),
+            (CodeList::MaintenanceType, MAINTENANCE_TYPE),
+        ] {
+            for (code, description) in codes {
+                code_sets.insert(list, CodeEntry::new(code, description));
+            }
+        }
+        code_sets
+    }
+}
+
+impl CodeSets {
+    /// Load every list file found in `dir`, e.g. `carc.csv` or `service-type.json`
+    /// (see `CodeList::file_stem`), on top of the built-in lists.
+    pub fn load_dir(dir: impl AsRef<Path>) -> EdiResult<CodeSets> {
+        let dir = dir.as_ref();
+        if !dir.is_dir() {
+            return Err(EdiError::MissingField(format!(
+                "Code set directory {:?} not found",
+                dir
+            )));
+        }
+        let mut code_sets = CodeSets::default();
+        for list in CodeList::ALL {
+            for extension in ["csv", "json"] {
+                let path = dir.join(format!("{}.{}", list.file_stem(), extension));
+                if path.is_file() {
+                    let count = code_sets.load_file(list, &path)?;
+                    info!("Loaded {} {} codes from {:?}", count, list, path);
+                }
+            }
+        }
+        Ok(code_sets)
+    }
+
+    /// Load one list file, CSV or JSON by its extension. Returns the number of entries.
+    pub fn load_file(&mut self, list: CodeList, path: impl AsRef<Path>) -> EdiResult<usize> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => self.load_json(list, &contents),
+            _ => self.load_csv(list, &contents),
+        }
+    }
+
+    /// Load entries from CSV text. Returns the number of entries.
+    pub fn load_csv(&mut self, list: CodeList, contents: &str) -> EdiResult<usize> {
+        let mut count = 0;
+        for (number, line) in contents.lines().enumerate() {
+            let fields = split_csv_line(line);
+            let code = fields.first().map(|f| f.trim()).unwrap_or("");
+            if code.is_empty() || (number == 0 && code.eq_ignore_ascii_case("code")) {
+                continue;
+            }
+            let field = |index: usize| fields.get(index).map(|f| f.trim()).unwrap_or("");
+            self.insert(
+                list,
+                CodeEntry {
+                    code: code.to_string(),
+                    description: field(1).to_string(),
+                    start: parse_date(field(2), number + 1)?,
+                    end: parse_date(field(3), number + 1)?,
+                },
+            );
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Load entries from a JSON array. Returns the number of entries.
+    pub fn load_json(&mut self, list: CodeList, contents: &str) -> EdiResult<usize> {
+        let entries: Vec<CodeEntry> = serde_json::from_str(contents)?;
+        let count = entries.len();
+        for (number, mut entry) in entries.into_iter().enumerate() {
+            entry.start = parse_date(entry.start.as_deref().unwrap_or(""), number + 1)?;
+            entry.end = parse_date(entry.end.as_deref().unwrap_or(""), number + 1)?;
+            self.insert(list, entry);
+        }
+        Ok(count)
+    }
+
+    /// Add one entry to a list.
+    pub fn insert(&mut self, list: CodeList, mut entry: CodeEntry) {
+        entry.code = normalize(list, &entry.code);
+        self.lists
+            .entry(list)
+            .or_default()
+            .entry(entry.code.clone())
+            .or_default()
+            .push(entry);
+    }
+
+    /// Whether any code of `list` is loaded; lists that are not are not checked.
+    pub fn is_loaded(&self, list: CodeList) -> bool {
+        self.lists.get(&list).is_some_and(|codes| !codes.is_empty())
+    }
+
+    /// Entries of `code` in `list`, for every period.
+    pub fn entries(&self, list: CodeList, code: &str) -> &[CodeEntry] {
+        self.lists
+            .get(&list)
+            .and_then(|codes| codes.get(&normalize(list, code)))
+            .map_or(&[], |entries| entries.as_slice())
+    }
+
+    /// The entry of `code` in effect on `date` (`CCYYMMDD`), or any entry if no date is given.
+    pub fn lookup(&self, list: CodeList, code: &str, date: Option<&str>) -> Option<&CodeEntry> {
+        self.entries(list, code)
+            .iter()
+            .find(|entry| date.is_none_or(|date| entry.is_effective(date)))
+    }
+
+    /// Description of `code` in `list` on `date`.
+    pub fn describe(&self, list: CodeList, code: &str, date: Option<&str>) -> Option<&str> {
+        self.lookup(list, code, date)
+            .map(|entry| entry.description.as_str())
+    }
+
+    /// Describe every code of raw EDI found in a loaded list, in content order.
+    pub fn describe_edi(&self, contents: &str) -> Vec<CodeDescription> {
+        let contents = clean_contents(contents.to_string());
+        let segments = tokenize(&contents);
+        let paths = loop_paths(&segments);
+        let dates = service_dates(&segments);
+        let mut descriptions = Vec::new();
+        for ((segment, path), date) in segments.iter().zip(&paths).zip(dates) {
+            for (binding, code) in bound_codes(segment) {
+                if let Some(description) = self.describe(binding.list, code, date) {
+                    descriptions.push(CodeDescription {
+                        segment_index: segment.index,
+                        loop_path: element_path(path, segment.id, Some(binding.element)),
+                        list: binding.list,
+                        code: code.to_string(),
+                        description: description.to_string(),
+                    });
+                }
+            }
+        }
+        descriptions
+    }
+}
+
+/// Codes of ICD-10-CM lists are compared without their decimal point, as in X12.
+fn normalize(list: CodeList, code: &str) -> String {
+    match list {
+        CodeList::Icd10 => code.replace('.', ""),
+        _ => code.to_string(),
+    }
+}
+
+/// A `CCYYMMDD` or `CCYY-MM-DD` date; empty for none.
+fn parse_date(value: &str, line: usize) -> EdiResult<Option<String>> {
+    let date = value.replace('-', "");
+    if date.is_empty() {
+        Ok(None)
+    } else if date.len() == 8 && date.chars().all(|c| c.is_ascii_digit()) {
+        Ok(Some(date))
+    } else {
+        Err(EdiError::ParseError(format!(
+            "Invalid effective date '{}' in code list entry {}",
+            value, line
+        )))
+    }
+}
+
+/// Split a CSV line, with `"`-quoted fields and `""` escapes.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// An element whose values come from a code list.
+struct Binding {
+    segment: &'static str,
+    /// Element position (1-based)
+    element: usize,
+    /// Component position (1-based) within a composite element
+    component: Option<usize>,
+    /// Component (1-based, 0 for the whole element) of the same element that
+    /// must hold one of the codes, e.g. the code list qualifier of a composite
+    qualifier: Option<(usize, usize, &'static [&'static str])>,
+    list: CodeList,
+}
+
+const fn bind(segment: &'static str, element: usize, list: CodeList) -> Binding {
+    Binding {
+        segment,
+        element,
+        component: None,
+        qualifier: None,
+        list,
+    }
+}
+
+const fn bind_component(
+    segment: &'static str,
+    element: usize,
+    component: usize,
+    qualifier: Option<(usize, usize, &'static [&'static str])>,
+    list: CodeList,
+) -> Binding {
+    Binding {
+        segment,
+        element,
+        component: Some(component),
+        qualifier,
+        list,
+    }
+}
+
+const ICD10_QUALIFIERS: &[&str] = &["ABK", "ABF", "ABJ", "ABN"];
+const PROCEDURE_QUALIFIERS: &[&str] = &["HC"];
+
+const BINDINGS: &[Binding] = &[
+    bind("CAS", 2, CodeList::Carc),
+    bind("CAS", 5, CodeList::Carc),
+    bind("CAS", 8, CodeList::Carc),
+    bind("CAS", 11, CodeList::Carc),
+    bind("CAS", 14, CodeList::Carc),
+    bind("CAS", 17, CodeList::Carc),
+    Binding {
+        qualifier: Some((1, 0, &["HE"])),
+        ..bind("LQ", 2, CodeList::Rarc)
+    },
+    bind("MIA", 5, CodeList::Rarc),
+    bind("MIA", 20, CodeList::Rarc),
+    bind("MIA", 21, CodeList::Rarc),
+    bind("MIA", 22, CodeList::Rarc),
+    bind("MIA", 23, CodeList::Rarc),
+    bind("MOA", 3, CodeList::Rarc),
+    bind("MOA", 4, CodeList::Rarc),
+    bind("MOA", 5, CodeList::Rarc),
+    bind("MOA", 6, CodeList::Rarc),
+    bind("MOA", 7, CodeList::Rarc),
+    bind_component("STC", 1, 1, None, CodeList::ClaimStatusCategory),
+    bind_component("STC", 1, 2, None, CodeList::ClaimStatus),
+    bind_component("STC", 10, 1, None, CodeList::ClaimStatusCategory),
+    bind_component("STC", 10, 2, None, CodeList::ClaimStatus),
+    bind_component("STC", 11, 1, None, CodeList::ClaimStatusCategory),
+    bind_component("STC", 11, 2, None, CodeList::ClaimStatus),
+    bind("EB", 3, CodeList::ServiceType),
+    bind("EQ", 1, CodeList::ServiceType),
+    bind_component("CLM", 5, 1, Some((5, 2, &["B"])), CodeList::PlaceOfService),
+    bind("SV1", 5, CodeList::PlaceOfService),
+    Binding {
+        qualifier: Some((2, 0, &["PXC"])),
+        ..bind("PRV", 3, CodeList::Taxonomy)
+    },
+    bind_component("HI", 1, 2, Some((1, 1, ICD10_QUALIFIERS)), CodeList::Icd10),
+    bind_component("HI", 2, 2, Some((2, 1, ICD10_QUALIFIERS)), CodeList::Icd10),
+    bind_component("HI", 3, 2, Some((3, 1, ICD10_QUALIFIERS)), CodeList::Icd10),
+    bind_component("HI", 4, 2, Some((4, 1, ICD10_QUALIFIERS)), CodeList::Icd10),
+    bind_component("HI", 5, 2, Some((5, 1, ICD10_QUALIFIERS)), CodeList::Icd10),
+    bind_component("HI", 6, 2, Some((6, 1, ICD10_QUALIFIERS)), CodeList::Icd10),
+    bind_component("HI", 7, 2, Some((7, 1, ICD10_QUALIFIERS)), CodeList::Icd10),
+    bind_component("HI", 8, 2, Some((8, 1, ICD10_QUALIFIERS)), CodeList::Icd10),
+    bind_component("HI", 9, 2, Some((9, 1, ICD10_QUALIFIERS)), CodeList::Icd10),
+    bind_component(
+        "HI",
+        10,
+        2,
+        Some((10, 1, ICD10_QUALIFIERS)),
+        CodeList::Icd10,
+    ),
+    bind_component(
+        "HI",
+        11,
+        2,
+        Some((11, 1, ICD10_QUALIFIERS)),
+        CodeList::Icd10,
+    ),
+    bind_component(
+        "HI",
+        12,
+        2,
+        Some((12, 1, ICD10_QUALIFIERS)),
+        CodeList::Icd10,
+    ),
+    bind_component(
+        "SV1",
+        1,
+        2,
+        Some((1, 1, PROCEDURE_QUALIFIERS)),
+        CodeList::Cpt,
+    ),
+    bind_component(
+        "SV2",
+        2,
+        2,
+        Some((2, 1, PROCEDURE_QUALIFIERS)),
+        CodeList::Cpt,
+    ),
+    bind_component("SV3", 1, 2, Some((1, 1, &["AD"])), CodeList::Cpt),
+    bind_component(
+        "SVC",
+        1,
+        2,
+        Some((1, 1, PROCEDURE_QUALIFIERS)),
+        CodeList::Cpt,
+    ),
+    bind_component(
+        "SVC",
+        6,
+        2,
+        Some((6, 1, PROCEDURE_QUALIFIERS)),
+        CodeList::Cpt,
+    ),
+    bind("CLP", 2, CodeList::PaymentClaimStatus),
+    bind("INS", 3, CodeList::MaintenanceType),
+];
+
+/// Element `element` (1-based) of a segment, or its component `component` (1-based; 0 for all of it).
+fn value<'a>(segment: &Segment<'a>, element: usize, component: usize) -> &'a str {
+    let value = segment.element(element - 1);
+    match component {
+        0 => value,
+        _ => value
+            .split(STANDARD_COMPONENT)
+            .nth(component - 1)
+            .unwrap_or(""),
+    }
+}
+
+/// Codes of a segment bound to a code list, every repetition separately.
+fn bound_codes<'a>(segment: &Segment<'a>) -> Vec<(&'static Binding, &'a str)> {
+    let mut codes = Vec::new();
+    for binding in BINDINGS.iter().filter(|b| b.segment == segment.id) {
+        if let Some((element, component, qualifiers)) = binding.qualifier {
+            if !qualifiers.contains(&value(segment, element, component)) {
+                continue;
+            }
+        }
+        for repetition in segment
+            .element(binding.element - 1)
+            .split(STANDARD_REPETITION)
+        {
+            let code = match binding.component {
+                Some(component) => repetition
+                    .split(STANDARD_COMPONENT)
+                    .nth(component - 1)
+                    .unwrap_or(""),
+                None => repetition,
+            };
+            if !code.is_empty() {
+                codes.push((binding, code));
+            }
+        }
+    }
+    codes
+}
+
+/// Dates codes are checked on, by segment position: the date of service of
+/// the service line or claim the segment is in (837 DTP*472/434, 835
+/// DTM*472/232/233), else the transaction date (BHT04, else BPR16, else GS04).
+fn service_dates<'a>(segments: &[Segment<'a>]) -> Vec<Option<&'a str>> {
+    let fallback = transaction_date(segments);
+    let mut dates = vec![fallback; segments.len()];
+
+    // Claims start at CLM (837) or CLP (835); service lines at LX (837) or SVC (835)
+    let is_835 = segments.iter().any(|segment| segment.id == "CLP");
+    let line_start = if is_835 { "SVC" } else { "LX" };
+    let ends_claim =
+        |id: &str| matches!(id, "CLM" | "CLP" | "HL" | "PLB" | "SE") || (is_835 && id == "LX");
+
+    let mut position = 0;
+    while position < segments.len() {
+        if !matches!(segments[position].id, "CLM" | "CLP") {
+            position += 1;
+            continue;
+        }
+        let claim_end = (position + 1..segments.len())
+            .find(|&i| ends_claim(segments[i].id))
+            .unwrap_or(segments.len());
+        let claim = &segments[position..claim_end];
+        let lines_start = claim
+            .iter()
+            .position(|segment| segment.id == line_start)
+            .unwrap_or(claim.len());
+
+        // A claim without its own date takes the date of its first dated line
+        let claim_date = claim[..lines_start]
+            .iter()
+            .find_map(service_date)
+            .or_else(|| claim[lines_start..].iter().find_map(service_date))
+            .or(fallback);
+        for date in &mut dates[position..position + lines_start] {
+            *date = claim_date;
+        }
+
+        let mut line = lines_start;
+        while line < claim.len() {
+            let line_end = (line + 1..claim.len())
+                .find(|&i| claim[i].id == line_start)
+                .unwrap_or(claim.len());
+            let line_date = claim[line..line_end]
+                .iter()
+                .find_map(service_date)
+                .or(claim_date);
+            for date in &mut dates[position + line..position + line_end] {
+                *date = line_date;
+            }
+            line = line_end;
+        }
+        position = claim_end;
+    }
+    dates
+}
+
+/// The date of service of a DTP*472/434 or DTM*472/232/233 segment; the start of a range.
+fn service_date<'a>(segment: &Segment<'a>) -> Option<&'a str> {
+    let (qualifiers, value): (&[&str], &str) = match segment.id {
+        "DTP" => (&["472", "434"], segment.element(2)),
+        "DTM" => (&["472", "232", "233"], segment.element(1)),
+        _ => return None,
+    };
+    Some(value)
+        .filter(|_| qualifiers.contains(&segment.element(0)))
+        .and_then(|value| value.get(..8))
+        .filter(|date| date.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// The transaction date: BHT04, else BPR16 (835, 820), else GS04.
+fn transaction_date<'a>(segments: &[Segment<'a>]) -> Option<&'a str> {
+    let element = |id: &str, index: usize| {
+        segments
+            .iter()
+            .find(|segment| segment.id == id)
+            .map(|segment| segment.element(index))
+            .filter(|value| value.len() == 8)
+    };
+    element("BHT", 3)
+        .or_else(|| element("BPR", 15))
+        .or_else(|| element("GS", 3))
+}
+
+/// Bound elements hold codes of their loaded code list, in effect on the date of service.
+pub(crate) struct CodeSetRule {
+    pub(crate) code_sets: CodeSets,
+}
+
+impl Rule for CodeSetRule {
+    fn id(&self) -> &str {
+        "EXTERNAL-CODES"
+    }
+
+    fn level(&self) -> SnipLevel {
+        SnipLevel::CodeSets
+    }
+
+    fn check(&self, context: &ValidationContext, diagnostics: &mut Diagnostics) {
+        let dates = service_dates(context.segments);
+        for (position, segment) in context.segments.iter().enumerate() {
+            let date = dates[position];
+            for (binding, code) in bound_codes(segment) {
+                if !self.code_sets.is_loaded(binding.list) {
+                    continue;
+                }
+                let entries = self.code_sets.entries(binding.list, code);
+                let message = if entries.is_empty() {
+                    format!("'{}' is not a {} code", code, binding.list)
+                } else if let Some(date) =
+                    date.filter(|date| !entries.iter().any(|e| e.is_effective(date)))
+                {
+                    format!(
+                        "{} code '{}' is not in effect on {}",
+                        binding.list, code, date
+                    )
+                } else {
+                    continue;
+                };
+                diagnostics.push(context.locate(
+                    Diagnostic::new(Severity::Error, DiagnosticCode::InvalidCode, message),
+                    position,
+                    Some(binding.element),
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation::Validator;
+
+    const ISA: &str = "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230501*1200*^*00501*000000001*0*T*:~";
+
+    fn code_sets() -> CodeSets {
+        let mut code_sets = CodeSets::default();
+        code_sets
+            .load_csv(
+                CodeList::Carc,
+                "code,description,start,end\n\
+45,\"Charge exceeds fee schedule, maximum allowable or contracted amount.\",,\n\
+A1,Claim/Service denied.,1995-01-01,2022-12-31\n",
+            )
+            .unwrap();
+        code_sets
+            .load_json(
+                CodeList::Icd10,
+                r#"[{"code": "J45.909", "description": "Unspecified asthma, uncomplicated", "start": "20151001"}]"#,
+            )
+            .unwrap();
+        code_sets
+    }
+
+    #[test]
+    fn test_load_and_describe() {
+        let code_sets = code_sets();
+        assert!(code_sets.is_loaded(CodeList::Carc));
+        assert!(!code_sets.is_loaded(CodeList::Rarc));
+        assert!(code_sets
+            .describe(CodeList::Carc, "45", Some("20230501"))
+            .unwrap()
+            .starts_with("Charge exceeds fee schedule, maximum"));
+        assert_eq!(
+            code_sets.lookup(CodeList::Carc, "A1", Some("20230501")),
+            None
+        );
+        assert!(code_sets
+            .lookup(CodeList::Carc, "A1", Some("20200101"))
+            .is_some());
+        // ICD-10-CM codes match with or without the decimal point
+        assert!(code_sets.lookup(CodeList::Icd10, "J45909", None).is_some());
+        assert_eq!(
+            code_sets.describe(CodeList::PaymentClaimStatus, "22", None),
+            Some("Reversal of Previous Payment")
+        );
+        assert!(CodeSets::default()
+            .load_csv(CodeList::Cpt, "99213,Office visit,2023-13,\n")
+            .is_err());
+    }
+
+    #[test]
+    fn test_validate_and_describe_edi() {
+        let contents = format!(
+            "{}GS*HP*SENDER*RECEIVER*20230501*1200*1*X*005010X221A1~ST*835*0001~\
+BPR*I*100*C*ACH*CCP*01*011000015*DA*123456*1512345678**01*031100209*DA*654321*20230501~TRN*1*1*1512345678~\
+N1*PR*PAYER~N1*PE*PAYEE*XX*1234567893~LX*1~CLP*A*5*100*80**MC*1~CAS*CO*45*10**A1*10~NM1*QC*1*DOE~\
+SE*11*0001~GE*1*1~IEA*1*000000001~",
+            ISA
+        );
+        let findings = Validator::empty()
+            .with_code_sets(code_sets())
+            .validate_edi(&contents, &[SnipLevel::CodeSets])
+            .unwrap();
+        let found: Vec<(&str, &str)> = findings
+            .iter()
+            .map(|f| {
+                (
+                    f.diagnostic.loop_path.as_str(),
+                    f.diagnostic.message.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("2000/2100/CLP02", "'5' is not a CLP02 claim status code"),
+                (
+                    "2000/2100/CAS05",
+                    "CARC code 'A1' is not in effect on 20230501"
+                ),
+            ]
+        );
+        assert_eq!(findings.iter().next().unwrap().rule, "EXTERNAL-CODES");
+
+        let described = code_sets().describe_edi(&contents);
+        assert_eq!(described.len(), 1);
+        assert_eq!(described[0].loop_path, "2000/2100/CAS02");
+        assert_eq!(described[0].list, CodeList::Carc);
+    }
+
+    #[test]
+    fn test_codes_in_effect_on_the_date_of_service() {
+        // A1 ended in 2022: the first claim's service line is from 2022, the
+        // second claim has no dates of service and falls back to BPR16
+        let contents = format!(
+            "{}GS*HP*SENDER*RECEIVER*20230501*1200*1*X*005010X221A1~ST*835*0001~\
+BPR*I*100*C*ACH*CCP*01*011000015*DA*123456*1512345678**01*031100209*DA*654321*20230501~TRN*1*1*1512345678~\
+N1*PR*PAYER~N1*PE*PAYEE*XX*1234567893~LX*1~CLP*A*1*100*80**MC*1~CAS*CO*A1*10~NM1*QC*1*DOE~\
+SVC*HC:99213*100*80~DTM*472*20221215~CAS*CO*A1*20~CLP*B*1*100*80**MC*2~CAS*CO*A1*10~NM1*QC*1*ROE~\
+SE*16*0001~GE*1*1~IEA*1*000000001~",
+            ISA
+        );
+        let segments = tokenize(&contents);
+        let dates = service_dates(&segments);
+        let date_of = |from: usize| {
+            let position = (from..segments.len())
+                .find(|&i| segments[i].id == "CAS")
+                .unwrap();
+            (position, dates[position])
+        };
+        let (first, first_date) = date_of(0);
+        assert_eq!(first_date, Some("20221215"));
+        let (line, line_date) = date_of(first + 1);
+        assert_eq!(line_date, Some("20221215"));
+        assert_eq!(date_of(line + 1).1, Some("20230501"));
+
+        let findings = Validator::empty()
+            .with_code_sets(code_sets())
+            .validate_edi(&contents, &[SnipLevel::CodeSets])
+            .unwrap();
+        let found: Vec<&str> = findings
+            .iter()
+            .map(|f| f.diagnostic.message.as_str())
+            .collect();
+        assert_eq!(found, vec!["CARC code 'A1' is not in effect on 20230501"]);
+    }
+}
@@ -0,0 +1,876 @@
+//! Validation engine
+//!
+//! Parsing only reports what it trips over (envelope mismatches, malformed
+//! amounts, segments it did not consume). `Validator` checks a parsed
+//! transaction set against rules organized by WEDI SNIP level:
+//!
+//! 1. Integrity: X12 syntax, envelopes, segments the parser did not recognize
+//! 2. Requirements: implementation guide requirements (guide, loop and segment usage)
+//! 3. Balancing: amounts and counts that must add up
+//! 4. Situational: segments required or forbidden depending on other data
+//! 5. Code sets: element values from internal and external code lists
+//! 6. Product types: rules of a subtype, e.g. 837P vs 837I vs 837D
+//! 7. Partner-specific: trading partner edits, added with `Validator::with_rule`
+//!
+//! Each run selects the levels to check and returns `Findings`, the
+//! diagnostics of every rule tagged with its level, rule ID and the control
+//! numbers of the transaction set they were found in.
+
+mod balancing;
+pub mod code_sets;
+mod rules;
+pub mod usage;
+
+use std::fmt;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{EdiError, EdiResult};
+use crate::helper::diagnostics::{Diagnostic, Diagnostics, Severity};
+use crate::helper::helper::clean_contents;
+use crate::helper::loop_path::loop_paths;
+use crate::helper::tokenizer::{tokenize, Segment};
+use crate::helper::unrecognized::UnrecognizedSegments;
+use crate::helper::writer_options::WriterOptions;
+use crate::interchange::{get_interchanges, AnyTransaction};
+use crate::validation::code_sets::CodeSets;
+
+/// WEDI SNIP validation levels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum SnipLevel {
+    Integrity = 1,
+    Requirements = 2,
+    Balancing = 3,
+    Situational = 4,
+    CodeSets = 5,
+    ProductType = 6,
+    PartnerSpecific = 7,
+}
+
+impl SnipLevel {
+    pub const ALL: [SnipLevel; 7] = [
+        SnipLevel::Integrity,
+        SnipLevel::Requirements,
+        SnipLevel::Balancing,
+        SnipLevel::Situational,
+        SnipLevel::CodeSets,
+        SnipLevel::ProductType,
+        SnipLevel::PartnerSpecific,
+    ];
+
+    pub fn number(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_number(number: u8) -> Option<SnipLevel> {
+        SnipLevel::ALL
+            .iter()
+            .copied()
+            .find(|level| level.number() == number)
+    }
+
+    /// Levels 1 through `level`, the usual way to run SNIP validation.
+    pub fn up_to(level: SnipLevel) -> Vec<SnipLevel> {
+        SnipLevel::ALL
+            .iter()
+            .copied()
+            .filter(|selected| *selected <= level)
+            .collect()
+    }
+
+    /// Parse a level selection such as `1-4`, `1,2,5` or `1-3,7`.
+    pub fn parse_levels(selection: &str) -> EdiResult<Vec<SnipLevel>> {
+        let invalid = || {
+            EdiError::ValidationError(format!(
+                "Invalid SNIP level selection '{}', expected e.g. 1-4 or 1,2,5",
+                selection
+            ))
+        };
+        let level = |number: &str| {
+            number
+                .trim()
+                .parse()
+                .ok()
+                .and_then(SnipLevel::from_number)
+                .ok_or_else(invalid)
+        };
+
+        let mut levels = Vec::new();
+        for part in selection.split(',') {
+            let (first, last) = match part.split_once('-') {
+                Some((first, last)) => (level(first)?, level(last)?),
+                None => (level(part)?, level(part)?),
+            };
+            levels.extend(
+                SnipLevel::ALL
+                    .iter()
+                    .filter(|selected| (first..=last).contains(*selected)),
+            );
+        }
+        levels.sort();
+        levels.dedup();
+        Ok(levels)
+    }
+}
+
+impl fmt::Display for SnipLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SNIP {} ({:?})", self.number(), self)
+    }
+}
+
+/// A diagnostic found by a validation rule.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Finding {
+    pub level: SnipLevel,
+    /// ID of the rule that found the problem, e.g. `837-REPLACEMENT-REF-F8`
+    pub rule: String,
+    /// Envelope of the transaction set the finding is in
+    #[serde(default, skip_serializing_if = "ControlNumbers::is_empty")]
+    pub control_numbers: ControlNumbers,
+    #[serde(flatten)]
+    pub diagnostic: Diagnostic,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SNIP {} [{}] ", self.level.number(), self.rule)?;
+        if !self.control_numbers.is_empty() {
+            write!(f, "{} ", self.control_numbers)?;
+        }
+        write!(f, "{}", self.diagnostic)
+    }
+}
+
+/// Envelope control numbers identifying one transaction set.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ControlNumbers {
+    /// ISA13
+    pub interchange: String,
+    /// GS06
+    pub group: String,
+    /// ST02
+    pub transaction: String,
+}
+
+impl ControlNumbers {
+    /// Control numbers of the first ISA, GS and ST segments.
+    pub fn of(segments: &[Segment]) -> ControlNumbers {
+        let element = |id: &str, index: usize| {
+            segments
+                .iter()
+                .find(|segment| segment.id == id)
+                .map(|segment| segment.element(index).to_string())
+                .unwrap_or_default()
+        };
+        ControlNumbers {
+            interchange: element("ISA", 12),
+            group: element("GS", 5),
+            transaction: element("ST", 1),
+        }
+    }
+
+    /// Control numbers of the envelopes enclosing the segment in position
+    /// `position`, e.g. only ISA13 for a segment between two groups.
+    pub fn at(segments: &[Segment], position: usize) -> ControlNumbers {
+        let mut numbers = ControlNumbers::default();
+        for (index, segment) in segments.iter().enumerate().take(position + 1) {
+            // A trailer belongs to its envelope, the segments after it do not
+            let closed = index < position;
+            match segment.id {
+                "ISA" => {
+                    numbers = ControlNumbers {
+                        interchange: segment.element(12).to_string(),
+                        ..Default::default()
+                    }
+                }
+                "GS" => {
+                    numbers.group = segment.element(5).to_string();
+                    numbers.transaction.clear();
+                }
+                "ST" => numbers.transaction = segment.element(1).to_string(),
+                "SE" if closed => numbers.transaction.clear(),
+                "GE" if closed => {
+                    numbers.group.clear();
+                    numbers.transaction.clear();
+                }
+                "IEA" if closed => numbers = ControlNumbers::default(),
+                _ => {}
+            }
+        }
+        numbers
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.interchange.is_empty() && self.group.is_empty() && self.transaction.is_empty()
+    }
+}
+
+impl fmt::Display for ControlNumbers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts = [
+            ("ISA13", &self.interchange),
+            ("GS06", &self.group),
+            ("ST02", &self.transaction),
+        ];
+        let parts: Vec<String> = parts
+            .iter()
+            .filter(|(_, number)| !number.is_empty())
+            .map(|(element, number)| format!("{} {}", element, number))
+            .collect();
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// Findings of a validation run, in the order the rules ran.
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Findings {
+    entries: Vec<Finding>,
+}
+
+impl Findings {
+    pub fn push(&mut self, finding: Finding) {
+        self.entries.push(finding);
+    }
+
+    pub fn extend(&mut self, other: Findings) {
+        self.entries.extend(other.entries);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Finding> {
+        self.entries.iter()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.entries
+            .iter()
+            .any(|f| f.diagnostic.severity == Severity::Error)
+    }
+
+    /// Findings of one SNIP level.
+    pub fn at_level(&self, level: SnipLevel) -> impl Iterator<Item = &Finding> {
+        self.entries.iter().filter(move |f| f.level == level)
+    }
+
+    /// Findings of one rule.
+    pub fn of_rule<'a>(&'a self, rule: &'a str) -> impl Iterator<Item = &'a Finding> {
+        self.entries.iter().filter(move |f| f.rule == rule)
+    }
+
+    /// Write every finding to the log, for the command line tool.
+    pub fn log(&self) {
+        for finding in &self.entries {
+            match finding.diagnostic.severity {
+                Severity::Info => info!("{}", finding),
+                Severity::Warning | Severity::Error => warn!("{}", finding),
+            }
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Findings {
+    type Item = &'a Finding;
+    type IntoIter = std::slice::Iter<'a, Finding>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+/// What a rule checks: one transaction set with its envelope, tokenized.
+pub struct ValidationContext<'a> {
+    /// ST01, e.g. `837`
+    pub transaction_set_id: String,
+    /// ST03, else GS08, e.g. `005010X222A1`
+    pub implementation_guide: String,
+    /// Segments with the standard delimiters, ISA first. For a transaction
+    /// set of a batch: its ISA, GS and ST..SE segments
+    pub segments: &'a [Segment<'a>],
+    /// Loop path of every segment, by position
+    pub paths: &'a [String],
+    /// Segments the parser did not recognize
+    pub unrecognized: &'a UnrecognizedSegments,
+    /// The parsed transaction set
+    pub transaction: &'a AnyTransaction,
+    /// Whether the ISA and GS levels were checked when the batch holding the
+    /// transaction set was parsed; `segments` then lack their GE and IEA
+    pub envelope_checked: bool,
+}
+
+impl ValidationContext<'_> {
+    /// Positions of the segments with ID `id`.
+    pub fn positions<'s>(&'s self, id: &'s str) -> impl Iterator<Item = usize> + 's {
+        self.segments
+            .iter()
+            .enumerate()
+            .filter(move |(_, segment)| segment.id == id)
+            .map(|(position, _)| position)
+    }
+
+    /// Locate `diagnostic` at the segment in position `position`, and at its `element` (1-based) if given.
+    pub fn locate(
+        &self,
+        diagnostic: Diagnostic,
+        position: usize,
+        element: Option<usize>,
+    ) -> Diagnostic {
+        diagnostic.at(&self.segments[position], &self.paths[position], element)
+    }
+}
+
+/// A validation rule.
+pub trait Rule {
+    /// Short unique ID, reported on every finding, e.g. `ENVELOPE`
+    fn id(&self) -> &str;
+
+    fn level(&self) -> SnipLevel;
+
+    /// Whether the rule applies to transaction set `transaction_set_id` (ST01)
+    fn applies_to(&self, _transaction_set_id: &str) -> bool {
+        true
+    }
+
+    fn check(&self, context: &ValidationContext, diagnostics: &mut Diagnostics);
+}
+
+/// A rule given as a closure, e.g. for trading partner edits.
+pub struct CustomRule<F> {
+    id: String,
+    level: SnipLevel,
+    transaction_set_id: Option<String>,
+    check: F,
+}
+
+impl<F> CustomRule<F>
+where
+    F: Fn(&ValidationContext, &mut Diagnostics),
+{
+    pub fn new(id: &str, level: SnipLevel, check: F) -> CustomRule<F> {
+        CustomRule {
+            id: id.to_string(),
+            level,
+            transaction_set_id: None,
+            check,
+        }
+    }
+
+    /// Only apply the rule to transaction set `transaction_set_id`.
+    pub fn for_transaction_set(mut self, transaction_set_id: &str) -> CustomRule<F> {
+        self.transaction_set_id = Some(transaction_set_id.to_string());
+        self
+    }
+}
+
+impl<F> Rule for CustomRule<F>
+where
+    F: Fn(&ValidationContext, &mut Diagnostics),
+{
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn level(&self) -> SnipLevel {
+        self.level
+    }
+
+    fn applies_to(&self, transaction_set_id: &str) -> bool {
+        self.transaction_set_id
+            .as_deref()
+            .is_none_or(|id| id == transaction_set_id)
+    }
+
+    fn check(&self, context: &ValidationContext, diagnostics: &mut Diagnostics) {
+        (self.check)(context, diagnostics)
+    }
+}
+
+/// Runs validation rules over parsed transaction sets.
+pub struct Validator {
+    rules: Vec<Box<dyn Rule>>,
+}
+
+impl Default for Validator {
+    /// A validator with the built-in rules of every level.
+    fn default() -> Self {
+        Validator {
+            rules: rules::built_in(),
+        }
+    }
+}
+
+impl Validator {
+    /// A validator without any rules.
+    pub fn empty() -> Validator {
+        Validator { rules: Vec::new() }
+    }
+
+    /// Add a rule, e.g. a trading partner edit.
+    pub fn with_rule(mut self, rule: impl Rule + 'static) -> Validator {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    /// Add the `EXTERNAL-CODES` rule (SNIP 5), checking elements against the loaded code lists.
+    pub fn with_code_sets(self, code_sets: CodeSets) -> Validator {
+        self.with_rule(code_sets::CodeSetRule { code_sets })
+    }
+
+    /// IDs and levels of the rules, in the order they run.
+    pub fn rules(&self) -> impl Iterator<Item = (&str, SnipLevel)> {
+        self.rules.iter().map(|rule| (rule.id(), rule.level()))
+    }
+
+    /// Check a parsed transaction set at the selected levels. Positions in
+    /// the findings refer to the transaction set as written.
+    pub fn validate(
+        &self,
+        transaction: &AnyTransaction,
+        levels: &[SnipLevel],
+    ) -> EdiResult<Findings> {
+        let contents = transaction.to_edi_with(&WriterOptions::single_line())?;
+        Ok(self.check_edi(&contents, transaction, levels))
+    }
+
+    /// Parse raw EDI holding one transaction set and check it at the selected
+    /// levels. Positions in the findings refer to `contents`.
+    pub fn validate_edi(&self, contents: &str, levels: &[SnipLevel]) -> EdiResult<Findings> {
+        let transaction = AnyTransaction::parse_any(contents)?;
+        Ok(self.check_edi(contents, &transaction, levels))
+    }
+
+    /// Parse raw EDI holding any number of interchanges and check every
+    /// transaction set at the selected levels. The ISA and GS level envelope
+    /// problems found while parsing are Integrity findings of the `ENVELOPE`
+    /// rule. Positions in the findings refer to `contents`.
+    pub fn validate_interchanges(
+        &self,
+        contents: &str,
+        levels: &[SnipLevel],
+    ) -> EdiResult<Findings> {
+        let interchanges = get_interchanges(contents)?;
+        let contents = clean_contents(contents.to_string());
+        let segments = tokenize(&contents);
+        let paths = loop_paths(&segments);
+
+        let mut findings = Findings::default();
+        if levels.contains(&SnipLevel::Integrity) {
+            for diagnostic in interchanges
+                .iter()
+                .flat_map(|interchange| &interchange.diagnostics)
+            {
+                findings.push(Finding {
+                    level: SnipLevel::Integrity,
+                    rule: "ENVELOPE".to_string(),
+                    control_numbers: diagnostic
+                        .segment_index
+                        .map(|index| ControlNumbers::at(&segments, index))
+                        .unwrap_or_default(),
+                    diagnostic: diagnostic.clone(),
+                });
+            }
+        }
+
+        let transactions = interchanges
+            .iter()
+            .flat_map(|interchange| &interchange.groups)
+            .flat_map(|group| &group.transactions);
+        for (transaction, positions) in transactions.zip(transaction_positions(&segments)) {
+            let enclosed: Vec<Segment> = positions.iter().map(|&p| segments[p].clone()).collect();
+            let enclosed_paths: Vec<String> = positions.iter().map(|&p| paths[p].clone()).collect();
+            findings.extend(self.check(&enclosed, &enclosed_paths, transaction, levels, true));
+        }
+        Ok(findings)
+    }
+
+    fn check_edi(
+        &self,
+        contents: &str,
+        transaction: &AnyTransaction,
+        levels: &[SnipLevel],
+    ) -> Findings {
+        let contents = clean_contents(contents.to_string());
+        let segments = tokenize(&contents);
+        let paths = loop_paths(&segments);
+        self.check(&segments, &paths, transaction, levels, false)
+    }
+
+    fn check(
+        &self,
+        segments: &[Segment],
+        paths: &[String],
+        transaction: &AnyTransaction,
+        levels: &[SnipLevel],
+        envelope_checked: bool,
+    ) -> Findings {
+        let element = |id: &str, index: usize| {
+            segments
+                .iter()
+                .find(|segment| segment.id == id)
+                .map(|segment| segment.element(index))
+                .filter(|value| !value.is_empty())
+        };
+        let context = ValidationContext {
+            transaction_set_id: transaction.transaction_set_id().to_string(),
+            implementation_guide: element("ST", 2)
+                .or_else(|| element("GS", 7))
+                .unwrap_or_default()
+                .to_string(),
+            segments,
+            paths,
+            unrecognized: transaction.unrecognized(),
+            transaction,
+            envelope_checked,
+        };
+
+        let control_numbers = ControlNumbers::of(segments);
+        let mut findings = Findings::default();
+        for rule in self.rules.iter().filter(|rule| {
+            levels.contains(&rule.level()) && rule.applies_to(&context.transaction_set_id)
+        }) {
+            let mut diagnostics = Diagnostics::default();
+            rule.check(&context, &mut diagnostics);
+            for diagnostic in diagnostics {
+                findings.push(Finding {
+                    level: rule.level(),
+                    rule: rule.id().to_string(),
+                    control_numbers: control_numbers.clone(),
+                    diagnostic,
+                });
+            }
+        }
+        findings
+    }
+}
+
+/// Positions of the ISA, GS and ST..SE segments of every transaction set in
+/// tokenized content, in the order `get_interchanges` parses them. Like the
+/// wrapper each transaction set is parsed from, so unrecognized segments line up.
+fn transaction_positions(segments: &[Segment]) -> Vec<Vec<usize>> {
+    let mut transactions = Vec::new();
+    let (mut isa, mut gs) = (None, None);
+    let mut pos = 0;
+    while pos < segments.len() {
+        match segments[pos].id {
+            "ISA" => (isa, gs) = (Some(pos), None),
+            "GS" if isa.is_some() => gs = Some(pos),
+            "GE" => gs = None,
+            "IEA" => (isa, gs) = (None, None),
+            "ST" => {
+                if let (Some(isa), Some(gs)) = (isa, gs) {
+                    let end = segments[pos..]
+                        .iter()
+                        .position(|s| matches!(s.id, "SE" | "GE" | "IEA"))
+                        .map(|offset| pos + offset)
+                        .unwrap_or(segments.len());
+                    let has_se = end < segments.len() && segments[end].id == "SE";
+                    let end = if has_se { end + 1 } else { end };
+                    transactions.push([isa, gs].into_iter().chain(pos..end).collect());
+                    pos = end;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        pos += 1;
+    }
+    transactions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::diagnostics::DiagnosticCode;
+
+    const ISA: &str = "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230501*1200*^*00501*000000001*0*T*:~";
+
+    fn claim_837(clm05: &str, extra: &str) -> String {
+        format!(
+            "{}GS*HC*SENDER*RECEIVER*20230501*1200*1*X*005010X222A1~ST*837*0001*005010X222A1~BHT*0019*00*1*20230501*1200*CH~\
+NM1*41*2*SUBMITTER*****46*S1~PER*IC*CONTACT*TE*5551234567~NM1*40*2*RECEIVER*****46*R1~HL*1**20*1~\
+NM1*85*2*BILLING*****XX*1234567893~N3*1 MAIN ST~N4*CITY*ST*12345~REF*EI*123456789~\
+HL*2*1*22*0~SBR*P*18*******MC~NM1*IL*1*DOE*JOHN****MI*123~NM1*PR*2*PAYER*****PI*P1~\
+CLM*C1*100***{}*Y*A*Y*Y~{}HI*ABK:I10~LX*1~SV1*HC:99213*100*UN*1***1~DTP*472*D8*20230501~SE*{}*0001~GE*1*1~IEA*1*000000001~",
+            ISA,
+            clm05,
+            extra,
+            if extra.is_empty() { 20 } else { 21 }
+        )
+    }
+
+    #[test]
+    fn test_parse_levels() {
+        assert_eq!(
+            SnipLevel::parse_levels("1-3,7").unwrap(),
+            vec![
+                SnipLevel::Integrity,
+                SnipLevel::Requirements,
+                SnipLevel::Balancing,
+                SnipLevel::PartnerSpecific
+            ]
+        );
+        assert_eq!(
+            SnipLevel::parse_levels("5, 2,2").unwrap(),
+            vec![SnipLevel::Requirements, SnipLevel::CodeSets]
+        );
+        assert_eq!(SnipLevel::up_to(SnipLevel::Situational).len(), 4);
+        assert!(SnipLevel::parse_levels("0-8").is_err());
+        assert!(SnipLevel::parse_levels("").is_err());
+    }
+
+    #[test]
+    fn test_validate_levels_and_findings() {
+        let validator = Validator::default();
+        let clean = claim_837("11:B:1", "");
+        let findings = validator.validate_edi(&clean, &SnipLevel::ALL).unwrap();
+        assert!(findings.is_empty(), "{:?}", findings);
+
+        // A replacement claim without the payer's claim control number, an
+        // institutional facility code qualifier and an unknown usage indicator
+        let broken = claim_837("11:A:7", "").replace("*0*T*:~", "*0*X*:~");
+        let findings = validator.validate_edi(&broken, &SnipLevel::ALL).unwrap();
+        let found: Vec<(u8, &str, DiagnosticCode, &str)> = findings
+            .iter()
+            .map(|f| {
+                (
+                    f.level.number(),
+                    f.rule.as_str(),
+                    f.diagnostic.code,
+                    f.diagnostic.loop_path.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    4,
+                    "837-REPLACEMENT-REF-F8",
+                    DiagnosticCode::SituationalRule,
+                    "2000B/2300/CLM05"
+                ),
+                (5, "ENVELOPE-CODES", DiagnosticCode::InvalidCode, "ISA15"),
+                (
+                    6,
+                    "837-FACILITY-QUALIFIER",
+                    DiagnosticCode::ProductTypeMismatch,
+                    "2000B/2300/CLM05"
+                ),
+            ]
+        );
+        assert!(findings.has_errors());
+
+        // Only the selected levels run
+        let situational = validator
+            .validate_edi(&broken, &[SnipLevel::Situational])
+            .unwrap();
+        assert_eq!(situational.len(), 1);
+        let fixed = claim_837("11:B:7", "REF*F8*ORIGINAL1~");
+        assert!(validator
+            .validate_edi(&fixed, &SnipLevel::ALL)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_integrity_and_requirements() {
+        let contents = format!(
+            "{}GS*HP*SENDER*RECEIVER*20230501*1200*1*X*005010X221A1~ST*835*0001~\
+BPR*I*100*C*ACH*CCP*01*011000015*DA*123456*1512345678**01*031100209*DA*654321*20230501~N1*PR*PAYER~N3*1 MAIN ST~N4*CITY*ST*12345~PER*BL*CONTACT~\
+N1*PE*PAYEE*XX*1234567890~ZZZ*1~LX*1~CLP*CLAIM1*1*1OO*100**MC*1~NM1*QC*1*DOE~SE*7*0002~GE*1*1~IEA*1*000000001~",
+            ISA
+        );
+        let findings = Validator::default()
+            .validate_edi(&contents, &SnipLevel::up_to(SnipLevel::Requirements))
+            .unwrap();
+        let found: Vec<(&str, DiagnosticCode, &str)> = findings
+            .iter()
+            .map(|f| {
+                (
+                    f.rule.as_str(),
+                    f.diagnostic.code,
+                    f.diagnostic.loop_path.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("ENVELOPE", DiagnosticCode::ControlNumberMismatch, "SE02"),
+                ("ENVELOPE", DiagnosticCode::CountMismatch, "SE01"),
+                (
+                    "ELEMENTS",
+                    DiagnosticCode::InvalidDecimal,
+                    "2000/2100/CLP03"
+                ),
+                (
+                    "UNRECOGNIZED",
+                    DiagnosticCode::UnprocessedSegment,
+                    "1000B/ZZZ"
+                ),
+                ("IG-USAGE", DiagnosticCode::MissingSegment, "TRN"),
+            ]
+        );
+        let unrecognized = findings.of_rule("UNRECOGNIZED").next().unwrap();
+        assert_eq!(
+            unrecognized.diagnostic.offset,
+            clean_contents(contents.clone()).find("ZZZ")
+        );
+        assert_eq!(findings.at_level(SnipLevel::Integrity).count(), 4);
+
+        let other_guide = claim_837("11:B:1", "").replace("005010X222A1~BHT", "005010X221A1~BHT");
+        let findings = Validator::default()
+            .validate_edi(&other_guide, &[SnipLevel::Requirements])
+            .unwrap();
+        let finding = findings.iter().next().unwrap();
+        assert_eq!(
+            finding.diagnostic.code,
+            DiagnosticCode::ImplementationGuideMismatch
+        );
+        assert_eq!(finding.diagnostic.loop_path, "ST03");
+    }
+
+    #[test]
+    fn test_element_violations_are_errors() {
+        let long_npi = claim_837("11:B:1", "").replace("XX*1234567893~", "XX*12345678931~");
+        let findings = Validator::default()
+            .validate_edi(&long_npi, &[SnipLevel::Integrity])
+            .unwrap();
+        assert!(findings.has_errors());
+        let finding = findings.of_rule("ELEMENTS").next().unwrap();
+        assert_eq!(finding.diagnostic.severity, Severity::Error);
+        assert_eq!(finding.diagnostic.code, DiagnosticCode::InvalidLength);
+        assert_eq!(finding.diagnostic.loop_path, "2000A/2010AA/NM109");
+    }
+
+    #[test]
+    fn test_partner_rule() {
+        let validator = Validator::default().with_rule(
+            CustomRule::new(
+                "PARTNER-NO-TEST-DATA",
+                SnipLevel::PartnerSpecific,
+                |context, diagnostics| {
+                    for position in context.positions("ISA") {
+                        if context.segments[position].element(14) == "T" {
+                            diagnostics.push(context.locate(
+                                Diagnostic::new(
+                                    Severity::Error,
+                                    DiagnosticCode::RuleViolation,
+                                    "Partner does not accept test data",
+                                ),
+                                position,
+                                Some(15),
+                            ));
+                        }
+                    }
+                },
+            )
+            .for_transaction_set("837"),
+        );
+        assert!(validator
+            .rules()
+            .any(|(id, _)| id == "PARTNER-NO-TEST-DATA"));
+
+        let transaction = AnyTransaction::parse_any(&claim_837("11:B:1", "")).unwrap();
+        let findings = validator
+            .validate(&transaction, &[SnipLevel::PartnerSpecific])
+            .unwrap();
+        assert_eq!(findings.len(), 1);
+        let finding = findings.iter().next().unwrap();
+        assert_eq!(finding.diagnostic.loop_path, "ISA15");
+        assert!(finding
+            .to_string()
+            .starts_with("SNIP 7 [PARTNER-NO-TEST-DATA] ISA13 000000001 GS06 1 ST02 0001 Error"));
+
+        let json = serde_json::to_string(&findings).unwrap();
+        assert!(json.starts_with(
+            r#"[{"level":"PartnerSpecific","rule":"PARTNER-NO-TEST-DATA","control_numbers":{"interchange":"000000001","group":"1","transaction":"0001"},"severity":"Error""#
+        ));
+    }
+
+    #[test]
+    fn test_findings_name_their_transaction_set() {
+        // Two claims in one group; only the second is a replacement without REF*F8
+        let first = claim_837("11:B:1", "");
+        let second = claim_837("11:B:7", "")
+            .replace("*0001*", "*0002*")
+            .replace("*0001~", "*0002~");
+        let start = second.find("ST*").unwrap();
+        let end = second.find("GE*").unwrap();
+        let contents = first.replace("GE*1*1~", &format!("{}GE*2*1~", &second[start..end]));
+
+        let findings = Validator::default()
+            .validate_interchanges(&contents, &[SnipLevel::Situational])
+            .unwrap();
+        assert_eq!(findings.len(), 1);
+        let finding = findings.iter().next().unwrap();
+        assert_eq!(
+            finding.control_numbers,
+            ControlNumbers {
+                interchange: "000000001".to_string(),
+                group: "1".to_string(),
+                transaction: "0002".to_string(),
+            }
+        );
+        // Located in the file, not in a wrapper around the transaction set
+        assert_eq!(finding.diagnostic.offset, contents.rfind("CLM*"));
+    }
+
+    #[test]
+    fn test_batch_envelope_errors_are_integrity_findings() {
+        // Two groups; the second has the wrong GE02 and the interchange the wrong IEA01
+        let single = claim_837("11:B:1", "");
+        let start = single.find("GS*").unwrap();
+        let end = single.find("IEA*").unwrap();
+        let second_group = single[start..end]
+            .replace("*1*X*", "*2*X*")
+            .replace("GE*1*1~", "GE*1*9~");
+        let contents = single
+            .replace("GE*1*1~", &format!("GE*1*1~{}", second_group))
+            .replace("IEA*1*", "IEA*3*");
+
+        let findings = Validator::default()
+            .validate_interchanges(&contents, &[SnipLevel::Integrity])
+            .unwrap();
+        assert!(findings.has_errors());
+        let found: Vec<(&str, DiagnosticCode, &str, String)> = findings
+            .iter()
+            .map(|f| {
+                (
+                    f.rule.as_str(),
+                    f.diagnostic.code,
+                    f.diagnostic.loop_path.as_str(),
+                    f.control_numbers.to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "ENVELOPE",
+                    DiagnosticCode::ControlNumberMismatch,
+                    "GE02",
+                    "ISA13 000000001 GS06 2".to_string()
+                ),
+                (
+                    "ENVELOPE",
+                    DiagnosticCode::CountMismatch,
+                    "IEA01",
+                    "ISA13 000000001".to_string()
+                ),
+            ]
+        );
+        let ge = findings.iter().next().unwrap();
+        assert_eq!(ge.diagnostic.offset, contents.find("GE*1*9~"));
+    }
+}
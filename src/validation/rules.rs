@@ -0,0 +1,426 @@
+//! Built-in validation rules
+//!
+//! Level 1 reuses the parse-time envelope and element checks; the other
+//! levels start with the rules below and are extended by later modules.
+
+use crate::envelope::functional_id_code;
+use crate::helper::diagnostics::{Diagnostic, DiagnosticCode, Diagnostics, Severity};
+use crate::helper::element_dictionary::check_elements;
+use crate::helper::envelope_validation::check_envelope;
+use crate::helper::hierarchy::check_hierarchy;
+use crate::helper::loop_path::element_path;
+use crate::helper::syntax_rules::check_syntax;
+use crate::validation::balancing;
+use crate::validation::usage::UsageRule;
+use crate::validation::{Rule, SnipLevel, ValidationContext};
+
+/// The rules of every level, in the order they run.
+pub(crate) fn built_in() -> Vec<Box<dyn Rule>> {
+    let mut rules: Vec<Box<dyn Rule>> = vec![
+        Box::new(EnvelopeRule),
+        Box::new(ElementRule),
+        Box::new(SyntaxRule),
+        Box::new(HierarchyRule),
+        Box::new(UnrecognizedRule),
+        Box::new(ImplementationGuideRule),
+        Box::new(UsageRule),
+        Box::new(ReplacementClaimRule),
+        Box::new(EnvelopeCodesRule),
+        Box::new(FacilityQualifierRule),
+        Box::new(ServiceLineRule),
+    ];
+    rules.extend(balancing::built_in());
+    rules
+}
+
+/// Implementation guide prefixes (ST03/GS08) of each transaction set.
+const GUIDES: &[(&str, &[&str])] = &[
+    ("270", &["005010X279"]),
+    ("271", &["005010X279"]),
+    ("276", &["005010X212"]),
+    ("277", &["005010X212", "005010X214"]),
+    ("278", &["005010X217"]),
+    ("820", &["005010X218"]),
+    ("834", &["005010X220"]),
+    ("835", &["005010X221"]),
+    ("837", &["005010X222", "005010X223", "005010X224"]),
+    ("999", &["005010X231"]),
+];
+
+/// ISA/IEA, GS/GE and ST/SE control numbers and counts.
+struct EnvelopeRule;
+
+impl Rule for EnvelopeRule {
+    fn id(&self) -> &str {
+        "ENVELOPE"
+    }
+
+    fn level(&self) -> SnipLevel {
+        SnipLevel::Integrity
+    }
+
+    fn check(&self, context: &ValidationContext, diagnostics: &mut Diagnostics) {
+        // Only ST/SE is left to check inside a batch
+        let start = if context.envelope_checked {
+            context.positions("ST").next().unwrap_or(0)
+        } else {
+            0
+        };
+        check_envelope(&context.segments[start..], diagnostics);
+    }
+}
+
+/// Segment IDs, and element values against the element dictionary: data type
+/// (numeric, decimal, date, time), length and date period formats.
+struct ElementRule;
+
+impl Rule for ElementRule {
+    fn id(&self) -> &str {
+        "ELEMENTS"
+    }
+
+    fn level(&self) -> SnipLevel {
+        SnipLevel::Integrity
+    }
+
+    fn check(&self, context: &ValidationContext, diagnostics: &mut Diagnostics) {
+        for (position, segment) in context.segments.iter().enumerate() {
+            let valid = (2..=3).contains(&segment.id.len())
+                && segment
+                    .id
+                    .chars()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+            if !valid {
+                diagnostics.push(context.locate(
+                    Diagnostic::new(
+                        Severity::Error,
+                        DiagnosticCode::InvalidSyntax,
+                        format!("'{}' is not a valid segment ID", segment.id),
+                    ),
+                    position,
+                    None,
+                ));
+            }
+        }
+        check_elements(context.segments, context.paths, diagnostics);
+    }
+}
+
+/// X12 syntax notes (paired, required, conditional, exclusion, list conditional).
+struct SyntaxRule;
+
+impl Rule for SyntaxRule {
+    fn id(&self) -> &str {
+        "SYNTAX"
+    }
+
+    fn level(&self) -> SnipLevel {
+        SnipLevel::Integrity
+    }
+
+    fn check(&self, context: &ValidationContext, diagnostics: &mut Diagnostics) {
+        check_syntax(context.segments, context.paths, diagnostics);
+    }
+}
+
+/// HL numbering, parents, level codes and child codes.
+struct HierarchyRule;
+
+impl Rule for HierarchyRule {
+    fn id(&self) -> &str {
+        "HIERARCHY"
+    }
+
+    fn level(&self) -> SnipLevel {
+        SnipLevel::Integrity
+    }
+
+    fn check(&self, context: &ValidationContext, diagnostics: &mut Diagnostics) {
+        check_hierarchy(context.segments, context.paths, diagnostics);
+    }
+}
+
+/// Segments the parser did not recognize.
+struct UnrecognizedRule;
+
+impl Rule for UnrecognizedRule {
+    fn id(&self) -> &str {
+        "UNRECOGNIZED"
+    }
+
+    fn level(&self) -> SnipLevel {
+        SnipLevel::Integrity
+    }
+
+    fn check(&self, context: &ValidationContext, diagnostics: &mut Diagnostics) {
+        for unrecognized in context.unrecognized.iter() {
+            let diagnostic = Diagnostic::new(
+                Severity::Warning,
+                DiagnosticCode::UnprocessedSegment,
+                format!("Segment {} was not recognized", unrecognized.segment),
+            );
+            let found = context
+                .segments
+                .get(unrecognized.position)
+                .filter(|segment| segment.raw == unrecognized.segment);
+            diagnostics.push(match found {
+                Some(_) => context.locate(diagnostic, unrecognized.position, None),
+                None => Diagnostic {
+                    loop_path: element_path(
+                        &unrecognized.loop_path,
+                        unrecognized.segment.split('*').next().unwrap_or(""),
+                        None,
+                    ),
+                    ..diagnostic
+                },
+            });
+        }
+    }
+}
+
+/// ST03/GS08 names an implementation guide of the transaction set.
+struct ImplementationGuideRule;
+
+impl Rule for ImplementationGuideRule {
+    fn id(&self) -> &str {
+        "IMPLEMENTATION-GUIDE"
+    }
+
+    fn level(&self) -> SnipLevel {
+        SnipLevel::Requirements
+    }
+
+    fn check(&self, context: &ValidationContext, diagnostics: &mut Diagnostics) {
+        let Some((_, guides)) = GUIDES
+            .iter()
+            .find(|(id, _)| *id == context.transaction_set_id)
+        else {
+            return;
+        };
+        let guide = &context.implementation_guide;
+        if guides.iter().any(|prefix| guide.starts_with(prefix)) {
+            return;
+        }
+        let diagnostic = Diagnostic::new(
+            Severity::Error,
+            DiagnosticCode::ImplementationGuideMismatch,
+            format!(
+                "Implementation guide '{}' is not one of {} for transaction set {}",
+                guide,
+                guides.join(", "),
+                context.transaction_set_id
+            ),
+        );
+        match context.positions("ST").next() {
+            Some(st) => diagnostics.push(context.locate(diagnostic, st, Some(3))),
+            None => diagnostics.push(diagnostic),
+        }
+    }
+}
+
+/// Position of every CLM segment and of the segment ending its claim (next CLM, HL or SE).
+pub(super) fn claims(context: &ValidationContext) -> Vec<(usize, usize)> {
+    context
+        .positions("CLM")
+        .map(|clm| {
+            let end = context.segments[clm + 1..]
+                .iter()
+                .position(|segment| matches!(segment.id, "CLM" | "HL" | "SE"))
+                .map_or(context.segments.len(), |offset| clm + 1 + offset);
+            (clm, end)
+        })
+        .collect()
+}
+
+/// Component `component` (1-based) of CLM05 health care service location information.
+fn clm05(context: &ValidationContext, clm: usize, component: usize) -> String {
+    context.segments[clm]
+        .element(4)
+        .split(':')
+        .nth(component - 1)
+        .unwrap_or("")
+        .to_string()
+}
+
+/// 837 replacement and void claims (CLM05-3 frequency 7 or 8) must carry the
+/// payer claim control number of the original claim (2300 REF*F8).
+struct ReplacementClaimRule;
+
+impl Rule for ReplacementClaimRule {
+    fn id(&self) -> &str {
+        "837-REPLACEMENT-REF-F8"
+    }
+
+    fn level(&self) -> SnipLevel {
+        SnipLevel::Situational
+    }
+
+    fn applies_to(&self, transaction_set_id: &str) -> bool {
+        transaction_set_id == "837"
+    }
+
+    fn check(&self, context: &ValidationContext, diagnostics: &mut Diagnostics) {
+        for (clm, end) in claims(context) {
+            let frequency = clm05(context, clm, 3);
+            if frequency != "7" && frequency != "8" {
+                continue;
+            }
+            let has_reference = (clm + 1..end).any(|position| {
+                let segment = &context.segments[position];
+                segment.id == "REF"
+                    && segment.element(0) == "F8"
+                    && context.paths[position].ends_with("2300")
+            });
+            if !has_reference {
+                diagnostics.push(context.locate(
+                    Diagnostic::new(
+                        Severity::Error,
+                        DiagnosticCode::SituationalRule,
+                        format!(
+                            "Claim {} has frequency code {} but no REF*F8 payer claim control number",
+                            context.segments[clm].element(0),
+                            frequency
+                        ),
+                    ),
+                    clm,
+                    Some(5),
+                ));
+            }
+        }
+    }
+}
+
+/// ISA14, ISA15 and GS01 code values.
+struct EnvelopeCodesRule;
+
+impl Rule for EnvelopeCodesRule {
+    fn id(&self) -> &str {
+        "ENVELOPE-CODES"
+    }
+
+    fn level(&self) -> SnipLevel {
+        SnipLevel::CodeSets
+    }
+
+    fn check(&self, context: &ValidationContext, diagnostics: &mut Diagnostics) {
+        let mut check = |position: usize, element: usize, valid: &[&str], name: &str| {
+            let value = context.segments[position].element(element - 1);
+            if !valid.contains(&value) {
+                diagnostics.push(context.locate(
+                    Diagnostic::new(
+                        Severity::Error,
+                        DiagnosticCode::InvalidCode,
+                        format!("{} '{}' is not one of {}", name, value, valid.join(", ")),
+                    ),
+                    position,
+                    Some(element),
+                ));
+            }
+        };
+        for isa in context.positions("ISA") {
+            check(isa, 14, &["0", "1"], "Acknowledgment requested");
+            check(isa, 15, &["P", "T", "I"], "Usage indicator");
+        }
+        if let Some(code) = functional_id_code(&context.transaction_set_id) {
+            for gs in context.positions("GS") {
+                check(gs, 1, &[code], "Functional identifier code");
+            }
+        }
+    }
+}
+
+/// Expected value of an 837 rule for the subtype named by the implementation guide.
+fn by_subtype<'a>(
+    guide: &str,
+    professional: &'a str,
+    institutional: &'a str,
+    dental: &'a str,
+) -> &'a str {
+    if guide.contains("X223") {
+        institutional
+    } else if guide.contains("X224") {
+        dental
+    } else {
+        professional
+    }
+}
+
+/// CLM05-2 facility code qualifier: `B` (place of service) for 837P and
+/// 837D, `A` (uniform billing claim form bill type) for 837I.
+struct FacilityQualifierRule;
+
+impl Rule for FacilityQualifierRule {
+    fn id(&self) -> &str {
+        "837-FACILITY-QUALIFIER"
+    }
+
+    fn level(&self) -> SnipLevel {
+        SnipLevel::ProductType
+    }
+
+    fn applies_to(&self, transaction_set_id: &str) -> bool {
+        transaction_set_id == "837"
+    }
+
+    fn check(&self, context: &ValidationContext, diagnostics: &mut Diagnostics) {
+        let expected = by_subtype(&context.implementation_guide, "B", "A", "B");
+        for (clm, _) in claims(context) {
+            let qualifier = clm05(context, clm, 2);
+            if qualifier != expected {
+                diagnostics.push(context.locate(
+                    Diagnostic::new(
+                        Severity::Error,
+                        DiagnosticCode::ProductTypeMismatch,
+                        format!(
+                            "Facility code qualifier '{}' of claim {} must be '{}' for {}",
+                            qualifier,
+                            context.segments[clm].element(0),
+                            expected,
+                            context.implementation_guide
+                        ),
+                    ),
+                    clm,
+                    Some(5),
+                ));
+            }
+        }
+    }
+}
+
+/// Service lines use the service segment of the 837 subtype: SV1
+/// (professional), SV2 (institutional) or SV3 (dental).
+struct ServiceLineRule;
+
+impl Rule for ServiceLineRule {
+    fn id(&self) -> &str {
+        "837-SERVICE-LINE"
+    }
+
+    fn level(&self) -> SnipLevel {
+        SnipLevel::ProductType
+    }
+
+    fn applies_to(&self, transaction_set_id: &str) -> bool {
+        transaction_set_id == "837"
+    }
+
+    fn check(&self, context: &ValidationContext, diagnostics: &mut Diagnostics) {
+        let expected = by_subtype(&context.implementation_guide, "SV1", "SV2", "SV3");
+        for (position, segment) in context.segments.iter().enumerate() {
+            if matches!(segment.id, "SV1" | "SV2" | "SV3") && segment.id != expected {
+                diagnostics.push(context.locate(
+                    Diagnostic::new(
+                        Severity::Error,
+                        DiagnosticCode::ProductTypeMismatch,
+                        format!(
+                            "{} service line in {}, which uses {}",
+                            segment.id, context.implementation_guide, expected
+                        ),
+                    ),
+                    position,
+                    None,
+                ));
+            }
+        }
+    }
+}
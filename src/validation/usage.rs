@@ -0,0 +1,1638 @@
+//! Implementation guide usage
+//!
+//! Usage metadata of each supported implementation guide: per loop, the
+//! loop's own usage and repeat, and per segment its usage (required,
+//! situational or not used), maximum use and required and unused elements.
+//! `UsageRule` checks a transaction set against the guide named by its ST03
+//! (or GS08), so a 5010 837P missing `SBR` or `NM1*85`, or an 835 missing
+//! `TRN`, is reported instead of parsing silently into defaults.
+//!
+//! Loops are identified as by `helper/loop_path.rs`; segments outside any
+//! loop (the heading and, e.g., the 835 PLB summary) belong to the loop with
+//! an empty ID. The metadata covers the segments that decide whether a
+//! transaction set is usable; segments not listed are left to other rules.
+
+use crate::helper::diagnostics::{Diagnostic, DiagnosticCode, Diagnostics, Severity};
+use crate::helper::loop_path::element_path;
+use crate::helper::tokenizer::Segment;
+use crate::validation::{Rule, SnipLevel, ValidationContext};
+
+use Usage::{NotUsed, Required, Situational};
+
+/// Envelope segments, checked by the envelope rules instead.
+const ENVELOPE: &[&str] = &["ISA", "GS", "ST", "SE", "GE", "IEA"];
+
+/// Maximum use or repeat of `>1` in the guides.
+pub const UNBOUNDED: usize = usize::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Usage {
+    Required,
+    Situational,
+    NotUsed,
+}
+
+/// Usage of a segment within its loop.
+#[derive(Debug)]
+pub struct SegmentUsage {
+    pub id: &'static str,
+    /// Element (1-based) and codes telling this segment apart from others with
+    /// the same ID in the loop, e.g. NM101 `85`; composites match on their first component
+    pub qualifier: Option<(usize, &'static [&'static str])>,
+    pub usage: Usage,
+    pub max_use: usize,
+    /// Elements (1-based) that must have a value
+    pub required: &'static [usize],
+    /// Elements (1-based) that must be empty
+    pub not_used: &'static [usize],
+}
+
+impl SegmentUsage {
+    /// Elements that must be empty, e.g. `segment("CLM", ...).not_used(&[3, 4])`.
+    pub const fn not_used(mut self, elements: &'static [usize]) -> SegmentUsage {
+        self.not_used = elements;
+        self
+    }
+
+    pub fn matches(&self, segment: &Segment) -> bool {
+        segment.id == self.id
+            && self.qualifier.is_none_or(|(element, codes)| {
+                let value = segment.element(element - 1);
+                codes.contains(&value.split(':').next().unwrap_or(value))
+            })
+    }
+
+    /// The segment reference, e.g. `NM1*85`.
+    pub fn name(&self) -> String {
+        match self.qualifier {
+            Some((1, codes)) => format!("{}*{}", self.id, codes.join("/")),
+            _ => self.id.to_string(),
+        }
+    }
+}
+
+/// Usage of a loop: how often it may repeat within its parent loop, and its segments.
+#[derive(Debug)]
+pub struct LoopUsage {
+    /// Loop ID, empty for segments outside any loop
+    pub loop_id: &'static str,
+    /// Loops this loop nests under; empty for a top-level loop
+    pub parents: &'static [&'static str],
+    pub usage: Usage,
+    pub max_repeat: usize,
+    /// Segments of the loop, the loop's trigger first
+    pub segments: &'static [SegmentUsage],
+}
+
+/// Usage metadata of an implementation guide.
+#[derive(Debug)]
+pub struct GuideUsage {
+    /// Implementation guide, e.g. `005010X222A1`; earlier and later errata
+    /// (`005010X222`, `005010X222A2`) share the metadata
+    pub guide: &'static str,
+    pub transaction_set_id: &'static str,
+    pub loops: &'static [LoopUsage],
+}
+
+impl GuideUsage {
+    pub fn loop_usage(&self, loop_id: &str) -> Option<&'static LoopUsage> {
+        self.loops.iter().find(|usage| usage.loop_id == loop_id)
+    }
+}
+
+const fn segment(
+    id: &'static str,
+    usage: Usage,
+    max_use: usize,
+    required: &'static [usize],
+) -> SegmentUsage {
+    SegmentUsage {
+        id,
+        qualifier: None,
+        usage,
+        max_use,
+        required,
+        not_used: &[],
+    }
+}
+
+const fn qualified(
+    id: &'static str,
+    element: usize,
+    codes: &'static [&'static str],
+    usage: Usage,
+    max_use: usize,
+    required: &'static [usize],
+) -> SegmentUsage {
+    SegmentUsage {
+        id,
+        qualifier: Some((element, codes)),
+        usage,
+        max_use,
+        required,
+        not_used: &[],
+    }
+}
+
+const fn looped(
+    loop_id: &'static str,
+    parents: &'static [&'static str],
+    usage: Usage,
+    max_repeat: usize,
+    segments: &'static [SegmentUsage],
+) -> LoopUsage {
+    LoopUsage {
+        loop_id,
+        parents,
+        usage,
+        max_repeat,
+        segments,
+    }
+}
+
+/// Name segments, elements NM101, NM102 and, for organizations and persons
+/// identified by ID, NM103, NM108 and NM109.
+const NAME: &[usize] = &[1, 2, 3, 8, 9];
+
+const HL: SegmentUsage = segment("HL", Required, 1, &[1, 3, 4]);
+const CHILD_HL: SegmentUsage = segment("HL", Required, 1, &[1, 2, 3, 4]);
+
+const USAGE_270: &[LoopUsage] = &[
+    looped(
+        "",
+        &[],
+        Required,
+        1,
+        &[segment("BHT", Required, 1, &[1, 2, 3, 4, 5])],
+    ),
+    looped("2000A", &[], Required, UNBOUNDED, &[HL]),
+    looped(
+        "2100A",
+        &["2000A"],
+        Required,
+        1,
+        &[segment("NM1", Required, 1, NAME)],
+    ),
+    looped("2000B", &[], Required, UNBOUNDED, &[CHILD_HL]),
+    looped(
+        "2100B",
+        &["2000B"],
+        Required,
+        1,
+        &[
+            segment("NM1", Required, 1, &[1, 2, 8, 9]),
+            segment("REF", Situational, 9, &[1, 2]),
+            segment("N3", Situational, 1, &[1]),
+            segment("N4", Situational, 1, &[1]),
+            segment("PRV", Situational, 1, &[1, 2, 3]),
+        ],
+    ),
+    looped(
+        "2000C",
+        &[],
+        Required,
+        UNBOUNDED,
+        &[CHILD_HL, segment("TRN", Situational, 2, &[1, 2, 3])],
+    ),
+    looped(
+        "2100C",
+        &["2000C"],
+        Required,
+        1,
+        &[
+            segment("NM1", Required, 1, &[1, 2]),
+            segment("REF", Situational, 9, &[1, 2]),
+            segment("N3", Situational, 1, &[1]),
+            segment("N4", Situational, 1, &[1]),
+            segment("DMG", Situational, 1, &[1, 2]),
+            segment("INS", Situational, 1, &[1, 2]),
+            segment("DTP", Situational, 2, &[1, 2, 3]),
+        ],
+    ),
+    looped(
+        "2110C",
+        &["2100C"],
+        Situational,
+        99,
+        &[segment("EQ", Required, 1, &[])],
+    ),
+    looped(
+        "2000D",
+        &[],
+        Situational,
+        UNBOUNDED,
+        &[CHILD_HL, segment("TRN", Situational, 2, &[1, 2, 3])],
+    ),
+    looped(
+        "2100D",
+        &["2000D"],
+        Required,
+        1,
+        &[
+            segment("NM1", Required, 1, &[1, 2]),
+            segment("DMG", Situational, 1, &[1, 2]),
+        ],
+    ),
+    looped(
+        "2110D",
+        &["2100D"],
+        Situational,
+        99,
+        &[segment("EQ", Required, 1, &[])],
+    ),
+];
+
+const USAGE_271: &[LoopUsage] = &[
+    looped(
+        "",
+        &[],
+        Required,
+        1,
+        &[segment("BHT", Required, 1, &[1, 2, 3, 4, 5])],
+    ),
+    looped(
+        "2000A",
+        &[],
+        Required,
+        UNBOUNDED,
+        &[HL, segment("AAA", Situational, 9, &[1, 3, 4])],
+    ),
+    looped(
+        "2100A",
+        &["2000A"],
+        Required,
+        1,
+        &[
+            segment("NM1", Required, 1, NAME),
+            segment("PER", Situational, 3, &[1]),
+            segment("AAA", Situational, 9, &[1, 3, 4]),
+        ],
+    ),
+    looped("2000B", &[], Situational, UNBOUNDED, &[CHILD_HL]),
+    looped(
+        "2100B",
+        &["2000B"],
+        Required,
+        1,
+        &[
+            segment("NM1", Required, 1, &[1, 2, 8, 9]),
+            segment("REF", Situational, 9, &[1, 2]),
+            segment("AAA", Situational, 9, &[1, 3, 4]),
+        ],
+    ),
+    looped(
+        "2000C",
+        &[],
+        Situational,
+        UNBOUNDED,
+        &[CHILD_HL, segment("TRN", Situational, 3, &[1, 2, 3])],
+    ),
+    looped(
+        "2100C",
+        &["2000C"],
+        Required,
+        1,
+        &[
+            segment("NM1", Required, 1, &[1, 2]),
+            segment("REF", Situational, 9, &[1, 2]),
+            segment("N3", Situational, 1, &[1]),
+            segment("N4", Situational, 1, &[1]),
+            segment("AAA", Situational, 9, &[1, 3, 4]),
+            segment("DMG", Situational, 1, &[1, 2]),
+            segment("INS", Situational, 1, &[1, 2]),
+            segment("DTP", Situational, 9, &[1, 2, 3]),
+        ],
+    ),
+    looped(
+        "2110C",
+        &["2100C"],
+        Situational,
+        UNBOUNDED,
+        &[segment("EB", Required, 1, &[1])],
+    ),
+    looped(
+        "2115C",
+        &["2110C"],
+        Situational,
+        10,
+        &[segment("III", Required, 1, &[1, 2])],
+    ),
+    looped(
+        "2120C",
+        &["2110C"],
+        Situational,
+        23,
+        &[segment("NM1", Required, 1, &[1, 2])],
+    ),
+    looped(
+        "2000D",
+        &[],
+        Situational,
+        UNBOUNDED,
+        &[CHILD_HL, segment("TRN", Situational, 3, &[1, 2, 3])],
+    ),
+    looped(
+        "2100D",
+        &["2000D"],
+        Required,
+        1,
+        &[segment("NM1", Required, 1, &[1, 2])],
+    ),
+    looped(
+        "2110D",
+        &["2100D"],
+        Situational,
+        UNBOUNDED,
+        &[segment("EB", Required, 1, &[1])],
+    ),
+    looped(
+        "2115D",
+        &["2110D"],
+        Situational,
+        10,
+        &[segment("III", Required, 1, &[1, 2])],
+    ),
+    looped(
+        "2120D",
+        &["2110D"],
+        Situational,
+        23,
+        &[segment("NM1", Required, 1, &[1, 2])],
+    ),
+];
+
+/// Loops shared by the 276 and 277 information source, receiver and provider levels.
+const USAGE_276: &[LoopUsage] = &[
+    looped(
+        "",
+        &[],
+        Required,
+        1,
+        &[segment("BHT", Required, 1, &[1, 2, 3, 4])],
+    ),
+    looped("2000A", &[], Required, UNBOUNDED, &[HL]),
+    looped(
+        "2100A",
+        &["2000A"],
+        Required,
+        1,
+        &[segment("NM1", Required, 1, NAME)],
+    ),
+    looped("2000B", &[], Required, UNBOUNDED, &[CHILD_HL]),
+    looped(
+        "2100B",
+        &["2000B"],
+        Required,
+        1,
+        &[segment("NM1", Required, 1, NAME)],
+    ),
+    looped("2000C", &[], Required, UNBOUNDED, &[CHILD_HL]),
+    looped(
+        "2100C",
+        &["2000C"],
+        Required,
+        1,
+        &[segment("NM1", Required, 1, NAME)],
+    ),
+    looped(
+        "2000D",
+        &[],
+        Required,
+        UNBOUNDED,
+        &[CHILD_HL, segment("DMG", Situational, 1, &[1, 2])],
+    ),
+    looped(
+        "2100D",
+        &["2000D"],
+        Required,
+        1,
+        &[segment("NM1", Required, 1, &[1, 2, 3])],
+    ),
+    looped(
+        "2200D",
+        &["2000D"],
+        Situational,
+        UNBOUNDED,
+        &[
+            segment("TRN", Required, 1, &[1, 2]),
+            segment("REF", Situational, 3, &[1, 2]),
+            segment("AMT", Situational, 1, &[1, 2]),
+            segment("DTP", Situational, 1, &[1, 2, 3]),
+        ],
+    ),
+    looped(
+        "2210D",
+        &["2200D"],
+        Situational,
+        UNBOUNDED,
+        &[segment("SVC", Required, 1, &[1, 2])],
+    ),
+    looped(
+        "2000E",
+        &[],
+        Situational,
+        UNBOUNDED,
+        &[CHILD_HL, segment("DMG", Situational, 1, &[1, 2])],
+    ),
+    looped(
+        "2100E",
+        &["2000E"],
+        Required,
+        1,
+        &[segment("NM1", Required, 1, &[1, 2, 3])],
+    ),
+    looped(
+        "2200E",
+        &["2000E"],
+        Required,
+        UNBOUNDED,
+        &[segment("TRN", Required, 1, &[1, 2])],
+    ),
+    looped(
+        "2210E",
+        &["2200E"],
+        Situational,
+        UNBOUNDED,
+        &[segment("SVC", Required, 1, &[1, 2])],
+    ),
+];
+
+const USAGE_277: &[LoopUsage] = &[
+    looped(
+        "",
+        &[],
+        Required,
+        1,
+        &[segment("BHT", Required, 1, &[1, 2, 3, 4])],
+    ),
+    looped("2000A", &[], Required, UNBOUNDED, &[HL]),
+    looped(
+        "2100A",
+        &["2000A"],
+        Required,
+        1,
+        &[segment("NM1", Required, 1, NAME)],
+    ),
+    looped("2000B", &[], Required, UNBOUNDED, &[CHILD_HL]),
+    looped(
+        "2100B",
+        &["2000B"],
+        Required,
+        1,
+        &[segment("NM1", Required, 1, NAME)],
+    ),
+    looped("2000C", &[], Required, UNBOUNDED, &[CHILD_HL]),
+    looped(
+        "2100C",
+        &["2000C"],
+        Required,
+        1,
+        &[segment("NM1", Required, 1, NAME)],
+    ),
+    looped("2000D", &[], Required, UNBOUNDED, &[CHILD_HL]),
+    looped(
+        "2100D",
+        &["2000D"],
+        Required,
+        1,
+        &[segment("NM1", Required, 1, &[1, 2, 3])],
+    ),
+    looped(
+        "2200D",
+        &["2000D"],
+        Situational,
+        UNBOUNDED,
+        &[
+            segment("TRN", Required, 1, &[1, 2]),
+            segment("STC", Required, UNBOUNDED, &[1]),
+            segment("REF", Situational, 3, &[1, 2]),
+            segment("DTP", Situational, 1, &[1, 2, 3]),
+        ],
+    ),
+    looped(
+        "2220D",
+        &["2200D"],
+        Situational,
+        UNBOUNDED,
+        &[
+            segment("SVC", Required, 1, &[1, 2]),
+            segment("STC", Required, UNBOUNDED, &[1]),
+        ],
+    ),
+    looped("2000E", &[], Situational, UNBOUNDED, &[CHILD_HL]),
+    looped(
+        "2100E",
+        &["2000E"],
+        Required,
+        1,
+        &[segment("NM1", Required, 1, &[1, 2, 3])],
+    ),
+    looped(
+        "2200E",
+        &["2000E"],
+        Required,
+        UNBOUNDED,
+        &[
+            segment("TRN", Required, 1, &[1, 2]),
+            segment("STC", Required, UNBOUNDED, &[1]),
+        ],
+    ),
+    looped(
+        "2220E",
+        &["2200E"],
+        Situational,
+        UNBOUNDED,
+        &[
+            segment("SVC", Required, 1, &[1, 2]),
+            segment("STC", Required, UNBOUNDED, &[1]),
+        ],
+    ),
+];
+
+const USAGE_278: &[LoopUsage] = &[
+    looped(
+        "",
+        &[],
+        Required,
+        1,
+        &[segment("BHT", Required, 1, &[1, 2, 3, 4, 5])],
+    ),
+    looped("2000A", &[], Required, 1, &[HL]),
+    looped(
+        "2010A",
+        &["2000A"],
+        Required,
+        1,
+        &[segment("NM1", Required, 1, NAME)],
+    ),
+    looped("2000B", &[], Required, 1, &[CHILD_HL]),
+    looped(
+        "2010B",
+        &["2000B"],
+        Required,
+        1,
+        &[segment("NM1", Required, 1, NAME)],
+    ),
+    looped("2000C", &[], Required, 1, &[CHILD_HL]),
+    looped(
+        "2010C",
+        &["2000C"],
+        Required,
+        1,
+        &[
+            segment("NM1", Required, 1, NAME),
+            segment("DMG", Situational, 1, &[1, 2]),
+        ],
+    ),
+    looped("2000D", &[], Situational, 1, &[CHILD_HL]),
+    looped(
+        "2010D",
+        &["2000D"],
+        Required,
+        1,
+        &[segment("NM1", Required, 1, &[1, 2])],
+    ),
+    looped(
+        "2000E",
+        &[],
+        Required,
+        1,
+        &[CHILD_HL, segment("UM", Required, 1, &[1, 2])],
+    ),
+    looped(
+        "2010E",
+        &["2000E"],
+        Situational,
+        3,
+        &[segment("NM1", Required, 1, &[1, 2])],
+    ),
+    looped("2000F", &[], Situational, UNBOUNDED, &[CHILD_HL]),
+    looped(
+        "2010F",
+        &["2000F"],
+        Situational,
+        3,
+        &[segment("NM1", Required, 1, &[1, 2])],
+    ),
+];
+
+const USAGE_820: &[LoopUsage] = &[
+    looped(
+        "",
+        &[],
+        Required,
+        1,
+        &[
+            segment("BPR", Required, 1, &[1, 2, 3, 4, 16]),
+            segment("TRN", Required, 1, &[1, 2]),
+            segment("CUR", Situational, 1, &[1, 2]),
+            segment("REF", Situational, 3, &[1, 2]),
+            segment("DTM", Situational, 1, &[1, 2]),
+        ],
+    ),
+    looped(
+        "1000A",
+        &[],
+        Required,
+        1,
+        &[qualified("N1", 1, &["PE"], Required, 1, &[1, 2])],
+    ),
+    looped(
+        "1000B",
+        &[],
+        Required,
+        1,
+        &[qualified("N1", 1, &["PR", "RM"], Required, 1, &[1])],
+    ),
+    looped(
+        "2000",
+        &[],
+        Situational,
+        UNBOUNDED,
+        &[segment("ENT", Required, 1, &[1])],
+    ),
+    looped(
+        "2100",
+        &["2000"],
+        Situational,
+        1,
+        &[segment("NM1", Required, 1, &[1, 2])],
+    ),
+    looped(
+        "2300",
+        &["2000"],
+        Situational,
+        UNBOUNDED,
+        &[
+            segment("RMR", Required, 1, &[1, 2, 4]),
+            segment("REF", Situational, 3, &[1, 2]),
+            segment("DTM", Situational, 1, &[1, 2]),
+        ],
+    ),
+];
+
+const USAGE_834: &[LoopUsage] = &[
+    looped(
+        "",
+        &[],
+        Required,
+        1,
+        &[
+            segment("BGN", Required, 1, &[1, 2, 3, 8]),
+            qualified("REF", 1, &["38"], Situational, 1, &[1, 2]),
+            segment("DTP", Situational, UNBOUNDED, &[1, 2, 3]),
+            segment("QTY", Situational, 3, &[1, 2]),
+        ],
+    ),
+    looped(
+        "1000A",
+        &[],
+        Required,
+        1,
+        &[qualified("N1", 1, &["P5"], Required, 1, &[1, 3, 4])],
+    ),
+    looped(
+        "1000B",
+        &[],
+        Required,
+        1,
+        &[qualified("N1", 1, &["IN"], Required, 1, &[1, 3, 4])],
+    ),
+    looped(
+        "1000C",
+        &[],
+        Situational,
+        2,
+        &[qualified("N1", 1, &["BO", "TV"], Required, 1, &[1, 3, 4])],
+    ),
+    looped(
+        "2000",
+        &[],
+        Situational,
+        UNBOUNDED,
+        &[
+            segment("INS", Required, 1, &[1, 2, 3, 5]),
+            qualified("REF", 1, &["0F"], Required, 1, &[1, 2]),
+            qualified("REF", 1, &["1L"], Situational, 1, &[1, 2]),
+            segment("DTP", Situational, UNBOUNDED, &[1, 2, 3]),
+        ],
+    ),
+    looped(
+        "2100A",
+        &["2000"],
+        Required,
+        1,
+        &[
+            qualified("NM1", 1, &["IL", "74"], Required, 1, &[1, 2, 3]),
+            segment("PER", Situational, 1, &[1]),
+            segment("N3", Situational, 1, &[1]),
+            segment("N4", Situational, 1, &[1]),
+            segment("DMG", Situational, 1, &[1, 2]),
+        ],
+    ),
+    looped(
+        "2300",
+        &["2000"],
+        Situational,
+        99,
+        &[
+            segment("HD", Required, 1, &[1, 3]),
+            qualified("DTP", 1, &["348"], Required, 1, &[1, 2, 3]),
+            qualified("DTP", 1, &["349"], Situational, 1, &[1, 2, 3]),
+        ],
+    ),
+    looped(
+        "2310",
+        &["2300"],
+        Situational,
+        30,
+        &[segment("LX", Required, 1, &[1])],
+    ),
+    looped(
+        "2320",
+        &["2300"],
+        Situational,
+        5,
+        &[segment("COB", Required, 1, &[1, 3])],
+    ),
+    looped(
+        "2330",
+        &["2320"],
+        Situational,
+        3,
+        &[segment("NM1", Required, 1, &[1, 2])],
+    ),
+];
+
+const USAGE_835: &[LoopUsage] = &[
+    looped(
+        "",
+        &[],
+        Required,
+        1,
+        &[
+            segment("BPR", Required, 1, &[1, 2, 3, 4, 16]),
+            segment("TRN", Required, 1, &[1, 2, 3]),
+            segment("CUR", Situational, 1, &[1, 2]),
+            qualified("REF", 1, &["EV"], Situational, 1, &[1, 2]),
+            qualified("REF", 1, &["F2"], Situational, 1, &[1, 2]),
+            qualified("DTM", 1, &["405"], Situational, 1, &[1, 2]),
+            segment("PLB", Situational, UNBOUNDED, &[1, 2, 3, 4]),
+        ],
+    ),
+    looped(
+        "1000A",
+        &[],
+        Required,
+        1,
+        &[
+            qualified("N1", 1, &["PR"], Required, 1, &[1, 2]),
+            segment("N3", Required, 1, &[1]),
+            segment("N4", Required, 1, &[1]),
+            qualified("PER", 1, &["BL"], Required, UNBOUNDED, &[1]),
+        ],
+    ),
+    looped(
+        "1000B",
+        &[],
+        Required,
+        1,
+        &[
+            qualified("N1", 1, &["PE"], Required, 1, &[1, 2, 3, 4]),
+            segment("N3", Situational, 1, &[1]),
+            segment("N4", Situational, 1, &[1]),
+        ],
+    ),
+    looped(
+        "2000",
+        &[],
+        Situational,
+        UNBOUNDED,
+        &[
+            segment("LX", Required, 1, &[1]),
+            segment("TS3", Situational, 1, &[1, 2, 3, 4, 5]),
+            segment("TS2", Situational, 1, &[]),
+        ],
+    ),
+    looped(
+        "2100",
+        &["2000"],
+        Required,
+        UNBOUNDED,
+        &[
+            segment("CLP", Required, 1, &[1, 2, 3, 4, 6, 7]),
+            segment("CAS", Situational, 99, &[1, 2, 3]),
+            qualified("NM1", 1, &["QC"], Required, 1, &[1, 2]),
+            qualified("NM1", 1, &["IL"], Situational, 1, &[1, 2]),
+            segment("MIA", Situational, 1, &[1]),
+            segment("MOA", Situational, 1, &[]),
+            segment("DTM", Situational, 4, &[1, 2]),
+        ],
+    ),
+    looped(
+        "2110",
+        &["2100"],
+        Situational,
+        999,
+        &[
+            segment("SVC", Required, 1, &[1, 2, 3]),
+            segment("DTM", Situational, 2, &[1, 2]),
+            segment("CAS", Situational, 99, &[1, 2, 3]),
+            segment("REF", Situational, 16, &[1, 2]),
+            segment("AMT", Situational, 9, &[1, 2]),
+            segment("LQ", Situational, 99, &[1, 2]),
+        ],
+    ),
+];
+
+/// Submitter, receiver, billing provider and subscriber loops shared by the
+/// three 837 guides.
+const PARTIES_837: &[LoopUsage] = &[
+    looped(
+        "",
+        &[],
+        Required,
+        1,
+        &[segment("BHT", Required, 1, &[1, 2, 3, 4, 5, 6])],
+    ),
+    looped(
+        "1000A",
+        &[],
+        Required,
+        1,
+        &[
+            qualified("NM1", 1, &["41"], Required, 1, NAME),
+            qualified("PER", 1, &["IC"], Required, 2, &[1, 3, 4]),
+        ],
+    ),
+    looped(
+        "1000B",
+        &[],
+        Required,
+        1,
+        &[qualified("NM1", 1, &["40"], Required, 1, NAME)],
+    ),
+    looped(
+        "2000A",
+        &[],
+        Required,
+        UNBOUNDED,
+        &[
+            HL,
+            qualified("PRV", 1, &["BI"], Situational, 1, &[1, 2, 3]),
+            segment("CUR", Situational, 1, &[1, 2]),
+        ],
+    ),
+    looped(
+        "2010AA",
+        &["2000A"],
+        Required,
+        1,
+        &[
+            qualified("NM1", 1, &["85"], Required, 1, NAME),
+            segment("N3", Required, 1, &[1]),
+            segment("N4", Required, 1, &[1]),
+            qualified("REF", 1, &["EI", "SY"], Required, 1, &[1, 2]),
+            segment("PER", Situational, 2, &[1]),
+        ],
+    ),
+    looped(
+        "2010AB",
+        &["2000A"],
+        Situational,
+        1,
+        &[
+            qualified("NM1", 1, &["87"], Required, 1, &[1, 2]),
+            segment("N3", Required, 1, &[1]),
+            segment("N4", Required, 1, &[1]),
+        ],
+    ),
+    looped(
+        "2010AC",
+        &["2000A"],
+        Situational,
+        1,
+        &[qualified("NM1", 1, &["PE"], Required, 1, NAME)],
+    ),
+    looped(
+        "2000B",
+        &[],
+        Required,
+        UNBOUNDED,
+        &[
+            CHILD_HL,
+            segment("SBR", Required, 1, &[1, 9]),
+            segment("PAT", Situational, 1, &[]),
+        ],
+    ),
+    looped(
+        "2010BA",
+        &["2000B"],
+        Required,
+        1,
+        &[
+            qualified("NM1", 1, &["IL"], Required, 1, &[1, 2, 3]),
+            segment("N3", Situational, 1, &[1]),
+            segment("N4", Situational, 1, &[1]),
+            segment("DMG", Situational, 1, &[1, 2]),
+        ],
+    ),
+    looped(
+        "2010BB",
+        &["2000B"],
+        Required,
+        1,
+        &[
+            qualified("NM1", 1, &["PR"], Required, 1, NAME),
+            segment("N3", Situational, 1, &[1]),
+            segment("N4", Situational, 1, &[1]),
+        ],
+    ),
+    looped(
+        "2000C",
+        &[],
+        Situational,
+        UNBOUNDED,
+        &[CHILD_HL, segment("PAT", Required, 1, &[1])],
+    ),
+    looped(
+        "2010CA",
+        &["2000C"],
+        Required,
+        1,
+        &[
+            qualified("NM1", 1, &["QC"], Required, 1, &[1, 2, 3]),
+            segment("N3", Required, 1, &[1]),
+            segment("N4", Required, 1, &[1]),
+            segment("DMG", Required, 1, &[1, 2, 3]),
+        ],
+    ),
+    looped(
+        "2320",
+        &["2300"],
+        Situational,
+        10,
+        &[
+            segment("SBR", Required, 1, &[1, 2, 9]),
+            segment("CAS", Situational, 5, &[1, 2, 3]),
+            segment("AMT", Situational, 3, &[1, 2]),
+            segment("OI", Required, 1, &[3, 6]),
+        ],
+    ),
+    looped(
+        "2330A",
+        &["2320"],
+        Required,
+        1,
+        &[qualified("NM1", 1, &["IL"], Required, 1, NAME)],
+    ),
+    looped(
+        "2330B",
+        &["2320"],
+        Required,
+        1,
+        &[qualified("NM1", 1, &["PR"], Required, 1, NAME)],
+    ),
+];
+
+const CLAIMS_837P: &[LoopUsage] = &[
+    looped(
+        "2300",
+        &["2000B", "2000C"],
+        Situational,
+        100,
+        &[
+            segment("CLM", Required, 1, &[1, 2, 5, 6, 7, 8, 9]).not_used(&[3, 4]),
+            segment("PWK", Situational, 10, &[1, 2]),
+            qualified("REF", 1, &["F8"], Situational, 1, &[1, 2]),
+            qualified("HI", 1, &["ABK", "BK"], Required, 1, &[1]),
+        ],
+    ),
+    looped(
+        "2310A",
+        &["2300"],
+        Situational,
+        2,
+        &[segment("NM1", Required, 1, &[1, 2])],
+    ),
+    looped(
+        "2310B",
+        &["2300"],
+        Situational,
+        1,
+        &[segment("NM1", Required, 1, &[1, 2, 3])],
+    ),
+    looped(
+        "2310C",
+        &["2300"],
+        Situational,
+        1,
+        &[segment("NM1", Required, 1, &[1, 2, 3])],
+    ),
+    looped(
+        "2400",
+        &["2300"],
+        Required,
+        50,
+        &[
+            segment("LX", Required, 1, &[1]),
+            segment("SV1", Required, 1, &[1, 2, 3, 4]),
+            segment("SV2", NotUsed, 1, &[]),
+            segment("SV3", NotUsed, 1, &[]),
+            qualified("DTP", 1, &["472"], Required, 1, &[1, 2, 3]),
+        ],
+    ),
+];
+
+const CLAIMS_837I: &[LoopUsage] = &[
+    looped(
+        "2300",
+        &["2000B", "2000C"],
+        Situational,
+        100,
+        &[
+            segment("CLM", Required, 1, &[1, 2, 5, 7, 8, 9]).not_used(&[3, 4]),
+            qualified("DTP", 1, &["434"], Required, 1, &[1, 2, 3]),
+            segment("CL1", Required, 1, &[1, 3]),
+            qualified("REF", 1, &["F8"], Situational, 1, &[1, 2]),
+            qualified("HI", 1, &["ABK", "BK"], Required, 1, &[1]),
+        ],
+    ),
+    looped(
+        "2310A",
+        &["2300"],
+        Situational,
+        1,
+        &[segment("NM1", Required, 1, &[1, 2, 3])],
+    ),
+    looped(
+        "2310B",
+        &["2300"],
+        Situational,
+        1,
+        &[segment("NM1", Required, 1, &[1, 2, 3])],
+    ),
+    looped(
+        "2400",
+        &["2300"],
+        Required,
+        999,
+        &[
+            segment("LX", Required, 1, &[1]),
+            segment("SV2", Required, 1, &[1, 3, 4, 5]),
+            segment("SV1", NotUsed, 1, &[]),
+            segment("SV3", NotUsed, 1, &[]),
+            qualified("DTP", 1, &["472"], Situational, 1, &[1, 2, 3]),
+        ],
+    ),
+];
+
+const CLAIMS_837D: &[LoopUsage] = &[
+    looped(
+        "2300",
+        &["2000B", "2000C"],
+        Situational,
+        100,
+        &[
+            segment("CLM", Required, 1, &[1, 2, 5, 6, 8, 9]).not_used(&[3, 4]),
+            qualified("REF", 1, &["F8"], Situational, 1, &[1, 2]),
+        ],
+    ),
+    looped(
+        "2310B",
+        &["2300"],
+        Situational,
+        1,
+        &[segment("NM1", Required, 1, &[1, 2, 3])],
+    ),
+    looped(
+        "2400",
+        &["2300"],
+        Required,
+        50,
+        &[
+            segment("LX", Required, 1, &[1]),
+            segment("SV3", Required, 1, &[1, 2]),
+            segment("SV1", NotUsed, 1, &[]),
+            segment("SV2", NotUsed, 1, &[]),
+            segment("TOO", Situational, 32, &[1, 2]),
+            qualified("DTP", 1, &["472"], Situational, 1, &[1, 2, 3]),
+        ],
+    ),
+];
+
+const USAGE_999: &[LoopUsage] = &[
+    looped(
+        "",
+        &[],
+        Required,
+        1,
+        &[
+            segment("AK1", Required, 1, &[1, 2]),
+            segment("AK9", Required, 1, &[1, 2, 3, 4]),
+        ],
+    ),
+    looped(
+        "2000",
+        &[],
+        Situational,
+        UNBOUNDED,
+        &[
+            segment("AK2", Required, 1, &[1, 2]),
+            segment("IK5", Required, 1, &[1]),
+        ],
+    ),
+    looped(
+        "2100",
+        &["2000"],
+        Situational,
+        UNBOUNDED,
+        &[
+            segment("IK3", Required, 1, &[1, 2, 4]),
+            segment("CTX", Situational, 10, &[1]),
+        ],
+    ),
+    looped(
+        "2110",
+        &["2100"],
+        Situational,
+        99,
+        &[
+            segment("IK4", Required, 1, &[1, 3]),
+            segment("CTX", Situational, 10, &[1]),
+        ],
+    ),
+];
+
+const fn guide(
+    guide: &'static str,
+    transaction_set_id: &'static str,
+    loops: &'static [LoopUsage],
+) -> GuideUsage {
+    GuideUsage {
+        guide,
+        transaction_set_id,
+        loops,
+    }
+}
+
+/// Usage metadata of every supported guide. The 837 guides list their party
+/// loops and their claim loops separately; `guide_usage` joins them.
+const GUIDES: &[(GuideUsage, &[LoopUsage])] = &[
+    (guide("005010X279A1", "270", USAGE_270), &[]),
+    (guide("005010X279A1", "271", USAGE_271), &[]),
+    (guide("005010X212", "276", USAGE_276), &[]),
+    (guide("005010X212", "277", USAGE_277), &[]),
+    (guide("005010X217", "278", USAGE_278), &[]),
+    (guide("005010X218", "820", USAGE_820), &[]),
+    (guide("005010X220A1", "834", USAGE_834), &[]),
+    (guide("005010X221A1", "835", USAGE_835), &[]),
+    (guide("005010X222A1", "837", PARTIES_837), CLAIMS_837P),
+    (guide("005010X223A2", "837", PARTIES_837), CLAIMS_837I),
+    (guide("005010X224A2", "837", PARTIES_837), CLAIMS_837D),
+    (guide("005010X231A1", "999", USAGE_999), &[]),
+];
+
+/// Usage metadata of transaction set `transaction_set_id` under
+/// `implementation_guide` (ST03 or GS08), matched without the errata suffix.
+pub fn guide_usage(
+    transaction_set_id: &str,
+    implementation_guide: &str,
+) -> Option<(&'static GuideUsage, &'static [LoopUsage])> {
+    GUIDES
+        .iter()
+        .find(|(usage, _)| {
+            usage.transaction_set_id == transaction_set_id
+                && implementation_guide.starts_with(&usage.guide[..10])
+        })
+        .map(|(usage, extra)| (usage, *extra))
+}
+
+/// One occurrence of a loop in a transaction set.
+struct Instance {
+    /// Full loop path, e.g. `2000B/2300`; empty outside any loop
+    path: String,
+    usage: Option<&'static LoopUsage>,
+    /// Positions of the loop's own segments
+    positions: Vec<usize>,
+    /// Instances of the loops nested directly in this one
+    children: Vec<usize>,
+}
+
+impl Instance {
+    fn loop_id(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or("")
+    }
+
+    fn first(&self) -> Option<usize> {
+        self.positions.first().copied()
+    }
+}
+
+/// Split a transaction set into loop instances; instance 0 holds the segments outside any loop.
+fn instances(
+    context: &ValidationContext,
+    lookup: &dyn Fn(&str) -> Option<&'static LoopUsage>,
+) -> Vec<Instance> {
+    let mut instances = vec![Instance {
+        path: String::new(),
+        usage: lookup(""),
+        positions: Vec::new(),
+        children: Vec::new(),
+    }];
+    // Open instances, outermost first; the root is always open
+    let mut open: Vec<usize> = vec![0];
+
+    for (position, segment) in context.segments.iter().enumerate() {
+        if ENVELOPE.contains(&segment.id) {
+            continue;
+        }
+        let path = &context.paths[position];
+        let loop_id = path.rsplit('/').next().unwrap_or("");
+        let usage = lookup(loop_id);
+        let is_trigger = !path.is_empty()
+            && usage
+                .and_then(|usage| usage.segments.first())
+                .is_some_and(|trigger| trigger.matches(segment));
+
+        // Close the loops the segment is not in; a trigger also closes its own loop
+        while let Some(&top) = open.last() {
+            let open_path = &instances[top].path;
+            let encloses = open_path.is_empty() || path.starts_with(&format!("{}/", open_path));
+            if top == 0 || encloses || (open_path == path && !is_trigger) {
+                break;
+            }
+            open.pop();
+        }
+
+        let top = *open.last().unwrap_or(&0);
+        if instances[top].path == *path {
+            instances[top].positions.push(position);
+        } else {
+            instances.push(Instance {
+                path: path.clone(),
+                usage,
+                positions: vec![position],
+                children: Vec::new(),
+            });
+            let opened = instances.len() - 1;
+            instances[top].children.push(opened);
+            open.push(opened);
+        }
+    }
+    instances
+}
+
+/// Loops and segments used as the implementation guide allows.
+pub(crate) struct UsageRule;
+
+impl Rule for UsageRule {
+    fn id(&self) -> &str {
+        "IG-USAGE"
+    }
+
+    fn level(&self) -> SnipLevel {
+        SnipLevel::Requirements
+    }
+
+    fn check(&self, context: &ValidationContext, diagnostics: &mut Diagnostics) {
+        let Some((guide, extra)) =
+            guide_usage(&context.transaction_set_id, &context.implementation_guide)
+        else {
+            return;
+        };
+        let lookup = |loop_id: &str| {
+            guide
+                .loop_usage(loop_id)
+                .or_else(|| extra.iter().find(|usage| usage.loop_id == loop_id))
+        };
+        let loops: Vec<&LoopUsage> = guide.loops.iter().chain(extra).collect();
+
+        let instances = instances(context, &lookup);
+        for instance in &instances {
+            if let Some(usage) = instance.usage {
+                check_segments(context, instance, usage, diagnostics);
+            }
+            check_loops(context, &instances, instance, &loops, diagnostics);
+        }
+    }
+}
+
+/// Check the segments of one loop instance.
+fn check_segments(
+    context: &ValidationContext,
+    instance: &Instance,
+    usage: &LoopUsage,
+    diagnostics: &mut Diagnostics,
+) {
+    for segment_usage in usage.segments {
+        let found: Vec<usize> = instance
+            .positions
+            .iter()
+            .copied()
+            .filter(|&position| segment_usage.matches(&context.segments[position]))
+            .collect();
+
+        if found.is_empty() && segment_usage.usage == Required {
+            let diagnostic = Diagnostic::new(
+                Severity::Error,
+                DiagnosticCode::MissingSegment,
+                format!(
+                    "Required segment {} is missing from {}",
+                    segment_usage.name(),
+                    describe(&instance.path)
+                ),
+            );
+            diagnostics.push(at_path(
+                context,
+                diagnostic,
+                instance.first(),
+                &instance.path,
+                segment_usage.id,
+            ));
+        }
+        if segment_usage.usage == NotUsed {
+            for &position in &found {
+                diagnostics.push(context.locate(
+                    Diagnostic::new(
+                        Severity::Error,
+                        DiagnosticCode::UsageViolation,
+                        format!(
+                            "Segment {} is not used in {}",
+                            segment_usage.name(),
+                            describe(&instance.path)
+                        ),
+                    ),
+                    position,
+                    None,
+                ));
+            }
+            continue;
+        }
+        if found.len() > segment_usage.max_use {
+            diagnostics.push(context.locate(
+                Diagnostic::new(
+                    Severity::Error,
+                    DiagnosticCode::RepeatExceeded,
+                    format!(
+                        "Segment {} occurs {} times in {}, at most {} allowed",
+                        segment_usage.name(),
+                        found.len(),
+                        describe(&instance.path),
+                        segment_usage.max_use
+                    ),
+                ),
+                found[segment_usage.max_use],
+                None,
+            ));
+        }
+
+        for &position in &found {
+            let segment = &context.segments[position];
+            for &element in segment_usage.required {
+                if segment.element(element - 1).is_empty() {
+                    diagnostics.push(context.locate(
+                        Diagnostic::new(
+                            Severity::Error,
+                            DiagnosticCode::MissingElement,
+                            format!("Required element {}{:02} is empty", segment.id, element),
+                        ),
+                        position,
+                        Some(element),
+                    ));
+                }
+            }
+            for &element in segment_usage.not_used {
+                if !segment.element(element - 1).is_empty() {
+                    diagnostics.push(context.locate(
+                        Diagnostic::new(
+                            Severity::Error,
+                            DiagnosticCode::UsageViolation,
+                            format!("Element {}{:02} is not used", segment.id, element),
+                        ),
+                        position,
+                        Some(element),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// Check the loops nested in one loop instance: required ones present, none repeated too often.
+fn check_loops(
+    context: &ValidationContext,
+    instances: &[Instance],
+    instance: &Instance,
+    loops: &[&LoopUsage],
+    diagnostics: &mut Diagnostics,
+) {
+    let parent = instance.loop_id();
+    // Top-level loops are checked once, against the instance holding the heading
+    if parent.is_empty() && !std::ptr::eq(instance, &instances[0]) {
+        return;
+    }
+    for usage in loops.iter().filter(|usage| {
+        !usage.loop_id.is_empty()
+            && (usage.parents.contains(&parent) || (parent.is_empty() && usage.parents.is_empty()))
+    }) {
+        let children: Vec<&Instance> = instance
+            .children
+            .iter()
+            .map(|&child| &instances[child])
+            .filter(|child| child.loop_id() == usage.loop_id)
+            .collect();
+
+        if children.is_empty() && usage.usage == Required {
+            let path = if instance.path.is_empty() {
+                usage.loop_id.to_string()
+            } else {
+                format!("{}/{}", instance.path, usage.loop_id)
+            };
+            let diagnostic = Diagnostic {
+                loop_path: path,
+                ..Diagnostic::new(
+                    Severity::Error,
+                    DiagnosticCode::MissingLoop,
+                    format!(
+                        "Required loop {} is missing from {}",
+                        usage.loop_id,
+                        describe(&instance.path)
+                    ),
+                )
+            };
+            diagnostics.push(match instance.first() {
+                Some(position) if !instance.path.is_empty() => Diagnostic {
+                    segment_index: Some(context.segments[position].index),
+                    offset: Some(context.segments[position].offset),
+                    ..diagnostic
+                },
+                _ => diagnostic,
+            });
+        }
+        if children.len() > usage.max_repeat {
+            if let Some(position) = children[usage.max_repeat].first() {
+                diagnostics.push(context.locate(
+                    Diagnostic::new(
+                        Severity::Error,
+                        DiagnosticCode::RepeatExceeded,
+                        format!(
+                            "Loop {} repeats {} times in {}, at most {} allowed",
+                            usage.loop_id,
+                            children.len(),
+                            describe(&instance.path),
+                            usage.max_repeat
+                        ),
+                    ),
+                    position,
+                    None,
+                ));
+            }
+        }
+    }
+}
+
+/// Locate a diagnostic about a segment missing from the loop at `path`,
+/// at the loop's first segment if it has one.
+fn at_path(
+    context: &ValidationContext,
+    diagnostic: Diagnostic,
+    first: Option<usize>,
+    path: &str,
+    segment_id: &str,
+) -> Diagnostic {
+    let diagnostic = match first {
+        Some(position) => context.locate(diagnostic, position, None),
+        None => diagnostic,
+    };
+    Diagnostic {
+        loop_path: element_path(path, segment_id, None),
+        ..diagnostic
+    }
+}
+
+fn describe(path: &str) -> String {
+    if path.is_empty() {
+        "the transaction set heading".to_string()
+    } else {
+        format!("loop {}", path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation::Validator;
+
+    const ISA: &str = "ISA*00*          *00*          *ZZ*SENDER         *ZZ*RECEIVER       *230501*1200*^*00501*000000001*0*T*:~";
+
+    fn usage_findings(contents: &str) -> Vec<(DiagnosticCode, String)> {
+        Validator::default()
+            .validate_edi(contents, &[SnipLevel::Requirements])
+            .unwrap()
+            .of_rule("IG-USAGE")
+            .map(|f| (f.diagnostic.code, f.diagnostic.loop_path.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_guide_usage_lookup() {
+        assert_eq!(
+            guide_usage("837", "005010X223A2").unwrap().0.guide,
+            "005010X223A2"
+        );
+        assert_eq!(
+            guide_usage("835", "005010X221").unwrap().0.guide,
+            "005010X221A1"
+        );
+        assert!(guide_usage("837", "004010X098A1").is_none());
+        for (usage, extra) in GUIDES {
+            for loop_usage in usage.loops.iter().chain(extra.iter()) {
+                assert!(!loop_usage.segments.is_empty(), "{}", loop_usage.loop_id);
+            }
+        }
+    }
+
+    #[test]
+    fn test_837p_usage() {
+        let contents = format!(
+            "{}GS*HC*SENDER*RECEIVER*20230501*1200*1*X*005010X222A1~ST*837*0001*005010X222A1~BHT*0019*00*1*20230501*1200*CH~\
+NM1*41*2*SUBMITTER*****46*S1~PER*IC*CONTACT*TE*5551234567~NM1*40*2*RECEIVER*****46*R1~\
+HL*1**20*1~NM1*85*2*BILLING*****XX*1234567893~N3*1 MAIN ST~N4*CITY*ST*12345~REF*EI*123456789~\
+HL*2*1*22*0~SBR*P*18*******MC~NM1*IL*1*DOE*JOHN****MI*123~NM1*PR*2*PAYER*****PI*P1~\
+CLM*C1*100***11:B:1*Y*A*Y*Y~HI*ABK:I10~LX*1~SV1*HC:99213*100*UN*1***1~DTP*472*D8*20230501~SE*21*0001~GE*1*1~IEA*1*000000001~",
+            ISA
+        );
+        assert!(
+            usage_findings(&contents).is_empty(),
+            "{:?}",
+            usage_findings(&contents)
+        );
+
+        let broken = contents
+            .replace("SBR*P*18*******MC~", "")
+            .replace("NM1*85*2*BILLING*****XX*1234567893~", "")
+            .replace("CLM*C1*100***", "CLM*C1*100*X**")
+            .replace(
+                "DTP*472*D8*20230501~",
+                "DTP*472*D8*20230501~SV2*0250**100*UN*1~",
+            );
+        assert_eq!(
+            usage_findings(&broken),
+            vec![
+                (DiagnosticCode::MissingLoop, "2000A/2010AA".to_string()),
+                (DiagnosticCode::MissingSegment, "2000B/SBR".to_string()),
+                (
+                    DiagnosticCode::UsageViolation,
+                    "2000B/2300/CLM03".to_string()
+                ),
+                (
+                    DiagnosticCode::UsageViolation,
+                    "2000B/2300/2400/SV2".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_835_usage() {
+        let contents = format!(
+            "{}GS*HP*SENDER*RECEIVER*20230501*1200*1*X*005010X221A1~ST*835*0001~BPR*I*100*C*ACH*CCP*01*011000015*DA*123456*1512345678**01*031100209*DA*654321*20230501~\
+N1*PR*PAYER~N3*1 MAIN ST~N4*CITY*ST*12345~PER*BL*CONTACT~N1*PE*PAYEE*XX*1234567893~\
+LX*1~CLP*A*1*100*100**MC*1~NM1*QC*1*DOE~CLP*B*1*50*50**MC*2~NM1*QC*1*ROE~SE*13*0001~GE*1*1~IEA*1*000000001~",
+            ISA
+        );
+        assert_eq!(
+            usage_findings(&contents),
+            vec![(DiagnosticCode::MissingSegment, "TRN".to_string())]
+        );
+
+        let without_patient = contents
+            .replace("NM1*QC*1*ROE~", "")
+            .replace("CLP*A*1*100*100**MC*1~", "CLP*A*1*100*100**MC~");
+        assert_eq!(
+            usage_findings(&without_patient),
+            vec![
+                (DiagnosticCode::MissingSegment, "TRN".to_string()),
+                (
+                    DiagnosticCode::MissingElement,
+                    "2000/2100/CLP07".to_string()
+                ),
+                (DiagnosticCode::MissingSegment, "2000/2100/NM1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_999_nested_loops_and_repeats() {
+        let contents = format!(
+            "{}GS*FA*S*R*20230501*1200*1*X*005010X231A1~ST*999*0001*005010X231A1~AK1*HC*1*005010X222A1~\
+AK2*837*0001~IK3*CLM*22**8~IK4*2*782*1~IK5*R*5~AK2*837*0002~IK5*A~IK5*A~AK9*P*2*2*1~SE*11*0001~GE*1*1~IEA*1*000000001~",
+            ISA
+        );
+        assert_eq!(
+            usage_findings(&contents),
+            vec![(DiagnosticCode::RepeatExceeded, "2000/IK5".to_string())]
+        );
+    }
+}